@@ -48,7 +48,7 @@ use std::task::{Context, Poll};
 use actix_service::{Service, Transform};
 use actix_web::dev::{RequestHead, ServiceRequest, ServiceResponse};
 use actix_web::error::{Error, ResponseError, Result};
-use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use actix_web::http::{self, Error as HttpError, Method, StatusCode, Uri};
 use actix_web::HttpResponse;
 use derive_more::Display;
@@ -184,6 +184,8 @@ impl Cors {
             cors: Some(Inner {
                 origins: AllOrSome::All,
                 origins_str: None,
+                origin_patterns: Vec::new(),
+                origin_fn: None,
                 methods: HashSet::new(),
                 headers: AllOrSome::All,
                 expose_hdrs: None,
@@ -204,6 +206,8 @@ impl Cors {
         let inner = Inner {
             origins: AllOrSome::default(),
             origins_str: None,
+            origin_patterns: Vec::new(),
+            origin_fn: None,
             methods: HashSet::from_iter(
                 vec![
                     Method::GET,
@@ -240,6 +244,10 @@ impl Cors {
     /// When `Some` is set, the client's `Origin` request header will be
     /// checked in a case-sensitive manner.
     ///
+    /// Origin could contain wildcard subdomain, e.g. `https://*.example.com`
+    /// matches `https://app.example.com` and `https://a.b.example.com`,
+    /// but not `https://example.com`.
+    ///
     /// This is the `list of origins` in the
     /// [Resource Processing Model](https://www.w3.org/TR/cors/#resource-processing-model).
     ///
@@ -248,23 +256,58 @@ impl Cors {
     /// Builder panics if supplied origin is not valid uri.
     pub fn allowed_origin(mut self, origin: &str) -> Cors {
         if let Some(cors) = cors(&mut self.cors, &self.error) {
-            match Uri::try_from(origin) {
-                Ok(_) => {
-                    if cors.origins.is_all() {
-                        cors.origins = AllOrSome::Some(HashSet::new());
+            if let Some(pattern) = OriginPattern::parse(origin) {
+                if cors.origins.is_all() {
+                    cors.origins = AllOrSome::Some(HashSet::new());
+                }
+                cors.origin_patterns.push(pattern);
+            } else {
+                match Uri::try_from(origin) {
+                    Ok(_) => {
+                        if cors.origins.is_all() {
+                            cors.origins = AllOrSome::Some(HashSet::new());
+                        }
+                        if let AllOrSome::Some(ref mut origins) = cors.origins {
+                            origins.insert(origin.to_owned());
+                        }
                     }
-                    if let AllOrSome::Some(ref mut origins) = cors.origins {
-                        origins.insert(origin.to_owned());
+                    Err(e) => {
+                        self.error = Some(e.into());
                     }
                 }
-                Err(e) => {
-                    self.error = Some(e.into());
-                }
             }
         }
         self
     }
 
+    /// Set a function that determines whether origin is allowed to make
+    /// requests, in addition to origins set with `allowed_origin()`.
+    ///
+    /// Function receives value of `Origin` request header and request head,
+    /// e.g. for checking origin against list of tenants.
+    ///
+    /// ```rust
+    /// use actix_cors::Cors;
+    ///
+    /// # fn main() {
+    /// let cors = Cors::new()
+    ///     .allowed_origin_fn(|origin, _req| origin.as_bytes().ends_with(b".example.org"))
+    ///     .finish();
+    /// # }
+    /// ```
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Cors
+    where
+        F: Fn(&HeaderValue, &RequestHead) -> bool + 'static,
+    {
+        if let Some(cors) = cors(&mut self.cors, &self.error) {
+            if cors.origins.is_all() {
+                cors.origins = AllOrSome::Some(HashSet::new());
+            }
+            cors.origin_fn = Some(Box::new(f));
+        }
+        self
+    }
+
     /// Set a list of methods which the allowed origins are allowed to access
     /// for requests.
     ///
@@ -445,6 +488,9 @@ impl Cors {
     /// dynamically generated (e.g. when there is more than one allowed
     /// origin, and an Origin than '*' is returned) informs CDNs and other
     /// caches that the CORS headers are dynamic, and cannot be cached.
+    /// Header is set on responses to requests without `Origin` too, and
+    /// preflight responses vary on `Access-Control-Request-Method` and
+    /// `Access-Control-Request-Headers` as well.
     ///
     /// By default `vary` header support is enabled.
     pub fn disable_vary_header(mut self) -> Cors {
@@ -494,10 +540,12 @@ impl Cors {
         }
 
         if let AllOrSome::Some(ref origins) = cors.origins {
-            let s = origins
-                .iter()
-                .fold(String::new(), |s, v| format!("{}, {}", s, v));
-            cors.origins_str = Some(HeaderValue::try_from(&s[2..]).unwrap());
+            if !origins.is_empty() {
+                let s = origins
+                    .iter()
+                    .fold(String::new(), |s, v| format!("{}, {}", s, v));
+                cors.origins_str = Some(HeaderValue::try_from(&s[2..]).unwrap());
+            }
         }
 
         if !slf.expose_hdrs.is_empty() {
@@ -564,10 +612,44 @@ pub struct CorsMiddleware<S> {
     inner: Rc<Inner>,
 }
 
+/// Origin with wildcard subdomain, e.g. `https://*.example.com`
+struct OriginPattern {
+    /// Scheme with separator, e.g. `https://`
+    prefix: String,
+    /// Parent domain with leading dot and optional port, e.g. `.example.com`
+    suffix: String,
+}
+
+impl OriginPattern {
+    fn parse(origin: &str) -> Option<OriginPattern> {
+        let idx = origin.find("://*.")?;
+        Some(OriginPattern {
+            prefix: origin[..idx + 3].to_owned(),
+            suffix: origin[idx + 4..].to_owned(),
+        })
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        if origin.len() <= self.prefix.len() + self.suffix.len()
+            || !origin.starts_with(&self.prefix)
+            || !origin.ends_with(&self.suffix)
+        {
+            return false;
+        }
+        let subdomain = &origin[self.prefix.len()..origin.len() - self.suffix.len()];
+        !subdomain.starts_with('.')
+            && subdomain
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+    }
+}
+
 struct Inner {
     methods: HashSet<Method>,
     origins: AllOrSome<HashSet<String>>,
     origins_str: Option<HeaderValue>,
+    origin_patterns: Vec<OriginPattern>,
+    origin_fn: Option<Box<dyn Fn(&HeaderValue, &RequestHead) -> bool>>,
     headers: AllOrSome<HashSet<HeaderName>>,
     expose_hdrs: Option<String>,
     max_age: Option<usize>,
@@ -578,15 +660,25 @@ struct Inner {
 }
 
 impl Inner {
+    fn is_origin_allowed(&self, origin: &HeaderValue, req: &RequestHead) -> bool {
+        match self.origins {
+            AllOrSome::All => true,
+            AllOrSome::Some(ref allowed_origins) => {
+                origin.to_str().map_or(false, |origin| {
+                    allowed_origins.contains(origin)
+                        || self.origin_patterns.iter().any(|p| p.matches(origin))
+                }) || self.origin_fn.as_ref().map_or(false, |f| f(origin, req))
+            }
+        }
+    }
+
     fn validate_origin(&self, req: &RequestHead) -> Result<(), CorsError> {
         if let Some(hdr) = req.headers().get(&header::ORIGIN) {
-            if let Ok(origin) = hdr.to_str() {
-                return match self.origins {
-                    AllOrSome::All => Ok(()),
-                    AllOrSome::Some(ref allowed_origins) => allowed_origins
-                        .get(origin)
-                        .and_then(|_| Some(()))
-                        .ok_or_else(|| CorsError::OriginNotAllowed),
+            if hdr.to_str().is_ok() {
+                return if self.is_origin_allowed(hdr, req) {
+                    Ok(())
+                } else {
+                    Err(CorsError::OriginNotAllowed)
                 };
             }
             Err(CorsError::BadOrigin)
@@ -609,18 +701,15 @@ impl Inner {
                     None
                 }
             }
-            AllOrSome::Some(ref origins) => {
-                if let Some(origin) =
-                    req.headers()
-                        .get(&header::ORIGIN)
-                        .filter(|o| match o.to_str() {
-                            Ok(os) => origins.contains(os),
-                            _ => false,
-                        })
+            AllOrSome::Some(_) => {
+                if let Some(origin) = req
+                    .headers()
+                    .get(&header::ORIGIN)
+                    .filter(|o| o.to_str().is_ok() && self.is_origin_allowed(o, req))
                 {
                     Some(origin.clone())
                 } else {
-                    Some(self.origins_str.as_ref().unwrap().clone())
+                    self.origins_str.clone()
                 }
             }
         }
@@ -743,6 +832,13 @@ where
                 .if_true(self.inner.supports_credentials, |resp| {
                     resp.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
                 })
+                .if_true(self.inner.vary_header, |resp| {
+                    resp.header(
+                        header::VARY,
+                        "Origin, Access-Control-Request-Method, \
+                         Access-Control-Request-Headers",
+                    );
+                })
                 .header(
                     header::ACCESS_CONTROL_ALLOW_METHODS,
                     &self
@@ -770,10 +866,9 @@ where
 
             Either::Right(
                 async move {
-                    let res = fut.await;
+                    let mut res = fut.await?;
 
                     if has_origin {
-                        let mut res = res?;
                         if let Some(origin) =
                             inner.access_control_allow_origin(res.request().head())
                         {
@@ -795,24 +890,12 @@ where
                                 HeaderValue::from_static("true"),
                             );
                         }
-                        if inner.vary_header {
-                            let value = if let Some(hdr) =
-                                res.headers_mut().get(&header::VARY)
-                            {
-                                let mut val: Vec<u8> =
-                                    Vec::with_capacity(hdr.as_bytes().len() + 8);
-                                val.extend(hdr.as_bytes());
-                                val.extend(b", Origin");
-                                HeaderValue::try_from(&val[..]).unwrap()
-                            } else {
-                                HeaderValue::from_static("Origin")
-                            };
-                            res.headers_mut().insert(header::VARY, value);
-                        }
-                        Ok(res)
-                    } else {
-                        res
                     }
+                    // response depends on origin, even if request has none
+                    if inner.vary_header {
+                        add_vary_origin(res.headers_mut());
+                    }
+                    Ok(res)
                 }
                 .boxed_local(),
             )
@@ -820,6 +903,26 @@ where
     }
 }
 
+/// Add `Origin` to `Vary` header, unless it is present already
+fn add_vary_origin(headers: &mut HeaderMap) {
+    let value = if let Some(hdr) = headers.get(&header::VARY) {
+        let present = hdr.to_str().map_or(false, |val| {
+            val.split(',')
+                .any(|v| v.trim().eq_ignore_ascii_case("origin") || v.trim() == "*")
+        });
+        if present {
+            return;
+        }
+        let mut val: Vec<u8> = Vec::with_capacity(hdr.as_bytes().len() + 8);
+        val.extend(hdr.as_bytes());
+        val.extend(b", Origin");
+        HeaderValue::try_from(&val[..]).unwrap()
+    } else {
+        HeaderValue::from_static("Origin")
+    };
+    headers.insert(header::VARY, value);
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::{fn_service, Transform};
@@ -1201,4 +1304,126 @@ mod tests {
                 .as_bytes()
         );
     }
+
+    #[actix_rt::test]
+    async fn test_wildcard_subdomain_origin() {
+        let mut cors = Cors::new()
+            .allowed_origin("https://*.example.com")
+            .allowed_methods(vec![Method::GET])
+            .finish()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        for origin in &["https://app.example.com", "https://a.b.example.com"] {
+            let req = TestRequest::with_header("Origin", *origin)
+                .method(Method::GET)
+                .to_srv_request();
+            let resp = test::call_service(&mut cors, req).await;
+            assert_eq!(
+                origin.as_bytes(),
+                resp.headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                    .unwrap()
+                    .as_bytes()
+            );
+        }
+
+        for origin in &[
+            "https://example.com",
+            "http://app.example.com",
+            "https://app.example.com.evil.com",
+            "https://evil.com/.example.com",
+        ] {
+            let req = TestRequest::with_header("Origin", *origin)
+                .method(Method::GET)
+                .to_srv_request();
+            let resp = test::call_service(&mut cors, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_origin_fn() {
+        let mut cors = Cors::new()
+            .allowed_origin("https://example.com")
+            .allowed_origin_fn(|origin, req| {
+                origin.as_bytes().ends_with(b".example.org")
+                    && req.headers().contains_key("x-tenant")
+            })
+            .finish()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header("Origin", "https://tenant.example.org")
+            .header("x-tenant", "1")
+            .method(Method::GET)
+            .to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(
+            &b"https://tenant.example.org"[..],
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap()
+                .as_bytes()
+        );
+
+        let req = TestRequest::with_header("Origin", "https://tenant.example.org")
+            .method(Method::GET)
+            .to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::with_header("Origin", "https://example.com")
+            .method(Method::GET)
+            .to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_vary_header() {
+        let mut cors = Cors::new()
+            .allowed_origin("https://example.com")
+            .allowed_origin("https://example.org")
+            .finish()
+            .new_transform(fn_service(|req: ServiceRequest| {
+                ok(req.into_response(
+                    HttpResponse::Ok().header(header::VARY, "origin").finish(),
+                ))
+            }))
+            .await
+            .unwrap();
+
+        // cacheable response without origin still depends on origin
+        let req = TestRequest::default().method(Method::GET).to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(
+            &b"origin"[..],
+            resp.headers().get(header::VARY).unwrap().as_bytes()
+        );
+
+        let req = TestRequest::with_header("Origin", "https://example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .method(Method::OPTIONS)
+            .to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(
+            &b"Origin, Access-Control-Request-Method, Access-Control-Request-Headers"[..],
+            resp.headers().get(header::VARY).unwrap().as_bytes()
+        );
+
+        let mut cors = Cors::new()
+            .finish()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().method(Method::GET).to_srv_request();
+        let resp = test::call_service(&mut cors, req).await;
+        assert_eq!(
+            &b"Origin"[..],
+            resp.headers().get(header::VARY).unwrap().as_bytes()
+        );
+    }
 }
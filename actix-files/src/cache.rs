@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use bytes::Bytes;
+
+/// In-memory cache of contents of small files.
+///
+/// Files that are not larger than max file size are read into memory on
+/// first request and served from memory afterwards, least recently used
+/// files are evicted when total size of cached contents exceeds capacity.
+/// Cached content is invalidated when modification time or size of file
+/// changes. Cache is attached to `Files` service with `Files::cache()`,
+/// clones of cache share contents.
+///
+/// ```rust
+/// use actix_files::{FileCache, Files};
+/// use actix_web::App;
+///
+/// let cache = FileCache::new(16 * 1024 * 1024).max_file_size(256 * 1024);
+/// let app = App::new().service(Files::new("/static", ".").cache(cache));
+/// ```
+#[derive(Clone)]
+pub struct FileCache(Rc<RefCell<Inner>>);
+
+struct Inner {
+    entries: HashMap<PathBuf, Entry>,
+    capacity: u64,
+    max_file_size: u64,
+    size: u64,
+    tick: u64,
+}
+
+struct Entry {
+    content: Bytes,
+    modified: SystemTime,
+    used: u64,
+}
+
+impl FileCache {
+    /// Create cache that holds up to `capacity` bytes of file contents.
+    ///
+    /// Max file size is 64 KiB, or `capacity` if it is smaller.
+    pub fn new(capacity: u64) -> Self {
+        FileCache(Rc::new(RefCell::new(Inner {
+            entries: HashMap::new(),
+            capacity,
+            max_file_size: capacity.min(65_536),
+            size: 0,
+            tick: 0,
+        })))
+    }
+
+    /// Set max size of cached files, larger files are streamed from disk.
+    pub fn max_file_size(self, size: u64) -> Self {
+        {
+            let mut inner = self.0.borrow_mut();
+            inner.max_file_size = size.min(inner.capacity);
+        }
+        self
+    }
+
+    /// Number of cached files.
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// Returns true if no files are cached.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().entries.is_empty()
+    }
+
+    /// Total size of cached contents.
+    pub fn size(&self) -> u64 {
+        self.0.borrow().size
+    }
+
+    /// Remove all cached contents.
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.entries.clear();
+        inner.size = 0;
+    }
+
+    /// Content of file at `path`, file is read into cache if it is small
+    /// enough. `None` is returned if file is not cacheable.
+    pub(crate) fn load(
+        &self,
+        path: &Path,
+        file: &mut File,
+        md: &Metadata,
+    ) -> io::Result<Option<Bytes>> {
+        let mut inner = self.0.borrow_mut();
+        let modified = match md.modified() {
+            Ok(modified) if md.len() <= inner.max_file_size => modified,
+            _ => return Ok(None),
+        };
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        if let Some(entry) = inner.entries.get_mut(path) {
+            if entry.modified == modified && entry.content.len() as u64 == md.len() {
+                entry.used = tick;
+                return Ok(Some(entry.content.clone()));
+            }
+        }
+        if let Some(entry) = inner.entries.remove(path) {
+            inner.size -= entry.content.len() as u64;
+        }
+
+        let mut buf = Vec::with_capacity(md.len() as usize);
+        file.seek(io::SeekFrom::Start(0))?;
+        file.by_ref().take(md.len() + 1).read_to_end(&mut buf)?;
+        if buf.len() as u64 != md.len() {
+            // file is modified while it is read
+            return Ok(None);
+        }
+        let content = Bytes::from(buf);
+
+        inner.size += md.len();
+        inner.entries.insert(
+            path.to_path_buf(),
+            Entry {
+                content: content.clone(),
+                modified,
+                used: tick,
+            },
+        );
+        inner.evict();
+        Ok(Some(content))
+    }
+}
+
+impl Inner {
+    /// Evict least recently used entries until size fits capacity
+    fn evict(&mut self) {
+        while self.size > self.capacity {
+            let path = match self.entries.iter().min_by_key(|(_, entry)| entry.used) {
+                Some((path, _)) => path.clone(),
+                None => return,
+            };
+            if let Some(entry) = self.entries.remove(&path) {
+                self.size -= entry.content.len() as u64;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FileCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("FileCache")
+            .field("capacity", &inner.capacity)
+            .field("max_file_size", &inner.max_file_size)
+            .field("size", &inner.size)
+            .field("files", &inner.entries.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn load(cache: &FileCache, path: &Path) -> Option<Bytes> {
+        let mut file = File::open(path).unwrap();
+        let md = file.metadata().unwrap();
+        cache.load(path, &mut file, &md).unwrap()
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let dir = std::env::temp_dir().join(format!(
+            "actix-files-cache-{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["a", "b", "c"] {
+            fs::write(dir.join(name), "0123456789").unwrap();
+        }
+        fs::write(dir.join("large"), vec![0; 100]).unwrap();
+
+        let cache = FileCache::new(25).max_file_size(20);
+        assert_eq!(load(&cache, &dir.join("large")), None);
+        assert!(cache.is_empty());
+
+        assert_eq!(
+            load(&cache, &dir.join("a")),
+            Some(Bytes::from_static(b"0123456789"))
+        );
+        load(&cache, &dir.join("b"));
+        // `a` is used more recently than `b`
+        load(&cache, &dir.join("a"));
+        load(&cache, &dir.join("c"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 20);
+        let inner = cache.0.borrow();
+        assert!(inner.entries.contains_key(&dir.join("a")));
+        assert!(!inner.entries.contains_key(&dir.join("b")));
+        drop(inner);
+
+        // modified file is read again
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(dir.join("a"), "updated").unwrap();
+        assert_eq!(
+            load(&cache, &dir.join("a")),
+            Some(Bytes::from_static(b"updated"))
+        );
+        assert_eq!(cache.size(), 17);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
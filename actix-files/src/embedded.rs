@@ -0,0 +1,384 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use actix_service::{Service, ServiceFactory};
+use actix_web::dev::{
+    AppService, HttpServiceFactory, ResourceDef, ServiceRequest, ServiceResponse,
+};
+use actix_web::error::Error;
+use actix_web::http::header::{self, EntityTag};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse};
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+use mime_guess::from_path;
+
+use crate::named::{any_match, if_range, none_match};
+use crate::range::HttpRange;
+
+/// File of virtual file system.
+#[derive(Debug, Clone)]
+pub struct VirtualFile {
+    content: Bytes,
+    etag: EntityTag,
+    modified: Option<SystemTime>,
+}
+
+impl VirtualFile {
+    /// Create file with content, strong `ETag` of file is derived from hash
+    /// of content.
+    pub fn new<B: Into<Bytes>>(content: B) -> VirtualFile {
+        let content = content.into();
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&content);
+        let etag =
+            EntityTag::strong(format!("{:x}:{:016x}", content.len(), hasher.finish()));
+        VirtualFile {
+            content,
+            etag,
+            modified: None,
+        }
+    }
+
+    /// Set modification time of file, it is sent in `Last-Modified` header.
+    pub fn modified(mut self, modified: SystemTime) -> Self {
+        self.modified = Some(modified);
+        self
+    }
+
+    /// Content of file.
+    pub fn content(&self) -> &Bytes {
+        &self.content
+    }
+
+    /// `ETag` of file.
+    pub fn etag(&self) -> &EntityTag {
+        &self.etag
+    }
+}
+
+/// Virtual file system that is served by `VirtualFiles` service.
+pub trait VirtualFs {
+    /// File at `path`, path is relative to root of file system and its
+    /// segments are separated by `/`.
+    fn file(&self, path: &str) -> Option<VirtualFile>;
+}
+
+/// Files that are embedded in binary, see `embed_files!`.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedFiles {
+    files: HashMap<String, VirtualFile>,
+}
+
+impl EmbeddedFiles {
+    /// Create empty file system.
+    pub fn new() -> Self {
+        EmbeddedFiles::default()
+    }
+
+    /// Add file at `path`, i.e. content that is embedded with
+    /// `include_bytes!()`.
+    pub fn add<P: Into<String>>(mut self, path: P, content: &'static [u8]) -> Self {
+        self.files
+            .insert(path.into(), VirtualFile::new(Bytes::from_static(content)));
+        self
+    }
+
+    /// Add file at `path`.
+    pub fn add_file<P: Into<String>>(mut self, path: P, file: VirtualFile) -> Self {
+        self.files.insert(path.into(), file);
+        self
+    }
+}
+
+impl VirtualFs for EmbeddedFiles {
+    fn file(&self, path: &str) -> Option<VirtualFile> {
+        self.files.get(path).cloned()
+    }
+}
+
+/// Create `EmbeddedFiles` from files that are embedded with
+/// `include_bytes!()`, paths of files are relative to current source file
+/// unless they are absolute.
+///
+/// ```rust,no_run
+/// use actix_files::embed_files;
+///
+/// let files = embed_files! {
+///     "README.md" => concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"),
+///     "img/test.png" => concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test.png"),
+/// };
+/// ```
+#[macro_export]
+macro_rules! embed_files {
+    ($($path:expr => $file:expr),* $(,)?) => {
+        $crate::EmbeddedFiles::new()$(.add($path, include_bytes!($file)))*
+    };
+}
+
+/// Service that serves files of virtual file system.
+///
+/// Files are served like `Files` do with `Content-Type` that is derived
+/// from file extension, `ETag`, conditional requests and single byte
+/// ranges, requests for multiple ranges are served with full content.
+/// `VirtualFiles` service must be registered with `App::service()` method.
+///
+/// ```rust
+/// use actix_files::{EmbeddedFiles, VirtualFiles};
+/// use actix_web::App;
+///
+/// let files = EmbeddedFiles::new()
+///     .add("index.html", b"<html></html>")
+///     .add("css/main.css", b"body {}");
+/// let app = App::new()
+///     .service(VirtualFiles::new("/static", files).index_file("index.html"));
+/// ```
+pub struct VirtualFiles {
+    path: String,
+    fs: Rc<dyn VirtualFs>,
+    index: Option<String>,
+}
+
+impl VirtualFiles {
+    /// Create new `VirtualFiles` instance for file system `fs` mounted at
+    /// `path`.
+    pub fn new<F: VirtualFs + 'static>(path: &str, fs: F) -> VirtualFiles {
+        VirtualFiles {
+            path: path.to_owned(),
+            fs: Rc::new(fs),
+            index: None,
+        }
+    }
+
+    /// Set index file
+    ///
+    /// Index file is served for root of file system and paths that end
+    /// with `/`, i.e. `dir/` is served with `dir/index.html`.
+    pub fn index_file<T: Into<String>>(mut self, index: T) -> Self {
+        self.index = Some(index.into());
+        self
+    }
+}
+
+impl fmt::Debug for VirtualFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualFiles")
+            .field("path", &self.path)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl HttpServiceFactory for VirtualFiles {
+    fn register(self, config: &mut AppService) {
+        let rdef = if config.is_root() {
+            ResourceDef::root_prefix(&self.path)
+        } else {
+            ResourceDef::prefix(&self.path)
+        };
+        config.register_service(rdef, None, self, None)
+    }
+}
+
+impl ServiceFactory for VirtualFiles {
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Config = ();
+    type Service = VirtualFilesService;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(VirtualFilesService {
+            fs: self.fs.clone(),
+            index: self.index.clone(),
+        })
+    }
+}
+
+pub struct VirtualFilesService {
+    fs: Rc<dyn VirtualFs>,
+    index: Option<String>,
+}
+
+impl VirtualFilesService {
+    /// Path of file in file system, `None` if path is not valid
+    fn file_path(&self, path: &str) -> Option<String> {
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                segment => segments.push(segment),
+            }
+        }
+        let mut file_path = segments.join("/");
+
+        if file_path.is_empty() || path.ends_with('/') {
+            let index = self.index.as_ref()?;
+            if !file_path.is_empty() {
+                file_path.push('/');
+            }
+            file_path.push_str(index);
+        }
+        Some(file_path)
+    }
+}
+
+impl Service for VirtualFilesService {
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        match *req.method() {
+            Method::HEAD | Method::GET => (),
+            _ => {
+                return ok(req.into_response(
+                    HttpResponse::MethodNotAllowed()
+                        .header(header::CONTENT_TYPE, "text/plain")
+                        .body("Request did not meet this resource's requirements."),
+                ))
+            }
+        }
+
+        let file = self
+            .file_path(req.match_info().path())
+            .and_then(|path| self.fs.file(&path).map(|file| (path, file)));
+        let (req, _) = req.into_parts();
+        let res = match file {
+            Some((path, file)) => respond(&path, &file, &req),
+            None => HttpResponse::NotFound().finish(),
+        };
+        ok(ServiceResponse::new(req, res))
+    }
+}
+
+/// Response of file, conditional and range requests are handled
+fn respond(path: &str, file: &VirtualFile, req: &HttpRequest) -> HttpResponse {
+    let etag = Some(&file.etag);
+    let last_modified = file.modified.map(header::HttpDate::from);
+
+    let mut resp = HttpResponse::Ok();
+    resp.set(header::ContentType(from_path(path).first_or_octet_stream()))
+        .set(header::ETag(file.etag.clone()))
+        .if_some(last_modified, |lm, resp| {
+            resp.set(header::LastModified(lm));
+        })
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    if !any_match(etag, req) {
+        return resp.status(StatusCode::PRECONDITION_FAILED).finish();
+    } else if !none_match(etag, req) {
+        return resp.status(StatusCode::NOT_MODIFIED).finish();
+    }
+
+    let size = file.content.len() as u64;
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .filter(|_| if_range(etag, last_modified.as_ref(), req));
+    if let Some(range) = range {
+        match range.to_str().map(|range| HttpRange::parse(range, size)) {
+            Ok(Ok(ranges)) => {
+                if let [range] = ranges.as_slice() {
+                    let start = range.start as usize;
+                    let end = (range.start + range.length) as usize;
+                    resp.header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end - 1, size),
+                    );
+                    return resp
+                        .status(StatusCode::PARTIAL_CONTENT)
+                        .body(file.content.slice(start..end));
+                }
+            }
+            Ok(Err(_)) => {
+                resp.header(header::CONTENT_RANGE, format!("bytes */{}", size));
+                return resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish();
+            }
+            Err(_) => return resp.status(StatusCode::BAD_REQUEST).finish(),
+        }
+    }
+
+    resp.body(file.content.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_virtual_files() {
+        let files = embed_files! {
+            "Cargo.toml" => "../Cargo.toml",
+            "img/test.png" => "../tests/test.png",
+        };
+        let mut srv = test::init_service(
+            App::new()
+                .service(VirtualFiles::new("/static", files).index_file("Cargo.toml")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/img/test.png").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/png"
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let bytes = test::read_body(resp).await;
+        assert_eq!(
+            bytes,
+            Bytes::from_static(include_bytes!("../tests/test.png"))
+        );
+
+        let req = TestRequest::with_uri("/static/img/test.png")
+            .header(header::IF_NONE_MATCH, etag)
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/")
+            .header(header::RANGE, "bytes=0-5")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_RANGE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!("bytes 0-5/{}", include_bytes!("../Cargo.toml").len())
+        );
+        let bytes = test::read_body(resp).await;
+        assert_eq!(
+            bytes,
+            Bytes::from_static(&include_bytes!("../Cargo.toml")[..6])
+        );
+
+        let req = TestRequest::with_uri("/static/../Cargo.toml").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::post().uri("/static/Cargo.toml").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -10,6 +10,7 @@ use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use std::time::SystemTime;
 use std::{cmp, io};
 
 use actix_service::boxed::{self, BoxService, BoxServiceFactory};
@@ -31,13 +32,21 @@ use mime_guess::from_ext;
 use percent_encoding::{utf8_percent_encode, CONTROLS};
 use v_htmlescape::escape as escape_html_entity;
 
+mod cache;
+mod embedded;
 mod error;
 mod named;
 mod range;
+mod sendfile;
 
 use self::error::{FilesError, UriSegmentError};
+pub use crate::cache::FileCache;
+pub use crate::embedded::{
+    EmbeddedFiles, VirtualFile, VirtualFiles, VirtualFilesService, VirtualFs,
+};
 pub use crate::named::NamedFile;
 pub use crate::range::HttpRange;
+pub use crate::sendfile::{Sendfile, SendfileConfig};
 
 type HttpService = BoxService<ServiceRequest, ServiceResponse, Error>;
 type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
@@ -117,6 +126,13 @@ impl Stream for ChunkedReadFile {
     }
 }
 
+// show file url as relative to static path
+macro_rules! encode_file_url {
+    ($path:ident) => {
+        utf8_percent_encode(&$path, CONTROLS)
+    };
+}
+
 type DirectoryRenderer =
     dyn Fn(&Directory, &HttpRequest) -> Result<ServiceResponse, io::Error>;
 
@@ -127,12 +143,24 @@ pub struct Directory {
     pub base: PathBuf,
     /// Path of subdirectory to generate listing for
     pub path: PathBuf,
+    symlinks: SymlinkPolicy,
 }
 
 impl Directory {
     /// Create a new directory
     pub fn new(base: PathBuf, path: PathBuf) -> Directory {
-        Directory { base, path }
+        Directory {
+            base,
+            path,
+            symlinks: SymlinkPolicy::Always,
+        }
+    }
+
+    /// Symbolic links policy of directory entries, links that would not be
+    /// served are not visible. Default is `SymlinkPolicy::Always`.
+    pub fn follow_symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
     }
 
     /// Is this entry visible from this directory?
@@ -145,25 +173,72 @@ impl Directory {
             }
             if let Ok(ref md) = entry.metadata() {
                 let ft = md.file_type();
-                return ft.is_dir() || ft.is_file() || ft.is_symlink();
+                if ft.is_symlink() {
+                    return match self.symlinks {
+                        SymlinkPolicy::Never => false,
+                        SymlinkPolicy::WithinRoot => entry
+                            .path()
+                            .canonicalize()
+                            .map(|path| path.starts_with(&self.base))
+                            .unwrap_or(false),
+                        SymlinkPolicy::Always => true,
+                    };
+                }
+                return ft.is_dir() || ft.is_file();
             }
         }
         false
     }
-}
 
-// show file url as relative to static path
-macro_rules! encode_file_url {
-    ($path:ident) => {
-        utf8_percent_encode(&$path, CONTROLS)
-    };
+    /// Visible entries of directory sorted by name, urls of entries are
+    /// relative to path of `req`.
+    pub fn entries(&self, req: &HttpRequest) -> io::Result<Vec<DirectoryEntry>> {
+        let base = Path::new(req.path());
+        let mut entries = Vec::new();
+
+        for entry in self.path.read_dir()? {
+            if !self.is_visible(&entry) {
+                continue;
+            }
+            let entry = entry?;
+            let p = match entry.path().strip_prefix(&self.path) {
+                Ok(p) if cfg!(windows) => {
+                    base.join(p).to_string_lossy().replace("\\", "/")
+                }
+                Ok(p) => base.join(p).to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            entries.push(DirectoryEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                url: encode_file_url!(p).to_string(),
+                is_dir: metadata.is_dir(),
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
 }
 
-// " -- &quot;  & -- &amp;  ' -- &#x27;  < -- &lt;  > -- &gt;  / -- &#x2f;
-macro_rules! encode_file_name {
-    ($entry:ident) => {
-        escape_html_entity(&$entry.file_name().to_string_lossy())
-    };
+/// Entry of directory listing.
+#[derive(Debug, Clone)]
+pub struct DirectoryEntry {
+    /// File name
+    pub name: String,
+    /// Percent encoded url of entry
+    pub url: String,
+    /// Entry is a directory
+    pub is_dir: bool,
+    /// Size of file
+    pub len: u64,
+    /// Modification time
+    pub modified: Option<SystemTime>,
 }
 
 fn directory_listing(
@@ -172,40 +247,16 @@ fn directory_listing(
 ) -> Result<ServiceResponse, io::Error> {
     let index_of = format!("Index of {}", req.path());
     let mut body = String::new();
-    let base = Path::new(req.path());
-
-    for entry in dir.path.read_dir()? {
-        if dir.is_visible(&entry) {
-            let entry = entry.unwrap();
-            let p = match entry.path().strip_prefix(&dir.path) {
-                Ok(p) if cfg!(windows) => {
-                    base.join(p).to_string_lossy().replace("\\", "/")
-                }
-                Ok(p) => base.join(p).to_string_lossy().into_owned(),
-                Err(_) => continue,
-            };
 
-            // if file is a directory, add '/' to the end of the name
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_dir() {
-                    let _ = write!(
-                        body,
-                        "<li><a href=\"{}\">{}/</a></li>",
-                        encode_file_url!(p),
-                        encode_file_name!(entry),
-                    );
-                } else {
-                    let _ = write!(
-                        body,
-                        "<li><a href=\"{}\">{}</a></li>",
-                        encode_file_url!(p),
-                        encode_file_name!(entry),
-                    );
-                }
-            } else {
-                continue;
-            }
-        }
+    for entry in dir.entries(req)? {
+        // if file is a directory, add '/' to the end of the name
+        let _ = write!(
+            body,
+            "<li><a href=\"{}\">{}{}</a></li>",
+            entry.url,
+            escape_html_entity(&entry.name),
+            if entry.is_dir { "/" } else { "" },
+        );
     }
 
     let html = format!(
@@ -227,6 +278,19 @@ fn directory_listing(
 
 type MimeOverride = dyn Fn(&mime::Name) -> DispositionType;
 
+type FileMapper = dyn Fn(NamedFile, &HttpRequest) -> NamedFile;
+
+/// Policy of following symbolic links by `Files` service.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Paths that contain symbolic links are not served
+    Never,
+    /// Symbolic links are followed if their targets are in base directory
+    WithinRoot,
+    /// Symbolic links are always followed
+    Always,
+}
+
 /// Static files handling
 ///
 /// `Files` service must be registered with `App::service()` method.
@@ -244,12 +308,18 @@ pub struct Files {
     path: String,
     directory: PathBuf,
     index: Option<String>,
+    spa_fallback: Option<String>,
     show_index: bool,
     redirect_to_slash: bool,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     renderer: Rc<DirectoryRenderer>,
     mime_override: Option<Rc<MimeOverride>>,
+    file_mapper: Option<Rc<FileMapper>>,
     file_flags: named::Flags,
+    precompressed: bool,
+    symlinks: SymlinkPolicy,
+    hidden_files: bool,
+    cache: Option<FileCache>,
     guards: Option<Rc<Box<dyn Guard>>>,
 }
 
@@ -258,13 +328,19 @@ impl Clone for Files {
         Self {
             directory: self.directory.clone(),
             index: self.index.clone(),
+            spa_fallback: self.spa_fallback.clone(),
             show_index: self.show_index,
             redirect_to_slash: self.redirect_to_slash,
             default: self.default.clone(),
             renderer: self.renderer.clone(),
             file_flags: self.file_flags,
+            precompressed: self.precompressed,
+            symlinks: self.symlinks,
+            hidden_files: self.hidden_files,
+            cache: self.cache.clone(),
             path: self.path.clone(),
             mime_override: self.mime_override.clone(),
+            file_mapper: self.file_mapper.clone(),
             guards: self.guards.clone(),
         }
     }
@@ -290,12 +366,18 @@ impl Files {
             path: path.to_string(),
             directory: dir,
             index: None,
+            spa_fallback: None,
             show_index: false,
             redirect_to_slash: false,
             default: Rc::new(RefCell::new(None)),
             renderer: Rc::new(directory_listing),
             mime_override: None,
+            file_mapper: None,
             file_flags: named::Flags::default(),
+            precompressed: false,
+            symlinks: SymlinkPolicy::Always,
+            hidden_files: false,
+            cache: None,
             guards: None,
         }
     }
@@ -326,6 +408,31 @@ impl Files {
         self
     }
 
+    /// Show files listing for directories, rendered by `f` from visible
+    /// entries of directory.
+    ///
+    /// ```rust
+    /// use actix_files::Files;
+    /// use actix_web::{App, HttpResponse};
+    ///
+    /// let files = Files::new("/static", ".").show_files_listing_with(|entries, _| {
+    ///     let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+    ///     HttpResponse::Ok().body(names.join("\n"))
+    /// });
+    /// let app = App::new().service(files);
+    /// ```
+    pub fn show_files_listing_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[DirectoryEntry], &HttpRequest) -> HttpResponse + 'static,
+    {
+        self.show_index = true;
+        self.renderer = Rc::new(move |dir: &Directory, req: &HttpRequest| {
+            let entries = dir.entries(req)?;
+            Ok(ServiceResponse::new(req.clone(), f(&entries, req)))
+        });
+        self
+    }
+
     /// Specifies mime override callback
     pub fn mime_override<F>(mut self, f: F) -> Self
     where
@@ -335,6 +442,33 @@ impl Files {
         self
     }
 
+    /// Specifies callback that customizes served files.
+    ///
+    /// Callback is called with every served file before response is
+    /// created, path and metadata of file are available with
+    /// `NamedFile::path()` and `NamedFile::metadata()`. It could set headers
+    /// of response and `ETag` of file.
+    ///
+    /// ```rust
+    /// use actix_files::Files;
+    /// use actix_web::http::header::{HeaderValue, CACHE_CONTROL};
+    ///
+    /// let files = Files::new("/static", ".").map_file(|file, _| {
+    ///     let cache_control = match file.path().extension() {
+    ///         Some(ext) if ext == "html" => "no-cache",
+    ///         _ => "public, max-age=31536000, immutable",
+    ///     };
+    ///     file.set_header(CACHE_CONTROL, HeaderValue::from_static(cache_control))
+    /// });
+    /// ```
+    pub fn map_file<F>(mut self, f: F) -> Self
+    where
+        F: Fn(NamedFile, &HttpRequest) -> NamedFile + 'static,
+    {
+        self.file_mapper = Some(Rc::new(f));
+        self
+    }
+
     /// Set index file
     ///
     /// Shows specific index file for directory "/" instead of
@@ -344,6 +478,18 @@ impl Files {
         self
     }
 
+    /// Set fallback file of single-page application.
+    ///
+    /// File at `path` relative to base directory is served for `GET` and
+    /// `HEAD` requests of paths that do not match any file, so routes of
+    /// client side router could be loaded directly. Paths with file
+    /// extension, i.e. `/app.js`, are not rewritten and still result in
+    /// `404 Not Found` or default handler.
+    pub fn spa_fallback<T: Into<String>>(mut self, path: T) -> Self {
+        self.spa_fallback = Some(path.into());
+        self
+    }
+
     #[inline]
     /// Specifies whether to use ETag or not.
     ///
@@ -362,6 +508,44 @@ impl Files {
         self
     }
 
+    /// Specifies whether to serve precompressed variants of files.
+    ///
+    /// `<file>.br` or `<file>.gz` is served instead of requested file if it
+    /// exists and its encoding is accepted by client, see
+    /// `NamedFile::open_precompressed()`. Default is false.
+    #[inline]
+    pub fn use_precompressed(mut self, value: bool) -> Self {
+        self.precompressed = value;
+        self
+    }
+
+    /// Specifies whether to follow symbolic links.
+    ///
+    /// Policy is checked against canonical path of requested file, paths
+    /// that are not allowed result in `404 Not Found`. Default is
+    /// `SymlinkPolicy::Always`.
+    #[inline]
+    pub fn follow_symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Specifies whether to serve hidden files, names of hidden files and
+    /// directories start with `.`.
+    ///
+    /// Hidden files are not shown in files listing. Default is false.
+    #[inline]
+    pub fn serve_hidden_files(mut self, value: bool) -> Self {
+        self.hidden_files = value;
+        self
+    }
+
+    /// Serve small files from in-memory cache, see `FileCache`.
+    pub fn cache(mut self, cache: FileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Specifies custom guards to use for directory listings and files.
     ///
     /// Default behaviour allows GET and HEAD.
@@ -427,12 +611,18 @@ impl ServiceFactory for Files {
         let mut srv = FilesService {
             directory: self.directory.clone(),
             index: self.index.clone(),
+            spa_fallback: self.spa_fallback.clone(),
             show_index: self.show_index,
             redirect_to_slash: self.redirect_to_slash,
             default: None,
             renderer: self.renderer.clone(),
             mime_override: self.mime_override.clone(),
+            file_mapper: self.file_mapper.clone(),
             file_flags: self.file_flags,
+            precompressed: self.precompressed,
+            symlinks: self.symlinks,
+            hidden_files: self.hidden_files,
+            cache: self.cache.clone(),
             guards: self.guards.clone(),
         };
 
@@ -456,12 +646,18 @@ impl ServiceFactory for Files {
 pub struct FilesService {
     directory: PathBuf,
     index: Option<String>,
+    spa_fallback: Option<String>,
     show_index: bool,
     redirect_to_slash: bool,
     default: Option<HttpService>,
     renderer: Rc<DirectoryRenderer>,
     mime_override: Option<Rc<MimeOverride>>,
+    file_mapper: Option<Rc<FileMapper>>,
     file_flags: named::Flags,
+    precompressed: bool,
+    symlinks: SymlinkPolicy,
+    hidden_files: bool,
+    cache: Option<FileCache>,
     guards: Option<Rc<Box<dyn Guard>>>,
 }
 
@@ -481,6 +677,78 @@ impl FilesService {
             Either::Left(ok(req.error_response(e)))
         }
     }
+
+    /// Canonical path of `path` relative to base directory, that is allowed by
+    /// symlink and hidden files policies
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let full_path = self.directory.join(path);
+        let canonical = full_path.canonicalize()?;
+
+        let allowed = match self.symlinks {
+            SymlinkPolicy::Never => canonical == full_path,
+            SymlinkPolicy::WithinRoot => canonical.starts_with(&self.directory),
+            SymlinkPolicy::Always => true,
+        };
+        let hidden = match canonical.strip_prefix(&self.directory) {
+            Ok(path) if !self.hidden_files => path
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.')),
+            _ => false,
+        };
+
+        if allowed && !hidden {
+            Ok(canonical)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Path is not allowed by policy",
+            ))
+        }
+    }
+
+    fn open_file(&self, path: &Path, req: &ServiceRequest) -> io::Result<NamedFile> {
+        if self.precompressed {
+            // variants are subject to the same policies as requested file
+            NamedFile::open_variant(path, req.headers(), |variant| self.resolve(variant))
+        } else {
+            NamedFile::open(path)
+        }
+    }
+
+    fn serve_file(
+        &mut self,
+        path: &Path,
+        req: ServiceRequest,
+    ) -> Either<
+        Ready<Result<ServiceResponse, Error>>,
+        LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+    > {
+        match self.open_file(path, &req) {
+            Ok(mut named_file) => {
+                if let Some(ref mime_override) = self.mime_override {
+                    let new_disposition =
+                        mime_override(&named_file.content_type.type_());
+                    named_file.content_disposition.disposition = new_disposition;
+                }
+
+                named_file.flags = self.file_flags;
+                if let Some(ref cache) = self.cache {
+                    if let Err(e) = named_file.load_cached(cache) {
+                        return self.handle_err(e, req);
+                    }
+                }
+                let (req, _) = req.into_parts();
+                if let Some(ref file_mapper) = self.file_mapper {
+                    named_file = file_mapper(named_file, &req);
+                }
+                Either::Left(ok(match named_file.into_response(&req) {
+                    Ok(item) => ServiceResponse::new(req, item),
+                    Err(e) => ServiceResponse::from_err(e, req),
+                }))
+            }
+            Err(e) => self.handle_err(e, req),
+        }
+    }
 }
 
 impl Service for FilesService {
@@ -516,15 +784,28 @@ impl Service for FilesService {
             )));
         }
 
-        let real_path = match PathBufWrp::get_pathbuf(req.match_info().path()) {
-            Ok(item) => item,
-            Err(e) => return Either::Left(ok(req.error_response(e))),
-        };
+        let real_path =
+            match PathBufWrp::parse(req.match_info().path(), self.hidden_files) {
+                Ok(item) => item,
+                Err(e) => return Either::Left(ok(req.error_response(e))),
+            };
 
         // full filepath
-        let path = match self.directory.join(&real_path.0).canonicalize() {
+        let path = match self.resolve(&real_path.0) {
             Ok(path) => path,
-            Err(e) => return self.handle_err(e, req),
+            Err(e) => {
+                if let Some(ref fallback) = self.spa_fallback {
+                    let is_route = match *req.method() {
+                        Method::HEAD | Method::GET => real_path.0.extension().is_none(),
+                        _ => false,
+                    };
+                    if is_route && e.kind() == io::ErrorKind::NotFound {
+                        let path = self.directory.join(fallback);
+                        return self.serve_file(&path, req);
+                    }
+                }
+                return self.handle_err(e, req);
+            }
         };
 
         if path.is_dir() {
@@ -539,27 +820,13 @@ impl Service for FilesService {
                     )));
                 }
 
-                let path = path.join(redir_index);
-
-                match NamedFile::open(path) {
-                    Ok(mut named_file) => {
-                        if let Some(ref mime_override) = self.mime_override {
-                            let new_disposition =
-                                mime_override(&named_file.content_type.type_());
-                            named_file.content_disposition.disposition = new_disposition;
-                        }
-
-                        named_file.flags = self.file_flags;
-                        let (req, _) = req.into_parts();
-                        Either::Left(ok(match named_file.into_response(&req) {
-                            Ok(item) => ServiceResponse::new(req, item),
-                            Err(e) => ServiceResponse::from_err(e, req),
-                        }))
-                    }
+                match self.resolve(&real_path.0.join(redir_index)) {
+                    Ok(path) => self.serve_file(&path, req),
                     Err(e) => self.handle_err(e, req),
                 }
             } else if self.show_index {
-                let dir = Directory::new(self.directory.clone(), path);
+                let dir = Directory::new(self.directory.clone(), path)
+                    .follow_symlinks(self.symlinks);
                 let (req, _) = req.into_parts();
                 let x = (self.renderer)(&dir, &req);
                 match x {
@@ -573,25 +840,7 @@ impl Service for FilesService {
                 )))
             }
         } else {
-            match NamedFile::open(path) {
-                Ok(mut named_file) => {
-                    if let Some(ref mime_override) = self.mime_override {
-                        let new_disposition =
-                            mime_override(&named_file.content_type.type_());
-                        named_file.content_disposition.disposition = new_disposition;
-                    }
-
-                    named_file.flags = self.file_flags;
-                    let (req, _) = req.into_parts();
-                    match named_file.into_response(&req) {
-                        Ok(item) => {
-                            Either::Left(ok(ServiceResponse::new(req.clone(), item)))
-                        }
-                        Err(e) => Either::Left(ok(ServiceResponse::from_err(e, req))),
-                    }
-                }
-                Err(e) => self.handle_err(e, req),
-            }
+            self.serve_file(&path, req)
         }
     }
 }
@@ -601,12 +850,18 @@ struct PathBufWrp(PathBuf);
 
 impl PathBufWrp {
     fn get_pathbuf(path: &str) -> Result<Self, UriSegmentError> {
+        PathBufWrp::parse(path, false)
+    }
+
+    fn parse(path: &str, hidden_files: bool) -> Result<Self, UriSegmentError> {
         let mut buf = PathBuf::new();
         for segment in path.split('/') {
             if segment == ".." {
                 buf.pop();
-            } else if segment.starts_with('.') {
+            } else if segment.starts_with('.') && !hidden_files {
                 return Err(UriSegmentError::BadStart('.'));
+            } else if segment == "." {
+                continue;
             } else if segment.starts_with('*') {
                 return Err(UriSegmentError::BadStart('*'));
             } else if segment.ends_with(':') {
@@ -766,7 +1021,7 @@ mod tests {
         );
         assert_eq!(
             resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
-            "inline; filename=\"貨物.toml\"; filename*=UTF-8''%E8%B2%A8%E7%89%A9.toml"
+            "inline; filename=\"__.toml\"; filename*=UTF-8''%E8%B2%A8%E7%89%A9.toml"
         );
     }
 
@@ -990,6 +1245,99 @@ mod tests {
         assert_eq!(contentrange, "bytes */100");
     }
 
+    #[actix_rt::test]
+    async fn test_named_file_multipart_ranges() {
+        let mut srv = test::init_service(App::new().service(Files::new("/", "."))).await;
+        let data = fs::read("tests/test.binary").unwrap();
+
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .header(header::RANGE, "bytes=0-4, 90-")
+            .to_request();
+        let response = test::call_service(&mut srv, request).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let boundary = &content_type["multipart/byteranges; boundary=".len()..];
+
+        let mut expected = Vec::new();
+        for &(start, end) in &[(0, 4), (90, 99)] {
+            expected.extend_from_slice(
+                format!(
+                    "--{}\r\ncontent-type: application/octet-stream\r\n\
+                     content-range: bytes {}-{}/100\r\n\r\n",
+                    boundary, start, end
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[start..=end]);
+            expected.extend_from_slice(b"\r\n");
+        }
+        expected.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        let bytes = test::read_body(response).await;
+        assert_eq!(bytes, Bytes::from(expected));
+
+        // overlapping ranges are merged
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .header(header::RANGE, "bytes=10-19, 0-9, 5-14")
+            .to_request();
+        let response = test::call_service(&mut srv, request).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 0-19/100"
+        );
+        let bytes = test::read_body(response).await;
+        assert_eq!(bytes, Bytes::from(data[..20].to_vec()));
+
+        // too many ranges
+        let ranges: Vec<_> = (0..20).map(|i| format!("{}-{}", i * 4, i * 4)).collect();
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .header(header::RANGE, format!("bytes={}", ranges.join(",")))
+            .to_request();
+        let response = test::call_service(&mut srv, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = test::read_body(response).await;
+        assert_eq!(bytes, Bytes::from(data));
+    }
+
+    #[actix_rt::test]
+    async fn test_named_file_if_range() {
+        let mut srv = test::init_service(App::new().service(Files::new("/", "."))).await;
+
+        let request = TestRequest::get().uri("/tests/test.binary").to_request();
+        let response = test::call_service(&mut srv, request).await;
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .header(header::RANGE, "bytes=10-20")
+            .header(header::IF_RANGE, etag)
+            .to_request();
+        let response = test::call_service(&mut srv, request).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        // stale validator, full content is sent
+        let request = TestRequest::get()
+            .uri("/tests/test.binary")
+            .header(header::RANGE, "bytes=10-20")
+            .header(header::IF_RANGE, "\"stale\"")
+            .to_request();
+        let response = test::call_service(&mut srv, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::CONTENT_RANGE));
+        let bytes = test::read_body(response).await;
+        assert_eq!(bytes.len(), 100);
+    }
+
     #[actix_rt::test]
     async fn test_named_file_content_length_headers() {
         // use actix_web::body::{MessageBody, ResponseBody};
@@ -1228,6 +1576,285 @@ mod tests {
         assert!(format!("{:?}", bytes).contains("/tests/test.png"));
     }
 
+    #[actix_rt::test]
+    async fn test_precompressed_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "actix-files-precompressed-{}",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.js"), "plain").unwrap();
+        fs::write(dir.join("app.js.gz"), "gzipped").unwrap();
+        fs::write(dir.join("other.js"), "other").unwrap();
+
+        let mut srv = test::init_service(
+            App::new()
+                .wrap(Compress::default())
+                .service(Files::new("/", &dir).use_precompressed(true)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app.js")
+            .header(header::ACCEPT_ENCODING, "br, gzip;q=0.8")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+        // content type of original file, not of gzip archive
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            from_ext("js").first_or_octet_stream().as_ref()
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(test::read_body(resp).await, Bytes::from_static(b"gzipped"));
+
+        // encoding is not accepted
+        let req = TestRequest::with_uri("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip;q=0, identity")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+        assert_ne!(resp.headers().get(header::ETAG).unwrap(), &etag);
+        assert_eq!(test::read_body(resp).await, Bytes::from_static(b"plain"));
+
+        // no precompressed variants
+        let req = TestRequest::with_uri("/other.js")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert!(!resp.headers().contains_key(header::VARY));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_spa_fallback() {
+        let mut srv = test::init_service(
+            App::new().service(Files::new("/", ".").spa_fallback("Cargo.toml")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/users/1/profile").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = test::read_body(resp).await;
+        assert_eq!(bytes, Bytes::from(fs::read("Cargo.toml").unwrap()));
+
+        // existing files are served as is
+        let req = TestRequest::with_uri("/tests/test.png").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/png"
+        );
+
+        // missing assets are not rewritten
+        let req = TestRequest::with_uri("/static/app.js").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_file_cache() {
+        let cache = FileCache::new(1024 * 1024);
+        let mut srv = test::init_service(
+            App::new().service(Files::new("/", ".").cache(cache.clone())),
+        )
+        .await;
+        let content = fs::read("Cargo.toml").unwrap();
+
+        for _ in 0..2 {
+            let req = TestRequest::with_uri("/Cargo.toml").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().contains_key(header::ETAG));
+            let bytes = test::read_body(resp).await;
+            assert_eq!(bytes, Bytes::from(content.clone()));
+        }
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), content.len() as u64);
+
+        let req = TestRequest::with_uri("/Cargo.toml")
+            .header(header::RANGE, "bytes=10-20")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let bytes = test::read_body(resp).await;
+        assert_eq!(bytes, Bytes::from(content[10..21].to_vec()));
+    }
+
+    #[actix_rt::test]
+    async fn test_files_listing_with() {
+        let mut srv = test::init_service(App::new().service(
+            Files::new("/", ".").show_files_listing_with(|entries, req| {
+                let names: Vec<_> = entries
+                    .iter()
+                    .filter(|entry| !entry.is_dir)
+                    .map(|entry| format!("{} {}", entry.url, entry.len))
+                    .collect();
+                HttpResponse::Ok()
+                    .header("x-path", req.path())
+                    .body(names.join("\n"))
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/tests").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-path").unwrap(), "/tests");
+        let bytes = test::read_body(resp).await;
+        assert_eq!(
+            bytes,
+            Bytes::from(format!(
+                "/tests/test space.binary 100\n/tests/test.binary 100\n/tests/test.png {}",
+                fs::metadata("tests/test.png").unwrap().len()
+            ))
+        );
+    }
+
+    #[cfg(unix)]
+    #[actix_rt::test]
+    async fn test_symlinks_and_hidden_files() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = std::env::temp_dir().join(format!(
+            "actix-files-symlinks-{}",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let root = tmp.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/file.txt"), "file").unwrap();
+        fs::write(root.join(".env"), "secret").unwrap();
+        fs::write(tmp.join("outside.txt"), "outside").unwrap();
+        symlink(root.join("sub/file.txt"), root.join("inside.txt")).unwrap();
+        symlink(tmp.join("outside.txt"), root.join("outside.txt")).unwrap();
+        symlink(root.join(".env"), root.join("env.txt")).unwrap();
+
+        let status = |files: Files, uri: &'static str| async move {
+            let mut srv = test::init_service(App::new().service(files)).await;
+            let req = TestRequest::with_uri(uri).to_request();
+            test::call_service(&mut srv, req).await.status()
+        };
+
+        let files = || Files::new("/", &root);
+        assert_eq!(status(files(), "/outside.txt").await, StatusCode::OK);
+        assert_eq!(status(files(), "/.env").await, StatusCode::BAD_REQUEST);
+        // target of link is hidden
+        assert_eq!(status(files(), "/env.txt").await, StatusCode::NOT_FOUND);
+
+        let within = || files().follow_symlinks(SymlinkPolicy::WithinRoot);
+        assert_eq!(status(within(), "/inside.txt").await, StatusCode::OK);
+        assert_eq!(
+            status(within(), "/outside.txt").await,
+            StatusCode::NOT_FOUND
+        );
+
+        let never = || files().follow_symlinks(SymlinkPolicy::Never);
+        assert_eq!(status(never(), "/sub/file.txt").await, StatusCode::OK);
+        assert_eq!(status(never(), "/inside.txt").await, StatusCode::NOT_FOUND);
+
+        let hidden = || files().serve_hidden_files(true);
+        assert_eq!(status(hidden(), "/.env").await, StatusCode::OK);
+        assert_eq!(status(hidden(), "/env.txt").await, StatusCode::OK);
+
+        // precompressed variants follow the same policy
+        fs::write(root.join("app.js"), "plain").unwrap();
+        fs::write(tmp.join("app.js.gz"), "outside").unwrap();
+        symlink(tmp.join("app.js.gz"), root.join("app.js.gz")).unwrap();
+        let body = |files: Files| async move {
+            let mut srv = test::init_service(App::new().service(files)).await;
+            let req = TestRequest::with_uri("/app.js")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .to_request();
+            test::read_response(&mut srv, req).await
+        };
+        let precompressed = || files().use_precompressed(true);
+        assert_eq!(body(precompressed()).await, Bytes::from("outside"));
+        assert_eq!(
+            body(precompressed().follow_symlinks(SymlinkPolicy::WithinRoot)).await,
+            Bytes::from("plain")
+        );
+        assert_eq!(
+            body(precompressed().follow_symlinks(SymlinkPolicy::Never)).await,
+            Bytes::from("plain")
+        );
+
+        // listings do not show links that are not served
+        let listing = |files: Files| async move {
+            let mut srv =
+                test::init_service(App::new().service(files.show_files_listing())).await;
+            let req = TestRequest::with_uri("/").to_request();
+            let bytes = test::read_response(&mut srv, req).await;
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+        let html = listing(files()).await;
+        assert!(html.contains("inside.txt") && html.contains("outside.txt"));
+        let html = listing(within()).await;
+        assert!(html.contains("inside.txt") && !html.contains("outside.txt"));
+        let html = listing(never()).await;
+        assert!(!html.contains("inside.txt") && html.contains("sub"));
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_map_file() {
+        let mut srv = test::init_service(App::new().service(
+            Files::new("/", ".").map_file(|file, _| {
+                let etag = file.content_etag().unwrap();
+                let cache_control = match file.path().extension() {
+                    Some(ext) if ext == "toml" => "no-cache",
+                    _ => "immutable",
+                };
+                file.set_etag(etag).set_header(
+                    header::CACHE_CONTROL,
+                    header::HeaderValue::from_static(cache_control),
+                )
+            }),
+        ))
+        .await;
+
+        let file = NamedFile::open("Cargo.toml").unwrap();
+        let expected = file.content_etag().unwrap();
+        let req = TestRequest::with_uri("/Cargo.toml").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(etag.to_str().unwrap(), expected.to_string());
+
+        let req = TestRequest::with_uri("/Cargo.toml")
+            .header(header::IF_NONE_MATCH, etag)
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+
+        let req = TestRequest::with_uri("/tests/test.png").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "immutable"
+        );
+    }
+
     #[actix_rt::test]
     async fn test_redirect_to_slash_directory() {
         // should not redirect if no index
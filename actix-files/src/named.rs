@@ -1,7 +1,12 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::VecDeque;
 use std::fs::{File, Metadata};
-use std::io;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Seek};
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(unix)]
@@ -12,15 +17,18 @@ use mime;
 use mime_guess::from_path;
 
 use actix_http::body::SizedStream;
-use actix_web::dev::BodyEncoding;
+use actix_web::dev::{BodyEncoding, HttpResponseBuilder};
 use actix_web::http::header::{
-    self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+    self, ContentDisposition, DispositionType, HeaderName, HeaderValue,
 };
-use actix_web::http::{ContentEncoding, StatusCode};
+use actix_web::http::{ContentEncoding, HeaderMap, StatusCode};
 use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, Responder};
+use bytes::Bytes;
 use futures::future::{ready, Ready};
+use futures::stream::Stream;
 
-use crate::range::HttpRange;
+use crate::cache::FileCache;
+use crate::range::{HttpRange, MAX_RANGES};
 use crate::ChunkedReadFile;
 
 bitflags! {
@@ -37,6 +45,11 @@ impl Default for Flags {
     }
 }
 
+/// Encodings and file extensions of precompressed variants, in order of
+/// preference
+const PRECOMPRESSED: [(ContentEncoding, &str); 2] =
+    [(ContentEncoding::Br, "br"), (ContentEncoding::Gzip, "gz")];
+
 /// A file with an associated name.
 #[derive(Debug)]
 pub struct NamedFile {
@@ -49,6 +62,15 @@ pub struct NamedFile {
     pub(crate) content_type: mime::Mime,
     pub(crate) content_disposition: header::ContentDisposition,
     pub(crate) encoding: Option<ContentEncoding>,
+    /// Encoding of file content, set for precompressed variants
+    precompressed: Option<ContentEncoding>,
+    /// Precompressed variants of file exist
+    vary: bool,
+    /// Content of file that is loaded from cache
+    content: Option<Bytes>,
+    /// `ETag` that overrides default one
+    custom_etag: Option<header::EntityTag>,
+    headers: Vec<(HeaderName, HeaderValue)>,
 }
 
 impl NamedFile {
@@ -94,19 +116,7 @@ impl NamedFile {
                 mime::IMAGE | mime::TEXT | mime::VIDEO => DispositionType::Inline,
                 _ => DispositionType::Attachment,
             };
-            let mut parameters =
-                vec![DispositionParam::Filename(String::from(filename.as_ref()))];
-            if !filename.is_ascii() {
-                parameters.push(DispositionParam::FilenameExt(ExtendedValue {
-                    charset: Charset::Ext(String::from("UTF-8")),
-                    language_tag: None,
-                    value: filename.into_owned().into_bytes(),
-                }))
-            }
-            let cd = ContentDisposition {
-                disposition: disposition_type,
-                parameters: parameters,
-            };
+            let cd = ContentDisposition::new(disposition_type).filename(filename);
             (ct, cd)
         };
 
@@ -121,6 +131,11 @@ impl NamedFile {
             md,
             modified,
             encoding,
+            precompressed: None,
+            vary: false,
+            content: None,
+            custom_etag: None,
+            headers: Vec::new(),
             status_code: StatusCode::OK,
             flags: Flags::default(),
         })
@@ -139,6 +154,66 @@ impl NamedFile {
         Self::from_file(File::open(&path)?, path)
     }
 
+    /// Attempts to open precompressed variant of file that is accepted by
+    /// `Accept-Encoding` header of request, `<path>.br` or `<path>.gz`. File
+    /// at `path` is opened if there is no such variant.
+    ///
+    /// `Content-Type` and `Content-Disposition` headers are determined by
+    /// `path`, `Content-Encoding` header is set for precompressed variant.
+    /// `Vary: Accept-Encoding` header is set if any variant exists.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use actix_files::NamedFile;
+    /// use actix_web::HttpRequest;
+    ///
+    /// async fn index(req: HttpRequest) -> std::io::Result<NamedFile> {
+    ///     NamedFile::open_precompressed("static/app.js", &req)
+    /// }
+    /// ```
+    pub fn open_precompressed<P: AsRef<Path>>(
+        path: P,
+        req: &HttpRequest,
+    ) -> io::Result<NamedFile> {
+        Self::open_variant(path.as_ref(), req.headers(), |variant| {
+            Ok(variant.to_path_buf())
+        })
+    }
+
+    /// Open accepted variant of file, paths of variants are checked with
+    /// `resolve` and variants are skipped on error.
+    pub(crate) fn open_variant<F>(
+        path: &Path,
+        headers: &HeaderMap,
+        resolve: F,
+    ) -> io::Result<NamedFile>
+    where
+        F: Fn(&Path) -> io::Result<PathBuf>,
+    {
+        let mut vary = false;
+        for &(encoding, ext) in PRECOMPRESSED.iter() {
+            let mut variant = path.as_os_str().to_owned();
+            variant.push(".");
+            variant.push(ext);
+            let variant = match resolve(Path::new(&variant)) {
+                Ok(variant) if variant.is_file() => variant,
+                _ => continue,
+            };
+            vary = true;
+            if accepts_encoding(headers, encoding) {
+                let mut named_file = Self::from_file(File::open(&variant)?, path)?;
+                named_file.precompressed = Some(encoding);
+                named_file.vary = true;
+                return Ok(named_file);
+            }
+        }
+
+        let mut named_file = Self::open(path)?;
+        named_file.vary = vary;
+        Ok(named_file)
+    }
+
     /// Returns reference to the underlying `File` object.
     #[inline]
     pub fn file(&self) -> &File {
@@ -226,6 +301,56 @@ impl NamedFile {
         self
     }
 
+    /// Set `ETag` of file, overrides default `ETag` that is derived from
+    /// metadata of file.
+    #[inline]
+    pub fn set_etag(mut self, etag: header::EntityTag) -> Self {
+        self.custom_etag = Some(etag);
+        self
+    }
+
+    /// Set header of response, i.e. `Cache-Control`.
+    ///
+    /// Header replaces header of the same name that is set for file.
+    #[inline]
+    pub fn set_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Returns metadata of file.
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.md
+    }
+
+    /// Strong `ETag` derived from hash of file content.
+    ///
+    /// Whole file is read to compute hash, so it is suitable for small
+    /// files, i.e. html pages that are rebuilt with the same content.
+    pub fn content_etag(&self) -> io::Result<header::EntityTag> {
+        let mut hasher = DefaultHasher::new();
+        match self.content {
+            Some(ref content) => hasher.write(content),
+            None => {
+                let mut file = self.file.try_clone()?;
+                file.seek(io::SeekFrom::Start(0))?;
+                let mut buf = [0; 8192];
+                loop {
+                    match file.read(&mut buf)? {
+                        0 => break,
+                        n => hasher.write(&buf[..n]),
+                    }
+                }
+            }
+        }
+        Ok(header::EntityTag::strong(format!(
+            "{:x}:{:016x}",
+            self.md.len(),
+            hasher.finish()
+        )))
+    }
+
     pub(crate) fn etag(&self) -> Option<header::EntityTag> {
         // This etag format is similar to Apache's.
         self.modified.as_ref().map(|mtime| {
@@ -257,6 +382,42 @@ impl NamedFile {
         self.modified.map(|mtime| mtime.into())
     }
 
+    /// Load content of file from cache, content is served from memory if
+    /// file is cached.
+    pub(crate) fn load_cached(&mut self, cache: &FileCache) -> io::Result<()> {
+        let path = match self.precompressed {
+            Some(encoding) => {
+                let ext = PRECOMPRESSED
+                    .iter()
+                    .find(|(enc, _)| *enc == encoding)
+                    .map_or("", |&(_, ext)| ext);
+                let mut path = self.path.as_os_str().to_owned();
+                path.push(".");
+                path.push(ext);
+                PathBuf::from(path)
+            }
+            None => self.path.clone(),
+        };
+        self.content = cache.load(&path, &mut self.file, &self.md)?;
+        Ok(())
+    }
+
+    /// Set `Content-Encoding` of precompressed variant, `Vary` and custom
+    /// headers
+    fn extra_headers(&self, resp: &mut HttpResponseBuilder) {
+        if let Some(encoding) = self.precompressed {
+            // content is sent as is, compress middleware skips responses
+            // with `Content-Encoding` header
+            resp.header(header::CONTENT_ENCODING, encoding.as_str());
+        }
+        if self.vary {
+            resp.header(header::VARY, "Accept-Encoding");
+        }
+        for (name, value) in &self.headers {
+            resp.set_header(name.clone(), value.clone());
+        }
+    }
+
     pub fn into_response(self, req: &HttpRequest) -> Result<HttpResponse, Error> {
         if self.status_code != StatusCode::OK {
             let mut resp = HttpResponse::build(self.status_code);
@@ -270,6 +431,10 @@ impl NamedFile {
             if let Some(current_encoding) = self.encoding {
                 resp.encoding(current_encoding);
             }
+            self.extra_headers(&mut resp);
+            if let Some(content) = self.content {
+                return Ok(resp.body(content));
+            }
             let reader = ChunkedReadFile {
                 size: self.md.len(),
                 offset: 0,
@@ -281,7 +446,7 @@ impl NamedFile {
         }
 
         let etag = if self.flags.contains(Flags::ETAG) {
-            self.etag()
+            self.custom_etag.clone().or_else(|| self.etag())
         } else {
             None
         };
@@ -324,6 +489,7 @@ impl NamedFile {
         } else {
             false
         };
+        let if_range = if_range(etag.as_ref(), last_modified.as_ref(), req);
 
         let mut resp = HttpResponse::build(self.status_code);
         resp.set(header::ContentType(self.content_type.clone()))
@@ -337,6 +503,7 @@ impl NamedFile {
         if let Some(current_encoding) = self.encoding {
             resp.encoding(current_encoding);
         }
+        self.extra_headers(&mut resp);
 
         resp.if_some(last_modified, |lm, resp| {
             resp.set(header::LastModified(lm));
@@ -349,23 +516,32 @@ impl NamedFile {
 
         let mut length = self.md.len();
         let mut offset = 0;
+        let mut multipart = None;
 
-        // check for range header
-        if let Some(ranges) = req.headers().get(&header::RANGE) {
+        // check for range header, range of stale representation is not sent
+        if let Some(ranges) = req.headers().get(&header::RANGE).filter(|_| if_range) {
             if let Ok(rangesheader) = ranges.to_str() {
                 if let Ok(rangesvec) = HttpRange::parse(rangesheader, length) {
-                    length = rangesvec[0].length;
-                    offset = rangesvec[0].start;
-                    resp.encoding(ContentEncoding::Identity);
-                    resp.header(
-                        header::CONTENT_RANGE,
-                        format!(
-                            "bytes {}-{}/{}",
-                            offset,
-                            offset + length - 1,
-                            self.md.len()
-                        ),
-                    );
+                    let rangesvec = HttpRange::merge(rangesvec);
+                    if rangesvec.len() > MAX_RANGES {
+                        // too many ranges are sent as full content
+                    } else if rangesvec.len() > 1 {
+                        resp.encoding(ContentEncoding::Identity);
+                        multipart = Some(rangesvec);
+                    } else if let Some(range) = rangesvec.first() {
+                        length = range.length;
+                        offset = range.start;
+                        resp.encoding(ContentEncoding::Identity);
+                        resp.header(
+                            header::CONTENT_RANGE,
+                            format!(
+                                "bytes {}-{}/{}",
+                                offset,
+                                offset + length - 1,
+                                self.md.len()
+                            ),
+                        );
+                    }
                 } else {
                     resp.header(header::CONTENT_RANGE, format!("bytes */{}", length));
                     return Ok(resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish());
@@ -381,6 +557,22 @@ impl NamedFile {
             return Ok(resp.status(StatusCode::NOT_MODIFIED).finish());
         }
 
+        if let Some(ranges) = multipart {
+            let boundary = boundary();
+            resp.content_type(format!("multipart/byteranges; boundary={}", boundary));
+            let body = self.multipart_ranges(&ranges, &boundary);
+            return Ok(resp.status(StatusCode::PARTIAL_CONTENT).body(body));
+        }
+
+        if let Some(content) = self.content {
+            let content = content.slice(offset as usize..(offset + length) as usize);
+            return if offset != 0 || length != self.md.len() {
+                Ok(resp.status(StatusCode::PARTIAL_CONTENT).body(content))
+            } else {
+                Ok(resp.body(content))
+            };
+        }
+
         let reader = ChunkedReadFile {
             offset,
             size: length,
@@ -394,6 +586,98 @@ impl NamedFile {
             Ok(resp.body(SizedStream::new(length, reader)))
         }
     }
+
+    /// Body of `multipart/byteranges` response, every range is sent as part
+    /// with `Content-Type` and `Content-Range` headers.
+    fn multipart_ranges(
+        self,
+        ranges: &[HttpRange],
+        boundary: &str,
+    ) -> SizedStream<MultipartRanges> {
+        let mut parts = VecDeque::with_capacity(ranges.len() * 3 + 1);
+        let mut size = 0;
+
+        for range in ranges {
+            let head = Bytes::from(format!(
+                "--{}\r\ncontent-type: {}\r\ncontent-range: bytes {}-{}/{}\r\n\r\n",
+                boundary,
+                self.content_type,
+                range.start,
+                range.start + range.length - 1,
+                self.md.len()
+            ));
+            size += head.len() as u64 + range.length + 2;
+            parts.push_back(RangePart::Bytes(head));
+
+            match self.content {
+                Some(ref content) => {
+                    let end = range.start + range.length;
+                    let data = content.slice(range.start as usize..end as usize);
+                    parts.push_back(RangePart::Bytes(data));
+                }
+                None => parts.push_back(RangePart::File(*range)),
+            }
+            parts.push_back(RangePart::Bytes(Bytes::from_static(b"\r\n")));
+        }
+
+        let tail = Bytes::from(format!("--{}--\r\n", boundary));
+        size += tail.len() as u64;
+        parts.push_back(RangePart::Bytes(tail));
+
+        let body = MultipartRanges {
+            parts,
+            reader: None,
+            file: Some(self.file),
+        };
+        SizedStream::new(size, body)
+    }
+}
+
+enum RangePart {
+    Bytes(Bytes),
+    File(HttpRange),
+}
+
+/// Stream of `multipart/byteranges` parts, ranges of file are read one
+/// after another with same file handle.
+pub(crate) struct MultipartRanges {
+    parts: VecDeque<RangePart>,
+    reader: Option<ChunkedReadFile>,
+    file: Option<File>,
+}
+
+impl Stream for MultipartRanges {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(ref mut reader) = self.reader {
+                match Pin::new(reader).poll_next(cx) {
+                    Poll::Ready(None) => {}
+                    poll => return poll,
+                }
+                // file is returned to reader after every chunk
+                self.file = self.reader.take().and_then(|reader| reader.file);
+            }
+
+            match self.parts.pop_front() {
+                Some(RangePart::Bytes(bytes)) => return Poll::Ready(Some(Ok(bytes))),
+                Some(RangePart::File(range)) => {
+                    self.reader = Some(ChunkedReadFile {
+                        offset: range.start,
+                        size: range.length,
+                        file: self.file.take(),
+                        fut: None,
+                        counter: 0,
+                    })
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
 }
 
 impl Deref for NamedFile {
@@ -410,8 +694,65 @@ impl DerefMut for NamedFile {
     }
 }
 
+/// Returns true if `Accept-Encoding` header allows `encoding`.
+fn accepts_encoding(headers: &HeaderMap, encoding: ContentEncoding) -> bool {
+    let mut wildcard = false;
+    for value in headers.get_all(header::ACCEPT_ENCODING) {
+        let value = match value.to_str() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        for item in value.split(',') {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or("").trim();
+            let quality = parts
+                .find_map(|param| {
+                    let param = param.trim();
+                    if param.starts_with("q=") {
+                        param[2..].trim().parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(1.0);
+
+            if name.eq_ignore_ascii_case(encoding.as_str()) {
+                return quality > 0.0;
+            } else if name == "*" {
+                wildcard = quality > 0.0;
+            }
+        }
+    }
+    wildcard
+}
+
+/// Returns true if `req` has no `If-Range` header or one which matches
+/// current `etag` or `last_modified` validator.
+pub(crate) fn if_range(
+    etag: Option<&header::EntityTag>,
+    last_modified: Option<&header::HttpDate>,
+    req: &HttpRequest,
+) -> bool {
+    if !req.headers().contains_key(&header::IF_RANGE) {
+        return true;
+    }
+    match req.get_header::<header::IfRange>() {
+        Some(header::IfRange::EntityTag(ref tag)) => {
+            etag.map_or(false, |etag| tag.strong_eq(etag))
+        }
+        Some(header::IfRange::Date(ref date)) => last_modified == Some(date),
+        None => false,
+    }
+}
+
+/// Random boundary of `multipart/byteranges` response
+fn boundary() -> String {
+    let random = || RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", random(), random())
+}
+
 /// Returns true if `req` has no `If-Match` header or one which matches `etag`.
-fn any_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
+pub(crate) fn any_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
     match req.get_header::<header::IfMatch>() {
         None | Some(header::IfMatch::Any) => true,
         Some(header::IfMatch::Items(ref items)) => {
@@ -428,7 +769,7 @@ fn any_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
 }
 
 /// Returns true if `req` doesn't have an `If-None-Match` header matching `req`.
-fn none_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
+pub(crate) fn none_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
     match req.get_header::<header::IfNoneMatch>() {
         Some(header::IfNoneMatch::Any) => false,
         Some(header::IfNoneMatch::Items(ref items)) => {
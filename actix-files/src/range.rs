@@ -1,3 +1,9 @@
+use std::cmp;
+
+/// Maximum number of ranges sent in `multipart/byteranges` response, full
+/// content is sent if request has more ranges after merging.
+pub(crate) const MAX_RANGES: usize = 16;
+
 /// HTTP Range header representation.
 #[derive(Debug, Clone, Copy)]
 pub struct HttpRange {
@@ -91,6 +97,24 @@ impl HttpRange {
 
         Ok(ranges)
     }
+
+    /// Sort ranges by start and merge overlapping or adjacent ranges.
+    pub(crate) fn merge(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = merged.last_mut() {
+                let end = last.start + last.length;
+                if range.start <= end {
+                    last.length = cmp::max(end, range.start + range.length) - last.start;
+                    continue;
+                }
+            }
+            merged.push(range);
+        }
+        merged
+    }
 }
 
 #[cfg(test)]
@@ -372,4 +396,17 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_merge() {
+        let ranges = HttpRange::parse("bytes=20-29,0-4,5-9,3-7,40-", 50).unwrap();
+        let merged: Vec<_> = HttpRange::merge(ranges)
+            .into_iter()
+            .map(|r| (r.start, r.length))
+            .collect();
+        assert_eq!(merged, vec![(0, 10), (20, 10), (40, 10)]);
+
+        let ranges = HttpRange::parse("bytes=0-9,-5,5-", 10).unwrap();
+        assert_eq!(HttpRange::merge(ranges).len(), 1);
+    }
 }
@@ -0,0 +1,234 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::{Error, HttpRequest, HttpResponse, Responder};
+use futures::future::{ready, Ready};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+use crate::named::{Flags, NamedFile};
+
+/// Characters that are encoded in `X-Accel-Redirect` uri
+const URI_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'?')
+    .add(b'<')
+    .add(b'>');
+
+#[derive(Debug, Clone)]
+enum Mode {
+    AccelRedirect { root: PathBuf, prefix: String },
+    Sendfile,
+}
+
+/// Configuration of `Sendfile` responder.
+///
+/// Configuration is registered as application data with `App::app_data()`,
+/// `Sendfile` streams file as `NamedFile` if it is not registered.
+#[derive(Debug, Clone)]
+pub struct SendfileConfig(Mode);
+
+impl SendfileConfig {
+    /// Use `X-Accel-Redirect` header of nginx.
+    ///
+    /// Files in `root` directory are redirected to internal location
+    /// `prefix`, i.e. file `root/a/b.txt` is redirected to `prefix/a/b.txt`.
+    /// Files outside of `root` directory are streamed.
+    pub fn x_accel_redirect<P: AsRef<Path>, S: Into<String>>(
+        root: P,
+        prefix: S,
+    ) -> SendfileConfig {
+        let root = root.as_ref();
+        SendfileConfig(Mode::AccelRedirect {
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            prefix: prefix.into().trim_end_matches('/').to_owned(),
+        })
+    }
+
+    /// Use `X-Sendfile` header of Apache `mod_xsendfile` and lighttpd,
+    /// header contains absolute path of file.
+    pub fn x_sendfile() -> SendfileConfig {
+        SendfileConfig(Mode::Sendfile)
+    }
+
+    /// Internal redirect header for file
+    fn header(&self, path: &Path) -> io::Result<Option<(HeaderName, HeaderValue)>> {
+        let path = path.canonicalize()?;
+
+        match self.0 {
+            Mode::AccelRedirect {
+                ref root,
+                ref prefix,
+            } => {
+                let relative = match path.strip_prefix(root) {
+                    Ok(relative) => relative,
+                    Err(_) => return Ok(None),
+                };
+                let mut uri = prefix.clone();
+                for component in relative.components() {
+                    let component = match component.as_os_str().to_str() {
+                        Some(component) => component,
+                        None => return Ok(None),
+                    };
+                    uri.push('/');
+                    uri.extend(utf8_percent_encode(component, URI_SET));
+                }
+                Ok(HeaderValue::from_str(&uri)
+                    .ok()
+                    .map(|val| (HeaderName::from_static("x-accel-redirect"), val)))
+            }
+            Mode::Sendfile => Ok(path
+                .to_str()
+                .and_then(|path| HeaderValue::from_str(path).ok())
+                .map(|val| (HeaderName::from_static("x-sendfile"), val))),
+        }
+    }
+}
+
+/// Responder that delegates sending of file to reverse proxy.
+///
+/// If `SendfileConfig` is registered, response has no body and contains
+/// internal redirect header, so proxy serves the file, handling ranges and
+/// conditional requests itself. Otherwise file is streamed by application
+/// as `NamedFile`.
+///
+/// ```rust
+/// use actix_files::{Sendfile, SendfileConfig};
+/// use actix_web::{web, App, HttpRequest};
+///
+/// async fn download(req: HttpRequest) -> std::io::Result<Sendfile> {
+///     let name = req.match_info().query("name");
+///     Sendfile::open(format!("/var/www/files/{}", name))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .app_data(SendfileConfig::x_accel_redirect("/var/www/files", "/protected"))
+///         .route("/files/{name}", web::get().to(download));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Sendfile(NamedFile);
+
+impl Sendfile {
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Sendfile> {
+        Ok(Sendfile(NamedFile::open(path)?))
+    }
+
+    /// Returns reference to the underlying `NamedFile`.
+    pub fn file(&self) -> &NamedFile {
+        &self.0
+    }
+}
+
+impl From<NamedFile> for Sendfile {
+    fn from(file: NamedFile) -> Sendfile {
+        Sendfile(file)
+    }
+}
+
+impl Responder for Sendfile {
+    type Error = Error;
+    type Future = Ready<Result<HttpResponse, Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let header = match req.app_data::<SendfileConfig>() {
+            Some(config) => match config.header(self.0.path()) {
+                Ok(header) => header,
+                Err(e) => return ready(Err(e.into())),
+            },
+            None => None,
+        };
+
+        let (name, value) = match header {
+            Some(header) => header,
+            None => return ready(self.0.into_response(req)),
+        };
+
+        let file = self.0;
+        let mut resp = HttpResponse::build(file.status_code);
+        resp.set(header::ContentType(file.content_type.clone()))
+            .if_true(file.flags.contains(Flags::CONTENT_DISPOSITION), |res| {
+                res.header(
+                    header::CONTENT_DISPOSITION,
+                    file.content_disposition.to_string(),
+                );
+            })
+            .header(name, value);
+        ready(Ok(resp.finish()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+
+    #[actix_rt::test]
+    async fn test_x_accel_redirect() {
+        let req = TestRequest::default()
+            .app_data(SendfileConfig::x_accel_redirect(".", "/protected/"))
+            .to_http_request();
+        let resp = Sendfile::open("tests/test space.binary")
+            .unwrap()
+            .respond_to(&req)
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers().get("x-accel-redirect").unwrap(),
+            "/protected/tests/test%20space.binary"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/octet-stream"
+        );
+        let resp = test::TestRequest::default().to_srv_response(resp);
+        assert!(test::read_body(resp).await.is_empty());
+
+        // file outside of root is streamed
+        let req = TestRequest::default()
+            .app_data(SendfileConfig::x_accel_redirect("tests", "/protected"))
+            .to_http_request();
+        let resp = Sendfile::open("Cargo.toml")
+            .unwrap()
+            .respond_to(&req)
+            .await
+            .unwrap();
+        assert!(!resp.headers().contains_key("x-accel-redirect"));
+        let resp = test::TestRequest::default().to_srv_response(resp);
+        assert!(!test::read_body(resp).await.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_x_sendfile() {
+        let req = TestRequest::default()
+            .app_data(SendfileConfig::x_sendfile())
+            .to_http_request();
+        let resp = Sendfile::open("Cargo.toml")
+            .unwrap()
+            .respond_to(&req)
+            .await
+            .unwrap();
+        let path = resp.headers().get("x-sendfile").unwrap().to_str().unwrap();
+        assert_eq!(
+            Path::new(path),
+            Path::new("Cargo.toml").canonicalize().unwrap()
+        );
+
+        // not configured
+        let req = TestRequest::default().to_http_request();
+        let resp = Sendfile::open("Cargo.toml")
+            .unwrap()
+            .respond_to(&req)
+            .await
+            .unwrap();
+        assert!(!resp.headers().contains_key("x-sendfile"));
+        let resp = test::TestRequest::default().to_srv_response(resp);
+        let body = test::read_body(resp).await;
+        assert_eq!(body, std::fs::read("Cargo.toml").unwrap());
+    }
+}
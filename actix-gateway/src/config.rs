@@ -0,0 +1,189 @@
+//! Route configuration file
+use std::convert::TryFrom;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+use actix_web::dev::ResourceDef;
+use actix_web::http::{StatusCode, Uri};
+use serde::Deserialize;
+
+use crate::error::ConfigError;
+use crate::route::prefix_pattern;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    routes: Vec<RouteConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteConfig {
+    path: String,
+    #[serde(default)]
+    handler: Option<String>,
+    #[serde(default)]
+    redirect: Option<String>,
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(rename = "static", default)]
+    static_dir: Option<PathBuf>,
+    #[serde(default)]
+    proxy: Option<String>,
+}
+
+/// Target of configured route
+#[derive(Debug, PartialEq)]
+pub(crate) enum Target {
+    Handler(String),
+    Redirect(String, StatusCode),
+    Static(PathBuf),
+    Proxy(String),
+}
+
+/// Parse route configuration, relative static directories are resolved
+/// against `base` directory.
+pub(crate) fn parse(
+    data: &[u8],
+    base: &Path,
+) -> Result<Vec<(String, Target)>, ConfigError> {
+    let config: Config = serde_json::from_slice(data)?;
+
+    let mut routes: Vec<(String, Target)> = Vec::with_capacity(config.routes.len());
+    for route in config.routes {
+        let path = route.path;
+        if !path.starts_with('/') {
+            return Err(ConfigError::route(&path, "path must start with /"));
+        }
+        if routes.iter().any(|(p, _)| *p == path) {
+            return Err(ConfigError::route(&path, "duplicate path"));
+        }
+        if route.status.is_some() && route.redirect.is_none() {
+            return Err(ConfigError::route(
+                &path,
+                "status is only allowed for redirect",
+            ));
+        }
+
+        let target = match (route.handler, route.redirect, route.static_dir, route.proxy)
+        {
+            (Some(name), None, None, None) => Target::Handler(name),
+            (None, Some(location), None, None) => {
+                let status = match route.status {
+                    Some(status) => StatusCode::from_u16(status)
+                        .ok()
+                        .filter(|status| status.is_redirection())
+                        .ok_or_else(|| {
+                            ConfigError::route(&path, "redirect status must be 3xx")
+                        })?,
+                    None => StatusCode::FOUND,
+                };
+                Target::Redirect(location, status)
+            }
+            (None, None, Some(dir), None) => {
+                let dir = base.join(dir);
+                if !dir.is_dir() {
+                    return Err(ConfigError::route(
+                        &path,
+                        format!("{} is not a directory", dir.display()),
+                    ));
+                }
+                Target::Static(dir)
+            }
+            (None, None, None, Some(url)) => {
+                let absolute = Uri::try_from(url.as_str())
+                    .map(|uri| uri.scheme().is_some() && uri.authority().is_some())
+                    .unwrap_or(false);
+                if !absolute {
+                    return Err(ConfigError::route(
+                        &path,
+                        format!("proxy target {} is not absolute url", url),
+                    ));
+                }
+                Target::Proxy(url)
+            }
+            _ => {
+                return Err(ConfigError::route(
+                    &path,
+                    "route must have one of handler, redirect, static or proxy",
+                ))
+            }
+        };
+
+        // malformed pattern would panic once resource is constructed
+        let pattern = match target {
+            Target::Static(_) | Target::Proxy(_) => prefix_pattern(&path),
+            _ => path.clone(),
+        };
+        if panic::catch_unwind(|| ResourceDef::new(pattern.as_str())).is_err() {
+            return Err(ConfigError::route(&path, "malformed path pattern"));
+        }
+        routes.push((path, target));
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let routes = parse(
+            br#"{"routes": [
+                {"path": "/", "handler": "index"},
+                {"path": "/old/{tail:.*}", "redirect": "/new/{tail}", "status": 301},
+                {"path": "/docs", "redirect": "https://docs.rs"},
+                {"path": "/src", "static": "src"},
+                {"path": "/api", "proxy": "http://127.0.0.1:8080/v1"}
+            ]}"#,
+            base,
+        )
+        .unwrap();
+        assert_eq!(
+            routes,
+            vec![
+                ("/".to_owned(), Target::Handler("index".to_owned())),
+                (
+                    "/old/{tail:.*}".to_owned(),
+                    Target::Redirect(
+                        "/new/{tail}".to_owned(),
+                        StatusCode::MOVED_PERMANENTLY
+                    )
+                ),
+                (
+                    "/docs".to_owned(),
+                    Target::Redirect("https://docs.rs".to_owned(), StatusCode::FOUND)
+                ),
+                ("/src".to_owned(), Target::Static(base.join("src"))),
+                (
+                    "/api".to_owned(),
+                    Target::Proxy("http://127.0.0.1:8080/v1".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let invalid: &[&[u8]] = &[
+            br#"{"routes": [{"path": "/"}]}"#,
+            br#"{"routes": [{"path": "/", "handler": "a", "proxy": "http://a"}]}"#,
+            br#"{"routes": [{"path": "a", "handler": "a"}]}"#,
+            br#"{"routes": [{"path": "/", "handler": "a"}, {"path": "/", "handler": "b"}]}"#,
+            br#"{"routes": [{"path": "/", "redirect": "/a", "status": 200}]}"#,
+            br#"{"routes": [{"path": "/", "handler": "a", "status": 301}]}"#,
+            br#"{"routes": [{"path": "/", "static": "missing"}]}"#,
+            br#"{"routes": [{"path": "/", "proxy": "/relative"}]}"#,
+            br#"{"routes": [{"path": "/", "handler": "a", "unknown": 1}]}"#,
+            br#"{"routes": [{"path": "/{bad", "handler": "a"}]}"#,
+            br#"{"routes": [{"path": "/{id:(}", "redirect": "/a"}]}"#,
+            br#"{"routes": [{"path": "/{bad", "proxy": "http://a"}]}"#,
+        ];
+        for data in invalid {
+            assert!(parse(data, base).is_err());
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Error and Result module
+use std::io;
+
+use derive_more::{Display, From};
+
+/// A set of errors that can occur during loading route configuration
+#[derive(Debug, Display, From)]
+pub enum ConfigError {
+    /// Can not read configuration file
+    #[display(fmt = "Can not read route configuration: {}", _0)]
+    Io(io::Error),
+    /// Can not parse json of configuration file
+    #[display(fmt = "Json deserialize error: {}", _0)]
+    Json(serde_json::Error),
+    /// Route is not valid, i.e. it refers to unknown handler
+    #[display(fmt = "Invalid route {}: {}", path, msg)]
+    #[from(ignore)]
+    Route { path: String, msg: String },
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ConfigError {
+    pub(crate) fn route<T: Into<String>>(path: &str, msg: T) -> Self {
+        ConfigError::Route {
+            path: path.to_owned(),
+            msg: msg.into(),
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Configuration file driven routing for actix web.
+//!
+//! `Gateway` loads routes from json file and serves them through
+//! `web::DynamicRoutes`, so routes are changed on running server without
+//! redeploy. Each route maps path pattern to one of targets:
+//!
+//! * `handler` - resource registered with `Gateway::handler()` by name
+//! * `redirect` - redirect to url, `{name}` segments of url are replaced
+//!   with matched segments of path, status is set with optional `status`
+//!   field and is `302 Found` by default
+//! * `static` - files of directory, path is prefix of served files,
+//!   relative directories are resolved against directory of configuration
+//!   file
+//! * `proxy` - upstream url, path is prefix of forwarded requests, path
+//!   under prefix and query are appended to upstream url, hop-by-hop
+//!   headers and headers listed in `Connection` header are not forwarded
+//!
+//! ```json
+//! {
+//!     "routes": [
+//!         { "path": "/health", "handler": "health" },
+//!         { "path": "/blog/{tail:.*}", "redirect": "https://blog.example.com/{tail}", "status": 301 },
+//!         { "path": "/assets", "static": "public" },
+//!         { "path": "/api", "proxy": "http://127.0.0.1:8081/v1" }
+//!     ]
+//! }
+//! ```
+//!
+//! `Gateway::watch()` checks configuration file for changes and reloads
+//! routes, routing table is swapped at once. If configuration is not
+//! valid, error is logged and previous routes are kept. Configuration
+//! file should be replaced with rename, so partially written file is not
+//! loaded.
+//!
+//! ```rust,no_run
+//! use actix_gateway::Gateway;
+//! use actix_web::{web, App, HttpResponse, HttpServer};
+//!
+//! #[actix_rt::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let gateway = Gateway::new("routes.json")
+//!         .handler("health", |r| r.route(web::get().to(|| HttpResponse::Ok())));
+//!     gateway
+//!         .load()
+//!         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+//!     actix_rt::spawn(gateway.clone().watch());
+//!
+//!     HttpServer::new(move || App::new().service(gateway.clone()))
+//!         .bind("127.0.0.1:8080")?
+//!         .run()
+//!         .await
+//! }
+//! ```
+mod config;
+mod error;
+mod route;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use actix_rt::time::delay_for;
+use actix_web::dev::{AppService, HttpServiceFactory};
+use actix_web::web::DynamicRoutes;
+use actix_web::Resource;
+use log::{error, info};
+
+use crate::config::Target;
+pub use crate::error::ConfigError;
+
+type ResourceFn = Arc<dyn Fn(Resource) -> Resource + Send + Sync>;
+
+/// Modification time and size of configuration file
+type Stamp = (SystemTime, u64);
+
+/// Routes loaded from configuration file.
+///
+/// Handle could be cloned, clones share routing table. `Gateway` must be
+/// registered with `App::service()` method, routes are matched after
+/// services registered before gateway.
+#[derive(Clone)]
+pub struct Gateway {
+    path: PathBuf,
+    handlers: HashMap<String, ResourceFn>,
+    interval: Duration,
+    routes: DynamicRoutes,
+    stamp: Arc<Mutex<Option<Stamp>>>,
+}
+
+impl Gateway {
+    /// Create new gateway for configuration file at `path`.
+    ///
+    /// Routing table is empty until configuration is loaded.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Gateway {
+            path: path.into(),
+            handlers: HashMap::new(),
+            interval: Duration::from_secs(1),
+            routes: DynamicRoutes::new(),
+            stamp: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Register named handler, routes with `handler` target are
+    /// configured by `f`.
+    pub fn handler<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(Resource) -> Resource + Send + Sync + 'static,
+    {
+        self.handlers.insert(name.to_owned(), Arc::new(f));
+        self
+    }
+
+    /// Set interval of configuration file checks, by default 1 second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Routing table of gateway
+    pub fn routes(&self) -> &DynamicRoutes {
+        &self.routes
+    }
+
+    /// Load routes from configuration file and replace routing table.
+    ///
+    /// Routing table is not changed if configuration is not valid.
+    pub fn load(&self) -> Result<(), ConfigError> {
+        let stamp = stamp(&self.path)?;
+        let res = self.load_routes();
+        *self.stamp.lock().unwrap() = Some(stamp);
+        res
+    }
+
+    /// Check configuration file for changes and reload routes, runs until
+    /// it is dropped.
+    pub async fn watch(self) {
+        loop {
+            delay_for(self.interval).await;
+
+            let stamp = match stamp(&self.path) {
+                Ok(stamp) => stamp,
+                Err(e) => {
+                    error!("Can not check {}: {}", self.path.display(), e);
+                    continue;
+                }
+            };
+            if *self.stamp.lock().unwrap() == Some(stamp) {
+                continue;
+            }
+
+            match self.load() {
+                Ok(_) => info!("Routes are reloaded from {}", self.path.display()),
+                Err(e) => error!(
+                    "Can not reload routes from {}, previous routes are kept: {}",
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    fn load_routes(&self) -> Result<(), ConfigError> {
+        let data = fs::read(&self.path)?;
+        let base = self.path.parent().unwrap_or_else(|| Path::new(""));
+
+        let routes = DynamicRoutes::new();
+        for (path, target) in config::parse(&data, base)? {
+            let res = match target {
+                Target::Handler(name) => {
+                    let f = self.handlers.get(&name).cloned().ok_or_else(|| {
+                        ConfigError::route(&path, format!("unknown handler {}", name))
+                    })?;
+                    routes.add(&path, move |r| f(r))
+                }
+                Target::Redirect(location, status) => {
+                    routes.add(&path, route::redirect(location, status))
+                }
+                Target::Static(dir) => {
+                    routes.add(&route::prefix_pattern(&path), route::files(dir))
+                }
+                Target::Proxy(url) => {
+                    routes.add(&route::prefix_pattern(&path), route::proxy(url))
+                }
+            };
+            res.map_err(|e| ConfigError::route(&path, e.to_string()))?;
+        }
+        self.routes.replace(&routes);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gateway")
+            .field("path", &self.path)
+            .field("interval", &self.interval)
+            .field("routes", &self.routes.paths())
+            .finish()
+    }
+}
+
+impl HttpServiceFactory for Gateway {
+    fn register(self, config: &mut AppService) {
+        self.routes.register(config)
+    }
+}
+
+fn stamp(path: &Path) -> Result<Stamp, ConfigError> {
+    let meta = fs::metadata(path)?;
+    Ok((meta.modified()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn write_config(path: &Path, config: &str) {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, config).unwrap();
+        fs::rename(&tmp, path).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_gateway() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("public")).unwrap();
+        fs::write(dir.path().join("public/index.html"), "index").unwrap();
+        fs::write(dir.path().join("public/a.txt"), "file").unwrap();
+
+        let upstream = test::start(|| {
+            App::new().route(
+                "/v1/{tail:.*}",
+                web::to(|req: HttpRequest, body: Bytes| async move {
+                    let host = req.headers().get("x-forwarded-host").cloned();
+                    let secret = req.headers().contains_key("x-secret");
+                    HttpResponse::Ok()
+                        .header("x-path", req.uri().to_string())
+                        .header("x-host", host.unwrap())
+                        .header("x-secret", secret.to_string())
+                        .body(body)
+                }),
+            )
+        });
+
+        let config = dir.path().join("routes.json");
+        write_config(
+            &config,
+            &format!(
+                r#"{{"routes": [
+                    {{"path": "/health", "handler": "health"}},
+                    {{"path": "/blog/{{tail:.*}}", "redirect": "/b/{{tail}}", "status": 301}},
+                    {{"path": "/assets", "static": "public"}},
+                    {{"path": "/api", "proxy": "{}"}}
+                ]}}"#,
+                upstream.url("/v1")
+            ),
+        );
+        let gateway = Gateway::new(&config)
+            .handler("health", |r| r.to(|| HttpResponse::Ok().body("ok")));
+        gateway.load().unwrap();
+        let mut srv = test::init_service(App::new().service(gateway.clone())).await;
+
+        let req = TestRequest::with_uri("/health").to_request();
+        assert_eq!(test::read_response(&mut srv, req).await, Bytes::from("ok"));
+
+        let req = TestRequest::with_uri("/blog/2020/post").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/b/2020/post"
+        );
+
+        let req = TestRequest::with_uri("/assets/a.txt").to_request();
+        assert_eq!(
+            test::read_response(&mut srv, req).await,
+            Bytes::from("file")
+        );
+        let req = TestRequest::with_uri("/assets/").to_request();
+        assert_eq!(
+            test::read_response(&mut srv, req).await,
+            Bytes::from("index")
+        );
+        let req = TestRequest::with_uri("/assets/../routes.json").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // symlinks that leave directory are not followed
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&config, dir.path().join("public/link")).unwrap();
+            let req = TestRequest::with_uri("/assets/link").to_request();
+            let resp = test::call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+
+        let req = TestRequest::post()
+            .uri("/api/users?id=1")
+            .header(header::HOST, "example.com")
+            .header(header::CONTENT_LENGTH, "4")
+            .set_payload("body")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-path").unwrap(), "/v1/users?id=1");
+        assert_eq!(resp.headers().get("x-host").unwrap(), "example.com");
+        assert_eq!(resp.headers().get("x-secret").unwrap(), "false");
+        assert_eq!(test::read_body(resp).await, Bytes::from("body"));
+
+        // headers listed in connection header are not forwarded
+        let req = TestRequest::with_uri("/api")
+            .header(header::CONNECTION, "x-secret")
+            .header("x-secret", "1")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-path").unwrap(), "/v1/");
+        assert_eq!(resp.headers().get("x-secret").unwrap(), "false");
+
+        let req = TestRequest::with_uri("/apiv2").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("routes.json");
+        write_config(&config, r#"{"routes": [{"path": "/a", "handler": "a"}]}"#);
+
+        let gateway = Gateway::new(&config)
+            .interval(Duration::from_millis(10))
+            .handler("a", |r| r.to(|| HttpResponse::Ok()))
+            .handler("b", |r| r.to(|| HttpResponse::Created()));
+        gateway.load().unwrap();
+        let mut srv = test::init_service(App::new().service(gateway.clone())).await;
+        actix_rt::spawn(gateway.clone().watch());
+
+        write_config(
+            &config,
+            r#"{"routes": [{"path": "/b", "handler": "b"}, {"path": "/c", "handler": "b"}]}"#,
+        );
+        delay_for(Duration::from_millis(100)).await;
+        assert_eq!(gateway.routes().paths(), vec!["/b", "/c"]);
+
+        let req = TestRequest::with_uri("/a").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let req = TestRequest::with_uri("/c").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        // invalid configuration keeps previous routes
+        write_config(&config, r#"{"routes": [{"path": "/d", "handler": "d"}]}"#);
+        delay_for(Duration::from_millis(100)).await;
+        assert_eq!(gateway.routes().paths(), vec!["/b", "/c"]);
+        assert!(gateway.load().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Resources of configured route targets
+use std::path::{Path, PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::client::Client;
+use actix_web::error::{ErrorBadGateway, ErrorNotFound};
+use actix_web::http::{header, HeaderMap, HeaderName, StatusCode};
+use actix_web::{guard, web, Error, HttpRequest, HttpResponse, Resource};
+use futures::future::ready;
+
+/// Name of pattern segment with path under prefix of static and proxy routes
+pub(crate) const TAIL: &str = "tail";
+
+/// Pattern of static and proxy routes, matches `prefix` and any path
+/// under it.
+pub(crate) fn prefix_pattern(prefix: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        format!("/{{{}:.*}}", TAIL)
+    } else {
+        format!("{}{{{}:(?:/.*)?}}", prefix, TAIL)
+    }
+}
+
+/// Path under route prefix, without leading slash
+fn tail(req: &HttpRequest) -> &str {
+    req.match_info().query(TAIL).trim_start_matches('/')
+}
+
+/// Redirect to `location`, `{name}` segments of location are replaced
+/// with matched segments of request path.
+pub(crate) fn redirect(
+    location: String,
+    status: StatusCode,
+) -> impl Fn(Resource) -> Resource + Send + Sync {
+    move |r| {
+        let location = location.clone();
+        r.to(move |req: HttpRequest| {
+            let mut location = location.clone();
+            for (name, value) in req.match_info().iter() {
+                location = location.replace(&format!("{{{}}}", name), value);
+            }
+            ready(
+                HttpResponse::build(status)
+                    .header(header::LOCATION, location)
+                    .finish(),
+            )
+        })
+    }
+}
+
+/// Serve files of `dir`, `index.html` is served for directories.
+///
+/// Files are resolved with symlinks, files outside of `dir` are not served.
+pub(crate) fn files(dir: PathBuf) -> impl Fn(Resource) -> Resource + Send + Sync {
+    let dir = dir.canonicalize().unwrap_or(dir);
+    move |r| {
+        let dir = dir.clone();
+        r.guard(guard::Any(guard::Get()).or(guard::Head()))
+            .to(move |req: HttpRequest| ready(serve_file(&dir, tail(&req))))
+    }
+}
+
+fn serve_file(dir: &Path, tail: &str) -> Result<NamedFile, Error> {
+    let mut path = file_path(dir, tail).ok_or_else(|| ErrorNotFound("Not found"))?;
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    let path = path
+        .canonicalize()
+        .ok()
+        .filter(|path| path.starts_with(dir))
+        .ok_or_else(|| ErrorNotFound("Not found"))?;
+    Ok(NamedFile::open(path)?)
+}
+
+/// Path of file in `dir`, hidden files and paths that leave `dir` are
+/// rejected.
+fn file_path(dir: &Path, tail: &str) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for segment in tail.split('/').filter(|s| !s.is_empty()) {
+        if segment.starts_with('.') || segment.contains('\\') {
+            return None;
+        }
+        path.push(segment);
+    }
+    Some(path)
+}
+
+/// Forward requests to `target` url, path under route prefix is appended
+/// to target path.
+pub(crate) fn proxy(target: String) -> impl Fn(Resource) -> Resource + Send + Sync {
+    move |r| {
+        let client = Client::default();
+        let target = target.clone();
+        r.to(move |req: HttpRequest, body: web::Payload| {
+            forward(client.clone(), target.clone(), req, body)
+        })
+    }
+}
+
+async fn forward(
+    client: Client,
+    target: String,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let mut url = format!("{}/{}", target.trim_end_matches('/'), tail(&req));
+    if !req.query_string().is_empty() {
+        url.push('?');
+        url.push_str(req.query_string());
+    }
+
+    let mut forwarded = client.request(req.method().clone(), url).no_decompress();
+    let listed = connection_headers(req.headers());
+    for (name, value) in req.headers().iter() {
+        if !is_hop_by_hop(name)
+            && !listed.contains(name)
+            && *name != header::HOST
+            && *name != header::CONTENT_LENGTH
+            && name.as_str() != "x-forwarded-for"
+        {
+            forwarded = forwarded.header(name.clone(), value.clone());
+        }
+    }
+    if let Some(addr) = req.peer_addr() {
+        let forwarded_for = match req.headers().get("x-forwarded-for") {
+            Some(prev) => {
+                format!("{}, {}", prev.to_str().unwrap_or_default(), addr.ip())
+            }
+            None => addr.ip().to_string(),
+        };
+        forwarded = forwarded.header("x-forwarded-for", forwarded_for);
+    }
+    forwarded = forwarded.header("x-forwarded-host", req.connection_info().host());
+
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse::<u64>().ok());
+    let res = match length {
+        Some(0) => forwarded.send(),
+        Some(len) => forwarded.send_sized_stream(len, body),
+        None if req.headers().contains_key(header::TRANSFER_ENCODING) => {
+            forwarded.send_stream(body)
+        }
+        None => forwarded.send(),
+    };
+    let res = res.await.map_err(ErrorBadGateway)?;
+
+    let mut resp = HttpResponse::build(res.status());
+    let listed = connection_headers(res.headers());
+    for (name, value) in res.headers().iter() {
+        if !is_hop_by_hop(name)
+            && !listed.contains(name)
+            && *name != header::CONTENT_LENGTH
+        {
+            resp.header(name.clone(), value.clone());
+        }
+    }
+    Ok(resp.streaming(res))
+}
+
+/// Headers that are meaningful for single connection only
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    *name == header::CONNECTION
+        || *name == header::PROXY_AUTHENTICATE
+        || *name == header::PROXY_AUTHORIZATION
+        || *name == header::TE
+        || *name == header::TRAILER
+        || *name == header::TRANSFER_ENCODING
+        || *name == header::UPGRADE
+        || name.as_str() == "keep-alive"
+}
+
+/// Headers listed in `Connection` header, RFC 7230 section 6.1
+fn connection_headers(headers: &HeaderMap) -> Vec<HeaderName> {
+    headers
+        .get_all(header::CONNECTION)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::dev::ResourceDef;
+    use actix_web::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_prefix_pattern() {
+        assert_eq!(prefix_pattern("/"), "/{tail:.*}");
+        assert_eq!(prefix_pattern("/static/"), "/static{tail:(?:/.*)?}");
+        assert_eq!(prefix_pattern("/api"), "/api{tail:(?:/.*)?}");
+
+        let rdef = ResourceDef::new(prefix_pattern("/api").as_str());
+        assert!(rdef.is_match("/api"));
+        assert!(rdef.is_match("/api/"));
+        assert!(rdef.is_match("/api/users"));
+        assert!(!rdef.is_match("/apiv2"));
+    }
+
+    #[test]
+    fn test_connection_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("keep-alive, X-Secret"),
+        );
+        headers.append(header::CONNECTION, HeaderValue::from_static("x-other"));
+        let listed = connection_headers(&headers);
+        assert_eq!(listed.len(), 3);
+        assert!(listed.contains(&HeaderName::from_static("keep-alive")));
+        assert!(listed.contains(&HeaderName::from_static("x-secret")));
+        assert!(listed.contains(&HeaderName::from_static("x-other")));
+    }
+
+    #[test]
+    fn test_file_path() {
+        let dir = Path::new("/srv");
+        assert_eq!(
+            file_path(dir, "a/b.txt"),
+            Some(PathBuf::from("/srv/a/b.txt"))
+        );
+        assert_eq!(file_path(dir, ""), Some(PathBuf::from("/srv")));
+        assert_eq!(file_path(dir, "a/../../etc/passwd"), None);
+        assert_eq!(file_path(dir, ".git/config"), None);
+        assert_eq!(file_path(dir, "a\\b"), None);
+    }
+}
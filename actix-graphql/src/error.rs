@@ -0,0 +1,42 @@
+//! Error and Result module
+use actix_web::error::QueryPayloadError;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use derive_more::{Display, From};
+
+/// A set of errors that can occur during parsing GraphQL requests
+#[derive(Debug, Display, From)]
+pub enum GraphQLError {
+    /// Content type of request is not supported
+    #[display(fmt = "Unsupported content type of GraphQL request")]
+    ContentType,
+    /// Can not parse query string of GET request
+    #[display(fmt = "{}", _0)]
+    Query(QueryPayloadError),
+    /// Can not parse json of variables or operations
+    #[display(fmt = "Json deserialize error: {}", _0)]
+    Json(serde_json::Error),
+    /// Query of GET request executes mutation
+    #[display(fmt = "Mutations are not allowed for GET requests")]
+    MutationOnGet,
+    /// Multipart request does not have header that forces CORS preflight
+    #[display(
+        fmt = "Multipart GraphQL request must have apollo-require-preflight or x-apollo-operation-name header"
+    )]
+    Preflight,
+    /// Multipart request does not follow upload spec
+    #[display(fmt = "Invalid GraphQL upload request: {}", _0)]
+    #[from(ignore)]
+    Upload(String),
+}
+
+/// Return `BadRequest` for `GraphQLError`
+impl ResponseError for GraphQLError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            GraphQLError::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            GraphQLError::MutationOnGet => StatusCode::METHOD_NOT_ALLOWED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
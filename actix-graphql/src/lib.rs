@@ -0,0 +1,467 @@
+#![allow(clippy::borrow_interior_mutable_const)]
+//! GraphQL endpoint integration for actix web.
+//!
+//! `GraphQL` service parses GraphQL requests, executes them with user
+//! supplied executor on blocking thread pool and responds with json result
+//! of execution. Executor is a function that runs request against schema,
+//! i.e. of `juniper` or `async-graphql` crate, so schema library is not
+//! tied to this crate. Optional GraphiQL or Playground page is served for
+//! exploring schema, page assets are loaded from pinned CDN versions or from
+//! urls set with `GraphQL::ui_assets()`.
+//!
+//! ```rust
+//! use actix_graphql::{GraphQL, GraphQLRequest};
+//! use actix_web::App;
+//! use serde_json::{json, Value};
+//!
+//! fn execute(req: GraphQLRequest) -> Value {
+//!     // execute `req.query()` with `req.get_variables()` against schema
+//!     json!({ "data": { "hello": "world" } })
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .service(GraphQL::new("/graphql", execute).graphiql("/graphiql"));
+//! }
+//! ```
+mod error;
+mod request;
+
+use std::fmt;
+use std::sync::Arc;
+
+use actix_web::dev::{AppService, HttpServiceFactory};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::{guard, web, Error, HttpResponse, Resource, Route};
+use futures::future::ready;
+use serde_json::Value;
+
+pub use self::error::GraphQLError;
+pub use self::request::{GraphQLConfig, GraphQLRequest, Upload};
+
+type Executor = Arc<dyn Fn(GraphQLRequest) -> Value + Send + Sync>;
+
+/// Execute GraphQL request on blocking thread pool and respond with json
+/// result of execution.
+///
+/// It could be used in handlers that need to prepare executor, i.e. with
+/// context of request.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use actix_graphql::{execute, GraphQLRequest};
+/// use actix_web::{web, Error, HttpRequest, HttpResponse};
+/// use serde_json::{json, Value};
+///
+/// struct Schema;
+///
+/// impl Schema {
+///     fn run(&self, req: &GraphQLRequest, user: &str) -> Value {
+///         json!({ "data": { "user": user } })
+///     }
+/// }
+///
+/// async fn graphql(
+///     req: HttpRequest,
+///     schema: web::Data<Arc<Schema>>,
+///     query: GraphQLRequest,
+/// ) -> Result<HttpResponse, Error> {
+///     let schema = schema.get_ref().clone();
+///     let user = req.match_info().query("user").to_owned();
+///     execute(query, move |query| schema.run(&query, &user)).await
+/// }
+/// ```
+pub async fn execute<F>(
+    request: GraphQLRequest,
+    executor: F,
+) -> Result<HttpResponse, Error>
+where
+    F: FnOnce(GraphQLRequest) -> Value + Send + 'static,
+{
+    let result = web::block(move || Ok::<_, ()>(executor(request)))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Page for exploring schema
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ui {
+    GraphiQL,
+    Playground,
+}
+
+const GRAPHIQL_CSS: &[&str] = &["https://unpkg.com/graphiql@0.17.5/graphiql.min.css"];
+const GRAPHIQL_JS: &[&str] = &[
+    "https://unpkg.com/react@16.12.0/umd/react.production.min.js",
+    "https://unpkg.com/react-dom@16.12.0/umd/react-dom.production.min.js",
+    "https://unpkg.com/graphiql@0.17.5/graphiql.min.js",
+];
+const PLAYGROUND_CSS: &[&str] = &[
+    "https://cdn.jsdelivr.net/npm/graphql-playground-react@1.7.20/build/static/css/index.css",
+];
+const PLAYGROUND_JS: &[&str] = &[
+    "https://cdn.jsdelivr.net/npm/graphql-playground-react@1.7.20/build/static/js/middleware.js",
+];
+
+impl Ui {
+    /// Stylesheets and scripts of page
+    fn assets(self) -> (Vec<UiAsset>, Vec<UiAsset>) {
+        let (css, js) = match self {
+            Ui::GraphiQL => (GRAPHIQL_CSS, GRAPHIQL_JS),
+            Ui::Playground => (PLAYGROUND_CSS, PLAYGROUND_JS),
+        };
+        (
+            css.iter().map(|url| UiAsset::new(*url)).collect(),
+            js.iter().map(|url| UiAsset::new(*url)).collect(),
+        )
+    }
+}
+
+/// Stylesheet or script of GraphiQL or Playground page
+#[derive(Clone, Debug)]
+pub struct UiAsset {
+    url: String,
+    integrity: Option<String>,
+}
+
+impl UiAsset {
+    /// Create asset that is loaded from `url`
+    pub fn new<T: Into<String>>(url: T) -> Self {
+        UiAsset {
+            url: url.into(),
+            integrity: None,
+        }
+    }
+
+    /// Set subresource integrity hash of asset, e.g. `sha384-...`, browser
+    /// refuses asset that does not match.
+    pub fn integrity<T: Into<String>>(mut self, hash: T) -> Self {
+        self.integrity = Some(hash.into());
+        self
+    }
+
+    /// Attributes of `link` or `script` element, `attr` is name of url
+    /// attribute
+    fn attrs(&self, attr: &str) -> String {
+        match self.integrity {
+            Some(ref hash) => format!(
+                "{}=\"{}\" integrity=\"{}\" crossorigin=\"anonymous\"",
+                attr,
+                escape(&self.url),
+                escape(hash)
+            ),
+            None => format!("{}=\"{}\"", attr, escape(&self.url)),
+        }
+    }
+}
+
+/// Service that serves GraphQL endpoint.
+///
+/// Endpoint accepts `GET` and `POST` requests that are parsed as
+/// [`GraphQLRequest`](struct.GraphQLRequest.html). `GraphQL` service must
+/// be registered with `App::service()` method.
+pub struct GraphQL {
+    path: String,
+    executor: Executor,
+    ui: Option<(String, Ui)>,
+    assets: Option<(Vec<UiAsset>, Vec<UiAsset>)>,
+    config: Option<GraphQLConfig>,
+}
+
+impl GraphQL {
+    /// Create new `GraphQL` service, endpoint is mounted at `path`.
+    ///
+    /// Executor runs on blocking thread pool, it should return response
+    /// object with `data` and `errors` fields.
+    pub fn new<F>(path: &str, executor: F) -> Self
+    where
+        F: Fn(GraphQLRequest) -> Value + Send + Sync + 'static,
+    {
+        GraphQL {
+            path: path.to_owned(),
+            executor: Arc::new(executor),
+            ui: None,
+            assets: None,
+            config: None,
+        }
+    }
+
+    /// Serve GraphiQL page at `path`.
+    pub fn graphiql(mut self, path: &str) -> Self {
+        self.ui = Some((path.to_owned(), Ui::GraphiQL));
+        self
+    }
+
+    /// Serve GraphQL Playground page at `path`.
+    pub fn playground(mut self, path: &str) -> Self {
+        self.ui = Some((path.to_owned(), Ui::Playground));
+        self
+    }
+
+    /// Set stylesheets and scripts of GraphiQL or Playground page, i.e.
+    /// self hosted assets or assets with integrity hashes.
+    ///
+    /// By default pinned versions are loaded from CDN.
+    ///
+    /// ```rust
+    /// use actix_graphql::{GraphQL, GraphQLRequest, UiAsset};
+    /// use serde_json::Value;
+    ///
+    /// fn execute(req: GraphQLRequest) -> Value {
+    ///     Value::Null
+    /// }
+    ///
+    /// let graphql = GraphQL::new("/graphql", execute)
+    ///     .playground("/playground")
+    ///     .ui_assets(
+    ///         vec![UiAsset::new("/static/playground.css")],
+    ///         vec![UiAsset::new("/static/playground.js").integrity("sha384-...")],
+    ///     );
+    /// ```
+    pub fn ui_assets(
+        mut self,
+        stylesheets: Vec<UiAsset>,
+        scripts: Vec<UiAsset>,
+    ) -> Self {
+        self.assets = Some((stylesheets, scripts));
+        self
+    }
+
+    /// Set configuration of endpoint requests, it overrides configuration
+    /// registered with `app_data()` of application.
+    pub fn config(mut self, config: GraphQLConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+}
+
+impl fmt::Debug for GraphQL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphQL")
+            .field("path", &self.path)
+            .field("ui", &self.ui)
+            .finish()
+    }
+}
+
+impl HttpServiceFactory for GraphQL {
+    fn register(self, config: &mut AppService) {
+        if let Some((ref path, ui)) = self.ui {
+            let (css, js) = self.assets.unwrap_or_else(|| ui.assets());
+            let page = ui_page(ui, &self.path, &css, &js);
+            Resource::new(path.as_str())
+                .route(Route::new().guard(guard::Get()).to(move || {
+                    ready(
+                        HttpResponse::Ok()
+                            .content_type("text/html; charset=utf-8")
+                            .body(page.clone()),
+                    )
+                }))
+                .register(config);
+        }
+
+        let executor = self.executor;
+        let handler = move |req: GraphQLRequest| {
+            let executor = executor.clone();
+            execute(req, move |req| executor(req))
+        };
+        let mut resource = Resource::new(self.path.as_str())
+            .route(web::get().to(handler.clone()))
+            .route(web::post().to(handler));
+        if let Some(cfg) = self.config {
+            resource = resource.app_data(cfg);
+        }
+        resource.register(config);
+    }
+}
+
+/// Html page of GraphiQL or Playground for endpoint
+fn ui_page(
+    ui: Ui,
+    endpoint: &str,
+    stylesheets: &[UiAsset],
+    scripts: &[UiAsset],
+) -> String {
+    let url = serde_json::to_string(endpoint)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    let mut assets = String::new();
+    for asset in stylesheets {
+        assets.push_str(&format!(
+            "<link rel=\"stylesheet\" {}>\n",
+            asset.attrs("href")
+        ));
+    }
+    for asset in scripts {
+        assets.push_str(&format!("<script {}></script>\n", asset.attrs("src")));
+    }
+    match ui {
+        Ui::GraphiQL => format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>GraphiQL</title>
+{}</head>
+<body style="margin: 0;">
+<div id="graphiql" style="height: 100vh;"></div>
+<script>
+function fetcher(params) {{
+    return fetch({}, {{
+        method: "post",
+        headers: {{ "Content-Type": "application/json" }},
+        body: JSON.stringify(params),
+        credentials: "same-origin"
+    }}).then(function(res) {{ return res.json(); }});
+}}
+ReactDOM.render(
+    React.createElement(GraphiQL, {{ fetcher: fetcher }}),
+    document.getElementById("graphiql")
+);
+</script>
+</body>
+</html>
+"#,
+            assets, url
+        ),
+        Ui::Playground => format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>GraphQL Playground</title>
+{}</head>
+<body>
+<div id="root"></div>
+<script>
+window.addEventListener("load", function() {{
+    GraphQLPlayground.init(document.getElementById("root"), {{ endpoint: {} }});
+}});
+</script>
+</body>
+</html>
+"#,
+            assets, url
+        ),
+    }
+}
+
+/// Escape text for html attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+    use serde_json::json;
+
+    use super::*;
+
+    fn executor(req: GraphQLRequest) -> Value {
+        json!({
+            "data": {
+                "query": req.query(),
+                "variables": req.get_variables(),
+                "get": req.is_get(),
+            }
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_graphql() {
+        let mut srv = test::init_service(
+            App::new().service(GraphQL::new("/graphql", executor).graphiql("/graphiql")),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/graphql")
+            .set_json(&json!({ "query": "{ hero }", "variables": { "id": 1 } }))
+            .to_request();
+        let res: Value = test::read_response_json(&mut srv, req).await;
+        assert_eq!(res["data"]["query"], "{ hero }");
+        assert_eq!(res["data"]["variables"], json!({ "id": 1 }));
+        assert_eq!(res["data"]["get"], false);
+
+        let req = TestRequest::with_uri("/graphql?query=%7Bhero%7D").to_request();
+        let res: Value = test::read_response_json(&mut srv, req).await;
+        assert_eq!(res["data"]["query"], "{hero}");
+        assert_eq!(res["data"]["get"], true);
+
+        let req = TestRequest::post()
+            .uri("/graphql")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("{ hero }")
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::with_uri("/graphiql").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("GraphiQL"));
+        assert!(body.contains("fetch(\"/graphql\""));
+        assert!(body.contains(
+            "<script src=\"https://unpkg.com/graphiql@0.17.5/graphiql.min.js\"></script>"
+        ));
+
+        // mutations are not executed for GET requests
+        let req =
+            TestRequest::with_uri("/graphql?query=mutation%7Bdelete%7D").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[actix_rt::test]
+    async fn test_config() {
+        let form = "--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+                    Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+                    {\"query\": \"{ hero }\"}\r\n\
+                    --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n";
+        let mut srv = test::init_service(
+            App::new().service(
+                GraphQL::new("/graphql", executor)
+                    .config(GraphQLConfig::default().require_preflight(false)),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/graphql")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+            )
+            .set_payload(form)
+            .to_request();
+        let res: Value = test::read_response_json(&mut srv, req).await;
+        assert_eq!(res["data"]["query"], "{ hero }");
+    }
+
+    #[test]
+    fn test_ui_page() {
+        let page = ui_page(Ui::Playground, "/api</script>", &[], &[]);
+        assert!(page.contains(r#"endpoint: "/api\u003c/script>""#));
+
+        let page = ui_page(
+            Ui::Playground,
+            "/graphql",
+            &[UiAsset::new("/static/ui.css")],
+            &[UiAsset::new("/static/ui.js").integrity("sha384-js")],
+        );
+        assert!(page.contains("<link rel=\"stylesheet\" href=\"/static/ui.css\">"));
+        assert!(page.contains(
+            "<script src=\"/static/ui.js\" integrity=\"sha384-js\" \
+             crossorigin=\"anonymous\"></script>"
+        ));
+    }
+}
@@ -0,0 +1,565 @@
+//! GraphQL request extractor
+use std::collections::HashMap;
+
+use actix_multipart::{MultipartForm, UploadedFile};
+use actix_web::dev::Payload;
+use actix_web::http::Method;
+use actix_web::openapi::Operation;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{err, ready, FutureExt, LocalBoxFuture};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::GraphQLError;
+
+/// GraphQL request, extracted from GET or POST request.
+///
+/// GET request carries `query`, `operationName` and json encoded
+/// `variables` in query string. POST request body is one of:
+///
+/// * `application/json` object with `query`, `operationName` and
+///   `variables` fields
+/// * `application/graphql` query text
+/// * `multipart/form-data` form that follows
+///   [GraphQL multipart request spec](https://github.com/jaydenseric/graphql-multipart-request-spec),
+///   `operations` field is json object of request, `map` field maps file
+///   fields to paths of variables. Files are available with
+///   `GraphQLRequest::upload()`, size of fields is limited with
+///   `actix_multipart::MultipartFormConfig`. Browsers send multipart forms
+///   across origins without CORS preflight, so multipart request must have
+///   `apollo-require-preflight` or `x-apollo-operation-name` header, see
+///   [`GraphQLConfig`](struct.GraphQLConfig.html).
+///
+/// Mutations are rejected for GET requests with `405 Method Not Allowed`.
+/// Batched operations are not supported.
+#[derive(Debug)]
+pub struct GraphQLRequest {
+    query: String,
+    operation_name: Option<String>,
+    variables: Value,
+    method: Method,
+    uploads: Vec<Upload>,
+}
+
+#[derive(Deserialize)]
+struct Params {
+    query: String,
+    #[serde(rename = "operationName", default)]
+    operation_name: Option<String>,
+    #[serde(default)]
+    variables: Value,
+}
+
+#[derive(Deserialize)]
+struct GetParams {
+    query: String,
+    #[serde(rename = "operationName", default)]
+    operation_name: Option<String>,
+    #[serde(default)]
+    variables: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UploadFields {
+    operations: String,
+    map: Option<String>,
+}
+
+impl GraphQLRequest {
+    /// Create new request with query text.
+    pub fn new<T: Into<String>>(query: T) -> Self {
+        GraphQLRequest {
+            query: query.into(),
+            operation_name: None,
+            variables: Value::Null,
+            method: Method::POST,
+            uploads: Vec::new(),
+        }
+    }
+
+    /// Set name of operation to execute.
+    pub fn operation_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.operation_name = Some(name.into());
+        self
+    }
+
+    /// Set variables of request.
+    pub fn variables(mut self, variables: Value) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Query text
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Name of operation to execute, if any
+    pub fn get_operation_name(&self) -> Option<&str> {
+        self.operation_name.as_ref().map(|s| s.as_str())
+    }
+
+    /// Variables of request, `Value::Null` if request has no variables.
+    ///
+    /// Variables that are uploaded files are `null`.
+    pub fn get_variables(&self) -> &Value {
+        &self.variables
+    }
+
+    /// Method of http request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns true if request is sent with `GET` method.
+    pub fn is_get(&self) -> bool {
+        self.method == Method::GET
+    }
+
+    /// Files uploaded with multipart request
+    pub fn uploads(&self) -> &[Upload] {
+        &self.uploads
+    }
+
+    /// File uploaded for path of operation field, i.e. `variables.file`
+    /// or `variables.files.0`
+    pub fn upload(&self, path: &str) -> Option<&UploadedFile> {
+        self.uploads
+            .iter()
+            .find(|upload| upload.paths.iter().any(|p| p == path))
+            .map(|upload| &upload.file)
+    }
+
+    /// Take uploaded files from request
+    pub fn take_uploads(&mut self) -> Vec<Upload> {
+        std::mem::replace(&mut self.uploads, Vec::new())
+    }
+
+    fn from_params(params: Params, method: Method) -> Self {
+        GraphQLRequest {
+            query: params.query,
+            operation_name: params.operation_name,
+            variables: params.variables,
+            method,
+            uploads: Vec::new(),
+        }
+    }
+}
+
+/// Headers that browsers can not send across origins without CORS preflight
+const PREFLIGHT_HEADERS: &[&str] =
+    &["apollo-require-preflight", "x-apollo-operation-name"];
+
+/// GraphQL request extractor configuration
+///
+/// ```rust
+/// use actix_graphql::GraphQLConfig;
+/// use actix_web::App;
+///
+/// fn main() {
+///     // endpoint is protected from cross-site requests by other means
+///     let app = App::new().app_data(GraphQLConfig::default().require_preflight(false));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GraphQLConfig {
+    require_preflight: bool,
+}
+
+impl GraphQLConfig {
+    /// Require `apollo-require-preflight` or `x-apollo-operation-name`
+    /// header for multipart requests, by default it is required.
+    pub fn require_preflight(mut self, required: bool) -> Self {
+        self.require_preflight = required;
+        self
+    }
+}
+
+impl Default for GraphQLConfig {
+    fn default() -> Self {
+        GraphQLConfig {
+            require_preflight: true,
+        }
+    }
+}
+
+impl FromRequest for GraphQLRequest {
+    type Config = GraphQLConfig;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/json");
+        op.add_request_content("application/graphql");
+        op.add_request_content("multipart/form-data");
+    }
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if *req.method() == Method::GET {
+            let res = web::Query::<GetParams>::from_query(req.query_string())
+                .map_err(GraphQLError::from)
+                .and_then(|params| -> Result<Self, GraphQLError> {
+                    let params = params.into_inner();
+                    let variables = match params.variables {
+                        Some(ref vars) if !vars.is_empty() => {
+                            serde_json::from_str(vars)?
+                        }
+                        _ => Value::Null,
+                    };
+                    let name = params.operation_name.as_ref().map(|s| s.as_str());
+                    if is_mutation(&params.query, name) {
+                        return Err(GraphQLError::MutationOnGet);
+                    }
+                    Ok(GraphQLRequest {
+                        query: params.query,
+                        operation_name: params.operation_name,
+                        variables,
+                        method: Method::GET,
+                        uploads: Vec::new(),
+                    })
+                })
+                .map_err(Error::from);
+            return ready(res).boxed_local();
+        }
+
+        let method = req.method().clone();
+        match req.content_type() {
+            "application/json" => web::Json::<Params>::from_request(req, payload)
+                .map(move |res| -> Result<Self, Error> {
+                    Ok(GraphQLRequest::from_params(res?.into_inner(), method))
+                })
+                .boxed_local(),
+            "application/graphql" => String::from_request(req, payload)
+                .map(move |res| -> Result<Self, Error> {
+                    let mut graphql = GraphQLRequest::new(res?);
+                    graphql.method = method;
+                    Ok(graphql)
+                })
+                .boxed_local(),
+            "multipart/form-data" => {
+                let require_preflight = req
+                    .app_data::<Self::Config>()
+                    .map(|c| c.require_preflight)
+                    .unwrap_or(true);
+                let preflighted = PREFLIGHT_HEADERS
+                    .iter()
+                    .any(|name| req.headers().contains_key(*name));
+                if require_preflight && !preflighted {
+                    return err(GraphQLError::Preflight.into()).boxed_local();
+                }
+                MultipartForm::<UploadFields>::from_request(req, payload)
+                    .map(move |res| -> Result<Self, Error> {
+                        Ok(from_multipart(res?, method)?)
+                    })
+                    .boxed_local()
+            }
+            _ => err(GraphQLError::ContentType.into()).boxed_local(),
+        }
+    }
+}
+
+/// Build request from multipart upload form
+fn from_multipart(
+    form: MultipartForm<UploadFields>,
+    method: Method,
+) -> Result<GraphQLRequest, GraphQLError> {
+    let (fields, mut files) = form.into_parts();
+    let operations: Value = serde_json::from_str(&fields.operations)?;
+    if operations.is_array() {
+        return Err(GraphQLError::Upload(
+            "batched operations are not supported".to_owned(),
+        ));
+    }
+    let params: Params = serde_json::from_value(operations)?;
+    let map: HashMap<String, Vec<String>> = match fields.map {
+        Some(ref map) => serde_json::from_str(map)?,
+        None => HashMap::new(),
+    };
+
+    let mut graphql = GraphQLRequest::from_params(params, method);
+    for (name, paths) in map {
+        let idx = files.iter().position(|(n, _)| *n == name).ok_or_else(|| {
+            GraphQLError::Upload(format!("file field {} is missing", name))
+        })?;
+        let (_, file) = files.swap_remove(idx);
+        graphql.uploads.push(Upload { name, paths, file });
+    }
+    graphql.uploads.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(graphql)
+}
+
+/// Returns true if operation of query that is executed for
+/// `operation_name` is mutation.
+///
+/// Only top level definitions are scanned, if operation is ambiguous any
+/// mutation of query is reported.
+fn is_mutation(query: &str, operation_name: Option<&str>) -> bool {
+    // kind and name of operation definitions
+    let mut ops: Vec<(&str, Option<&str>)> = Vec::new();
+    let mut kind = None;
+    let mut name = None;
+    let mut expect_name = false;
+    let mut depth = 0usize;
+
+    let bytes = query.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'#' => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b'"' => {
+                let block = bytes[pos..].starts_with(b"\"\"\"");
+                pos += if block { 3 } else { 1 };
+                while pos < bytes.len() {
+                    if bytes[pos] == b'\\' {
+                        pos += 2;
+                    } else if block && bytes[pos..].starts_with(b"\"\"\"") {
+                        pos += 3;
+                        break;
+                    } else if !block && bytes[pos] == b'"' {
+                        pos += 1;
+                        break;
+                    } else {
+                        pos += 1;
+                    }
+                }
+                continue;
+            }
+            b'{' => {
+                if depth == 0 {
+                    ops.push((kind.take().unwrap_or("query"), name.take()));
+                    expect_name = false;
+                }
+                depth += 1;
+            }
+            b'(' | b'[' => {
+                if depth == 0 {
+                    expect_name = false;
+                }
+                depth += 1;
+            }
+            b'}' | b')' | b']' => depth = depth.saturating_sub(1),
+            b'@' if depth == 0 => expect_name = false,
+            c if c == b'_' || c.is_ascii_alphanumeric() => {
+                let start = pos;
+                while pos < bytes.len()
+                    && (bytes[pos] == b'_' || bytes[pos].is_ascii_alphanumeric())
+                {
+                    pos += 1;
+                }
+                if depth == 0 {
+                    let token = &query[start..pos];
+                    if kind.is_none() {
+                        kind = Some(token);
+                        expect_name = true;
+                    } else if expect_name {
+                        name = Some(token);
+                        expect_name = false;
+                    }
+                }
+                continue;
+            }
+            _ => (),
+        }
+        pos += 1;
+    }
+
+    match operation_name {
+        Some(operation_name) => ops
+            .iter()
+            .filter(|op| op.0 != "fragment")
+            .find(|op| op.1 == Some(operation_name))
+            .map_or(false, |op| op.0 == "mutation"),
+        None => ops.iter().any(|op| op.0 == "mutation"),
+    }
+}
+
+/// File uploaded with multipart GraphQL request
+#[derive(Debug)]
+pub struct Upload {
+    name: String,
+    paths: Vec<String>,
+    file: UploadedFile,
+}
+
+impl Upload {
+    /// Name of multipart field with file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Paths of operation fields that are set to the file
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Uploaded file
+    pub fn file(&self) -> &UploadedFile {
+        &self.file
+    }
+
+    /// Convert into uploaded file
+    pub fn into_file(self) -> UploadedFile {
+        self.file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+    use bytes::Bytes;
+    use serde_json::json;
+
+    use super::*;
+
+    async fn extract(req: TestRequest) -> Result<GraphQLRequest, Error> {
+        let (req, mut payload) = req.to_http_parts();
+        GraphQLRequest::from_request(&req, &mut payload).await
+    }
+
+    #[actix_rt::test]
+    async fn test_get() {
+        let req = TestRequest::with_uri(
+            "/graphql?query=query%20Hero(%24id%3AID)%7Bname%7D\
+             &operationName=Hero&variables=%7B%22id%22%3A%221%22%7D",
+        );
+        let graphql = extract(req).await.unwrap();
+        assert_eq!(graphql.query(), "query Hero($id:ID){name}");
+        assert_eq!(graphql.get_operation_name(), Some("Hero"));
+        assert_eq!(graphql.get_variables(), &json!({"id": "1"}));
+        assert!(graphql.is_get());
+
+        let req = TestRequest::with_uri("/graphql?query=%7Bname%7D&variables=null");
+        let graphql = extract(req).await.unwrap();
+        assert_eq!(graphql.get_variables(), &Value::Null);
+
+        let req = TestRequest::with_uri("/graphql?variables=%7B%7D");
+        assert!(extract(req).await.is_err());
+
+        let req = TestRequest::with_uri("/graphql?query=mutation%7Bdelete%7D");
+        let err = extract(req).await.err().unwrap();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+
+    #[test]
+    fn test_is_mutation() {
+        assert!(!is_mutation("{ hero }", None));
+        assert!(!is_mutation("query Hero($id: ID) { hero(id: $id) }", None));
+        assert!(is_mutation("mutation { delete }", None));
+        assert!(is_mutation(
+            "  # comment\n mutation Delete @log { delete }",
+            None
+        ));
+        assert!(!is_mutation(
+            "query A { a(s: \"mutation\") } mutation B { b }",
+            Some("A")
+        ));
+        assert!(is_mutation("query A { a } mutation B { b }", Some("B")));
+        assert!(is_mutation("query A { a } mutation B { b }", None));
+        assert!(!is_mutation(
+            "fragment F on mutation { a } query Q { ...F }",
+            Some("Q")
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_post() {
+        let req = TestRequest::post()
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(r#"{"query": "{name}", "variables": {"id": 1}}"#);
+        let graphql = extract(req).await.unwrap();
+        assert_eq!(graphql.query(), "{name}");
+        assert_eq!(graphql.get_operation_name(), None);
+        assert_eq!(graphql.get_variables(), &json!({"id": 1}));
+        assert!(!graphql.is_get());
+
+        let req = TestRequest::post()
+            .header(header::CONTENT_TYPE, "application/graphql")
+            .set_payload("{name}");
+        let graphql = extract(req).await.unwrap();
+        assert_eq!(graphql.query(), "{name}");
+
+        let req = TestRequest::post()
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("{name}");
+        let err = extract(req).await.err().unwrap();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_upload() {
+        let req = TestRequest::post()
+            .header("apollo-require-preflight", "true")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+            )
+            .set_payload(Bytes::from_static(
+                b"--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+                  Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+                  {\"query\": \"mutation($file: Upload!) { upload(file: $file) }\", \
+                  \"variables\": {\"file\": null}}\r\n\
+                  --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+                  Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+                  {\"0\": [\"variables.file\"]}\r\n\
+                  --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+                  Content-Disposition: form-data; name=\"0\"; filename=\"a.txt\"\r\n\
+                  Content-Type: text/plain\r\n\r\n\
+                  content\r\n\
+                  --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n",
+            ));
+        let mut graphql = extract(req).await.unwrap();
+        assert_eq!(
+            graphql.query(),
+            "mutation($file: Upload!) { upload(file: $file) }"
+        );
+        assert_eq!(graphql.get_variables(), &json!({"file": null}));
+        let file = graphql.upload("variables.file").unwrap();
+        assert_eq!(file.file_name(), Some("a.txt"));
+        assert_eq!(file.bytes().unwrap(), &Bytes::from_static(b"content"));
+
+        let uploads = graphql.take_uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].name(), "0");
+        assert!(graphql.uploads().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_upload_preflight() {
+        let form = "--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+                    Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+                    {\"query\": \"{ name }\"}\r\n\
+                    --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n";
+        let request = || {
+            TestRequest::post()
+                .header(
+                    header::CONTENT_TYPE,
+                    "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+                )
+                .set_payload(form)
+        };
+
+        let err = extract(request()).await.err().unwrap();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+
+        let req = request().header("x-apollo-operation-name", "Name");
+        assert_eq!(extract(req).await.unwrap().query(), "{ name }");
+
+        let req = request().app_data(GraphQLConfig::default().require_preflight(false));
+        assert_eq!(extract(req).await.unwrap().query(), "{ name }");
+    }
+}
@@ -3,6 +3,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::{fmt, mem};
 
+use actix_codec::AsyncRead;
 use bytes::{Bytes, BytesMut};
 use futures_core::Stream;
 use pin_project::{pin_project, project};
@@ -143,6 +144,15 @@ impl Body {
     pub fn from_message<B: MessageBody + 'static>(body: B) -> Body {
         Body::Message(Box::new(body))
     }
+
+    /// Create streaming body from `AsyncRead`, that is read by chunks of
+    /// `chunk_size` bytes.
+    pub fn from_reader<R: AsyncRead + Unpin + 'static>(
+        reader: R,
+        chunk_size: usize,
+    ) -> Body {
+        Body::from_message(ReaderBody::new(reader, chunk_size))
+    }
 }
 
 impl MessageBody for Body {
@@ -432,6 +442,147 @@ where
     }
 }
 
+/// Transformation of body chunks, which is applied with `TransformBody`.
+pub trait BodyTransform {
+    /// Transform chunk of body. Returned chunk could be empty, i.e. if
+    /// data is kept until next chunk.
+    fn chunk(&mut self, chunk: Bytes) -> Result<Bytes, Error>;
+
+    /// Return remaining data after last chunk of body.
+    fn finish(&mut self) -> Result<Bytes, Error> {
+        Ok(Bytes::new())
+    }
+}
+
+impl<F> BodyTransform for F
+where
+    F: FnMut(Bytes) -> Result<Bytes, Error>,
+{
+    fn chunk(&mut self, chunk: Bytes) -> Result<Bytes, Error> {
+        (self)(chunk)
+    }
+}
+
+/// Type represent body that is transformed chunk by chunk while it is streamed.
+/// Size of transformed body is not known, so appropriate transfer encoding is used.
+pub struct TransformBody<B, T> {
+    body: B,
+    transform: T,
+    done: bool,
+}
+
+impl<B, T> TransformBody<B, T>
+where
+    B: MessageBody,
+    T: BodyTransform,
+{
+    pub fn new(body: B, transform: T) -> Self {
+        TransformBody {
+            body,
+            transform,
+            done: false,
+        }
+    }
+}
+
+impl<B, T> MessageBody for TransformBody<B, T>
+where
+    B: MessageBody,
+    T: BodyTransform,
+{
+    fn size(&self) -> BodySize {
+        match self.body.size() {
+            BodySize::None => BodySize::None,
+            _ => BodySize::Stream,
+        }
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let res = match self.body.poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.transform.chunk(chunk),
+                Poll::Ready(Some(Err(e))) => Err(e),
+                Poll::Ready(None) => {
+                    self.done = true;
+                    self.transform.finish()
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match res {
+                // empty chunk terminates chunked transfer encoding
+                Ok(chunk) if chunk.is_empty() => continue,
+                Ok(chunk) => return Poll::Ready(Some(Ok(chunk))),
+                Err(e) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+/// Type represent streaming body that is read from `AsyncRead`.
+/// Next chunk is read only after previous chunk is written, so reader is not
+/// read faster than client receives body.
+pub struct ReaderBody<R> {
+    reader: R,
+    buf: BytesMut,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<R> ReaderBody<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Panics if `chunk_size` is 0.
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunk size must be positive");
+        ReaderBody {
+            reader,
+            buf: BytesMut::new(),
+            chunk_size,
+            done: false,
+        }
+    }
+}
+
+impl<R> MessageBody for ReaderBody<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.buf.len() < self.chunk_size {
+            self.buf.resize(self.chunk_size, 0);
+        }
+
+        match Pin::new(&mut self.reader).poll_read(cx, &mut self.buf) {
+            Poll::Ready(Ok(0)) => {
+                self.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(n)) => Poll::Ready(Some(Ok(self.buf.split_to(n).freeze()))),
+            Poll::Ready(Err(e)) => {
+                self.done = true;
+                Poll::Ready(Some(Err(e.into())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -589,4 +740,50 @@ mod tests {
             BodySize::Sized(25)
         );
     }
+
+    #[actix_rt::test]
+    async fn test_transform_body() {
+        struct Inject(&'static str);
+
+        impl BodyTransform for Inject {
+            fn chunk(&mut self, chunk: Bytes) -> Result<Bytes, Error> {
+                Ok(chunk)
+            }
+
+            fn finish(&mut self) -> Result<Bytes, Error> {
+                Ok(Bytes::from_static(self.0.as_bytes()))
+            }
+        }
+
+        let body = BodyStream::new(futures_util::stream::iter(vec![
+            Ok::<_, Error>(Bytes::from_static(b"a")),
+            Ok(Bytes::new()),
+            Ok(Bytes::from_static(b"b")),
+        ]));
+        let upper =
+            |chunk: Bytes| Ok::<_, Error>(Bytes::from(chunk.to_ascii_uppercase()));
+        let mut body = TransformBody::new(TransformBody::new(body, upper), Inject("c"));
+        assert_eq!(body.size(), BodySize::Stream);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next(cx)).await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks, vec!["A", "B", "c"]);
+
+        let body = TransformBody::new(Body::None, |chunk: Bytes| Ok::<_, Error>(chunk));
+        assert_eq!(body.size(), BodySize::None);
+    }
+
+    #[actix_rt::test]
+    async fn test_reader_body() {
+        let mut body = Body::from_reader(&b"hello world"[..], 4);
+        assert_eq!(body.size(), BodySize::Stream);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next(cx)).await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks, vec!["hell", "o wo", "rld"]);
+    }
 }
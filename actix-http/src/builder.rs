@@ -1,16 +1,24 @@
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::Duration;
 use std::{fmt, net};
 
 use actix_codec::Framed;
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
 
 use crate::body::MessageBody;
-use crate::config::{KeepAlive, ServiceConfig};
+use crate::config::{
+    H2Options, KeepAlive, ServiceConfig, TcpOptions, MAX_FRAME_SIZE, MAX_WINDOW_SIZE,
+    MIN_FRAME_SIZE,
+};
 use crate::error::Error;
-use crate::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
+use crate::extensions::{ConnectionExtensions, Extensions};
+use crate::h1::{self, Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::h2::H2Service;
-use crate::helpers::{Data, DataFactory};
+use crate::helpers::{Data, DataChain, DataFactory};
+use crate::http::StatusCode;
+use crate::idle::IdleLimit;
+use crate::message::{self, PoolConfig};
 use crate::request::Request;
 use crate::response::Response;
 use crate::service::HttpService;
@@ -23,8 +31,28 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler<T>> {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_disconnect: u64,
+    client_read_timeout: u64,
     secure: bool,
     local_addr: Option<net::SocketAddr>,
+    proxy_protocol: bool,
+    h2c: bool,
+    max_header_size: usize,
+    max_headers: usize,
+    strict_parsing: bool,
+    max_pipelined_requests: usize,
+    early_hints: bool,
+    max_requests: usize,
+    max_lifetime: u64,
+    tcp: TcpOptions,
+    h2: H2Options,
+    date_interval: u64,
+    date_header: bool,
+    buffer_capacity: usize,
+    max_buffer_capacity: usize,
+    error_response: Option<Rc<dyn Fn(StatusCode) -> Response>>,
+    idle_limit: Option<IdleLimit>,
+    request_pool: Option<PoolConfig>,
+    response_pool: Option<PoolConfig>,
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
@@ -44,8 +72,28 @@ where
             keep_alive: KeepAlive::Timeout(5),
             client_timeout: 5000,
             client_disconnect: 0,
+            client_read_timeout: 0,
             secure: false,
             local_addr: None,
+            proxy_protocol: false,
+            h2c: false,
+            max_header_size: h1::MAX_BUFFER_SIZE,
+            max_headers: h1::MAX_HEADERS,
+            strict_parsing: false,
+            max_pipelined_requests: h1::MAX_PIPELINED_MESSAGES,
+            early_hints: false,
+            max_requests: 0,
+            max_lifetime: 0,
+            tcp: TcpOptions::default(),
+            h2: H2Options::default(),
+            date_interval: 500,
+            date_header: true,
+            buffer_capacity: h1::HW_BUFFER_SIZE,
+            max_buffer_capacity: 0,
+            error_response: None,
+            idle_limit: None,
+            request_pool: None,
+            response_pool: None,
             expect: ExpectHandler,
             upgrade: None,
             on_connect: None,
@@ -116,6 +164,330 @@ where
         self
     }
 
+    /// Set server client read timeout in milliseconds for subsequent requests.
+    ///
+    /// Defines a timeout for reading request headers of keep-alive requests,
+    /// measured from the first received byte, and a timeout between reads of
+    /// request payload. If it expires while reading headers, the request is
+    /// terminated with the 408 (Request Time-out) error. If it expires while
+    /// reading payload, the payload fails with an error. Connection is closed
+    /// in both cases. This protects server from clients that send requests slowly.
+    ///
+    /// To disable timeout set value to 0.
+    ///
+    /// By default client read timeout is disabled.
+    pub fn client_read_timeout(mut self, val: u64) -> Self {
+        self.client_read_timeout = val;
+        self
+    }
+
+    /// Expect HAProxy PROXY protocol header (v1 or v2) on every connection.
+    ///
+    /// Source address announced by the proxy is used as the request's peer
+    /// address, `LOCAL` and `UNKNOWN` connections keep address of the socket.
+    /// Connections without valid header get closed. Header must arrive within
+    /// client timeout, or within 5 seconds if client timeout is disabled.
+    /// This setting affects services created with `HttpService::tcp()` method.
+    ///
+    /// By default PROXY protocol is disabled.
+    pub fn proxy_protocol(mut self, val: bool) -> Self {
+        self.proxy_protocol = val;
+        self
+    }
+
+    /// Accept cleartext HTTP/2 (h2c) connections.
+    ///
+    /// Connections that start with HTTP/2 connection preface get served with
+    /// HTTP/2 protocol, all other connections use HTTP/1.x. Connections that
+    /// send incomplete preface within client timeout are served as HTTP/1.x
+    /// and get `408 Request Timeout` response. HTTP/1.1 connections are
+    /// upgraded to HTTP/2 with `Upgrade: h2c` requests without body, other
+    /// upgrade requests are served over HTTP/1.1 as if upgrade header is absent.
+    /// This setting affects services created with `HttpService::tcp()` method.
+    ///
+    /// By default h2c is disabled.
+    pub fn h2c(mut self, val: bool) -> Self {
+        self.h2c = val;
+        self
+    }
+
+    /// Set maximum size of request head in bytes, including request line
+    /// and headers.
+    ///
+    /// Requests with larger head are responded with
+    /// 431 (Request Header Fields Too Large) error and connection gets closed.
+    ///
+    /// By default maximum head size is 128 KiB.
+    pub fn max_header_size(mut self, val: usize) -> Self {
+        self.max_header_size = val;
+        self
+    }
+
+    /// Set maximum number of headers in request.
+    ///
+    /// Requests with more headers are responded with
+    /// 431 (Request Header Fields Too Large) error and connection gets closed.
+    ///
+    /// By default maximum number of headers is 96.
+    pub fn max_headers(mut self, val: usize) -> Self {
+        self.max_headers = val;
+        self
+    }
+
+    /// Set maximum number of pipelined HTTP/1.1 requests buffered per connection.
+    ///
+    /// Dispatcher stops reading request heads once this number of requests
+    /// waits for processing, read buffer is capped at buffer capacity until
+    /// then, so client is held back. Requests are always processed and responded
+    /// in order. Value 0 disables pipelining, next request is read only after
+    /// response to the previous one is sent.
+    ///
+    /// By default 16 requests could be buffered.
+    pub fn max_pipelined_requests(mut self, val: usize) -> Self {
+        self.max_pipelined_requests = val;
+        self
+    }
+
+    /// Enable strict parsing of HTTP/1 request heads.
+    ///
+    /// In strict mode requests with both `Transfer-Encoding` and
+    /// `Content-Length` headers, multiple `Content-Length` headers,
+    /// transfer codings other than `chunked`, obsolete line folding or
+    /// bare LF line endings are responded with 400 (Bad Request) error
+    /// and connection gets closed. Header names are validated in both modes.
+    ///
+    /// Strict mode prevents request smuggling when server is running behind
+    /// proxy that parses requests differently.
+    ///
+    /// By default strict parsing is disabled.
+    pub fn strict_parsing(mut self, val: bool) -> Self {
+        self.strict_parsing = val;
+        self
+    }
+
+    /// Enable `103 Early Hints` informational responses.
+    ///
+    /// If enabled, `h1::EarlyHints` handle is added to extensions of
+    /// HTTP/1.1 requests, service could use it for sending `Link` headers
+    /// before final response is ready.
+    ///
+    /// By default early hints are disabled.
+    pub fn early_hints(mut self, val: bool) -> Self {
+        self.early_hints = val;
+        self
+    }
+
+    /// Set maximum number of requests served by one HTTP/1 connection.
+    ///
+    /// Last allowed request is responded with `Connection: close` header and
+    /// connection gets closed afterwards. Load balancers can use this to
+    /// rebalance keep-alive connections.
+    ///
+    /// By default number of requests is not limited, value 0 disables limit.
+    pub fn max_requests(mut self, val: usize) -> Self {
+        self.max_requests = val;
+        self
+    }
+
+    /// Set maximum lifetime of HTTP/1 connection in seconds.
+    ///
+    /// First request received after lifetime elapsed is responded with
+    /// `Connection: close` header and connection gets closed afterwards.
+    ///
+    /// By default lifetime is not limited, value 0 disables limit.
+    pub fn max_lifetime(mut self, val: u64) -> Self {
+        self.max_lifetime = val;
+        self
+    }
+
+    /// Set `TCP_NODELAY` option on accepted tcp connections.
+    ///
+    /// Disables Nagle's algorithm, small responses are sent without delay.
+    ///
+    /// By default option is not set.
+    pub fn tcp_nodelay(mut self, val: bool) -> Self {
+        self.tcp.nodelay = val;
+        self
+    }
+
+    /// Set `SO_KEEPALIVE` option with given idle time on accepted tcp connections.
+    ///
+    /// By default operating system setting is used.
+    pub fn tcp_keepalive(mut self, val: Option<Duration>) -> Self {
+        self.tcp.keepalive = val;
+        self
+    }
+
+    /// Set `SO_RCVBUF` size of accepted tcp connections.
+    ///
+    /// By default operating system setting is used, value 0 keeps it.
+    pub fn tcp_recv_buffer_size(mut self, val: usize) -> Self {
+        self.tcp.recv_buffer_size = val;
+        self
+    }
+
+    /// Set `SO_SNDBUF` size of accepted tcp connections.
+    ///
+    /// By default operating system setting is used, value 0 keeps it.
+    pub fn tcp_send_buffer_size(mut self, val: usize) -> Self {
+        self.tcp.send_buffer_size = val;
+        self
+    }
+
+    /// Set initial HTTP/2 stream flow control window size.
+    ///
+    /// By default h2 crate setting (64 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value exceeds 2^31-1, maximum window size of HTTP/2.
+    pub fn h2_initial_window_size(mut self, val: u32) -> Self {
+        assert!(
+            val <= MAX_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31-1"
+        );
+        self.h2.initial_window_size = val;
+        self
+    }
+
+    /// Set initial HTTP/2 connection flow control window size.
+    ///
+    /// By default h2 crate setting (64 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value exceeds 2^31-1, maximum window size of HTTP/2.
+    pub fn h2_initial_connection_window_size(mut self, val: u32) -> Self {
+        assert!(
+            val <= MAX_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31-1"
+        );
+        self.h2.initial_connection_window_size = val;
+        self
+    }
+
+    /// Set maximum number of concurrent HTTP/2 streams per connection.
+    ///
+    /// By default number of streams is not limited, value 0 keeps it.
+    pub fn h2_max_concurrent_streams(mut self, val: u32) -> Self {
+        self.h2.max_concurrent_streams = val;
+        self
+    }
+
+    /// Set maximum HTTP/2 frame size accepted from client.
+    ///
+    /// By default h2 crate setting (16 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value is not in 16384..=16777215 range allowed by HTTP/2.
+    pub fn h2_max_frame_size(mut self, val: u32) -> Self {
+        assert!(
+            val == 0 || (MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(&val),
+            "HTTP/2 max frame size must be in 16384..=16777215 range"
+        );
+        self.h2.max_frame_size = val;
+        self
+    }
+
+    /// Set maximum size of HTTP/2 request header list.
+    ///
+    /// By default size is not limited, value 0 keeps it.
+    pub fn h2_max_header_list_size(mut self, val: u32) -> Self {
+        self.h2.max_header_list_size = val;
+        self
+    }
+
+    /// Set refresh interval of cached `Date` header value in milliseconds.
+    ///
+    /// Date value is formatted once per interval and shared by all
+    /// connections of a worker. Zero value keeps default interval.
+    ///
+    /// By default interval is set to 500 milliseconds.
+    pub fn date_interval(mut self, val: u64) -> Self {
+        self.date_interval = val;
+        self
+    }
+
+    /// Enable or disable `Date` header for responses.
+    ///
+    /// Explicitly set `Date` header of a response is always sent.
+    ///
+    /// By default `Date` header is enabled.
+    pub fn date_header(mut self, val: bool) -> Self {
+        self.date_header = val;
+        self
+    }
+
+    /// Set initial capacity of connection read and write buffers.
+    ///
+    /// Buffers are extended by this amount when running low, payload
+    /// streaming pauses once write buffer holds this many bytes.
+    /// Capacity is never less than 4kb.
+    ///
+    /// By default capacity is set to 32kb.
+    pub fn buffer_capacity(mut self, val: usize) -> Self {
+        self.buffer_capacity = val;
+        self
+    }
+
+    /// Set capacity limit of retained connection buffers.
+    ///
+    /// Buffers that grew over this limit, i.e. for large responses, are
+    /// released once they are drained instead of being kept for the rest
+    /// of the connection lifetime. Value 0 disables the limit.
+    ///
+    /// By default buffers are not released.
+    pub fn max_buffer_capacity(mut self, val: usize) -> Self {
+        self.max_buffer_capacity = val;
+        self
+    }
+
+    /// Set factory of responses for protocol level errors.
+    ///
+    /// Factory get called with response status when server responds
+    /// without calling the service: `400 Bad Request` for malformed
+    /// requests, `408 Request Timeout` for slow requests, `431 Request Header
+    /// Fields Too Large` for too large request heads and `500 Internal Server
+    /// Error` for internal http/1 protocol errors.
+    ///
+    /// By default responses with empty body are sent.
+    pub fn error_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(StatusCode) -> Response + 'static,
+    {
+        self.error_response = Some(Rc::new(f));
+        self
+    }
+
+    /// Set limit of idle keep-alive connections.
+    ///
+    /// Once number of idle connections exceeds the limit, least recently
+    /// used idle connection of all services sharing the limit gets closed.
+    /// Limit could be shared by services of different workers.
+    ///
+    /// By default idle connections are not limited.
+    pub fn idle_limit(mut self, limit: IdleLimit) -> Self {
+        self.idle_limit = Some(limit);
+        self
+    }
+
+    /// Set pool configuration of request heads.
+    ///
+    /// Pools are kept per thread and are shared by all services and
+    /// clients of the thread, configuration is applied to thread that
+    /// builds the service, i.e. to server worker.
+    ///
+    /// By default pool keeps up to 128 request heads.
+    pub fn request_pool(mut self, config: PoolConfig) -> Self {
+        self.request_pool = Some(config);
+        self
+    }
+
+    /// Set pool configuration of response heads.
+    ///
+    /// Pools are kept per thread, same as pool of request heads.
+    ///
+    /// By default pool keeps up to 128 response heads.
+    pub fn response_pool(mut self, config: PoolConfig) -> Self {
+        self.response_pool = Some(config);
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -133,8 +505,28 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            client_read_timeout: self.client_read_timeout,
             secure: self.secure,
             local_addr: self.local_addr,
+            proxy_protocol: self.proxy_protocol,
+            h2c: self.h2c,
+            max_header_size: self.max_header_size,
+            max_headers: self.max_headers,
+            strict_parsing: self.strict_parsing,
+            max_pipelined_requests: self.max_pipelined_requests,
+            early_hints: self.early_hints,
+            max_requests: self.max_requests,
+            max_lifetime: self.max_lifetime,
+            tcp: self.tcp,
+            h2: self.h2,
+            date_interval: self.date_interval,
+            date_header: self.date_header,
+            buffer_capacity: self.buffer_capacity,
+            max_buffer_capacity: self.max_buffer_capacity,
+            error_response: self.error_response,
+            idle_limit: self.idle_limit,
+            request_pool: self.request_pool,
+            response_pool: self.response_pool,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect: self.on_connect,
@@ -146,6 +538,10 @@ where
     ///
     /// If service is provided then normal requests handling get halted
     /// and this service get called with original request and framed object.
+    ///
+    /// `CONNECT` requests are passed to this service as well. Without upgrade
+    /// service, request payload of `CONNECT` request streams raw tunnel data
+    /// and successful streaming response is sent without transfer encoding.
     pub fn upgrade<F, U1>(self, upgrade: F) -> HttpServiceBuilder<T, S, X, U1>
     where
         F: IntoServiceFactory<U1>,
@@ -162,8 +558,28 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            client_read_timeout: self.client_read_timeout,
             secure: self.secure,
             local_addr: self.local_addr,
+            proxy_protocol: self.proxy_protocol,
+            h2c: self.h2c,
+            max_header_size: self.max_header_size,
+            max_headers: self.max_headers,
+            strict_parsing: self.strict_parsing,
+            max_pipelined_requests: self.max_pipelined_requests,
+            early_hints: self.early_hints,
+            max_requests: self.max_requests,
+            max_lifetime: self.max_lifetime,
+            tcp: self.tcp,
+            h2: self.h2,
+            date_interval: self.date_interval,
+            date_header: self.date_header,
+            buffer_capacity: self.buffer_capacity,
+            max_buffer_capacity: self.max_buffer_capacity,
+            error_response: self.error_response,
+            idle_limit: self.idle_limit,
+            request_pool: self.request_pool,
+            response_pool: self.response_pool,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect: self.on_connect,
@@ -174,16 +590,81 @@ where
     /// Set on-connect callback.
     ///
     /// It get called once per connection and result of the call
-    /// get stored to the request's extensions.
+    /// get stored to the request's extensions. If several callbacks
+    /// are set, all of them get called.
     pub fn on_connect<F, I>(mut self, f: F) -> Self
     where
         F: Fn(&T) -> I + 'static,
         I: Clone + 'static,
+        T: 'static,
     {
-        self.on_connect = Some(Rc::new(move |io| Box::new(Data(f(io)))));
+        let f: Rc<dyn Fn(&T) -> Box<dyn DataFactory>> =
+            Rc::new(move |io: &T| -> Box<dyn DataFactory> { Box::new(Data(f(io))) });
+
+        self.on_connect = Some(match self.on_connect.take() {
+            None => f,
+            Some(prev) => Rc::new(move |io: &T| -> Box<dyn DataFactory> {
+                Box::new(DataChain(prev(io), f(io)))
+            }),
+        });
         self
     }
 
+    /// Set on-connect callback with access to connection extensions.
+    ///
+    /// It get called once per connection, values inserted to extensions
+    /// are available to all requests of the connection with
+    /// `ConnectionExtensions` stored to the request's extensions.
+    pub fn on_connect_ext<F>(self, f: F) -> Self
+    where
+        F: Fn(&T, &mut Extensions) + 'static,
+        T: 'static,
+    {
+        self.on_connect(move |io| {
+            let mut ext = Extensions::new();
+            f(io, &mut ext);
+            ConnectionExtensions::new(ext)
+        })
+    }
+
+    fn service_config(&self) -> ServiceConfig {
+        let mut cfg = ServiceConfig::new(
+            self.keep_alive,
+            self.client_timeout,
+            self.client_disconnect,
+            self.secure,
+            self.local_addr,
+        );
+        cfg.set_proxy_protocol(self.proxy_protocol);
+        cfg.set_h2c(self.h2c);
+        cfg.set_max_header_size(self.max_header_size);
+        cfg.set_max_headers(self.max_headers);
+        cfg.set_strict_parsing(self.strict_parsing);
+        cfg.set_max_pipelined_requests(self.max_pipelined_requests);
+        cfg.set_early_hints(self.early_hints);
+        cfg.set_client_read_timeout(self.client_read_timeout);
+        cfg.set_max_requests(self.max_requests);
+        cfg.set_max_lifetime(self.max_lifetime);
+        cfg.set_tcp_options(self.tcp);
+        cfg.set_h2_options(self.h2);
+        cfg.set_date_interval(self.date_interval);
+        cfg.set_date_header(self.date_header);
+        cfg.set_buffer_capacity(self.buffer_capacity);
+        cfg.set_max_buffer_capacity(self.max_buffer_capacity);
+        cfg.set_error_response(self.error_response.clone());
+        cfg.set_idle_limit(self.idle_limit.clone());
+
+        // pools are thread local, they are configured on thread that
+        // builds the service
+        if let Some(ref config) = self.request_pool {
+            message::set_request_pool(config.clone());
+        }
+        if let Some(ref config) = self.response_pool {
+            message::set_response_pool(config.clone());
+        }
+        cfg
+    }
+
     /// Finish service configuration and create *http service* for HTTP/1 protocol.
     pub fn h1<F, B>(self, service: F) -> H1Service<T, S, B, X, U>
     where
@@ -193,13 +674,7 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -216,13 +691,7 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
         H2Service::with_config(cfg, service.into_factory()).on_connect(self.on_connect)
     }
 
@@ -236,16 +705,56 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
             .on_connect(self.on_connect)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_service::boxed::BoxServiceFactory;
+
+    use super::*;
+
+    type Builder = HttpServiceBuilder<
+        actix_rt::net::TcpStream,
+        BoxServiceFactory<(), Request, Response, Error, ()>,
+    >;
+
+    #[test]
+    fn test_h2_settings_bounds() {
+        let _ = Builder::new()
+            .h2_max_frame_size(0)
+            .h2_max_frame_size(MIN_FRAME_SIZE)
+            .h2_max_frame_size(MAX_FRAME_SIZE)
+            .h2_initial_window_size(MAX_WINDOW_SIZE)
+            .h2_initial_connection_window_size(MAX_WINDOW_SIZE);
+    }
+
+    #[test]
+    #[should_panic(expected = "HTTP/2 max frame size must be in 16384..=16777215 range")]
+    fn test_h2_max_frame_size_too_small() {
+        let _ = Builder::new().h2_max_frame_size(MIN_FRAME_SIZE - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "HTTP/2 max frame size must be in 16384..=16777215 range")]
+    fn test_h2_max_frame_size_too_large() {
+        let _ = Builder::new().h2_max_frame_size(MAX_FRAME_SIZE + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "HTTP/2 window size must not exceed 2^31-1")]
+    fn test_h2_window_size_too_large() {
+        let _ = Builder::new().h2_initial_window_size(MAX_WINDOW_SIZE + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "HTTP/2 window size must not exceed 2^31-1")]
+    fn test_h2_connection_window_size_too_large() {
+        let _ = Builder::new().h2_initial_connection_window_size(MAX_WINDOW_SIZE + 1);
+    }
+}
@@ -9,11 +9,14 @@ use actix_connect::{
 use actix_rt::net::TcpStream;
 use actix_service::{apply_fn, Service};
 use actix_utils::timeout::{TimeoutError, TimeoutService};
+use futures_util::future::{Either, LocalBoxFuture};
 use http::Uri;
 
 use super::connection::Connection;
 use super::error::ConnectError;
-use super::pool::{ConnectionPool, Protocol};
+use super::pool::{ConnectionPool, PoolMetrics, Protocol};
+use super::proxy::{Proxy, ProxyConnector};
+use super::resolver::HostResolver;
 use super::Connect;
 
 #[cfg(feature = "openssl")]
@@ -53,6 +56,11 @@ pub struct Connector<T, U> {
     conn_keep_alive: Duration,
     disconnect_timeout: Duration,
     limit: usize,
+    limit_per_host: usize,
+    metrics: PoolMetrics,
+    resolver: Option<HostResolver>,
+    proxy: Option<Proxy>,
+    h2_prior_knowledge: bool,
     #[allow(dead_code)]
     ssl: SslConnector,
     _t: PhantomData<U>,
@@ -104,6 +112,11 @@ impl Connector<(), ()> {
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Duration::from_millis(3000),
             limit: 100,
+            limit_per_host: 0,
+            metrics: PoolMetrics::default(),
+            resolver: None,
+            proxy: None,
+            h2_prior_knowledge: false,
             _t: PhantomData,
         }
     }
@@ -127,10 +140,26 @@ impl<T, U> Connector<T, U> {
             conn_keep_alive: self.conn_keep_alive,
             disconnect_timeout: self.disconnect_timeout,
             limit: self.limit,
+            limit_per_host: self.limit_per_host,
+            metrics: self.metrics,
+            resolver: self.resolver,
+            proxy: self.proxy,
+            h2_prior_knowledge: self.h2_prior_knowledge,
             ssl: self.ssl,
             _t: PhantomData,
         }
     }
+
+    /// Connect to remote hosts through HTTP or SOCKS5 proxy.
+    ///
+    /// Connections are tunneled through proxy, tls handshake is done with
+    /// remote host over the tunnel. Use `Proxy::from_env()` to read proxy
+    /// configuration from `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// variables.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
 }
 
 impl<T, U> Connector<T, U>
@@ -150,6 +179,16 @@ where
         self
     }
 
+    /// Use custom host name resolution.
+    ///
+    /// Static addresses of hosts override dns, addresses of host are tried
+    /// one by one until connection succeeds. By default hosts are resolved
+    /// by tcp connector.
+    pub fn resolver(mut self, resolver: HostResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     #[cfg(feature = "openssl")]
     /// Use custom `SslConnector` instance.
     pub fn ssl(mut self, connector: OpensslConnector) -> Self {
@@ -172,6 +211,32 @@ where
         self
     }
 
+    /// Set number of simultaneous connections to single host.
+    ///
+    /// Requests wait for available connection once limit is reached,
+    /// requests to other hosts are not blocked. If limit is 0, the
+    /// connector has no per-host limit, this is the default.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.limit_per_host = limit;
+        self
+    }
+
+    /// Register metrics handle of connection pool.
+    pub fn metrics(mut self, metrics: PoolMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Use HTTP/2 with prior knowledge for plain text connections.
+    ///
+    /// Connections to `http://` uris use HTTP/2 from the start, server has
+    /// to support cleartext HTTP/2. Protocol of tls connections is negotiated
+    /// with ALPN regardless of this setting. Disabled by default.
+    pub fn h2_prior_knowledge(mut self) -> Self {
+        self.h2_prior_knowledge = true;
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -212,16 +277,28 @@ where
     pub fn finish(
         self,
     ) -> impl Service<Request = Connect, Response = impl Connection, Error = ConnectError>
-           + Clone {
+           + Clone
+    where
+        T::Future: 'static,
+    {
+        let resolver = self.resolver;
+        // protocol of plain text connections
+        let protocol = if self.h2_prior_knowledge {
+            Protocol::Http2
+        } else {
+            Protocol::Http1
+        };
+
         #[cfg(not(any(feature = "openssl", feature = "rustls")))]
         {
             let connector = TimeoutService::new(
                 self.timeout,
-                apply_fn(self.connector, |msg: Connect, srv| {
-                    srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                })
+                apply_fn(
+                    ProxyConnector::new(self.connector, self.proxy, false),
+                    move |msg: Connect, srv| tcp_connect(&resolver, srv, msg),
+                )
                 .map_err(ConnectError::from)
-                .map(|stream| (stream.into_parts().0, Protocol::Http1)),
+                .map(move |stream| (stream.into_parts().0, protocol)),
             )
             .map_err(|e| match e {
                 TimeoutError::Service(e) => e,
@@ -235,6 +312,8 @@ where
                     self.conn_keep_alive,
                     None,
                     self.limit,
+                    self.limit_per_host,
+                    self.metrics,
                 ),
             }
         }
@@ -247,12 +326,18 @@ where
             use actix_connect::ssl::rustls::{RustlsConnector, Session};
             use actix_service::{boxed::service, pipeline};
 
+            let ssl_resolver = resolver.clone();
             let ssl_service = TimeoutService::new(
                 self.timeout,
                 pipeline(
-                    apply_fn(self.connector.clone(), |msg: Connect, srv| {
-                        srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                    })
+                    apply_fn(
+                        ProxyConnector::new(
+                            self.connector.clone(),
+                            self.proxy.clone(),
+                            true,
+                        ),
+                        move |msg: Connect, srv| tcp_connect(&ssl_resolver, srv, msg),
+                    )
                     .map_err(ConnectError::from),
                 )
                 .and_then(match self.ssl {
@@ -302,11 +387,12 @@ where
 
             let tcp_service = TimeoutService::new(
                 self.timeout,
-                apply_fn(self.connector, |msg: Connect, srv| {
-                    srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-                })
+                apply_fn(
+                    ProxyConnector::new(self.connector, self.proxy, false),
+                    move |msg: Connect, srv| tcp_connect(&resolver, srv, msg),
+                )
                 .map_err(ConnectError::from)
-                .map(|stream| (stream.into_parts().0, Protocol::Http1)),
+                .map(move |stream| (stream.into_parts().0, protocol)),
             )
             .map_err(|e| match e {
                 TimeoutError::Service(e) => e,
@@ -320,6 +406,8 @@ where
                     self.conn_keep_alive,
                     None,
                     self.limit,
+                    self.limit_per_host,
+                    self.metrics.clone(),
                 ),
                 ssl_pool: ConnectionPool::new(
                     ssl_service,
@@ -327,12 +415,42 @@ where
                     self.conn_keep_alive,
                     Some(self.disconnect_timeout),
                     self.limit,
+                    self.limit_per_host,
+                    self.metrics,
                 ),
             }
         }
     }
 }
 
+/// Connect to host of request, host is resolved by resolver of connector
+/// unless address of request is set
+fn tcp_connect<T, U>(
+    resolver: &Option<HostResolver>,
+    srv: &mut T,
+    msg: Connect,
+) -> Either<
+    T::Future,
+    LocalBoxFuture<'static, Result<TcpConnection<Uri, U>, actix_connect::ConnectError>>,
+>
+where
+    T: Service<
+            Request = TcpConnect<Uri>,
+            Response = TcpConnection<Uri, U>,
+            Error = actix_connect::ConnectError,
+        > + Clone
+        + 'static,
+    T::Future: 'static,
+    U: 'static,
+{
+    match resolver {
+        Some(ref resolver) if msg.addr.is_none() => {
+            Either::Right(resolver.connect(srv, msg.uri))
+        }
+        _ => Either::Left(srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))),
+    }
+}
+
 #[cfg(not(any(feature = "openssl", feature = "rustls")))]
 mod connect_impl {
     use std::task::{Context, Poll};
@@ -108,20 +108,25 @@ pub enum SendRequestError {
     /// Response took too long
     #[display(fmt = "Timeout out while waiting for response")]
     Timeout,
+    /// Response head is not received in time after connection is established
+    #[display(fmt = "Timeout out while waiting for response head")]
+    ResponseTimeout,
     /// Tunnels are not supported for http2 connection
     #[display(fmt = "Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
     /// Error sending request body
     Body(Error),
+    /// Redirect limit is reached
+    #[display(fmt = "Too many redirects")]
+    TooManyRedirects,
 }
 
 /// Convert `SendRequestError` to a server `Response`
 impl ResponseError for SendRequestError {
     fn status_code(&self) -> StatusCode {
         match *self {
-            SendRequestError::Connect(ConnectError::Timeout) => {
-                StatusCode::GATEWAY_TIMEOUT
-            }
+            SendRequestError::Connect(ConnectError::Timeout)
+            | SendRequestError::ResponseTimeout => StatusCode::GATEWAY_TIMEOUT,
             SendRequestError::Connect(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
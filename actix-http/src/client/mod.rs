@@ -7,11 +7,19 @@ mod error;
 mod h1proto;
 mod h2proto;
 mod pool;
+mod proxy;
+mod resolver;
+#[cfg(unix)]
+mod uds;
 
 pub use self::connection::Connection;
 pub use self::connector::Connector;
 pub use self::error::{ConnectError, FreezeRequestError, InvalidUrl, SendRequestError};
-pub use self::pool::Protocol;
+pub use self::pool::{PoolMetrics, Protocol};
+pub use self::proxy::Proxy;
+pub use self::resolver::{AddressOrder, HostResolver, Resolve, SystemResolver};
+#[cfg(unix)]
+pub use self::uds::UdsConnector;
 
 #[derive(Clone)]
 pub struct Connect {
@@ -1,8 +1,8 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
@@ -40,6 +40,52 @@ impl From<Authority> for Key {
     }
 }
 
+/// Metrics of client connection pool.
+///
+/// Metrics handle is registered with `Connector::metrics()` and is shared
+/// by all pools of connector, so it could be cloned and inspected at any
+/// time.
+#[derive(Clone, Default, Debug)]
+pub struct PoolMetrics(Rc<Metrics>);
+
+#[derive(Default, Debug)]
+struct Metrics {
+    opened: Cell<usize>,
+    reused: Cell<usize>,
+    active: Cell<usize>,
+    idle: Cell<usize>,
+}
+
+impl PoolMetrics {
+    /// Number of opened connections.
+    pub fn opened(&self) -> usize {
+        self.0.opened.get()
+    }
+
+    /// Number of times idle connection is reused.
+    pub fn reused(&self) -> usize {
+        self.0.reused.get()
+    }
+
+    /// Number of connections in use, including connections being opened.
+    pub fn active(&self) -> usize {
+        self.0.active.get()
+    }
+
+    /// Number of idle connections kept in pool.
+    pub fn idle(&self) -> usize {
+        self.0.idle.get()
+    }
+
+    fn incr(cell: &Cell<usize>) {
+        cell.set(cell.get() + 1);
+    }
+
+    fn decr(cell: &Cell<usize>) {
+        cell.set(cell.get().saturating_sub(1));
+    }
+}
+
 /// Connections pool
 pub(crate) struct ConnectionPool<T, Io: 'static>(Rc<RefCell<T>>, Rc<RefCell<Inner<Io>>>);
 
@@ -55,6 +101,8 @@ where
         conn_keep_alive: Duration,
         disconnect_timeout: Option<Duration>,
         limit: usize,
+        limit_per_host: usize,
+        metrics: PoolMetrics,
     ) -> Self {
         ConnectionPool(
             Rc::new(RefCell::new(connector)),
@@ -63,7 +111,11 @@ where
                 conn_keep_alive,
                 disconnect_timeout,
                 limit,
+                limit_per_host,
                 acquired: 0,
+                acquired_per_host: FxHashMap::default(),
+                metrics,
+                support: false,
                 waiters: Slab::new(),
                 waiters_queue: IndexSet::new(),
                 available: FxHashMap::default(),
@@ -99,10 +151,15 @@ where
 
     fn call(&mut self, req: Connect) -> Self::Future {
         // start support future
-        actix_rt::spawn(ConnectorPoolSupport {
-            connector: self.0.clone(),
-            inner: self.1.clone(),
-        });
+        if !self.1.borrow().support {
+            let keep_alive = self.1.borrow().conn_keep_alive;
+            self.1.borrow_mut().support = true;
+            actix_rt::spawn(ConnectorPoolSupport {
+                connector: Rc::downgrade(&self.0),
+                inner: Rc::downgrade(&self.1),
+                evict: delay_for(keep_alive),
+            });
+        }
 
         let mut connector = self.0.clone();
         let inner = self.1.clone();
@@ -224,6 +281,9 @@ where
     }
 
     fn consume(mut self) -> Acquired<Io> {
+        if let Some(ref inner) = self.inner {
+            PoolMetrics::incr(&inner.borrow().metrics.0.opened);
+        }
         Acquired(self.key.clone(), self.inner.take())
     }
 }
@@ -235,7 +295,7 @@ where
     fn drop(&mut self) {
         if let Some(i) = self.inner.take() {
             let mut inner = i.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
             inner.check_availibility();
         }
     }
@@ -258,7 +318,12 @@ pub(crate) struct Inner<Io> {
     conn_keep_alive: Duration,
     disconnect_timeout: Option<Duration>,
     limit: usize,
+    limit_per_host: usize,
     acquired: usize,
+    acquired_per_host: FxHashMap<Key, usize>,
+    metrics: PoolMetrics,
+    /// Support future is spawned
+    support: bool,
     available: FxHashMap<Key, VecDeque<AvailableConnection<Io>>>,
     waiters: Slab<
         Option<(
@@ -271,12 +336,35 @@ pub(crate) struct Inner<Io> {
 }
 
 impl<Io> Inner<Io> {
-    fn reserve(&mut self) {
+    fn reserve(&mut self, key: &Key) {
         self.acquired += 1;
+        *self.acquired_per_host.entry(key.clone()).or_insert(0) += 1;
+        PoolMetrics::incr(&self.metrics.0.active);
     }
 
-    fn release(&mut self) {
+    fn release(&mut self, key: &Key) {
         self.acquired -= 1;
+        if let Some(acquired) = self.acquired_per_host.get_mut(key) {
+            *acquired -= 1;
+            if *acquired == 0 {
+                self.acquired_per_host.remove(key);
+            }
+        }
+        PoolMetrics::decr(&self.metrics.0.active);
+    }
+
+    fn check_availibility(&self) {
+        if !self.waiters_queue.is_empty()
+            && (self.limit == 0 || self.acquired < self.limit)
+        {
+            self.waker.wake();
+        }
+    }
+
+    /// Check if limit of connections to host is not reached
+    fn host_available(&self, key: &Key) -> bool {
+        self.limit_per_host == 0
+            || self.acquired_per_host.get(key).map_or(0, |n| *n) < self.limit_per_host
     }
 
     fn release_waiter(&mut self, key: &Key, token: usize) {
@@ -310,17 +398,19 @@ where
 
     fn acquire(&mut self, key: &Key, cx: &mut Context<'_>) -> Acquire<Io> {
         // check limits
-        if self.limit > 0 && self.acquired >= self.limit {
+        if (self.limit > 0 && self.acquired >= self.limit) || !self.host_available(key) {
             return Acquire::NotAvailable;
         }
 
-        self.reserve();
+        self.reserve(key);
 
         // check if open connection is available
         // cleanup stale connections at the same time
         if let Some(ref mut connections) = self.available.get_mut(key) {
             let now = Instant::now();
             while let Some(conn) = connections.pop_back() {
+                PoolMetrics::decr(&self.metrics.0.idle);
+
                 // check if it still usable
                 if (now - conn.used) > self.conn_keep_alive
                     || (now - conn.created) > self.conn_lifetime
@@ -333,22 +423,43 @@ where
                 } else {
                     let mut io = conn.io;
                     let mut buf = [0; 2];
-                    if let ConnectionType::H1(ref mut s) = io {
-                        match Pin::new(s).poll_read(cx, &mut buf) {
-                            Poll::Pending => (),
-                            Poll::Ready(Ok(n)) if n > 0 => {
-                                if let Some(timeout) = self.disconnect_timeout {
-                                    if let ConnectionType::H1(io) = io {
-                                        actix_rt::spawn(CloseConnection::new(
-                                            io, timeout,
-                                        ))
+                    match io {
+                        ConnectionType::H1(ref mut s) => {
+                            match Pin::new(s).poll_read(cx, &mut buf) {
+                                Poll::Pending => (),
+                                Poll::Ready(Ok(n)) if n > 0 => {
+                                    if let Some(timeout) = self.disconnect_timeout {
+                                        if let ConnectionType::H1(io) = io {
+                                            actix_rt::spawn(CloseConnection::new(
+                                                io, timeout,
+                                            ))
+                                        }
                                     }
+                                    continue;
                                 }
+                                _ => continue,
+                            }
+                        }
+                        ConnectionType::H2(ref mut snd) => {
+                            // connection is closed
+                            if let Poll::Ready(Err(_)) = snd.poll_ready(cx) {
                                 continue;
                             }
-                            _ => continue,
+
+                            // http/2 connection is shared by requests, it
+                            // stays in pool
+                            let shared = ConnectionType::H2(snd.clone());
+                            connections.push_back(AvailableConnection {
+                                io,
+                                used: conn.used,
+                                created: conn.created,
+                            });
+                            PoolMetrics::incr(&self.metrics.0.idle);
+                            PoolMetrics::incr(&self.metrics.0.reused);
+                            return Acquire::Acquired(shared, conn.created);
                         }
                     }
+                    PoolMetrics::incr(&self.metrics.0.reused);
                     return Acquire::Acquired(io, conn.created);
                 }
             }
@@ -357,7 +468,24 @@ where
     }
 
     fn release_conn(&mut self, key: &Key, io: ConnectionType<Io>, created: Instant) {
-        self.acquired -= 1;
+        self.release(key);
+
+        // shared http/2 connection is kept in pool already
+        if let ConnectionType::H2(_) = io {
+            let shared = self.available.get_mut(key).and_then(|connections| {
+                connections.iter_mut().find(|conn| match conn.io {
+                    ConnectionType::H2(_) => true,
+                    ConnectionType::H1(_) => false,
+                })
+            });
+            if let Some(conn) = shared {
+                conn.used = Instant::now();
+                self.check_availibility();
+                return;
+            }
+        }
+
+        PoolMetrics::incr(&self.metrics.0.idle);
         self.available
             .entry(key.clone())
             .or_insert_with(VecDeque::new)
@@ -369,19 +497,42 @@ where
         self.check_availibility();
     }
 
-    fn release_close(&mut self, io: ConnectionType<Io>) {
-        self.acquired -= 1;
+    fn release_close(&mut self, key: &Key, io: ConnectionType<Io>) {
+        self.release(key);
+        self.close(io);
+        self.check_availibility();
+    }
+
+    fn close(&self, io: ConnectionType<Io>) {
         if let Some(timeout) = self.disconnect_timeout {
             if let ConnectionType::H1(io) = io {
                 actix_rt::spawn(CloseConnection::new(io, timeout))
             }
         }
-        self.check_availibility();
     }
 
-    fn check_availibility(&self) {
-        if !self.waiters_queue.is_empty() && self.acquired < self.limit {
-            self.waker.wake();
+    /// Close idle connections that exceeded keep-alive period or lifetime
+    fn evict(&mut self) {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        for connections in self.available.values_mut() {
+            for _ in 0..connections.len() {
+                let conn = connections.pop_front().unwrap();
+                if (now - conn.used) > self.conn_keep_alive
+                    || (now - conn.created) > self.conn_lifetime
+                {
+                    expired.push(conn.io);
+                } else {
+                    connections.push_back(conn);
+                }
+            }
+        }
+        self.available
+            .retain(|_, connections| !connections.is_empty());
+
+        for io in expired {
+            PoolMetrics::decr(&self.metrics.0.idle);
+            self.close(io);
         }
     }
 }
@@ -422,12 +573,15 @@ where
     }
 }
 
+/// Support future of pool, opens connections for waiters and evicts idle
+/// connections. It is stopped once pool is dropped.
 struct ConnectorPoolSupport<T, Io>
 where
     Io: AsyncRead + AsyncWrite + Unpin + 'static,
 {
-    connector: T,
-    inner: Rc<RefCell<Inner<Io>>>,
+    connector: Weak<RefCell<T>>,
+    inner: Weak<RefCell<Inner<Io>>>,
+    evict: Delay,
 }
 
 impl<T, Io> Future for ConnectorPoolSupport<T, Io>
@@ -441,19 +595,32 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = unsafe { self.get_unchecked_mut() };
 
-        let mut inner = this.inner.as_ref().borrow_mut();
+        let (connector, inner_rc) =
+            match (this.connector.upgrade(), this.inner.upgrade()) {
+                (Some(connector), Some(inner)) => (connector, inner),
+                _ => return Poll::Ready(()),
+            };
+        let mut inner = inner_rc.as_ref().borrow_mut();
         inner.waker.register(cx.waker());
 
-        // check waiters
-        loop {
-            let (key, token) = {
-                if let Some((key, token)) = inner.waiters_queue.get_index(0) {
-                    (key.clone(), *token)
-                } else {
-                    break;
-                }
-            };
+        // evict idle connections
+        if Pin::new(&mut this.evict).poll(cx).is_ready() {
+            inner.evict();
+            this.evict.reset(actix_rt::time::Instant::from_std(
+                Instant::now() + inner.conn_keep_alive,
+            ));
+            let _ = Pin::new(&mut this.evict).poll(cx);
+        }
+
+        // check waiters, waiters of hosts that reached limit are skipped
+        let mut idx = 0;
+        while let Some((key, token)) = inner.waiters_queue.get_index(idx).cloned() {
             if inner.waiters.get(token).unwrap().is_none() {
+                let _ = inner.waiters_queue.shift_remove_index(idx);
+                continue;
+            }
+            if !inner.host_available(&key) {
+                idx += 1;
                 continue;
             }
 
@@ -464,7 +631,7 @@ where
                     if let Err(conn) = tx.send(Ok(IoConnection::new(
                         io,
                         created,
-                        Some(Acquired(key.clone(), Some(this.inner.clone()))),
+                        Some(Acquired(key.clone(), Some(inner_rc.clone()))),
                     ))) {
                         let (io, created) = conn.unwrap().into_inner();
                         inner.release_conn(&key, io, created);
@@ -476,12 +643,12 @@ where
                     OpenWaitingConnection::spawn(
                         key.clone(),
                         tx,
-                        this.inner.clone(),
-                        this.connector.call(connect),
+                        inner_rc.clone(),
+                        connector.borrow_mut().call(connect),
                     );
                 }
             }
-            let _ = inner.waiters_queue.swap_remove_index(0);
+            let _ = inner.waiters_queue.shift_remove_index(idx);
         }
 
         Poll::Pending
@@ -525,6 +692,18 @@ where
     }
 }
 
+impl<F, Io> OpenWaitingConnection<F, Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    fn acquired(&mut self) -> Acquired<Io> {
+        if let Some(ref inner) = self.inner {
+            PoolMetrics::incr(&inner.borrow().metrics.0.opened);
+        }
+        Acquired(self.key.clone(), self.inner.take())
+    }
+}
+
 impl<F, Io> Drop for OpenWaitingConnection<F, Io>
 where
     Io: AsyncRead + AsyncWrite + Unpin + 'static,
@@ -532,7 +711,7 @@ where
     fn drop(&mut self) {
         if let Some(inner) = self.inner.take() {
             let mut inner = inner.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
             inner.check_availibility();
         }
     }
@@ -556,7 +735,7 @@ where
                     let _ = rx.send(Ok(IoConnection::new(
                         ConnectionType::H2(snd),
                         Instant::now(),
-                        Some(Acquired(this.key.clone(), this.inner.take())),
+                        Some(this.acquired()),
                     )));
                     Poll::Ready(())
                 }
@@ -585,7 +764,7 @@ where
                     let _ = rx.send(Ok(IoConnection::new(
                         ConnectionType::H1(io),
                         Instant::now(),
-                        Some(Acquired(this.key.clone(), this.inner.take())),
+                        Some(this.acquired()),
                     )));
                     Poll::Ready(())
                 } else {
@@ -607,7 +786,7 @@ where
     pub(crate) fn close(&mut self, conn: IoConnection<T>) {
         if let Some(inner) = self.1.take() {
             let (io, _) = conn.into_inner();
-            inner.as_ref().borrow_mut().release_close(io);
+            inner.as_ref().borrow_mut().release_close(&self.0, io);
         }
     }
     pub(crate) fn release(&mut self, conn: IoConnection<T>) {
@@ -624,7 +803,9 @@ where
 impl<T> Drop for Acquired<T> {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
-            inner.as_ref().borrow_mut().release();
+            let mut inner = inner.as_ref().borrow_mut();
+            inner.release(&self.0);
+            inner.check_availibility();
         }
     }
 }
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::rc::Rc;
+use std::time::Duration;
+use std::{fmt, io};
+
+use actix_connect::{Connect as TcpConnect, ConnectError, Connection as TcpConnection};
+use actix_rt::time::timeout;
+use actix_service::Service;
+use actix_threadpool::BlockingError;
+use futures_util::future::{ok, poll_fn, FutureExt, LocalBoxFuture};
+use http::Uri;
+use rand::seq::SliceRandom;
+
+/// Resolver of host names.
+///
+/// ```rust,no_run
+/// use std::io;
+/// use std::net::IpAddr;
+///
+/// use actix_http::client::Resolve;
+/// use futures_util::future::{ok, FutureExt, LocalBoxFuture};
+///
+/// struct LocalResolver;
+///
+/// impl Resolve for LocalResolver {
+///     fn lookup(
+///         &self,
+///         _: &str,
+///     ) -> LocalBoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+///         ok(vec!["127.0.0.1".parse().unwrap()]).boxed_local()
+///     }
+/// }
+/// ```
+pub trait Resolve {
+    /// Resolve `host` to ip addresses.
+    fn lookup(
+        &self,
+        host: &str,
+    ) -> LocalBoxFuture<'static, Result<Vec<IpAddr>, io::Error>>;
+}
+
+/// Resolver of operating system, `getaddrinfo` is called on thread pool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn lookup(
+        &self,
+        host: &str,
+    ) -> LocalBoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+        let host = host.to_owned();
+        actix_threadpool::run(move || {
+            (host.as_str(), 0)
+                .to_socket_addrs()
+                .map(|addrs| addrs.map(|addr| addr.ip()).collect::<Vec<_>>())
+        })
+        .map(|res| {
+            res.map_err(|e| match e {
+                BlockingError::Error(e) => e,
+                BlockingError::Canceled => {
+                    io::Error::new(io::ErrorKind::Other, "Thread pool is gone")
+                }
+            })
+        })
+        .boxed_local()
+    }
+}
+
+/// Order in which addresses of host are tried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressOrder {
+    /// Addresses are tried in order of resolver
+    Resolved,
+    /// IPv4 addresses are tried before IPv6 addresses
+    Ipv4First,
+    /// IPv6 addresses are tried before IPv4 addresses
+    Ipv6First,
+    /// Only IPv4 addresses are tried
+    Ipv4Only,
+    /// Only IPv6 addresses are tried
+    Ipv6Only,
+    /// Addresses are tried in random order, connections are spread among
+    /// addresses of host
+    Random,
+}
+
+impl AddressOrder {
+    fn apply(self, mut addrs: Vec<IpAddr>) -> Vec<IpAddr> {
+        match self {
+            AddressOrder::Resolved => (),
+            AddressOrder::Ipv4First => addrs.sort_by_key(|ip| ip.is_ipv6()),
+            AddressOrder::Ipv6First => addrs.sort_by_key(|ip| ip.is_ipv4()),
+            AddressOrder::Ipv4Only => addrs.retain(|ip| ip.is_ipv4()),
+            AddressOrder::Ipv6Only => addrs.retain(|ip| ip.is_ipv6()),
+            AddressOrder::Random => addrs.shuffle(&mut rand::thread_rng()),
+        }
+        addrs
+    }
+}
+
+/// Host name resolution of client connector.
+///
+/// Static addresses of hosts override dns, they are useful for tests and
+/// for connecting to specific server while tls handshake and `Host` header
+/// use name of host. Other hosts are resolved by resolver set with
+/// `resolver()`, or by tcp connector if it is not set. Connector tries
+/// addresses of host one by one until connection succeeds, order of
+/// addresses is set with `order()`. Connection to each address but the last
+/// one is limited by `address_timeout()`, so unresponsive address does not
+/// take whole connector timeout.
+///
+/// Explicit address of request, i.e. `ClientRequest::address()` of awc,
+/// is used as is.
+///
+/// ```rust,no_run
+/// use actix_http::client::{AddressOrder, Connector, HostResolver, SystemResolver};
+///
+/// let connector = Connector::new()
+///     .resolver(
+///         HostResolver::new()
+///             .host("api.local", vec!["127.0.0.1".parse().unwrap()])
+///             .resolver(SystemResolver)
+///             .order(AddressOrder::Ipv4First),
+///     )
+///     .finish();
+/// ```
+#[derive(Clone)]
+pub struct HostResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    resolver: Option<Rc<dyn Resolve>>,
+    order: AddressOrder,
+    address_timeout: Duration,
+}
+
+impl Default for HostResolver {
+    fn default() -> Self {
+        HostResolver::new()
+    }
+}
+
+impl HostResolver {
+    /// Create resolver without static hosts, tcp connector resolves all
+    /// hosts.
+    pub fn new() -> Self {
+        HostResolver {
+            hosts: HashMap::new(),
+            resolver: None,
+            order: AddressOrder::Resolved,
+            address_timeout: Duration::from_millis(500),
+        }
+    }
+
+    /// Set static addresses of host.
+    pub fn host<I>(mut self, host: &str, addrs: I) -> Self
+    where
+        I: IntoIterator<Item = IpAddr>,
+    {
+        self.hosts
+            .insert(host.to_ascii_lowercase(), addrs.into_iter().collect());
+        self
+    }
+
+    /// Use custom resolver for hosts without static addresses.
+    pub fn resolver<R: Resolve + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Rc::new(resolver));
+        self
+    }
+
+    /// Set order of addresses, addresses are tried in order of resolver
+    /// by default.
+    pub fn order(mut self, order: AddressOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Set timeout of connection to single address of host, by default
+    /// 500 milliseconds.
+    ///
+    /// Next address is tried once timeout expires. Last address is tried
+    /// until connector timeout expires.
+    pub fn address_timeout(mut self, timeout: Duration) -> Self {
+        self.address_timeout = timeout;
+        self
+    }
+
+    /// Connect to host of `uri`, addresses of host are tried one by one
+    pub(crate) fn connect<T, U>(
+        &self,
+        connector: &mut T,
+        uri: Uri,
+    ) -> LocalBoxFuture<'static, Result<TcpConnection<Uri, U>, ConnectError>>
+    where
+        T: Service<
+                Request = TcpConnect<Uri>,
+                Response = TcpConnection<Uri, U>,
+                Error = ConnectError,
+            > + Clone
+            + 'static,
+        T::Future: 'static,
+        U: 'static,
+    {
+        let req = TcpConnect::new(uri.clone());
+        let host = req.host().to_ascii_lowercase();
+        let port = req.port();
+
+        let lookup = if host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .is_ok()
+        {
+            None
+        } else if let Some(addrs) = self.hosts.get(&host) {
+            Some(ok(addrs.clone()).boxed_local())
+        } else {
+            self.resolver
+                .as_ref()
+                .map(|resolver| resolver.lookup(&host))
+        };
+        let lookup = match lookup {
+            Some(lookup) => lookup,
+            None => return connector.call(req).boxed_local(),
+        };
+
+        let order = self.order;
+        let address_timeout = self.address_timeout;
+        let mut connector = connector.clone();
+        async move {
+            let addrs = order.apply(lookup.await.map_err(ConnectError::Io)?);
+
+            let mut error = ConnectError::NoRecords;
+            let last = addrs.len().saturating_sub(1);
+            for (idx, ip) in addrs.into_iter().enumerate() {
+                poll_fn(|cx| connector.poll_ready(cx)).await?;
+                let req = TcpConnect::new(uri.clone())
+                    .set_addr(Some(SocketAddr::new(ip, port)));
+                let res = if idx == last {
+                    connector.call(req).await
+                } else {
+                    timeout(address_timeout, connector.call(req))
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(ConnectError::Io(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "Connect timeout",
+                            )))
+                        })
+                };
+                match res {
+                    Ok(conn) => return Ok(conn),
+                    Err(e) => {
+                        trace!("Can not connect to {}: {:?}", ip, e);
+                        error = e;
+                    }
+                }
+            }
+            Err(error)
+        }
+        .boxed_local()
+    }
+}
+
+impl fmt::Debug for HostResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostResolver")
+            .field("hosts", &self.hosts)
+            .field("order", &self.order)
+            .field("address_timeout", &self.address_timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_service::IntoService;
+    use futures_util::future::pending;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_address_timeout() {
+        let calls = Rc::new(Cell::new(0));
+        let uri: Uri = "http://api.local".parse().unwrap();
+
+        // first address does not respond
+        let (c, u) = (calls.clone(), uri.clone());
+        let mut connector = (move |_: TcpConnect<Uri>| {
+            c.set(c.get() + 1);
+            if c.get() == 1 {
+                pending().boxed_local()
+            } else {
+                ok::<_, ConnectError>(TcpConnection::new((), u.clone())).boxed_local()
+            }
+        })
+        .into_service();
+
+        let resolver = HostResolver::new()
+            .host(
+                "api.local",
+                vec!["127.0.0.2".parse().unwrap(), "127.0.0.1".parse().unwrap()],
+            )
+            .address_timeout(Duration::from_millis(50));
+        assert!(resolver.connect(&mut connector, uri).await.is_ok());
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_address_order() {
+        let addrs: Vec<IpAddr> = vec![
+            "::1".parse().unwrap(),
+            "127.0.0.1".parse().unwrap(),
+            "::2".parse().unwrap(),
+            "127.0.0.2".parse().unwrap(),
+        ];
+        let order = |order: AddressOrder| -> Vec<String> {
+            order
+                .apply(addrs.clone())
+                .iter()
+                .map(|ip| ip.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            order(AddressOrder::Resolved),
+            ["::1", "127.0.0.1", "::2", "127.0.0.2"]
+        );
+        assert_eq!(
+            order(AddressOrder::Ipv4First),
+            ["127.0.0.1", "127.0.0.2", "::1", "::2"]
+        );
+        assert_eq!(
+            order(AddressOrder::Ipv6First),
+            ["::1", "::2", "127.0.0.1", "127.0.0.2"]
+        );
+        assert_eq!(order(AddressOrder::Ipv4Only), ["127.0.0.1", "127.0.0.2"]);
+        assert_eq!(order(AddressOrder::Ipv6Only), ["::1", "::2"]);
+        assert_eq!(AddressOrder::Random.apply(addrs.clone()).len(), 4);
+    }
+}
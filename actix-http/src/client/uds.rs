@@ -0,0 +1,65 @@
+use std::convert::TryFrom;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_connect::{Connect as TcpConnect, ConnectError, Connection as TcpConnection};
+use actix_rt::net::UnixStream;
+use actix_service::Service;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use http::Uri;
+
+/// Connector of unix domain sockets.
+///
+/// Connections are opened to the socket regardless of host of request,
+/// host is used for `Host` header only. It is used in place of tcp
+/// connector, i.e. for the docker daemon socket:
+///
+/// ```rust,no_run
+/// use actix_http::client::{Connector, UdsConnector};
+///
+/// let connector = Connector::new()
+///     .connector(UdsConnector::new("/var/run/docker.sock"))
+///     .finish();
+/// ```
+#[derive(Clone, Debug)]
+pub struct UdsConnector {
+    path: Rc<PathBuf>,
+}
+
+impl UdsConnector {
+    /// Create connector of socket at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        UdsConnector {
+            path: Rc::new(path.as_ref().to_owned()),
+        }
+    }
+}
+
+impl Service for UdsConnector {
+    type Request = TcpConnect<Uri>;
+    type Response = TcpConnection<Uri, UnixStream>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: TcpConnect<Uri>) -> Self::Future {
+        let path = self.path.clone();
+        let uri = Uri::try_from(format!("http://{}:{}", req.host(), req.port()));
+
+        async move {
+            let uri = uri.map_err(|e| {
+                ConnectError::Io(io::Error::new(io::ErrorKind::InvalidInput, e))
+            })?;
+            let stream = UnixStream::connect(path.as_ref())
+                .await
+                .map_err(ConnectError::Io)?;
+            Ok(TcpConnection::new(stream, uri))
+        }
+        .boxed_local()
+    }
+}
@@ -1,17 +1,27 @@
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
 use std::fmt::Write;
 use std::rc::Rc;
 use std::time::Duration;
-use std::{fmt, net};
+use std::{fmt, io, net};
 
-use actix_rt::time::{delay_for, delay_until, Delay, Instant};
-use bytes::BytesMut;
-use futures_util::{future, FutureExt};
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_rt::net::TcpStream;
+use actix_rt::time::{delay_until, interval_at, Delay, Instant};
+use bytes::{Bytes, BytesMut};
+use h2::server::{self, Handshake};
 use time;
 
+use crate::h1;
+use crate::http::StatusCode;
+use crate::idle::IdleLimit;
+use crate::response::Response;
+
 // "Sun, 06 Nov 1994 08:49:37 GMT".len()
 const DATE_VALUE_LENGTH: usize = 29;
 
+/// Default interval of cached date refresh
+const DATE_INTERVAL: Duration = Duration::from_millis(500);
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// Server keep-alive setting
 pub enum KeepAlive {
@@ -42,16 +52,104 @@ impl From<Option<usize>> for KeepAlive {
 /// Http service configuration
 pub struct ServiceConfig(Rc<Inner>);
 
+#[derive(Clone)]
 struct Inner {
     keep_alive: Option<Duration>,
     client_timeout: u64,
     client_disconnect: u64,
+    client_read_timeout: u64,
     ka_enabled: bool,
     secure: bool,
     local_addr: Option<std::net::SocketAddr>,
+    proxy_protocol: bool,
+    h2c: bool,
+    max_header_size: usize,
+    max_headers: usize,
+    strict_parsing: bool,
+    max_pipelined_requests: usize,
+    early_hints: bool,
+    max_requests: usize,
+    max_lifetime: u64,
+    tcp: TcpOptions,
+    h2: H2Options,
+    date_header: bool,
+    buffer_capacity: usize,
+    max_buffer_capacity: usize,
+    error_response: Option<Rc<dyn Fn(StatusCode) -> Response>>,
+    idle: Option<IdleLimit>,
     timer: DateService,
 }
 
+/// Socket options applied to accepted tcp connections
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TcpOptions {
+    pub(crate) nodelay: bool,
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) recv_buffer_size: usize,
+    pub(crate) send_buffer_size: usize,
+}
+
+impl TcpOptions {
+    /// Apply options to the stream, options with default values are skipped.
+    pub(crate) fn apply(&self, io: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            io.set_nodelay(true)?;
+        }
+        if self.keepalive.is_some() {
+            io.set_keepalive(self.keepalive)?;
+        }
+        if self.recv_buffer_size != 0 {
+            io.set_recv_buffer_size(self.recv_buffer_size)?;
+        }
+        if self.send_buffer_size != 0 {
+            io.set_send_buffer_size(self.send_buffer_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Max flow control window size of HTTP/2, RFC 7540 section 6.9.1
+pub(crate) const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+/// Bounds of HTTP/2 `SETTINGS_MAX_FRAME_SIZE`, RFC 7540 section 6.5.2
+pub(crate) const MIN_FRAME_SIZE: u32 = 1 << 14;
+pub(crate) const MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// HTTP/2 connection settings, zero values keep defaults of h2 crate
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct H2Options {
+    pub(crate) initial_window_size: u32,
+    pub(crate) initial_connection_window_size: u32,
+    pub(crate) max_concurrent_streams: u32,
+    pub(crate) max_frame_size: u32,
+    pub(crate) max_header_list_size: u32,
+}
+
+impl H2Options {
+    /// Start HTTP/2 handshake with configured settings.
+    pub(crate) fn handshake<T>(&self, io: T) -> Handshake<T, Bytes>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut builder = server::Builder::new();
+        if self.initial_window_size != 0 {
+            builder.initial_window_size(self.initial_window_size);
+        }
+        if self.initial_connection_window_size != 0 {
+            builder.initial_connection_window_size(self.initial_connection_window_size);
+        }
+        if self.max_concurrent_streams != 0 {
+            builder.max_concurrent_streams(self.max_concurrent_streams);
+        }
+        if self.max_frame_size != 0 {
+            builder.max_frame_size(self.max_frame_size);
+        }
+        if self.max_header_list_size != 0 {
+            builder.max_header_list_size(self.max_header_list_size);
+        }
+        builder.handshake(io)
+    }
+}
+
 impl Clone for ServiceConfig {
     fn clone(&self) -> Self {
         ServiceConfig(self.0.clone())
@@ -89,12 +187,130 @@ impl ServiceConfig {
             ka_enabled,
             client_timeout,
             client_disconnect,
+            client_read_timeout: 0,
             secure,
             local_addr,
-            timer: DateService::new(),
+            proxy_protocol: false,
+            h2c: false,
+            max_header_size: h1::MAX_BUFFER_SIZE,
+            max_headers: h1::MAX_HEADERS,
+            strict_parsing: false,
+            max_pipelined_requests: h1::MAX_PIPELINED_MESSAGES,
+            early_hints: false,
+            max_requests: 0,
+            max_lifetime: 0,
+            tcp: TcpOptions::default(),
+            h2: H2Options::default(),
+            date_header: true,
+            buffer_capacity: h1::HW_BUFFER_SIZE,
+            max_buffer_capacity: 0,
+            error_response: None,
+            idle: None,
+            timer: DateService::new(DATE_INTERVAL),
         }))
     }
 
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::make_mut(&mut self.0)
+    }
+
+    /// Expect PROXY protocol header at the start of every connection.
+    pub(crate) fn set_proxy_protocol(&mut self, val: bool) {
+        self.inner_mut().proxy_protocol = val;
+    }
+
+    /// Detect HTTP/2 connection preface on plain text connections.
+    pub(crate) fn set_h2c(&mut self, val: bool) {
+        self.inner_mut().h2c = val;
+    }
+
+    /// Set maximum size of request head.
+    pub(crate) fn set_max_header_size(&mut self, val: usize) {
+        self.inner_mut().max_header_size = val;
+    }
+
+    /// Set maximum number of request headers.
+    pub(crate) fn set_max_headers(&mut self, val: usize) {
+        self.inner_mut().max_headers = val;
+    }
+
+    /// Enable strict parsing of request heads.
+    pub(crate) fn set_strict_parsing(&mut self, val: bool) {
+        self.inner_mut().strict_parsing = val;
+    }
+
+    /// Set maximum number of pipelined requests buffered per connection.
+    pub(crate) fn set_max_pipelined_requests(&mut self, val: usize) {
+        self.inner_mut().max_pipelined_requests = val;
+    }
+
+    /// Enable `103 Early Hints` for HTTP/1.1 requests.
+    pub(crate) fn set_early_hints(&mut self, val: bool) {
+        self.inner_mut().early_hints = val;
+    }
+
+    /// Set client read timeout in milliseconds.
+    pub(crate) fn set_client_read_timeout(&mut self, val: u64) {
+        self.inner_mut().client_read_timeout = val;
+    }
+
+    /// Set maximum number of requests served by one connection.
+    pub(crate) fn set_max_requests(&mut self, val: usize) {
+        self.inner_mut().max_requests = val;
+    }
+
+    /// Set maximum lifetime of a connection in seconds.
+    pub(crate) fn set_max_lifetime(&mut self, val: u64) {
+        self.inner_mut().max_lifetime = val;
+    }
+
+    /// Set socket options for accepted tcp connections.
+    pub(crate) fn set_tcp_options(&mut self, val: TcpOptions) {
+        self.inner_mut().tcp = val;
+    }
+
+    /// Set HTTP/2 connection settings.
+    pub(crate) fn set_h2_options(&mut self, val: H2Options) {
+        self.inner_mut().h2 = val;
+    }
+
+    /// Set refresh interval of cached date value in milliseconds.
+    ///
+    /// Zero value keeps default interval.
+    pub(crate) fn set_date_interval(&mut self, val: u64) {
+        if val != 0 {
+            self.inner_mut().timer = DateService::new(Duration::from_millis(val));
+        }
+    }
+
+    /// Enable or disable `Date` header generation.
+    pub(crate) fn set_date_header(&mut self, val: bool) {
+        self.inner_mut().date_header = val;
+    }
+
+    /// Set initial capacity of connection buffers.
+    pub(crate) fn set_buffer_capacity(&mut self, val: usize) {
+        self.inner_mut().buffer_capacity = val;
+    }
+
+    /// Set capacity limit of retained connection buffers.
+    pub(crate) fn set_max_buffer_capacity(&mut self, val: usize) {
+        self.inner_mut().max_buffer_capacity = val;
+    }
+
+    /// Set factory of protocol level error responses.
+    pub(crate) fn set_error_response(
+        &mut self,
+        val: Option<Rc<dyn Fn(StatusCode) -> Response>>,
+    ) {
+        self.inner_mut().error_response = val;
+    }
+
+    /// Set limit of idle keep-alive connections.
+    pub(crate) fn set_idle_limit(&mut self, val: Option<IdleLimit>) {
+        self.inner_mut().idle = val;
+    }
+
     #[inline]
     /// Returns true if connection is secure(https)
     pub fn secure(&self) -> bool {
@@ -107,6 +323,94 @@ impl ServiceConfig {
         self.0.local_addr
     }
 
+    #[inline]
+    /// Returns true if connections start with PROXY protocol header
+    pub fn proxy_protocol(&self) -> bool {
+        self.0.proxy_protocol
+    }
+
+    #[inline]
+    /// Returns true if HTTP/2 prior knowledge is accepted on plain text connections
+    pub fn h2c(&self) -> bool {
+        self.0.h2c
+    }
+
+    #[inline]
+    /// Maximum size of request head, including request line and headers
+    pub fn max_header_size(&self) -> usize {
+        self.0.max_header_size
+    }
+
+    #[inline]
+    /// Maximum number of request headers
+    pub fn max_headers(&self) -> usize {
+        self.0.max_headers
+    }
+
+    #[inline]
+    /// Returns true if request heads are parsed in strict mode
+    pub fn strict_parsing(&self) -> bool {
+        self.0.strict_parsing
+    }
+
+    #[inline]
+    /// Maximum number of buffered pipelined requests, 0 if pipelining is disabled
+    pub fn max_pipelined_requests(&self) -> usize {
+        self.0.max_pipelined_requests
+    }
+
+    #[inline]
+    /// Returns true if services could send `103 Early Hints` responses
+    pub fn early_hints(&self) -> bool {
+        self.0.early_hints
+    }
+
+    #[inline]
+    /// Maximum number of requests per connection, 0 if unlimited
+    pub fn max_requests(&self) -> usize {
+        self.0.max_requests
+    }
+
+    /// Connection lifetime expire time, for a connection created now
+    pub fn max_lifetime_expire(&self) -> Option<Instant> {
+        let lifetime = self.0.max_lifetime;
+        if lifetime != 0 {
+            Some(self.0.timer.now() + Duration::from_secs(lifetime))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// Socket options for accepted tcp connections
+    pub(crate) fn tcp_options(&self) -> &TcpOptions {
+        &self.0.tcp
+    }
+
+    #[inline]
+    /// HTTP/2 connection settings
+    pub(crate) fn h2_options(&self) -> &H2Options {
+        &self.0.h2
+    }
+
+    #[inline]
+    /// Returns true if `Date` header is added to responses
+    pub fn date_header(&self) -> bool {
+        self.0.date_header
+    }
+
+    #[inline]
+    /// Initial capacity of connection read and write buffers
+    pub fn buffer_capacity(&self) -> usize {
+        self.0.buffer_capacity
+    }
+
+    #[inline]
+    /// Buffers grown over this capacity are released once drained, 0 if not limited
+    pub fn max_buffer_capacity(&self) -> usize {
+        self.0.max_buffer_capacity
+    }
+
     #[inline]
     /// Keep alive duration if configured.
     pub fn keep_alive(&self) -> Option<Duration> {
@@ -142,6 +446,16 @@ impl ServiceConfig {
         }
     }
 
+    /// Client read timeout for subsequent requests and request payloads.
+    pub fn client_read_timer_expire(&self) -> Option<Instant> {
+        let delay = self.0.client_read_timeout;
+        if delay != 0 {
+            Some(self.0.timer.now() + Duration::from_millis(delay))
+        } else {
+            None
+        }
+    }
+
     /// Client disconnect timer
     pub fn client_disconnect_timer(&self) -> Option<Instant> {
         let delay = self.0.client_disconnect;
@@ -187,7 +501,20 @@ impl ServiceConfig {
         dst.extend_from_slice(&buf);
     }
 
-    pub(crate) fn set_date_header(&self, dst: &mut BytesMut) {
+    /// Response for protocol level error, i.e. malformed request or slow request
+    pub(crate) fn error_response(&self, status: StatusCode) -> Response {
+        match self.0.error_response {
+            Some(ref f) => f(status),
+            None => Response::new(status),
+        }
+    }
+
+    /// Limit of idle keep-alive connections, if set
+    pub(crate) fn idle_limit(&self) -> Option<&IdleLimit> {
+        self.0.idle.as_ref()
+    }
+
+    pub(crate) fn write_date_header(&self, dst: &mut BytesMut) {
         self.0
             .timer
             .set_date(|date| dst.extend_from_slice(&date.bytes));
@@ -229,12 +556,16 @@ struct DateService(Rc<DateServiceInner>);
 
 struct DateServiceInner {
     current: UnsafeCell<Option<(Date, Instant)>>,
+    interval: Duration,
+    started: Cell<bool>,
 }
 
 impl DateServiceInner {
-    fn new() -> Self {
+    fn new(interval: Duration) -> Self {
         DateServiceInner {
             current: UnsafeCell::new(None),
+            interval,
+            started: Cell::new(false),
         }
     }
 
@@ -250,20 +581,30 @@ impl DateServiceInner {
 }
 
 impl DateService {
-    fn new() -> Self {
-        DateService(Rc::new(DateServiceInner::new()))
+    fn new(interval: Duration) -> Self {
+        DateService(Rc::new(DateServiceInner::new(interval)))
     }
 
     fn check_date(&self) {
         if unsafe { (&*self.0.current.get()).is_none() } {
             self.0.update();
 
-            // periodic date update
-            let s = self.clone();
-            actix_rt::spawn(delay_for(Duration::from_millis(500)).then(move |_| {
-                s.0.reset();
-                future::ready(())
-            }));
+            // periodic date update, single task per service.
+            // task stops once service is dropped
+            if !self.0.started.replace(true) {
+                let inner = Rc::downgrade(&self.0);
+                let mut interval =
+                    interval_at(Instant::now() + self.0.interval, self.0.interval);
+                actix_rt::spawn(async move {
+                    loop {
+                        interval.tick().await;
+                        match inner.upgrade() {
+                            Some(inner) => inner.reset(),
+                            None => break,
+                        }
+                    }
+                });
+            }
         }
     }
 
@@ -1,25 +1,35 @@
+use std::cmp;
 use std::future::Future;
 use std::io::{self, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use actix_threadpool::{run, CpuFuture};
+use actix_threadpool::{run, BlockingError, CpuFuture};
+#[cfg(feature = "brotli")]
 use brotli2::write::BrotliDecoder;
 use bytes::Bytes;
 use flate2::write::{GzDecoder, ZlibDecoder};
 use futures_core::{ready, Stream};
 
-use super::Writer;
+use super::{LimitExceeded, Writer};
 use crate::error::PayloadError;
 use crate::http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING};
 
 const INPLACE: usize = 2049;
 
+/// Decoded size from which compression ratio is checked, small payloads
+/// legitimately have high ratios
+const RATIO_THRESHOLD: u64 = 65_536;
+
 pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
     eof: bool,
     fut: Option<CpuFuture<(Option<Bytes>, ContentDecoder), io::Error>>,
+    limit: Option<u64>,
+    ratio: Option<u64>,
+    decoded: u64,
+    encoded: u64,
 }
 
 impl<S> Decoder<S>
@@ -30,6 +40,7 @@ where
     #[inline]
     pub fn new(stream: S, encoding: ContentEncoding) -> Decoder<S> {
         let decoder = match encoding {
+            #[cfg(feature = "brotli")]
             ContentEncoding::Br => Some(ContentDecoder::Br(Box::new(
                 BrotliDecoder::new(Writer::new()),
             ))),
@@ -46,6 +57,76 @@ where
             stream,
             fut: None,
             eof: false,
+            limit: None,
+            ratio: None,
+            decoded: 0,
+            encoded: 0,
+        }
+    }
+
+    /// Set max size of decoded payload, stream fails with
+    /// `PayloadError::Overflow` error once decoded payload exceeds it.
+    ///
+    /// Limit protects from decompression bombs, payload without content
+    /// encoding is not limited.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Set max ratio of decoded and encoded size, stream fails with
+    /// `PayloadError::Overflow` error once decoded payload is `ratio` times
+    /// larger than encoded payload.
+    ///
+    /// Ratio is checked for payloads that are decoded to more than 64KB.
+    pub fn ratio(mut self, ratio: u64) -> Self {
+        self.ratio = Some(ratio);
+        self
+    }
+
+    /// Max size of data decoder may write before stream fails, decoder
+    /// aborts once it is exceeded, so a single highly compressed chunk
+    /// is never fully decoded into memory.
+    fn remaining(&self) -> Option<u64> {
+        let ratio = self
+            .ratio
+            .map(|ratio| cmp::max(RATIO_THRESHOLD, self.encoded.saturating_mul(ratio)));
+        let max = match (self.limit, ratio) {
+            (Some(limit), Some(ratio)) => Some(cmp::min(limit, ratio)),
+            (limit, ratio) => limit.or(ratio),
+        };
+        max.map(|max| max.saturating_sub(self.decoded))
+    }
+
+    /// Fail stream with decoder error, decoder writes beyond limits are
+    /// reported as `PayloadError::Overflow`
+    fn fail(&mut self, err: io::Error) -> Poll<Option<Result<Bytes, PayloadError>>> {
+        self.eof = true;
+        self.decoder = None;
+        if err.get_ref().map_or(false, |e| e.is::<LimitExceeded>()) {
+            Poll::Ready(Some(Err(PayloadError::Overflow)))
+        } else {
+            Poll::Ready(Some(Err(err.into())))
+        }
+    }
+
+    /// Account decoded chunk against limits
+    fn check_limit(
+        &mut self,
+        chunk: Bytes,
+    ) -> Poll<Option<Result<Bytes, PayloadError>>> {
+        self.decoded += chunk.len() as u64;
+        let overflow = self.limit.map_or(false, |limit| self.decoded > limit)
+            || self.ratio.map_or(false, |ratio| {
+                self.decoded > RATIO_THRESHOLD
+                    && self.decoded > self.encoded.saturating_mul(ratio)
+            });
+        if overflow {
+            self.eof = true;
+            self.decoder = None;
+            Poll::Ready(Some(Err(PayloadError::Overflow)))
+        } else {
+            Poll::Ready(Some(Ok(chunk)))
         }
     }
 
@@ -81,12 +162,22 @@ where
             if let Some(ref mut fut) = self.fut {
                 let (chunk, decoder) = match ready!(Pin::new(fut).poll(cx)) {
                     Ok(item) => item,
-                    Err(e) => return Poll::Ready(Some(Err(e.into()))),
+                    Err(e) => {
+                        self.fut.take();
+                        let e = match e {
+                            BlockingError::Error(e) => e,
+                            BlockingError::Canceled => io::Error::new(
+                                io::ErrorKind::Other,
+                                "Thread pool is gone",
+                            ),
+                        };
+                        return self.fail(e);
+                    }
                 };
                 self.decoder = Some(decoder);
                 self.fut.take();
                 if let Some(chunk) = chunk {
-                    return Poll::Ready(Some(Ok(chunk)));
+                    return self.check_limit(chunk);
                 }
             }
 
@@ -97,12 +188,17 @@ where
             match Pin::new(&mut self.stream).poll_next(cx) {
                 Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
                 Poll::Ready(Some(Ok(chunk))) => {
+                    self.encoded += chunk.len() as u64;
                     if let Some(mut decoder) = self.decoder.take() {
+                        decoder.set_limit(self.remaining());
                         if chunk.len() < INPLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk = match decoder.feed_data(chunk) {
+                                Ok(chunk) => chunk,
+                                Err(e) => return self.fail(e),
+                            };
                             self.decoder = Some(decoder);
                             if let Some(chunk) = chunk {
-                                return Poll::Ready(Some(Ok(chunk)));
+                                return self.check_limit(chunk);
                             }
                         } else {
                             self.fut = Some(run(move || {
@@ -118,10 +214,11 @@ where
                 Poll::Ready(None) => {
                     self.eof = true;
                     return if let Some(mut decoder) = self.decoder.take() {
+                        decoder.set_limit(self.remaining());
                         match decoder.feed_eof() {
-                            Ok(Some(res)) => Poll::Ready(Some(Ok(res))),
+                            Ok(Some(res)) => self.check_limit(res),
                             Ok(None) => Poll::Ready(None),
-                            Err(err) => Poll::Ready(Some(Err(err.into()))),
+                            Err(err) => self.fail(err),
                         }
                     } else {
                         Poll::Ready(None)
@@ -137,12 +234,24 @@ where
 enum ContentDecoder {
     Deflate(Box<ZlibDecoder<Writer>>),
     Gzip(Box<GzDecoder<Writer>>),
+    #[cfg(feature = "brotli")]
     Br(Box<BrotliDecoder<Writer>>),
 }
 
 impl ContentDecoder {
+    /// Set max size of data written by next `feed_data()` or `feed_eof()`
+    fn set_limit(&mut self, limit: Option<u64>) {
+        match self {
+            #[cfg(feature = "brotli")]
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut().limit = limit,
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut().limit = limit,
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut().limit = limit,
+        }
+    }
+
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
+            #[cfg(feature = "brotli")]
             ContentDecoder::Br(ref mut decoder) => match decoder.flush() {
                 Ok(()) => {
                     let b = decoder.get_mut().take();
@@ -181,6 +290,7 @@ impl ContentDecoder {
 
     fn feed_data(&mut self, data: Bytes) -> io::Result<Option<Bytes>> {
         match self {
+            #[cfg(feature = "brotli")]
             ContentDecoder::Br(ref mut decoder) => match decoder.write_all(&data) {
                 Ok(_) => {
                     decoder.flush()?;
@@ -220,3 +330,31 @@ impl ContentDecoder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn test_decoder_limit() {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(&vec![0; 16 * 1024 * 1024]).unwrap();
+        let data = Bytes::from(enc.finish().unwrap());
+
+        let mut decoder = ContentDecoder::Gzip(Box::new(GzDecoder::new(Writer::new())));
+        decoder.set_limit(Some(65_536));
+        let err = decoder.feed_data(data.clone()).unwrap_err();
+        assert!(err.get_ref().unwrap().is::<LimitExceeded>());
+        if let ContentDecoder::Gzip(ref mut decoder) = decoder {
+            assert!(decoder.get_mut().buf.len() <= 65_536);
+        }
+
+        let mut decoder = ContentDecoder::Gzip(Box::new(GzDecoder::new(Writer::new())));
+        decoder.set_limit(None);
+        let chunk = decoder.feed_data(data).unwrap().unwrap();
+        assert_eq!(chunk.len(), 16 * 1024 * 1024);
+    }
+}
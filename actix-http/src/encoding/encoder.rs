@@ -5,6 +5,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use actix_threadpool::{run, CpuFuture};
+#[cfg(feature = "brotli")]
 use brotli2::write::BrotliEncoder;
 use bytes::Bytes;
 use flate2::write::{GzEncoder, ZlibEncoder};
@@ -19,6 +20,10 @@ use super::Writer;
 
 const INPLACE: usize = 1024;
 
+/// Default brotli quality
+#[cfg(feature = "brotli")]
+const BROTLI_QUALITY: u32 = 3;
+
 pub struct Encoder<B> {
     eof: bool,
     body: EncoderBody<B>,
@@ -31,6 +36,19 @@ impl<B: MessageBody> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        Encoder::response_with_level(encoding, None, head, body)
+    }
+
+    /// Same as `response()`, but uses specified compression level.
+    ///
+    /// Level is in range 0-9 for gzip and deflate, and 0-11 for brotli.
+    /// Default level is used if `level` is `None`.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
         let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
@@ -56,7 +74,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
+            if let Some(enc) = ContentEncoder::encoder(encoding, level) {
                 update_head(encoding, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
@@ -174,23 +192,30 @@ fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
 enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
+    #[cfg(feature = "brotli")]
     Br(BrotliEncoder<Writer>),
 }
 
 impl ContentEncoder {
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
+        let compression = level
+            .map(|level| flate2::Compression::new(level.min(9)))
+            .unwrap_or_else(flate2::Compression::fast);
+
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                compression,
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                compression,
+            ))),
+            #[cfg(feature = "brotli")]
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.map_or(BROTLI_QUALITY, |level| level.min(11)),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
-            }
             _ => None,
         }
     }
@@ -198,6 +223,7 @@ impl ContentEncoder {
     #[inline]
     pub(crate) fn take(&mut self) -> Bytes {
         match *self {
+            #[cfg(feature = "brotli")]
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
@@ -206,6 +232,7 @@ impl ContentEncoder {
 
     fn finish(self) -> Result<Bytes, io::Error> {
         match self {
+            #[cfg(feature = "brotli")]
             ContentEncoder::Br(encoder) => match encoder.finish() {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
@@ -223,6 +250,7 @@ impl ContentEncoder {
 
     fn write(&mut self, data: &[u8]) -> Result<(), io::Error> {
         match *self {
+            #[cfg(feature = "brotli")]
             ContentEncoder::Br(ref mut encoder) => match encoder.write_all(data) {
                 Ok(_) => Ok(()),
                 Err(err) => {
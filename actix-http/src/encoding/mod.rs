@@ -2,6 +2,7 @@
 use std::io;
 
 use bytes::{Bytes, BytesMut};
+use derive_more::Display;
 
 mod decoder;
 mod encoder;
@@ -11,12 +12,23 @@ pub use self::encoder::Encoder;
 
 pub(self) struct Writer {
     buf: BytesMut,
+    /// Max size of buffered data, writes beyond it fail with
+    /// `LimitExceeded` error
+    limit: Option<u64>,
 }
 
+/// Error of `Writer` write beyond its limit
+#[derive(Debug, Display)]
+#[display(fmt = "Written data exceeds limit")]
+struct LimitExceeded;
+
+impl std::error::Error for LimitExceeded {}
+
 impl Writer {
     fn new() -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit: None,
         }
     }
 
@@ -27,6 +39,11 @@ impl Writer {
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(limit) = self.limit {
+            if (self.buf.len() + buf.len()) as u64 > limit {
+                return Err(io::Error::new(io::ErrorKind::Other, LimitExceeded));
+            }
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
@@ -279,10 +279,14 @@ pub enum ParseError {
     Utf8(Utf8Error),
 }
 
-/// Return `BadRequest` for `ParseError`
+/// Return `BadRequest` for `ParseError`, `RequestHeaderFieldsTooLarge` for
+/// too large message head
 impl ResponseError for ParseError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match self {
+            ParseError::TooLarge => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
@@ -979,6 +983,9 @@ mod tests {
         let resp: Response = ParseError::Incomplete.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
+        let resp: Response = ParseError::TooLarge.error_response();
+        assert_eq!(resp.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
         let err: HttpError = StatusCode::from_u16(10000).err().unwrap().into();
         let resp: Response = err.error_response();
         assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -1,5 +1,6 @@
 use std::any::{Any, TypeId};
 use std::fmt;
+use std::rc::Rc;
 
 use fxhash::FxHashMap;
 
@@ -70,6 +71,30 @@ impl fmt::Debug for Extensions {
     }
 }
 
+#[derive(Clone, Debug)]
+/// Connection level extensions shared by all requests of a connection.
+///
+/// Extensions are filled once per connection by on-connect callback,
+/// see `HttpServiceBuilder::on_connect_ext()`, and stored to the
+/// extensions of every request of the connection.
+pub struct ConnectionExtensions(Rc<Extensions>);
+
+impl ConnectionExtensions {
+    pub(crate) fn new(ext: Extensions) -> Self {
+        ConnectionExtensions(Rc::new(ext))
+    }
+
+    /// Check if container contains entry
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.0.contains::<T>()
+    }
+
+    /// Get a reference to a type inserted by on-connect callback.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.0.get::<T>()
+    }
+}
+
 #[test]
 fn test_extensions() {
     #[derive(Debug, PartialEq)]
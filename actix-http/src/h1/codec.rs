@@ -1,6 +1,7 @@
 use std::{fmt, io};
 
 use actix_codec::{Decoder, Encoder};
+use actix_rt::time::Instant;
 use bitflags::bitflags;
 use bytes::BytesMut;
 use http::{Method, Version};
@@ -11,15 +12,18 @@ use super::{Message, MessageType};
 use crate::body::BodySize;
 use crate::config::ServiceConfig;
 use crate::error::ParseError;
+use crate::header::HeaderMap;
 use crate::message::ConnectionType;
 use crate::request::Request;
 use crate::response::Response;
+use crate::trailers::Trailers;
 
 bitflags! {
     struct Flags: u8 {
         const HEAD              = 0b0000_0001;
         const KEEPALIVE_ENABLED = 0b0000_0010;
         const STREAM            = 0b0000_0100;
+        const CONNECT           = 0b0000_1000;
     }
 }
 
@@ -28,12 +32,16 @@ pub struct Codec {
     config: ServiceConfig,
     decoder: decoder::MessageDecoder<Request>,
     payload: Option<PayloadDecoder>,
+    trailers: Option<HeaderMap>,
     version: Version,
     ctype: ConnectionType,
+    requests: usize,
+    expire: Option<Instant>,
 
     // encoder part
     flags: Flags,
     encoder: encoder::MessageEncoder<Response<()>>,
+    res_trailers: Option<Trailers>,
 }
 
 impl Default for Codec {
@@ -58,14 +66,22 @@ impl Codec {
         } else {
             Flags::empty()
         };
+        let decoder =
+            decoder::MessageDecoder::new(config.max_header_size(), config.max_headers())
+                .strict(config.strict_parsing());
+        let expire = config.max_lifetime_expire();
         Codec {
             config,
             flags,
-            decoder: decoder::MessageDecoder::default(),
+            decoder,
             payload: None,
+            trailers: None,
             version: Version::HTTP_11,
             ctype: ConnectionType::Close,
+            requests: 0,
+            expire,
             encoder: encoder::MessageEncoder::default(),
+            res_trailers: None,
         }
     }
 
@@ -103,6 +119,24 @@ impl Codec {
     pub fn config(&self) -> &ServiceConfig {
         &self.config
     }
+
+    /// Take trailer fields of last request's chunked payload
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
+    }
+
+    /// Check if connection served allowed number of requests or outlived
+    /// its maximum lifetime
+    fn exhausted(&self) -> bool {
+        let max = self.config.max_requests();
+        if max != 0 && self.requests >= max {
+            return true;
+        }
+        match self.expire {
+            Some(expire) => self.config.now() >= expire,
+            None => false,
+        }
+    }
 }
 
 impl Decoder for Codec {
@@ -114,7 +148,8 @@ impl Decoder for Codec {
             Ok(match self.payload.as_mut().unwrap().decode(src)? {
                 Some(PayloadItem::Chunk(chunk)) => Some(Message::Chunk(Some(chunk))),
                 Some(PayloadItem::Eof) => {
-                    self.payload.take();
+                    self.trailers =
+                        self.payload.take().and_then(|mut pl| pl.take_trailers());
                     Some(Message::Chunk(None))
                 }
                 None => None,
@@ -122,6 +157,8 @@ impl Decoder for Codec {
         } else if let Some((req, payload)) = self.decoder.decode(src)? {
             let head = req.head();
             self.flags.set(Flags::HEAD, head.method == Method::HEAD);
+            self.flags
+                .set(Flags::CONNECT, head.method == Method::CONNECT);
             self.version = head.version;
             self.ctype = head.connection_type();
             if self.ctype == ConnectionType::KeepAlive
@@ -129,6 +166,10 @@ impl Decoder for Codec {
             {
                 self.ctype = ConnectionType::Close
             }
+            self.requests += 1;
+            if self.ctype == ConnectionType::KeepAlive && self.exhausted() {
+                self.ctype = ConnectionType::Close
+            }
             match payload {
                 PayloadType::None => self.payload = None,
                 PayloadType::Payload(pl) => self.payload = Some(pl),
@@ -169,6 +210,23 @@ impl Encoder for Codec {
                     self.ctype
                 };
 
+                // successful response to CONNECT turns connection into a tunnel,
+                // it must contain neither content-length nor transfer-encoding
+                let length = if self.flags.contains(Flags::CONNECT)
+                    && res.status().is_success()
+                {
+                    res.head_mut().no_chunking(true);
+                    match length {
+                        BodySize::Empty => BodySize::None,
+                        length => length,
+                    }
+                } else {
+                    length
+                };
+
+                // trailers are sent after chunked body
+                self.res_trailers = res.extensions().get::<Trailers>().cloned();
+
                 // encode message
                 self.encoder.encode(
                     dst,
@@ -186,7 +244,11 @@ impl Encoder for Codec {
                 self.encoder.encode_chunk(bytes.as_ref(), dst)?;
             }
             Message::Chunk(None) => {
-                self.encoder.encode_eof(dst)?;
+                if let Some(trailers) = self.res_trailers.take() {
+                    self.encoder.encode_trailers(&trailers, dst)?;
+                } else {
+                    self.encoder.encode_eof(dst)?;
+                }
             }
         }
         Ok(())
@@ -237,4 +299,46 @@ mod tests {
         assert_eq!(*req.method(), Method::POST);
         assert!(req.chunked().unwrap());
     }
+
+    #[actix_rt::test]
+    async fn test_connect_tunnel() {
+        let mut codec = Codec::default();
+
+        let mut buf = BytesMut::from("CONNECT example.com:443 HTTP/1.1\r\n\r\nhello");
+        let item = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(*item.message().method(), Method::CONNECT);
+        assert_eq!(codec.message_type(), MessageType::Stream);
+        let msg = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.chunk().as_ref(), b"hello");
+
+        let mut dst = BytesMut::new();
+        codec
+            .encode(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Stream)),
+                &mut dst,
+            )
+            .unwrap();
+        codec
+            .encode(Message::Chunk(Some("world".into())), &mut dst)
+            .unwrap();
+        let data = String::from_utf8(dst.to_vec()).unwrap();
+        assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!data.contains("transfer-encoding"));
+        assert!(!data.contains("content-length"));
+        assert!(data.ends_with("\r\n\r\nworld"));
+    }
+
+    #[test]
+    fn test_max_requests() {
+        let mut config = ServiceConfig::default();
+        config.set_max_requests(2);
+        let mut codec = Codec::new(config);
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\n\r\nGET /test HTTP/1.1\r\n\r\n");
+        codec.decode(&mut buf).unwrap().unwrap();
+        assert!(codec.keepalive());
+        codec.decode(&mut buf).unwrap().unwrap();
+        assert!(!codec.keepalive());
+    }
 }
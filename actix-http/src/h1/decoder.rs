@@ -15,12 +15,18 @@ use crate::error::ParseError;
 use crate::header::HeaderMap;
 use crate::message::{ConnectionType, ResponseHead};
 use crate::request::Request;
+use crate::trailers;
 
-const MAX_BUFFER_SIZE: usize = 131_072;
-const MAX_HEADERS: usize = 96;
+pub(crate) const MAX_BUFFER_SIZE: usize = 131_072;
+pub(crate) const MAX_HEADERS: usize = 96;
 
 /// Incoming messagd decoder
-pub(crate) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(crate) struct MessageDecoder<T: MessageType> {
+    max_size: usize,
+    max_headers: usize,
+    strict: bool,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -32,7 +38,25 @@ pub(crate) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(MAX_BUFFER_SIZE, MAX_HEADERS)
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Create decoder with limits for message head size and headers count
+    pub(crate) fn new(max_size: usize, max_headers: usize) -> Self {
+        MessageDecoder {
+            max_size,
+            max_headers,
+            strict: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Enable strict parsing of message heads
+    pub(crate) fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
     }
 }
 
@@ -41,7 +65,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, self.max_size, self.max_headers, self.strict)
     }
 }
 
@@ -58,7 +82,12 @@ pub(crate) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+        strict: bool,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
@@ -67,6 +96,7 @@ pub(crate) trait MessageType: Sized {
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade = false;
+        let mut h2c_upgrade = false;
         let mut expect = false;
         let mut chunked = false;
         let mut content_length = None;
@@ -131,6 +161,12 @@ pub(crate) trait MessageType: Sized {
                         if let Ok(val) = value.to_str().map(|val| val.trim()) {
                             if val.eq_ignore_ascii_case("websocket") {
                                 content_length = None;
+                            } else if val.eq_ignore_ascii_case("h2c") {
+                                // h2c upgrade is not a raw upgrade stream, request
+                                // is either upgraded by dispatcher or served over
+                                // HTTP/1.1 as if upgrade is absent (RFC 7540, 3.2)
+                                has_upgrade = false;
+                                h2c_upgrade = true;
                             }
                         }
                     }
@@ -146,6 +182,9 @@ pub(crate) trait MessageType: Sized {
                 headers.append(name, value);
             }
         }
+        if h2c_upgrade && ka == Some(ConnectionType::Upgrade) {
+            ka = None;
+        }
         self.set_connection_type(ka);
         if expect {
             self.set_expect()
@@ -186,19 +225,45 @@ impl MessageType for Request {
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+        strict: bool,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
             unsafe { MaybeUninit::uninit().assume_init() };
+        let mut headers_heap;
+        let headers = if max_headers <= MAX_HEADERS {
+            &mut headers[..max_headers]
+        } else {
+            headers_heap = vec![HeaderIndex::EMPTY; max_headers];
+            &mut headers_heap[..]
+        };
 
         let (len, method, uri, ver, h_len) = {
             let mut parsed: [httparse::Header<'_>; MAX_HEADERS] =
                 unsafe { MaybeUninit::uninit().assume_init() };
+            let mut parsed_heap;
+            let parsed = if max_headers <= MAX_HEADERS {
+                &mut parsed[..max_headers]
+            } else {
+                parsed_heap = vec![httparse::EMPTY_HEADER; max_headers];
+                &mut parsed_heap[..]
+            };
 
-            let mut req = httparse::Request::new(&mut parsed);
+            let mut req = httparse::Request::new(parsed);
             match req.parse(src)? {
+                httparse::Status::Complete(len) if len > max_size => {
+                    trace!("Request head exceeds {} bytes, closing", max_size);
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Complete(len) => {
+                    if strict {
+                        check_strict_head(&src[..len])?;
+                    }
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
                     let uri = Uri::try_from(req.path.unwrap())?;
@@ -207,10 +272,14 @@ impl MessageType for Request {
                     } else {
                         Version::HTTP_10
                     };
-                    HeaderIndex::record(src, req.headers, &mut headers);
+                    HeaderIndex::record(src, req.headers, headers);
 
                     (len, method, uri, version, req.headers.len())
                 }
+                httparse::Status::Partial if src.len() >= max_size => {
+                    trace!("Request head exceeds {} bytes, closing", max_size);
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Partial => return Ok(None),
             }
         };
@@ -219,6 +288,9 @@ impl MessageType for Request {
 
         // convert headers
         let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        if strict {
+            check_strict_headers(&msg.head().headers)?;
+        }
 
         // payload decoder
         let decoder = match length {
@@ -262,7 +334,15 @@ impl MessageType for ResponseHead {
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+        _: bool,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        // response heads are parsed by client, buffer on stack is always enough
+        let max_headers = max_headers.min(MAX_HEADERS);
+
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -272,8 +352,11 @@ impl MessageType for ResponseHead {
             let mut parsed: [httparse::Header<'_>; MAX_HEADERS] =
                 unsafe { MaybeUninit::uninit().assume_init() };
 
-            let mut res = httparse::Response::new(&mut parsed);
+            let mut res = httparse::Response::new(&mut parsed[..max_headers]);
             match res.parse(src)? {
+                httparse::Status::Complete(len) if len > max_size => {
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Complete(len) => {
                     let version = if res.version.unwrap() == 1 {
                         Version::HTTP_11
@@ -286,6 +369,9 @@ impl MessageType for ResponseHead {
 
                     (len, version, status, res.headers.len())
                 }
+                httparse::Status::Partial if src.len() >= max_size => {
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Partial => return Ok(None),
             }
         };
@@ -319,6 +405,50 @@ impl MessageType for ResponseHead {
     }
 }
 
+/// Reject bare LF line endings and obsolete line folding.
+///
+/// Header names are always validated by parser.
+fn check_strict_head(head: &[u8]) -> Result<(), ParseError> {
+    let mut prev = 0;
+    for (idx, &b) in head.iter().enumerate() {
+        if b == b'\n' {
+            if prev != b'\r' {
+                debug!("Bare LF in message head");
+                return Err(ParseError::Header);
+            }
+            if let Some(b' ') | Some(b'\t') = head.get(idx + 1) {
+                debug!("Obsolete line folding in message head");
+                return Err(ParseError::Header);
+            }
+        }
+        prev = b;
+    }
+    Ok(())
+}
+
+/// Reject ambiguous message length, RFC 7230, 3.3.3
+fn check_strict_headers(headers: &HeaderMap) -> Result<(), ParseError> {
+    let mut te = headers.get_all(header::TRANSFER_ENCODING);
+    if let Some(value) = te.next() {
+        if headers.contains_key(header::CONTENT_LENGTH) {
+            debug!("Both Transfer-Encoding and Content-Length are set");
+            return Err(ParseError::Header);
+        }
+        let chunked = value
+            .to_str()
+            .map(|s| s.trim().eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false);
+        if !chunked || te.next().is_some() {
+            debug!("Unsupported Transfer-Encoding: {:?}", value);
+            return Err(ParseError::Header);
+        }
+    } else if headers.get_all(header::CONTENT_LENGTH).count() > 1 {
+        debug!("Multiple Content-Length headers");
+        return Err(ParseError::Header);
+    }
+    Ok(())
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct HeaderIndex {
     pub(crate) name: (usize, usize),
@@ -326,6 +456,11 @@ pub(crate) struct HeaderIndex {
 }
 
 impl HeaderIndex {
+    const EMPTY: HeaderIndex = HeaderIndex {
+        name: (0, 0),
+        value: (0, 0),
+    };
+
     pub(crate) fn record(
         bytes: &[u8],
         headers: &[httparse::Header<'_>],
@@ -354,26 +489,37 @@ pub enum PayloadItem {
 ///
 /// If a message body does not include a Transfer-Encoding, it *should*
 /// include a Content-Length header.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct PayloadDecoder {
     kind: Kind,
+    trailers: Option<HeaderMap>,
 }
 
 impl PayloadDecoder {
     pub fn length(x: u64) -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Length(x),
+            trailers: None,
         }
     }
 
     pub fn chunked() -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Chunked(ChunkedState::Size, 0),
+            trailers: None,
         }
     }
 
     pub fn eof() -> PayloadDecoder {
-        PayloadDecoder { kind: Kind::Eof }
+        PayloadDecoder {
+            kind: Kind::Eof,
+            trailers: None,
+        }
+    }
+
+    /// Take trailer fields received after last chunk of chunked payload
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
     }
 }
 
@@ -443,6 +589,22 @@ impl Decoder for PayloadDecoder {
             }
             Kind::Chunked(ref mut state, ref mut size) => {
                 loop {
+                    // trailer fields follow last chunk
+                    if *state == ChunkedState::EndCr
+                        && !src.is_empty()
+                        && src[0] != b'\r'
+                    {
+                        return match parse_trailers(src)? {
+                            Some(trailers) => {
+                                trace!("End of chunked stream with trailers");
+                                self.trailers = Some(trailers);
+                                *state = ChunkedState::End;
+                                Ok(Some(PayloadItem::Eof))
+                            }
+                            None => Ok(None),
+                        };
+                    }
+
                     let mut buf = None;
                     // advances the chunked state
                     *state = match state.step(src, size, &mut buf) {
@@ -473,6 +635,42 @@ impl Decoder for PayloadDecoder {
     }
 }
 
+/// Parse trailer fields, returns `None` if trailer section is incomplete
+///
+/// Fields that are not allowed in trailers are dropped.
+fn parse_trailers(src: &mut BytesMut) -> Result<Option<HeaderMap>, io::Error> {
+    let invalid =
+        || io::Error::new(io::ErrorKind::InvalidInput, "Invalid chunked trailer");
+
+    let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let (len, trailers) = match httparse::parse_headers(src, &mut parsed) {
+        Ok(httparse::Status::Complete((len, headers))) => {
+            let mut trailers = HeaderMap::with_capacity(headers.len());
+            for h in headers {
+                let name =
+                    HeaderName::from_bytes(h.name.as_bytes()).map_err(|_| invalid())?;
+                let value = HeaderValue::from_bytes(h.value).map_err(|_| invalid())?;
+                if !trailers::is_forbidden(&name) {
+                    trailers.append(name, value);
+                }
+            }
+            (len, trailers)
+        }
+        Ok(httparse::Status::Partial) => {
+            if src.len() >= MAX_BUFFER_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Chunked trailer is too large",
+                ));
+            }
+            return Ok(None);
+        }
+        Err(_) => return Err(invalid()),
+    };
+    src.advance(len);
+    Ok(Some(trailers))
+}
+
 macro_rules! byte (
     ($rdr:ident) => ({
         if $rdr.len() > 0 {
@@ -729,6 +927,74 @@ mod tests {
         assert_eq!(req.path(), "/test");
     }
 
+    #[test]
+    fn test_parse_head_limits() {
+        let mut reader = MessageDecoder::<Request>::new(64, 2);
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        let mut buf = BytesMut::from(
+            format!("GET /test HTTP/1.1\r\nA: {}", "a".repeat(64)).as_str(),
+        );
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        let mut buf = BytesMut::from(
+            format!("GET /test HTTP/1.1\r\nA: {}\r\n\r\n", "a".repeat(40)).as_str(),
+        );
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        // more headers than fit into stack buffer
+        let mut reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, 200);
+        let mut req = String::from("GET /test HTTP/1.1\r\n");
+        for i in 0..150 {
+            req.push_str(&format!("x-header-{}: {}\r\n", i, i));
+        }
+        req.push_str("\r\n");
+        let mut buf = BytesMut::from(req.as_str());
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 150);
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        let mut reader = MessageDecoder::<Request>::default().strict(true);
+
+        let mut buf =
+            BytesMut::from("POST /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        for req in &[
+            "POST /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\
+             content-length: 4\r\n\r\n",
+            "POST /test HTTP/1.1\r\ntransfer-encoding: gzip, chunked\r\n\r\n",
+            "POST /test HTTP/1.1\r\ncontent-length: 4\r\ncontent-length: 5\r\n\r\n",
+            "GET /test HTTP/1.1\r\nx-header: a\r\n b\r\n\r\n",
+            "GET /test HTTP/1.1\nx-header: a\n\n",
+        ] {
+            let mut buf = BytesMut::from(*req);
+            assert!(reader.decode(&mut buf).is_err(), "{:?}", req);
+        }
+
+        // lenient parser accepts bare LF
+        let mut reader = MessageDecoder::<Request>::default();
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\nx-header: a\n\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+    }
+
     #[test]
     fn test_parse_post() {
         let mut buf = BytesMut::from("POST /test2 HTTP/1.0\r\n\r\n");
@@ -955,6 +1221,22 @@ mod tests {
         assert_eq!(req.head().connection_type(), ConnectionType::Upgrade);
     }
 
+    #[test]
+    fn test_conn_upgrade_h2c() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             upgrade: h2c\r\n\
+             connection: upgrade\r\n\
+             http2-settings: AAMAAABkAAQAAP__\r\n\r\n",
+        );
+        let mut reader = MessageDecoder::<Request>::default();
+        let (req, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.head().connection_type(), ConnectionType::KeepAlive);
+        // connection header is preserved for h2c handshake
+        assert!(req.head().upgrade());
+        assert!(!pl.is_unhandled());
+    }
+
     #[test]
     fn test_conn_upgrade_connect_method() {
         let mut buf = BytesMut::from(
@@ -1210,6 +1492,38 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\nx-checksum: 1234\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(b"x-rows: 1\r\ncontent-length: 10\r\nhost: example.com\r\n\r\n");
+        buf.extend(b"GET /next HTTP/1.1\r\n\r\n");
+        let msg = pl.decode(&mut buf).unwrap().unwrap();
+        assert!(msg.eof());
+        let trailers = pl.take_trailers().unwrap();
+        assert_eq!(trailers.get("x-checksum").unwrap(), "1234");
+        assert_eq!(trailers.get("x-rows").unwrap(), "1");
+        assert!(trailers.get(header::CONTENT_LENGTH).is_none());
+        assert!(trailers.get(header::HOST).is_none());
+        assert_eq!(&buf[..], b"GET /next HTTP/1.1\r\n\r\n");
+
+        let mut pl = PayloadDecoder::chunked();
+        let mut buf = BytesMut::from(&"0\r\nbad trailer\r\n\r\n"[..]);
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from(&"HTTP/1.0 200 Ok\r\n\r\ntest data"[..]);
@@ -0,0 +1,123 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// Handle for detecting client disconnect while request is handled.
+///
+/// Handle is available in request extensions of HTTP/1 requests. Handle
+/// is notified if connection is closed by peer, including half-closed
+/// connections, or connection is dropped before response head is ready.
+/// Once response head is ready handle is not notified anymore.
+///
+/// ```rust
+/// use actix_http::h1::Disconnect;
+/// use actix_http::{HttpMessage, Request, Response};
+///
+/// async fn index(req: Request) -> Result<Response, ()> {
+///     if let Some(disconnect) = req.extensions().get::<Disconnect>() {
+///         if disconnect.is_disconnected() {
+///             return Err(());
+///         }
+///     }
+///     Ok(Response::Ok().finish())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Disconnect(Rc<Inner>);
+
+struct Inner {
+    disconnected: Cell<bool>,
+    tasks: RefCell<Vec<Waker>>,
+}
+
+impl Disconnect {
+    pub(crate) fn new() -> Self {
+        Disconnect(Rc::new(Inner {
+            disconnected: Cell::new(false),
+            tasks: RefCell::new(Vec::new()),
+        }))
+    }
+
+    /// Returns true if client is disconnected.
+    pub fn is_disconnected(&self) -> bool {
+        self.0.disconnected.get()
+    }
+
+    /// Check if client is disconnected, current task is woken up once
+    /// client disconnects otherwise.
+    pub fn poll_disconnected(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0.disconnected.get() {
+            return Poll::Ready(());
+        }
+
+        let mut tasks = self.0.tasks.borrow_mut();
+        if !tasks.iter().any(|task| task.will_wake(cx.waker())) {
+            tasks.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    fn notify(&self) {
+        self.0.disconnected.set(true);
+        for task in self.0.tasks.borrow_mut().drain(..) {
+            task.wake();
+        }
+    }
+}
+
+/// Dispatcher side of disconnect handle of in-flight request, handle is
+/// notified on drop.
+pub(crate) struct DisconnectGuard(Option<Disconnect>);
+
+impl DisconnectGuard {
+    pub(crate) fn new() -> Self {
+        DisconnectGuard(None)
+    }
+
+    /// Start tracking handle of new request.
+    pub(crate) fn set(&mut self, disconnect: Disconnect) {
+        self.0 = Some(disconnect);
+    }
+
+    /// Stop tracking handle, response is ready.
+    pub(crate) fn release(&mut self) {
+        self.0 = None;
+    }
+
+    /// Notify handle of in-flight request, if any.
+    pub(crate) fn disconnect(&mut self) {
+        if let Some(disconnect) = self.0.take() {
+            disconnect.notify();
+        }
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::poll_fn;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_disconnect() {
+        let disconnect = Disconnect::new();
+        let mut guard = DisconnectGuard::new();
+        guard.set(disconnect.clone());
+        guard.release();
+        drop(guard);
+        assert!(!disconnect.is_disconnected());
+
+        let mut guard = DisconnectGuard::new();
+        guard.set(disconnect.clone());
+        let handle = disconnect.clone();
+        actix_rt::spawn(async move { guard.disconnect() });
+        poll_fn(|cx| handle.poll_disconnected(cx)).await;
+        assert!(disconnect.is_disconnected());
+    }
+}
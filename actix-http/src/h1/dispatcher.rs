@@ -2,32 +2,38 @@ use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::{fmt, io, net};
+use std::{cmp, fmt, io, mem, net};
 
 use actix_codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed, FramedParts};
 use actix_rt::time::{delay_until, Delay, Instant};
 use actix_service::Service;
 use bitflags::bitflags;
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use log::{error, trace};
 
-use crate::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::body::{BodySize, MessageBody, ResponseBody};
 use crate::cloneable::CloneableService;
 use crate::config::ServiceConfig;
 use crate::error::{DispatchError, Error};
 use crate::error::{ParseError, PayloadError};
+use crate::h2::h2c;
 use crate::helpers::DataFactory;
+use crate::http::{StatusCode, Version};
 use crate::httpmessage::HttpMessage;
+use crate::idle::IdleToken;
 use crate::request::Request;
 use crate::response::Response;
+use crate::trailers::Trailers;
 
 use super::codec::Codec;
+use super::disconnect::{Disconnect, DisconnectGuard};
+use super::hints::EarlyHints;
 use super::payload::{Payload, PayloadSender, PayloadStatus};
 use super::{Message, MessageType};
 
 const LW_BUFFER_SIZE: usize = 4096;
-const HW_BUFFER_SIZE: usize = 32_768;
-const MAX_PIPELINED_MESSAGES: usize = 16;
+pub(crate) const HW_BUFFER_SIZE: usize = 32_768;
+pub(crate) const MAX_PIPELINED_MESSAGES: usize = 16;
 
 bitflags! {
     pub struct Flags: u8 {
@@ -38,6 +44,7 @@ bitflags! {
         const READ_DISCONNECT    = 0b0001_0000;
         const WRITE_DISCONNECT   = 0b0010_0000;
         const UPGRADE            = 0b0100_0000;
+        const H2C                = 0b1000_0000;
     }
 }
 
@@ -90,21 +97,43 @@ where
 
     state: State<S, B, X>,
     payload: Option<PayloadSender>,
+    trailers: Option<Trailers>,
+    hints: Option<EarlyHints>,
+    disconnect: DisconnectGuard,
+    idle: Option<IdleToken>,
     messages: VecDeque<DispatcherMessage>,
 
     ka_expire: Instant,
     ka_timer: Option<Delay>,
+    // slow read timer, flag is set if timer guards request payload
+    read_timer: Option<(Delay, bool)>,
 
     io: T,
     read_buf: BytesMut,
     write_buf: BytesMut,
     codec: Codec,
+
+    // h2c settings and upgrade request frame
+    h2c: Option<(Bytes, Bytes)>,
 }
 
 enum DispatcherMessage {
     Item(Request),
     Upgrade(Request),
-    Error(Response<()>),
+    H2c(Request, Bytes),
+    Error(Response),
+}
+
+/// Parts of connection that switches to HTTP/2 with `Upgrade: h2c` request.
+pub(crate) struct H2cUpgrade<T, S: Service> {
+    pub(crate) io: T,
+    pub(crate) read_buf: BytesMut,
+    pub(crate) settings: Bytes,
+    pub(crate) headers: Bytes,
+    pub(crate) config: ServiceConfig,
+    pub(crate) service: CloneableService<S>,
+    pub(crate) on_connect: Option<Box<dyn DataFactory>>,
+    pub(crate) peer_addr: Option<net::SocketAddr>,
 }
 
 enum State<S, B, X>
@@ -144,6 +173,7 @@ where
 
 enum PollResponse {
     Upgrade(Request),
+    H2c(Request, Bytes),
     DoNothing,
     DrainWriteBuf,
 }
@@ -186,11 +216,12 @@ where
         on_connect: Option<Box<dyn DataFactory>>,
         peer_addr: Option<net::SocketAddr>,
     ) -> Self {
+        let read_buf = BytesMut::with_capacity(config.buffer_capacity());
         Dispatcher::with_timeout(
             stream,
             Codec::new(config.clone()),
             config,
-            BytesMut::with_capacity(HW_BUFFER_SIZE),
+            read_buf,
             None,
             service,
             expect,
@@ -231,8 +262,12 @@ where
 
         Dispatcher {
             inner: DispatcherState::Normal(InnerDispatcher {
-                write_buf: BytesMut::with_capacity(HW_BUFFER_SIZE),
+                write_buf: BytesMut::with_capacity(config.buffer_capacity()),
                 payload: None,
+                trailers: None,
+                hints: None,
+                disconnect: DisconnectGuard::new(),
+                idle: None,
                 state: State::None,
                 error: None,
                 messages: VecDeque::new(),
@@ -247,9 +282,45 @@ where
                 peer_addr,
                 ka_expire,
                 ka_timer,
+                read_timer: None,
+                h2c: None,
             }),
         }
     }
+
+    /// Allow switching connection to HTTP/2 with `Upgrade: h2c` request.
+    pub(crate) fn allow_h2c(mut self) -> Self {
+        if let DispatcherState::Normal(ref mut inner) = self.inner {
+            inner.flags.insert(Flags::H2C);
+        }
+        self
+    }
+
+    /// Take connection parts once `101 Switching Protocols` response
+    /// to `Upgrade: h2c` request is written.
+    pub(crate) fn take_h2c(&mut self) -> Option<H2cUpgrade<T, S>> {
+        match self.inner {
+            DispatcherState::Normal(ref inner) if inner.h2c.is_some() => {}
+            _ => return None,
+        }
+        if let DispatcherState::Normal(inner) =
+            mem::replace(&mut self.inner, DispatcherState::None)
+        {
+            let (settings, headers) = inner.h2c.unwrap();
+            Some(H2cUpgrade {
+                settings,
+                headers,
+                config: inner.codec.config().clone(),
+                io: inner.io,
+                read_buf: inner.read_buf,
+                service: inner.service,
+                on_connect: inner.on_connect,
+                peer_addr: inner.peer_addr,
+            })
+        } else {
+            unreachable!()
+        }
+    }
 }
 
 impl<T, S, B, X, U> InnerDispatcher<T, S, B, X, U>
@@ -286,6 +357,19 @@ where
         }
     }
 
+    /// Drop empty buffers that grew over configured limit
+    fn release_buffers(&mut self) {
+        let max = self.codec.config().max_buffer_capacity();
+        if max != 0 {
+            if self.read_buf.is_empty() && self.read_buf.capacity() > max {
+                self.read_buf = BytesMut::new();
+            }
+            if self.write_buf.is_empty() && self.write_buf.capacity() > max {
+                self.write_buf = BytesMut::new();
+            }
+        }
+    }
+
     /// Flush stream
     ///
     /// true - got whouldblock
@@ -332,6 +416,7 @@ where
         message: Response<()>,
         body: ResponseBody<B>,
     ) -> Result<State<S, B, X>, DispatchError> {
+        self.disconnect.release();
         self.codec
             .encode(Message::Item((message, body.size())), &mut self.write_buf)
             .map_err(|err| {
@@ -353,6 +438,30 @@ where
             .extend_from_slice(b"HTTP/1.1 100 Continue\r\n\r\n");
     }
 
+    /// Switch protocols, upgrade request is served over
+    /// HTTP/2 connection as stream 1 (RFC 7540, 3.2)
+    fn send_switching_protocols(&mut self, req: Request, settings: Bytes) {
+        self.write_buf.extend_from_slice(
+            b"HTTP/1.1 101 Switching Protocols\r\n\
+              connection: upgrade\r\nupgrade: h2c\r\n\r\n",
+        );
+        self.h2c = Some((settings, h2c::headers_frame(req.head())));
+    }
+
+    /// Write early hints sent by service, hints are not accepted
+    /// once service call is completed.
+    fn send_hints(&mut self, cx: &mut Context<'_>, done: bool) {
+        if let Some(hints) = self.hints.take() {
+            hints.write(&mut self.write_buf);
+            if done {
+                hints.close();
+            } else {
+                hints.register(cx.waker());
+                self.hints = Some(hints);
+            }
+        }
+    }
+
     fn poll_response(
         &mut self,
         cx: &mut Context<'_>,
@@ -361,14 +470,18 @@ where
             let state = match self.state {
                 State::None => match self.messages.pop_front() {
                     Some(DispatcherMessage::Item(req)) => {
-                        Some(self.handle_request(req, cx)?)
+                        Some(self.handle_request(req)?)
                     }
                     Some(DispatcherMessage::Error(res)) => {
-                        Some(self.send_response(res, ResponseBody::Other(Body::Empty))?)
+                        let (res, body) = res.replace_body(());
+                        Some(self.send_response(res, body.into_body())?)
                     }
                     Some(DispatcherMessage::Upgrade(req)) => {
                         return Ok(PollResponse::Upgrade(req));
                     }
+                    Some(DispatcherMessage::H2c(req, settings)) => {
+                        return Ok(PollResponse::H2c(req, settings));
+                    }
                     None => None,
                 },
                 State::ExpectCall(ref mut fut) => {
@@ -379,6 +492,8 @@ where
                             continue;
                         }
                         Poll::Ready(Err(e)) => {
+                            // request body is not sent, close connection
+                            self.flags.insert(Flags::READ_DISCONNECT);
                             let res: Response = e.into().into();
                             let (res, body) = res.replace_body(());
                             Some(self.send_response(res, body.into_body())?)
@@ -387,9 +502,30 @@ where
                     }
                 }
                 State::ServiceCall(ref mut fut) => {
-                    match unsafe { Pin::new_unchecked(fut) }.poll(cx) {
+                    let res = unsafe { Pin::new_unchecked(fut) }.poll(cx);
+                    self.send_hints(cx, res.is_ready());
+                    match res {
                         Poll::Ready(Ok(res)) => {
                             let (res, body) = res.into().replace_body(());
+                            // informational status is not a final response,
+                            // except for protocol upgrades
+                            let status = res.head().status;
+                            let (res, body) = if status.is_informational()
+                                && status != StatusCode::SWITCHING_PROTOCOLS
+                            {
+                                error!(
+                                    "Service returned `{}` as final response",
+                                    status
+                                );
+                                let res = self
+                                    .codec
+                                    .config()
+                                    .error_response(StatusCode::INTERNAL_SERVER_ERROR);
+                                let (res, body) = res.replace_body(());
+                                (res, body.into_body())
+                            } else {
+                                (res, body)
+                            };
                             self.state = self.send_response(res, body)?;
                             continue;
                         }
@@ -403,7 +539,7 @@ where
                 }
                 State::SendPayload(ref mut stream) => {
                     loop {
-                        if self.write_buf.len() < HW_BUFFER_SIZE {
+                        if self.write_buf.len() < self.codec.config().buffer_capacity() {
                             match stream.poll_next(cx) {
                                 Poll::Ready(Some(Ok(item))) => {
                                     self.codec.encode(
@@ -448,6 +584,12 @@ where
                     }
                 } else if !self.messages.is_empty() {
                     continue;
+                } else if !self.read_buf.is_empty()
+                    && !self.flags.contains(Flags::READ_DISCONNECT)
+                    && self.poll_request(cx)?
+                {
+                    // requests held back by pipelining limit
+                    continue;
                 }
             }
             break;
@@ -456,44 +598,28 @@ where
         Ok(PollResponse::DoNothing)
     }
 
-    fn handle_request(
-        &mut self,
-        req: Request,
-        cx: &mut Context<'_>,
-    ) -> Result<State<S, B, X>, DispatchError> {
-        // Handle `EXPECT: 100-Continue` header
-        let req = if req.head().expect() {
-            let mut task = self.expect.call(req);
-            match unsafe { Pin::new_unchecked(&mut task) }.poll(cx) {
-                Poll::Ready(Ok(req)) => {
-                    self.send_continue();
-                    req
-                }
-                Poll::Pending => return Ok(State::ExpectCall(task)),
-                Poll::Ready(Err(e)) => {
-                    let e = e.into();
-                    let res: Response = e.into();
-                    let (res, body) = res.replace_body(());
-                    return self.send_response(res, body.into_body());
-                }
-            }
+    fn handle_request(&mut self, req: Request) -> Result<State<S, B, X>, DispatchError> {
+        let disconnect = Disconnect::new();
+        req.extensions_mut().insert(disconnect.clone());
+        self.disconnect.set(disconnect);
+
+        // 1xx responses must not be sent to HTTP/1.0 clients
+        self.hints = if self.codec.config().early_hints()
+            && req.head().version == Version::HTTP_11
+        {
+            let hints = EarlyHints::new();
+            req.extensions_mut().insert(hints.clone());
+            Some(hints)
         } else {
-            req
+            None
         };
 
-        // Call service
-        let mut task = self.service.call(req);
-        match unsafe { Pin::new_unchecked(&mut task) }.poll(cx) {
-            Poll::Ready(Ok(res)) => {
-                let (res, body) = res.into().replace_body(());
-                self.send_response(res, body)
-            }
-            Poll::Pending => Ok(State::ServiceCall(task)),
-            Poll::Ready(Err(e)) => {
-                let res: Response = e.into().into();
-                let (res, body) = res.replace_body(());
-                self.send_response(res, body.into_body())
-            }
+        // futures are polled in place by `poll_response`, service future
+        // must not be moved after it is polled
+        if req.head().expect() {
+            Ok(State::ExpectCall(self.expect.call(req)))
+        } else {
+            Ok(State::ServiceCall(self.service.call(req)))
         }
     }
 
@@ -502,13 +628,17 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Result<bool, DispatchError> {
-        // limit a mount of non processed requests
-        if self.messages.len() >= MAX_PIPELINED_MESSAGES || !self.can_read(cx) {
+        if !self.can_read(cx) {
             return Ok(false);
         }
 
         let mut updated = false;
         loop {
+            // limit amount of non processed requests
+            if self.payload.is_none() && self.pipeline_full() {
+                break;
+            }
+
             match self.codec.decode(&mut self.read_buf) {
                 Ok(Some(msg)) => {
                     updated = true;
@@ -524,6 +654,17 @@ where
                                 on_connect.set(&mut req.extensions_mut());
                             }
 
+                            if pl == MessageType::None && self.flags.contains(Flags::H2C)
+                            {
+                                if let Some(settings) = h2c::settings(req.head()) {
+                                    // rest of buffer belongs to http/2 connection
+                                    self.flags.insert(Flags::UPGRADE);
+                                    self.messages.push_back(DispatcherMessage::H2c(
+                                        req, settings,
+                                    ));
+                                    break;
+                                }
+                            }
                             if pl == MessageType::Stream && self.upgrade.is_some() {
                                 self.messages.push_back(DispatcherMessage::Upgrade(req));
                                 break;
@@ -534,11 +675,20 @@ where
                                     req.replace_payload(crate::Payload::H1(pl));
                                 req = req1;
                                 self.payload = Some(ps);
+
+                                // chunked payload could be followed by trailers
+                                self.trailers = if req.chunked().unwrap_or(false) {
+                                    let trailers = Trailers::new();
+                                    req.extensions_mut().insert(trailers.clone());
+                                    Some(trailers)
+                                } else {
+                                    None
+                                };
                             }
 
                             // handle request early
                             if self.state.is_empty() {
-                                self.state = self.handle_request(req, cx)?;
+                                self.state = self.handle_request(req)?;
                             } else {
                                 self.messages.push_back(DispatcherMessage::Item(req));
                             }
@@ -552,7 +702,9 @@ where
                                 );
                                 self.flags.insert(Flags::READ_DISCONNECT);
                                 self.messages.push_back(DispatcherMessage::Error(
-                                    Response::InternalServerError().finish().drop_body(),
+                                    self.codec.config().error_response(
+                                        StatusCode::INTERNAL_SERVER_ERROR,
+                                    ),
                                 ));
                                 self.error = Some(DispatchError::InternalError);
                                 break;
@@ -560,12 +712,19 @@ where
                         }
                         Message::Chunk(None) => {
                             if let Some(mut payload) = self.payload.take() {
+                                if let Some(trailers) = self.trailers.take() {
+                                    if let Some(fields) = self.codec.take_trailers() {
+                                        trailers.extend(fields);
+                                    }
+                                }
                                 payload.feed_eof();
                             } else {
                                 error!("Internal server error: unexpected eof");
                                 self.flags.insert(Flags::READ_DISCONNECT);
                                 self.messages.push_back(DispatcherMessage::Error(
-                                    Response::InternalServerError().finish().drop_body(),
+                                    self.codec.config().error_response(
+                                        StatusCode::INTERNAL_SERVER_ERROR,
+                                    ),
                                 ));
                                 self.error = Some(DispatchError::InternalError);
                                 break;
@@ -584,9 +743,16 @@ where
                         payload.set_error(PayloadError::EncodingCorrupted);
                     }
 
-                    // Malformed requests should be responded with 400
+                    // Malformed requests should be responded with 400,
+                    // too large heads with 431
+                    let status = match e {
+                        ParseError::TooLarge => {
+                            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+                        }
+                        _ => StatusCode::BAD_REQUEST,
+                    };
                     self.messages.push_back(DispatcherMessage::Error(
-                        Response::BadRequest().finish().drop_body(),
+                        self.codec.config().error_response(status),
                     ));
                     self.flags.insert(Flags::READ_DISCONNECT);
                     self.error = Some(e.into());
@@ -603,6 +769,100 @@ where
         Ok(updated)
     }
 
+    /// Check if pipelined request heads should not be read.
+    ///
+    /// With pipelining disabled next request is read only once response
+    /// to the previous one is sent.
+    fn pipeline_full(&self) -> bool {
+        match self.codec.config().max_pipelined_requests() {
+            0 => !self.state.is_empty() || !self.messages.is_empty(),
+            max => self.messages.len() >= max,
+        }
+    }
+
+    /// Track idle keep-alive state of connection.
+    ///
+    /// Returns true if connection got evicted by idle connections limit.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> bool {
+        let idle = self.flags.contains(Flags::STARTED)
+            && self.state.is_empty()
+            && self.messages.is_empty()
+            && self.payload.is_none()
+            && self.read_buf.is_empty()
+            && self.write_buf.is_empty();
+        if !idle {
+            self.idle = None;
+            return false;
+        }
+
+        if self.idle.is_none() {
+            self.idle = match self.codec.config().idle_limit() {
+                Some(limit) => limit.enter(cx),
+                None => return false,
+            };
+        }
+        match self.idle {
+            Some(ref token) => token.poll_evicted(cx),
+            None => false,
+        }
+    }
+
+    /// Slow read timer for subsequent request heads and request payloads.
+    ///
+    /// Head of keep-alive request must be received completely within timeout,
+    /// measured from its first byte. For request payload timeout is measured
+    /// from the last read.
+    fn poll_read_timeout(&mut self, cx: &mut Context<'_>, read_some: bool) {
+        let expire = match self.codec.config().client_read_timer_expire() {
+            Some(expire) => expire,
+            None => return,
+        };
+
+        // timer runs only while dispatcher waits for data from client
+        if !self.flags.contains(Flags::STARTED)
+            || (self.payload.is_none() && self.pipeline_full())
+            || !self.can_read(cx)
+        {
+            self.read_timer = None;
+            return;
+        }
+
+        let is_payload = if self.payload.is_some() {
+            true
+        } else if !self.read_buf.is_empty() {
+            false
+        } else {
+            self.read_timer = None;
+            return;
+        };
+
+        match self.read_timer {
+            Some((ref mut timer, payload)) if payload == is_payload => {
+                if is_payload && read_some {
+                    timer.reset(expire);
+                }
+            }
+            _ => self.read_timer = Some((delay_until(expire), is_payload)),
+        }
+
+        if let Some((ref mut timer, _)) = self.read_timer {
+            if Pin::new(timer).poll(cx).is_ready() {
+                trace!("Slow read timeout, close connection");
+                self.read_timer = None;
+                self.flags.insert(Flags::READ_DISCONNECT);
+                if let Some(mut payload) = self.payload.take() {
+                    payload.set_error(PayloadError::Incomplete(None));
+                } else {
+                    self.messages.push_back(DispatcherMessage::Error(
+                        self.codec
+                            .config()
+                            .error_response(StatusCode::REQUEST_TIMEOUT),
+                    ));
+                }
+            }
+        }
+    }
+
     /// keep-alive timer
     fn poll_keepalive(&mut self, cx: &mut Context<'_>) -> Result<(), DispatchError> {
         if self.ka_timer.is_none() {
@@ -651,15 +911,21 @@ where
                             // timeout on first request (slow request) return 408
                             if !self.flags.contains(Flags::STARTED) {
                                 trace!("Slow request timeout");
-                                let _ = self.send_response(
-                                    Response::RequestTimeout().finish().drop_body(),
-                                    ResponseBody::Other(Body::Empty),
-                                );
+                                let (res, body) = self
+                                    .codec
+                                    .config()
+                                    .error_response(StatusCode::REQUEST_TIMEOUT)
+                                    .replace_body(());
+                                self.state =
+                                    self.send_response(res, body.into_body())?;
+                                // response is written by dispatcher, connection
+                                // gets closed once it is flushed
+                                self.flags
+                                    .insert(Flags::STARTED | Flags::READ_DISCONNECT);
                             } else {
                                 trace!("Keep-alive connection timeout");
+                                self.flags.insert(Flags::STARTED | Flags::SHUTDOWN);
                             }
-                            self.flags.insert(Flags::STARTED | Flags::SHUTDOWN);
-                            self.state = State::None;
                         }
                     } else if let Some(deadline) =
                         self.codec.config().keep_alive_expire()
@@ -733,10 +999,24 @@ where
                         }
                     }
                 } else {
+                    // while pipeline is full read buffer is capped, so client
+                    // is held back by tcp flow control
+                    let hw = inner.codec.config().buffer_capacity();
+                    let paused = inner.payload.is_none() && inner.pipeline_full();
+                    let limit = if paused { hw } else { usize::max_value() };
+
                     // read socket into a buf
                     let should_disconnect =
-                        if !inner.flags.contains(Flags::READ_DISCONNECT) {
-                            read_available(cx, &mut inner.io, &mut inner.read_buf)?
+                        if !inner.flags.contains(Flags::READ_DISCONNECT)
+                            && inner.read_buf.len() < limit
+                        {
+                            read_available(
+                                cx,
+                                &mut inner.io,
+                                &mut inner.read_buf,
+                                hw,
+                                limit,
+                            )?
                         } else {
                             None
                         };
@@ -744,17 +1024,18 @@ where
                     inner.poll_request(cx)?;
                     if let Some(true) = should_disconnect {
                         inner.flags.insert(Flags::READ_DISCONNECT);
+                        inner.disconnect.disconnect();
                         if let Some(mut payload) = inner.payload.take() {
                             payload.feed_eof();
                         }
                     };
+                    inner.poll_read_timeout(cx, should_disconnect.is_some());
 
                     loop {
-                        let remaining =
-                            inner.write_buf.capacity() - inner.write_buf.len();
-                        if remaining < LW_BUFFER_SIZE {
-                            inner.write_buf.reserve(HW_BUFFER_SIZE - remaining);
-                        }
+                        reserve_buf(
+                            &mut inner.write_buf,
+                            inner.codec.config().buffer_capacity(),
+                        );
                         let result = inner.poll_response(cx)?;
                         let drain = result == PollResponse::DrainWriteBuf;
 
@@ -778,6 +1059,9 @@ where
                                 panic!()
                             }
                         }
+                        if let PollResponse::H2c(req, settings) = result {
+                            inner.send_switching_protocols(req, settings);
+                        }
 
                         // we didnt get WouldBlock from write operation,
                         // so data get written to kernel completely (OSX)
@@ -792,6 +1076,27 @@ where
                         return Poll::Ready(Ok(()));
                     }
 
+                    // connection is handed over to http/2 dispatcher
+                    if inner.h2c.is_some() {
+                        return if inner.write_buf.is_empty() {
+                            Poll::Ready(Ok(()))
+                        } else {
+                            Poll::Pending
+                        };
+                    }
+                    inner.release_buffers();
+
+                    // resume reading once pipelined requests are handled
+                    if paused && !(inner.payload.is_none() && inner.pipeline_full()) {
+                        cx.waker().wake_by_ref();
+                    }
+
+                    // idle keep-alive connections are closed if limit is exceeded
+                    if inner.poll_idle(cx) {
+                        trace!("Idle connections limit exceeded, close connection");
+                        inner.flags.insert(Flags::SHUTDOWN);
+                    }
+
                     let is_empty = inner.state.is_empty();
 
                     // read half is closed and we do not processing any responses
@@ -833,20 +1138,31 @@ where
     }
 }
 
+/// Reserve buffer space if remaining capacity drops below low watermark
+fn reserve_buf(buf: &mut BytesMut, hw: usize) {
+    let remaining = buf.capacity() - buf.len();
+    if remaining < LW_BUFFER_SIZE {
+        buf.reserve(cmp::max(hw, LW_BUFFER_SIZE) - remaining);
+    }
+}
+
+/// Read socket until it would block or buffer reaches limit
 fn read_available<T>(
     cx: &mut Context<'_>,
     io: &mut T,
     buf: &mut BytesMut,
+    hw: usize,
+    limit: usize,
 ) -> Result<Option<bool>, io::Error>
 where
     T: AsyncRead + Unpin,
 {
     let mut read_some = false;
     loop {
-        let remaining = buf.capacity() - buf.len();
-        if remaining < LW_BUFFER_SIZE {
-            buf.reserve(HW_BUFFER_SIZE - remaining);
+        if buf.len() >= limit {
+            return if read_some { Ok(Some(false)) } else { Ok(None) };
         }
+        reserve_buf(buf, hw);
 
         match read(cx, io, buf) {
             Poll::Pending => {
@@ -890,7 +1206,7 @@ where
 #[cfg(test)]
 mod tests {
     use actix_service::IntoService;
-    use futures_util::future::{lazy, ok};
+    use futures_util::future::{lazy, ok, pending};
 
     use super::*;
     use crate::error::Error;
@@ -925,4 +1241,133 @@ mod tests {
         })
         .await;
     }
+
+    #[actix_rt::test]
+    async fn test_disconnect() {
+        let disconnect = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let disconnect2 = disconnect.clone();
+
+        lazy(|cx| {
+            let buf = TestBuffer::new("GET /test HTTP/1.1\r\n\r\n");
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                ServiceConfig::default(),
+                CloneableService::new(
+                    (move |req: Request| {
+                        *disconnect2.borrow_mut() =
+                            req.extensions().get::<Disconnect>().cloned();
+                        pending::<Result<Response, Error>>()
+                    })
+                    .into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+                None,
+            );
+            assert!(Pin::new(&mut h1).poll(cx).is_pending());
+            let handle = disconnect.borrow().clone().unwrap();
+            assert!(!handle.is_disconnected());
+
+            // connection is dropped before response is ready
+            drop(h1);
+            assert!(handle.is_disconnected());
+        })
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn test_pipelining_disabled() {
+        use futures_channel::{mpsc, oneshot};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let calls2 = calls.clone();
+        let (res_tx, res_rx) = oneshot::channel::<Response>();
+        let res_rx = RefCell::new(Some(res_rx));
+
+        lazy(|cx| {
+            let buf = TestBuffer::new(
+                "GET /first HTTP/1.1\r\n\r\nGET /second HTTP/1.1\r\nconnection: close\r\n\r\n",
+            );
+            let mut cfg = ServiceConfig::default();
+            cfg.set_max_pipelined_requests(0);
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                cfg,
+                CloneableService::new(
+                    (move |req: Request| {
+                        calls2.borrow_mut().push(req.path().to_owned());
+                        let rx = res_rx.borrow_mut().take();
+                        async move {
+                            match rx {
+                                Some(rx) => Ok::<_, Error>(rx.await.unwrap()),
+                                None => Ok(Response::Ok().finish()),
+                            }
+                        }
+                    })
+                    .into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+                None,
+            );
+            let read_buf = |h1: &Dispatcher<_, _, _, _, _>| match h1.inner {
+                DispatcherState::Normal(ref inner) => {
+                    assert!(inner.messages.is_empty());
+                    inner.read_buf.clone()
+                }
+                _ => panic!(),
+            };
+
+            // second request is not read while first is handled
+            assert!(Pin::new(&mut h1).poll(cx).is_pending());
+            assert_eq!(*calls.borrow(), vec!["/first"]);
+            assert!(read_buf(&h1).starts_with(b"GET /second"));
+
+            // read buffer is capped while pipeline is full
+            if let DispatcherState::Normal(ref mut inner) = h1.inner {
+                inner.io.extend_read_buf(vec![b'a'; 4 * HW_BUFFER_SIZE]);
+            }
+            assert!(Pin::new(&mut h1).poll(cx).is_pending());
+            match h1.inner {
+                DispatcherState::Normal(ref inner) => {
+                    assert!(inner.read_buf.len() <= 2 * HW_BUFFER_SIZE);
+                    assert!(!inner.io.read_buf.is_empty());
+                }
+                _ => panic!(),
+            }
+
+            // nor while first response is written
+            let (body_tx, body_rx) = mpsc::unbounded::<Result<Bytes, Error>>();
+            let _ = res_tx.send(Response::Ok().streaming(body_rx));
+            assert!(Pin::new(&mut h1).poll(cx).is_pending());
+            assert_eq!(*calls.borrow(), vec!["/first"]);
+            assert!(read_buf(&h1).starts_with(b"GET /second"));
+
+            drop(body_tx);
+            assert!(Pin::new(&mut h1).poll(cx).is_ready());
+            assert_eq!(*calls.borrow(), vec!["/first", "/second"]);
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_reserve_buf() {
+        let mut buf = BytesMut::new();
+        reserve_buf(&mut buf, 1024);
+        assert!(buf.capacity() >= LW_BUFFER_SIZE);
+
+        let mut buf = BytesMut::new();
+        reserve_buf(&mut buf, HW_BUFFER_SIZE);
+        assert!(buf.capacity() >= HW_BUFFER_SIZE);
+
+        let cap = buf.capacity();
+        reserve_buf(&mut buf, HW_BUFFER_SIZE);
+        assert_eq!(buf.capacity(), cap);
+    }
 }
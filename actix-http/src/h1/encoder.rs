@@ -14,6 +14,7 @@ use crate::http::header::{CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
 use crate::http::{HeaderMap, StatusCode, Version};
 use crate::message::{ConnectionType, RequestHeadType};
 use crate::response::Response;
+use crate::trailers::Trailers;
 
 const AVERAGE_HEADER_SIZE: usize = 30;
 
@@ -226,7 +227,7 @@ pub(crate) trait MessageType: Sized {
         }
 
         // optimized date header, set_date writes \r\n
-        if !has_date {
+        if !has_date && config.date_header() {
             config.set_date(dst);
         } else {
             // msg eof
@@ -323,6 +324,15 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
+    /// Encode eof with trailer fields
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &Trailers,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.te.encode_trailers(trailers, buf)
+    }
+
     pub fn encode(
         &mut self,
         dst: &mut BytesMut,
@@ -473,6 +483,28 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof followed by trailer fields, trailers are sent only
+    /// for chunked transfer encoding
+    #[inline]
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &Trailers,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) => {
+                if !*eof {
+                    *eof = true;
+                    buf.extend_from_slice(b"0\r\n");
+                    trailers.write(buf);
+                    buf.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 struct Writer<'a>(pub &'a mut BytesMut);
@@ -527,7 +559,7 @@ mod tests {
     use http::header::AUTHORIZATION;
 
     use super::*;
-    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
     use crate::RequestHead;
 
     #[test]
@@ -544,6 +576,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let trailers = Trailers::new();
+        trailers.insert(
+            HeaderName::from_static("x-checksum"),
+            HeaderValue::from_static("1234"),
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(
+            bytes.split().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\nx-checksum: 1234\r\n\r\n")
+        );
+
+        let mut enc = TransferEncoding::length(4);
+        assert!(enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(bytes.split().freeze(), Bytes::from_static(b"test"));
+    }
+
     #[test]
     fn test_camel_case() {
         let mut bytes = BytesMut::with_capacity(2048);
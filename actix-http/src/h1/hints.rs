@@ -0,0 +1,122 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::task::Waker;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::response::Response;
+
+/// Handle for sending `103 Early Hints` informational responses.
+///
+/// Handle is available in request extensions of HTTP/1.1 requests if
+/// early hints are enabled with `HttpServiceBuilder::early_hints()`.
+/// Service could send any number of hints until final response is ready.
+/// Informational status is not allowed for final response, such response
+/// is replaced with `500 Internal Server Error`.
+///
+/// ```rust
+/// use actix_http::h1::EarlyHints;
+/// use actix_http::{http::header, HttpMessage, Request, Response};
+///
+/// async fn index(req: Request) -> Result<Response, ()> {
+///     if let Some(hints) = req.extensions().get::<EarlyHints>() {
+///         hints.send(
+///             Response::EarlyHints()
+///                 .header(header::LINK, "</style.css>; rel=preload; as=style")
+///                 .finish(),
+///         );
+///     }
+///     Ok(Response::Ok().finish())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct EarlyHints(Rc<Inner>);
+
+struct Inner {
+    buf: RefCell<BytesMut>,
+    task: RefCell<Option<Waker>>,
+    closed: Cell<bool>,
+}
+
+impl EarlyHints {
+    pub(crate) fn new() -> Self {
+        EarlyHints(Rc::new(Inner {
+            buf: RefCell::new(BytesMut::new()),
+            task: RefCell::new(None),
+            closed: Cell::new(false),
+        }))
+    }
+
+    /// Send informational response with headers of provided response.
+    ///
+    /// Response status and body are ignored. Returns `false` if final
+    /// response is already sent.
+    pub fn send<B>(&self, res: Response<B>) -> bool {
+        if self.0.closed.get() {
+            return false;
+        }
+
+        let mut buf = self.0.buf.borrow_mut();
+        buf.extend_from_slice(b"HTTP/1.1 103 Early Hints\r\n");
+        for (key, value) in res.headers() {
+            buf.reserve(key.as_str().len() + value.len() + 4);
+            buf.put_slice(key.as_str().as_bytes());
+            buf.put_slice(b": ");
+            buf.put_slice(value.as_bytes());
+            buf.put_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"\r\n");
+
+        if let Some(task) = self.0.task.borrow_mut().take() {
+            task.wake();
+        }
+        true
+    }
+
+    /// Move pending hints to connection's write buffer.
+    pub(crate) fn write(&self, dst: &mut BytesMut) {
+        let mut buf = self.0.buf.borrow_mut();
+        if !buf.is_empty() {
+            dst.extend_from_slice(&buf);
+            buf.clear();
+        }
+    }
+
+    pub(crate) fn register(&self, waker: &Waker) {
+        *self.0.task.borrow_mut() = Some(waker.clone());
+    }
+
+    pub(crate) fn close(&self) {
+        self.0.closed.set(true);
+        self.0.task.borrow_mut().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+
+    #[test]
+    fn test_early_hints() {
+        let hints = EarlyHints::new();
+        assert!(hints.send(
+            Response::EarlyHints()
+                .header(header::LINK, "</style.css>; rel=preload")
+                .finish()
+        ));
+
+        let mut dst = BytesMut::new();
+        hints.write(&mut dst);
+        assert_eq!(
+            dst.as_ref(),
+            &b"HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload\r\n\r\n"[..]
+        );
+
+        hints.close();
+        assert!(!hints.send(Response::EarlyHints().finish()));
+        let mut dst = BytesMut::new();
+        hints.write(&mut dst);
+        assert!(dst.is_empty());
+    }
+}
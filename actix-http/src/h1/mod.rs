@@ -4,9 +4,11 @@ use bytes::{Bytes, BytesMut};
 mod client;
 mod codec;
 mod decoder;
+mod disconnect;
 mod dispatcher;
 mod encoder;
 mod expect;
+mod hints;
 mod payload;
 mod service;
 mod upgrade;
@@ -14,13 +16,18 @@ mod utils;
 
 pub use self::client::{ClientCodec, ClientPayloadCodec};
 pub use self::codec::Codec;
+pub use self::disconnect::Disconnect;
 pub use self::dispatcher::Dispatcher;
 pub use self::expect::ExpectHandler;
+pub use self::hints::EarlyHints;
 pub use self::payload::Payload;
 pub use self::service::{H1Service, H1ServiceHandler, OneRequest};
-pub use self::upgrade::UpgradeHandler;
+pub use self::upgrade::{UpgradeHandler, Upgraded};
 pub use self::utils::SendResponse;
 
+pub(crate) use self::decoder::{MAX_BUFFER_SIZE, MAX_HEADERS};
+pub(crate) use self::dispatcher::{HW_BUFFER_SIZE, MAX_PIPELINED_MESSAGES};
+
 #[derive(Debug)]
 /// Codec message
 pub enum Message<T> {
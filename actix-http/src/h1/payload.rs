@@ -170,11 +170,17 @@ impl Inner {
     #[inline]
     fn set_error(&mut self, err: PayloadError) {
         self.err = Some(err);
+        if let Some(task) = self.task.take() {
+            task.wake()
+        }
     }
 
     #[inline]
     fn feed_eof(&mut self) {
         self.eof = true;
+        if let Some(task) = self.task.take() {
+            task.wake()
+        }
     }
 
     #[inline]
@@ -226,6 +232,10 @@ impl Inner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    use actix_rt::time::{delay_for, timeout};
+    use futures_channel::oneshot;
     use futures_util::future::poll_fn;
 
     #[actix_rt::test]
@@ -241,4 +251,18 @@ mod tests {
             poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
         );
     }
+
+    #[actix_rt::test]
+    async fn test_eof_wakes_reader() {
+        let (mut sender, mut payload) = Payload::create(false);
+        let (tx, rx) = oneshot::channel();
+
+        actix_rt::spawn(async move {
+            let _ = tx.send(poll_fn(|cx| payload.readany(cx)).await.is_none());
+        });
+        // reader is waiting for data
+        delay_for(Duration::from_millis(10)).await;
+        sender.feed_eof();
+        assert!(timeout(Duration::from_secs(1), rx).await.unwrap().unwrap());
+    }
 }
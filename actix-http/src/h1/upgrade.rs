@@ -1,13 +1,22 @@
+use std::io;
 use std::marker::PhantomData;
+use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use actix_codec::Framed;
+use actix_codec::{AsyncRead, AsyncWrite, Framed};
 use actix_service::{Service, ServiceFactory};
+use bytes::Bytes;
+use futures_channel::mpsc::{channel, Sender};
+use futures_core::Stream;
 use futures_util::future::Ready;
+use futures_util::StreamExt;
+use http::StatusCode;
 
-use crate::error::Error;
+use crate::error::{Error, PayloadError};
 use crate::h1::Codec;
+use crate::payload::PayloadStream;
 use crate::request::Request;
+use crate::response::{Response, ResponseBuilder};
 
 pub struct UpgradeHandler<T>(PhantomData<T>);
 
@@ -39,3 +48,162 @@ impl<T> Service for UpgradeHandler<T> {
         unimplemented!()
     }
 }
+
+/// Number of written chunks buffered until response body is polled
+const WRITE_BUFFER: usize = 8;
+
+/// Connection switched to a custom protocol.
+///
+/// `Upgraded` emulates io over request payload and response body streams,
+/// it is not raw connection io. Reads return bytes sent by the peer after
+/// request head, including already buffered ones. Written bytes are sent as
+/// chunks of streaming body of `101 Switching Protocols` response, writes
+/// are pending while dispatcher does not take buffered chunks, flush does
+/// not wait for data to be written to connection. Response body ends and
+/// connection is closed once `Upgraded` is shut down or dropped.
+///
+/// ```rust
+/// use actix_codec::{BytesCodec, Framed};
+/// use actix_http::h1::Upgraded;
+/// use actix_http::{http::StatusCode, Request, Response};
+/// use futures::{SinkExt, StreamExt};
+///
+/// fn upgrade(mut req: Request) -> Response {
+///     let (res, io) = Upgraded::handshake(
+///         req.take_payload(),
+///         Response::build(StatusCode::SWITCHING_PROTOCOLS).upgrade("echo"),
+///     );
+///     actix_rt::spawn(async move {
+///         let mut framed = Framed::new(io, BytesCodec);
+///         while let Some(Ok(chunk)) = framed.next().await {
+///             let _ = framed.send(chunk.freeze()).await;
+///         }
+///     });
+///     res
+/// }
+/// ```
+pub struct Upgraded {
+    payload: PayloadStream,
+    buf: Bytes,
+    tx: Option<Sender<Bytes>>,
+}
+
+impl Upgraded {
+    /// Create `101 Switching Protocols` response and connection handle.
+    ///
+    /// Request must carry `Upgrade` header (or be a `CONNECT` request),
+    /// otherwise request payload does not contain raw connection data.
+    pub fn handshake<S>(payload: S, res: &mut ResponseBuilder) -> (Response, Upgraded)
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        let (tx, rx) = channel(WRITE_BUFFER);
+        // raw bytes of custom protocol must not be chunk encoded
+        let res = res
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .no_chunking()
+            .streaming(rx.map(Ok::<_, Error>));
+        let upgraded = Upgraded {
+            payload: Box::pin(payload),
+            buf: Bytes::new(),
+            tx: Some(tx),
+        };
+        (res, upgraded)
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        while self.buf.is_empty() {
+            match self.payload.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buf = chunk,
+                Poll::Ready(Some(Err(PayloadError::Io(e)))) => {
+                    return Poll::Ready(Err(e))
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        e.to_string(),
+                    )))
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let len = std::cmp::min(buf.len(), self.buf.len());
+        buf[..len].copy_from_slice(&self.buf.split_to(len));
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let tx = match self.tx {
+            Some(ref mut tx) => tx,
+            None => return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        };
+        match tx.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                if tx.start_send(Bytes::copy_from_slice(buf)).is_ok() {
+                    Poll::Ready(Ok(buf.len()))
+                } else {
+                    Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+                }
+            }
+            Poll::Ready(Err(_)) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.tx.take();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::lazy;
+    use futures_util::stream;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_upgraded_write_backpressure() {
+        let (res, mut io) =
+            Upgraded::handshake(stream::empty(), &mut Response::build(StatusCode::OK));
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        lazy(|cx| {
+            let mut written = 0;
+            while let Poll::Ready(res) = Pin::new(&mut io).poll_write(cx, b"data") {
+                assert_eq!(res.unwrap(), 4);
+                written += 1;
+            }
+            assert_eq!(written, WRITE_BUFFER + 1);
+
+            // response body is gone
+            drop(res);
+            match Pin::new(&mut io).poll_write(cx, b"data") {
+                Poll::Ready(Err(e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+                _ => panic!(),
+            }
+        })
+        .await;
+    }
+}
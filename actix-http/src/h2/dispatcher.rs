@@ -1,17 +1,25 @@
+use std::cell::Cell;
 use std::convert::TryFrom;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::net;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_rt::time::{Delay, Instant};
 use actix_service::Service;
+use actix_utils::mpsc;
 use bytes::{Bytes, BytesMut};
-use h2::server::{Connection, SendResponse};
+use futures_core::Stream;
+use h2::server::{Connection, SendPushedResponse, SendResponse};
 use h2::SendStream;
-use http::header::{HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
+use http::header::{
+    HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONNECTION,
+    CONTENT_LENGTH, COOKIE, DATE, TRANSFER_ENCODING, USER_AGENT,
+};
+use http::{HeaderMap, StatusCode, Uri};
 use log::{error, trace};
 
 use crate::body::{BodySize, MessageBody, ResponseBody};
@@ -20,13 +28,22 @@ use crate::config::ServiceConfig;
 use crate::error::{DispatchError, Error};
 use crate::helpers::DataFactory;
 use crate::httpmessage::HttpMessage;
-use crate::message::ResponseHead;
+use crate::message::{RequestHead, ResponseHead};
 use crate::payload::Payload;
 use crate::request::Request;
 use crate::response::Response;
 
+use super::push::ServerPush;
+
 const CHUNK_SIZE: usize = 16_384;
 
+/// Headers of request that are copied to its promised requests
+const PUSH_HEADERS: &[HeaderName] =
+    &[ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, COOKIE, USER_AGENT];
+
+/// Promised request and stream of its response
+type Pushed = (Request, SendPushedResponse<Bytes>);
+
 /// Dispatcher for HTTP/2 protocol
 #[pin_project::pin_project]
 pub struct Dispatcher<T, S: Service<Request = Request>, B: MessageBody>
@@ -40,6 +57,9 @@ where
     peer_addr: Option<net::SocketAddr>,
     ka_expire: Instant,
     ka_timer: Option<Delay>,
+    push_enabled: Rc<Cell<bool>>,
+    push_tx: mpsc::Sender<Pushed>,
+    pushed: mpsc::Receiver<Pushed>,
     _t: PhantomData<B>,
 }
 
@@ -75,6 +95,7 @@ where
         } else {
             (config.now(), None)
         };
+        let (push_tx, pushed) = mpsc::channel();
 
         Dispatcher {
             service,
@@ -84,6 +105,9 @@ where
             on_connect,
             ka_expire,
             ka_timer,
+            push_enabled: Rc::new(Cell::new(true)),
+            push_tx,
+            pushed,
             _t: PhantomData,
         }
     }
@@ -104,6 +128,28 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        // promised requests are handled the same way as requests of client
+        while let Poll::Ready(Some((mut req, send))) =
+            Pin::new(&mut this.pushed).poll_next(cx)
+        {
+            req.head_mut().peer_addr = this.peer_addr;
+            if let Some(ref on_connect) = this.on_connect {
+                on_connect.set(&mut req.extensions_mut());
+            }
+
+            actix_rt::spawn(ServiceResponse::<S::Future, S::Response, S::Error, B> {
+                state: ServiceResponseState::ServiceCall(
+                    this.service.call(req),
+                    Some(Sender::Pushed(send)),
+                ),
+                config: this.config.clone(),
+                buffer: None,
+                head: false,
+                promises: None,
+                _t: PhantomData,
+            });
+        }
+
         loop {
             match Pin::new(&mut this.connection).poll_accept(cx) {
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
@@ -135,6 +181,17 @@ where
                         on_connect.set(&mut req.extensions_mut());
                     }
 
+                    // response to HEAD request has no payload
+                    let head = req.head().method == http::Method::HEAD;
+
+                    let promises = if this.push_enabled.get() {
+                        let handle = ServerPush::new(this.push_enabled.clone());
+                        req.extensions_mut().insert(handle.clone());
+                        Some(Promises::new(handle, req.head(), this.push_tx.clone()))
+                    } else {
+                        None
+                    };
+
                     actix_rt::spawn(ServiceResponse::<
                         S::Future,
                         S::Response,
@@ -143,10 +200,12 @@ where
                     > {
                         state: ServiceResponseState::ServiceCall(
                             this.service.call(req),
-                            Some(res),
+                            Some(Sender::Response(res)),
                         ),
                         config: this.config.clone(),
                         buffer: None,
+                        head,
+                        promises,
                         _t: PhantomData,
                     });
                 }
@@ -156,16 +215,97 @@ where
     }
 }
 
+/// Requests promised by service while it handles request
+struct Promises {
+    handle: ServerPush,
+    uri: Uri,
+    headers: HeaderMap,
+    tx: mpsc::Sender<Pushed>,
+}
+
+impl Promises {
+    fn new(handle: ServerPush, head: &RequestHead, tx: mpsc::Sender<Pushed>) -> Self {
+        let mut headers = HeaderMap::new();
+        for name in PUSH_HEADERS {
+            for value in head.headers.get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+        Promises {
+            handle,
+            uri: head.uri.clone(),
+            headers,
+            tx,
+        }
+    }
+
+    /// Send `PUSH_PROMISE` frames and start handling of promised requests,
+    /// frames have to be sent before response to request.
+    fn send(self, send: &mut SendResponse<Bytes>) {
+        for path in self.handle.take() {
+            // promised request has the same origin as request
+            let mut parts = path.into_parts();
+            parts.scheme = self.uri.scheme().cloned();
+            parts.authority = self.uri.authority().cloned();
+            let uri = match Uri::from_parts(parts) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+
+            let mut promise = http::Request::new(());
+            *promise.uri_mut() = uri.clone();
+            *promise.headers_mut() = self.headers.clone();
+            let pushed = match send.push_request(promise) {
+                Ok(pushed) => pushed,
+                Err(e) => {
+                    trace!("Push promise is refused: {:?}", e);
+                    self.handle.disable();
+                    return;
+                }
+            };
+
+            let mut req = Request::new();
+            let head = req.head_mut();
+            head.uri = uri;
+            head.method = http::Method::GET;
+            head.version = http::Version::HTTP_2;
+            head.headers = self.headers.clone().into();
+            let _ = self.tx.send((req, pushed));
+        }
+    }
+}
+
+/// Stream of response, it is either request of client or promised request
+enum Sender {
+    Response(SendResponse<Bytes>),
+    Pushed(SendPushedResponse<Bytes>),
+}
+
+impl Sender {
+    fn send_response(
+        &mut self,
+        res: http::Response<()>,
+        eof: bool,
+    ) -> Result<SendStream<Bytes>, h2::Error> {
+        match self {
+            Sender::Response(send) => send.send_response(res, eof),
+            Sender::Pushed(send) => send.send_response(res, eof),
+        }
+    }
+}
+
 #[pin_project::pin_project]
 struct ServiceResponse<F, I, E, B> {
     state: ServiceResponseState<F, B>,
     config: ServiceConfig,
     buffer: Option<Bytes>,
+    head: bool,
+    promises: Option<Promises>,
     _t: PhantomData<(I, E)>,
 }
 
 enum ServiceResponseState<F, B> {
-    ServiceCall(F, Option<SendResponse<Bytes>>),
+    ServiceCall(F, Option<Sender>),
     SendPayload(SendStream<Bytes>, ResponseBody<B>),
 }
 
@@ -226,9 +366,9 @@ where
         }
 
         // set date header
-        if !has_date {
+        if !has_date && self.config.date_header() {
             let mut bytes = BytesMut::with_capacity(29);
-            self.config.set_date_header(&mut bytes);
+            self.config.write_date_header(&mut bytes);
             res.headers_mut().insert(DATE, unsafe {
                 HeaderValue::from_maybe_shared_unchecked(bytes.freeze())
             });
@@ -255,14 +395,32 @@ where
                 match unsafe { Pin::new_unchecked(call) }.poll(cx) {
                     Poll::Ready(Ok(res)) => {
                         let (res, body) = res.into().replace_body(());
+                        // informational status is not a final response
+                        let status = res.head().status;
+                        let (res, body) = if status.is_informational() {
+                            error!("Service returned `{}` as final response", status);
+                            let res = this
+                                .config
+                                .error_response(StatusCode::INTERNAL_SERVER_ERROR);
+                            let (res, body) = res.replace_body(());
+                            (res, body.into_body())
+                        } else {
+                            (res, body)
+                        };
 
                         let mut send = send.take().unwrap();
+                        if let (Some(promises), Sender::Response(ref mut send)) =
+                            (this.promises.take(), &mut send)
+                        {
+                            promises.send(send);
+                        }
                         let mut size = body.size();
                         let h2_res =
                             self.as_mut().prepare_response(res.head(), &mut size);
                         this = self.as_mut().project();
 
-                        let stream = match send.send_response(h2_res, size.is_eof()) {
+                        let eof = size.is_eof() || *this.head;
+                        let stream = match send.send_response(h2_res, eof) {
                             Err(e) => {
                                 trace!("Error sending h2 response: {:?}", e);
                                 return Poll::Ready(());
@@ -270,7 +428,7 @@ where
                             Ok(stream) => stream,
                         };
 
-                        if size.is_eof() {
+                        if eof {
                             Poll::Ready(())
                         } else {
                             *this.state =
@@ -289,7 +447,8 @@ where
                             self.as_mut().prepare_response(res.head(), &mut size);
                         this = self.as_mut().project();
 
-                        let stream = match send.send_response(h2_res, size.is_eof()) {
+                        let eof = size.is_eof() || *this.head;
+                        let stream = match send.send_response(h2_res, eof) {
                             Err(e) => {
                                 trace!("Error sending h2 response: {:?}", e);
                                 return Poll::Ready(());
@@ -297,7 +456,7 @@ where
                             Ok(stream) => stream,
                         };
 
-                        if size.is_eof() {
+                        if eof {
                             Poll::Ready(())
                         } else {
                             *this.state = ServiceResponseState::SendPayload(
@@ -0,0 +1,427 @@
+//! Upgrade of HTTP/1.1 connection to cleartext HTTP/2 (RFC 7540, 3.2)
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{cmp, io, mem};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_rt::time::Delay;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::header::{HeaderName, CONNECTION, HOST, TE, TRANSFER_ENCODING, UPGRADE};
+
+use crate::error::DispatchError;
+use crate::message::RequestHead;
+
+/// HTTP/2 connection preface (RFC 7540, 3.5)
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_SIZE: usize = 9;
+/// Default `SETTINGS_MAX_FRAME_SIZE`
+const MAX_FRAME_SIZE: usize = 16_384;
+
+const HEADERS: u8 = 0x1;
+const SETTINGS: u8 = 0x4;
+const CONTINUATION: u8 = 0x9;
+
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const ACK: u8 = 0x1;
+
+/// Connection specific headers are not allowed in HTTP/2 (RFC 7540, 8.1.2.2)
+const CONNECTION_HEADERS: &[HeaderName] =
+    &[CONNECTION, HOST, TE, TRANSFER_ENCODING, UPGRADE];
+
+/// Decoded `HTTP2-Settings` header of `Upgrade: h2c` request.
+///
+/// Returns `None` if request can not be upgraded, such request
+/// is served over HTTP/1.1.
+pub(crate) fn settings(head: &RequestHead) -> Option<Bytes> {
+    let upgrade = head.headers.get(UPGRADE)?.to_str().ok()?;
+    if !upgrade.trim().eq_ignore_ascii_case("h2c") || !head.upgrade() {
+        return None;
+    }
+    let conn = head.headers.get(CONNECTION)?.to_str().ok()?;
+    if !conn
+        .split(',')
+        .any(|opt| opt.trim().eq_ignore_ascii_case("http2-settings"))
+    {
+        return None;
+    }
+
+    // exactly one settings header is required
+    let mut values = head.headers.get_all("http2-settings");
+    let value = values.next()?;
+    if values.next().is_some() {
+        return None;
+    }
+    let settings =
+        base64::decode_config(value.as_bytes(), base64::URL_SAFE_NO_PAD).ok()?;
+    if settings.len() % 6 == 0 {
+        Some(Bytes::from(settings))
+    } else {
+        None
+    }
+}
+
+/// Encode upgrade request as `HEADERS` frame of stream 1.
+///
+/// Header block uses literal fields only, so no dynamic table state
+/// is shared with the client.
+pub(crate) fn headers_frame(head: &RequestHead) -> Bytes {
+    let mut block = BytesMut::new();
+    encode_field(&mut block, b":method", head.method.as_str().as_bytes());
+    encode_field(&mut block, b":scheme", b"http");
+    let path = head.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    encode_field(&mut block, b":path", path.as_bytes());
+    if let Some(authority) = head.uri.authority() {
+        encode_field(&mut block, b":authority", authority.as_str().as_bytes());
+    } else if let Some(host) = head.headers.get(HOST) {
+        encode_field(&mut block, b":authority", host.as_bytes());
+    }
+    for (name, value) in head.headers.iter() {
+        if !CONNECTION_HEADERS.contains(name)
+            && name != "http2-settings"
+            && name != "keep-alive"
+            && name != "proxy-connection"
+        {
+            encode_field(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+
+    // request is complete, stream is half-closed by client
+    let mut frames = BytesMut::with_capacity(block.len() + FRAME_HEADER_SIZE);
+    let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+    let (mut kind, mut flags) = (HEADERS, END_STREAM);
+    while let Some(chunk) = chunks.next() {
+        if chunks.peek().is_none() {
+            flags |= END_HEADERS;
+        }
+        encode_frame_header(&mut frames, chunk.len(), kind, flags, 1);
+        frames.extend_from_slice(chunk);
+        kind = CONTINUATION;
+        flags = 0;
+    }
+    frames.freeze()
+}
+
+/// Literal header field without indexing (RFC 7541, 6.2.2)
+fn encode_field(dst: &mut BytesMut, name: &[u8], value: &[u8]) {
+    dst.put_u8(0);
+    encode_str(dst, name);
+    encode_str(dst, value);
+}
+
+/// String literal without huffman encoding (RFC 7541, 5.2)
+fn encode_str(dst: &mut BytesMut, val: &[u8]) {
+    let mut len = val.len();
+    if len < 0x7f {
+        dst.put_u8(len as u8);
+    } else {
+        dst.put_u8(0x7f);
+        len -= 0x7f;
+        while len >= 0x80 {
+            dst.put_u8((len & 0x7f) as u8 | 0x80);
+            len >>= 7;
+        }
+        dst.put_u8(len as u8);
+    }
+    dst.extend_from_slice(val);
+}
+
+fn encode_frame_header(dst: &mut BytesMut, len: usize, kind: u8, flags: u8, id: u32) {
+    dst.put_uint(len as u64, 3);
+    dst.put_u8(kind);
+    dst.put_u8(flags);
+    dst.put_u32(id);
+}
+
+/// Client connection preface of upgraded connection.
+///
+/// Future resolves once preface and its `SETTINGS` frame are received.
+/// Settings of `HTTP2-Settings` header are merged into that frame and
+/// upgrade request is replayed as stream 1 right after it.
+pub(crate) struct Preface<T> {
+    io: Option<T>,
+    buf: BytesMut,
+    settings: Bytes,
+    headers: Bytes,
+    timer: Option<Delay>,
+}
+
+impl<T> Preface<T> {
+    pub(crate) fn new(
+        io: T,
+        buf: BytesMut,
+        settings: Bytes,
+        headers: Bytes,
+        timer: Option<Delay>,
+    ) -> Self {
+        Preface {
+            io: Some(io),
+            buf,
+            settings,
+            headers,
+            timer,
+        }
+    }
+
+    /// Size of preface including `SETTINGS` frame, if it is read
+    fn parse(&self) -> Result<Option<usize>, DispatchError> {
+        let buf = &self.buf[..];
+        let n = cmp::min(buf.len(), PREFACE.len());
+        if buf[..n] != PREFACE[..n] {
+            return Err(invalid_preface());
+        }
+        if buf.len() < PREFACE.len() + FRAME_HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let head = &buf[PREFACE.len()..PREFACE.len() + FRAME_HEADER_SIZE];
+        let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+        if head[3] != SETTINGS || head[4] & ACK != 0 || head[5..] != [0, 0, 0, 0] {
+            return Err(invalid_preface());
+        }
+        let size = PREFACE.len() + FRAME_HEADER_SIZE + len;
+        Ok(if buf.len() >= size { Some(size) } else { None })
+    }
+}
+
+fn invalid_preface() -> DispatchError {
+    DispatchError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Invalid HTTP/2 connection preface",
+    ))
+}
+
+impl<T> Future for Preface<T>
+where
+    T: AsyncRead + Unpin,
+{
+    type Output = Result<Rewind<T>, DispatchError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(size) = this.parse()? {
+                let mut rest = mem::replace(&mut this.buf, BytesMut::new());
+                let mut frame = rest.split_to(size);
+                let payload = frame.split_off(PREFACE.len() + FRAME_HEADER_SIZE);
+
+                let mut buf = BytesMut::with_capacity(
+                    size + this.settings.len() + this.headers.len() + rest.len(),
+                );
+                buf.extend_from_slice(PREFACE);
+                encode_frame_header(
+                    &mut buf,
+                    this.settings.len() + payload.len(),
+                    SETTINGS,
+                    0,
+                    0,
+                );
+                // settings of preface override settings of upgrade request
+                buf.extend_from_slice(&this.settings);
+                buf.extend_from_slice(&payload);
+                buf.extend_from_slice(&this.headers);
+                buf.extend_from_slice(&rest);
+
+                let io = this.io.take().unwrap();
+                return Poll::Ready(Ok(Rewind::new(io, buf.freeze())));
+            }
+
+            if this.buf.capacity() - this.buf.len() < FRAME_HEADER_SIZE {
+                this.buf.reserve(MAX_FRAME_SIZE);
+            }
+            match Pin::new(this.io.as_mut().unwrap()).poll_read_buf(cx, &mut this.buf) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(DispatchError::Io(
+                        io::ErrorKind::UnexpectedEof.into(),
+                    )))
+                }
+                Poll::Ready(Ok(_)) => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => {
+                    if let Some(ref mut timer) = this.timer {
+                        if Pin::new(timer).poll(cx).is_ready() {
+                            return Poll::Ready(Err(DispatchError::SlowRequestTimeout));
+                        }
+                    }
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Client preface of connection with prior knowledge of HTTP/2 (RFC 7540, 3.4).
+///
+/// Future reads connection until it either contains complete preface or
+/// differs from it. Read data is returned with a flag of complete preface,
+/// so it can be replayed to either http/1 or http/2 dispatcher.
+pub(crate) struct PriorKnowledge<T> {
+    io: Option<T>,
+    buf: BytesMut,
+    timer: Option<Delay>,
+}
+
+impl<T> PriorKnowledge<T> {
+    pub(crate) fn new(io: T, buf: BytesMut, timer: Option<Delay>) -> Self {
+        PriorKnowledge {
+            io: Some(io),
+            buf,
+            timer,
+        }
+    }
+
+    fn take(&mut self, is_h2: bool) -> (T, BytesMut, bool) {
+        let buf = mem::replace(&mut self.buf, BytesMut::new());
+        (self.io.take().unwrap(), buf, is_h2)
+    }
+}
+
+impl<T> Future for PriorKnowledge<T>
+where
+    T: AsyncRead + Unpin,
+{
+    type Output = Result<(T, BytesMut, bool), DispatchError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            let n = cmp::min(this.buf.len(), PREFACE.len());
+            if this.buf[..n] != PREFACE[..n] {
+                return Poll::Ready(Ok(this.take(false)));
+            } else if n == PREFACE.len() {
+                return Poll::Ready(Ok(this.take(true)));
+            }
+
+            if this.buf.capacity() - this.buf.len() < PREFACE.len() {
+                this.buf.reserve(PREFACE.len());
+            }
+            match Pin::new(this.io.as_mut().unwrap()).poll_read_buf(cx, &mut this.buf) {
+                // http/1 dispatcher handles disconnect
+                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(this.take(false))),
+                Poll::Ready(Ok(_)) => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => {
+                    // slow client gets `408 Request Timeout` from http/1 dispatcher
+                    if let Some(ref mut timer) = this.timer {
+                        if Pin::new(timer).poll(cx).is_ready() {
+                            return Poll::Ready(Ok(this.take(false)));
+                        }
+                    }
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Io stream that yields buffered data before reading from inner stream.
+pub(crate) struct Rewind<T> {
+    pre: Bytes,
+    io: T,
+}
+
+impl<T> Rewind<T> {
+    pub(crate) fn new(io: T, pre: Bytes) -> Self {
+        Rewind { pre, io }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Rewind<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.pre.is_empty() {
+            let len = cmp::min(buf.len(), self.pre.len());
+            buf[..len].copy_from_slice(&self.pre[..len]);
+            self.pre.advance(len);
+            return Poll::Ready(Ok(len));
+        }
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Rewind<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    #[test]
+    fn test_settings() {
+        let mut head = RequestHead::default();
+        head.headers
+            .insert(CONNECTION, "Upgrade, HTTP2-Settings".parse().unwrap());
+        head.headers.insert(UPGRADE, "h2c".parse().unwrap());
+        assert!(settings(&head).is_none());
+
+        head.headers.insert(
+            HeaderName::from_static("http2-settings"),
+            "AAMAAABkAAQAAP__".parse().unwrap(),
+        );
+        let val = settings(&head).unwrap();
+        assert_eq!(&val[..], &[0, 3, 0, 0, 0, 100, 0, 4, 0, 0, 255, 255][..]);
+
+        head.headers.append(
+            HeaderName::from_static("http2-settings"),
+            "AAMAAABkAAQAAP__".parse().unwrap(),
+        );
+        assert!(settings(&head).is_none());
+
+        head.headers.insert(UPGRADE, "websocket".parse().unwrap());
+        assert!(settings(&head).is_none());
+    }
+
+    #[test]
+    fn test_headers_frame() {
+        let mut head = RequestHead::default();
+        head.method = Method::POST;
+        head.uri = "/index.html?q=1".parse().unwrap();
+        head.headers.insert(HOST, "localhost".parse().unwrap());
+        head.headers.insert(UPGRADE, "h2c".parse().unwrap());
+
+        let frame = headers_frame(&head);
+        let len = (frame[1] as usize) << 8 | frame[2] as usize;
+        assert_eq!(frame.len(), len + FRAME_HEADER_SIZE);
+        assert_eq!(frame[3], HEADERS);
+        assert_eq!(frame[4], END_STREAM | END_HEADERS);
+        assert_eq!(&frame[5..9], &[0, 0, 0, 1]);
+        assert_eq!(
+            &frame[9..],
+            &b"\0\x07:method\x04POST\0\x07:scheme\x04http\
+               \0\x05:path\x0f/index.html?q=1\0\x0a:authority\x09localhost"[..]
+        );
+
+        let mut dst = BytesMut::new();
+        encode_str(&mut dst, &[b'a'; 200]);
+        assert_eq!(&dst[..2], &[0x7f, 200 - 0x7f]);
+        assert_eq!(dst.len(), 202);
+    }
+}
@@ -7,9 +7,12 @@ use futures_core::Stream;
 use h2::RecvStream;
 
 mod dispatcher;
+pub(crate) mod h2c;
+mod push;
 mod service;
 
 pub use self::dispatcher::Dispatcher;
+pub use self::push::ServerPush;
 pub use self::service::H2Service;
 use crate::error::PayloadError;
 
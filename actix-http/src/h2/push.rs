@@ -0,0 +1,111 @@
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use http::Uri;
+
+/// Handle for HTTP/2 server push.
+///
+/// Handle is available in request extensions of HTTP/2 requests. Service
+/// could promise any number of resources until its response is ready,
+/// `PUSH_PROMISE` frames are sent right before the response and promised
+/// requests are handled by the same service as requests sent by client.
+///
+/// ```rust
+/// use actix_http::h2::ServerPush;
+/// use actix_http::{HttpMessage, Request, Response};
+///
+/// async fn index(req: Request) -> Result<Response, ()> {
+///     if let Some(push) = req.extensions().get::<ServerPush>() {
+///         push.push("/style.css");
+///     }
+///     Ok(Response::Ok().finish())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ServerPush(Rc<Inner>);
+
+struct Inner {
+    enabled: Rc<Cell<bool>>,
+    promised: RefCell<Vec<Uri>>,
+    closed: Cell<bool>,
+}
+
+impl ServerPush {
+    pub(crate) fn new(enabled: Rc<Cell<bool>>) -> Self {
+        ServerPush(Rc::new(Inner {
+            enabled,
+            promised: RefCell::new(Vec::new()),
+            closed: Cell::new(false),
+        }))
+    }
+
+    /// Check if push is not refused on this connection yet.
+    ///
+    /// Returns `true` until the first push promise is refused, it does not
+    /// reflect `SETTINGS_ENABLE_PUSH` setting of client, which is not exposed
+    /// by the h2 connection. Push is disabled for the rest of connection once
+    /// client refuses a push promise.
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.get()
+    }
+
+    /// Promise `GET` request for `path` of the same origin.
+    ///
+    /// `path` is path and query of pushed resource, e.g. `/style.css`.
+    /// Returns `false` if path is not valid, push is disabled or response
+    /// is already sent.
+    pub fn push<U>(&self, path: U) -> bool
+    where
+        Uri: TryFrom<U>,
+    {
+        if self.0.closed.get() || !self.is_enabled() {
+            return false;
+        }
+        match Uri::try_from(path) {
+            Ok(uri) if uri.authority().is_none() && uri.path().starts_with('/') => {
+                self.0.promised.borrow_mut().push(uri);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Take promised requests, no more requests are accepted after this.
+    pub(crate) fn take(&self) -> Vec<Uri> {
+        self.0.closed.set(true);
+        std::mem::replace(&mut *self.0.promised.borrow_mut(), Vec::new())
+    }
+
+    pub(crate) fn disable(&self) {
+        self.0.enabled.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_push() {
+        let enabled = Rc::new(Cell::new(true));
+        let push = ServerPush::new(enabled.clone());
+        assert!(push.is_enabled());
+        assert!(push.push("/style.css"));
+        assert!(push.push("/app.js?v=1"));
+        assert!(!push.push("https://example.com/style.css"));
+        assert!(!push.push("style.css"));
+
+        let promised = push.take();
+        assert_eq!(promised.len(), 2);
+        assert_eq!(promised[1].path_and_query().unwrap(), "/app.js?v=1");
+        assert!(!push.push("/style.css"));
+
+        // push is disabled for whole connection
+        let push = ServerPush::new(enabled.clone());
+        push.disable();
+        assert!(!enabled.get());
+        assert!(!push.is_enabled());
+        assert!(!push.push("/style.css"));
+    }
+}
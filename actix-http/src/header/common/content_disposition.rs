@@ -10,7 +10,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use std::fmt::{self, Write};
 
-use crate::header::{self, ExtendedValue, Header, IntoHeaderValue, Writer};
+use crate::header::{self, Charset, ExtendedValue, Header, IntoHeaderValue, Writer};
 
 /// Split at the index of the first `needle` if it exists or at the end.
 fn split_once(haystack: &str, needle: char) -> (&str, &str) {
@@ -297,6 +297,72 @@ pub struct ContentDisposition {
 }
 
 impl ContentDisposition {
+    /// Create Content-Disposition header of disposition type without
+    /// parameters.
+    pub fn new(disposition: DispositionType) -> ContentDisposition {
+        ContentDisposition {
+            disposition,
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Create `attachment` Content-Disposition header with file name, so
+    /// content is downloaded and saved with this name.
+    pub fn attachment<T: AsRef<str>>(filename: T) -> ContentDisposition {
+        ContentDisposition::new(DispositionType::Attachment).filename(filename)
+    }
+
+    /// Create `inline` Content-Disposition header with file name, which is
+    /// used if content is saved.
+    pub fn inline<T: AsRef<str>>(filename: T) -> ContentDisposition {
+        ContentDisposition::new(DispositionType::Inline).filename(filename)
+    }
+
+    /// Set file name, replacing *filename* and *filename\** parameters.
+    ///
+    /// *filename* contains ASCII fallback of file name, non-ASCII and
+    /// control characters are replaced with `_`. If file name is changed by
+    /// this, it is sent in *filename\** parameter with UTF-8 charset as
+    /// well, as defined in [RFC5987](https://tools.ietf.org/html/rfc5987).
+    ///
+    /// ```
+    /// use actix_http::http::header::ContentDisposition;
+    ///
+    /// let cd = ContentDisposition::attachment("€ rates.pdf");
+    /// assert_eq!(
+    ///     cd.to_string(),
+    ///     "attachment; filename=\"_ rates.pdf\"; filename*=UTF-8''%E2%82%AC%20rates.pdf"
+    /// );
+    /// ```
+    pub fn filename<T: AsRef<str>>(mut self, filename: T) -> Self {
+        let filename = filename.as_ref();
+        let fallback: String = filename
+            .chars()
+            .map(|c| {
+                if c.is_ascii() && !c.is_ascii_control() {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.parameters
+            .retain(|p| !p.is_filename() && !p.is_filename_ext());
+        let ext = if fallback != filename {
+            Some(DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext(String::from("UTF-8")),
+                language_tag: None,
+                value: filename.as_bytes().to_vec(),
+            }))
+        } else {
+            None
+        };
+        self.parameters.push(DispositionParam::Filename(fallback));
+        self.parameters.extend(ext);
+        self
+    }
+
     /// Parse a raw Content-Disposition header value.
     pub fn from_raw(hv: &header::HeaderValue) -> Result<Self, crate::error::ParseError> {
         // `header::from_one_raw_str` invokes `hv.to_str` which assumes `hv` contains only visible
@@ -995,4 +1061,28 @@ mod tests {
         assert_eq!(cd.get_unknown_ext("dummy"), None);
         assert_eq!(cd.get_unknown("duMMy"), Some("3"));
     }
+
+    #[test]
+    fn test_builder() {
+        let cd = ContentDisposition::attachment("report.pdf");
+        assert_eq!(cd.to_string(), "attachment; filename=\"report.pdf\"");
+
+        let cd = ContentDisposition::inline("a \"quoted\" \\ name.txt");
+        assert_eq!(
+            cd.to_string(),
+            "inline; filename=\"a \\\"quoted\\\" \\\\ name.txt\""
+        );
+
+        let cd = ContentDisposition::attachment("old.txt").filename("新\nname.txt");
+        assert_eq!(cd.get_filename(), Some("__name.txt"));
+        assert_eq!(
+            cd.get_filename_ext().map(|ev| ev.value.as_ref()),
+            Some("新\nname.txt".as_bytes())
+        );
+        assert_eq!(
+            cd.to_string(),
+            "attachment; filename=\"__name.txt\"; filename*=UTF-8''%E6%96%B0%0Aname.txt"
+        );
+        assert!(HeaderValue::from_str(&cd.to_string()).is_ok());
+    }
 }
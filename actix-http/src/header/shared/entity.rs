@@ -75,6 +75,17 @@ impl EntityTag {
         EntityTag::new(false, tag)
     }
 
+    /// Constructs a new strong EntityTag from hash of content, so it
+    /// changes only if content changes.
+    pub fn from_content(content: &[u8]) -> EntityTag {
+        use sha1::Digest;
+        let hash = sha1::Sha1::digest(content);
+        EntityTag::strong(base64::encode_config(
+            hash.as_ref(),
+            base64::URL_SAFE_NO_PAD,
+        ))
+    }
+
     /// Get the tag.
     pub fn tag(&self) -> &str {
         self.tag.as_ref()
@@ -262,4 +273,13 @@ mod tests {
         assert!(!etag1.strong_ne(&etag2));
         assert!(!etag1.weak_ne(&etag2));
     }
+
+    #[test]
+    fn test_etag_from_content() {
+        let etag = EntityTag::from_content(b"content");
+        assert!(!etag.weak);
+        assert_eq!(etag, EntityTag::from_content(b"content"));
+        assert_ne!(etag, EntityTag::from_content(b"content2"));
+        assert_eq!(etag.to_string().parse::<EntityTag>().unwrap(), etag);
+    }
 }
@@ -194,6 +194,19 @@ impl<T: Clone + 'static> DataFactory for Data<T> {
     }
 }
 
+/// Data of several on-connect callbacks
+pub(crate) struct DataChain(
+    pub(crate) Box<dyn DataFactory>,
+    pub(crate) Box<dyn DataFactory>,
+);
+
+impl DataFactory for DataChain {
+    fn set(&self, ext: &mut Extensions) {
+        self.0.set(ext);
+        self.1.set(ext);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
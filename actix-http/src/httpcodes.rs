@@ -14,6 +14,7 @@ macro_rules! STATIC_RESP {
 }
 
 impl Response {
+    STATIC_RESP!(EarlyHints, StatusCode::from_u16(103).unwrap());
     STATIC_RESP!(Ok, StatusCode::OK);
     STATIC_RESP!(Created, StatusCode::CREATED);
     STATIC_RESP!(Accepted, StatusCode::ACCEPTED);
@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+
+/// Limit of idle keep-alive connections.
+///
+/// Limit could be shared by services of all workers of a server. Once
+/// number of idle connections exceeds the limit, least recently used idle
+/// connection of any worker gets closed.
+///
+/// ```rust
+/// use actix_http::{HttpService, IdleLimit, Request, Response};
+/// use futures::future::ok;
+///
+/// let limit = IdleLimit::new(1024);
+/// let srv = HttpService::build()
+///     .idle_limit(limit.clone())
+///     .finish(|_: Request| ok::<_, ()>(Response::Ok().finish()))
+///     .tcp();
+/// ```
+#[derive(Clone, Debug)]
+pub struct IdleLimit(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    max: AtomicUsize,
+    evicted: AtomicU64,
+    lru: Mutex<Lru>,
+}
+
+/// Idle connections of all services, ordered from least recently used
+#[derive(Debug, Default)]
+struct Lru {
+    entries: VecDeque<Arc<Entry>>,
+    live: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    evicted: AtomicBool,
+    done: AtomicBool,
+    task: Mutex<Option<Waker>>,
+}
+
+impl Entry {
+    fn is_live(&self) -> bool {
+        !self.evicted.load(Ordering::Acquire) && !self.done.load(Ordering::Acquire)
+    }
+}
+
+impl IdleLimit {
+    /// Create limit, value 0 disables it.
+    pub fn new(max: usize) -> Self {
+        IdleLimit(Arc::new(Inner {
+            max: AtomicUsize::new(max),
+            evicted: AtomicU64::new(0),
+            lru: Mutex::new(Lru::default()),
+        }))
+    }
+
+    /// Maximum number of idle connections, 0 if not limited
+    pub fn max(&self) -> usize {
+        self.0.max.load(Ordering::Relaxed)
+    }
+
+    /// Change maximum number of idle connections.
+    ///
+    /// New value applies to connections that become idle afterwards.
+    pub fn set_max(&self, max: usize) {
+        self.0.max.store(max, Ordering::Relaxed);
+    }
+
+    /// Number of idle keep-alive connections
+    pub fn idle(&self) -> usize {
+        self.0.lru.lock().unwrap().live
+    }
+
+    /// Total number of evicted connections
+    pub fn evicted(&self) -> u64 {
+        self.0.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Register connection that became idle.
+    ///
+    /// Returns `None` if idle connections are not limited.
+    pub(crate) fn enter(&self, cx: &mut Context<'_>) -> Option<IdleToken> {
+        let max = self.max();
+        if max == 0 {
+            return None;
+        }
+
+        let entry = Arc::new(Entry::default());
+        *entry.task.lock().unwrap() = Some(cx.waker().clone());
+
+        let mut lru = self.0.lru.lock().unwrap();
+        lru.live += 1;
+
+        // drop entries of connections that are not idle anymore
+        if lru.entries.len() > lru.live * 2 + 16 {
+            lru.entries.retain(|e| e.is_live());
+        }
+        lru.entries.push_back(entry.clone());
+
+        // evict least recently used connections
+        while lru.live > max {
+            let e = match lru.entries.pop_front() {
+                Some(e) => e,
+                None => break,
+            };
+            if e.is_live() {
+                e.evicted.store(true, Ordering::Release);
+                lru.live -= 1;
+                self.0.evicted.fetch_add(1, Ordering::Relaxed);
+                if let Some(task) = e.task.lock().unwrap().take() {
+                    task.wake();
+                }
+            }
+        }
+
+        Some(IdleToken {
+            limit: self.clone(),
+            entry,
+        })
+    }
+}
+
+/// Registration of idle connection, dropped once connection is not idle
+pub(crate) struct IdleToken {
+    limit: IdleLimit,
+    entry: Arc<Entry>,
+}
+
+impl IdleToken {
+    /// Check if connection got evicted, register waker otherwise.
+    pub(crate) fn poll_evicted(&self, cx: &mut Context<'_>) -> bool {
+        // flag is set before waker is taken, so waker is not lost
+        let mut task = self.entry.task.lock().unwrap();
+        if self.entry.evicted.load(Ordering::Acquire) {
+            return true;
+        }
+        match *task {
+            Some(ref waker) if waker.will_wake(cx.waker()) => (),
+            _ => *task = Some(cx.waker().clone()),
+        }
+        false
+    }
+}
+
+impl Drop for IdleToken {
+    fn drop(&mut self) {
+        let mut lru = self.limit.0.lru.lock().unwrap();
+        if self.entry.is_live() {
+            self.entry.done.store(true, Ordering::Release);
+            lru.live -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::task::noop_waker;
+
+    #[test]
+    fn test_idle_limit() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let limit = IdleLimit::new(0);
+        assert!(limit.enter(&mut cx).is_none());
+
+        limit.set_max(2);
+        let first = limit.enter(&mut cx).unwrap();
+        let second = limit.enter(&mut cx).unwrap();
+        assert_eq!(limit.idle(), 2);
+        assert!(!first.poll_evicted(&mut cx));
+
+        let third = limit.enter(&mut cx).unwrap();
+        assert_eq!(limit.idle(), 2);
+        assert_eq!(limit.evicted(), 1);
+        assert!(first.poll_evicted(&mut cx));
+        assert!(!second.poll_evicted(&mut cx));
+        assert!(!third.poll_evicted(&mut cx));
+
+        drop(first);
+        drop(second);
+        assert_eq!(limit.idle(), 1);
+        let _fourth = limit.enter(&mut cx).unwrap();
+        assert_eq!(limit.idle(), 2);
+        assert_eq!(limit.evicted(), 1);
+        assert!(!third.poll_evicted(&mut cx));
+    }
+
+    #[test]
+    fn test_idle_limit_shared() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let limit = IdleLimit::new(1);
+
+        // idle connection of other worker is evicted
+        let other = std::thread::spawn({
+            let limit = limit.clone();
+            move || {
+                let waker = noop_waker();
+                let mut cx = Context::from_waker(&waker);
+                limit.enter(&mut cx).unwrap()
+            }
+        })
+        .join()
+        .unwrap();
+        assert!(!other.poll_evicted(&mut cx));
+
+        let local = limit.enter(&mut cx).unwrap();
+        assert!(other.poll_evicted(&mut cx));
+        assert!(!local.poll_evicted(&mut cx));
+        assert_eq!(limit.idle(), 1);
+        assert_eq!(limit.evicted(), 1);
+    }
+}
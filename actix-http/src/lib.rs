@@ -22,11 +22,14 @@ mod header;
 mod helpers;
 mod httpcodes;
 pub mod httpmessage;
+mod idle;
 mod message;
 mod payload;
+mod proxy_protocol;
 mod request;
 mod response;
 mod service;
+mod trailers;
 
 pub mod cookie;
 pub mod error;
@@ -38,13 +41,18 @@ pub mod ws;
 pub use self::builder::HttpServiceBuilder;
 pub use self::config::{KeepAlive, ServiceConfig};
 pub use self::error::{Error, ResponseError, Result};
-pub use self::extensions::Extensions;
+pub use self::extensions::{ConnectionExtensions, Extensions};
 pub use self::httpmessage::HttpMessage;
-pub use self::message::{Message, RequestHead, RequestHeadType, ResponseHead};
+pub use self::idle::IdleLimit;
+pub use self::message::{
+    Message, ObjectPool, PoolConfig, PoolStats, RequestHead, RequestHeadType,
+    ResponseHead,
+};
 pub use self::payload::{Payload, PayloadStream};
 pub use self::request::Request;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
+pub use self::trailers::Trailers;
 
 pub mod http {
     //! Various HTTP related types
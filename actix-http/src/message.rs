@@ -1,6 +1,8 @@
 use std::cell::{Ref, RefCell, RefMut};
 use std::net;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use bitflags::bitflags;
 use copyless::BoxHelper;
@@ -35,6 +37,8 @@ bitflags! {
 pub trait Head: Default + 'static {
     fn clear(&mut self);
 
+    fn headers_capacity(&self) -> usize;
+
     fn pool() -> &'static MessagePool<Self>;
 }
 
@@ -70,6 +74,10 @@ impl Head for RequestHead {
         self.extensions.borrow_mut().clear();
     }
 
+    fn headers_capacity(&self) -> usize {
+        self.headers.capacity()
+    }
+
     fn pool() -> &'static MessagePool<Self> {
         REQUEST_POOL.with(|p| *p)
     }
@@ -418,28 +426,205 @@ impl Drop for BoxedResponseHead {
     }
 }
 
+/// Configuration of object pool, i.e. of request or response heads.
+///
+/// Pools are kept per thread, released objects are reused for next
+/// messages up to capacity of the pool. Objects with header map that grew
+/// over `max_headers_capacity()` are dropped instead of pooled, so
+/// occasional large messages do not keep memory allocated. Pool with zero
+/// capacity is disabled.
+///
+/// By default pool keeps up to 128 objects.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    capacity: usize,
+    max_headers_capacity: usize,
+    stats: Option<PoolStats>,
+}
+
+impl PoolConfig {
+    /// Create pool configuration with specified capacity.
+    pub fn new(capacity: usize) -> Self {
+        PoolConfig {
+            capacity,
+            max_headers_capacity: usize::max_value(),
+            stats: None,
+        }
+    }
+
+    /// Configuration of disabled pool, every message allocates new object.
+    pub fn disabled() -> Self {
+        PoolConfig::new(0)
+    }
+
+    /// Set max capacity of header map of pooled object.
+    ///
+    /// By default capacity is not limited.
+    pub fn max_headers_capacity(mut self, val: usize) -> Self {
+        self.max_headers_capacity = val;
+        self
+    }
+
+    /// Set stats handle, pool records hits and misses to it.
+    pub fn stats(mut self, stats: PoolStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Record result of pool lookup
+    fn record(&self, hit: bool) {
+        if let Some(ref stats) = self.stats {
+            if hit {
+                stats.0.hits.fetch_add(1, Ordering::Relaxed);
+            } else {
+                stats.0.misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Check if released object could be added to pool of `len` objects
+    fn accept(&self, len: usize, headers_capacity: usize) -> bool {
+        if len < self.capacity && headers_capacity <= self.max_headers_capacity {
+            true
+        } else {
+            if let Some(ref stats) = self.stats {
+                stats.0.discarded.fetch_add(1, Ordering::Relaxed);
+            }
+            false
+        }
+    }
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig::new(128)
+    }
+}
+
+/// Counters of object pool.
+///
+/// Stats handle is registered with `PoolConfig::stats()`, it could be
+/// cloned and shared by pools of different threads.
+#[derive(Clone, Default, Debug)]
+pub struct PoolStats(Arc<Stats>);
+
+#[derive(Default, Debug)]
+struct Stats {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    discarded: AtomicUsize,
+}
+
+impl PoolStats {
+    /// Create new stats handle.
+    pub fn new() -> Self {
+        PoolStats::default()
+    }
+
+    /// Number of objects taken from pool.
+    pub fn hits(&self) -> usize {
+        self.0.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of objects allocated because pool is empty.
+    pub fn misses(&self) -> usize {
+        self.0.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of released objects dropped because pool is full or header
+    /// map of object is too large.
+    pub fn discarded(&self) -> usize {
+        self.0.discarded.load(Ordering::Relaxed)
+    }
+}
+
+/// Object pool that follows `PoolConfig`.
+///
+/// Pool is not thread safe, it is meant to be kept per thread like pools
+/// of request and response heads. Hits, misses and discarded objects are
+/// recorded to stats handle of configuration.
+pub struct ObjectPool<T> {
+    pool: RefCell<Vec<T>>,
+    config: RefCell<PoolConfig>,
+}
+
+impl<T> ObjectPool<T> {
+    /// Create new empty pool.
+    pub fn new(config: PoolConfig) -> Self {
+        ObjectPool {
+            pool: RefCell::new(Vec::with_capacity(config.capacity.min(128))),
+            config: RefCell::new(config),
+        }
+    }
+
+    /// Replace configuration, objects over new capacity are dropped.
+    pub fn configure(&self, config: PoolConfig) {
+        let mut pool = self.pool.borrow_mut();
+        pool.truncate(config.capacity);
+        pool.shrink_to_fit();
+        *self.config.borrow_mut() = config;
+    }
+
+    /// Take object from the pool, `None` if pool is empty.
+    pub fn get(&self) -> Option<T> {
+        let obj = self.pool.borrow_mut().pop();
+        self.config.borrow().record(obj.is_some());
+        obj
+    }
+
+    /// Return object to the pool.
+    ///
+    /// Object is dropped if pool is full or `headers_capacity` is over
+    /// `PoolConfig::max_headers_capacity()`.
+    pub fn release(&self, obj: T, headers_capacity: usize) {
+        let mut pool = self.pool.borrow_mut();
+        if self.config.borrow().accept(pool.len(), headers_capacity) {
+            pool.push(obj);
+        }
+    }
+
+    /// Drop all pooled objects.
+    pub fn clear(&self) {
+        self.pool.borrow_mut().clear()
+    }
+}
+
+/// Set pool configuration of request heads of current thread.
+pub(crate) fn set_request_pool(config: PoolConfig) {
+    RequestHead::pool().configure(config)
+}
+
+/// Set pool configuration of response heads of current thread.
+pub(crate) fn set_response_pool(config: PoolConfig) {
+    RESPONSE_POOL.with(|p| p.configure(config))
+}
+
 #[doc(hidden)]
 /// Request's objects pool
-pub struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>);
+pub struct MessagePool<T: Head>(ObjectPool<Rc<T>>);
 
 #[doc(hidden)]
-#[allow(clippy::vec_box)]
 /// Request's objects pool
-pub struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>);
+pub struct BoxedResponsePool(ObjectPool<Box<ResponseHead>>);
 
 thread_local!(static REQUEST_POOL: &'static MessagePool<RequestHead> = MessagePool::<RequestHead>::create());
 thread_local!(static RESPONSE_POOL: &'static BoxedResponsePool = BoxedResponsePool::create());
 
 impl<T: Head> MessagePool<T> {
     fn create() -> &'static MessagePool<T> {
-        let pool = MessagePool(RefCell::new(Vec::with_capacity(128)));
+        let pool = MessagePool(ObjectPool::new(PoolConfig::default()));
         Box::leak(Box::new(pool))
     }
 
+    /// Replace configuration, objects over new capacity are dropped
+    fn configure(&self, config: PoolConfig) {
+        self.0.configure(config)
+    }
+
     /// Get message from the pool
     #[inline]
     fn get_message(&'static self) -> Message<T> {
-        if let Some(mut msg) = self.0.borrow_mut().pop() {
+        if let Some(mut msg) = self.0.get() {
             if let Some(r) = Rc::get_mut(&mut msg) {
                 r.clear();
             }
@@ -454,23 +639,26 @@ impl<T: Head> MessagePool<T> {
     #[inline]
     /// Release request instance
     fn release(&self, msg: Rc<T>) {
-        let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
-            v.push(msg);
-        }
+        let headers_capacity = msg.headers_capacity();
+        self.0.release(msg, headers_capacity)
     }
 }
 
 impl BoxedResponsePool {
     fn create() -> &'static BoxedResponsePool {
-        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(128)));
+        let pool = BoxedResponsePool(ObjectPool::new(PoolConfig::default()));
         Box::leak(Box::new(pool))
     }
 
+    /// Replace configuration, objects over new capacity are dropped
+    fn configure(&self, config: PoolConfig) {
+        self.0.configure(config)
+    }
+
     /// Get message from the pool
     #[inline]
     fn get_message(&'static self, status: StatusCode) -> BoxedResponseHead {
-        if let Some(mut head) = self.0.borrow_mut().pop() {
+        if let Some(mut head) = self.0.get() {
             head.reason = None;
             head.status = status;
             head.headers.clear();
@@ -486,10 +674,62 @@ impl BoxedResponsePool {
     #[inline]
     /// Release request instance
     fn release(&self, msg: Box<ResponseHead>) {
-        let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
-            msg.extensions.borrow_mut().clear();
-            v.push(msg);
+        let headers_capacity = msg.headers.capacity();
+        msg.extensions.borrow_mut().clear();
+        self.0.release(msg, headers_capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HeaderName, HeaderValue};
+
+    #[test]
+    fn test_request_pool() {
+        let stats = PoolStats::new();
+        let pool = MessagePool::<RequestHead>::create();
+        pool.configure(
+            PoolConfig::new(1)
+                .max_headers_capacity(32)
+                .stats(stats.clone()),
+        );
+
+        // dropped message returns its head to pool of current thread unless
+        // head is shared, so heads are released to fresh pool explicitly
+        let msg = pool.get_message();
+        let msg2 = pool.get_message();
+        pool.release(msg.head.clone());
+        pool.release(msg2.head.clone());
+        drop((msg, msg2));
+        assert_eq!(stats.misses(), 2);
+        assert_eq!(stats.discarded(), 1);
+
+        let mut msg = pool.get_message();
+        assert_eq!(stats.hits(), 1);
+        for idx in 0..64 {
+            msg.headers.append(
+                HeaderName::from_bytes(format!("x-{}", idx).as_bytes()).unwrap(),
+                HeaderValue::from_static("value"),
+            );
         }
+        pool.release(msg.head.clone());
+        drop(msg);
+        assert_eq!(stats.discarded(), 2);
+
+        let _msg = pool.get_message();
+        assert_eq!(stats.misses(), 3);
+    }
+
+    #[test]
+    fn test_response_pool_disabled() {
+        let stats = PoolStats::new();
+        set_response_pool(PoolConfig::disabled().stats(stats.clone()));
+
+        drop(BoxedResponseHead::new(StatusCode::OK));
+        drop(BoxedResponseHead::new(StatusCode::OK));
+        assert_eq!(stats.hits(), 0);
+        assert_eq!(stats.misses(), 2);
+        assert_eq!(stats.discarded(), 2);
     }
 }
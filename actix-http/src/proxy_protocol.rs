@@ -0,0 +1,239 @@
+//! HAProxy PROXY protocol support.
+//!
+//! Both the human-readable (v1) and the binary (v2) formats of the
+//! [PROXY protocol](https://www.haproxy.org/download/1.8/doc/proxy-protocol.txt)
+//! are supported. Header is read byte-exact from the connection, so any data
+//! sent after it is left untouched for the protocol dispatcher.
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix_codec::AsyncRead;
+use actix_rt::time::{delay_for, Delay};
+use futures_util::future::{poll_fn, select, Either};
+use futures_util::pin_mut;
+
+/// v2 header signature
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+/// v1 header prefix
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Maximum length of v1 header, including CRLF
+const V1_MAX_LENGTH: usize = 107;
+/// Header timeout, used if client timeout is disabled
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read PROXY protocol header from the stream.
+///
+/// Returns the source address announced by the proxy. `None` is returned
+/// for `LOCAL` (v2) and `UNKNOWN` (v1) connections, and for address families
+/// other than TCP over IPv4/IPv6.
+pub(crate) async fn read_header<T>(io: &mut T) -> io::Result<Option<SocketAddr>>
+where
+    T: AsyncRead + Unpin,
+{
+    // both v1 and v2 headers are at least 12 bytes long
+    let mut buf = vec![0; 12];
+    read_exact(io, &mut buf).await?;
+
+    if &buf[..] == V2_SIGNATURE {
+        buf.resize(16, 0);
+        read_exact(io, &mut buf[12..]).await?;
+        let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        buf.resize(16 + len, 0);
+        read_exact(io, &mut buf[16..]).await?;
+        parse_v2(&buf)
+    } else if buf.starts_with(V1_PREFIX) {
+        // v1 header does not carry its own length, read until CRLF
+        while !buf.ends_with(b"\r\n") {
+            if buf.len() >= V1_MAX_LENGTH {
+                return Err(invalid("PROXY v1 header is too long"));
+            }
+            let mut b = [0; 1];
+            read_exact(io, &mut b).await?;
+            buf.push(b[0]);
+        }
+        parse_v1(&buf)
+    } else {
+        Err(invalid("PROXY protocol header is expected"))
+    }
+}
+
+/// Read PROXY protocol header, giving up once `timer` expires.
+///
+/// Header read is never unbounded, without `timer` it is limited
+/// to 5 seconds.
+pub(crate) async fn read_header_timeout<T>(
+    io: &mut T,
+    timer: Option<Delay>,
+) -> io::Result<Option<SocketAddr>>
+where
+    T: AsyncRead + Unpin,
+{
+    let fut = read_header(io);
+    pin_mut!(fut);
+
+    let timer = timer.unwrap_or_else(|| delay_for(HEADER_TIMEOUT));
+    match select(fut, timer).await {
+        Either::Left((res, _)) => res,
+        Either::Right(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "PROXY protocol header timeout",
+        )),
+    }
+}
+
+/// Parse complete v1 header, including trailing CRLF.
+fn parse_v1(buf: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..buf.len() - 2])
+        .map_err(|_| invalid("PROXY v1 header is not valid utf-8"))?;
+    let mut parts = line.split(' ');
+
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => (),
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid("Unsupported PROXY v1 protocol")),
+    }
+
+    let src: IpAddr = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid PROXY v1 source address"))?;
+    let _dst: IpAddr = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid PROXY v1 destination address"))?;
+    let port: u16 = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid PROXY v1 source port"))?;
+    let _dst_port: u16 = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid PROXY v1 destination port"))?;
+
+    if parts.next().is_some() {
+        return Err(invalid("Unexpected data in PROXY v1 header"));
+    }
+    Ok(Some(SocketAddr::new(src, port)))
+}
+
+/// Parse complete v2 header, including address block.
+fn parse_v2(buf: &[u8]) -> io::Result<Option<SocketAddr>> {
+    if buf[12] >> 4 != 2 {
+        return Err(invalid("Unsupported PROXY protocol version"));
+    }
+    match buf[12] & 0x0F {
+        // LOCAL command, connection established by proxy itself
+        0x0 => return Ok(None),
+        // PROXY command
+        0x1 => (),
+        _ => return Err(invalid("Unsupported PROXY v2 command")),
+    }
+
+    let addr = &buf[16..];
+    match buf[13] {
+        // TCP over IPv4
+        0x11 if addr.len() >= 12 => {
+            let ip = Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]);
+            let port = u16::from_be_bytes([addr[8], addr[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(ip), port)))
+        }
+        // TCP over IPv6
+        0x21 if addr.len() >= 36 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&addr[..16]);
+            let port = u16::from_be_bytes([addr[32], addr[33]]);
+            Ok(Some(SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(octets)),
+                port,
+            )))
+        }
+        0x11 | 0x21 => Err(invalid("PROXY v2 address block is too short")),
+        // UNSPEC, UDP and unix sockets
+        _ => Ok(None),
+    }
+}
+
+async fn read_exact<T>(io: &mut T, buf: &mut [u8]) -> io::Result<()>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut pos = 0;
+    while pos < buf.len() {
+        let n = poll_fn(|cx| Pin::new(&mut *io).poll_read(cx, &mut buf[pos..])).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        pos += n;
+    }
+    Ok(())
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestBuffer;
+
+    #[actix_rt::test]
+    async fn test_v1() {
+        let mut buf = TestBuffer::new(
+            "PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET / HTTP/1.1\r\n",
+        );
+        let addr = read_header(&mut buf).await.unwrap();
+        assert_eq!(addr, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(&buf.read_buf[..], b"GET / HTTP/1.1\r\n");
+
+        let mut buf = TestBuffer::new("PROXY TCP6 ::1 ::1 8080 80\r\n");
+        let addr = read_header(&mut buf).await.unwrap();
+        assert_eq!(addr, Some("[::1]:8080".parse().unwrap()));
+
+        let mut buf = TestBuffer::new("PROXY UNKNOWN\r\nGET");
+        assert_eq!(read_header(&mut buf).await.unwrap(), None);
+        assert_eq!(&buf.read_buf[..], b"GET");
+    }
+
+    #[actix_rt::test]
+    async fn test_v1_errors() {
+        let mut buf = TestBuffer::new("GET / HTTP/1.1\r\n\r\n");
+        assert!(read_header(&mut buf).await.is_err());
+
+        let mut buf = TestBuffer::new("PROXY TCP4 192.168.0.1 56324 443\r\n");
+        assert!(read_header(&mut buf).await.is_err());
+
+        let mut buf = TestBuffer::new("PROXY UDP4 192.168.0.1 192.168.0.11 1 2\r\n");
+        assert!(read_header(&mut buf).await.is_err());
+
+        let mut buf =
+            TestBuffer::new(format!("PROXY TCP4 {}\r\n", "1".repeat(120)).as_str());
+        assert!(read_header(&mut buf).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_v2() {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0, 80]);
+        data.extend_from_slice(b"GET");
+        let mut buf = TestBuffer::new(&data[..]);
+        let addr = read_header(&mut buf).await.unwrap();
+        assert_eq!(addr, Some("10.0.0.1:8080".parse().unwrap()));
+        assert_eq!(&buf.read_buf[..], b"GET");
+
+        // LOCAL command
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        let mut buf = TestBuffer::new(&data[..]);
+        assert_eq!(read_header(&mut buf).await.unwrap(), None);
+
+        // truncated address block
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x21, 0, 4, 0, 0, 0, 0]);
+        let mut buf = TestBuffer::new(&data[..]);
+        assert!(read_header(&mut buf).await.is_err());
+    }
+}
@@ -8,10 +8,14 @@ use std::{fmt, str};
 
 use bytes::{Bytes, BytesMut};
 use futures_core::Stream;
+use futures_util::TryStreamExt;
 use serde::Serialize;
 use serde_json;
 
-use crate::body::{Body, BodyStream, MessageBody, ResponseBody};
+use crate::body::{
+    Body, BodyStream, BodyTransform, MessageBody, ResponseBody, SizedStream,
+    TransformBody,
+};
 use crate::cookie::{Cookie, CookieJar};
 use crate::error::Error;
 use crate::extensions::Extensions;
@@ -19,6 +23,7 @@ use crate::header::{Header, IntoHeaderValue};
 use crate::http::header::{self, HeaderName, HeaderValue};
 use crate::http::{Error as HttpError, HeaderMap, StatusCode};
 use crate::message::{BoxedResponseHead, ConnectionType, ResponseHead};
+use crate::trailers::Trailers;
 
 /// An HTTP Response
 pub struct Response<B = Body> {
@@ -262,6 +267,22 @@ impl<B> Response<B> {
     pub fn take_body(&mut self) -> ResponseBody<B> {
         self.body.take_body()
     }
+
+    /// Transform body chunk by chunk while it is streamed
+    ///
+    /// `Content-Length` header is removed, size of transformed body is not
+    /// known, so body is sent with chunked transfer encoding.
+    pub fn transform_body<T>(
+        mut self,
+        transform: T,
+    ) -> Response<TransformBody<ResponseBody<B>, T>>
+    where
+        B: MessageBody,
+        T: BodyTransform,
+    {
+        self.headers_mut().remove(header::CONTENT_LENGTH);
+        self.map_body(|_, body| ResponseBody::Body(TransformBody::new(body, transform)))
+    }
 }
 
 impl<B: MessageBody> fmt::Debug for Response<B> {
@@ -368,6 +389,36 @@ impl ResponseBuilder {
         self
     }
 
+    /// Insert a typed header, replacing existing values of the header.
+    ///
+    /// Typed headers implement `Header` trait and are formatted by their
+    /// type, i.e. `ContentDisposition`, `CacheControl` or `ETag`.
+    ///
+    /// ```rust
+    /// use actix_http::{http::header, Response};
+    ///
+    /// fn index() -> Response {
+    ///     Response::Ok()
+    ///         .insert_typed(header::CacheControl(vec![
+    ///             header::CacheDirective::Public,
+    ///             header::CacheDirective::MaxAge(3600),
+    ///         ]))
+    ///         .insert_typed(header::ETag(header::EntityTag::strong("v1".to_owned())))
+    ///         .finish()
+    /// }
+    /// ```
+    pub fn insert_typed<H: Header>(&mut self, hdr: H) -> &mut Self {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            match hdr.try_into() {
+                Ok(value) => {
+                    parts.headers.insert(H::name(), value);
+                }
+                Err(e) => self.err = Some(e.into()),
+            }
+        }
+        self
+    }
+
     /// Append a header to existing headers.
     ///
     /// ```rust
@@ -480,6 +531,18 @@ impl ResponseBuilder {
         self
     }
 
+    /// Send trailer fields after chunked response body.
+    ///
+    /// Fields could be added to provided handle until response body is
+    /// complete. Trailer names should be declared with `Trailer` header.
+    #[inline]
+    pub fn trailers(&mut self, trailers: Trailers) -> &mut Self {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            parts.extensions.get_mut().insert(trailers);
+        }
+        self
+    }
+
     /// Set response content type
     #[inline]
     pub fn content_type<V>(&mut self, value: V) -> &mut Self
@@ -559,6 +622,32 @@ impl ResponseBuilder {
         self
     }
 
+    /// Remove cookie by name, `path` and `domain` have to match attributes
+    /// of the cookie that was set.
+    ///
+    /// ```rust
+    /// use actix_http::Response;
+    ///
+    /// fn logout() -> Response {
+    ///     Response::Ok()
+    ///         .del_cookie_by_name("session", "/", Some("example.com"))
+    ///         .finish()
+    /// }
+    /// ```
+    pub fn del_cookie_by_name(
+        &mut self,
+        name: &str,
+        path: &str,
+        domain: Option<&str>,
+    ) -> &mut Self {
+        let mut cookie = Cookie::new(name.to_owned(), "");
+        cookie.set_path(path.to_owned());
+        if let Some(domain) = domain {
+            cookie.set_domain(domain.to_owned());
+        }
+        self.del_cookie(&cookie)
+    }
+
     /// This method calls provided closure with builder reference if value is
     /// true.
     pub fn if_true<F>(&mut self, value: bool, f: F) -> &mut Self
@@ -643,6 +732,24 @@ impl ResponseBuilder {
         self.body(Body::from_message(BodyStream::new(stream)))
     }
 
+    #[inline]
+    /// Set a streaming body of known length and generate `Response`.
+    ///
+    /// Response is sent with `Content-Length` header instead of chunked
+    /// transfer encoding, stream must produce exactly `length` bytes.
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    pub fn sized_streaming<S, E>(&mut self, length: u64, stream: S) -> Response
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        self.body(Body::from_message(SizedStream::new(
+            length,
+            stream.map_err(Into::into),
+        )))
+    }
+
     #[inline]
     /// Set a json body and generate `Response`
     ///
@@ -853,7 +960,7 @@ impl From<BytesMut> for Response {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::body::Body;
+    use crate::body::{Body, BodySize};
     use crate::http::header::{HeaderValue, CONTENT_TYPE, COOKIE, SET_COOKIE};
 
     #[test]
@@ -901,6 +1008,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cookie_same_site_and_del_by_name() {
+        let resp = Response::Ok()
+            .cookie(
+                crate::http::Cookie::build("cross", "value")
+                    .secure(true)
+                    .same_site(crate::cookie::SameSite::None)
+                    .finish(),
+            )
+            .del_cookie_by_name("session", "/app", Some("example.com"))
+            .finish();
+
+        let mut val: Vec<_> = resp
+            .headers()
+            .get_all(SET_COOKIE)
+            .map(|v| v.to_str().unwrap().to_owned())
+            .collect();
+        val.sort();
+        assert_eq!(val[0], "cross=value; Secure; SameSite=None");
+        assert!(
+            val[1].starts_with("session=; Path=/app; Domain=example.com; Max-Age=0;")
+        );
+    }
+
     #[test]
     fn test_update_response_cookies() {
         let mut r = Response::Ok()
@@ -942,6 +1073,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_insert_typed() {
+        let resp = Response::Ok()
+            .header(header::CACHE_CONTROL, "no-cache")
+            .insert_typed(header::CacheControl(vec![
+                header::CacheDirective::Private,
+                header::CacheDirective::MaxAge(60),
+            ]))
+            .insert_typed(header::ContentDisposition {
+                disposition: header::DispositionType::Attachment,
+                parameters: vec![header::DispositionParam::Filename(
+                    "file.txt".to_owned(),
+                )],
+            })
+            .finish();
+        let values: Vec<_> = resp.headers().get_all(header::CACHE_CONTROL).collect();
+        assert_eq!(values, vec!["private, max-age=60"]);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"file.txt\""
+        );
+    }
+
     #[test]
     fn test_force_close() {
         let resp = Response::build(StatusCode::OK).force_close().finish();
@@ -964,6 +1118,18 @@ mod tests {
         assert_eq!(resp.body().get_ref(), b"[\"v1\",\"v2\",\"v3\"]");
     }
 
+    #[test]
+    fn test_sized_streaming() {
+        let resp = Response::Ok().sized_streaming(
+            6,
+            futures_util::stream::iter(vec![
+                Ok::<_, Error>(Bytes::from_static(b"foo")),
+                Ok(Bytes::from_static(b"bar")),
+            ]),
+        );
+        assert_eq!(resp.body().size(), BodySize::Sized64(6));
+    }
+
     #[test]
     fn test_json_ct() {
         let resp = Response::build(StatusCode::OK)
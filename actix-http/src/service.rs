@@ -6,10 +6,9 @@ use std::{fmt, net, rc};
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
 use actix_rt::net::TcpStream;
 use actix_service::{pipeline_factory, IntoServiceFactory, Service, ServiceFactory};
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_core::{ready, Future};
-use futures_util::future::ok;
-use h2::server::{self, Handshake};
+use h2::server::Handshake;
 use pin_project::{pin_project, project};
 
 use crate::body::MessageBody;
@@ -17,10 +16,11 @@ use crate::builder::HttpServiceBuilder;
 use crate::cloneable::CloneableService;
 use crate::config::{KeepAlive, ServiceConfig};
 use crate::error::{DispatchError, Error};
+use crate::h2::h2c::{Preface, PriorKnowledge, Rewind};
 use crate::helpers::DataFactory;
 use crate::request::Request;
 use crate::response::Response;
-use crate::{h1, h2::Dispatcher, Protocol};
+use crate::{h1, h2::Dispatcher, proxy_protocol, Protocol};
 
 /// `ServiceFactory` HTTP1.1/HTTP2 transport implementation
 pub struct HttpService<T, S, B, X = h1::ExpectHandler, U = h1::UpgradeHandler<T>> {
@@ -29,6 +29,7 @@ pub struct HttpService<T, S, B, X = h1::ExpectHandler, U = h1::UpgradeHandler<T>
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<rc::Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
+    h2c: bool,
     _t: PhantomData<(T, B)>,
 }
 
@@ -66,6 +67,7 @@ where
             expect: h1::ExpectHandler,
             upgrade: None,
             on_connect: None,
+            h2c: false,
             _t: PhantomData,
         }
     }
@@ -81,6 +83,7 @@ where
             expect: h1::ExpectHandler,
             upgrade: None,
             on_connect: None,
+            h2c: false,
             _t: PhantomData,
         }
     }
@@ -113,6 +116,7 @@ where
             srv: self.srv,
             upgrade: self.upgrade,
             on_connect: self.on_connect,
+            h2c: self.h2c,
             _t: PhantomData,
         }
     }
@@ -138,6 +142,7 @@ where
             srv: self.srv,
             expect: self.expect,
             on_connect: self.on_connect,
+            h2c: self.h2c,
             _t: PhantomData,
         }
     }
@@ -175,7 +180,7 @@ where
 {
     /// Create simple tcp stream service
     pub fn tcp(
-        self,
+        mut self,
     ) -> impl ServiceFactory<
         Config = (),
         Request = TcpStream,
@@ -183,19 +188,43 @@ where
         Error = DispatchError,
         InitError = (),
     > {
-        pipeline_factory(|io: TcpStream| {
-            let peer_addr = io.peer_addr().ok();
-            ok((io, Protocol::Http1, peer_addr))
+        let cfg = self.cfg.clone();
+        // h2c upgrade is allowed for cleartext connections only
+        self.h2c = cfg.h2c();
+
+        pipeline_factory(move |mut io: TcpStream| {
+            let cfg = cfg.clone();
+            async move {
+                set_tcp_options(&cfg, &io);
+
+                let peer_addr = if cfg.proxy_protocol() {
+                    // `LOCAL` and `UNKNOWN` connections carry no source address
+                    proxy_protocol::read_header_timeout(&mut io, cfg.client_timer())
+                        .await?
+                        .or_else(|| io.peer_addr().ok())
+                } else {
+                    io.peer_addr().ok()
+                };
+                Ok::<_, DispatchError>((io, Protocol::Http1, peer_addr))
+            }
         })
         .and_then(self)
     }
 }
 
+/// Set socket options of accepted connection, errors are not fatal
+fn set_tcp_options(cfg: &ServiceConfig, io: &TcpStream) {
+    if let Err(e) = cfg.tcp_options().apply(io) {
+        log::debug!("Can not set socket options: {}", e);
+    }
+}
+
 #[cfg(feature = "openssl")]
 mod openssl {
     use super::*;
     use actix_tls::openssl::{Acceptor, SslAcceptor, SslStream};
     use actix_tls::{openssl::HandshakeError, SslError};
+    use futures_util::future::ok;
 
     impl<S, B, X, U> HttpService<SslStream<TcpStream>, S, B, X, U>
     where
@@ -229,7 +258,13 @@ mod openssl {
             Error = SslError<HandshakeError<TcpStream>, DispatchError>,
             InitError = (),
         > {
-            pipeline_factory(
+            let cfg = self.cfg.clone();
+
+            pipeline_factory(move |io: TcpStream| {
+                set_tcp_options(&cfg, &io);
+                ok::<_, SslError<HandshakeError<TcpStream>, DispatchError>>(io)
+            })
+            .and_then(
                 Acceptor::new(acceptor)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
@@ -257,6 +292,7 @@ mod rustls {
     use super::*;
     use actix_tls::rustls::{Acceptor, ServerConfig, Session, TlsStream};
     use actix_tls::SslError;
+    use futures_util::future::ok;
     use std::io;
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
@@ -280,7 +316,7 @@ mod rustls {
         U::InitError: fmt::Debug,
         <U::Service as Service>::Future: 'static,
     {
-        /// Create openssl based service
+        /// Create rustls based service
         pub fn rustls(
             self,
             mut config: ServerConfig,
@@ -294,7 +330,13 @@ mod rustls {
             let protos = vec!["h2".to_string().into(), "http/1.1".to_string().into()];
             config.set_protocols(&protos);
 
-            pipeline_factory(
+            let cfg = self.cfg.clone();
+
+            pipeline_factory(move |io: TcpStream| {
+                set_tcp_options(&cfg, &io);
+                ok::<_, SslError<io::Error, DispatchError>>(io)
+            })
+            .and_then(
                 Acceptor::new(config)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
@@ -356,6 +398,7 @@ where
             upgrade: None,
             on_connect: self.on_connect.clone(),
             cfg: self.cfg.clone(),
+            h2c: self.h2c,
             _t: PhantomData,
         }
     }
@@ -380,6 +423,7 @@ pub struct HttpServiceResponse<
     upgrade: Option<U::Service>,
     on_connect: Option<rc::Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
     cfg: ServiceConfig,
+    h2c: bool,
     _t: PhantomData<(T, B)>,
 }
 
@@ -437,6 +481,7 @@ where
                 this.expect.take().unwrap(),
                 this.upgrade.take(),
                 this.on_connect.clone(),
+                *this.h2c,
             )
         }))
     }
@@ -449,6 +494,7 @@ pub struct HttpServiceHandler<T, S: Service, B, X: Service, U: Service> {
     upgrade: Option<CloneableService<U>>,
     cfg: ServiceConfig,
     on_connect: Option<rc::Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
+    h2c: bool,
     _t: PhantomData<(T, B, X)>,
 }
 
@@ -470,10 +516,12 @@ where
         expect: X,
         upgrade: Option<U>,
         on_connect: Option<rc::Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
+        h2c: bool,
     ) -> HttpServiceHandler<T, S, B, X, U> {
         HttpServiceHandler {
             cfg,
             on_connect,
+            h2c,
             srv: CloneableService::new(srv),
             expect: CloneableService::new(expect),
             upgrade: upgrade.map(CloneableService::new),
@@ -552,13 +600,31 @@ where
         match proto {
             Protocol::Http2 => HttpServiceHandlerResponse {
                 state: State::H2Handshake(Some((
-                    server::handshake(io),
+                    self.cfg
+                        .h2_options()
+                        .handshake(Rewind::new(io, Bytes::new())),
                     self.cfg.clone(),
                     self.srv.clone(),
                     on_connect,
                     peer_addr,
                 ))),
             },
+            // cleartext connection may start with http/2 preface
+            Protocol::Http1 if self.h2c => HttpServiceHandlerResponse {
+                state: State::H2PriorKnowledge(Some((
+                    PriorKnowledge::new(
+                        io,
+                        BytesMut::with_capacity(self.cfg.buffer_capacity()),
+                        self.cfg.client_timer(),
+                    ),
+                    self.cfg.clone(),
+                    self.srv.clone(),
+                    self.expect.clone(),
+                    self.upgrade.clone(),
+                    on_connect,
+                    peer_addr,
+                ))),
+            },
             Protocol::Http1 => HttpServiceHandlerResponse {
                 state: State::H1(h1::Dispatcher::new(
                     io,
@@ -588,10 +654,30 @@ where
     U::Error: fmt::Display,
 {
     H1(#[pin] h1::Dispatcher<T, S, B, X, U>),
-    H2(#[pin] Dispatcher<T, S, B>),
+    H2(#[pin] Dispatcher<Rewind<T>, S, B>),
+    H2PriorKnowledge(
+        Option<(
+            PriorKnowledge<T>,
+            ServiceConfig,
+            CloneableService<S>,
+            CloneableService<X>,
+            Option<CloneableService<U>>,
+            Option<Box<dyn DataFactory>>,
+            Option<net::SocketAddr>,
+        )>,
+    ),
+    H2cPreface(
+        Option<(
+            Preface<T>,
+            ServiceConfig,
+            CloneableService<S>,
+            Option<Box<dyn DataFactory>>,
+            Option<net::SocketAddr>,
+        )>,
+    ),
     H2Handshake(
         Option<(
-            Handshake<T, Bytes>,
+            Handshake<Rewind<T>, Bytes>,
             ServiceConfig,
             CloneableService<S>,
             Option<Box<dyn DataFactory>>,
@@ -657,8 +743,81 @@ where
     ) -> Poll<Result<(), DispatchError>> {
         #[project]
         match self.as_mut().project() {
-            State::H1(disp) => disp.poll(cx),
+            State::H1(mut disp) => {
+                ready!(disp.as_mut().poll(cx))?;
+
+                // switch to http/2 after `Upgrade: h2c` request
+                if let Some(upgrade) = disp.get_mut().take_h2c() {
+                    let timer = upgrade.config.client_timer();
+                    self.set(State::H2cPreface(Some((
+                        Preface::new(
+                            upgrade.io,
+                            upgrade.read_buf,
+                            upgrade.settings,
+                            upgrade.headers,
+                            timer,
+                        ),
+                        upgrade.config,
+                        upgrade.service,
+                        upgrade.on_connect,
+                        upgrade.peer_addr,
+                    ))));
+                    self.poll(cx)
+                } else {
+                    Poll::Ready(Ok(()))
+                }
+            }
             State::H2(disp) => disp.poll(cx),
+            State::H2PriorKnowledge(ref mut data) => {
+                let (io, buf, is_h2) = if let Some(ref mut item) = data {
+                    ready!(Pin::new(&mut item.0).poll(cx))?
+                } else {
+                    panic!()
+                };
+                let (_, cfg, srv, expect, upgrade, on_connect, peer_addr) =
+                    data.take().unwrap();
+                if is_h2 {
+                    // replay preface to http/2 connection
+                    self.set(State::H2Handshake(Some((
+                        cfg.h2_options().handshake(Rewind::new(io, buf.freeze())),
+                        cfg,
+                        srv,
+                        on_connect,
+                        peer_addr,
+                    ))));
+                } else {
+                    let disp = h1::Dispatcher::with_timeout(
+                        io,
+                        h1::Codec::new(cfg.clone()),
+                        cfg,
+                        buf,
+                        None,
+                        srv,
+                        expect,
+                        upgrade,
+                        on_connect,
+                        peer_addr,
+                    );
+                    self.set(State::H1(disp.allow_h2c()));
+                }
+                self.poll(cx)
+            }
+            State::H2cPreface(ref mut data) => {
+                let io = if let Some(ref mut item) = data {
+                    ready!(Pin::new(&mut item.0).poll(cx))?
+                } else {
+                    panic!()
+                };
+                let (_, cfg, srv, on_connect, peer_addr) = data.take().unwrap();
+                self.set(State::H2Handshake(Some((
+                    cfg.h2_options().handshake(io),
+                    cfg,
+                    srv,
+                    on_connect,
+                    peer_addr,
+                ))));
+                self.poll(cx)
+            }
             State::H2Handshake(ref mut data) => {
                 let conn = if let Some(ref mut item) = data {
                     match Pin::new(&mut item.0).poll(cx) {
@@ -0,0 +1,184 @@
+use std::cell::{Ref, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::header::map::AsName;
+use crate::header::HeaderMap;
+use crate::http::header::{self, HeaderName, HeaderValue};
+
+/// Fields that must not be sent in trailer section, RFC 7230 section 4.1.2
+const FORBIDDEN: &[HeaderName] = &[
+    // message framing
+    header::TRANSFER_ENCODING,
+    header::CONTENT_LENGTH,
+    header::TRAILER,
+    // routing and request modifiers
+    header::HOST,
+    header::CACHE_CONTROL,
+    header::EXPECT,
+    header::MAX_FORWARDS,
+    header::PRAGMA,
+    header::RANGE,
+    header::TE,
+    header::IF_MATCH,
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    header::IF_UNMODIFIED_SINCE,
+    header::IF_RANGE,
+    // authentication
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::WWW_AUTHENTICATE,
+    header::PROXY_AUTHENTICATE,
+    header::SET_COOKIE,
+    // response control data
+    header::AGE,
+    header::DATE,
+    header::EXPIRES,
+    header::LOCATION,
+    header::RETRY_AFTER,
+    header::VARY,
+    header::WARNING,
+    // payload processing
+    header::CONTENT_ENCODING,
+    header::CONTENT_TYPE,
+    header::CONTENT_RANGE,
+];
+
+/// Check if field is not allowed in trailer section.
+pub(crate) fn is_forbidden(name: &HeaderName) -> bool {
+    FORBIDDEN.contains(name)
+}
+
+/// Trailer fields sent after chunked message body.
+///
+/// For requests handle is available in request extensions of HTTP/1.1
+/// requests with chunked payload, fields are filled in before payload
+/// stream returns end of stream.
+///
+/// For responses handle is attached with `ResponseBuilder::trailers()`,
+/// fields could be added while response body is being streamed. Trailers
+/// are sent only if response body is chunked.
+///
+/// Fields used for message framing, routing, authentication, request
+/// modifiers, response control and payload processing are not allowed in
+/// trailers (RFC 7230 section 4.1.2), such fields are dropped.
+///
+/// ```rust
+/// use actix_http::{http::header, HttpMessage, Request, Response, Trailers};
+///
+/// async fn index(req: Request) -> Result<Response, ()> {
+///     let checksum = req
+///         .extensions()
+///         .get::<Trailers>()
+///         .and_then(|trailers| trailers.get("x-checksum"));
+///
+///     let trailers = Trailers::new();
+///     trailers.insert(
+///         header::HeaderName::from_static("x-rows"),
+///         header::HeaderValue::from_static("0"),
+///     );
+///     Ok(Response::Ok()
+///         .header(header::TRAILER, "x-rows")
+///         .trailers(trailers)
+///         .finish())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Trailers(Rc<RefCell<HeaderMap>>);
+
+impl Trailers {
+    /// Create empty set of trailer fields.
+    pub fn new() -> Self {
+        Trailers(Rc::new(RefCell::new(HeaderMap::new())))
+    }
+
+    /// Insert trailer field, value is appended to existing values.
+    ///
+    /// Fields that are not allowed in trailers are ignored.
+    pub fn insert(&self, name: HeaderName, value: HeaderValue) {
+        if is_forbidden(&name) {
+            return;
+        }
+        self.0.borrow_mut().append(name, value);
+    }
+
+    /// Get first value of trailer field.
+    pub fn get<N: AsName>(&self, name: N) -> Option<HeaderValue> {
+        self.0.borrow().get(name).cloned()
+    }
+
+    /// Trailer fields
+    pub fn headers(&self) -> Ref<'_, HeaderMap> {
+        self.0.borrow()
+    }
+
+    /// Check if there are no trailer fields.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Add received trailer fields.
+    pub(crate) fn extend(&self, headers: HeaderMap) {
+        for (name, value) in headers.iter() {
+            self.insert(name.clone(), value.clone());
+        }
+    }
+
+    /// Write trailer fields in HTTP/1.1 format.
+    pub(crate) fn write(&self, dst: &mut BytesMut) {
+        for (key, value) in self.0.borrow().iter() {
+            dst.reserve(key.as_str().len() + value.len() + 4);
+            dst.put_slice(key.as_str().as_bytes());
+            dst.put_slice(b": ");
+            dst.put_slice(value.as_bytes());
+            dst.put_slice(b"\r\n");
+        }
+    }
+}
+
+impl Default for Trailers {
+    fn default() -> Self {
+        Trailers::new()
+    }
+}
+
+impl fmt::Debug for Trailers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trailers")?;
+        for (key, val) in self.0.borrow().iter() {
+            writeln!(f, "  {:?}: {:?}", key, val)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forbidden_fields() {
+        let trailers = Trailers::new();
+        trailers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("0"));
+        trailers.insert(header::HOST, HeaderValue::from_static("example.com"));
+        trailers.insert(header::TRAILER, HeaderValue::from_static("x-rows"));
+        assert!(trailers.is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("gzip"));
+        headers.insert(
+            HeaderName::from_static("x-rows"),
+            HeaderValue::from_static("1"),
+        );
+        trailers.extend(headers);
+        assert!(trailers.get(header::TRANSFER_ENCODING).is_none());
+        assert_eq!(trailers.get("x-rows").unwrap(), "1");
+
+        let mut buf = BytesMut::new();
+        trailers.write(&mut buf);
+        assert_eq!(&buf[..], b"x-rows: 1\r\n");
+    }
+}
@@ -1,6 +1,8 @@
 use actix_codec::{Decoder, Encoder};
 use bytes::{Bytes, BytesMut};
 
+#[cfg(feature = "compress")]
+use super::deflate::DeflateContext;
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
 use super::ProtocolError;
@@ -50,11 +52,13 @@ pub enum Item {
     Last(Bytes),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 /// WebSockets protocol codec
 pub struct Codec {
     flags: Flags,
     max_size: usize,
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateContext>,
 }
 
 bitflags::bitflags! {
@@ -62,6 +66,7 @@ bitflags::bitflags! {
         const SERVER         = 0b0000_0001;
         const CONTINUATION   = 0b0000_0010;
         const W_CONTINUATION = 0b0000_0100;
+        const R_COMPRESSED   = 0b0000_1000;
     }
 }
 
@@ -71,6 +76,8 @@ impl Codec {
         Codec {
             max_size: 65_536,
             flags: Flags::SERVER,
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 
@@ -89,6 +96,80 @@ impl Codec {
         self.flags.remove(Flags::SERVER);
         self
     }
+
+    /// Set compression context of negotiated `permessage-deflate`
+    /// extension, text and binary messages are compressed.
+    ///
+    /// Clone of codec starts with empty compression context.
+    #[cfg(feature = "compress")]
+    pub fn deflate(mut self, context: DeflateContext) -> Self {
+        self.deflate = Some(context);
+        self
+    }
+
+    /// Write frame of text or binary message, `first` is set for first
+    /// frame of message
+    #[cfg_attr(not(feature = "compress"), allow(unused_variables))]
+    fn write_data(
+        &mut self,
+        dst: &mut BytesMut,
+        data: &[u8],
+        op: OpCode,
+        first: bool,
+        fin: bool,
+    ) -> Result<(), ProtocolError> {
+        let mask = !self.flags.contains(Flags::SERVER);
+        #[cfg(feature = "compress")]
+        {
+            if let Some(ref mut deflate) = self.deflate {
+                let data = deflate.compress(data, fin)?;
+                Parser::write_frame(dst, data, op, fin, first, mask);
+                return Ok(());
+            }
+        }
+        Parser::write_message(dst, data, op, fin, mask);
+        Ok(())
+    }
+
+    /// Decompress payload of compressed message, compressed messages have
+    /// `RSV1` bit set on first frame
+    fn decompress(
+        &mut self,
+        finished: bool,
+        rsv1: bool,
+        opcode: OpCode,
+        payload: Option<BytesMut>,
+    ) -> Result<Option<BytesMut>, ProtocolError> {
+        let compressed = match opcode {
+            OpCode::Text | OpCode::Binary => {
+                self.flags.set(Flags::R_COMPRESSED, rsv1 && !finished);
+                rsv1
+            }
+            OpCode::Continue if !rsv1 => {
+                let compressed = self.flags.contains(Flags::R_COMPRESSED);
+                if finished {
+                    self.flags.remove(Flags::R_COMPRESSED);
+                }
+                compressed
+            }
+            _ if rsv1 => return Err(ProtocolError::Compression),
+            _ => false,
+        };
+        if !compressed {
+            return Ok(payload);
+        }
+
+        #[cfg(feature = "compress")]
+        {
+            if let Some(ref mut deflate) = self.deflate {
+                let payload = payload.unwrap_or_default();
+                return deflate
+                    .decompress(&payload, finished, self.max_size)
+                    .map(Some);
+            }
+        }
+        Err(ProtocolError::Compression)
+    }
 }
 
 impl Encoder for Codec {
@@ -97,20 +178,12 @@ impl Encoder for Codec {
 
     fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Text(txt) => Parser::write_message(
-                dst,
-                txt,
-                OpCode::Text,
-                true,
-                !self.flags.contains(Flags::SERVER),
-            ),
-            Message::Binary(bin) => Parser::write_message(
-                dst,
-                bin,
-                OpCode::Binary,
-                true,
-                !self.flags.contains(Flags::SERVER),
-            ),
+            Message::Text(txt) => {
+                self.write_data(dst, txt.as_bytes(), OpCode::Text, true, true)?
+            }
+            Message::Binary(bin) => {
+                self.write_data(dst, &bin[..], OpCode::Binary, true, true)?
+            }
             Message::Ping(txt) => Parser::write_message(
                 dst,
                 txt,
@@ -134,13 +207,7 @@ impl Encoder for Codec {
                         return Err(ProtocolError::ContinuationStarted);
                     } else {
                         self.flags.insert(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Binary,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data[..], OpCode::Text, true, false)?
                     }
                 }
                 Item::FirstBinary(data) => {
@@ -148,24 +215,12 @@ impl Encoder for Codec {
                         return Err(ProtocolError::ContinuationStarted);
                     } else {
                         self.flags.insert(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Text,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data[..], OpCode::Binary, true, false)?
                     }
                 }
                 Item::Continue(data) => {
                     if self.flags.contains(Flags::W_CONTINUATION) {
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Continue,
-                            false,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data[..], OpCode::Continue, false, false)?
                     } else {
                         return Err(ProtocolError::ContinuationNotStarted);
                     }
@@ -173,13 +228,7 @@ impl Encoder for Codec {
                 Item::Last(data) => {
                     if self.flags.contains(Flags::W_CONTINUATION) {
                         self.flags.remove(Flags::W_CONTINUATION);
-                        Parser::write_message(
-                            dst,
-                            &data[..],
-                            OpCode::Continue,
-                            true,
-                            !self.flags.contains(Flags::SERVER),
-                        )
+                        self.write_data(dst, &data[..], OpCode::Continue, false, true)?
                     } else {
                         return Err(ProtocolError::ContinuationNotStarted);
                     }
@@ -196,8 +245,11 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.contains(Flags::SERVER), self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
+        match Parser::parse_frame(src, self.flags.contains(Flags::SERVER), self.max_size)
+        {
+            Ok(Some((finished, rsv1, opcode, payload))) => {
+                let payload = self.decompress(finished, rsv1, opcode, payload)?;
+
                 // continuation is not supported
                 if !finished {
                     return match opcode {
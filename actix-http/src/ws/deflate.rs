@@ -0,0 +1,612 @@
+//! `permessage-deflate` extension, defined in
+//! [RFC7692](https://tools.ietf.org/html/rfc7692)
+use std::convert::TryFrom;
+use std::fmt;
+
+use bytes::{Bytes, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+
+use super::ProtocolError;
+use crate::header::{self, HeaderMap, HeaderValue};
+use crate::message::RequestHead;
+
+const EXTENSION: &str = "permessage-deflate";
+
+/// Empty block of deflate stream flushed with sync flush, it is removed
+/// from the end of compressed message
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Window bits of compressor, compressor of `flate2` backend does not
+/// support smaller windows
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// `permessage-deflate` extension configuration
+///
+/// Extension is negotiated during handshake, server accepts offer of
+/// client with `negotiate()`, client sends offer created with `offer()`
+/// and checks response of server with `accept_response()`. Negotiated
+/// `DeflateContext` is set on codec with `Codec::deflate()`.
+///
+/// ```rust
+/// use actix_http::ws::{self, DeflateConfig};
+/// use actix_http::{http::header, Request};
+///
+/// fn handshake(req: &Request) -> Result<ws::Codec, ws::HandshakeError> {
+///     let mut res = ws::handshake(req.head())?;
+///     let mut codec = ws::Codec::new();
+///
+///     let config = DeflateConfig::new().no_context_takeover(true);
+///     if let Some((extension, context)) = config.negotiate(req.head()) {
+///         res.header(header::SEC_WEBSOCKET_EXTENSIONS, extension);
+///         codec = codec.deflate(context);
+///     }
+///     // send `res` to client and use `codec` for frames
+///     Ok(codec)
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeflateConfig {
+    level: Compression,
+    no_context_takeover: bool,
+    peer_no_context_takeover: bool,
+    peer_max_window_bits: Option<u8>,
+}
+
+impl DeflateConfig {
+    /// Create default configuration.
+    pub fn new() -> Self {
+        DeflateConfig::default()
+    }
+
+    /// Set compression level, from 0 to 9. By default level is 6.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.level = Compression::new(level.min(9));
+        self
+    }
+
+    /// Reset compression context after each message.
+    ///
+    /// Compression context is not kept between messages, so memory is
+    /// saved at the cost of compression ratio. By default context is kept.
+    pub fn no_context_takeover(mut self, value: bool) -> Self {
+        self.no_context_takeover = value;
+        self
+    }
+
+    /// Request peer to reset compression context after each message.
+    pub fn peer_no_context_takeover(mut self, value: bool) -> Self {
+        self.peer_no_context_takeover = value;
+        self
+    }
+
+    /// Request peer to use LZ77 sliding window of at most `2^bits` bytes
+    /// for compression, `bits` is from 8 to 15.
+    ///
+    /// Peer is requested to use default window of 15 bits if it is not set.
+    pub fn peer_max_window_bits(mut self, bits: u8) -> Self {
+        assert!(
+            (8..=MAX_WINDOW_BITS).contains(&bits),
+            "Window bits must be from 8 to 15"
+        );
+        self.peer_max_window_bits = Some(bits);
+        self
+    }
+
+    /// Accept extension offer of client handshake request.
+    ///
+    /// Returns value of `Sec-WebSocket-Extensions` header of handshake
+    /// response and context for codec, or `None` if client does not offer
+    /// extension or none of offers could be accepted.
+    pub fn negotiate(&self, req: &RequestHead) -> Option<(HeaderValue, DeflateContext)> {
+        for params in parse_params(&req.headers).into_iter().flatten() {
+            // window of compressor could not be limited
+            if params
+                .server_max_window_bits
+                .map_or(false, |bits| bits < MAX_WINDOW_BITS)
+            {
+                continue;
+            }
+
+            let reset_compress =
+                self.no_context_takeover || params.server_no_context_takeover;
+            let mut extension = String::from(EXTENSION);
+            if reset_compress {
+                extension.push_str("; server_no_context_takeover");
+            }
+            if self.peer_no_context_takeover {
+                extension.push_str("; client_no_context_takeover");
+            }
+            // client_max_window_bits is sent only if client supports it
+            if let (Some(bits), Some(offered)) =
+                (self.peer_max_window_bits, params.client_max_window_bits)
+            {
+                let bits = offered.map_or(bits, |offered| offered.min(bits));
+                extension.push_str(&format!("; client_max_window_bits={}", bits));
+            }
+
+            return Some((
+                HeaderValue::try_from(extension).unwrap(),
+                DeflateContext::new(
+                    self.level,
+                    reset_compress,
+                    self.peer_no_context_takeover,
+                ),
+            ));
+        }
+        None
+    }
+
+    /// Value of `Sec-WebSocket-Extensions` header of client handshake
+    /// request.
+    pub fn offer(&self) -> HeaderValue {
+        let mut offer = String::from(EXTENSION);
+        if self.no_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+        if self.peer_no_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+        if let Some(bits) = self.peer_max_window_bits {
+            offer.push_str(&format!("; server_max_window_bits={}", bits));
+        }
+        HeaderValue::try_from(offer).unwrap()
+    }
+
+    /// Check extension of server handshake response for offer created with
+    /// `offer()`.
+    ///
+    /// Returns context for codec, or `None` if server does not accept
+    /// extension. Response with invalid extension parameters is an error,
+    /// connection has to be failed.
+    pub fn accept_response(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<Option<DeflateContext>, ProtocolError> {
+        let mut params = parse_params(headers);
+        if params.len() > 1 {
+            return Err(ProtocolError::InvalidExtension);
+        }
+        let params = match params.pop() {
+            Some(Some(params)) => params,
+            Some(None) => return Err(ProtocolError::InvalidExtension),
+            None => return Ok(None),
+        };
+
+        // client_max_window_bits is not offered, window of compressor
+        // could not be limited
+        if let Some(bits) = params.client_max_window_bits {
+            if bits != Some(MAX_WINDOW_BITS) {
+                return Err(ProtocolError::InvalidExtension);
+            }
+        }
+
+        Ok(Some(DeflateContext::new(
+            self.level,
+            self.no_context_takeover || params.client_no_context_takeover,
+            params.server_no_context_takeover,
+        )))
+    }
+}
+
+/// Parameters of `permessage-deflate` offer or response
+#[derive(Debug, Default, PartialEq)]
+struct Params {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+    /// `Some(None)` if parameter has no value
+    client_max_window_bits: Option<Option<u8>>,
+}
+
+/// Parse `permessage-deflate` elements of `Sec-WebSocket-Extensions`
+/// headers, elements with invalid parameters are `None`
+fn parse_params(headers: &HeaderMap) -> Vec<Option<Params>> {
+    headers
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .filter_map(parse_element)
+        .collect()
+}
+
+fn parse_element(element: &str) -> Option<Option<Params>> {
+    let mut parts = element.split(';').map(|s| s.trim());
+    if !parts.next()?.eq_ignore_ascii_case(EXTENSION) {
+        return None;
+    }
+
+    let mut params = Params::default();
+    for part in parts {
+        let mut kv = part.splitn(2, '=');
+        let name = kv.next().unwrap_or("").trim();
+        let value = kv.next().map(|v| v.trim().trim_matches('"'));
+
+        match (name, value) {
+            ("server_no_context_takeover", None)
+                if !params.server_no_context_takeover =>
+            {
+                params.server_no_context_takeover = true
+            }
+            ("client_no_context_takeover", None)
+                if !params.client_no_context_takeover =>
+            {
+                params.client_no_context_takeover = true
+            }
+            ("server_max_window_bits", Some(value))
+                if params.server_max_window_bits.is_none() =>
+            {
+                match window_bits(value) {
+                    Some(bits) => params.server_max_window_bits = Some(bits),
+                    None => return Some(None),
+                }
+            }
+            ("client_max_window_bits", value)
+                if params.client_max_window_bits.is_none() =>
+            {
+                match value.map(window_bits) {
+                    Some(None) => return Some(None),
+                    bits => params.client_max_window_bits = Some(bits.and_then(|b| b)),
+                }
+            }
+            _ => return Some(None),
+        }
+    }
+    Some(Some(params))
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    if !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match value.parse::<u8>() {
+        Ok(bits) if (8..=MAX_WINDOW_BITS).contains(&bits) => Some(bits),
+        _ => None,
+    }
+}
+
+/// Compression context of negotiated `permessage-deflate` extension
+///
+/// Clone of context starts with empty compression context, so context
+/// could be cloned for separate encoder and decoder of connection before
+/// any frame is processed.
+pub struct DeflateContext {
+    level: Compression,
+    reset_compress: bool,
+    reset_decompress: bool,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl DeflateContext {
+    fn new(level: Compression, reset_compress: bool, reset_decompress: bool) -> Self {
+        DeflateContext {
+            level,
+            reset_compress,
+            reset_decompress,
+            compress: Compress::new(level, false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Compress part of message, `end` is set for last part.
+    pub(crate) fn compress(
+        &mut self,
+        data: &[u8],
+        end: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let mut consumed = 0;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity());
+            }
+            let total_in = self.compress.total_in();
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|_| ProtocolError::Compression)?;
+            consumed += (self.compress.total_in() - total_in) as usize;
+
+            // flush is complete if there is space left in output
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if end {
+            if out.ends_with(&TAIL[..]) {
+                out.truncate(out.len() - TAIL.len());
+            }
+            if self.reset_compress {
+                self.compress.reset();
+            }
+        }
+        Ok(Bytes::from(out))
+    }
+
+    /// Decompress part of message, `end` is set for last part. Decompressed
+    /// data could not exceed `limit`.
+    pub(crate) fn decompress(
+        &mut self,
+        data: &[u8],
+        end: bool,
+        limit: usize,
+    ) -> Result<BytesMut, ProtocolError> {
+        let mut out = Vec::with_capacity((data.len() * 2).min(limit) + 64);
+        self.decompress_chunk(data, &mut out, limit)?;
+        if end {
+            self.decompress_chunk(&TAIL, &mut out, limit)?;
+            if self.reset_decompress {
+                self.decompress.reset(false);
+            }
+        }
+        Ok(BytesMut::from(&out[..]))
+    }
+
+    fn decompress_chunk(
+        &mut self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> Result<(), ProtocolError> {
+        let mut consumed = 0;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity());
+            }
+            let total_in = self.decompress.total_in();
+            let total_out = self.decompress.total_out();
+            self.decompress
+                .decompress_vec(&data[consumed..], out, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::Compression)?;
+            consumed += (self.decompress.total_in() - total_in) as usize;
+
+            if out.len() > limit {
+                return Err(ProtocolError::Overflow);
+            }
+            if consumed == data.len() && out.len() < out.capacity() {
+                return Ok(());
+            }
+            // no progress, compressed data is truncated
+            if self.decompress.total_in() == total_in
+                && self.decompress.total_out() == total_out
+            {
+                return Err(ProtocolError::Compression);
+            }
+        }
+    }
+}
+
+impl Clone for DeflateContext {
+    fn clone(&self) -> Self {
+        DeflateContext::new(self.level, self.reset_compress, self.reset_decompress)
+    }
+}
+
+impl fmt::Debug for DeflateContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeflateContext")
+            .field("level", &self.level)
+            .field("reset_compress", &self.reset_compress)
+            .field("reset_decompress", &self.reset_decompress)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    fn params(val: &'static str) -> Vec<Option<Params>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_static(val),
+        );
+        parse_params(&headers)
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(params("x-webkit-deflate-frame"), vec![]);
+        assert_eq!(
+            params("permessage-deflate; client_max_window_bits, permessage-deflate"),
+            vec![
+                Some(Params {
+                    client_max_window_bits: Some(None),
+                    ..Params::default()
+                }),
+                Some(Params::default()),
+            ]
+        );
+        assert_eq!(
+            params(
+                "Permessage-Deflate; server_no_context_takeover; \
+                 client_no_context_takeover; server_max_window_bits=\"10\"; \
+                 client_max_window_bits=12"
+            ),
+            vec![Some(Params {
+                server_no_context_takeover: true,
+                client_no_context_takeover: true,
+                server_max_window_bits: Some(10),
+                client_max_window_bits: Some(Some(12)),
+            })]
+        );
+        assert_eq!(
+            params("permessage-deflate; server_max_window_bits"),
+            vec![None]
+        );
+        assert_eq!(
+            params("permessage-deflate; server_max_window_bits=16"),
+            vec![None]
+        );
+        assert_eq!(params("permessage-deflate; unknown"), vec![None]);
+        assert_eq!(
+            params("permessage-deflate; server_no_context_takeover; server_no_context_takeover"),
+            vec![None]
+        );
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let config = DeflateConfig::new()
+            .peer_no_context_takeover(true)
+            .peer_max_window_bits(10);
+
+        let req = TestRequest::default().finish();
+        assert!(config.negotiate(req.head()).is_none());
+
+        // smaller window of compressor is not supported
+        let req = TestRequest::default()
+            .header(
+                header::SEC_WEBSOCKET_EXTENSIONS,
+                "permessage-deflate; server_max_window_bits=10, \
+                 permessage-deflate; server_no_context_takeover; client_max_window_bits",
+            )
+            .finish();
+        let (extension, context) = config.negotiate(req.head()).unwrap();
+        assert_eq!(
+            extension,
+            "permessage-deflate; server_no_context_takeover; \
+             client_no_context_takeover; client_max_window_bits=10"
+        );
+        assert!(context.reset_compress);
+        assert!(context.reset_decompress);
+
+        let req = TestRequest::default()
+            .header(header::SEC_WEBSOCKET_EXTENSIONS, "permessage-deflate")
+            .finish();
+        let (extension, context) = DeflateConfig::new().negotiate(req.head()).unwrap();
+        assert_eq!(extension, "permessage-deflate");
+        assert!(!context.reset_compress);
+        assert!(!context.reset_decompress);
+    }
+
+    #[test]
+    fn test_accept_response() {
+        let config = DeflateConfig::new().no_context_takeover(true);
+        assert_eq!(
+            config.offer(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+
+        let mut headers = HeaderMap::new();
+        assert!(config.accept_response(&headers).unwrap().is_none());
+
+        headers.insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_static(
+                "permessage-deflate; server_no_context_takeover; server_max_window_bits=9",
+            ),
+        );
+        let context = config.accept_response(&headers).unwrap().unwrap();
+        assert!(context.reset_compress);
+        assert!(context.reset_decompress);
+
+        headers.insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_static("permessage-deflate; client_max_window_bits=10"),
+        );
+        assert!(config.accept_response(&headers).is_err());
+    }
+
+    #[test]
+    fn test_compress() {
+        let mut context = DeflateContext::new(Compression::default(), false, false);
+
+        // example of RFC7692 section 7.2.3.1
+        let data = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        let data = context.decompress(&data, true, 1024).unwrap();
+        assert_eq!(&data[..], b"Hello");
+
+        let data = context.compress(b"Hello", true).unwrap();
+        assert!(!data.ends_with(&TAIL[..]));
+        let data = context.decompress(&data, true, 1024).unwrap();
+        assert_eq!(&data[..], b"Hello");
+
+        // message in parts
+        let part1 = context.compress(b"Hello, ", false).unwrap();
+        let part2 = context.compress(b"world", true).unwrap();
+        let mut data = context.decompress(&part1, false, 1024).unwrap();
+        data.extend_from_slice(&context.decompress(&part2, true, 1024).unwrap());
+        assert_eq!(&data[..], b"Hello, world");
+
+        let data = context.compress(&[b'a'; 2048][..], true).unwrap();
+        assert!(data.len() < 2048);
+        match context.decompress(&data, true, 1024) {
+            Err(ProtocolError::Overflow) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_codec() {
+        use actix_codec::{Decoder, Encoder};
+
+        use crate::ws::{Codec, Frame, Item, Message};
+
+        let context = DeflateContext::new(Compression::default(), false, false);
+        let mut server = Codec::new().deflate(context.clone());
+        let mut client = Codec::new().client_mode().deflate(context);
+
+        // example of RFC7692 section 7.2.3.1
+        let mut buf =
+            BytesMut::from(&[0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00][..]);
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from_static(b"Hello")))
+        );
+
+        let text = "compressed text ".repeat(100);
+        let mut buf = BytesMut::new();
+        server
+            .encode(Message::Text(text.clone()), &mut buf)
+            .unwrap();
+        assert_eq!(buf[0], 0xc1);
+        assert!(buf.len() < text.len());
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from(text)))
+        );
+
+        // only first frame of message has RSV1 bit, control frames are
+        // not compressed
+        let mut buf = BytesMut::new();
+        client
+            .encode(
+                Message::Continuation(Item::FirstBinary("a".into())),
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(buf[0], 0x42);
+        let mut last = BytesMut::new();
+        client
+            .encode(Message::Continuation(Item::Last("b".into())), &mut last)
+            .unwrap();
+        assert_eq!(last[0], 0x80);
+        let mut ping = BytesMut::new();
+        client
+            .encode(Message::Ping("ping".into()), &mut ping)
+            .unwrap();
+        assert_eq!(ping[0], 0x89);
+        buf.extend_from_slice(&last);
+        buf.extend_from_slice(&ping);
+        assert!(server.decode(&mut buf).unwrap().is_some());
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"b"))))
+        );
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Frame::Ping(Bytes::from_static(b"ping")))
+        );
+
+        // compression is not negotiated
+        let mut buf =
+            BytesMut::from(&[0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00][..]);
+        match Codec::new().client_mode().decode(&mut buf) {
+            Err(ProtocolError::Compression) => (),
+            _ => panic!(),
+        }
+    }
+}
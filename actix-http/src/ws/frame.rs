@@ -17,7 +17,8 @@ impl Parser {
         src: &[u8],
         server: bool,
         max_size: usize,
-    ) -> Result<Option<(usize, bool, OpCode, usize, Option<u32>)>, ProtocolError> {
+    ) -> Result<Option<(usize, bool, bool, OpCode, usize, Option<u32>)>, ProtocolError>
+    {
         let chunk_len = src.len();
 
         let mut idx = 2;
@@ -28,6 +29,7 @@ impl Parser {
         let first = src[0];
         let second = src[1];
         let finished = first & 0x80 != 0;
+        let rsv1 = first & 0x40 != 0;
 
         // check masking
         let masked = second & 0x80 != 0;
@@ -86,7 +88,7 @@ impl Parser {
             None
         };
 
-        Ok(Some((idx, finished, opcode, length, mask)))
+        Ok(Some((idx, finished, rsv1, opcode, length, mask)))
     }
 
     /// Parse the input stream into a frame.
@@ -95,8 +97,19 @@ impl Parser {
         server: bool,
         max_size: usize,
     ) -> Result<Option<(bool, OpCode, Option<BytesMut>)>, ProtocolError> {
+        Ok(Parser::parse_frame(src, server, max_size)?
+            .map(|(finished, _, opcode, payload)| (finished, opcode, payload)))
+    }
+
+    /// Parse the input stream into a frame, returns `RSV1` bit of frame as
+    /// well, which marks compressed messages.
+    pub(crate) fn parse_frame(
+        src: &mut BytesMut,
+        server: bool,
+        max_size: usize,
+    ) -> Result<Option<(bool, bool, OpCode, Option<BytesMut>)>, ProtocolError> {
         // try to parse ws frame metadata
-        let (idx, finished, opcode, length, mask) =
+        let (idx, finished, rsv1, opcode, length, mask) =
             match Parser::parse_metadata(src, server, max_size)? {
                 None => return Ok(None),
                 Some(res) => res,
@@ -112,7 +125,7 @@ impl Parser {
 
         // no need for body
         if length == 0 {
-            return Ok(Some((finished, opcode, None)));
+            return Ok(Some((finished, rsv1, opcode, None)));
         }
 
         let mut data = src.split_to(length);
@@ -124,7 +137,7 @@ impl Parser {
             }
             OpCode::Close if length > 125 => {
                 debug!("Received close frame with payload length exceeding 125. Morphing to protocol close frame.");
-                return Ok(Some((true, OpCode::Close, None)));
+                return Ok(Some((true, false, OpCode::Close, None)));
             }
             _ => (),
         }
@@ -134,7 +147,7 @@ impl Parser {
             apply_mask(&mut data, mask);
         }
 
-        Ok(Some((finished, opcode, Some(data))))
+        Ok(Some((finished, rsv1, opcode, Some(data))))
     }
 
     /// Parse the payload of a close frame.
@@ -160,13 +173,29 @@ impl Parser {
         op: OpCode,
         fin: bool,
         mask: bool,
+    ) {
+        Parser::write_frame(dst, pl, op, fin, false, mask)
+    }
+
+    /// Generate binary representation, with `RSV1` bit set for compressed
+    /// messages
+    pub(crate) fn write_frame<B: AsRef<[u8]>>(
+        dst: &mut BytesMut,
+        pl: B,
+        op: OpCode,
+        fin: bool,
+        rsv1: bool,
+        mask: bool,
     ) {
         let payload = pl.as_ref();
-        let one: u8 = if fin {
+        let mut one: u8 = if fin {
             0x80 | Into::<u8>::into(op)
         } else {
             op.into()
         };
+        if rsv1 {
+            one |= 0x40;
+        }
         let payload_len = payload.len();
         let (two, p_len) = if mask {
             (0x80, payload_len + 4)
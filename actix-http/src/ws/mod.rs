@@ -13,12 +13,16 @@ use crate::message::RequestHead;
 use crate::response::{Response, ResponseBuilder};
 
 mod codec;
+#[cfg(feature = "compress")]
+mod deflate;
 mod dispatcher;
 mod frame;
 mod mask;
 mod proto;
 
 pub use self::codec::{Codec, Frame, Item, Message};
+#[cfg(feature = "compress")]
+pub use self::deflate::{DeflateConfig, DeflateContext};
 pub use self::dispatcher::Dispatcher;
 pub use self::frame::Parser;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
@@ -53,6 +57,12 @@ pub enum ProtocolError {
     /// Unknown continuation fragment
     #[display(fmt = "Unknown continuation fragment.")]
     ContinuationFragment(OpCode),
+    /// Compressed frame is invalid or compression is not negotiated
+    #[display(fmt = "Invalid compressed frame")]
+    Compression,
+    /// Invalid `Sec-WebSocket-Extensions` header of handshake response
+    #[display(fmt = "Invalid extensions header")]
+    InvalidExtension,
     /// Io error
     #[display(fmt = "io error: {}", _0)]
     Io(io::Error),
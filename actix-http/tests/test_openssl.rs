@@ -74,6 +74,20 @@ async fn test_h2() -> io::Result<()> {
     Ok(())
 }
 
+#[actix_rt::test]
+async fn test_h2_informational_final_response() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(|_| ok::<_, Error>(Response::EarlyHints().finish()))
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.sget("/").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    Ok(())
+}
+
 #[actix_rt::test]
 async fn test_h2_1() -> io::Result<()> {
     let srv = test_server(move || {
@@ -115,6 +129,34 @@ async fn test_h2_body() -> io::Result<()> {
     Ok(())
 }
 
+#[actix_rt::test]
+async fn test_h2_settings() -> io::Result<()> {
+    let data = "HELLOWORLD".to_owned().repeat(64 * 1024);
+    let mut srv = test_server(move || {
+        HttpService::build()
+            .h2_initial_window_size(1024 * 1024)
+            .h2_initial_connection_window_size(4 * 1024 * 1024)
+            .h2_max_concurrent_streams(10)
+            .h2_max_frame_size(64 * 1024)
+            .h2_max_header_list_size(16 * 1024)
+            .h2(|mut req: Request<_>| {
+                async move {
+                    let body = load_body(req.take_payload()).await?;
+                    Ok::<_, Error>(Response::Ok().body(body))
+                }
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.sget("/").send_body(data.clone()).await.unwrap();
+    assert!(response.status().is_success());
+
+    let body = srv.load_body(response).await.unwrap();
+    assert_eq!(&body, data.as_bytes());
+    Ok(())
+}
+
 #[actix_rt::test]
 async fn test_h2_content_length() {
     let srv = test_server(move || {
@@ -139,18 +181,21 @@ async fn test_h2_content_length() {
     let value = HeaderValue::from_static("0");
 
     {
-        for i in 0..4 {
-            let req = srv
-                .request(Method::GET, srv.surl(&format!("/{}", i)))
-                .send();
-            let response = req.await.unwrap();
-            assert_eq!(response.headers().get(&header), None);
+        let req = srv.request(Method::GET, srv.surl("/0")).send();
+        let response = req.await.unwrap();
+        assert_eq!(response.headers().get(&header), None);
 
+        let req = srv.request(Method::HEAD, srv.surl("/0")).send();
+        let response = req.await.unwrap();
+        assert_eq!(response.headers().get(&header), None);
+
+        // informational status is not a final response
+        for i in 1..4 {
             let req = srv
-                .request(Method::HEAD, srv.surl(&format!("/{}", i)))
+                .request(Method::GET, srv.surl(&format!("/{}", i)))
                 .send();
             let response = req.await.unwrap();
-            assert_eq!(response.headers().get(&header), None);
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
         }
 
         for i in 4..6 {
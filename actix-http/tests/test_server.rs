@@ -3,16 +3,19 @@ use std::time::Duration;
 use std::{net, thread};
 
 use actix_http_test::test_server;
+use actix_rt::net::TcpStream;
 use actix_rt::time::delay_for;
 use actix_service::fn_service;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::future::{self, err, ok, ready, FutureExt};
 use futures::stream::{once, StreamExt};
 use regex::Regex;
 
+use actix_http::h2::ServerPush;
 use actix_http::httpmessage::HttpMessage;
 use actix_http::{
-    body, error, http, http::header, Error, HttpService, KeepAlive, Request, Response,
+    body, error, http, http::header, ConnectionExtensions, Error, HttpService,
+    IdleLimit, KeepAlive, Request, Response, Trailers,
 };
 
 #[actix_rt::test]
@@ -52,6 +55,246 @@ async fn test_h1_2() {
     assert!(response.status().is_success());
 }
 
+#[actix_rt::test]
+async fn test_h2c_prior_knowledge() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h2c(true)
+            .finish(|req: Request| {
+                assert!(req.peer_addr().is_some());
+                let body = format!("{:?}", req.version());
+                future::ok::<_, ()>(Response::Ok().body(body))
+            })
+            .tcp()
+    });
+
+    let io = actix_rt::net::TcpStream::connect(srv.addr()).await.unwrap();
+    let (client, conn) = h2::client::handshake(io).await.unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::get("/").body(()).unwrap();
+    let (response, _) = client.send_request(req, true).unwrap();
+    let response = response.await.unwrap();
+    assert!(response.status().is_success());
+
+    let mut body = response.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(bytes, b"HTTP/2.0");
+
+    // http/1 connections are still served
+    let response = srv.get("/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"HTTP/1.1"));
+}
+
+#[actix_rt::test]
+async fn test_h2_server_push() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h2c(true)
+            .finish(|req: Request| {
+                let body = match req.path() {
+                    "/" => {
+                        let push = req.extensions().get::<ServerPush>().cloned();
+                        if let Some(push) = push {
+                            assert!(push.is_enabled());
+                            assert!(push.push("/style.css"));
+                        }
+                        "index"
+                    }
+                    _ => {
+                        // pushed resources can not push
+                        assert!(req.extensions().get::<ServerPush>().is_none());
+                        assert_eq!(req.headers().get(header::ACCEPT).unwrap(), "*/*");
+                        "style"
+                    }
+                };
+                future::ok::<_, ()>(Response::Ok().body(body))
+            })
+            .tcp()
+    });
+
+    async fn read_body(mut body: h2::RecvStream) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    // h2 server does not open pushed streams until client
+    // advertises SETTINGS_MAX_CONCURRENT_STREAMS
+    let io = TcpStream::connect(srv.addr()).await.unwrap();
+    let (client, conn) = h2::client::Builder::new()
+        .max_concurrent_streams(100)
+        .handshake::<_, Bytes>(io)
+        .await
+        .unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::get("http://localhost/")
+        .header("accept", "*/*")
+        .body(())
+        .unwrap();
+    let (mut response, _) = client.send_request(req, true).unwrap();
+    let mut pushes = response.push_promises();
+    let response = response.await.unwrap();
+    assert_eq!(read_body(response.into_body()).await, b"index");
+
+    let (req, pushed) = pushes.push_promise().await.unwrap().unwrap().into_parts();
+    assert_eq!(req.uri(), "http://localhost/style.css");
+    assert_eq!(req.headers().get("accept").unwrap(), "*/*");
+    let pushed = pushed.await.unwrap();
+    assert!(pushed.status().is_success());
+    assert_eq!(read_body(pushed.into_body()).await, b"style");
+    assert!(pushes.push_promise().await.is_none());
+
+    // client with disabled push gets no promises
+    let io = TcpStream::connect(srv.addr()).await.unwrap();
+    let (client, conn) = h2::client::Builder::new()
+        .enable_push(false)
+        .handshake::<_, Bytes>(io)
+        .await
+        .unwrap();
+    actix_rt::spawn(async move {
+        let _ = conn.await;
+    });
+
+    let mut client = client.ready().await.unwrap();
+    let req = ::http::Request::get("http://localhost/").body(()).unwrap();
+    let (response, _) = client.send_request(req, true).unwrap();
+    let response = response.await.unwrap();
+    assert_eq!(read_body(response.into_body()).await, b"index");
+}
+
+#[actix_rt::test]
+async fn test_h2c_partial_preface() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_timeout(200)
+            .h2c(true)
+            .finish(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    // preface is sent in parts
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"PRI * HTTP/2.0\r\n");
+    thread::sleep(Duration::from_millis(50));
+    let _ = stream.write_all(b"\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0");
+    let mut frame = [0; 9];
+    stream.read_exact(&mut frame).unwrap();
+    // server starts with SETTINGS frame
+    assert_eq!(frame[3], 0x04);
+
+    // incomplete preface is handled as slow http/1 request
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"PRI * ");
+    let mut data = Vec::new();
+    let _ = stream.read_to_end(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 408"));
+}
+
+#[actix_rt::test]
+async fn test_h2c_upgrade() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h2c(true)
+            .finish(|req: Request| {
+                assert_eq!(req.version(), http::Version::HTTP_2);
+                assert_eq!(req.path(), "/index");
+                assert_eq!(req.headers().get("x-test").unwrap(), "1");
+                future::ok::<_, ()>(Response::Ok().body("h2"))
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /index HTTP/1.1\r\nhost: localhost\r\nx-test: 1\r\n\
+          connection: upgrade, http2-settings\r\nupgrade: h2c\r\n\
+          http2-settings: AAMAAABkAAQAAP__\r\n\r\n",
+    );
+    let mut data = Vec::new();
+    while !data.ends_with(b"\r\n\r\n") {
+        let mut b = [0; 1];
+        stream.read_exact(&mut b).unwrap();
+        data.push(b[0]);
+    }
+    assert!(data.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+
+    // client preface with empty settings frame
+    let _ = stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0");
+
+    // response to upgrade request is sent on stream 1
+    let mut headers = false;
+    loop {
+        let mut head = [0; 9];
+        stream.read_exact(&mut head).unwrap();
+        let len = (head[1] as usize) << 8 | head[2] as usize;
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        match head[3] {
+            // HEADERS
+            0x1 => {
+                assert_eq!(&head[5..], &[0, 0, 0, 1]);
+                headers = true;
+            }
+            // DATA
+            0x0 => {
+                assert!(headers);
+                assert_eq!(&head[5..], &[0, 0, 0, 1]);
+                assert_eq!(payload, b"h2");
+                break;
+            }
+            // SETTINGS, WINDOW_UPDATE
+            _ => (),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_h2c_upgrade_fallback() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h2c(true)
+            .finish(|_| future::ok::<_, ()>(Response::Ok().body("h1")))
+            .tcp()
+    });
+
+    // request with body is served over http/1.1
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST / HTTP/1.1\r\nconnection: upgrade, http2-settings\r\n\
+          upgrade: h2c\r\nhttp2-settings: AAMAAABkAAQAAP__\r\n\
+          content-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("h1"));
+
+    // upgrade requires settings header
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: h2c\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("h1"));
+}
+
 #[actix_rt::test]
 async fn test_expect_continue() {
     let srv = test_server(|| {
@@ -181,6 +424,30 @@ async fn test_slow_request() {
     assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
 }
 
+#[actix_rt::test]
+async fn test_slow_keepalive_request() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_read_timeout(100)
+            .finish(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK"));
+
+    // second request head trickles in
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    thread::sleep(Duration::from_millis(50));
+    let _ = stream.write_all(b"x-header: value\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+}
+
 #[actix_rt::test]
 async fn test_http1_malformed_request() {
     let srv = test_server(|| {
@@ -196,6 +463,28 @@ async fn test_http1_malformed_request() {
     assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
 }
 
+#[actix_rt::test]
+async fn test_http1_custom_error_response() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .error_response(|status| {
+                Response::build(status)
+                    .content_type("application/json")
+                    .body(format!("{{\"status\":{}}}", status.as_u16()))
+            })
+            .h1(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+    assert!(data.contains("content-type: application/json"));
+    assert!(data.ends_with("{\"status\":400}"));
+}
+
 #[actix_rt::test]
 async fn test_http1_keepalive() {
     let srv = test_server(|| {
@@ -350,7 +639,7 @@ async fn test_content_length() {
     let value = HeaderValue::from_static("0");
 
     {
-        for i in 0..4 {
+        for i in &[0, 2] {
             let req = srv.request(http::Method::GET, srv.url(&format!("/{}", i)));
             let response = req.send().await.unwrap();
             assert_eq!(response.headers().get(&header), None);
@@ -360,6 +649,13 @@ async fn test_content_length() {
             assert_eq!(response.headers().get(&header), None);
         }
 
+        // informational status is not a final response
+        for i in &[1, 3] {
+            let req = srv.request(http::Method::GET, srv.url(&format!("/{}", i)));
+            let response = req.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
         for i in 4..6 {
             let req = srv.request(http::Method::GET, srv.url(&format!("/{}", i)));
             let response = req.send().await.unwrap();
@@ -652,3 +948,283 @@ async fn test_h1_on_connect() {
     let response = srv.get("/").send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[actix_rt::test]
+async fn test_h1_connect_tunnel() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h1(|mut req: Request| {
+                assert_eq!(*req.method(), http::Method::CONNECT);
+                future::ok::<_, ()>(Response::Ok().streaming(req.take_payload()))
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"CONNECT example.com:443 HTTP/1.1\r\n\r\nhello");
+    let _ = stream.shutdown(net::Shutdown::Write);
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!data.contains("transfer-encoding"));
+    assert!(data.ends_with("\r\n\r\nhello"));
+}
+
+#[actix_rt::test]
+async fn test_h1_upgraded() {
+    use actix_codec::{BytesCodec, Framed};
+    use actix_http::h1::Upgraded;
+    use futures::SinkExt;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|mut req: Request| {
+                let (res, io) = Upgraded::handshake(
+                    req.take_payload(),
+                    Response::build(http::StatusCode::OK).upgrade("echo"),
+                );
+                actix_rt::spawn(async move {
+                    let mut framed = Framed::new(io, BytesCodec);
+                    while let Some(Ok(chunk)) = framed.next().await {
+                        let _ = framed.send(chunk.freeze()).await;
+                    }
+                });
+                future::ok::<_, ()>(res)
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\nhello",
+    );
+    let _ = stream.shutdown(net::Shutdown::Write);
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(data.contains("upgrade: echo\r\n"));
+    assert!(!data.contains("transfer-encoding"));
+    assert!(data.ends_with("\r\n\r\nhello"));
+}
+
+#[actix_rt::test]
+async fn test_h1_early_hints() {
+    use actix_http::h1::EarlyHints;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .early_hints(true)
+            .h1(|req: Request| {
+                let hints = req.extensions().get::<EarlyHints>().cloned();
+                async move {
+                    if let Some(hints) = hints {
+                        hints.send(
+                            Response::EarlyHints()
+                                .header(header::LINK, "</style.css>; rel=preload")
+                                .finish(),
+                        );
+                    }
+                    delay_for(Duration::from_millis(10)).await;
+                    Ok::<_, ()>(Response::Ok().body("done"))
+                }
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with(
+        "HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload\r\n\r\n\
+         HTTP/1.1 200 OK\r\n"
+    ));
+    assert!(data.ends_with("done"));
+
+    // hints are not sent to HTTP/1.0 clients
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.0\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+}
+
+#[actix_rt::test]
+async fn test_h1_informational_final_response() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h1(|_| ok::<_, ()>(Response::EarlyHints().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+    assert!(!data.contains("103"));
+}
+
+#[actix_rt::test]
+async fn test_h1_pipelining_disabled() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let release = Arc::new(AtomicBool::new(false));
+    let (calls2, release2) = (calls.clone(), release.clone());
+    let srv = test_server(move || {
+        let (calls, release) = (calls2.clone(), release2.clone());
+        HttpService::build()
+            .max_pipelined_requests(0)
+            .h1(move |req: Request| {
+                let path = req.path().to_string();
+                calls.lock().unwrap().push(path.clone());
+
+                // body of first response ends once test releases it
+                let release = release.clone();
+                let tail = futures::stream::unfold(false, move |done| {
+                    let release = release.clone();
+                    async move {
+                        if done {
+                            return None;
+                        }
+                        while !release.load(Ordering::SeqCst) {
+                            delay_for(Duration::from_millis(5)).await;
+                        }
+                        Some((Ok::<_, Error>(Bytes::from_static(b"-end")), true))
+                    }
+                });
+                let body = once(ok::<_, Error>(Bytes::from(path))).chain(tail);
+                ok::<_, ()>(Response::Ok().streaming(Box::pin(body)))
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /first HTTP/1.1\r\n\r\nGET /second HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+
+    // first response is being written
+    let mut data = vec![0; 1024];
+    let mut len = 0;
+    while !String::from_utf8_lossy(&data[..len]).contains("/first") {
+        len += stream.read(&mut data[len..]).unwrap();
+    }
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(*calls.lock().unwrap(), vec!["/first".to_owned()]);
+
+    release.store(true, Ordering::SeqCst);
+    let mut rest = String::new();
+    let _ = stream.read_to_string(&mut rest);
+    let data = String::from_utf8_lossy(&data[..len]).into_owned() + &rest;
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec!["/first".to_owned(), "/second".to_owned()]
+    );
+    let first = data.find("/first").unwrap();
+    let second = data.find("/second").unwrap();
+    assert!(first < data.find("-end").unwrap());
+    assert!(first < second);
+    assert_eq!(data.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+}
+
+#[actix_rt::test]
+async fn test_h1_idle_limit() {
+    let limit = IdleLimit::new(1);
+    let limit2 = limit.clone();
+    let srv = test_server(move || {
+        HttpService::build()
+            .idle_limit(limit2.clone())
+            .h1(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    fn request(stream: &mut net::TcpStream) {
+        let _ = stream.write_all(b"GET /test HTTP/1.1\r\n\r\n");
+        let mut data = vec![0; 1024];
+        let mut len = 0;
+        while !data[..len].ends_with(b"\r\n\r\n") {
+            len += stream.read(&mut data[len..]).unwrap();
+        }
+        assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    }
+
+    let mut first = net::TcpStream::connect(srv.addr()).unwrap();
+    request(&mut first);
+    let mut second = net::TcpStream::connect(srv.addr()).unwrap();
+    request(&mut second);
+
+    // least recently used idle connection is closed
+    let mut data = vec![0; 1024];
+    assert_eq!(first.read(&mut data).unwrap(), 0);
+    assert_eq!(limit.evicted(), 1);
+    assert_eq!(limit.idle(), 1);
+    request(&mut second);
+}
+
+#[actix_rt::test]
+async fn test_h1_chunked_trailers() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+                let mut body = BytesMut::new();
+                while let Some(chunk) = pl.next().await {
+                    body.extend_from_slice(&chunk?);
+                }
+                let checksum = req
+                    .extensions()
+                    .get::<Trailers>()
+                    .and_then(|trailers| trailers.get("x-checksum"))
+                    .unwrap();
+
+                let trailers = Trailers::new();
+                let name = header::HeaderName::from_static("x-checksum");
+                trailers.insert(name, checksum);
+                Ok::<_, Error>(
+                    Response::Ok()
+                        .header(header::TRAILER, "x-checksum")
+                        .trailers(trailers)
+                        .streaming(once(ok::<_, Error>(body.freeze()))),
+                )
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST / HTTP/1.1\r\nconnection: close\r\ntransfer-encoding: chunked\r\n\r\n\
+          4\r\ndata\r\n0\r\nx-checksum: 1234\r\ncontent-length: 4\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("\r\n\r\n4\r\ndata\r\n0\r\nx-checksum: 1234\r\n\r\n"));
+}
+
+#[actix_rt::test]
+async fn test_h1_on_connect_ext() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .on_connect(|_| 10usize)
+            .on_connect_ext(|io: &TcpStream, ext| {
+                ext.insert(io.peer_addr().unwrap());
+            })
+            .h1(|req: Request| {
+                assert!(req.extensions().contains::<usize>());
+                let ext = req.extensions();
+                let conn = ext.get::<ConnectionExtensions>().unwrap();
+                assert!(conn.contains::<net::SocketAddr>());
+                future::ok::<_, ()>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let response = srv.get("/").send().await.unwrap();
+    assert!(response.status().is_success());
+}
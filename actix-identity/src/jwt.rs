@@ -0,0 +1,591 @@
+//! Identity policy that stores identity in JSON Web Token
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::future::{ok, ready, Ready};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use actix_web::cookie::{Cookie, CookieJar, Key, SameSite};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::{Error, ErrorInternalServerError, Result};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::HttpMessage;
+
+use crate::IdentityPolicy;
+
+/// Name of cookie that is used for encryption of tokens
+const TOKEN: &str = "token";
+
+enum Transport {
+    Cookie,
+    Header(HeaderName),
+}
+
+struct JwtKey {
+    secret: Vec<u8>,
+    key: Key,
+}
+
+impl JwtKey {
+    fn new(secret: &[u8]) -> JwtKey {
+        JwtKey {
+            secret: secret.to_vec(),
+            key: Key::from_master(secret),
+        }
+    }
+}
+
+struct JwtIdentityInner {
+    keys: Vec<JwtKey>,
+    encrypt: bool,
+    transport: Transport,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    same_site: Option<SameSite>,
+    expiry: Duration,
+    refresh: Option<Duration>,
+    audience: Option<String>,
+    issuer: Option<String>,
+    leeway: u64,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    sub: &'a str,
+    iat: u64,
+    exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct IdentityClaims {
+    sub: String,
+    exp: u64,
+}
+
+/// Marker of identity, which token has to be reissued
+struct JwtReissue;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl JwtIdentityInner {
+    fn validation(&self) -> Validation {
+        let mut validation = Validation {
+            leeway: self.leeway,
+            iss: self.issuer.clone(),
+            ..Validation::default()
+        };
+        if let Some(ref aud) = self.audience {
+            validation.set_audience(&[aud]);
+        }
+        validation
+    }
+
+    fn encode(&self, identity: &str) -> Result<String> {
+        let iat = now();
+        let claims = Claims {
+            sub: identity,
+            iat,
+            exp: (iat as i64 + self.expiry.num_seconds()).max(0) as u64,
+            aud: self.audience.as_ref().map(|s| s.as_str()),
+            iss: self.issuer.as_ref().map(|s| s.as_str()),
+        };
+        let key = &self.keys[0];
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&key.secret),
+        )
+        .map_err(ErrorInternalServerError)?;
+
+        if self.encrypt {
+            let mut jar = CookieJar::new();
+            jar.private(&key.key).add(Cookie::new(TOKEN, token));
+            Ok(jar.get(TOKEN).unwrap().value().to_owned())
+        } else {
+            Ok(token)
+        }
+    }
+
+    /// Decode and validate token, returned flag is set if token is protected
+    /// with one of previous keys.
+    fn decode(&self, value: &str) -> Option<(IdentityClaims, bool)> {
+        let validation = self.validation();
+
+        for (idx, key) in self.keys.iter().enumerate() {
+            let token = if self.encrypt {
+                let mut jar = CookieJar::new();
+                jar.add_original(Cookie::new(TOKEN, value.to_owned()));
+                match jar.private(&key.key).get(TOKEN) {
+                    Some(cookie) => cookie.value().to_owned(),
+                    None => continue,
+                }
+            } else {
+                value.to_owned()
+            };
+
+            let key = DecodingKey::from_secret(&key.secret);
+            if let Ok(data) = decode::<IdentityClaims>(&token, &key, &validation) {
+                return Some((data.claims, idx > 0));
+            }
+        }
+        None
+    }
+
+    fn load(&self, req: &ServiceRequest) -> Option<String> {
+        match self.transport {
+            Transport::Cookie => req.cookie(&self.name).map(|c| c.value().to_owned()),
+            Transport::Header(_) => {
+                let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+                if value.len() > 7 && value[..7].eq_ignore_ascii_case("bearer ") {
+                    Some(value[7..].trim().to_owned())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn set_token<B>(
+        &self,
+        res: &mut ServiceResponse<B>,
+        token: Option<String>,
+    ) -> Result<()> {
+        let name = match self.transport {
+            Transport::Cookie => &self.name,
+            Transport::Header(ref name) => {
+                if let Some(token) = token {
+                    res.headers_mut()
+                        .insert(name.clone(), HeaderValue::from_str(&token)?);
+                }
+                return Ok(());
+            }
+        };
+
+        let add_cookie = token.is_some();
+        let mut cookie = Cookie::new(name.clone(), token.unwrap_or_default());
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(true);
+
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+
+        let mut jar = CookieJar::new();
+        if add_cookie {
+            cookie.set_max_age(self.expiry);
+            jar.add(cookie);
+        } else {
+            jar.add_original(cookie.clone());
+            jar.remove(cookie);
+        }
+        for cookie in jar.delta() {
+            let val = HeaderValue::from_str(&cookie.to_string())?;
+            res.headers_mut().append(header::SET_COOKIE, val);
+        }
+        Ok(())
+    }
+}
+
+/// Use JSON Web Tokens for request identity storage.
+///
+/// Identity is stored in `sub` claim of token signed with `HS256`
+/// algorithm, so it could be validated by any instance that shares the
+/// secret, without server side state. Token is stored in a cookie, or is
+/// taken from `Authorization: Bearer` request header, if `header()` is set.
+///
+/// Token expires after `expiry`, which is one day by default, it could be
+/// reissued before expiration with `refresh()`. Token is encrypted, so
+/// identity is not visible to clients, if `encrypt()` is set.
+///
+/// The constructor takes a secret as an argument, when this value is
+/// changed all identities are lost, unless previous secret is registered
+/// with `previous_key()`. The constructor will panic if the secret is less
+/// than 32 bytes in length.
+///
+/// # Example
+///
+/// ```rust
+/// use actix_web::App;
+/// use actix_identity::{IdentityService, JwtIdentityPolicy};
+///
+/// fn main() {
+///     let app = App::new().wrap(IdentityService::new(
+///         JwtIdentityPolicy::new(&[0; 32])
+///             .previous_key(&[1; 32])
+///             .audience("api.example.com")
+///             .issuer("auth.example.com")
+///             .expiry(time::Duration::hours(1))
+///             .refresh(time::Duration::minutes(15)),
+///     ));
+/// }
+/// ```
+pub struct JwtIdentityPolicy(Rc<JwtIdentityInner>);
+
+impl JwtIdentityPolicy {
+    /// Construct new `JwtIdentityPolicy` instance.
+    ///
+    /// Panics if secret length is less than 32 bytes.
+    pub fn new(secret: &[u8]) -> JwtIdentityPolicy {
+        JwtIdentityPolicy(Rc::new(JwtIdentityInner {
+            keys: vec![JwtKey::new(secret)],
+            encrypt: false,
+            transport: Transport::Cookie,
+            name: "actix-identity".to_owned(),
+            path: "/".to_owned(),
+            domain: None,
+            secure: true,
+            same_site: None,
+            expiry: Duration::days(1),
+            refresh: None,
+            audience: None,
+            issuer: None,
+            leeway: 0,
+        }))
+    }
+
+    /// Accept tokens protected with previous secret, used for key rotation.
+    ///
+    /// Could be called multiple times, secrets are tried in registration
+    /// order after the current one. Tokens protected with previous secret
+    /// are reissued. Panics if secret length is less than 32 bytes.
+    pub fn previous_key(mut self, secret: &[u8]) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .keys
+            .push(JwtKey::new(secret));
+        self
+    }
+
+    /// Encrypt tokens, with key derived from the secret.
+    pub fn encrypt(mut self, value: bool) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().encrypt = value;
+        self
+    }
+
+    /// Take token from `Authorization: Bearer` request header instead of
+    /// cookie. New tokens are set to specified response header.
+    pub fn header(mut self, name: HeaderName) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().transport = Transport::Header(name);
+        self
+    }
+
+    /// Sets the `name` field in the identity cookie being built.
+    pub fn name<S: Into<String>>(mut self, value: S) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().name = value.into();
+        self
+    }
+
+    /// Sets the `path` field in the identity cookie being built.
+    pub fn path<S: Into<String>>(mut self, value: S) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().path = value.into();
+        self
+    }
+
+    /// Sets the `domain` field in the identity cookie being built.
+    pub fn domain<S: Into<String>>(mut self, value: S) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().domain = Some(value.into());
+        self
+    }
+
+    /// Sets the `secure` field in the identity cookie being built.
+    pub fn secure(mut self, value: bool) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().secure = value;
+        self
+    }
+
+    /// Sets the `same_site` field in the identity cookie being built.
+    pub fn same_site(mut self, same_site: SameSite) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().same_site = Some(same_site);
+        self
+    }
+
+    /// Sets lifetime of tokens, and `max-age` of identity cookie.
+    ///
+    /// By default tokens expire after one day.
+    pub fn expiry(mut self, value: Duration) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().expiry = value;
+        self
+    }
+
+    /// Reissue tokens that expire within given duration.
+    ///
+    /// By default tokens are not reissued.
+    pub fn refresh(mut self, value: Duration) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().refresh = Some(value);
+        self
+    }
+
+    /// Sets `aud` claim of issued tokens, and accepts only tokens with it.
+    pub fn audience<S: Into<String>>(mut self, value: S) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().audience = Some(value.into());
+        self
+    }
+
+    /// Sets `iss` claim of issued tokens, and accepts only tokens with it.
+    pub fn issuer<S: Into<String>>(mut self, value: S) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().issuer = Some(value.into());
+        self
+    }
+
+    /// Sets leeway in seconds for validation of token expiration, for clock
+    /// skew between instances. By default is 0.
+    pub fn leeway(mut self, seconds: u64) -> JwtIdentityPolicy {
+        Rc::get_mut(&mut self.0).unwrap().leeway = seconds;
+        self
+    }
+}
+
+impl IdentityPolicy for JwtIdentityPolicy {
+    type Future = Ready<Result<Option<String>, Error>>;
+    type ResponseFuture = Ready<Result<(), Error>>;
+
+    fn from_request(&self, req: &mut ServiceRequest) -> Self::Future {
+        let claims = self.0.load(req).and_then(|value| self.0.decode(&value));
+
+        ok(claims.map(|(claims, outdated)| {
+            let expiring = self.0.refresh.map_or(false, |refresh| {
+                (claims.exp as i64) - (now() as i64) < refresh.num_seconds()
+            });
+            if outdated || expiring {
+                req.extensions_mut().insert(JwtReissue);
+            }
+            claims.sub
+        }))
+    }
+
+    fn to_response<B>(
+        &self,
+        id: Option<String>,
+        changed: bool,
+        res: &mut ServiceResponse<B>,
+    ) -> Self::ResponseFuture {
+        let reissue = res
+            .request()
+            .extensions_mut()
+            .remove::<JwtReissue>()
+            .is_some();
+
+        let result = if changed || (reissue && id.is_some()) {
+            match id {
+                Some(id) => self
+                    .0
+                    .encode(&id)
+                    .and_then(|token| self.0.set_token(res, Some(token))),
+                None => self.0.set_token(res, None),
+            }
+        } else {
+            Ok(())
+        };
+        ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Identity, IdentityService};
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    const SECRET: [u8; 32] = [0; 32];
+    const X_AUTH_TOKEN: &str = "x-auth-token";
+
+    async fn create_jwt_server(
+        policy: JwtIdentityPolicy,
+    ) -> impl actix_service::Service<
+        Request = actix_http::Request,
+        Response = ServiceResponse<actix_web::body::Body>,
+        Error = Error,
+    > {
+        test::init_service(
+            App::new()
+                .wrap(IdentityService::new(policy))
+                .service(web::resource("/index").to(|id: Identity| {
+                    HttpResponse::Ok().body(id.identity().unwrap_or_default())
+                }))
+                .service(web::resource("/login").to(|id: Identity| {
+                    id.remember("user".to_owned());
+                    HttpResponse::Ok()
+                }))
+                .service(web::resource("/logout").to(|id: Identity| {
+                    id.forget();
+                    HttpResponse::Ok()
+                })),
+        )
+        .await
+    }
+
+    #[actix_rt::test]
+    async fn test_jwt_cookie() {
+        let mut srv = create_jwt_server(
+            JwtIdentityPolicy::new(&SECRET)
+                .name("auth")
+                .expiry(Duration::hours(1)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&mut srv, TestRequest::with_uri("/login").to_request())
+                .await;
+        let c = resp.response().cookies().next().unwrap().to_owned();
+        assert_eq!(c.name(), "auth");
+        assert_eq!(c.max_age(), Some(Duration::hours(1)));
+        assert_eq!(c.value().split('.').count(), 3);
+
+        let req = TestRequest::with_uri("/index")
+            .cookie(c.clone())
+            .to_request();
+        let body = test::read_response(&mut srv, req).await;
+        assert_eq!(body, "user");
+
+        let req = TestRequest::with_uri("/logout").cookie(c).to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        let c = resp.response().cookies().next().unwrap().to_owned();
+        assert_eq!(c.value(), "");
+
+        // invalid signature
+        let mut srv =
+            create_jwt_server(JwtIdentityPolicy::new(&[1; 32]).name("auth")).await;
+        let req = TestRequest::with_uri("/login").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        let c = resp.response().cookies().next().unwrap().to_owned();
+        let mut srv =
+            create_jwt_server(JwtIdentityPolicy::new(&SECRET).name("auth")).await;
+        let req = TestRequest::with_uri("/index").cookie(c).to_request();
+        assert_eq!(test::read_response(&mut srv, req).await, "");
+    }
+
+    #[actix_rt::test]
+    async fn test_jwt_header() {
+        let policy = || {
+            JwtIdentityPolicy::new(&SECRET)
+                .header(HeaderName::from_static(X_AUTH_TOKEN))
+                .encrypt(true)
+        };
+        let mut srv = create_jwt_server(policy()).await;
+
+        let req = TestRequest::with_uri("/login").to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert!(!resp.headers().contains_key(header::SET_COOKIE));
+        let token = resp.headers().get(X_AUTH_TOKEN).unwrap().to_str().unwrap();
+        // token is encrypted
+        assert_eq!(token.split('.').count(), 1);
+        let token = format!("Bearer {}", token);
+
+        let req = TestRequest::with_uri("/index")
+            .header(header::AUTHORIZATION, token.as_str())
+            .to_request();
+        assert_eq!(test::read_response(&mut srv, req).await, "user");
+
+        // not encrypted token is not accepted
+        let mut plain = create_jwt_server(
+            JwtIdentityPolicy::new(&SECRET)
+                .header(HeaderName::from_static(X_AUTH_TOKEN)),
+        )
+        .await;
+        let req = TestRequest::with_uri("/login").to_request();
+        let resp = test::call_service(&mut plain, req).await;
+        let token = resp.headers().get(X_AUTH_TOKEN).unwrap().to_str().unwrap();
+        let req = TestRequest::with_uri("/index")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .to_request();
+        assert_eq!(test::read_response(&mut srv, req).await, "");
+    }
+
+    #[actix_rt::test]
+    async fn test_jwt_validation() {
+        let mut srv = create_jwt_server(
+            JwtIdentityPolicy::new(&SECRET)
+                .header(HeaderName::from_static(X_AUTH_TOKEN))
+                .audience("api")
+                .issuer("auth"),
+        )
+        .await;
+
+        for (policy, valid) in vec![
+            (
+                JwtIdentityPolicy::new(&SECRET)
+                    .audience("api")
+                    .issuer("auth"),
+                true,
+            ),
+            (
+                JwtIdentityPolicy::new(&SECRET)
+                    .audience("other")
+                    .issuer("auth"),
+                false,
+            ),
+            (JwtIdentityPolicy::new(&SECRET).audience("api"), false),
+            (
+                JwtIdentityPolicy::new(&SECRET)
+                    .audience("api")
+                    .issuer("auth")
+                    .expiry(Duration::seconds(-10)),
+                false,
+            ),
+        ] {
+            let token = format!("Bearer {}", policy.0.encode("user").unwrap());
+            let req = TestRequest::with_uri("/index")
+                .header(header::AUTHORIZATION, token)
+                .to_request();
+            let body = test::read_response(&mut srv, req).await;
+            assert_eq!(body == "user", valid);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_jwt_reissue() {
+        let previous = JwtIdentityPolicy::new(&[1; 32]);
+        let mut srv = create_jwt_server(
+            JwtIdentityPolicy::new(&SECRET)
+                .previous_key(&[1; 32])
+                .header(HeaderName::from_static(X_AUTH_TOKEN))
+                .expiry(Duration::hours(1))
+                .refresh(Duration::minutes(10)),
+        )
+        .await;
+
+        // token with previous key is accepted and reissued
+        let token = format!("Bearer {}", previous.0.encode("user").unwrap());
+        let req = TestRequest::with_uri("/index")
+            .header(header::AUTHORIZATION, token)
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        let token = resp.headers().get(X_AUTH_TOKEN).unwrap().to_str().unwrap();
+        assert!(JwtIdentityPolicy::new(&SECRET).0.decode(token).is_some());
+
+        // fresh token is not reissued
+        let req = TestRequest::with_uri("/index")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert!(!resp.headers().contains_key(X_AUTH_TOKEN));
+
+        // expiring token is reissued
+        let expiring = JwtIdentityPolicy::new(&SECRET).expiry(Duration::minutes(5));
+        let token = format!("Bearer {}", expiring.0.encode("user").unwrap());
+        let req = TestRequest::with_uri("/index")
+            .header(header::AUTHORIZATION, token)
+            .to_request();
+        let resp = test::call_service(&mut srv, req).await;
+        assert!(resp.headers().contains_key(X_AUTH_TOKEN));
+    }
+}
@@ -9,6 +9,9 @@
 //! [**CookieIdentityPolicy**](struct.CookieIdentityPolicy.html)
 //! uses cookies as identity storage.
 //!
+//! [**JwtIdentityPolicy**](struct.JwtIdentityPolicy.html)
+//! uses JSON Web Tokens as identity storage, it requires `jwt` feature.
+//!
 //! To access current request identity
 //! [**Identity**](struct.Identity.html) extractor should be used.
 //!
@@ -63,6 +66,11 @@ use actix_web::error::{Error, Result};
 use actix_web::http::header::{self, HeaderValue};
 use actix_web::{FromRequest, HttpMessage, HttpRequest};
 
+#[cfg(feature = "jwt")]
+mod jwt;
+#[cfg(feature = "jwt")]
+pub use crate::jwt::JwtIdentityPolicy;
+
 /// The extractor type to obtain your identity from a request.
 ///
 /// ```rust
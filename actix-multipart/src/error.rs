@@ -1,4 +1,6 @@
 //! Error and Result module
+use std::io;
+
 use actix_web::error::{ParseError, PayloadError};
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
@@ -31,12 +33,34 @@ pub enum MultipartError {
     /// Not consumed
     #[display(fmt = "Multipart stream is not consumed")]
     NotConsumed,
+    /// Field is larger than allowed by form config
+    #[display(fmt = "Multipart field {} exceeds size limit", _0)]
+    #[from(ignore)]
+    FieldLimit(String),
+    /// Total size of fields is larger than allowed
+    #[display(fmt = "Multipart payload exceeds size limit")]
+    TotalLimit,
+    /// Number of fields is larger than allowed by form config
+    #[display(fmt = "Multipart form has too many fields")]
+    FieldsLimit,
+    /// Can not deserialize form fields
+    #[display(fmt = "{}", _0)]
+    Deserialize(serde_urlencoded::de::Error),
+    /// Can not store uploaded file
+    #[display(fmt = "{}", _0)]
+    Io(io::Error),
 }
 
 /// Return `BadRequest` for `MultipartError`
 impl ResponseError for MultipartError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match *self {
+            MultipartError::FieldLimit(_)
+            | MultipartError::TotalLimit
+            | MultipartError::FieldsLimit => StatusCode::PAYLOAD_TOO_LARGE,
+            MultipartError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
@@ -49,5 +73,9 @@ mod tests {
     fn test_multipart_error() {
         let resp: HttpResponse = MultipartError::Boundary.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp: HttpResponse =
+            MultipartError::FieldLimit("file".to_string()).error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 }
@@ -0,0 +1,594 @@
+//! Typed multipart form extractor
+use std::io::{self, Write};
+use std::{fmt, ops};
+
+use actix_web::error::{BlockingError, ParseError};
+use actix_web::{dev::Payload, web, Error, FromRequest, HttpRequest};
+use bytes::{Bytes, BytesMut};
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use serde::de::{self, DeserializeOwned};
+use tempfile::NamedTempFile;
+
+use crate::error::MultipartError;
+use crate::server::{Field, Multipart};
+
+/// Extract typed multipart form from the request.
+///
+/// Text fields are deserialized into `T` the same way as urlencoded forms,
+/// fields with file name are collected as [`UploadedFile`](struct.UploadedFile.html).
+/// Multiple files of a field that are sent as nested `multipart/mixed` stream
+/// are collected with name of the field.
+/// Size and number of fields are limited with
+/// [**MultipartFormConfig**](struct.MultipartFormConfig.html).
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_multipart::MultipartForm;
+/// use actix_web::{web, App, HttpResponse};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Upload {
+///     title: String,
+/// }
+///
+/// async fn upload(form: MultipartForm<Upload>) -> HttpResponse {
+///     match form.file("image") {
+///         Some(image) => HttpResponse::Ok().body(format!(
+///             "{}: {:?} {} bytes",
+///             form.title,
+///             image.file_name(),
+///             image.size()
+///         )),
+///         None => HttpResponse::BadRequest().finish(),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/upload", web::post().to(upload));
+/// }
+/// ```
+pub struct MultipartForm<T> {
+    data: T,
+    files: Vec<(String, UploadedFile)>,
+}
+
+impl<T> MultipartForm<T> {
+    /// Deconstruct to a inner value
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    /// Deconstruct to a inner value and uploaded files with field names
+    pub fn into_parts(self) -> (T, Vec<(String, UploadedFile)>) {
+        (self.data, self.files)
+    }
+
+    /// First file uploaded with field name
+    pub fn file<'a>(&'a self, name: &'a str) -> Option<&'a UploadedFile> {
+        self.files(name).next()
+    }
+
+    /// Files uploaded with field name
+    pub fn files<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a UploadedFile> {
+        self.files
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, file)| file)
+    }
+}
+
+impl<T> ops::Deref for MultipartForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<T> ops::DerefMut for MultipartForm<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MultipartForm<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartForm")
+            .field("data", &self.data)
+            .field("files", &self.files)
+            .finish()
+    }
+}
+
+impl<T> FromRequest for MultipartForm<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Config = MultipartFormConfig;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = req
+            .app_data::<MultipartFormConfig>()
+            .cloned()
+            .unwrap_or_default();
+        let mut multipart = Multipart::new(req.headers(), payload.take());
+
+        async move {
+            let mut text = Vec::new();
+            let mut files = Vec::new();
+            let mut total = 0;
+            let mut fields = 0;
+
+            while let Some(field) = multipart.next().await {
+                let mut field = field?;
+                fields += 1;
+                if fields > config.max_fields {
+                    return Err(MultipartError::FieldsLimit.into());
+                }
+                let cd = field.content_disposition();
+                let name = match cd.as_ref().and_then(|cd| cd.get_name()) {
+                    Some(name) => name.to_string(),
+                    None => {
+                        // unnamed fields are not part of the form, but count
+                        // to the total
+                        while let Some(chunk) = field.next().await {
+                            total += chunk?.len();
+                            if total > config.total_limit {
+                                return Err(MultipartError::TotalLimit.into());
+                            }
+                        }
+                        continue;
+                    }
+                };
+                let file_name = cd
+                    .as_ref()
+                    .and_then(|cd| cd.get_filename())
+                    .map(|s| s.to_string());
+
+                if field.is_multipart() {
+                    // files of field are parts of nested stream
+                    let mut nested = field.into_multipart();
+                    while let Some(part) = nested.next().await {
+                        let part = part?;
+                        fields += 1;
+                        if fields > config.max_fields {
+                            return Err(MultipartError::FieldsLimit.into());
+                        }
+                        let file_name = part
+                            .content_disposition()
+                            .as_ref()
+                            .and_then(|cd| cd.get_filename())
+                            .map(|s| s.to_string());
+                        let remaining = config.total_limit - total;
+                        let file = read_file(part, &name, file_name, &config, remaining)
+                            .await?;
+                        total += file.size;
+                        files.push((name.clone(), file));
+                    }
+                } else if file_name.is_some() {
+                    let remaining = config.total_limit - total;
+                    let file =
+                        read_file(field, &name, file_name, &config, remaining).await?;
+                    total += file.size;
+                    files.push((name, file));
+                } else {
+                    let remaining = config.total_limit - total;
+                    let data =
+                        read_bytes(&mut field, &name, config.text_limit, remaining)
+                            .await?;
+                    total += data.len();
+                    let value = String::from_utf8(data.to_vec()).map_err(|e| {
+                        MultipartError::Parse(ParseError::Utf8(e.utf8_error()))
+                    })?;
+                    text.push((name, value));
+                }
+            }
+
+            // reuse urlencoded deserializer for text fields
+            let encoded = serde_urlencoded::to_string(&text)
+                .map_err(|e| MultipartError::Deserialize(de::Error::custom(e)))?;
+            let data = serde_urlencoded::from_str::<T>(&encoded)
+                .map_err(MultipartError::Deserialize)?;
+
+            Ok(MultipartForm { data, files })
+        }
+        .boxed_local()
+    }
+}
+
+/// Check size of field against limit of field and remaining size of form
+fn check_limit(
+    size: usize,
+    name: &str,
+    limit: usize,
+    remaining: usize,
+) -> Result<(), MultipartError> {
+    if size > remaining {
+        Err(MultipartError::TotalLimit)
+    } else if size > limit {
+        Err(MultipartError::FieldLimit(name.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+async fn read_bytes(
+    field: &mut Field,
+    name: &str,
+    limit: usize,
+    remaining: usize,
+) -> Result<BytesMut, MultipartError> {
+    let mut data = BytesMut::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk?;
+        check_limit(data.len() + chunk.len(), name, limit, remaining)?;
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+async fn read_file(
+    mut field: Field,
+    name: &str,
+    file_name: Option<String>,
+    config: &MultipartFormConfig,
+    remaining: usize,
+) -> Result<UploadedFile, MultipartError> {
+    let content_type = field.content_type().clone();
+
+    if !config.temp_files {
+        let data = read_bytes(&mut field, name, config.file_limit, remaining).await?;
+        return Ok(UploadedFile {
+            file_name,
+            content_type,
+            size: data.len(),
+            data: FileData::Memory(data.freeze()),
+        });
+    }
+
+    let mut file = web::block(NamedTempFile::new)
+        .await
+        .map_err(blocking_error)?;
+    let mut size = 0;
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk?;
+        size += chunk.len();
+        check_limit(size, name, config.file_limit, remaining)?;
+        file = web::block(move || file.write_all(&chunk).map(|_| file))
+            .await
+            .map_err(blocking_error)?;
+    }
+
+    Ok(UploadedFile {
+        file_name,
+        content_type,
+        size,
+        data: FileData::Temp(file),
+    })
+}
+
+pub(crate) fn blocking_error(err: BlockingError<io::Error>) -> MultipartError {
+    match err {
+        BlockingError::Error(e) => MultipartError::Io(e),
+        BlockingError::Canceled => MultipartError::Io(io::Error::new(
+            io::ErrorKind::Other,
+            "Thread pool is gone",
+        )),
+    }
+}
+
+/// File uploaded with multipart form
+pub struct UploadedFile {
+    file_name: Option<String>,
+    content_type: mime::Mime,
+    size: usize,
+    data: FileData,
+}
+
+enum FileData {
+    Memory(Bytes),
+    Temp(NamedTempFile),
+}
+
+impl UploadedFile {
+    /// File name provided by client
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_ref().map(|s| s.as_str())
+    }
+
+    /// Content type of the file
+    pub fn content_type(&self) -> &mime::Mime {
+        &self.content_type
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Content of file kept in memory, `None` for temporary files
+    pub fn bytes(&self) -> Option<&Bytes> {
+        match self.data {
+            FileData::Memory(ref data) => Some(data),
+            FileData::Temp(_) => None,
+        }
+    }
+
+    /// Temporary file with content, `None` for files kept in memory.
+    ///
+    /// File is removed once it is dropped, use `NamedTempFile::persist()`
+    /// to keep it.
+    pub fn temp_file(&self) -> Option<&NamedTempFile> {
+        match self.data {
+            FileData::Memory(_) => None,
+            FileData::Temp(ref file) => Some(file),
+        }
+    }
+
+    /// Convert into temporary file, `None` for files kept in memory
+    pub fn into_temp_file(self) -> Option<NamedTempFile> {
+        match self.data {
+            FileData::Memory(_) => None,
+            FileData::Temp(file) => Some(file),
+        }
+    }
+}
+
+impl fmt::Debug for UploadedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UploadedFile")
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// Multipart form extractor configuration
+///
+/// ```rust
+/// use actix_multipart::{MultipartForm, MultipartFormConfig};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn upload(form: MultipartForm<()>) -> HttpResponse {
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .app_data(
+///                 MultipartFormConfig::default()
+///                     .file_limit(16 * 1024 * 1024)
+///                     .temp_files(true),
+///             )
+///             .route(web::post().to(upload)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultipartFormConfig {
+    text_limit: usize,
+    file_limit: usize,
+    total_limit: usize,
+    max_fields: usize,
+    temp_files: bool,
+}
+
+impl MultipartFormConfig {
+    /// Change maximum size of text field, by default 16kB.
+    pub fn text_limit(mut self, limit: usize) -> Self {
+        self.text_limit = limit;
+        self
+    }
+
+    /// Change maximum size of file, by default 1MB.
+    pub fn file_limit(mut self, limit: usize) -> Self {
+        self.file_limit = limit;
+        self
+    }
+
+    /// Change maximum total size of all fields, by default 16MB.
+    ///
+    /// Files of nested `multipart/mixed` streams and fields without name
+    /// count to the total, size of every file is limited with `file_limit()`.
+    pub fn total_limit(mut self, limit: usize) -> Self {
+        self.total_limit = limit;
+        self
+    }
+
+    /// Change maximum number of fields, by default 100.
+    ///
+    /// Every file of nested `multipart/mixed` stream counts as a field.
+    pub fn max_fields(mut self, max: usize) -> Self {
+        self.max_fields = max;
+        self
+    }
+
+    /// Store files in temporary files instead of memory, disabled by default.
+    pub fn temp_files(mut self, enabled: bool) -> Self {
+        self.temp_files = enabled;
+        self
+    }
+}
+
+impl Default for MultipartFormConfig {
+    fn default() -> Self {
+        MultipartFormConfig {
+            text_limit: 16_384,
+            file_limit: 1_048_576,
+            total_limit: 16_777_216,
+            max_fields: 100,
+            temp_files: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+    use serde_derive::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Upload {
+        title: String,
+        count: u32,
+        note: Option<String>,
+    }
+
+    fn form_request(config: MultipartFormConfig) -> (HttpRequest, Payload) {
+        TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+        )
+        .app_data(config)
+        .set_payload(Bytes::from_static(
+            b"--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+              holiday\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"count\"\r\n\r\n\
+              2\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"image\"; filename=\"a.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              first\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"image\"; filename=\"b.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              second\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n",
+        ))
+        .to_http_parts()
+    }
+
+    #[actix_rt::test]
+    async fn test_multipart_form() {
+        let (req, mut pl) = form_request(MultipartFormConfig::default());
+        let form = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(
+            *form,
+            Upload {
+                title: "holiday".to_string(),
+                count: 2,
+                note: None,
+            }
+        );
+        let files: Vec<_> = form.files("image").collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_name(), Some("a.txt"));
+        assert_eq!(files[0].content_type(), &mime::TEXT_PLAIN);
+        assert_eq!(files[0].bytes().unwrap(), &Bytes::from_static(b"first"));
+        assert_eq!(files[1].size(), 6);
+        assert!(form.file("other").is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_multipart_form_temp_files() {
+        let (req, mut pl) =
+            form_request(MultipartFormConfig::default().temp_files(true));
+        let form = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        let file = form.file("image").unwrap();
+        assert!(file.bytes().is_none());
+        let mut content = String::new();
+        file.temp_file()
+            .unwrap()
+            .reopen()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first");
+    }
+
+    #[actix_rt::test]
+    async fn test_multipart_form_limits() {
+        let (req, mut pl) = form_request(MultipartFormConfig::default().file_limit(5));
+        let err = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart field image exceeds size limit");
+
+        let (req, mut pl) = form_request(MultipartFormConfig::default().text_limit(4));
+        let err = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart field title exceeds size limit");
+
+        let (req, mut pl) = form_request(MultipartFormConfig::default().total_limit(15));
+        let err = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart payload exceeds size limit");
+
+        // total is checked while field is read
+        let (req, mut pl) = form_request(MultipartFormConfig::default().total_limit(10));
+        let err = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart payload exceeds size limit");
+
+        let (req, mut pl) = form_request(MultipartFormConfig::default().max_fields(3));
+        let err = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart form has too many fields");
+    }
+
+    #[actix_rt::test]
+    async fn test_multipart_form_nested() {
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=AaB03x",
+        )
+        .set_payload(Bytes::from_static(
+            b"--AaB03x\r\n\
+              Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+              holiday\r\n\
+              --AaB03x\r\n\
+              Content-Disposition: form-data; name=\"count\"\r\n\r\n\
+              2\r\n\
+              --AaB03x\r\n\
+              Content-Disposition: form-data; name=\"image\"\r\n\
+              Content-Type: multipart/mixed; boundary=BbC04y\r\n\r\n\
+              --BbC04y\r\n\
+              Content-Disposition: file; filename=\"a.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              first\r\n\
+              --BbC04y\r\n\
+              Content-Disposition: file; filename=\"b.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              second\r\n\
+              --BbC04y--\r\n\
+              --AaB03x--\r\n",
+        ))
+        .to_http_parts();
+        let form = MultipartForm::<Upload>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(form.title, "holiday");
+        let files: Vec<_> = form.files("image").collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_name(), Some("a.txt"));
+        assert_eq!(files[1].bytes().unwrap(), &Bytes::from_static(b"second"));
+    }
+}
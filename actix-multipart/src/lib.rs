@@ -2,7 +2,11 @@
 
 mod error;
 mod extractor;
+mod form;
+mod save;
 mod server;
 
 pub use self::error::MultipartError;
+pub use self::form::{MultipartForm, MultipartFormConfig, UploadedFile};
+pub use self::save::{sanitize_file_name, SaveConfig, SavedFile};
 pub use self::server::{Field, Multipart};
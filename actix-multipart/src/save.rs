@@ -0,0 +1,486 @@
+//! Saving of uploaded files
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{env, fmt};
+
+use actix_web::web;
+use futures::StreamExt;
+
+use crate::error::MultipartError;
+use crate::form::blocking_error;
+use crate::server::{Field, Multipart};
+
+type Sink = dyn Fn(&str, &str, &mime::Mime) -> io::Result<Box<dyn Write + Send>>;
+
+/// Configuration of `Multipart::save_files()`.
+///
+/// Files are saved to temporary directory of operating system by default,
+/// other directory is set with `dir()` and custom writers with `sink()`.
+/// Size of every file is limited to 16MB and total size of all fields is
+/// limited to 64MB by default.
+///
+/// ```rust
+/// use actix_multipart::{Multipart, SaveConfig};
+/// use actix_web::{Error, HttpResponse};
+///
+/// async fn upload(payload: Multipart) -> Result<HttpResponse, Error> {
+///     let files = payload
+///         .save_files(
+///             &SaveConfig::default()
+///                 .dir("./uploads")
+///                 .file_limit(1024 * 1024),
+///         )
+///         .await?;
+///     Ok(HttpResponse::Ok().body(format!("{} files saved", files.len())))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SaveConfig {
+    target: Target,
+    file_limit: u64,
+    total_limit: u64,
+}
+
+#[derive(Clone)]
+enum Target {
+    Dir(PathBuf),
+    Sink(Rc<Sink>),
+}
+
+impl SaveConfig {
+    /// Save files to directory `dir`, directory must exist.
+    ///
+    /// Name of saved file is sanitized file name provided by client, number
+    /// is appended to the name if file with the same name exists already.
+    pub fn dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.target = Target::Dir(dir.into());
+        self
+    }
+
+    /// Write files to writers created by `sink`.
+    ///
+    /// `sink` is called with field name, sanitized file name and content
+    /// type of every file, writes are done on thread pool.
+    pub fn sink<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&str, &str, &mime::Mime) -> io::Result<Box<dyn Write + Send>> + 'static,
+    {
+        self.target = Target::Sink(Rc::new(sink));
+        self
+    }
+
+    /// Change maximum size of every file, by default 16MB.
+    pub fn file_limit(mut self, limit: u64) -> Self {
+        self.file_limit = limit;
+        self
+    }
+
+    /// Change maximum total size of all fields, by default 64MB.
+    pub fn total_limit(mut self, limit: u64) -> Self {
+        self.total_limit = limit;
+        self
+    }
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        SaveConfig {
+            target: Target::Dir(env::temp_dir()),
+            file_limit: 16_777_216,
+            total_limit: 67_108_864,
+        }
+    }
+}
+
+impl fmt::Debug for SaveConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("SaveConfig");
+        if let Target::Dir(ref dir) = self.target {
+            f.field("dir", dir);
+        }
+        f.field("file_limit", &self.file_limit)
+            .field("total_limit", &self.total_limit)
+            .finish()
+    }
+}
+
+/// File saved by `Multipart::save_files()`
+#[derive(Debug, Clone)]
+pub struct SavedFile {
+    name: String,
+    file_name: String,
+    original_file_name: String,
+    content_type: mime::Mime,
+    path: Option<PathBuf>,
+    size: u64,
+}
+
+impl SavedFile {
+    /// Name of the field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sanitized file name, it is name of saved file for directory targets
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// File name provided by client, must not be used as path
+    pub fn original_file_name(&self) -> &str {
+        &self.original_file_name
+    }
+
+    /// Content type of the file
+    pub fn content_type(&self) -> &mime::Mime {
+        &self.content_type
+    }
+
+    /// Path of saved file, `None` for files written to sink
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|p| p.as_path())
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Multipart {
+    /// Save file fields of multipart stream and return saved files.
+    ///
+    /// Fields without file name are read and discarded, size of them counts
+    /// to total limit. Files of nested `multipart/mixed` streams are saved
+    /// with name of the field. Files that are saved to directory are removed
+    /// if request fails, i.e. when limit is exceeded.
+    pub async fn save_files(
+        mut self,
+        config: &SaveConfig,
+    ) -> Result<Vec<SavedFile>, MultipartError> {
+        let mut saved = Vec::new();
+        let mut total = 0;
+
+        while let Some(field) = self.next().await {
+            let res = match field {
+                Ok(field) => save_fields(field, config, &mut total, &mut saved).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                remove_files(&saved).await;
+                return Err(e);
+            }
+        }
+        Ok(saved)
+    }
+}
+
+/// Save field, files of nested multipart stream are saved with name of field
+async fn save_fields(
+    field: Field,
+    config: &SaveConfig,
+    total: &mut u64,
+    saved: &mut Vec<SavedFile>,
+) -> Result<(), MultipartError> {
+    let name = field
+        .content_disposition()
+        .as_ref()
+        .and_then(|cd| cd.get_name())
+        .map(|s| s.to_string());
+
+    if !field.is_multipart() {
+        if let Some(file) = save_field(field, name, config, total).await? {
+            saved.push(file);
+        }
+        return Ok(());
+    }
+    let mut nested = field.into_multipart();
+    while let Some(part) = nested.next().await {
+        if let Some(file) = save_field(part?, name.clone(), config, total).await? {
+            saved.push(file);
+        }
+    }
+    Ok(())
+}
+
+/// Save field with `name` if it is a file, other fields are discarded
+async fn save_field(
+    mut field: Field,
+    name: Option<String>,
+    config: &SaveConfig,
+    total: &mut u64,
+) -> Result<Option<SavedFile>, MultipartError> {
+    let original_file_name = field
+        .content_disposition()
+        .as_ref()
+        .and_then(|cd| cd.get_filename())
+        .map(|s| s.to_string());
+    let (name, original_file_name) = match (name, original_file_name) {
+        // browsers send empty file name if no file is selected
+        (Some(name), Some(file_name)) if !file_name.is_empty() => (name, file_name),
+        _ => {
+            while let Some(chunk) = field.next().await {
+                *total += chunk?.len() as u64;
+                if *total > config.total_limit {
+                    return Err(MultipartError::TotalLimit);
+                }
+            }
+            return Ok(None);
+        }
+    };
+    let file_name = sanitize_file_name(&original_file_name);
+    let content_type = field.content_type().clone();
+
+    let (file_name, path, writer) = match config.target {
+        Target::Dir(ref dir) => {
+            let dir = dir.clone();
+            let (file_name, path, file) =
+                web::block(move || create_file(&dir, &file_name))
+                    .await
+                    .map_err(blocking_error)?;
+            let writer: Box<dyn Write + Send> = Box::new(file);
+            (file_name, Some(path), writer)
+        }
+        Target::Sink(ref sink) => {
+            let writer = sink(&name, &file_name, &content_type)?;
+            (file_name, None, writer)
+        }
+    };
+    let mut saved = SavedFile {
+        name,
+        file_name,
+        original_file_name,
+        content_type,
+        path,
+        size: 0,
+    };
+
+    match write_field(&mut field, writer, &mut saved, config, total).await {
+        Ok(()) => Ok(Some(saved)),
+        Err(e) => {
+            remove_files(std::slice::from_ref(&saved)).await;
+            Err(e)
+        }
+    }
+}
+
+/// Write content of field, size of file is updated
+async fn write_field(
+    field: &mut Field,
+    mut writer: Box<dyn Write + Send>,
+    saved: &mut SavedFile,
+    config: &SaveConfig,
+    total: &mut u64,
+) -> Result<(), MultipartError> {
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk?;
+        saved.size += chunk.len() as u64;
+        *total += chunk.len() as u64;
+        if saved.size > config.file_limit {
+            return Err(MultipartError::FieldLimit(saved.name.clone()));
+        } else if *total > config.total_limit {
+            return Err(MultipartError::TotalLimit);
+        }
+        writer = web::block(move || writer.write_all(&chunk).map(|_| writer))
+            .await
+            .map_err(blocking_error)?;
+    }
+    web::block(move || writer.flush())
+        .await
+        .map_err(blocking_error)
+}
+
+/// Create new file in `dir`, number is appended to name of file if file
+/// exists already
+fn create_file(dir: &Path, file_name: &str) -> io::Result<(String, PathBuf, File)> {
+    let (stem, ext) = match file_name.rfind('.') {
+        Some(idx) if idx > 0 => file_name.split_at(idx),
+        _ => (file_name, ""),
+    };
+    for n in 0..1000 {
+        let name = if n == 0 {
+            file_name.to_string()
+        } else {
+            format!("{}-{}{}", stem, n, ext)
+        };
+        let path = dir.join(&name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((name, path, file)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "Can not find unique file name",
+    ))
+}
+
+/// Remove saved files, errors are ignored
+async fn remove_files(files: &[SavedFile]) {
+    let paths: Vec<_> = files.iter().filter_map(|f| f.path.clone()).collect();
+    if !paths.is_empty() {
+        let _ = web::block(move || {
+            for path in paths {
+                let _ = fs::remove_file(path);
+            }
+            Ok::<_, ()>(())
+        })
+        .await;
+    }
+}
+
+/// Make file name provided by client safe to use as name of file.
+///
+/// Directory components, control characters and characters that are not
+/// allowed in file names on common platforms are removed, as well as
+/// leading dots so files can not be hidden. Name is truncated to 255 bytes.
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let file_name = file_name
+        .rsplit(|c: char| c == '/' || c == '\\')
+        .next()
+        .unwrap_or("");
+    let mut name: String = file_name
+        .chars()
+        .filter(|c| !c.is_control())
+        .filter(|c| match *c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => false,
+            _ => true,
+        })
+        .collect();
+    name = name
+        .trim_start_matches(|c: char| c == '.' || c.is_whitespace())
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+
+    if name.len() > 255 {
+        let mut idx = 255;
+        while !name.is_char_boundary(idx) {
+            idx -= 1;
+        }
+        name.truncate(idx);
+    }
+    if name.is_empty() {
+        name.push_str("file");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use actix_web::http::header::{self, HeaderMap};
+    use bytes::Bytes;
+    use futures::stream;
+
+    use super::*;
+
+    fn multipart() -> Multipart {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static(
+                "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+            ),
+        );
+        let body = Bytes::from_static(
+            b"--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+              holiday\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"image\"; filename=\"../../a.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              first\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"image\"; filename=\"a.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\n\
+              second\r\n\
+              --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n",
+        );
+        Multipart::new(&headers, stream::once(async { Ok(body) }).boxed_local())
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "actix-multipart-save-{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        for (file_name, sanitized) in vec![
+            ("a.txt", "a.txt"),
+            ("../../etc/passwd", "passwd"),
+            ("C:\\Users\\a\\b.png", "b.png"),
+            (".hidden", "hidden"),
+            ("a<b>:c?.txt ", "abc.txt"),
+            ("a\0b\n.txt", "ab.txt"),
+            ("..", "file"),
+            ("", "file"),
+        ] {
+            assert_eq!(sanitize_file_name(file_name), sanitized, "{}", file_name);
+        }
+        assert_eq!(sanitize_file_name(&"é".repeat(200)).len(), 254);
+    }
+
+    #[actix_rt::test]
+    async fn test_save_files() {
+        let dir = temp_dir();
+        let files = multipart()
+            .save_files(&SaveConfig::default().dir(&dir))
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name(), "image");
+        assert_eq!(files[0].original_file_name(), "../../a.txt");
+        assert_eq!(files[0].file_name(), "a.txt");
+        assert_eq!(files[0].content_type(), &mime::TEXT_PLAIN);
+        assert_eq!(files[0].size(), 5);
+        assert_eq!(files[1].file_name(), "a-1.txt");
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "first");
+        assert_eq!(files[1].path().unwrap(), dir.join("a-1.txt").as_path());
+        assert_eq!(fs::read_to_string(dir.join("a-1.txt")).unwrap(), "second");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_save_files_limits() {
+        let dir = temp_dir();
+        let err = multipart()
+            .save_files(&SaveConfig::default().dir(&dir).file_limit(5))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart field image exceeds size limit");
+        // files saved before error are removed
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let err = multipart()
+            .save_files(&SaveConfig::default().dir(&dir).total_limit(10))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Multipart payload exceeds size limit");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_save_files_sink() {
+        let files = multipart()
+            .save_files(&SaveConfig::default().sink(|_, _, _| Ok(Box::new(io::sink()))))
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].path().is_none());
+        assert_eq!(files[1].size(), 6);
+    }
+}
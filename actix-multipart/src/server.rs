@@ -24,10 +24,9 @@ const MAX_HEADERS: usize = 32;
 
 /// The server-side implementation of `multipart/form-data` requests.
 ///
-/// This will parse the incoming stream into `MultipartItem` instances via its
-/// Stream implementation.
-/// `MultipartItem::Field` contains multipart field. `MultipartItem::Multipart`
-/// is used for nested multipart streams.
+/// This will parse the incoming stream into `Field` instances via its
+/// Stream implementation. Nested multipart streams, i.e. `multipart/mixed`
+/// fields with multiple files, are parsed with `Field::into_multipart()`.
 pub struct Multipart {
     safety: Safety,
     error: Option<MultipartError>,
@@ -56,6 +55,7 @@ struct InnerMultipart {
     boundary: String,
     state: InnerState,
     item: InnerMultipartItem,
+    nested: bool,
 }
 
 impl Multipart {
@@ -64,7 +64,19 @@ impl Multipart {
     where
         S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
     {
-        match Self::boundary(headers) {
+        Self::from_stream(Self::boundary(headers), stream, false)
+    }
+
+    fn from_stream<S, E>(
+        boundary: Result<String, MultipartError>,
+        stream: S,
+        nested: bool,
+    ) -> Multipart
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Into<MultipartError>,
+    {
+        match boundary {
             Ok(boundary) => Multipart {
                 error: None,
                 safety: Safety::new(),
@@ -73,6 +85,7 @@ impl Multipart {
                     payload: PayloadRef::new(PayloadBuffer::new(Box::new(stream))),
                     state: InnerState::FirstBoundary,
                     item: InnerMultipartItem::None,
+                    nested,
                 }))),
             },
             Err(err) => Multipart {
@@ -345,8 +358,9 @@ impl InnerMultipart {
 
             self.state = InnerState::Boundary;
 
-            // nested multipart stream
-            if mt.type_() == mime::MULTIPART {
+            // multipart streams are nested only one level deep, i.e.
+            // `multipart/mixed` field of `multipart/form-data` stream
+            if self.nested && mt.type_() == mime::MULTIPART {
                 Poll::Ready(Some(Err(MultipartError::Nested)))
             } else {
                 let field = Rc::new(RefCell::new(InnerField::new(
@@ -402,6 +416,27 @@ impl Field {
         &self.ct
     }
 
+    /// Returns true if field is nested multipart stream, i.e.
+    /// `multipart/mixed` field with multiple files.
+    pub fn is_multipart(&self) -> bool {
+        self.ct.type_() == mime::MULTIPART
+    }
+
+    /// Convert field into nested multipart stream.
+    ///
+    /// RFC 7578 allows multiple files of a field to be sent as
+    /// `multipart/mixed` stream, parts of the stream are fields with
+    /// `file` content disposition. Stream yields error if field is not
+    /// multipart stream or if it is part of nested stream.
+    pub fn into_multipart(self) -> Multipart {
+        let boundary = if self.is_multipart() {
+            Multipart::boundary(&self.headers)
+        } else {
+            Err(MultipartError::ParseContentType)
+        };
+        Multipart::from_stream(boundary, self, true)
+    }
+
     /// Get the content disposition of the field, if it exists
     pub fn content_disposition(&self) -> Option<ContentDisposition> {
         // RFC 7578: 'Each part MUST contain a Content-Disposition header field
@@ -421,8 +456,11 @@ impl Stream for Field {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         if self.safety.current() {
             let mut inner = self.inner.borrow_mut();
-            if let Some(mut payload) =
-                inner.payload.as_ref().unwrap().get_mut(&self.safety)
+            // payload is released once field is read completely
+            if let Some(mut payload) = inner
+                .payload
+                .as_ref()
+                .and_then(|payload| payload.get_mut(&self.safety))
             {
                 payload.poll_stream(cx)?;
             }
@@ -529,8 +567,8 @@ impl InnerField {
             };
         }
 
-        // check boundary
-        if len > 4 && payload.buf[0] == b'\r' {
+        // check boundary, buffer may contain only delimiter prefix
+        if len >= 4 && payload.buf[0] == b'\r' {
             let b_len = if &payload.buf[..2] == b"\r\n" && &payload.buf[2..4] == b"--" {
                 Some(4)
             } else if &payload.buf[1..3] == b"--" {
@@ -720,23 +758,27 @@ impl Drop for Safety {
 struct PayloadBuffer {
     eof: bool,
     buf: BytesMut,
-    stream: LocalBoxStream<'static, Result<Bytes, PayloadError>>,
+    stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
 }
 
 impl PayloadBuffer {
     /// Create new `PayloadBuffer` instance
-    fn new<S>(stream: S) -> Self
+    fn new<S, E>(stream: S) -> Self
     where
-        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<MultipartError>,
     {
         PayloadBuffer {
             eof: false,
             buf: BytesMut::new(),
-            stream: stream.boxed_local(),
+            stream: stream.map(|res| res.map_err(Into::into)).boxed_local(),
         }
     }
 
-    fn poll_stream(&mut self, cx: &mut Context) -> Result<(), PayloadError> {
+    fn poll_stream(&mut self, cx: &mut Context) -> Result<(), MultipartError> {
+        if self.eof {
+            return Ok(());
+        }
         loop {
             match Pin::new(&mut self.stream).poll_next(cx) {
                 Poll::Ready(Some(Ok(data))) => self.buf.extend_from_slice(&data),
@@ -1056,6 +1098,72 @@ mod tests {
         }
     }
 
+    #[actix_rt::test]
+    async fn test_nested() {
+        let bytes = Bytes::from(
+            "--AaB03x\r\n\
+             Content-Disposition: form-data; name=\"files\"\r\n\
+             Content-Type: multipart/mixed; boundary=BbC04y\r\n\r\n\
+             --BbC04y\r\n\
+             Content-Disposition: file; filename=\"file1.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             first\r\n\
+             --BbC04y\r\n\
+             Content-Disposition: file; filename=\"file2.gif\"\r\n\
+             Content-Type: image/gif\r\n\r\n\
+             second\r\n\
+             --BbC04y--\r\n\
+             --AaB03x\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+             holiday\r\n\
+             --AaB03x--\r\n",
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("multipart/form-data; boundary=AaB03x"),
+        );
+
+        let mut multipart = Multipart::new(&headers, SlowStream::new(bytes));
+        let field = multipart.next().await.unwrap().unwrap();
+        assert!(field.is_multipart());
+
+        let mut nested = field.into_multipart();
+        let mut files = Vec::new();
+        while let Some(part) = nested.next().await {
+            let mut part = part.unwrap();
+            assert!(!part.is_multipart());
+            let cd = part.content_disposition().unwrap();
+            files.push((
+                cd.get_filename().unwrap().to_string(),
+                get_whole_field(&mut part).await,
+            ));
+        }
+        assert_eq!(
+            files,
+            vec![
+                ("file1.txt".to_string(), BytesMut::from("first")),
+                ("file2.gif".to_string(), BytesMut::from("second")),
+            ]
+        );
+        drop(nested);
+
+        let mut field = multipart.next().await.unwrap().unwrap();
+        assert!(!field.is_multipart());
+        assert_eq!(get_whole_field(&mut field).await, "holiday");
+        drop(field);
+        assert!(multipart.next().await.is_none());
+
+        // text field is not multipart stream
+        let (bytes, headers) = create_simple_request_with_header();
+        let mut multipart = Multipart::new(&headers, SlowStream::new(bytes));
+        let field = multipart.next().await.unwrap().unwrap();
+        match field.into_multipart().next().await {
+            Some(Err(MultipartError::ParseContentType)) => (),
+            _ => unreachable!(),
+        }
+    }
+
     #[actix_rt::test]
     async fn test_basic() {
         let (_, payload) = Payload::create(false);
@@ -13,7 +13,8 @@
 //! cookie may neither be viewed nor modified by the client.
 //!
 //! The constructors take a key as an argument. This is the private key
-//! for cookie session - when this value is changed, all session data is lost.
+//! for cookie session - when this value is changed, all session data is lost,
+//! unless previous key is registered with `CookieSession::previous_key()`.
 
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -28,6 +29,7 @@ use derive_more::{Display, From};
 use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
 use serde_json::error::Error as JsonError;
 
+use crate::expiry::{self, Expiry};
 use crate::{Session, SessionStatus};
 
 /// Errors that can occur during handling cookie session
@@ -50,6 +52,7 @@ enum CookieSecurity {
 
 struct CookieSessionInner {
     key: Key,
+    previous_keys: Vec<Key>,
     security: CookieSecurity,
     name: String,
     path: String,
@@ -58,6 +61,7 @@ struct CookieSessionInner {
     http_only: bool,
     max_age: Option<time::Duration>,
     same_site: Option<SameSite>,
+    expiry: Expiry,
 }
 
 impl CookieSessionInner {
@@ -65,6 +69,7 @@ impl CookieSessionInner {
         CookieSessionInner {
             security,
             key: Key::from_master(key),
+            previous_keys: Vec::new(),
             name: "actix-session".to_owned(),
             path: "/".to_owned(),
             domain: None,
@@ -72,6 +77,7 @@ impl CookieSessionInner {
             http_only: true,
             max_age: None,
             same_site: None,
+            expiry: Expiry::default(),
         }
     }
 
@@ -79,8 +85,11 @@ impl CookieSessionInner {
         &self,
         res: &mut ServiceResponse<B>,
         state: impl Iterator<Item = (String, String)>,
+        created: u64,
+        now: u64,
     ) -> Result<(), Error> {
-        let state: HashMap<String, String> = state.collect();
+        let mut state: HashMap<String, String> = state.collect();
+        self.expiry.stamp(&mut state, created, now);
         let value =
             serde_json::to_string(&state).map_err(CookieSessionError::Serialize)?;
         if value.len() > 4064 {
@@ -132,28 +141,30 @@ impl CookieSessionInner {
         Ok(())
     }
 
-    fn load(&self, req: &ServiceRequest) -> (bool, HashMap<String, String>) {
-        if let Ok(cookies) = req.cookies() {
-            for cookie in cookies.iter() {
-                if cookie.name() == self.name {
-                    let mut jar = CookieJar::new();
-                    jar.add_original(cookie.clone());
-
+    /// Load session state from the cookie, returned flag is set if the cookie
+    /// is protected with one of previous keys and has to be reissued.
+    fn load(&self, req: &ServiceRequest) -> Option<(HashMap<String, String>, bool)> {
+        let cookies = req.cookies().ok()?;
+        for cookie in cookies.iter() {
+            if cookie.name() == self.name {
+                let mut jar = CookieJar::new();
+                jar.add_original(cookie.clone());
+
+                let keys = Some(&self.key).into_iter().chain(&self.previous_keys);
+                for (idx, key) in keys.enumerate() {
                     let cookie_opt = match self.security {
-                        CookieSecurity::Signed => jar.signed(&self.key).get(&self.name),
-                        CookieSecurity::Private => {
-                            jar.private(&self.key).get(&self.name)
-                        }
+                        CookieSecurity::Signed => jar.signed(key).get(&self.name),
+                        CookieSecurity::Private => jar.private(key).get(&self.name),
                     };
                     if let Some(cookie) = cookie_opt {
                         if let Ok(val) = serde_json::from_str(cookie.value()) {
-                            return (false, val);
+                            return Some((val, idx > 0));
                         }
                     }
                 }
             }
         }
-        (true, HashMap::new())
+        None
     }
 }
 
@@ -179,6 +190,10 @@ impl CookieSessionInner {
 /// all session data is lost. The constructors will panic if the key is less
 /// than 32 bytes in length.
 ///
+/// Keys could be rotated without invalidating live sessions, by registering
+/// former keys with `previous_key()`. Cookies protected with previous key
+/// are accepted and reissued with the current key.
+///
 /// The backend relies on `cookie` crate to create and read cookies.
 /// By default all cookies are percent encoded, but certain symbols may
 /// cause troubles when reading cookie, if they are not properly percent encoded.
@@ -222,6 +237,18 @@ impl CookieSession {
         )))
     }
 
+    /// Accept cookies protected with previous key, used for key rotation.
+    ///
+    /// Could be called multiple times, keys are tried in registration order
+    /// after the current key. Panics if key length is less than 32 bytes.
+    pub fn previous_key(mut self, key: &[u8]) -> CookieSession {
+        Rc::get_mut(&mut self.0)
+            .unwrap()
+            .previous_keys
+            .push(Key::from_master(key));
+        self
+    }
+
     /// Sets the `path` field in the session cookie being built.
     pub fn path<S: Into<String>>(mut self, value: S) -> CookieSession {
         Rc::get_mut(&mut self.0).unwrap().path = value.into();
@@ -271,6 +298,24 @@ impl CookieSession {
         Rc::get_mut(&mut self.0).unwrap().max_age = Some(value);
         self
     }
+
+    /// Sets absolute lifetime of session, in seconds.
+    ///
+    /// Session expires after specified time since creation, regardless of
+    /// activity. Renewed session is considered as new one.
+    pub fn lifetime(mut self, seconds: u64) -> CookieSession {
+        Rc::get_mut(&mut self.0).unwrap().expiry.lifetime = Some(seconds);
+        self
+    }
+
+    /// Sets idle timeout of session, in seconds.
+    ///
+    /// Session expires if it is not accessed for specified time, session
+    /// cookie is reissued on each request.
+    pub fn idle_timeout(mut self, seconds: u64) -> CookieSession {
+        Rc::get_mut(&mut self.0).unwrap().expiry.idle_timeout = Some(seconds);
+        self
+    }
 }
 
 impl<S, B: 'static> Transform<S> for CookieSession
@@ -322,7 +367,15 @@ where
     /// and this will trigger removal of the session cookie in the response.
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
         let inner = self.inner.clone();
-        let (is_new, state) = self.inner.load(&req);
+        let now = expiry::now();
+        let loaded = self.inner.load(&req).and_then(|(mut state, outdated)| {
+            let created = inner.expiry.validate(&mut state, now)?;
+            Some((state, outdated, created))
+        });
+        let (is_new, outdated, created, state) = match loaded {
+            Some((state, outdated, created)) => (false, outdated, created, state),
+            None => (true, false, now, HashMap::new()),
+        };
         Session::set_session(state.into_iter(), &mut req);
 
         let fut = self.service.call(req);
@@ -330,18 +383,28 @@ where
         async move {
             fut.await.map(|mut res| {
                 match Session::get_changes(&mut res) {
-                    (SessionStatus::Changed, Some(state))
-                    | (SessionStatus::Renewed, Some(state)) => {
-                        res.checked_expr(|res| inner.set_cookie(res, state))
+                    (SessionStatus::Changed, Some(state)) => res
+                        .checked_expr(|res| inner.set_cookie(res, state, created, now)),
+                    (SessionStatus::Renewed, Some(state)) => {
+                        res.checked_expr(|res| inner.set_cookie(res, state, now, now))
                     }
-                    (SessionStatus::Unchanged, _) =>
+                    (SessionStatus::Unchanged, state) =>
                     // set a new session cookie upon first request (new client)
                     {
                         if is_new {
                             let state: HashMap<String, String> = HashMap::new();
                             res.checked_expr(|res| {
-                                inner.set_cookie(res, state.into_iter())
+                                inner.set_cookie(res, state.into_iter(), now, now)
                             })
+                        } else if outdated || inner.expiry.idle_timeout.is_some() {
+                            // reissue cookie with current key, or refresh
+                            // time of last access
+                            match state {
+                                Some(state) => res.checked_expr(|res| {
+                                    inner.set_cookie(res, state, created, now)
+                                }),
+                                None => res,
+                            }
                         } else {
                             res
                         }
@@ -477,4 +540,121 @@ mod tests {
         let body = test::read_response(&mut app, request).await;
         assert_eq!(body, Bytes::from_static(b"counter: 100"));
     }
+
+    #[actix_rt::test]
+    async fn key_rotation() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::private(&[1; 32]).secure(false))
+                .service(web::resource("/").to(|ses: Session| {
+                    async move {
+                        let _ = ses.set("counter", 100);
+                        "test"
+                    }
+                })),
+        )
+        .await;
+
+        let request = test::TestRequest::get().to_request();
+        let response = app.call(request).await.unwrap();
+        let cookie = response
+            .response()
+            .cookies()
+            .find(|c| c.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let mut app = test::init_service(
+            App::new()
+                .wrap(
+                    CookieSession::private(&[2; 32])
+                        .previous_key(&[1; 32])
+                        .secure(false),
+                )
+                .service(web::resource("/").to(|ses: Session| {
+                    async move {
+                        let val: usize = ses.get("counter").unwrap().unwrap();
+                        format!("counter: {}", val)
+                    }
+                })),
+        )
+        .await;
+
+        // cookie is reissued with current key
+        let request = test::TestRequest::get().cookie(cookie).to_request();
+        let response = app.call(request).await.unwrap();
+        let cookie = response
+            .response()
+            .cookies()
+            .find(|c| c.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+        let body = test::read_body(response).await;
+        assert_eq!(body, Bytes::from_static(b"counter: 100"));
+
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::private(&[2; 32]).secure(false))
+                .service(web::resource("/").to(|ses: Session| {
+                    async move {
+                        let val: usize = ses.get("counter").unwrap().unwrap();
+                        format!("counter: {}", val)
+                    }
+                })),
+        )
+        .await;
+
+        let request = test::TestRequest::get().cookie(cookie).to_request();
+        let body = test::read_response(&mut app, request).await;
+        assert_eq!(body, Bytes::from_static(b"counter: 100"));
+    }
+
+    #[actix_rt::test]
+    async fn idle_timeout() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(
+                    CookieSession::signed(&[0; 32])
+                        .secure(false)
+                        .lifetime(3600)
+                        .idle_timeout(600),
+                )
+                .service(web::resource("/").to(|ses: Session| {
+                    async move {
+                        let _ = ses.set("counter", 100);
+                        "test"
+                    }
+                }))
+                .service(web::resource("/test/").to(|ses: Session| {
+                    async move {
+                        let val: usize = ses.get("counter").unwrap().unwrap();
+                        assert!(ses.get::<u64>("$created").unwrap().is_none());
+                        format!("counter: {}", val)
+                    }
+                })),
+        )
+        .await;
+
+        let request = test::TestRequest::get().to_request();
+        let response = app.call(request).await.unwrap();
+        let cookie = response
+            .response()
+            .cookies()
+            .find(|c| c.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        // unchanged session cookie is reissued to refresh time of last access
+        let request = test::TestRequest::with_uri("/test/")
+            .cookie(cookie)
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert!(response
+            .response()
+            .cookies()
+            .find(|c| c.name() == "actix-session")
+            .is_some());
+        let body = test::read_body(response).await;
+        assert_eq!(body, Bytes::from_static(b"counter: 100"));
+    }
 }
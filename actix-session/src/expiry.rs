@@ -0,0 +1,137 @@
+//! Session expiration.
+//!
+//! Timestamps of session creation and last access are stored in session
+//! state under reserved keys, so client could not extend session by
+//! keeping the cookie. Timestamps are removed from state on load, they are
+//! not visible through `Session` api.
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reserved key of session creation time
+const CREATED: &str = "$created";
+
+/// Reserved key of session last access time
+const ACCESSED: &str = "$accessed";
+
+/// Expiration policy of session backend, durations are in seconds.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Expiry {
+    /// Session expires after this time since creation
+    pub(crate) lifetime: Option<u64>,
+    /// Session expires after this time since last access
+    pub(crate) idle_timeout: Option<u64>,
+}
+
+impl Expiry {
+    /// Remove timestamps from loaded session state, returns time of
+    /// session creation or `None` if session is expired.
+    ///
+    /// Sessions without timestamps, e.g. created before policy has been
+    /// configured, are considered as created now.
+    pub(crate) fn validate(
+        &self,
+        state: &mut HashMap<String, String>,
+        now: u64,
+    ) -> Option<u64> {
+        let created = take_timestamp(state, CREATED).unwrap_or(now);
+        let accessed = take_timestamp(state, ACCESSED).unwrap_or(now);
+
+        if self
+            .lifetime
+            .map_or(false, |secs| now >= created.saturating_add(secs))
+            || self
+                .idle_timeout
+                .map_or(false, |secs| now >= accessed.saturating_add(secs))
+        {
+            None
+        } else {
+            Some(created)
+        }
+    }
+
+    /// Add timestamps to session state before storing it
+    pub(crate) fn stamp(
+        &self,
+        state: &mut HashMap<String, String>,
+        created: u64,
+        now: u64,
+    ) {
+        if self.lifetime.is_some() || self.idle_timeout.is_some() {
+            state.insert(CREATED.to_owned(), created.to_string());
+            state.insert(ACCESSED.to_owned(), now.to_string());
+        }
+    }
+
+    /// Seconds until session expires, if policy is configured
+    pub(crate) fn remaining(&self, created: u64, now: u64) -> Option<u64> {
+        let lifetime = self
+            .lifetime
+            .map(|secs| created.saturating_add(secs).saturating_sub(now));
+        match (lifetime, self.idle_timeout) {
+            (Some(lifetime), Some(idle)) => Some(lifetime.min(idle)),
+            (lifetime, idle) => lifetime.or(idle),
+        }
+    }
+}
+
+fn take_timestamp(state: &mut HashMap<String, String>, key: &str) -> Option<u64> {
+    state.remove(key).and_then(|val| val.parse().ok())
+}
+
+/// Current unix time, in seconds
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifetime() {
+        let expiry = Expiry {
+            lifetime: Some(100),
+            idle_timeout: None,
+        };
+        let mut state = HashMap::new();
+        expiry.stamp(&mut state, 1000, 1050);
+        assert_eq!(state.len(), 2);
+        assert_eq!(expiry.validate(&mut state.clone(), 1099), Some(1000));
+        assert_eq!(expiry.validate(&mut state, 1100), None);
+        // timestamps are removed
+        assert!(state.is_empty());
+
+        assert_eq!(expiry.remaining(1000, 1050), Some(50));
+        assert_eq!(expiry.remaining(1000, 1200), Some(0));
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let expiry = Expiry {
+            lifetime: Some(100),
+            idle_timeout: Some(10),
+        };
+        let mut state = HashMap::new();
+        state.insert("counter".to_owned(), "1".to_owned());
+        expiry.stamp(&mut state, 1000, 1050);
+        assert_eq!(expiry.validate(&mut state.clone(), 1059), Some(1000));
+        assert_eq!(expiry.validate(&mut state, 1060), None);
+        assert_eq!(state.len(), 1);
+
+        assert_eq!(expiry.remaining(1000, 1050), Some(10));
+        assert_eq!(expiry.remaining(1000, 1095), Some(5));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let expiry = Expiry::default();
+        let mut state = HashMap::new();
+        expiry.stamp(&mut state, 1000, 1050);
+        assert!(state.is_empty());
+        assert_eq!(expiry.validate(&mut state, 5000), Some(5000));
+        assert_eq!(expiry.remaining(1000, 1050), None);
+    }
+}
@@ -4,7 +4,8 @@
 //! middlewares could provide different implementations which could
 //! be accessed via general session api.
 //!
-//! By default, only cookie session backend is implemented. Other
+//! By default, only cookie session backend is implemented. Redis
+//! session backend is available with `redis-session` feature. Other
 //! backend implementations can be added.
 //!
 //! In general, you insert a *session* middleware and initialize it
@@ -56,8 +57,16 @@ use serde_json;
 
 #[cfg(feature = "cookie-session")]
 mod cookie;
+#[cfg(any(feature = "cookie-session", feature = "redis-session"))]
+mod expiry;
 #[cfg(feature = "cookie-session")]
 pub use crate::cookie::CookieSession;
+#[cfg(feature = "redis-session")]
+mod redis_session;
+#[cfg(feature = "redis-session")]
+pub use crate::redis_session::{
+    JsonSerializer, RedisSession, RedisSessionError, SessionSerializer,
+};
 
 /// The high-level interface you use to modify session data.
 ///
@@ -0,0 +1,525 @@
+//! Redis session.
+//!
+//! [**RedisSession**](struct.RedisSession.html) stores session state in
+//! redis, the cookie contains only signed session id. Session state is not
+//! limited in size, survives server restarts and is shared between all
+//! server instances that use the same redis server.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::cookie::{Cookie, CookieJar, Key, SameSite};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header::SET_COOKIE, HeaderValue};
+use actix_web::{Error, HttpMessage, ResponseError};
+use derive_more::{Display, From};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use rand::{distributions::Alphanumeric, Rng};
+use redis::aio::MultiplexedConnection;
+use redis::{Client, Cmd, FromRedisValue, RedisError};
+use serde_json::error::Error as JsonError;
+
+use crate::expiry::{self, Expiry};
+use crate::{Session, SessionStatus};
+
+/// Errors that can occur during handling redis session
+#[derive(Debug, From, Display)]
+pub enum RedisSessionError {
+    /// Redis command failed.
+    #[display(fmt = "Redis error: {}", _0)]
+    Redis(RedisError),
+    /// Fail to serialize session.
+    #[display(fmt = "Fail to serialize session")]
+    Serialize(JsonError),
+}
+
+impl ResponseError for RedisSessionError {}
+
+/// Serialization format of session state stored in redis.
+pub trait SessionSerializer {
+    /// Serialize session state.
+    fn serialize(&self, state: &HashMap<String, String>) -> Result<Vec<u8>, Error>;
+
+    /// Deserialize session state.
+    fn deserialize(&self, data: &[u8]) -> Result<HashMap<String, String>, Error>;
+}
+
+/// Stores session state as json object, default serializer of
+/// `RedisSession`.
+pub struct JsonSerializer;
+
+impl SessionSerializer for JsonSerializer {
+    fn serialize(&self, state: &HashMap<String, String>) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(state).map_err(RedisSessionError::Serialize)?)
+    }
+
+    fn deserialize(&self, data: &[u8]) -> Result<HashMap<String, String>, Error> {
+        Ok(serde_json::from_slice(data).map_err(RedisSessionError::Serialize)?)
+    }
+}
+
+struct RedisSessionInner {
+    client: Client,
+    conn: RefCell<Option<MultiplexedConnection>>,
+    key: Key,
+    prefix: String,
+    ttl: u32,
+    serializer: Box<dyn SessionSerializer>,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    max_age: Option<time::Duration>,
+    same_site: Option<SameSite>,
+    expiry: Expiry,
+}
+
+impl RedisSessionInner {
+    fn new(addr: &str, key: &[u8]) -> RedisSessionInner {
+        RedisSessionInner {
+            client: Client::open(addr).expect("Invalid redis address"),
+            conn: RefCell::new(None),
+            key: Key::from_master(key),
+            prefix: "session:".to_owned(),
+            ttl: 7200,
+            serializer: Box::new(JsonSerializer),
+            name: "actix-session".to_owned(),
+            path: "/".to_owned(),
+            domain: None,
+            secure: true,
+            http_only: true,
+            max_age: None,
+            same_site: None,
+            expiry: Expiry::default(),
+        }
+    }
+
+    /// Session id from signed cookie
+    fn session_id(&self, req: &ServiceRequest) -> Option<String> {
+        let cookies = req.cookies().ok()?;
+        for cookie in cookies.iter() {
+            if cookie.name() == self.name {
+                let mut jar = CookieJar::new();
+                jar.add_original(cookie.clone());
+                if let Some(cookie) = jar.signed(&self.key).get(&self.name) {
+                    return Some(cookie.value().to_owned());
+                }
+            }
+        }
+        None
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, RedisSessionError> {
+        if let Some(ref conn) = *self.conn.borrow() {
+            return Ok(conn.clone());
+        }
+        let conn = self.client.get_multiplexed_tokio_connection().await?;
+        *self.conn.borrow_mut() = Some(conn.clone());
+        Ok(conn)
+    }
+
+    async fn query<T: FromRedisValue>(&self, cmd: Cmd) -> Result<T, RedisSessionError> {
+        let mut conn = self.connection().await?;
+        match cmd.query_async(&mut conn).await {
+            Ok(val) => Ok(val),
+            Err(e) => {
+                // reconnect on next request
+                self.conn.borrow_mut().take();
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn load(&self, id: &str) -> Result<Option<HashMap<String, String>>, Error> {
+        let mut cmd = redis::cmd("GET");
+        cmd.arg(format!("{}{}", self.prefix, id));
+
+        let data: Option<Vec<u8>> = self.query(cmd).await?;
+        Ok(data.and_then(|data| self.serializer.deserialize(&data).ok()))
+    }
+
+    async fn save(
+        &self,
+        id: &str,
+        mut state: HashMap<String, String>,
+        created: u64,
+        now: u64,
+    ) -> Result<(), Error> {
+        self.expiry.stamp(&mut state, created, now);
+        let ttl = self
+            .expiry
+            .remaining(created, now)
+            .unwrap_or_else(|| u64::from(self.ttl));
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(format!("{}{}", self.prefix, id))
+            .arg(self.serializer.serialize(&state)?)
+            .arg("EX")
+            .arg(ttl.max(1));
+
+        Ok(self.query(cmd).await?)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), Error> {
+        let mut cmd = redis::cmd("DEL");
+        cmd.arg(format!("{}{}", self.prefix, id));
+
+        Ok(self.query(cmd).await?)
+    }
+
+    async fn update<B>(
+        &self,
+        res: &mut ServiceResponse<B>,
+        id: Option<String>,
+        created: u64,
+        now: u64,
+    ) -> Result<(), Error> {
+        match Session::get_changes(res) {
+            (SessionStatus::Changed, Some(state)) => {
+                let id = id.unwrap_or_else(generate_id);
+                self.save(&id, state.collect(), created, now).await?;
+                self.set_cookie(res, &id)
+            }
+            (SessionStatus::Renewed, Some(state)) => {
+                if let Some(id) = id {
+                    self.delete(&id).await?;
+                }
+                let id = generate_id();
+                self.save(&id, state.collect(), now, now).await?;
+                self.set_cookie(res, &id)
+            }
+            (SessionStatus::Unchanged, Some(state)) => match id {
+                // refresh time of last access
+                Some(ref id) if self.expiry.idle_timeout.is_some() => {
+                    self.save(id, state.collect(), created, now).await
+                }
+                _ => Ok(()),
+            },
+            (SessionStatus::Purged, _) => {
+                if let Some(id) = id {
+                    self.delete(&id).await?;
+                }
+                self.remove_cookie(res)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn set_cookie<B>(
+        &self,
+        res: &mut ServiceResponse<B>,
+        id: &str,
+    ) -> Result<(), Error> {
+        let mut cookie = Cookie::new(self.name.clone(), id.to_owned());
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+
+        let mut jar = CookieJar::new();
+        jar.signed(&self.key).add(cookie);
+
+        for cookie in jar.delta() {
+            let val = HeaderValue::from_str(&cookie.encoded().to_string())?;
+            res.headers_mut().append(SET_COOKIE, val);
+        }
+
+        Ok(())
+    }
+
+    /// invalidates session cookie
+    fn remove_cookie<B>(&self, res: &mut ServiceResponse<B>) -> Result<(), Error> {
+        let mut cookie = Cookie::named(self.name.clone());
+        cookie.set_path(self.path.clone());
+        cookie.set_value("");
+        cookie.set_max_age(time::Duration::seconds(0));
+        cookie.set_expires(time::now() - time::Duration::days(365));
+
+        let val = HeaderValue::from_str(&cookie.to_string())?;
+        res.headers_mut().append(SET_COOKIE, val);
+
+        Ok(())
+    }
+}
+
+/// Generate random session id
+fn generate_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .collect()
+}
+
+/// Use redis for session storage.
+///
+/// Session state is stored in redis under random session id, the id is
+/// sent to the client in a *signed* cookie. New session id is assigned
+/// when session is created or renewed, and state of purged session is
+/// deleted from redis. Session expires in redis after `ttl` seconds since
+/// last change, or according to `lifetime` and `idle_timeout` if set.
+///
+/// Connection to redis is opened on first request. `RedisSession` should be
+/// created in application factory, each worker uses own connection.
+///
+/// The constructor takes a key as an argument. This is the private key
+/// for session cookie signing - when this value is changed, all sessions
+/// are lost. The constructor will panic if the key is less than 32 bytes
+/// in length, or if redis address is invalid.
+///
+/// # Example
+///
+/// ```rust
+/// use actix_session::RedisSession;
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+///
+/// fn main() {
+///     let app = App::new().wrap(
+///         RedisSession::new("redis://127.0.0.1:6379", &[0; 32])
+///             .ttl(3600)
+///             .name("actix_session")
+///             .secure(true))
+///         .service(web::resource("/").to(|| HttpResponse::Ok()));
+/// }
+/// ```
+pub struct RedisSession(Rc<RedisSessionInner>);
+
+impl RedisSession {
+    /// Construct new `RedisSession` instance, `addr` is redis connection
+    /// url, e.g. `redis://127.0.0.1:6379/0`.
+    ///
+    /// Panics if key length is less than 32 bytes.
+    pub fn new(addr: &str, key: &[u8]) -> RedisSession {
+        RedisSession(Rc::new(RedisSessionInner::new(addr, key)))
+    }
+
+    /// Sets time to live of session state in redis, in seconds.
+    /// By default is 7200 seconds.
+    pub fn ttl(mut self, seconds: u32) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().ttl = seconds;
+        self
+    }
+
+    /// Sets prefix of redis keys. By default is `session:`.
+    pub fn prefix<S: Into<String>>(mut self, value: S) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().prefix = value.into();
+        self
+    }
+
+    /// Sets serialization format of session state. By default state is
+    /// stored as json object.
+    pub fn serializer<T: SessionSerializer + 'static>(
+        mut self,
+        value: T,
+    ) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().serializer = Box::new(value);
+        self
+    }
+
+    /// Sets absolute lifetime of session, in seconds.
+    ///
+    /// Session expires after specified time since creation, regardless of
+    /// activity. Renewed session is considered as new one.
+    pub fn lifetime(mut self, seconds: u64) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().expiry.lifetime = Some(seconds);
+        self
+    }
+
+    /// Sets idle timeout of session, in seconds.
+    ///
+    /// Session expires if it is not accessed for specified time, each
+    /// request refreshes session in redis. Overrides `ttl`.
+    pub fn idle_timeout(mut self, seconds: u64) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().expiry.idle_timeout = Some(seconds);
+        self
+    }
+
+    /// Sets the `path` field in the session cookie being built.
+    pub fn path<S: Into<String>>(mut self, value: S) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().path = value.into();
+        self
+    }
+
+    /// Sets the `name` field in the session cookie being built.
+    pub fn name<S: Into<String>>(mut self, value: S) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().name = value.into();
+        self
+    }
+
+    /// Sets the `domain` field in the session cookie being built.
+    pub fn domain<S: Into<String>>(mut self, value: S) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().domain = Some(value.into());
+        self
+    }
+
+    /// Sets the `secure` field in the session cookie being built.
+    ///
+    /// If the `secure` field is set, a cookie will only be transmitted when the
+    /// connection is secure - i.e. `https`
+    pub fn secure(mut self, value: bool) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().secure = value;
+        self
+    }
+
+    /// Sets the `http_only` field in the session cookie being built.
+    pub fn http_only(mut self, value: bool) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().http_only = value;
+        self
+    }
+
+    /// Sets the `same_site` field in the session cookie being built.
+    pub fn same_site(mut self, value: SameSite) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().same_site = Some(value);
+        self
+    }
+
+    /// Sets the `max-age` field in the session cookie being built.
+    pub fn max_age(self, seconds: i64) -> RedisSession {
+        self.max_age_time(time::Duration::seconds(seconds))
+    }
+
+    /// Sets the `max-age` field in the session cookie being built.
+    pub fn max_age_time(mut self, value: time::Duration) -> RedisSession {
+        Rc::get_mut(&mut self.0).unwrap().max_age = Some(value);
+        self
+    }
+}
+
+impl<S, B: 'static> Transform<S> for RedisSession
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RedisSessionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RedisSessionMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            inner: self.0.clone(),
+        })
+    }
+}
+
+/// Redis session middleware
+pub struct RedisSessionMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    inner: Rc<RedisSessionInner>,
+}
+
+impl<S, B: 'static> Service for RedisSessionMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            let now = expiry::now();
+            let (id, created) = match inner.session_id(&req) {
+                Some(id) => match inner.load(&id).await {
+                    Ok(Some(mut state)) => {
+                        if let Some(created) = inner.expiry.validate(&mut state, now) {
+                            Session::set_session(state.into_iter(), &mut req);
+                            (Some(id), created)
+                        } else {
+                            (None, now)
+                        }
+                    }
+                    // session is expired or unknown
+                    Ok(None) => (None, now),
+                    Err(e) => return Ok(req.error_response(e)),
+                },
+                None => (None, now),
+            };
+
+            let fut = srv.borrow_mut().call(req);
+            let mut res = fut.await?;
+
+            match inner.update(&mut res, id, created, now).await {
+                Ok(()) => Ok(res),
+                Err(e) => Ok(res.error_response(e)),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+
+    #[test]
+    fn json_serializer() {
+        let mut state = HashMap::new();
+        state.insert("counter".to_string(), "100".to_string());
+
+        let data = JsonSerializer.serialize(&state).unwrap();
+        assert_eq!(JsonSerializer.deserialize(&data).unwrap(), state);
+        assert!(JsonSerializer.deserialize(b"{").is_err());
+    }
+
+    #[test]
+    fn generated_id() {
+        let id = generate_id();
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, generate_id());
+    }
+
+    #[test]
+    fn signed_session_id() {
+        let session = RedisSession::new("redis://127.0.0.1:6379", &[0; 32]);
+        let mut res = test::TestRequest::default()
+            .to_srv_request()
+            .into_response(actix_web::HttpResponse::Ok().finish());
+        session.0.set_cookie(&mut res, "abc").unwrap();
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+        assert_ne!(cookie.value(), "abc");
+
+        let req = test::TestRequest::default().cookie(cookie).to_srv_request();
+        assert_eq!(session.0.session_id(&req), Some("abc".to_string()));
+
+        // unsigned cookie is ignored
+        let req = test::TestRequest::default()
+            .cookie(Cookie::new("actix-session", "abc"))
+            .to_srv_request();
+        assert_eq!(session.0.session_id(&req), None);
+    }
+}
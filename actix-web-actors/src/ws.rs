@@ -1,22 +1,27 @@
 //! Websocket integration
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::io;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
 use actix::dev::{
     AsyncContextParts, ContextFut, ContextParts, Envelope, Mailbox, StreamHandler,
     ToEnvelope,
 };
-use actix::fut::ActorFuture;
+use actix::fut::{wrap_future, ActorFuture};
 use actix::{
-    Actor, ActorContext, ActorState, Addr, AsyncContext, Handler,
+    Actor, ActorContext, ActorState, Addr, Arbiter, AsyncContext, Handler,
     Message as ActixMessage, SpawnHandle,
 };
 use actix_codec::{Decoder, Encoder};
+#[cfg(feature = "compress")]
+pub use actix_http::ws::DeflateConfig;
 use actix_http::ws::{hash_key, Codec};
 pub use actix_http::ws::{
-    CloseCode, CloseReason, Frame, HandshakeError, Message, ProtocolError,
+    CloseCode, CloseReason, Frame, HandshakeError, Item, Message, ProtocolError,
 };
 use actix_web::dev::HttpResponseBuilder;
 use actix_web::error::{Error, PayloadError};
@@ -65,7 +70,9 @@ where
 
 /// Do websocket handshake and start ws actor.
 ///
-/// `protocols` is a sequence of known protocols.
+/// `protocols` is a sequence of known protocols. Protocol selected with
+/// `select_protocol()` is echoed in response and is available to actor
+/// with `WebsocketContext::protocol()`.
 pub fn start_with_protocols<A, T>(
     actor: A,
     protocols: &[&str],
@@ -78,7 +85,37 @@ where
     T: Stream<Item = Result<Bytes, PayloadError>> + 'static,
 {
     let mut res = handshake_with_protocols(req, protocols)?;
-    Ok(res.streaming(WebsocketContext::create(actor, stream)))
+    let protocol = select_protocol(req, protocols).map(|p| p.to_owned());
+    let out_stream = WebsocketContext::with_factory(stream, move |ctx| {
+        ctx.protocol = protocol;
+        actor
+    });
+    Ok(res.streaming(out_stream))
+}
+
+/// Do websocket handshake and start ws actor, with `permessage-deflate`
+/// extension if client offers it.
+///
+/// Text and binary messages are compressed if extension is negotiated.
+#[cfg(feature = "compress")]
+pub fn start_with_deflate<A, T>(
+    actor: A,
+    config: &DeflateConfig,
+    req: &HttpRequest,
+    stream: T,
+) -> Result<HttpResponse, Error>
+where
+    A: Actor<Context = WebsocketContext<A>>
+        + StreamHandler<Result<Message, ProtocolError>>,
+    T: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+{
+    let mut res = handshake(req)?;
+    let mut codec = Codec::new();
+    if let Some((extension, context)) = config.negotiate(req.head()) {
+        res.header(header::SEC_WEBSOCKET_EXTENSIONS, extension);
+        codec = codec.deflate(context);
+    }
+    Ok(res.streaming(WebsocketContext::with_codec(actor, stream, codec)))
 }
 
 /// Prepare `WebSocket` handshake response.
@@ -150,16 +187,7 @@ pub fn handshake_with_protocols(
     };
 
     // check requested protocols
-    let protocol =
-        req.headers()
-            .get(&header::SEC_WEBSOCKET_PROTOCOL)
-            .and_then(|req_protocols| {
-                let req_protocols = req_protocols.to_str().ok()?;
-                req_protocols
-                    .split(',')
-                    .map(|req_p| req_p.trim())
-                    .find(|req_p| protocols.iter().any(|p| p == req_p))
-            });
+    let protocol = select_protocol(req, protocols);
 
     let mut response = HttpResponse::build(StatusCode::SWITCHING_PROTOCOLS)
         .upgrade("websocket")
@@ -174,13 +202,139 @@ pub fn handshake_with_protocols(
     Ok(response)
 }
 
+/// Select subprotocol for `WebSocket` connection.
+///
+/// Returns the first protocol of `Sec-WebSocket-Protocol` request headers
+/// which is in `protocols` sequence of known protocols.
+pub fn select_protocol<'a>(req: &'a HttpRequest, protocols: &[&str]) -> Option<&'a str> {
+    req.headers()
+        .get_all(&header::SEC_WEBSOCKET_PROTOCOL)
+        .filter_map(|req_protocols| req_protocols.to_str().ok())
+        .flat_map(|req_protocols| req_protocols.split(','))
+        .map(|req_p| req_p.trim())
+        .find(|req_p| protocols.iter().any(|p| p == req_p))
+}
+
 /// Execution context for `WebSockets` actors
 pub struct WebsocketContext<A>
 where
     A: Actor<Context = WebsocketContext<A>>,
 {
     inner: ContextParts<A>,
-    messages: VecDeque<Option<Message>>,
+    shared: Rc<Shared>,
+    protocol: Option<String>,
+    high_water_mark: Option<(usize, HighWaterAction)>,
+    /// Close frame is sent with `close_with_timeout()`
+    closing: bool,
+}
+
+/// Action applied once buffered messages exceed high-water mark
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighWaterAction {
+    /// Pause actor until buffer is drained
+    Pause,
+    /// Drop buffered messages and close connection
+    Close,
+}
+
+/// Maximum size of chunk of encoded messages passed to connection
+const MAX_CHUNK_SIZE: usize = 65_536;
+
+/// State shared by context, stream of incoming frames and stream of
+/// outgoing messages
+struct Shared {
+    /// Time of last frame received from peer
+    last_frame: Cell<Instant>,
+    /// Maximum size of reassembled message, continuation frames are not
+    /// reassembled if it is not set
+    max_message_size: Cell<Option<usize>>,
+    /// Close code set by stream on protocol violation
+    close: Cell<Option<CloseCode>>,
+    /// Close frame received from peer
+    peer_close: RefCell<Option<Option<CloseReason>>>,
+    /// Messages written by actor, that are not yet passed to connection
+    messages: RefCell<VecDeque<Option<Message>>>,
+    /// Size of buffered messages
+    buffered: Cell<usize>,
+    /// Tasks waiting for buffer to be drained
+    drain_waiters: RefCell<Vec<Waker>>,
+    /// Task of outgoing stream, it waits for written messages
+    writer: RefCell<Option<Waker>>,
+    /// Task of actor, it is woken once connection is closed
+    actor: RefCell<Option<Waker>>,
+    /// Actor is stopped, no more messages are written
+    stopped: Cell<bool>,
+    /// Outgoing stream is dropped, i.e. connection is closed
+    disconnected: Cell<bool>,
+}
+
+impl Shared {
+    fn new() -> Rc<Shared> {
+        Rc::new(Shared {
+            last_frame: Cell::new(Instant::now()),
+            max_message_size: Cell::new(None),
+            close: Cell::new(None),
+            peer_close: RefCell::new(None),
+            messages: RefCell::new(VecDeque::new()),
+            buffered: Cell::new(0),
+            drain_waiters: RefCell::new(Vec::new()),
+            writer: RefCell::new(None),
+            actor: RefCell::new(None),
+            stopped: Cell::new(false),
+            disconnected: Cell::new(false),
+        })
+    }
+
+    fn wake_writer(&self) {
+        if let Some(waker) = self.writer.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    /// Reset buffered size and wake tasks waiting for drain
+    fn drain(&self) {
+        self.buffered.set(0);
+        for waker in self.drain_waiters.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by `WebsocketContext::drained()`
+pub struct Drained {
+    shared: Rc<Shared>,
+}
+
+impl Future for Drained {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.buffered.get() == 0 {
+            Poll::Ready(())
+        } else {
+            self.shared
+                .drain_waiters
+                .borrow_mut()
+                .push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Size of message payload
+fn message_size(msg: &Message) -> usize {
+    match msg {
+        Message::Text(text) => text.len(),
+        Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data.len(),
+        Message::Continuation(Item::FirstText(data))
+        | Message::Continuation(Item::FirstBinary(data))
+        | Message::Continuation(Item::Continue(data))
+        | Message::Continuation(Item::Last(data)) => data.len(),
+        Message::Close(Some(reason)) => {
+            2 + reason.description.as_ref().map_or(0, |desc| desc.len())
+        }
+        Message::Close(None) | Message::Nop => 0,
+    }
 }
 
 impl<A> ActorContext for WebsocketContext<A>
@@ -268,13 +422,17 @@ where
         let mb = Mailbox::default();
         let mut ctx = WebsocketContext {
             inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
+            shared: Shared::new(),
+            protocol: None,
+            high_water_mark: None,
+            closing: false,
         };
-        ctx.add_stream(WsStream::new(stream, Codec::new()));
+        ctx.add_stream(WsStream::new(stream, Codec::new(), ctx.shared.clone()));
 
         let addr = ctx.address();
+        let stream = WebsocketContextFut::spawn(ctx, actor, mb, Codec::new());
 
-        (addr, WebsocketContextFut::new(ctx, actor, mb, Codec::new()))
+        (addr, stream)
     }
 
     #[inline]
@@ -291,11 +449,14 @@ where
         let mb = Mailbox::default();
         let mut ctx = WebsocketContext {
             inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
+            shared: Shared::new(),
+            protocol: None,
+            high_water_mark: None,
+            closing: false,
         };
-        ctx.add_stream(WsStream::new(stream, codec));
+        ctx.add_stream(WsStream::new(stream, codec.clone(), ctx.shared.clone()));
 
-        WebsocketContextFut::new(ctx, actor, mb, codec)
+        WebsocketContextFut::spawn(ctx, actor, mb, codec)
     }
 
     /// Create a new Websocket context
@@ -311,13 +472,16 @@ where
         let mb = Mailbox::default();
         let mut ctx = WebsocketContext {
             inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
+            shared: Shared::new(),
+            protocol: None,
+            high_water_mark: None,
+            closing: false,
         };
-        ctx.add_stream(WsStream::new(stream, Codec::new()));
+        ctx.add_stream(WsStream::new(stream, Codec::new(), ctx.shared.clone()));
 
         let act = f(&mut ctx);
 
-        WebsocketContextFut::new(ctx, act, mb, Codec::new())
+        WebsocketContextFut::spawn(ctx, act, mb, Codec::new())
     }
 }
 
@@ -333,7 +497,32 @@ where
     /// that handle the framing for you.
     #[inline]
     pub fn write_raw(&mut self, msg: Message) {
-        self.messages.push_back(Some(msg));
+        if self.shared.disconnected.get() {
+            return;
+        }
+        let size = message_size(&msg);
+        let buffered = self.shared.buffered.get() + size;
+        self.shared.buffered.set(buffered);
+        self.shared.messages.borrow_mut().push_back(Some(msg));
+        self.shared.wake_writer();
+
+        if let Some((mark, action)) = self.high_water_mark {
+            if buffered > mark && buffered - size <= mark {
+                match action {
+                    HighWaterAction::Pause => {
+                        let drained = self.drained();
+                        self.wait(wrap_future(drained));
+                    }
+                    HighWaterAction::Close => {
+                        self.high_water_mark = None;
+                        self.shared.messages.borrow_mut().clear();
+                        self.shared.drain();
+                        self.close(Some(CloseCode::Policy.into()));
+                        self.stop();
+                    }
+                }
+            }
+        }
     }
 
     /// Send text frame
@@ -366,6 +555,147 @@ where
         self.write_raw(Message::Close(reason));
     }
 
+    /// Send close frame and stop actor once peer acknowledges close.
+    ///
+    /// Actor is stopped and connection is closed after `timeout` if peer
+    /// does not respond with close frame. Close frame of peer is passed to
+    /// stream handler as usual.
+    pub fn close_with_timeout(
+        &mut self,
+        reason: Option<CloseReason>,
+        timeout: Duration,
+    ) {
+        self.close(reason);
+        self.closing = true;
+        self.run_later(timeout, |_, ctx| ctx.stop());
+    }
+
+    /// Check if close frame is received from peer.
+    pub fn peer_closed(&self) -> bool {
+        self.shared.peer_close.borrow().is_some()
+    }
+
+    /// Close code and reason of close frame received from peer.
+    pub fn peer_close_reason(&self) -> Option<CloseReason> {
+        self.shared
+            .peer_close
+            .borrow()
+            .clone()
+            .and_then(|reason| reason)
+    }
+
+    /// Send close frame on protocol violation of peer, returns true if
+    /// actor has to be stopped because of it or acknowledged close.
+    ///
+    /// Close frame is sent even if actor is already stopping, stream of
+    /// incoming frames ends right after violation.
+    fn stop_requested(&mut self) -> bool {
+        let violation = if let Some(code) = self.shared.close.take() {
+            self.close(Some(code.into()));
+            true
+        } else {
+            false
+        };
+        if self.state() != ActorState::Running {
+            return false;
+        }
+        violation || (self.closing && self.peer_closed())
+    }
+
+    /// Send ping frame every `interval` and close connection if no frames
+    /// are received from peer during `timeout`.
+    ///
+    /// Any received frame, not only pong, resets timeout. Heartbeat is
+    /// usually started in `Actor::started()`, it could be cancelled with
+    /// returned handle.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use actix::{Actor, StreamHandler};
+    /// use actix_web_actors::ws;
+    ///
+    /// struct Ws;
+    ///
+    /// impl Actor for Ws {
+    ///     type Context = ws::WebsocketContext<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.heartbeat(Duration::from_secs(5), Duration::from_secs(10));
+    ///     }
+    /// }
+    ///
+    /// impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Ws {
+    ///     fn handle(
+    ///         &mut self,
+    ///         msg: Result<ws::Message, ws::ProtocolError>,
+    ///         ctx: &mut Self::Context,
+    ///     ) {
+    ///         if let Ok(ws::Message::Text(text)) = msg {
+    ///             ctx.text(text)
+    ///         }
+    ///     }
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn heartbeat(&mut self, interval: Duration, timeout: Duration) -> SpawnHandle {
+        self.shared.last_frame.set(Instant::now());
+        self.run_interval(interval, move |_, ctx| {
+            if ctx.shared.last_frame.get().elapsed() > timeout {
+                ctx.close(None);
+                ctx.stop();
+            } else {
+                ctx.ping(b"");
+            }
+        })
+    }
+
+    /// Size of messages that are written but not yet passed to connection.
+    ///
+    /// Messages are passed to connection if its write buffer is not full.
+    /// Actor keeps handling incoming frames, mailbox and timers meanwhile,
+    /// so buffered size grows if peer does not read data fast enough.
+    pub fn buffered(&self) -> usize {
+        self.shared.buffered.get()
+    }
+
+    /// Future that resolves once all written messages are passed to
+    /// connection, or connection is closed.
+    ///
+    /// It could be awaited with `AsyncContext::wait()` to pause actor until
+    /// slow peer catches up.
+    pub fn drained(&self) -> Drained {
+        Drained {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Set maximum size of buffered messages.
+    ///
+    /// `action` is applied once buffered size exceeds `size`, actor is
+    /// either paused until buffer is drained, or buffered messages are
+    /// dropped and connection is closed with `1008 Policy Violation` close
+    /// code. By default buffer size is not limited.
+    pub fn set_high_water_mark(&mut self, size: usize, action: HighWaterAction) {
+        self.high_water_mark = Some((size, action));
+    }
+
+    /// Subprotocol selected during handshake, it is set only if actor is
+    /// started with `start_with_protocols()`.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_ref().map(|p| p.as_str())
+    }
+
+    /// Reassemble continuation frames into complete `Text` and `Binary`
+    /// messages.
+    ///
+    /// Connection is closed with `1009 Message Too Big` close code and
+    /// `ProtocolError::Overflow` error is passed to stream handler if
+    /// reassembled message exceeds `max_size` bytes. It is usually called
+    /// in `Actor::started()`.
+    pub fn aggregate_continuations(&mut self, max_size: usize) {
+        self.shared.max_message_size.set(Some(max_size));
+    }
+
     /// Handle of the running future
     ///
     /// SpawnHandle is the handle returned by `AsyncContext::spawn()` method.
@@ -390,35 +720,85 @@ where
     }
 }
 
+/// Future that runs actor, it is spawned on its own task, so actor is not
+/// blocked while connection does not accept data.
 struct WebsocketContextFut<A>
 where
     A: Actor<Context = WebsocketContext<A>>,
 {
     fut: ContextFut<A, WebsocketContext<A>>,
-    encoder: Codec,
-    buf: BytesMut,
-    closed: bool,
 }
 
 impl<A> WebsocketContextFut<A>
 where
     A: Actor<Context = WebsocketContext<A>>,
 {
-    fn new(ctx: WebsocketContext<A>, act: A, mailbox: Mailbox<A>, codec: Codec) -> Self {
-        let fut = ContextFut::new(ctx, act, mailbox);
-        WebsocketContextFut {
-            fut,
+    /// Spawn actor and return stream of its messages
+    fn spawn(
+        ctx: WebsocketContext<A>,
+        act: A,
+        mailbox: Mailbox<A>,
+        codec: Codec,
+    ) -> WebsocketContextStream {
+        let stream = WebsocketContextStream {
+            shared: ctx.shared.clone(),
             encoder: codec,
-            buf: BytesMut::new(),
             closed: false,
+        };
+        Arbiter::spawn(WebsocketContextFut {
+            fut: ContextFut::new(ctx, act, mailbox),
+        });
+        stream
+    }
+}
+
+impl<A> Future for WebsocketContextFut<A>
+where
+    A: Actor<Context = WebsocketContext<A>>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+
+        if this.fut.ctx().shared.disconnected.get() {
+            this.fut.ctx().stop();
+        }
+        let _ = Pin::new(&mut this.fut).poll(cx);
+
+        if this.fut.ctx().stop_requested() && this.fut.alive() {
+            this.fut.ctx().stop();
+            let _ = Pin::new(&mut this.fut).poll(cx);
+        }
+
+        if this.fut.alive() {
+            *this.fut.ctx().shared.actor.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
         }
     }
 }
 
-impl<A> Stream for WebsocketContextFut<A>
+impl<A> Drop for WebsocketContextFut<A>
 where
     A: Actor<Context = WebsocketContext<A>>,
 {
+    fn drop(&mut self) {
+        let shared = &self.fut.ctx().shared;
+        shared.stopped.set(true);
+        shared.wake_writer();
+    }
+}
+
+/// Stream of encoded messages of actor, it is body of websocket response
+struct WebsocketContextStream {
+    shared: Rc<Shared>,
+    encoder: Codec,
+    closed: bool,
+}
+
+impl Stream for WebsocketContextStream {
     type Item = Result<Bytes, Error>;
 
     fn poll_next(
@@ -426,27 +806,51 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
-
-        if this.fut.alive() {
-            let _ = Pin::new(&mut this.fut).poll(cx);
-        }
-
-        // encode messages
-        while let Some(item) = this.fut.ctx().messages.pop_front() {
-            if let Some(msg) = item {
-                this.encoder.encode(msg, &mut this.buf)?;
-            } else {
-                this.closed = true;
-                break;
+        let mut buf = BytesMut::new();
+
+        // encode messages, stream is polled while connection accepts data
+        if !this.closed {
+            let mut messages = this.shared.messages.borrow_mut();
+            while buf.len() < MAX_CHUNK_SIZE {
+                match messages.pop_front() {
+                    Some(Some(msg)) => {
+                        let buffered = this.shared.buffered.get();
+                        this.shared
+                            .buffered
+                            .set(buffered.saturating_sub(message_size(&msg)));
+                        this.encoder.encode(msg, &mut buf)?;
+                    }
+                    Some(None) => {
+                        this.closed = true;
+                        break;
+                    }
+                    None => break,
+                }
             }
         }
+        if this.shared.buffered.get() == 0 {
+            this.shared.drain();
+        }
 
-        if !this.buf.is_empty() {
-            Poll::Ready(Some(Ok(this.buf.split().freeze())))
-        } else if this.fut.alive() && !this.closed {
-            Poll::Pending
-        } else {
+        if !buf.is_empty() {
+            Poll::Ready(Some(Ok(buf.freeze())))
+        } else if this.closed || this.shared.stopped.get() {
             Poll::Ready(None)
+        } else {
+            *this.shared.writer.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for WebsocketContextStream {
+    fn drop(&mut self) {
+        // messages are not sent anymore, actor is stopped
+        self.shared.disconnected.set(true);
+        self.shared.messages.borrow_mut().clear();
+        self.shared.drain();
+        if let Some(waker) = self.shared.actor.borrow_mut().take() {
+            waker.wake();
         }
     }
 }
@@ -469,22 +873,68 @@ struct WsStream<S> {
     decoder: Codec,
     buf: BytesMut,
     closed: bool,
+    shared: Rc<Shared>,
+    /// Reassembled message, flag is set for text message
+    message: Option<(bool, BytesMut)>,
 }
 
 impl<S> WsStream<S>
 where
     S: Stream<Item = Result<Bytes, PayloadError>>,
 {
-    fn new(stream: S, codec: Codec) -> Self {
+    fn new(stream: S, codec: Codec, shared: Rc<Shared>) -> Self {
         Self {
             stream,
             decoder: codec,
             buf: BytesMut::new(),
             closed: false,
+            shared,
+            message: None,
         }
     }
 }
 
+/// Append continuation frame to reassembled message, returns complete
+/// message on last frame.
+fn aggregate(
+    message: &mut Option<(bool, BytesMut)>,
+    item: Item,
+    max_size: usize,
+) -> Result<Option<Frame>, ProtocolError> {
+    let (last, data) = match item {
+        Item::FirstText(data) => {
+            *message = Some((true, BytesMut::new()));
+            (false, data)
+        }
+        Item::FirstBinary(data) => {
+            *message = Some((false, BytesMut::new()));
+            (false, data)
+        }
+        Item::Continue(data) => (false, data),
+        Item::Last(data) => (true, data),
+    };
+
+    match message {
+        Some((_, buf)) => {
+            if buf.len() + data.len() > max_size {
+                *message = None;
+                return Err(ProtocolError::Overflow);
+            }
+            buf.extend_from_slice(&data);
+        }
+        None => return Err(ProtocolError::ContinuationNotStarted),
+    }
+
+    if !last {
+        return Ok(None);
+    }
+    match message.take() {
+        Some((true, buf)) => Ok(Some(Frame::Text(buf.freeze()))),
+        Some((false, buf)) => Ok(Some(Frame::Binary(buf.freeze()))),
+        None => Ok(None),
+    }
+}
+
 impl<S> Stream for WsStream<S>
 where
     S: Stream<Item = Result<Bytes, PayloadError>>,
@@ -518,34 +968,58 @@ where
             }
         }
 
-        match this.decoder.decode(this.buf)? {
-            None => {
-                if *this.closed {
-                    Poll::Ready(None)
-                } else {
-                    Poll::Pending
+        loop {
+            let frm = match this.decoder.decode(this.buf)? {
+                None => {
+                    return if *this.closed {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Pending
+                    };
                 }
-            }
-            Some(frm) => {
-                let msg = match frm {
-                    Frame::Text(data) => Message::Text(
-                        std::str::from_utf8(&data)
-                            .map_err(|e| {
-                                ProtocolError::Io(io::Error::new(
-                                    io::ErrorKind::Other,
-                                    format!("{}", e),
-                                ))
-                            })?
-                            .to_string(),
-                    ),
-                    Frame::Binary(data) => Message::Binary(data),
-                    Frame::Ping(s) => Message::Ping(s),
-                    Frame::Pong(s) => Message::Pong(s),
-                    Frame::Close(reason) => Message::Close(reason),
-                    Frame::Continuation(item) => Message::Continuation(item),
-                };
-                Poll::Ready(Some(Ok(msg)))
-            }
+                Some(frm) => frm,
+            };
+            this.shared.last_frame.set(Instant::now());
+
+            let frm = match (frm, this.shared.max_message_size.get()) {
+                (Frame::Continuation(item), Some(max_size)) => {
+                    match aggregate(this.message, item, max_size) {
+                        Ok(Some(frm)) => frm,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            if let ProtocolError::Overflow = e {
+                                this.shared.close.set(Some(CloseCode::Size));
+                            }
+                            *this.closed = true;
+                            this.buf.clear();
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                    }
+                }
+                (frm, _) => frm,
+            };
+
+            let msg = match frm {
+                Frame::Text(data) => Message::Text(
+                    std::str::from_utf8(&data)
+                        .map_err(|e| {
+                            ProtocolError::Io(io::Error::new(
+                                io::ErrorKind::Other,
+                                format!("{}", e),
+                            ))
+                        })?
+                        .to_string(),
+                ),
+                Frame::Binary(data) => Message::Binary(data),
+                Frame::Ping(s) => Message::Ping(s),
+                Frame::Pong(s) => Message::Pong(s),
+                Frame::Close(reason) => {
+                    this.shared.peer_close.replace(Some(reason.clone()));
+                    Message::Close(reason)
+                }
+                Frame::Continuation(item) => Message::Continuation(item),
+            };
+            return Poll::Ready(Some(Ok(msg)));
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use actix::prelude::*;
 use actix_web::{test, web, App, HttpRequest};
 use actix_web_actors::*;
@@ -65,3 +67,407 @@ async fn test_simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[actix_rt::test]
+async fn test_deflate() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move {
+                    ws::start_with_deflate(Ws, &ws::DeflateConfig::new(), &req, stream)
+                }
+            },
+        ))
+    });
+
+    // client service
+    let (res, mut framed) = actix_web::client::Client::new()
+        .ws(srv.url("/"))
+        .deflate(ws::DeflateConfig::new())
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()
+            .get(actix_web::http::header::SEC_WEBSOCKET_EXTENSIONS)
+            .unwrap(),
+        "permessage-deflate"
+    );
+
+    let text = "compressed text ".repeat(1000);
+    for _ in 0..2 {
+        framed.send(ws::Message::Text(text.clone())).await.unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from(text.clone())));
+    }
+
+    framed
+        .send(ws::Message::Binary("text".into()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(Bytes::from_static(b"text")));
+}
+
+struct Heartbeat;
+
+impl Actor for Heartbeat {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.heartbeat(Duration::from_millis(10), Duration::from_millis(100));
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Heartbeat {
+    fn handle(
+        &mut self,
+        _: Result<ws::Message, ws::ProtocolError>,
+        _: &mut Self::Context,
+    ) {
+    }
+}
+
+#[actix_rt::test]
+async fn test_heartbeat() {
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Heartbeat, &req, stream) }
+            },
+        ))
+    });
+
+    // client does not respond to pings
+    let mut framed = srv.ws().await.unwrap();
+    let mut pings = 0;
+    loop {
+        match framed.next().await.unwrap().unwrap() {
+            ws::Frame::Ping(_) => pings += 1,
+            ws::Frame::Close(None) => break,
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+    assert!(pings > 1);
+    assert!(framed.next().await.is_none());
+}
+
+#[actix_rt::test]
+async fn test_ws_client() {
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Ws, &req, stream) }
+            },
+        ))
+    });
+
+    let mut client = srv
+        .ws_client()
+        .await
+        .unwrap()
+        .timeout(Duration::from_secs(1));
+    client.send(ws::Message::Text("text".to_string())).await;
+    client.expect_text("text").await;
+    client.send(ws::Message::Binary("bin".into())).await;
+    client.expect_binary("bin").await;
+    client.ping(b"ping").await;
+    let reason = client.close(Some(ws::CloseCode::Normal.into())).await;
+    assert_eq!(reason, Some(ws::CloseCode::Normal.into()));
+
+    // client answers pings of server heartbeat
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Heartbeat, &req, stream) }
+            },
+        ))
+    });
+
+    let mut client = srv.ws_client().await.unwrap();
+    for _ in 0..20 {
+        assert_eq!(client.expect_ping().await, Bytes::new());
+    }
+}
+
+struct Aggregate;
+
+impl Actor for Aggregate {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.aggregate_continuations(8);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Aggregate {
+    fn handle(
+        &mut self,
+        msg: Result<ws::Message, ws::ProtocolError>,
+        ctx: &mut Self::Context,
+    ) {
+        match msg {
+            Ok(ws::Message::Text(text)) => ctx.text(text),
+            Ok(ws::Message::Binary(bin)) => ctx.binary(bin),
+            _ => (),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_aggregate_continuations() {
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Aggregate, &req, stream) }
+            },
+        ))
+    });
+
+    let mut framed = srv.ws().await.unwrap();
+    for item in vec![
+        ws::Item::FirstText("ab".into()),
+        ws::Item::Continue("cd".into()),
+        ws::Item::Last("ef".into()),
+    ] {
+        framed.send(ws::Message::Continuation(item)).await.unwrap();
+    }
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"abcdef")));
+
+    // message is too big
+    for item in vec![
+        ws::Item::FirstBinary("abcd".into()),
+        ws::Item::Continue("efgh".into()),
+        ws::Item::Last("ij".into()),
+    ] {
+        framed.send(ws::Message::Continuation(item)).await.unwrap();
+    }
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Size.into())));
+}
+
+struct Protocol;
+
+impl Actor for Protocol {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let protocol = ctx.protocol().unwrap_or("none").to_owned();
+        ctx.text(protocol);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Protocol {
+    fn handle(
+        &mut self,
+        _: Result<ws::Message, ws::ProtocolError>,
+        _: &mut Self::Context,
+    ) {
+    }
+}
+
+#[actix_rt::test]
+async fn test_protocols() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move {
+                    ws::start_with_protocols(Protocol, &["graphql-ws", "chat"], &req, stream)
+                }
+            },
+        ))
+    });
+
+    let (res, mut framed) = actix_web::client::Client::new()
+        .ws(srv.url("/"))
+        .protocols(&["superchat", "chat"])
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()
+            .get(actix_web::http::header::SEC_WEBSOCKET_PROTOCOL)
+            .unwrap(),
+        "chat"
+    );
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"chat")));
+
+    // no supported protocol
+    let (res, mut framed) = actix_web::client::Client::new()
+        .ws(srv.url("/"))
+        .protocols(&["superchat"])
+        .connect()
+        .await
+        .unwrap();
+    assert!(!res
+        .headers()
+        .contains_key(actix_web::http::header::SEC_WEBSOCKET_PROTOCOL));
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"none")));
+}
+
+struct Flood;
+
+impl Actor for Flood {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.set_high_water_mark(20, ws::HighWaterAction::Close);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Flood {
+    fn handle(
+        &mut self,
+        msg: Result<ws::Message, ws::ProtocolError>,
+        ctx: &mut Self::Context,
+    ) {
+        if let Ok(ws::Message::Text(text)) = msg {
+            let count = text.parse::<usize>().unwrap();
+            for _ in 0..count {
+                ctx.text("text");
+            }
+            let buffered = ctx.buffered();
+            ctx.text(buffered.to_string());
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_high_water_mark() {
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Flood, &req, stream) }
+            },
+        ))
+    });
+
+    let mut framed = srv.ws().await.unwrap();
+    framed
+        .send(ws::Message::Text("4".to_string()))
+        .await
+        .unwrap();
+    for _ in 0..4 {
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+    }
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"16")));
+
+    // buffered messages are dropped
+    framed
+        .send(ws::Message::Text("5".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Policy.into())));
+}
+
+struct Closer;
+
+impl Actor for Closer {
+    type Context = ws::WebsocketContext<Self>;
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Closer {
+    fn handle(
+        &mut self,
+        msg: Result<ws::Message, ws::ProtocolError>,
+        ctx: &mut Self::Context,
+    ) {
+        match msg.unwrap() {
+            ws::Message::Text(_) => ctx.close_with_timeout(
+                Some(ws::CloseCode::Normal.into()),
+                Duration::from_millis(100),
+            ),
+            ws::Message::Close(reason) => {
+                assert!(ctx.peer_closed());
+                assert_eq!(ctx.peer_close_reason(), reason);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_close_with_timeout() {
+    let mut srv = test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                async move { ws::start(Closer, &req, stream) }
+            },
+        ))
+    });
+
+    // peer acknowledges close
+    let mut framed = srv.ws().await.unwrap();
+    framed
+        .send(ws::Message::Text("close".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    assert!(framed.next().await.is_none());
+
+    // connection is closed after timeout
+    let mut framed = srv.ws().await.unwrap();
+    framed
+        .send(ws::Message::Text("close".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+    assert!(framed.next().await.is_none());
+}
+
+struct Writer;
+
+impl Actor for Writer {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.set_high_water_mark(1000, ws::HighWaterAction::Close);
+        ctx.run_interval(Duration::from_millis(1), |_, ctx| {
+            ctx.binary(Bytes::from_static(&[0; 100]));
+        });
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Writer {
+    fn handle(
+        &mut self,
+        _: Result<ws::Message, ws::ProtocolError>,
+        _: &mut Self::Context,
+    ) {
+    }
+}
+
+#[actix_rt::test]
+async fn test_high_water_mark_stalled_reader() {
+    use actix_codec::Decoder;
+    use actix_web::error::PayloadError;
+    use bytes::BytesMut;
+
+    let payload = futures::stream::pending::<Result<Bytes, PayloadError>>();
+    let body = ws::WebsocketContext::create(Writer, payload);
+
+    // actor keeps writing while body is not polled
+    actix_rt::time::delay_for(Duration::from_millis(100)).await;
+
+    let chunks: Vec<_> = body.collect().await;
+    let mut buf = BytesMut::new();
+    for chunk in chunks {
+        buf.extend_from_slice(&chunk.unwrap());
+    }
+    let mut codec = actix_http::ws::Codec::new().client_mode();
+    let item = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Policy.into())));
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+}
@@ -22,6 +22,11 @@
 //! - `"path"` - Raw literal string with path for which to register handle. Mandatory.
 //! - `guard="function_name"` - Registers function as guard using `actix_web::guard::fn_guard`
 //!
+//! ## Url params
+//!
+//! `#[derive(UrlParams)]` implements `actix_web::UrlParams` for a struct, path segments
+//! are checked against struct fields at compile time. See [UrlParams](derive.UrlParams.html)
+//!
 //! ## Notes
 //!
 //! Function name can be specified as any expression that is going to be accessible to the generate
@@ -43,6 +48,7 @@
 extern crate proc_macro;
 
 mod route;
+mod url_params;
 
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
@@ -184,3 +190,42 @@ pub fn patch(args: TokenStream, input: TokenStream) -> TokenStream {
     };
     gen.generate()
 }
+
+/// Derives `actix_web::UrlParams` for struct with fields named after path segments.
+///
+/// Syntax: `#[url(name = "resource name", path = "path pattern")]`
+///
+/// Every dynamic segment of the path must have a field and every field must be a
+/// segment, otherwise compilation fails. Field values are converted with `ToString`.
+///
+/// ## Example:
+///
+/// ```rust
+/// use actix_web::{web, App, HttpRequest, HttpResponse, UrlParams};
+///
+/// #[derive(UrlParams)]
+/// #[url(name = "post", path = "/user/{user}/post/{id}")]
+/// struct PostUrl {
+///     user: String,
+///     id: u32,
+/// }
+///
+/// async fn index(req: HttpRequest) -> HttpResponse {
+///     let url = req.url_for_params(&PostUrl { user: "bob".to_string(), id: 10 });
+///     HttpResponse::Ok().body(url.unwrap().to_string())
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .service(web::resource_for::<PostUrl>().to(index));
+/// }
+/// ```
+#[proc_macro_derive(UrlParams, attributes(url))]
+pub fn url_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let gen = match url_params::UrlParams::new(input) {
+        Ok(gen) => gen,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    gen.generate()
+}
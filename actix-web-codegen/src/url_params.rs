@@ -0,0 +1,190 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+pub struct UrlParams {
+    input: DeriveInput,
+    name: syn::LitStr,
+    path: syn::LitStr,
+    segments: Vec<syn::Ident>,
+}
+
+/// Names of dynamic segments of path pattern, in pattern order
+fn segment_names(path: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut chars = path.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '}' {
+            return Err(format!("Unexpected '}}' in path pattern {:?}", path));
+        }
+        if ch != '{' {
+            continue;
+        }
+
+        // segment name ends with ':' or '}', custom regex may contain braces
+        let mut name = String::new();
+        let mut depth = 1;
+        let mut in_name = true;
+        for ch in &mut chars {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                ':' if depth == 1 => in_name = false,
+                _ if in_name => name.push(ch),
+                _ => (),
+            }
+        }
+        if depth != 0 {
+            return Err(format!("Unclosed '{{' in path pattern {:?}", path));
+        }
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(format!("Empty segment name in path pattern {:?}", path));
+        }
+        if names.contains(&name) {
+            return Err(format!("Duplicate segment {:?} in path pattern", name));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+impl UrlParams {
+    pub fn new(input: DeriveInput) -> syn::Result<Self> {
+        let mut name = None;
+        let mut path = None;
+
+        for attr in input.attrs.iter().filter(|a| a.path.is_ident("url")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        r#"Expected #[url(name = "...", path = "...")]"#,
+                    ));
+                }
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(nv)) => {
+                        let lit = match nv.lit {
+                            Lit::Str(lit) => lit,
+                            lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "Attribute value expects literal string!",
+                                ));
+                            }
+                        };
+                        if nv.path.is_ident("name") {
+                            name = Some(lit);
+                        } else if nv.path.is_ident("path") {
+                            path = Some(lit);
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                nv.path,
+                                "Unknown attribute key is specified. Allowed: name, path",
+                            ));
+                        }
+                    }
+                    arg => {
+                        return Err(syn::Error::new_spanned(arg, "Unknown attribute"));
+                    }
+                }
+            }
+        }
+
+        let name = name.ok_or_else(|| {
+            syn::Error::new(Span::call_site(), r#"Missing #[url(name = "...")]"#)
+        })?;
+        let path = path.ok_or_else(|| {
+            syn::Error::new(Span::call_site(), r#"Missing #[url(path = "...")]"#)
+        })?;
+        let names = segment_names(&path.value())
+            .map_err(|e| syn::Error::new_spanned(&path, e))?;
+
+        let fields = match input.data {
+            Data::Struct(ref data) => match data.fields {
+                Fields::Named(ref fields) => fields
+                    .named
+                    .iter()
+                    .filter_map(|f| f.ident.clone())
+                    .collect::<Vec<_>>(),
+                Fields::Unit => Vec::new(),
+                Fields::Unnamed(ref fields) => {
+                    return Err(syn::Error::new_spanned(
+                        fields,
+                        "Url params fields must be named after path segments",
+                    ));
+                }
+            },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Url params could be derived only for structs",
+                ));
+            }
+        };
+
+        // every path segment needs a field and every field needs a segment
+        for field in &fields {
+            if !names.iter().any(|n| field == n) {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    format!(
+                        "Field `{}` is not a segment of path {:?}",
+                        field,
+                        path.value()
+                    ),
+                ));
+            }
+        }
+        let mut segments = Vec::new();
+        for n in &names {
+            match fields.iter().find(|f| *f == n) {
+                Some(field) => segments.push(field.clone()),
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        &input.ident,
+                        format!("Missing field for path segment `{}`", n),
+                    ));
+                }
+            }
+        }
+
+        Ok(UrlParams {
+            input,
+            name,
+            path,
+            segments,
+        })
+    }
+
+    pub fn generate(&self) -> TokenStream {
+        let ident = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) =
+            self.input.generics.split_for_impl();
+        let name = &self.name;
+        let path = &self.path;
+        let segments = &self.segments;
+
+        let stream = quote! {
+            impl #impl_generics actix_web::UrlParams for #ident #ty_generics #where_clause {
+                const NAME: &'static str = #name;
+                const PATH: &'static str = #path;
+
+                fn elements(&self) -> Vec<String> {
+                    vec![#(self.#segments.to_string()),*]
+                }
+            }
+        };
+        stream.into()
+    }
+}
@@ -1,5 +1,7 @@
-use actix_web::{http, test, web::Path, App, HttpResponse, Responder};
-use actix_web_codegen::{connect, delete, get, head, options, patch, post, put, trace};
+use actix_web::{http, test, web, web::Path, App, HttpRequest, HttpResponse, Responder};
+use actix_web_codegen::{
+    connect, delete, get, head, options, patch, post, put, trace, UrlParams,
+};
 use futures::{future, Future};
 
 #[get("/test")]
@@ -149,3 +151,37 @@ async fn test_auto_async() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[derive(UrlParams)]
+#[url(name = "post", path = "/user/{user}/post/{id:\\d+}")]
+struct PostUrl {
+    id: u32,
+    user: String,
+}
+
+async fn post_url(req: HttpRequest) -> HttpResponse {
+    let url = req.url_for_params(&PostUrl {
+        id: 10,
+        user: "bob".to_string(),
+    });
+    HttpResponse::Ok().body(url.unwrap().path().to_string())
+}
+
+#[actix_rt::test]
+async fn test_url_params() {
+    let srv = test::start(|| {
+        App::new()
+            .service(web::resource_for::<PostUrl>().to(post_url))
+            .route("/", web::get().to(post_url))
+    });
+
+    let request = srv.request(http::Method::GET, srv.url("/"));
+    let mut response = request.send().await.unwrap();
+    assert!(response.status().is_success());
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"/user/bob/post/10");
+
+    let request = srv.request(http::Method::GET, srv.url("/user/bob/post/10"));
+    let response = request.send().await.unwrap();
+    assert!(response.status().is_success());
+}
@@ -6,10 +6,11 @@ use std::time::Duration;
 
 use actix_http::client::{Connect, ConnectError, Connection, Connector};
 use actix_http::http::{header, Error as HttpError, HeaderMap, HeaderName};
+use actix_http::RequestHead;
 use actix_service::Service;
 
-use crate::connect::ConnectorWrapper;
-use crate::{Client, ClientConfig};
+use crate::connect::{ConnectorWrapper, Timeouts};
+use crate::{Client, ClientConfig, CookieStore, Middleware, RetryPolicy};
 
 /// An HTTP Client builder
 ///
@@ -36,10 +37,20 @@ impl ClientBuilder {
             max_redirects: 10,
             config: ClientConfig {
                 headers: HeaderMap::new(),
-                timeout: Some(Duration::from_secs(5)),
+                timeouts: Timeouts {
+                    total: Some(Duration::from_secs(5)),
+                    ..Timeouts::default()
+                },
+                on_request: Vec::new(),
+                middleware: Vec::new(),
+                retry: None,
+                cookies: None,
+                decompress: true,
+                decompress_limit: None,
                 connector: RefCell::new(Box::new(ConnectorWrapper(
                     Connector::new().finish(),
                 ))),
+                proxied: RefCell::new(Vec::new()),
             },
         }
     }
@@ -61,13 +72,85 @@ impl ClientBuilder {
     /// Request timeout is the total time before a response must be received.
     /// Default value is 5 seconds.
     pub fn timeout(mut self, timeout: Duration) -> Self {
-        self.config.timeout = Some(timeout);
+        self.config.timeouts.total = Some(timeout);
         self
     }
 
     /// Disable request timeout.
     pub fn disable_timeout(mut self) -> Self {
-        self.config.timeout = None;
+        self.config.timeouts.total = None;
+        self
+    }
+
+    /// Set connect timeout
+    ///
+    /// Connect timeout is the time to establish connection to host.
+    /// It is not set by default, connector's own timeout applies.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Set response timeout
+    ///
+    /// Response timeout is the time to send request and receive response head
+    /// once connection is established. It is not set by default.
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.response = Some(timeout);
+        self
+    }
+
+    /// Send requests over unix domain socket at `path`.
+    ///
+    /// All connections of client are opened to the socket, host of request
+    /// is used for `Host` header only.
+    ///
+    /// ```rust
+    /// # async fn containers() {
+    /// let client = awc::Client::build().uds("/var/run/docker.sock").finish();
+    /// let res = client.get("http://localhost/containers/json").send().await;
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn uds<P: AsRef<std::path::Path>>(self, path: P) -> Self {
+        self.connector(
+            Connector::new()
+                .connector(actix_http::client::UdsConnector::new(path))
+                .finish(),
+        )
+    }
+
+    /// Retry failed requests according to `policy`.
+    ///
+    /// Requests are not retried by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = Some(policy);
+        self
+    }
+
+    /// Use cookie store, cookies of responses are stored and sent with
+    /// subsequent requests of client.
+    pub fn cookie_store(mut self, store: CookieStore) -> Self {
+        self.config.cookies = Some(store);
+        self
+    }
+
+    /// Disable automatic decompress of response bodies.
+    ///
+    /// `Accept-Encoding` header is not set and bodies of responses are
+    /// returned as is. Decompress is enabled by default.
+    pub fn disable_decompress(mut self) -> Self {
+        self.config.decompress = false;
+        self
+    }
+
+    /// Set max size of decompressed response body.
+    ///
+    /// Reading of compressed body fails with `PayloadError::Overflow` error
+    /// once decompressed data exceeds `limit`, it protects from
+    /// decompression bombs. Decompressed size is not limited by default.
+    pub fn decompress_limit(mut self, limit: u64) -> Self {
+        self.config.decompress_limit = Some(limit);
         self
     }
 
@@ -138,6 +221,31 @@ impl ClientBuilder {
         self.header(header::AUTHORIZATION, format!("Bearer {}", token))
     }
 
+    /// Register function that is called for every created request.
+    ///
+    /// Function is called after default headers are added, it could be
+    /// used for adding headers that depend on current context, i.e.
+    /// trace context of request that is handled.
+    pub fn on_request<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut RequestHead) + 'static,
+    {
+        self.config.on_request.push(Box::new(f));
+        self
+    }
+
+    /// Register middleware of client.
+    ///
+    /// Middlewares are called in order of registration, first registered
+    /// middleware sees request first and response last.
+    pub fn wrap<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        self.config.middleware.push(Box::new(middleware));
+        self
+    }
+
     /// Finish build process and create `Client` instance.
     pub fn finish(self) -> Client {
         Client(Rc::new(self.config))
@@ -189,4 +297,19 @@ mod tests {
             "Bearer someS3cr3tAutht0k3n"
         );
     }
+
+    #[test]
+    fn client_on_request() {
+        let client = ClientBuilder::new()
+            .header("x-default", "1")
+            .on_request(|head| {
+                let value = head.headers.get("x-default").unwrap().clone();
+                head.headers
+                    .insert(HeaderName::from_static("x-hook"), value);
+            })
+            .finish();
+
+        let req = client.get("http://localhost/");
+        assert_eq!(req.headers().get("x-hook").unwrap(), "1");
+    }
 }
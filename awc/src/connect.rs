@@ -2,6 +2,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{fmt, io, mem, net};
 
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
@@ -12,18 +13,53 @@ use actix_http::client::{
 use actix_http::h1::ClientCodec;
 use actix_http::http::HeaderMap;
 use actix_http::{RequestHead, RequestHeadType, ResponseHead};
+use actix_rt::time::timeout;
 use actix_service::Service;
 
 use crate::response::ClientResponse;
 
 pub(crate) struct ConnectorWrapper<T>(pub T);
 
+/// Timeouts of request
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    /// Time to establish connection to host
+    pub(crate) connect: Option<Duration>,
+    /// Time to receive response head after connection is established
+    pub(crate) response: Option<Duration>,
+    /// Total time before response must be received
+    pub(crate) total: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Unset timeouts are taken from `defaults`
+    pub(crate) fn or(self, defaults: &Timeouts) -> Timeouts {
+        Timeouts {
+            connect: self.connect.or(defaults.connect),
+            response: self.response.or(defaults.response),
+            total: self.total.or(defaults.total),
+        }
+    }
+}
+
+/// Resolve future with `err` if it does not complete in time
+async fn with_timeout<F, T, E>(dur: Option<Duration>, fut: F, err: E) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    match dur {
+        Some(dur) => timeout(dur, fut).await.unwrap_or_else(|_| Err(err)),
+        None => fut.await,
+    }
+}
+
 pub(crate) trait Connect {
     fn send_request(
         &mut self,
         head: RequestHead,
         body: Body,
         addr: Option<net::SocketAddr>,
+        timeouts: Timeouts,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>;
 
     fn send_request_extra(
@@ -32,6 +68,7 @@ pub(crate) trait Connect {
         extra_headers: Option<HeaderMap>,
         body: Body,
         addr: Option<net::SocketAddr>,
+        timeouts: Timeouts,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>;
 
     /// Send request, returns Response and Framed
@@ -82,6 +119,7 @@ where
         head: RequestHead,
         body: Body,
         addr: Option<net::SocketAddr>,
+        timeouts: Timeouts,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
         // connect to the host
         let fut = self.0.call(ClientConnect {
@@ -90,11 +128,12 @@ where
         });
 
         Box::pin(async move {
-            let connection = fut.await?;
+            let connection =
+                with_timeout(timeouts.connect, fut, ConnectError::Timeout).await?;
 
             // send request
-            connection
-                .send_request(RequestHeadType::from(head), body)
+            let fut = connection.send_request(RequestHeadType::from(head), body);
+            with_timeout(timeouts.response, fut, SendRequestError::ResponseTimeout)
                 .await
                 .map(|(head, payload)| ClientResponse::new(head, payload))
         })
@@ -106,6 +145,7 @@ where
         extra_headers: Option<HeaderMap>,
         body: Body,
         addr: Option<net::SocketAddr>,
+        timeouts: Timeouts,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
         // connect to the host
         let fut = self.0.call(ClientConnect {
@@ -114,12 +154,15 @@ where
         });
 
         Box::pin(async move {
-            let connection = fut.await?;
+            let connection =
+                with_timeout(timeouts.connect, fut, ConnectError::Timeout).await?;
 
             // send request
-            let (head, payload) = connection
-                .send_request(RequestHeadType::Rc(head, extra_headers), body)
-                .await?;
+            let fut =
+                connection.send_request(RequestHeadType::Rc(head, extra_headers), body);
+            let (head, payload) =
+                with_timeout(timeouts.response, fut, SendRequestError::ResponseTimeout)
+                    .await?;
 
             Ok(ClientResponse::new(head, payload))
         })
@@ -0,0 +1,310 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_http::cookie::Cookie;
+use actix_http::http::header::{self, HeaderMap, HeaderValue};
+use actix_http::http::Uri;
+
+/// Store of cookies of client.
+///
+/// Store records cookies of `Set-Cookie` headers of responses and sends
+/// matching cookies in `Cookie` header of subsequent requests, domain,
+/// path, secure and expiry attributes of cookies are honored. Store is
+/// attached to client with `ClientBuilder::cookie_store()`, clones of store
+/// share cookies.
+///
+/// ```rust
+/// use awc::{Client, CookieStore};
+///
+/// let store = CookieStore::new();
+/// let client = Client::build().cookie_store(store.clone()).finish();
+/// ```
+#[derive(Clone, Default)]
+pub struct CookieStore(Rc<RefCell<Cookies>>);
+
+#[derive(Default)]
+struct Cookies {
+    cookies: Vec<StoredCookie>,
+    /// Creation sequence of next stored cookie
+    next: u64,
+}
+
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    host_only: bool,
+    path: String,
+    /// Expiry time in seconds since unix epoch
+    expires: Option<i64>,
+    /// Creation sequence, kept when cookie is replaced
+    created: u64,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(host, &self.domain)
+        };
+        domain_matches
+            && path_matches(path, &self.path)
+            && (secure || !self.cookie.secure().unwrap_or(false))
+    }
+}
+
+impl CookieStore {
+    /// Create empty store.
+    pub fn new() -> Self {
+        CookieStore::default()
+    }
+
+    /// Store cookie, as if it is set by response of request to `uri`.
+    ///
+    /// Cookie is ignored if its domain does not match host of `uri`.
+    pub fn insert(&self, cookie: Cookie<'static>, uri: &Uri) {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return,
+        };
+
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) if !domain.is_empty() => {
+                let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+                // cookies could not be set for top level domains and
+                // other ip addresses
+                if !domain_matches(&host, &domain)
+                    || (domain != host && !domain.contains('.'))
+                {
+                    return;
+                }
+                (domain, false)
+            }
+            _ => (host, true),
+        };
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(uri.path()),
+        };
+
+        let now = now();
+        let expires = match (cookie.max_age(), cookie.expires()) {
+            (Some(max_age), _) => Some(now.saturating_add(max_age.num_seconds())),
+            (None, Some(expires)) => Some(expires.to_timespec().sec),
+            (None, None) => None,
+        };
+
+        let mut inner = self.0.borrow_mut();
+        let mut created = None;
+        inner.cookies.retain(|c| {
+            if c.cookie.name() == cookie.name() && c.domain == domain && c.path == path {
+                created = Some(c.created);
+                false
+            } else {
+                !c.is_expired(now)
+            }
+        });
+        let created = created.unwrap_or_else(|| {
+            inner.next += 1;
+            inner.next
+        });
+
+        let stored = StoredCookie {
+            cookie,
+            domain,
+            host_only,
+            path,
+            expires,
+            created,
+        };
+        if !stored.is_expired(now) {
+            inner.cookies.push(stored);
+        }
+    }
+
+    /// Cookies that are sent with request to `uri`.
+    ///
+    /// Cookies with longer paths are listed first, cookies with equal path
+    /// lengths are listed in order of creation (RFC 6265, section 5.4).
+    pub fn cookies(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        let host = match uri.host() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return Vec::new(),
+        };
+        let secure = match uri.scheme_str() {
+            Some("https") | Some("wss") => true,
+            _ => false,
+        };
+        let now = now();
+
+        let inner = self.0.borrow();
+        let mut cookies: Vec<_> = inner
+            .cookies
+            .iter()
+            .filter(|c| !c.is_expired(now) && c.matches(&host, uri.path(), secure))
+            .collect();
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        cookies.into_iter().map(|c| c.cookie.clone()).collect()
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.0.borrow_mut().cookies.clear();
+    }
+
+    /// Add matching cookies to `Cookie` header of request.
+    pub(crate) fn add_cookies(&self, headers: &mut HeaderMap, uri: &Uri) {
+        let cookies = self.cookies(uri);
+        if cookies.is_empty() {
+            return;
+        }
+
+        let mut value = headers
+            .get(header::COOKIE)
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_owned())
+            .unwrap_or_default();
+        for cookie in cookies {
+            if !value.is_empty() {
+                value.push_str("; ");
+            }
+            value.push_str(cookie.name());
+            value.push('=');
+            value.push_str(cookie.value());
+        }
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(header::COOKIE, value);
+        }
+    }
+
+    /// Store cookies of `Set-Cookie` headers of response.
+    pub(crate) fn store_cookies(&self, headers: &HeaderMap, uri: &Uri) {
+        for value in headers.get_all(header::SET_COOKIE) {
+            if let Ok(cookie) = value
+                .to_str()
+                .map_err(|_| ())
+                .and_then(|val| Cookie::parse(val.to_owned()).map_err(|_| ()))
+            {
+                self.insert(cookie, uri);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_list()
+            .entries(inner.cookies.iter().map(|c| c.cookie.name()))
+            .finish()
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.len() > domain.len()
+        && host.ends_with(domain)
+        && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+        && host.trim_start_matches('[').parse::<IpAddr>().is_err()
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/')
+                || path.as_bytes()[cookie_path.len()] == b'/'))
+}
+
+/// Directory of request path
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(idx) => path[..idx].to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(cookies: Vec<Cookie<'static>>) -> Vec<String> {
+        cookies.iter().map(|c| c.name().to_owned()).collect()
+    }
+
+    #[test]
+    fn test_domain_and_path() {
+        let store = CookieStore::new();
+        let uri = Uri::from_static("http://www.example.com/a/b");
+        for cookie in &[
+            "host=1",
+            "domain=2; Domain=.example.com",
+            "path=3; Path=/a/b",
+            "secure=4; Secure",
+            "other=5; Domain=other.com",
+            "tld=6; Domain=com",
+        ] {
+            store.insert(Cookie::parse(*cookie).unwrap(), &uri);
+        }
+
+        assert_eq!(names(store.cookies(&uri)), vec!["path", "host", "domain"]);
+        assert_eq!(
+            names(store.cookies(&Uri::from_static("https://www.example.com/a/"))),
+            vec!["host", "domain", "secure"]
+        );
+        assert_eq!(
+            names(store.cookies(&Uri::from_static("http://api.example.com/a/c"))),
+            vec!["domain"]
+        );
+        assert!(store
+            .cookies(&Uri::from_static("http://www.example.com/"))
+            .is_empty());
+
+        // replaced cookie keeps its creation time
+        store.insert(Cookie::parse("host=7").unwrap(), &uri);
+        assert_eq!(names(store.cookies(&uri)), vec!["path", "host", "domain"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("own=0"));
+        store.add_cookies(&mut headers, &Uri::from_static("http://api.example.com/a"));
+        assert_eq!(headers.get(header::COOKIE).unwrap(), "own=0; domain=2");
+    }
+
+    #[test]
+    fn test_expiry() {
+        let store = CookieStore::new();
+        let uri = Uri::from_static("http://example.com/");
+        store.insert(Cookie::parse("a=1; Max-Age=100").unwrap(), &uri);
+        store.insert(
+            Cookie::parse("b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT").unwrap(),
+            &uri,
+        );
+        assert_eq!(names(store.cookies(&uri)), vec!["a"]);
+
+        // replaced and removed
+        store.insert(Cookie::parse("a=2").unwrap(), &uri);
+        assert_eq!(store.cookies(&uri)[0].value(), "2");
+        store.insert(Cookie::parse("a=; Max-Age=0").unwrap(), &uri);
+        assert!(store.cookies(&uri).is_empty());
+    }
+}
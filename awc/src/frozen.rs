@@ -1,7 +1,6 @@
 use std::convert::TryFrom;
 use std::net;
 use std::rc::Rc;
-use std::time::Duration;
 
 use bytes::Bytes;
 use futures_core::Stream;
@@ -12,6 +11,10 @@ use actix_http::http::header::IntoHeaderValue;
 use actix_http::http::{Error as HttpError, HeaderMap, HeaderName, Method, Uri};
 use actix_http::{Error, RequestHead};
 
+use crate::connect::Timeouts;
+use crate::multipart::Form;
+use crate::redirect::RedirectPolicy;
+use crate::retry::RetryPolicy;
 use crate::sender::{RequestSender, SendClientRequest};
 use crate::ClientConfig;
 
@@ -22,7 +25,9 @@ pub struct FrozenClientRequest {
     pub(crate) head: Rc<RequestHead>,
     pub(crate) addr: Option<net::SocketAddr>,
     pub(crate) response_decompress: bool,
-    pub(crate) timeout: Option<Duration>,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) redirect: Option<RedirectPolicy>,
+    pub(crate) retry: Option<RetryPolicy>,
     pub(crate) config: Rc<ClientConfig>,
 }
 
@@ -50,8 +55,10 @@ impl FrozenClientRequest {
         RequestSender::Rc(self.head.clone(), None).send_body(
             self.addr,
             self.response_decompress,
-            self.timeout,
-            self.config.as_ref(),
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
             body,
         )
     }
@@ -61,8 +68,10 @@ impl FrozenClientRequest {
         RequestSender::Rc(self.head.clone(), None).send_json(
             self.addr,
             self.response_decompress,
-            self.timeout,
-            self.config.as_ref(),
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
             value,
         )
     }
@@ -72,12 +81,27 @@ impl FrozenClientRequest {
         RequestSender::Rc(self.head.clone(), None).send_form(
             self.addr,
             self.response_decompress,
-            self.timeout,
-            self.config.as_ref(),
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
             value,
         )
     }
 
+    /// Send a `multipart/form-data` body.
+    pub fn send_multipart(&self, form: Form) -> SendClientRequest {
+        RequestSender::Rc(self.head.clone(), None).send_multipart(
+            self.addr,
+            self.response_decompress,
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
+            form,
+        )
+    }
+
     /// Send a streaming body.
     pub fn send_stream<S, E>(&self, stream: S) -> SendClientRequest
     where
@@ -87,8 +111,28 @@ impl FrozenClientRequest {
         RequestSender::Rc(self.head.clone(), None).send_stream(
             self.addr,
             self.response_decompress,
-            self.timeout,
-            self.config.as_ref(),
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
+            stream,
+        )
+    }
+
+    /// Send a streaming body of known size.
+    pub fn send_sized_stream<S, E>(&self, size: u64, stream: S) -> SendClientRequest
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        RequestSender::Rc(self.head.clone(), None).send_sized_stream(
+            self.addr,
+            self.response_decompress,
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
+            size,
             stream,
         )
     }
@@ -98,8 +142,10 @@ impl FrozenClientRequest {
         RequestSender::Rc(self.head.clone(), None).send(
             self.addr,
             self.response_decompress,
-            self.timeout,
-            self.config.as_ref(),
+            self.timeouts,
+            self.redirect,
+            self.retry,
+            &self.config,
         )
     }
 
@@ -165,8 +211,10 @@ impl FrozenSendBuilder {
         RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_body(
             self.req.addr,
             self.req.response_decompress,
-            self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
             body,
         )
     }
@@ -180,8 +228,10 @@ impl FrozenSendBuilder {
         RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_json(
             self.req.addr,
             self.req.response_decompress,
-            self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
             value,
         )
     }
@@ -195,12 +245,31 @@ impl FrozenSendBuilder {
         RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_form(
             self.req.addr,
             self.req.response_decompress,
-            self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
             value,
         )
     }
 
+    /// Complete request construction and send a `multipart/form-data` body.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        if let Some(e) = self.err {
+            return e.into();
+        }
+
+        RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_multipart(
+            self.req.addr,
+            self.req.response_decompress,
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
+            form,
+        )
+    }
+
     /// Complete request construction and send a streaming body.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
@@ -214,8 +283,32 @@ impl FrozenSendBuilder {
         RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_stream(
             self.req.addr,
             self.req.response_decompress,
-            self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
+            stream,
+        )
+    }
+
+    /// Complete request construction and send a streaming body of known size.
+    pub fn send_sized_stream<S, E>(self, size: u64, stream: S) -> SendClientRequest
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        if let Some(e) = self.err {
+            return e.into();
+        }
+
+        RequestSender::Rc(self.req.head, Some(self.extra_headers)).send_sized_stream(
+            self.req.addr,
+            self.req.response_decompress,
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
+            size,
             stream,
         )
     }
@@ -229,8 +322,10 @@ impl FrozenSendBuilder {
         RequestSender::Rc(self.req.head, Some(self.extra_headers)).send(
             self.req.addr,
             self.req.response_decompress,
-            self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.timeouts,
+            self.req.redirect,
+            self.req.retry,
+            &self.req.config,
         )
     }
 }
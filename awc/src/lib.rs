@@ -2,7 +2,8 @@
 #![allow(
     clippy::type_complexity,
     clippy::borrow_interior_mutable_const,
-    clippy::needless_doctest_main
+    clippy::needless_doctest_main,
+    clippy::too_many_arguments
 )]
 //! An HTTP Client
 //!
@@ -23,34 +24,44 @@
 //!     println!("Response: {:?}", response);
 //! }
 //! ```
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
 use std::convert::TryFrom;
 use std::rc::Rc;
 use std::time::Duration;
 
-pub use actix_http::{client::Connector, cookie, http};
+pub use actix_http::client::{Connector, PoolMetrics, Proxy};
+pub use actix_http::{cookie, http};
 
 use actix_http::http::{Error as HttpError, HeaderMap, Method, Uri};
 use actix_http::RequestHead;
 
 mod builder;
 mod connect;
+mod cookie_store;
 pub mod error;
 mod frozen;
+mod middleware;
+pub mod multipart;
+mod redirect;
 mod request;
 mod response;
+mod retry;
 mod sender;
 pub mod test;
 pub mod ws;
 
 pub use self::builder::ClientBuilder;
 pub use self::connect::BoxedSocket;
+pub use self::cookie_store::CookieStore;
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
+pub use self::middleware::{Middleware, Next};
+pub use self::redirect::RedirectPolicy;
 pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
+pub use self::retry::RetryPolicy;
 pub use self::sender::SendClientRequest;
 
-use self::connect::{Connect, ConnectorWrapper};
+use self::connect::{Connect, ConnectorWrapper, Timeouts};
 
 /// An HTTP Client
 ///
@@ -74,8 +85,17 @@ pub struct Client(Rc<ClientConfig>);
 
 pub(crate) struct ClientConfig {
     pub(crate) connector: RefCell<Box<dyn Connect>>,
+    /// Connectors of requests with proxy override, `None` is for direct
+    /// connections
+    pub(crate) proxied: RefCell<Vec<(Option<Proxy>, Box<dyn Connect>)>>,
     pub(crate) headers: HeaderMap,
-    pub(crate) timeout: Option<Duration>,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) on_request: Vec<Box<dyn Fn(&mut RequestHead)>>,
+    pub(crate) middleware: Vec<Box<dyn Middleware>>,
+    pub(crate) retry: Option<RetryPolicy>,
+    pub(crate) cookies: Option<CookieStore>,
+    pub(crate) decompress: bool,
+    pub(crate) decompress_limit: Option<u64>,
 }
 
 impl Default for Client {
@@ -84,12 +104,56 @@ impl Default for Client {
             connector: RefCell::new(Box::new(ConnectorWrapper(
                 Connector::new().finish(),
             ))),
+            proxied: RefCell::new(Vec::new()),
             headers: HeaderMap::new(),
-            timeout: Some(Duration::from_secs(5)),
+            timeouts: Timeouts {
+                total: Some(Duration::from_secs(5)),
+                ..Timeouts::default()
+            },
+            on_request: Vec::new(),
+            middleware: Vec::new(),
+            retry: None,
+            cookies: None,
+            decompress: true,
+            decompress_limit: None,
         }))
     }
 }
 
+/// Proxy of request that overrides proxy of client connector
+#[derive(Clone)]
+pub(crate) struct ProxyOverride(pub(crate) Option<Proxy>);
+
+impl ClientConfig {
+    /// Connector of request.
+    ///
+    /// Requests with proxy override use connector with default settings,
+    /// it is created once per proxy and is kept for later requests.
+    pub(crate) fn connector(&self, head: &RequestHead) -> RefMut<'_, Box<dyn Connect>> {
+        let proxy = match head.extensions().get::<ProxyOverride>() {
+            Some(ProxyOverride(proxy)) => proxy.clone(),
+            None => return self.connector.borrow_mut(),
+        };
+
+        RefMut::map(self.proxied.borrow_mut(), move |proxied| {
+            let idx = match proxied.iter().position(|(p, _)| *p == proxy) {
+                Some(idx) => idx,
+                None => {
+                    let connector: Box<dyn Connect> = match proxy.clone() {
+                        Some(proxy) => Box::new(ConnectorWrapper(
+                            Connector::new().proxy(proxy).finish(),
+                        )),
+                        None => Box::new(ConnectorWrapper(Connector::new().finish())),
+                    };
+                    proxied.push((proxy, connector));
+                    proxied.len() - 1
+                }
+            };
+            &mut proxied[idx].1
+        })
+    }
+}
+
 impl Client {
     /// Create new client instance with default settings.
     pub fn new() -> Client {
@@ -112,6 +176,9 @@ impl Client {
         for (key, value) in self.0.headers.iter() {
             req = req.set_header_if_none(key.clone(), value.clone());
         }
+        for f in self.0.on_request.iter() {
+            f(&mut req.head);
+        }
         req
     }
 
@@ -0,0 +1,135 @@
+use std::net;
+use std::rc::Rc;
+
+use actix_http::body::Body;
+use actix_http::client::SendRequestError;
+use actix_http::RequestHead;
+use futures_core::future::LocalBoxFuture;
+
+use crate::connect::Timeouts;
+use crate::redirect::{self, RedirectPolicy};
+use crate::response::ClientResponse;
+use crate::retry::RetryPolicy;
+use crate::ClientConfig;
+
+/// Middleware of client.
+///
+/// Middlewares are registered with `ClientBuilder::wrap()` and are called
+/// for every request of client in order of registration. Middleware could
+/// modify request, pass it to the next middleware with `Next::send()` and
+/// inspect response, or resolve request without sending it.
+///
+/// Middlewares see one logical request, redirects and retries of request
+/// happen after the last middleware. Websocket handshakes do not go
+/// through middlewares.
+///
+/// ```rust
+/// use actix_http::body::Body;
+/// use actix_http::RequestHead;
+/// use awc::error::SendRequestError;
+/// use awc::http::HeaderValue;
+/// use awc::{ClientResponse, Middleware, Next};
+/// use futures::future::{FutureExt, LocalBoxFuture};
+///
+/// struct Auth(HeaderValue);
+///
+/// impl Middleware for Auth {
+///     fn handle(
+///         &self,
+///         mut head: RequestHead,
+///         body: Body,
+///         next: Next,
+///     ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+///         head.headers
+///             .insert(awc::http::header::AUTHORIZATION, self.0.clone());
+///         next.send(head, body)
+///             .map(|res| {
+///                 if let Ok(ref res) = res {
+///                     println!("{}", res.status());
+///                 }
+///                 res
+///             })
+///             .boxed_local()
+///     }
+/// }
+///
+/// let client = awc::Client::build()
+///     .wrap(Auth(HeaderValue::from_static("Bearer token")))
+///     .finish();
+/// ```
+pub trait Middleware {
+    /// Handle request, request is sent with `next.send()`.
+    fn handle(
+        &self,
+        head: RequestHead,
+        body: Body,
+        next: Next,
+    ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>>;
+}
+
+/// Rest of middleware chain of request
+pub struct Next {
+    chain: Rc<Chain>,
+    idx: usize,
+}
+
+struct Chain {
+    config: Rc<ClientConfig>,
+    addr: Option<net::SocketAddr>,
+    timeouts: Timeouts,
+    redirect: Option<RedirectPolicy>,
+    retry: Option<RetryPolicy>,
+}
+
+impl Next {
+    /// Pass request to the next middleware, request is sent after the last
+    /// middleware.
+    pub fn send(
+        self,
+        head: RequestHead,
+        body: Body,
+    ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+        let chain = self.chain;
+        match chain.config.middleware.get(self.idx) {
+            Some(middleware) => {
+                let next = Next {
+                    chain: chain.clone(),
+                    idx: self.idx + 1,
+                };
+                middleware.handle(head, body, next)
+            }
+            None => redirect::send(
+                chain.config.clone(),
+                head,
+                body,
+                chain.addr,
+                chain.timeouts,
+                chain.redirect,
+                chain.retry,
+            ),
+        }
+    }
+}
+
+/// Send request through middlewares of client.
+pub(crate) fn send(
+    config: Rc<ClientConfig>,
+    head: RequestHead,
+    body: Body,
+    addr: Option<net::SocketAddr>,
+    timeouts: Timeouts,
+    redirect: Option<RedirectPolicy>,
+    retry: Option<RetryPolicy>,
+) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+    let next = Next {
+        chain: Rc::new(Chain {
+            config,
+            addr,
+            timeouts,
+            redirect,
+            retry,
+        }),
+        idx: 0,
+    };
+    next.send(head, body)
+}
@@ -0,0 +1,346 @@
+//! Multipart form bodies
+//!
+//! ```rust
+//! use awc::multipart::{Form, Part};
+//!
+//! # async fn upload() {
+//! let form = Form::new()
+//!     .text("title", "report")
+//!     .part(
+//!         "file",
+//!         Part::bytes("col1,col2\n")
+//!             .file_name("report.csv")
+//!             .content_type(mime::TEXT_CSV),
+//!     );
+//!
+//! let res = awc::Client::new()
+//!     .post("http://localhost:8080/upload")
+//!     .send_multipart(form)
+//!     .await;
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::fmt;
+use std::task::{Context, Poll};
+
+use actix_codec::AsyncRead;
+use actix_http::body::{Body, BodySize, BodyStream, MessageBody, ReaderBody};
+use actix_http::Error;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_core::Stream;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Chunk size of reader parts
+const READ_CHUNK_SIZE: usize = 65_536;
+
+/// `multipart/form-data` body
+///
+/// Body is sent with `Content-Length` header if sizes of all parts are
+/// known, otherwise chunked transfer encoding is used.
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Form::new()
+    }
+}
+
+impl Form {
+    /// Create empty form with random boundary.
+    pub fn new() -> Form {
+        let boundary = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .collect();
+        Form {
+            boundary,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Boundary of parts.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Add text field.
+    pub fn text<N, V>(self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.part(name, Part::text(value))
+    }
+
+    /// Add part.
+    pub fn part<N: Into<String>>(mut self, name: N, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Value of `Content-Type` header of request.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Convert form to request body.
+    pub fn into_body(self) -> Body {
+        let mut chunks = VecDeque::with_capacity(self.parts.len() * 3 + 1);
+        let mut size = Some(0u64);
+
+        for (name, part) in self.parts {
+            let head = part.head(&self.boundary, &name);
+            let part_size = match part.body.size() {
+                BodySize::None | BodySize::Empty => Some(0),
+                BodySize::Sized(size) => Some(size as u64),
+                BodySize::Sized64(size) => Some(size),
+                BodySize::Stream => part.size,
+            };
+            size = match (size, part_size) {
+                (Some(size), Some(part_size)) => {
+                    Some(size + head.len() as u64 + part_size + 2)
+                }
+                _ => None,
+            };
+
+            chunks.push_back(Chunk::Bytes(head));
+            chunks.push_back(Chunk::Body(part.body));
+            chunks.push_back(Chunk::Bytes(Bytes::from_static(b"\r\n")));
+        }
+
+        let tail = Bytes::from(format!("--{}--\r\n", self.boundary));
+        let size = match size {
+            Some(size) => BodySize::Sized64(size + tail.len() as u64),
+            None => BodySize::Stream,
+        };
+        chunks.push_back(Chunk::Bytes(tail));
+
+        Body::from_message(FormBody { chunks, size })
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+/// Part of multipart form
+pub struct Part {
+    body: Box<dyn MessageBody>,
+    /// Size of streaming body
+    size: Option<u64>,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+}
+
+impl Part {
+    fn new<B: MessageBody + 'static>(body: B, size: Option<u64>) -> Part {
+        Part {
+            body: Box::new(body),
+            size,
+            file_name: None,
+            content_type: None,
+        }
+    }
+
+    /// Create text part.
+    pub fn text<V: Into<String>>(value: V) -> Part {
+        Part::new(Bytes::from(value.into()), None)
+    }
+
+    /// Create part from bytes in memory.
+    pub fn bytes<B: Into<Bytes>>(bytes: B) -> Part {
+        Part::new(bytes.into(), None)
+    }
+
+    /// Create part from stream of unknown size, form is sent with chunked
+    /// transfer encoding.
+    pub fn stream<S, E>(stream: S) -> Part
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Part::new(BodyStream::new(stream), None)
+    }
+
+    /// Create part from stream of `size` bytes, i.e. content of file.
+    pub fn sized_stream<S, E>(size: u64, stream: S) -> Part
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Part::new(BodyStream::new(stream), Some(size))
+    }
+
+    /// Create part from async reader of unknown size.
+    pub fn reader<R>(reader: R) -> Part
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        Part::new(ReaderBody::new(reader, READ_CHUNK_SIZE), None)
+    }
+
+    /// Set file name of part.
+    ///
+    /// Content type of file parts is `application/octet-stream` unless it
+    /// is set with `content_type()`.
+    pub fn file_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
+
+    /// Set content type of part.
+    pub fn content_type(mut self, content_type: mime::Mime) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// Boundary and headers of part
+    fn head(&self, boundary: &str, name: &str) -> Bytes {
+        let mut head = BytesMut::with_capacity(boundary.len() + name.len() + 64);
+        head.put_slice(b"--");
+        head.put_slice(boundary.as_bytes());
+        head.put_slice(b"\r\ncontent-disposition: form-data; name=\"");
+        head.put_slice(escape(name).as_bytes());
+        head.put_slice(b"\"");
+        if let Some(ref file_name) = self.file_name {
+            head.put_slice(b"; filename=\"");
+            head.put_slice(escape(file_name).as_bytes());
+            head.put_slice(b"\"");
+        }
+        head.put_slice(b"\r\n");
+
+        let content_type = match (&self.content_type, &self.file_name) {
+            (Some(content_type), _) => Some(content_type.as_ref()),
+            (None, Some(_)) => Some("application/octet-stream"),
+            (None, None) => None,
+        };
+        if let Some(content_type) = content_type {
+            head.put_slice(b"content-type: ");
+            head.put_slice(content_type.as_bytes());
+            head.put_slice(b"\r\n");
+        }
+        head.put_slice(b"\r\n");
+        head.freeze()
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+/// Escape quotes and new lines of field and file names
+fn escape(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Body(Box<dyn MessageBody>),
+}
+
+struct FormBody {
+    chunks: VecDeque<Chunk>,
+    size: BodySize,
+}
+
+impl MessageBody for FormBody {
+    fn size(&self) -> BodySize {
+        self.size
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        loop {
+            match self.chunks.pop_front() {
+                Some(Chunk::Bytes(bytes)) => return Poll::Ready(Some(Ok(bytes))),
+                Some(Chunk::Body(mut body)) => match body.poll_next(cx) {
+                    Poll::Ready(None) => (),
+                    Poll::Ready(Some(Ok(ref chunk))) if chunk.is_empty() => {
+                        self.chunks.push_front(Chunk::Body(body))
+                    }
+                    res => {
+                        self.chunks.push_front(Chunk::Body(body));
+                        return res;
+                    }
+                },
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::poll_fn;
+    use futures::stream;
+
+    use super::*;
+
+    async fn read_body(mut body: Body) -> Bytes {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[actix_rt::test]
+    async fn test_form() {
+        let form = Form::new()
+            .text("a\"b", "text")
+            .part("file", Part::bytes("data").file_name("f.txt"));
+        let boundary = form.boundary().to_owned();
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+
+        let body = form.into_body();
+        let expected = format!(
+            "--{b}\r\ncontent-disposition: form-data; name=\"a%22b\"\r\n\r\ntext\r\n\
+             --{b}\r\ncontent-disposition: form-data; name=\"file\"; filename=\"f.txt\"\r\n\
+             content-type: application/octet-stream\r\n\r\ndata\r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(body.size(), BodySize::Sized64(expected.len() as u64));
+        assert_eq!(read_body(body).await, Bytes::from(expected));
+    }
+
+    #[actix_rt::test]
+    async fn test_stream_parts() {
+        let chunks = || {
+            stream::iter(vec![
+                Ok::<_, Error>(Bytes::from_static(b"ab")),
+                Ok(Bytes::from_static(b"cd")),
+            ])
+        };
+
+        let form = Form::new().part("s", Part::sized_stream(4, chunks()));
+        let body = form.into_body();
+        let size = match body.size() {
+            BodySize::Sized64(size) => size,
+            size => panic!("unexpected size: {:?}", size),
+        };
+        assert_eq!(read_body(body).await.len() as u64, size);
+
+        let form = Form::new().part("s", Part::stream(chunks()));
+        let body = form.into_body();
+        assert_eq!(body.size(), BodySize::Stream);
+        assert!(read_body(body).await.windows(4).any(|w| w == b"abcd"));
+    }
+}
@@ -0,0 +1,256 @@
+use std::convert::TryFrom;
+use std::future::Future;
+use std::net;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use actix_http::body::Body;
+use actix_http::client::SendRequestError;
+use actix_http::http::{header, Method, StatusCode, Uri};
+use actix_http::RequestHead;
+
+use crate::connect::Timeouts;
+use crate::response::ClientResponse;
+use crate::retry::{self, RetryPolicy};
+use crate::sender::{copy_head, replay_body};
+use crate::ClientConfig;
+
+/// Policy of following redirects, it is enabled per request with
+/// `ClientRequest::follow_redirects()`.
+///
+/// `301 Moved Permanently` and `302 Found` redirects of `POST` request,
+/// and `303 See Other` redirects of all requests except `HEAD` are
+/// followed with `GET` request without body. Method and body are preserved
+/// for other redirects, response is returned as is if streaming body has
+/// to be sent again.
+///
+/// `Authorization`, `Proxy-Authorization` and `Cookie` headers are removed
+/// on redirects to other origin.
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectPolicy {
+    max_redirects: usize,
+    same_origin: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::new(10)
+    }
+}
+
+impl RedirectPolicy {
+    /// Follow up to `max_redirects` redirects, request fails with
+    /// `SendRequestError::TooManyRedirects` error after that.
+    pub fn new(max_redirects: usize) -> Self {
+        RedirectPolicy {
+            max_redirects,
+            same_origin: false,
+        }
+    }
+
+    /// Follow only redirects to same scheme, host and port, redirect
+    /// response to other origin is returned as is.
+    pub fn same_origin(mut self) -> Self {
+        self.same_origin = true;
+        self
+    }
+}
+
+/// Send request, retry it and follow redirects of response according to
+/// policies. Cookies of client's store are sent and recorded on every
+/// redirect.
+pub(crate) fn send(
+    config: Rc<ClientConfig>,
+    mut head: RequestHead,
+    mut body: Body,
+    mut addr: Option<net::SocketAddr>,
+    timeouts: Timeouts,
+    redirect: Option<RedirectPolicy>,
+    retry: Option<RetryPolicy>,
+) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
+    Box::pin(async move {
+        let mut redirects = 0;
+        loop {
+            let replay = replay_body(&body);
+            let mut req = copy_head(&head, None);
+            if let Some(ref cookies) = config.cookies {
+                cookies.add_cookies(&mut req.headers, &req.uri);
+            }
+            let res =
+                retry::send(&config, req, body, addr, timeouts, retry.as_ref()).await?;
+            if let Some(ref cookies) = config.cookies {
+                cookies.store_cookies(res.headers(), &head.uri);
+            }
+
+            let policy = match redirect {
+                Some(policy) => policy,
+                None => return Ok(res),
+            };
+            let uri = match location(&res, &head.uri) {
+                Some(uri) => uri,
+                None => return Ok(res),
+            };
+            let same_origin = is_same_origin(&head.uri, &uri);
+            if policy.same_origin && !same_origin {
+                return Ok(res);
+            }
+
+            // method and body of redirected request
+            let status = res.status();
+            let change_method = match status {
+                StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => {
+                    head.method == Method::POST
+                }
+                StatusCode::SEE_OTHER => head.method != Method::HEAD,
+                _ => false,
+            };
+            body = if change_method {
+                head.method = Method::GET;
+                for name in &[
+                    header::CONTENT_TYPE,
+                    header::CONTENT_LENGTH,
+                    header::CONTENT_ENCODING,
+                    header::TRANSFER_ENCODING,
+                ] {
+                    head.headers.remove(name);
+                }
+                Body::Empty
+            } else {
+                match replay {
+                    Some(body) => body,
+                    None => return Ok(res),
+                }
+            };
+
+            if redirects >= policy.max_redirects {
+                return Err(SendRequestError::TooManyRedirects);
+            }
+            redirects += 1;
+
+            if !same_origin {
+                for name in &[
+                    header::AUTHORIZATION,
+                    header::PROXY_AUTHORIZATION,
+                    header::COOKIE,
+                    header::HOST,
+                ] {
+                    head.headers.remove(name);
+                }
+                // address is set for original host
+                addr = None;
+            }
+            head.uri = uri;
+        }
+    })
+}
+
+/// Uri of redirect response, it is resolved against uri of request
+fn location(res: &ClientResponse, base: &Uri) -> Option<Uri> {
+    match res.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => (),
+        _ => return None,
+    }
+
+    let location = res.headers().get(header::LOCATION)?.to_str().ok()?.trim();
+    let location = match location.find('#') {
+        Some(idx) => &location[..idx],
+        None => location,
+    };
+    let uri = resolve(base, location)?;
+    match uri.scheme_str() {
+        Some("http") | Some("https") => Some(uri),
+        _ => None,
+    }
+}
+
+/// Resolve reference against base uri
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    let scheme = base.scheme_str()?;
+    let authority = base.authority()?.as_str();
+
+    // absolute reference, relative references may contain `://` in query
+    if let Ok(uri) = Uri::try_from(location) {
+        if uri.scheme().is_some() {
+            return Some(uri);
+        }
+    }
+
+    let uri = if location.starts_with("//") {
+        format!("{}:{}", scheme, location)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else if location.starts_with('?') {
+        format!("{}://{}{}{}", scheme, authority, base.path(), location)
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map_or(0, |idx| idx + 1)];
+        format!("{}://{}{}{}", scheme, authority, dir, location)
+    };
+    Uri::try_from(uri).ok()
+}
+
+fn is_same_origin(a: &Uri, b: &Uri) -> bool {
+    fn port(uri: &Uri) -> u16 {
+        uri.port_u16().unwrap_or_else(|| match uri.scheme_str() {
+            Some("https") => 443,
+            _ => 80,
+        })
+    }
+
+    a.scheme_str() == b.scheme_str()
+        && a.host().map(|h| h.to_ascii_lowercase())
+            == b.host().map(|h| h.to_ascii_lowercase())
+        && port(a) == port(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base = Uri::from_static("http://example.com/a/b?q=1");
+        for (location, uri) in vec![
+            ("https://other.com/c", "https://other.com/c"),
+            ("//other.com/c", "http://other.com/c"),
+            ("/c/d", "http://example.com/c/d"),
+            ("c", "http://example.com/a/c"),
+            ("?q=2", "http://example.com/a/b?q=2"),
+            (
+                "/c?to=http://other.com/",
+                "http://example.com/c?to=http://other.com/",
+            ),
+            (
+                "c?to=https://other.com",
+                "http://example.com/a/c?to=https://other.com",
+            ),
+        ] {
+            assert_eq!(resolve(&base, location).unwrap(), uri, "{}", location);
+        }
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let uri = Uri::from_static("http://example.com/a");
+        assert!(is_same_origin(
+            &uri,
+            &Uri::from_static("http://Example.com:80/b")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("https://example.com/a")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("http://example.com:8080/")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("http://www.example.com/")
+        ));
+    }
+}
@@ -13,20 +13,26 @@ use actix_http::body::Body;
 use actix_http::cookie::{Cookie, CookieJar, USERINFO};
 use actix_http::http::header::{self, Header, IntoHeaderValue};
 use actix_http::http::{
-    uri, ConnectionType, Error as HttpError, HeaderMap, HeaderName, HeaderValue, Method,
-    Uri, Version,
+    ConnectionType, Error as HttpError, HeaderMap, HeaderName, HeaderValue, Method, Uri,
+    Version,
 };
 use actix_http::{Error, RequestHead};
 
+use crate::connect::Timeouts;
 use crate::error::{FreezeRequestError, InvalidUrl};
 use crate::frozen::FrozenClientRequest;
+use crate::multipart::Form;
+use crate::redirect::RedirectPolicy;
+use crate::retry::RetryPolicy;
 use crate::sender::{PrepForSendingError, RequestSender, SendClientRequest};
-use crate::ClientConfig;
+use crate::{ClientConfig, Proxy, ProxyOverride};
 
-#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+#[cfg(all(feature = "compress", feature = "brotli"))]
 const HTTPS_ENCODING: &str = "br, gzip, deflate";
-#[cfg(not(any(feature = "flate2-zlib", feature = "flate2-rust")))]
-const HTTPS_ENCODING: &str = "br";
+#[cfg(all(feature = "compress", not(feature = "brotli")))]
+const HTTPS_ENCODING: &str = "gzip, deflate";
+#[cfg(feature = "compress")]
+const HTTP_ENCODING: &str = "gzip, deflate";
 
 /// An HTTP Client request builder
 ///
@@ -56,7 +62,9 @@ pub struct ClientRequest {
     addr: Option<net::SocketAddr>,
     cookies: Option<CookieJar>,
     response_decompress: bool,
-    timeout: Option<Duration>,
+    timeouts: Timeouts,
+    redirect: Option<RedirectPolicy>,
+    retry: Option<RetryPolicy>,
     config: Rc<ClientConfig>,
 }
 
@@ -68,13 +76,15 @@ impl ClientRequest {
         <Uri as TryFrom<U>>::Error: Into<HttpError>,
     {
         ClientRequest {
-            config,
             head: RequestHead::default(),
             err: None,
             addr: None,
             cookies: None,
-            timeout: None,
-            response_decompress: true,
+            timeouts: Timeouts::default(),
+            redirect: None,
+            retry: config.retry,
+            response_decompress: config.decompress,
+            config,
         }
         .method(method)
         .uri(uri)
@@ -108,6 +118,38 @@ impl ClientRequest {
         self
     }
 
+    /// Send request through `proxy`, instead of proxy of client connector.
+    ///
+    /// Request is sent with connector that has default settings and is
+    /// shared by requests of client that use the same proxy.
+    ///
+    /// ```rust,no_run
+    /// use awc::{Client, Proxy};
+    ///
+    /// # async fn send() {
+    /// let res = Client::new()
+    ///     .get("http://www.rust-lang.org")
+    ///     .proxy(Proxy::new("http://proxy.local:3128".parse().unwrap()))
+    ///     .send()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn proxy(self, proxy: Proxy) -> Self {
+        self.head
+            .extensions_mut()
+            .insert(ProxyOverride(Some(proxy)));
+        self
+    }
+
+    /// Send request directly, bypassing proxy of client connector.
+    ///
+    /// Request is sent with connector that has default settings and is
+    /// shared by direct requests of client.
+    pub fn no_proxy(self) -> Self {
+        self.head.extensions_mut().insert(ProxyOverride(None));
+        self
+    }
+
     /// Set HTTP method of this request.
     #[inline]
     pub fn method(mut self, method: Method) -> Self {
@@ -337,6 +379,9 @@ impl ClientRequest {
     }
 
     /// Disable automatic decompress of response's body
+    ///
+    /// `Accept-Encoding` header is not set and body of response is returned
+    /// as is, i.e. raw bytes of compressed body.
     pub fn no_decompress(mut self) -> Self {
         self.response_decompress = false;
         self
@@ -344,10 +389,66 @@ impl ClientRequest {
 
     /// Set request timeout. Overrides client wide timeout setting.
     ///
-    /// Request timeout is the total time before a response must be received.
+    /// Request timeout is the total time before a response must be received,
+    /// request fails with `SendRequestError::Timeout` error after that.
     /// Default value is 5 seconds.
     pub fn timeout(mut self, timeout: Duration) -> Self {
-        self.timeout = Some(timeout);
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Set connect timeout. Overrides client wide connect timeout setting.
+    ///
+    /// Connect timeout is the time to establish connection to host, request
+    /// fails with `SendRequestError::Connect(ConnectError::Timeout)` error
+    /// after that. Connection is not limited by default except by connector's
+    /// own timeout.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Set response timeout. Overrides client wide response timeout setting.
+    ///
+    /// Response timeout is the time to send request and receive response head
+    /// once connection is established, request fails with
+    /// `SendRequestError::ResponseTimeout` error after that. Response
+    /// timeout is not set by default.
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.response = Some(timeout);
+        self
+    }
+
+    /// Follow redirects of response according to `policy`.
+    ///
+    /// Redirects are not followed by default.
+    ///
+    /// ```rust
+    /// use awc::RedirectPolicy;
+    ///
+    /// # async fn request() {
+    /// let res = awc::Client::new()
+    ///     .get("http://www.rust-lang.org")
+    ///     .follow_redirects(RedirectPolicy::new(5).same_origin())
+    ///     .send()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn follow_redirects(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = Some(policy);
+        self
+    }
+
+    /// Retry failed request according to `policy`. Overrides client wide
+    /// retry policy.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Do not retry failed request.
+    pub fn no_retry(mut self) -> Self {
+        self.retry = None;
         self
     }
 
@@ -410,7 +511,9 @@ impl ClientRequest {
             head: Rc::new(slf.head),
             addr: slf.addr,
             response_decompress: slf.response_decompress,
-            timeout: slf.timeout,
+            timeouts: slf.timeouts,
+            redirect: slf.redirect,
+            retry: slf.retry,
             config: slf.config,
         };
 
@@ -430,8 +533,10 @@ impl ClientRequest {
         RequestSender::Owned(slf.head).send_body(
             slf.addr,
             slf.response_decompress,
-            slf.timeout,
-            slf.config.as_ref(),
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
             body,
         )
     }
@@ -446,8 +551,10 @@ impl ClientRequest {
         RequestSender::Owned(slf.head).send_json(
             slf.addr,
             slf.response_decompress,
-            slf.timeout,
-            slf.config.as_ref(),
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
             value,
         )
     }
@@ -464,12 +571,32 @@ impl ClientRequest {
         RequestSender::Owned(slf.head).send_form(
             slf.addr,
             slf.response_decompress,
-            slf.timeout,
-            slf.config.as_ref(),
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
             value,
         )
     }
 
+    /// Set a `multipart/form-data` body and generate `ClientRequest`.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(e) => return e.into(),
+        };
+
+        RequestSender::Owned(slf.head).send_multipart(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
+            form,
+        )
+    }
+
     /// Set an streaming body and generate `ClientRequest`.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
@@ -484,8 +611,36 @@ impl ClientRequest {
         RequestSender::Owned(slf.head).send_stream(
             slf.addr,
             slf.response_decompress,
-            slf.timeout,
-            slf.config.as_ref(),
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
+            stream,
+        )
+    }
+
+    /// Set an streaming body of known size and generate `ClientRequest`.
+    ///
+    /// Body is sent with `Content-Length` header instead of chunked
+    /// transfer encoding, stream must produce exactly `size` bytes.
+    pub fn send_sized_stream<S, E>(self, size: u64, stream: S) -> SendClientRequest
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(e) => return e.into(),
+        };
+
+        RequestSender::Owned(slf.head).send_sized_stream(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
+            size,
             stream,
         )
     }
@@ -500,8 +655,10 @@ impl ClientRequest {
         RequestSender::Owned(slf.head).send(
             slf.addr,
             slf.response_decompress,
-            slf.timeout,
-            slf.config.as_ref(),
+            slf.timeouts,
+            slf.redirect,
+            slf.retry,
+            &slf.config,
         )
     }
 
@@ -539,28 +696,23 @@ impl ClientRequest {
             );
         }
 
-        let mut slf = self;
-
-        if slf.response_decompress {
-            let https = slf
-                .head
-                .uri
-                .scheme()
-                .map(|s| s == &uri::Scheme::HTTPS)
-                .unwrap_or(true);
-
-            if https {
-                slf = slf.set_header_if_none(header::ACCEPT_ENCODING, HTTPS_ENCODING)
-            } else {
-                #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
-                {
-                    slf =
-                        slf.set_header_if_none(header::ACCEPT_ENCODING, "gzip, deflate")
-                }
-            };
+        // brotli is advertised for secure connections only
+        #[cfg(feature = "compress")]
+        {
+            if self.response_decompress {
+                let https = self
+                    .head
+                    .uri
+                    .scheme()
+                    .map(|s| s == &actix_http::http::uri::Scheme::HTTPS)
+                    .unwrap_or(true);
+
+                let encoding = if https { HTTPS_ENCODING } else { HTTP_ENCODING };
+                self = self.set_header_if_none(header::ACCEPT_ENCODING, encoding);
+            }
         }
 
-        Ok(slf)
+        Ok(self)
     }
 }
 
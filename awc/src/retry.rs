@@ -0,0 +1,214 @@
+use std::net;
+use std::time::{Duration, Instant};
+
+use actix_http::body::Body;
+use actix_http::client::SendRequestError;
+use actix_http::http::{header, Method};
+use actix_http::RequestHead;
+use actix_rt::time::delay_for;
+use rand::Rng;
+
+use crate::connect::Timeouts;
+use crate::response::ClientResponse;
+use crate::sender::{copy_head, replay_body};
+use crate::ClientConfig;
+
+/// Policy of retrying failed requests.
+///
+/// Requests are retried if connection to host fails. Requests with
+/// idempotent methods, `GET`, `HEAD`, `PUT`, `DELETE`, `OPTIONS` and
+/// `TRACE`, are retried on errors of sending request and reading response
+/// head as well, including response timeouts. Requests with streaming body
+/// are not retried.
+///
+/// Delay between attempts grows exponentially, it is chosen randomly from
+/// upper half of current backoff so clients do not retry at the same time.
+/// Total request timeout covers all attempts, connect and response timeouts
+/// apply to every attempt.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use awc::{Client, RetryPolicy};
+///
+/// let client = Client::build()
+///     .retry(
+///         RetryPolicy::new(3)
+///             .backoff(Duration::from_millis(50), Duration::from_secs(1))
+///             .server_errors(),
+///     )
+///     .finish();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+    deadline: Option<Duration>,
+    server_errors: bool,
+    any_method: bool,
+}
+
+impl RetryPolicy {
+    /// Retry request up to `max_retries` times.
+    ///
+    /// Initial backoff is 100 milliseconds, max backoff is 10 seconds.
+    pub fn new(max_retries: usize) -> Self {
+        RetryPolicy {
+            max_retries,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            deadline: None,
+            server_errors: false,
+            any_method: false,
+        }
+    }
+
+    /// Set initial and max backoff, backoff is doubled after every
+    /// attempt.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set overall deadline, request is not retried if delay of next
+    /// attempt ends after the deadline.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Retry responses with `5xx` status, `Retry-After` header of response
+    /// overrides backoff. Last response is returned if all attempts fail.
+    pub fn server_errors(mut self) -> Self {
+        self.server_errors = true;
+        self
+    }
+
+    /// Retry requests with non-idempotent methods as well.
+    pub fn any_method(mut self) -> Self {
+        self.any_method = true;
+        self
+    }
+
+    /// Delay before retry, `attempt` starts from zero
+    fn delay(&self, attempt: usize) -> Duration {
+        let backoff = 1u32
+            .checked_shl(attempt as u32)
+            .and_then(|factor| self.backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+
+        // jitter
+        let half = backoff / 2;
+        let nanos = (backoff - half).as_nanos() as u64;
+        half + Duration::from_nanos(rand::thread_rng().gen_range(0, nanos + 1))
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::HEAD
+        | Method::PUT
+        | Method::DELETE
+        | Method::OPTIONS
+        | Method::TRACE => true,
+        _ => false,
+    }
+}
+
+/// Delay of `Retry-After` header in seconds
+fn retry_after(res: &ClientResponse) -> Option<Duration> {
+    res.headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Send request, request is retried according to policy.
+pub(crate) async fn send(
+    config: &ClientConfig,
+    head: RequestHead,
+    mut body: Body,
+    addr: Option<net::SocketAddr>,
+    timeouts: Timeouts,
+    policy: Option<&RetryPolicy>,
+) -> Result<ClientResponse, SendRequestError> {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => {
+            let fut = config
+                .connector(&head)
+                .send_request(head, body, addr, timeouts);
+            return fut.await;
+        }
+    };
+
+    let start = Instant::now();
+    let idempotent = policy.any_method || is_idempotent(&head.method);
+    let mut attempt = 0;
+    loop {
+        let replay = replay_body(&body);
+        let fut = config.connector(&head).send_request(
+            copy_head(&head, None),
+            body,
+            addr,
+            timeouts,
+        );
+        let res = fut.await;
+
+        let retry = match res {
+            Err(SendRequestError::Connect(_)) => true,
+            Err(SendRequestError::Send(_))
+            | Err(SendRequestError::Response(_))
+            | Err(SendRequestError::H2(_))
+            | Err(SendRequestError::ResponseTimeout) => idempotent,
+            Ok(ref res) => {
+                policy.server_errors && idempotent && res.status().is_server_error()
+            }
+            Err(_) => false,
+        };
+        body = match replay {
+            Some(body) if retry && attempt < policy.max_retries => body,
+            _ => return res,
+        };
+
+        let delay = match res {
+            Ok(ref res) => retry_after(res).map_or_else(
+                || policy.delay(attempt),
+                |delay| delay.min(policy.max_backoff),
+            ),
+            Err(_) => policy.delay(attempt),
+        };
+        if let Some(deadline) = policy.deadline {
+            if start.elapsed() + delay > deadline {
+                return res;
+            }
+        }
+        drop(res);
+
+        delay_for(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(10)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1));
+        for (attempt, max) in vec![(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000)] {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_millis(max / 2), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(max), "{:?}", delay);
+        }
+        assert!(policy.delay(100) <= Duration::from_secs(1));
+    }
+}
@@ -11,9 +11,11 @@ use futures_core::{Future, Stream};
 use serde::Serialize;
 use serde_json;
 
-use actix_http::body::{Body, BodyStream};
+use actix_http::body::{Body, BodySize, BodyStream, MessageBody};
 use actix_http::http::header::{self, IntoHeaderValue};
-use actix_http::http::{Error as HttpError, HeaderMap, HeaderName};
+use actix_http::http::{
+    ConnectionType, Error as HttpError, HeaderMap, HeaderName, HeaderValue,
+};
 use actix_http::{Error, RequestHead};
 
 #[cfg(feature = "compress")]
@@ -23,9 +25,14 @@ use actix_http::http::header::ContentEncoding;
 #[cfg(feature = "compress")]
 use actix_http::{Payload, PayloadStream};
 
+use crate::connect::Timeouts;
 use crate::error::{FreezeRequestError, InvalidUrl, SendRequestError};
+use crate::middleware;
+use crate::multipart::Form;
+use crate::redirect::RedirectPolicy;
 use crate::response::ClientResponse;
-use crate::ClientConfig;
+use crate::retry::RetryPolicy;
+use crate::{ClientConfig, ProxyOverride};
 
 #[derive(Debug, From)]
 pub(crate) enum PrepForSendingError {
@@ -58,6 +65,7 @@ pub enum SendClientRequest {
         Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
         Option<Delay>,
         bool,
+        Option<u64>,
     ),
     Err(Option<SendRequestError>),
 }
@@ -67,9 +75,10 @@ impl SendClientRequest {
         send: Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
         response_decompress: bool,
         timeout: Option<Duration>,
+        decompress_limit: Option<u64>,
     ) -> SendClientRequest {
         let delay = timeout.map(delay_for);
-        SendClientRequest::Fut(send, delay, response_decompress)
+        SendClientRequest::Fut(send, delay, response_decompress, decompress_limit)
     }
 }
 
@@ -82,7 +91,7 @@ impl Future for SendClientRequest {
         let this = self.get_mut();
 
         match this {
-            SendClientRequest::Fut(send, delay, response_decompress) => {
+            SendClientRequest::Fut(send, delay, response_decompress, limit) => {
                 if delay.is_some() {
                     match Pin::new(delay.as_mut().unwrap()).poll(cx) {
                         Poll::Pending => (),
@@ -90,13 +99,15 @@ impl Future for SendClientRequest {
                     }
                 }
 
+                let limit = *limit;
                 let res = futures_core::ready!(Pin::new(send).poll(cx)).map(|res| {
                     res.map_body(|head, payload| {
                         if *response_decompress {
-                            Payload::Stream(Decoder::from_headers(
-                                payload,
-                                &head.headers,
-                            ))
+                            let decoder = Decoder::from_headers(payload, &head.headers);
+                            Payload::Stream(match limit {
+                                Some(limit) => decoder.limit(limit),
+                                None => decoder,
+                            })
                         } else {
                             Payload::Stream(Decoder::new(
                                 payload,
@@ -123,7 +134,7 @@ impl Future for SendClientRequest {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
         match this {
-            SendClientRequest::Fut(send, delay, _) => {
+            SendClientRequest::Fut(send, delay, _, _) => {
                 if delay.is_some() {
                     match Pin::new(delay.as_mut().unwrap()).poll(cx) {
                         Poll::Pending => (),
@@ -175,28 +186,60 @@ impl RequestSender {
         self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
-        timeout: Option<Duration>,
-        config: &ClientConfig,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
         body: B,
     ) -> SendClientRequest
     where
         B: Into<Body>,
     {
-        let mut connector = config.connector.borrow_mut();
-
-        let fut = match self {
-            RequestSender::Owned(head) => {
-                connector.send_request(head, body.into(), addr)
-            }
-            RequestSender::Rc(head, extra_headers) => {
-                connector.send_request_extra(head, extra_headers, body.into(), addr)
+        let timeouts = timeouts.or(&config.timeouts);
+        let fut = if redirect.is_none()
+            && retry.is_none()
+            && config.cookies.is_none()
+            && config.middleware.is_empty()
+        {
+            let mut connector = config.connector(match self {
+                RequestSender::Owned(ref head) => head,
+                RequestSender::Rc(ref head, _) => head,
+            });
+            match self {
+                RequestSender::Owned(head) => {
+                    connector.send_request(head, body.into(), addr, timeouts)
+                }
+                RequestSender::Rc(head, extra_headers) => connector.send_request_extra(
+                    head,
+                    extra_headers,
+                    body.into(),
+                    addr,
+                    timeouts,
+                ),
             }
+        } else {
+            let head = match self {
+                RequestSender::Owned(head) => head,
+                RequestSender::Rc(head, extra_headers) => {
+                    copy_head(&head, extra_headers.as_ref())
+                }
+            };
+            middleware::send(
+                config.clone(),
+                head,
+                body.into(),
+                addr,
+                timeouts,
+                redirect,
+                retry,
+            )
         };
 
         SendClientRequest::new(
             fut,
             response_decompress,
-            timeout.or_else(|| config.timeout),
+            timeouts.total,
+            config.decompress_limit,
         )
     }
 
@@ -204,8 +247,10 @@ impl RequestSender {
         mut self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
-        timeout: Option<Duration>,
-        config: &ClientConfig,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
         let body = match serde_json::to_string(value) {
@@ -221,7 +266,9 @@ impl RequestSender {
         self.send_body(
             addr,
             response_decompress,
-            timeout,
+            timeouts,
+            redirect,
+            retry,
             config,
             Body::Bytes(Bytes::from(body)),
         )
@@ -231,8 +278,10 @@ impl RequestSender {
         mut self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
-        timeout: Option<Duration>,
-        config: &ClientConfig,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
         let body = match serde_urlencoded::to_string(value) {
@@ -251,18 +300,57 @@ impl RequestSender {
         self.send_body(
             addr,
             response_decompress,
-            timeout,
+            timeouts,
+            redirect,
+            retry,
             config,
             Body::Bytes(Bytes::from(body)),
         )
     }
 
+    pub(crate) fn send_multipart(
+        mut self,
+        addr: Option<net::SocketAddr>,
+        response_decompress: bool,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
+        form: Form,
+    ) -> SendClientRequest {
+        // content type contains boundary of form
+        let content_type = match HeaderValue::from_str(&form.content_type()) {
+            Ok(value) => value,
+            Err(e) => return HttpError::from(e).into(),
+        };
+        match self {
+            RequestSender::Owned(ref mut head) => {
+                head.headers.insert(header::CONTENT_TYPE, content_type)
+            }
+            RequestSender::Rc(_, ref mut extra_headers) => extra_headers
+                .get_or_insert(HeaderMap::new())
+                .insert(header::CONTENT_TYPE, content_type),
+        }
+
+        self.send_body(
+            addr,
+            response_decompress,
+            timeouts,
+            redirect,
+            retry,
+            config,
+            form.into_body(),
+        )
+    }
+
     pub(crate) fn send_stream<S, E>(
         self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
-        timeout: Option<Duration>,
-        config: &ClientConfig,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
         stream: S,
     ) -> SendClientRequest
     where
@@ -272,20 +360,61 @@ impl RequestSender {
         self.send_body(
             addr,
             response_decompress,
-            timeout,
+            timeouts,
+            redirect,
+            retry,
             config,
             Body::from_message(BodyStream::new(stream)),
         )
     }
 
+    pub(crate) fn send_sized_stream<S, E>(
+        self,
+        addr: Option<net::SocketAddr>,
+        response_decompress: bool,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
+        size: u64,
+        stream: S,
+    ) -> SendClientRequest
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        self.send_body(
+            addr,
+            response_decompress,
+            timeouts,
+            redirect,
+            retry,
+            config,
+            Body::from_message(SizedBody {
+                size,
+                body: BodyStream::new(stream),
+            }),
+        )
+    }
+
     pub(crate) fn send(
         self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
-        timeout: Option<Duration>,
-        config: &ClientConfig,
+        timeouts: Timeouts,
+        redirect: Option<RedirectPolicy>,
+        retry: Option<RetryPolicy>,
+        config: &Rc<ClientConfig>,
     ) -> SendClientRequest {
-        self.send_body(addr, response_decompress, timeout, config, Body::Empty)
+        self.send_body(
+            addr,
+            response_decompress,
+            timeouts,
+            redirect,
+            retry,
+            config,
+            Body::Empty,
+        )
     }
 
     fn set_header_if_none<V>(
@@ -323,3 +452,59 @@ impl RequestSender {
         Ok(())
     }
 }
+
+/// Streaming body of known size, it is sent with `Content-Length` header
+struct SizedBody<S, E> {
+    size: u64,
+    body: BodyStream<S, E>,
+}
+
+impl<S, E> MessageBody for SizedBody<S, E>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<Error>,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Sized64(self.size)
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        self.body.poll_next(cx)
+    }
+}
+
+/// Copy of request head, extra headers override headers of head
+pub(crate) fn copy_head(
+    head: &RequestHead,
+    extra_headers: Option<&HeaderMap>,
+) -> RequestHead {
+    let mut copy = RequestHead::default();
+    copy.uri = head.uri.clone();
+    copy.method = head.method.clone();
+    copy.version = head.version;
+    copy.headers = head.headers.clone();
+    if let Some(extra_headers) = extra_headers {
+        for (name, value) in extra_headers.iter() {
+            copy.headers.insert(name.clone(), value.clone());
+        }
+    }
+    copy.peer_addr = head.peer_addr;
+    if let Some(proxy) = head.extensions().get::<ProxyOverride>() {
+        copy.extensions_mut().insert(proxy.clone());
+    }
+    copy.set_camel_case_headers(head.camel_case_headers());
+    if head.connection_type() == ConnectionType::Close {
+        copy.set_connection_type(ConnectionType::Close);
+    }
+    copy
+}
+
+/// Copy of body that could be sent again
+pub(crate) fn replay_body(body: &Body) -> Option<Body> {
+    match body {
+        Body::None => Some(Body::None),
+        Body::Empty => Some(Body::Empty),
+        Body::Bytes(bytes) => Some(Body::Bytes(bytes.clone())),
+        Body::Message(_) => None,
+    }
+}
@@ -1,4 +1,5 @@
 //! Websockets client
+use std::cell::Cell;
 use std::convert::TryFrom;
 use std::fmt::Write as FmtWrite;
 use std::net::SocketAddr;
@@ -12,6 +13,8 @@ use actix_rt::time::timeout;
 use percent_encoding::percent_encode;
 
 use actix_http::cookie::USERINFO;
+#[cfg(feature = "compress")]
+pub use actix_http::ws::DeflateConfig;
 pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message};
 
 use crate::connect::BoxedSocket;
@@ -34,6 +37,8 @@ pub struct WebsocketsRequest {
     addr: Option<SocketAddr>,
     max_size: usize,
     server_mode: bool,
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateConfig>,
     cookies: Option<CookieJar>,
     config: Rc<ClientConfig>,
 }
@@ -64,6 +69,8 @@ impl WebsocketsRequest {
             protocols: None,
             max_size: 65_536,
             server_mode: false,
+            #[cfg(feature = "compress")]
+            deflate: None,
             cookies: None,
         }
     }
@@ -130,6 +137,14 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Offer `permessage-deflate` extension, messages are compressed if
+    /// server accepts it.
+    #[cfg(feature = "compress")]
+    pub fn deflate(mut self, config: DeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+
     /// Append a header.
     ///
     /// Header gets appended to existing header.
@@ -238,10 +253,13 @@ impl WebsocketsRequest {
         }
 
         if !self.head.headers.contains_key(header::HOST) {
-            self.head.headers.insert(
-                header::HOST,
-                HeaderValue::from_str(uri.host().unwrap()).unwrap(),
-            );
+            let host = match uri.port_u16() {
+                None | Some(80) | Some(443) => uri.host().unwrap().to_owned(),
+                Some(port) => format!("{}:{}", uri.host().unwrap(), port),
+            };
+            self.head
+                .headers
+                .insert(header::HOST, HeaderValue::from_str(&host).unwrap());
         }
 
         // set cookies
@@ -279,6 +297,15 @@ impl WebsocketsRequest {
             );
         }
 
+        #[cfg(feature = "compress")]
+        {
+            if let Some(ref deflate) = self.deflate {
+                self.head
+                    .headers
+                    .insert(header::SEC_WEBSOCKET_EXTENSIONS, deflate.offer());
+            }
+        }
+
         // Generate a random key for the `Sec-WebSocket-Key` header.
         // a base64-encoded (see Section 4 of [RFC4648]) value that,
         // when decoded, is 16 bytes in length (RFC 6455)
@@ -294,14 +321,10 @@ impl WebsocketsRequest {
         let max_size = self.max_size;
         let server_mode = self.server_mode;
 
-        let fut = self
-            .config
-            .connector
-            .borrow_mut()
-            .open_tunnel(head, self.addr);
+        let fut = self.config.connector(&head).open_tunnel(head, self.addr);
 
         // set request timeout
-        let (head, framed) = if let Some(to) = self.config.timeout {
+        let (head, framed) = if let Some(to) = self.config.timeouts.total {
             timeout(to, fut)
                 .await
                 .map_err(|_| SendRequestError::Timeout)
@@ -364,16 +387,29 @@ impl WebsocketsRequest {
             return Err(WsClientError::MissingWebSocketAcceptHeader);
         };
 
+        let codec = if server_mode {
+            ws::Codec::new().max_size(max_size)
+        } else {
+            ws::Codec::new().max_size(max_size).client_mode()
+        };
+
+        #[cfg(feature = "compress")]
+        let codec = match self.deflate {
+            Some(ref deflate) => match deflate.accept_response(&head.headers)? {
+                Some(context) => codec.deflate(context),
+                None => codec,
+            },
+            None => codec,
+        };
+
+        // codec with deflate context is not `Copy`, while `map_codec()`
+        // takes `Fn` closure
+        let codec = Cell::new(Some(codec));
+
         // response and ws framed
         Ok((
             ClientResponse::new(head, Payload::None),
-            framed.map_codec(|_| {
-                if server_mode {
-                    ws::Codec::new().max_size(max_size)
-                } else {
-                    ws::Codec::new().max_size(max_size).client_mode()
-                }
-            }),
+            framed.map_codec(|_| codec.take().unwrap()),
         ))
     }
 }
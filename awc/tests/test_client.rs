@@ -88,6 +88,77 @@ async fn test_json() {
     assert!(response.status().is_success());
 }
 
+#[actix_rt::test]
+async fn test_multipart() {
+    use awc::multipart::{Form, Part};
+
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| {
+                let content_type = req.headers().get(header::CONTENT_TYPE).unwrap();
+                let boundary = content_type
+                    .to_str()
+                    .unwrap()
+                    .trim_start_matches("multipart/form-data; boundary=");
+                let body = String::from_utf8(body.to_vec()).unwrap();
+                let chunked = req.headers().contains_key(header::TRANSFER_ENCODING);
+
+                if body.starts_with(&format!("--{}\r\n", boundary))
+                    && body.ends_with(&format!("--{}--\r\n", boundary))
+                    && body.contains("name=\"file\"; filename=\"a.txt\"")
+                    && body.contains("\r\n\r\nfile content")
+                {
+                    HttpResponse::Ok().body(if chunked { "chunked" } else { "sized" })
+                } else {
+                    HttpResponse::BadRequest().finish()
+                }
+            },
+        )))
+    });
+
+    let stream = || {
+        futures::stream::iter(vec![
+            Ok::<_, Error>(Bytes::from_static(b"file ")),
+            Ok(Bytes::from_static(b"content")),
+        ])
+    };
+
+    let form = Form::new()
+        .text("name", "value")
+        .part("file", Part::sized_stream(12, stream()).file_name("a.txt"));
+    let mut res = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"sized"));
+
+    let form = Form::new().part("file", Part::stream(stream()).file_name("a.txt"));
+    let mut res = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"chunked"));
+}
+
+#[actix_rt::test]
+async fn test_sized_stream() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| {
+                let length = req.headers().get(header::CONTENT_LENGTH).unwrap();
+                if length == "12" && body == Bytes::from_static(b"file content") {
+                    HttpResponse::Ok()
+                } else {
+                    HttpResponse::BadRequest()
+                }
+            },
+        )))
+    });
+
+    let stream = futures::stream::iter(vec![
+        Ok::<_, Error>(Bytes::from_static(b"file ")),
+        Ok(Bytes::from_static(b"content")),
+    ]);
+    let res = srv.post("/").send_sized_stream(12, stream).await.unwrap();
+    assert!(res.status().is_success());
+}
+
 #[actix_rt::test]
 async fn test_form() {
     let srv = test::start(|| {
@@ -158,6 +229,238 @@ async fn test_timeout_override() {
     }
 }
 
+#[actix_rt::test]
+async fn test_response_timeout() {
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|| {
+            async {
+                actix_rt::time::delay_for(Duration::from_millis(200)).await;
+                Ok::<_, Error>(HttpResponse::Ok().body(STR))
+            }
+        })))
+    });
+
+    let client = awc::Client::build()
+        .connect_timeout(Duration::from_secs(5))
+        .response_timeout(Duration::from_millis(50))
+        .finish();
+    match client.get(srv.url("/")).send().await {
+        Err(SendRequestError::ResponseTimeout) => (),
+        _ => panic!(),
+    }
+
+    // timeout of request overrides client wide setting
+    let request = client
+        .get(srv.url("/"))
+        .response_timeout(Duration::from_secs(5))
+        .send();
+    assert!(request.await.unwrap().status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_host_resolver() {
+    use std::io;
+    use std::net::IpAddr;
+
+    use actix_http::client::{HostResolver, Resolve};
+    use futures::future::{FutureExt, LocalBoxFuture};
+
+    struct LocalResolver;
+
+    impl Resolve for LocalResolver {
+        fn lookup(
+            &self,
+            host: &str,
+        ) -> LocalBoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+            assert_eq!(host, "resolved.test");
+            ok(vec!["127.0.0.1".parse().unwrap()]).boxed_local()
+        }
+    }
+
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|req: HttpRequest| {
+            let host = req.headers().get(header::HOST).unwrap();
+            HttpResponse::Ok().body(host.to_str().unwrap().to_owned())
+        })))
+    });
+    let port = srv.addr().port();
+
+    let connector = awc::Connector::new()
+        .resolver(
+            HostResolver::new()
+                // connection to first address is refused
+                .host(
+                    "static.test",
+                    vec!["127.0.0.2".parse().unwrap(), "127.0.0.1".parse().unwrap()],
+                )
+                .resolver(LocalResolver),
+        )
+        .finish();
+    let client = awc::Client::build().connector(connector).finish();
+
+    for host in &["static.test", "resolved.test"] {
+        let url = format!("http://{}:{}/", host, port);
+        let mut res = client.get(url).send().await.unwrap();
+        assert!(res.status().is_success());
+        let body = res.body().await.unwrap();
+        assert_eq!(body, Bytes::from(format!("{}:{}", host, port)));
+    }
+}
+
+#[actix_rt::test]
+async fn test_h2_prior_knowledge() {
+    use actix_web::http::Version;
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2c(true)
+            .finish(|req: actix_http::Request| {
+                let version = format!("{:?}", req.version());
+                ok::<_, ()>(actix_http::Response::Ok().body(version))
+            })
+            .tcp()
+    });
+
+    let metrics = awc::PoolMetrics::default();
+    let connector = awc::Connector::new()
+        .h2_prior_knowledge()
+        .metrics(metrics.clone())
+        .finish();
+    let client = awc::Client::build().connector(connector).finish();
+
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"HTTP/2.0"));
+
+    // concurrent requests are multiplexed over one connection
+    let requests = (0..5).map(|_| client.get(srv.url("/")).send());
+    for res in futures::future::join_all(requests).await {
+        assert!(res.unwrap().status().is_success());
+    }
+    assert_eq!(metrics.opened(), 1);
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn test_uds() {
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir()
+        .join(format!("awc-test-{}.sock", rand::thread_rng().gen::<u64>()));
+    let listener = UnixListener::bind(&path).unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = Vec::new();
+        let mut chunk = [0; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).unwrap();
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let body = if buf.starts_with(b"GET /containers/json HTTP/1.1\r\n") {
+            "[]"
+        } else {
+            "unexpected request"
+        };
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+
+    let client = awc::Client::build().uds(&path).finish();
+    let mut res = client
+        .get("http://localhost/containers/json")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"[]"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[actix_rt::test]
+async fn test_client_middleware() {
+    use actix_http::body::Body;
+    use actix_http::RequestHead;
+    use awc::{ClientResponse, Middleware, Next};
+    use futures::future::{ready, FutureExt, LocalBoxFuture};
+
+    struct Tag(Arc<AtomicUsize>);
+
+    impl Middleware for Tag {
+        fn handle(
+            &self,
+            mut head: RequestHead,
+            body: Body,
+            next: Next,
+        ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+            head.headers.insert(
+                header::HeaderName::from_static("x-tag"),
+                header::HeaderValue::from_static("1"),
+            );
+            let counter = self.0.clone();
+            next.send(head, body)
+                .map(move |res| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    res
+                })
+                .boxed_local()
+        }
+    }
+
+    struct Cache;
+
+    impl Middleware for Cache {
+        fn handle(
+            &self,
+            head: RequestHead,
+            body: Body,
+            next: Next,
+        ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+            if head.uri.path() == "/cached" {
+                ready(Ok(awc::test::TestResponse::default()
+                    .set_payload("cached")
+                    .finish()))
+                .boxed_local()
+            } else {
+                next.send(head, body)
+            }
+        }
+    }
+
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|req: HttpRequest| {
+            if req.headers().contains_key("x-tag") {
+                HttpResponse::Ok().body(STR)
+            } else {
+                HttpResponse::BadRequest().finish()
+            }
+        })))
+    });
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let client = awc::Client::build()
+        .wrap(Tag(counter.clone()))
+        .wrap(Cache)
+        .finish();
+
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(STR.as_ref()));
+
+    // request is resolved by middleware without sending it
+    let mut res = client.get(srv.url("/cached")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"cached"));
+    assert_eq!(counter.load(Ordering::Relaxed), 2);
+}
+
 #[actix_rt::test]
 async fn test_connection_reuse() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -195,6 +498,52 @@ async fn test_connection_reuse() {
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
 
+#[actix_rt::test]
+async fn test_connection_limit_per_host() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::new(map_config(
+                App::new()
+                    .service(web::resource("/").route(web::to(|| HttpResponse::Ok()))),
+                |_| AppConfig::default(),
+            ))
+            .tcp(),
+        )
+    });
+
+    let metrics = awc::PoolMetrics::default();
+    let client = awc::Client::build()
+        .connector(
+            awc::Connector::new()
+                .limit_per_host(1)
+                .metrics(metrics.clone())
+                .finish(),
+        )
+        .finish();
+
+    // concurrent requests wait for single connection
+    let (res1, res2) = futures::join!(
+        client.get(srv.url("/")).send(),
+        client.get(srv.url("/")).send()
+    );
+    assert!(res1.unwrap().status().is_success());
+    assert!(res2.unwrap().status().is_success());
+
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.opened(), 1);
+    assert_eq!(metrics.reused(), 1);
+    assert_eq!(metrics.active(), 0);
+    assert_eq!(metrics.idle(), 1);
+}
+
 #[actix_rt::test]
 async fn test_connection_force_close() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -386,6 +735,153 @@ async fn test_with_query_parameter() {
     assert!(res.status().is_success());
 }
 
+#[actix_rt::test]
+async fn test_follow_redirects() {
+    let srv = test::start(|| {
+        App::new()
+            .service(web::resource("/echo").to(|req: HttpRequest, body: Bytes| {
+                HttpResponse::Ok().body(format!(
+                    "{} {} {}",
+                    req.method(),
+                    String::from_utf8_lossy(&body),
+                    req.headers().contains_key(header::AUTHORIZATION)
+                ))
+            }))
+            .service(web::resource("/found").to(|| {
+                HttpResponse::Found()
+                    .header(header::LOCATION, "echo")
+                    .finish()
+            }))
+            .service(web::resource("/temporary").to(|| {
+                HttpResponse::TemporaryRedirect()
+                    .header(header::LOCATION, "/echo")
+                    .finish()
+            }))
+            .service(web::resource("/loop").to(|| {
+                HttpResponse::Found()
+                    .header(header::LOCATION, "/loop")
+                    .finish()
+            }))
+            .service(web::resource("/other").to(|req: HttpRequest| {
+                let host = req.headers().get(header::HOST).unwrap().to_str().unwrap();
+                let location =
+                    format!("http://{}/echo", host.replace("localhost", "127.0.0.1"));
+                HttpResponse::Found()
+                    .header(header::LOCATION, location)
+                    .finish()
+            }))
+    });
+    let policy = awc::RedirectPolicy::new(3);
+
+    // redirects are not followed by default
+    let res = srv.get("/found").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 302);
+
+    // POST is changed to GET
+    let mut res = srv
+        .post("/found")
+        .follow_redirects(policy)
+        .send_body("data")
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"GET  false"));
+
+    // method and body are preserved
+    let mut res = srv
+        .post("/temporary")
+        .follow_redirects(policy)
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(
+        res.body().await.unwrap(),
+        Bytes::from_static(b"POST data false")
+    );
+
+    let res = srv.get("/loop").follow_redirects(policy).send().await;
+    match res {
+        Err(SendRequestError::TooManyRedirects) => (),
+        _ => panic!("redirect loop is not detected"),
+    }
+
+    // credentials are not sent to other origin
+    let mut res = srv
+        .get("/found")
+        .bearer_auth("token")
+        .follow_redirects(policy)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"GET  true"));
+    let mut res = srv
+        .get("/other")
+        .bearer_auth("token")
+        .follow_redirects(policy)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"GET  false"));
+
+    let res = srv
+        .get("/other")
+        .follow_redirects(policy.same_origin())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 302);
+}
+
+#[actix_rt::test]
+async fn test_retry() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test::start(move || {
+        let num2 = num2.clone();
+        App::new().service(web::resource("/").to(move || {
+            if num2.fetch_add(1, Ordering::Relaxed) % 3 < 2 {
+                HttpResponse::ServiceUnavailable().finish()
+            } else {
+                HttpResponse::Ok().finish()
+            }
+        }))
+    });
+    let policy = awc::RetryPolicy::new(2)
+        .backoff(Duration::from_millis(10), Duration::from_millis(50))
+        .server_errors();
+
+    let res = srv.get("/").retry(policy).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 3);
+
+    // non-idempotent requests are not retried
+    let res = srv.post("/").retry(policy).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+    assert_eq!(num.load(Ordering::Relaxed), 4);
+
+    let res = srv
+        .post("/")
+        .retry(policy.any_method())
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 6);
+
+    // connection errors are retried
+    let client = awc::Client::build()
+        .retry(
+            awc::RetryPolicy::new(2)
+                .backoff(Duration::from_millis(10), Duration::from_millis(10)),
+        )
+        .finish();
+    let start = std::time::Instant::now();
+    let res = client.get("http://127.0.0.1:1/").send().await;
+    assert!(res.is_err());
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
+
 #[actix_rt::test]
 async fn test_no_decompress() {
     let srv = test::start(|| {
@@ -453,6 +949,50 @@ async fn test_client_gzip_encoding() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[actix_rt::test]
+async fn test_client_decompress_limit() {
+    use awc::error::PayloadError;
+
+    let srv = test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|req: HttpRequest| {
+            let accept = req
+                .headers()
+                .get(header::ACCEPT_ENCODING)
+                .map(|val| val.to_str().unwrap().to_owned())
+                .unwrap_or_default();
+
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(&[b'a'; 65_536]).unwrap();
+            let data = e.finish().unwrap();
+
+            HttpResponse::Ok()
+                .header("content-encoding", "gzip")
+                .header("x-accept-encoding", accept)
+                .body(data)
+        })))
+    });
+
+    let client = awc::Client::build().decompress_limit(1024).finish();
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert_eq!(
+        res.headers().get("x-accept-encoding").unwrap(),
+        "gzip, deflate"
+    );
+    match res.body().await {
+        Err(PayloadError::Overflow) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // raw body of response
+    let client = awc::Client::build().disable_decompress().finish();
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert_eq!(res.headers().get("x-accept-encoding").unwrap(), "");
+    let bytes = res.body().await.unwrap();
+    let mut dec = Vec::new();
+    GzDecoder::new(&bytes[..]).read_to_end(&mut dec).unwrap();
+    assert_eq!(dec.len(), 65_536);
+}
+
 #[actix_rt::test]
 async fn test_client_gzip_encoding_large() {
     let srv = test::start(|| {
@@ -760,6 +1300,55 @@ async fn test_client_cookie_handling() {
 //     assert_eq!(bytes, Bytes::from_static(b"welcome!"));
 // }
 
+#[actix_rt::test]
+async fn test_client_cookie_store() {
+    let srv = test::start(|| {
+        App::new()
+            .route(
+                "/login",
+                web::to(|| {
+                    HttpResponse::Found()
+                        .cookie(Cookie::build("session", "secret").path("/").finish())
+                        .header(header::LOCATION, "/api/user")
+                        .finish()
+                }),
+            )
+            .route(
+                "/api/user",
+                web::to(|req: HttpRequest| match req.cookie("session") {
+                    Some(ref cookie) if cookie.value() == "secret" => {
+                        HttpResponse::Ok().finish()
+                    }
+                    _ => HttpResponse::Unauthorized().finish(),
+                }),
+            )
+    });
+
+    let store = awc::CookieStore::new();
+    let client = awc::Client::build().cookie_store(store.clone()).finish();
+
+    let res = client.get(srv.url("/api/user")).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+
+    // cookie is stored before redirect is followed
+    let res = client
+        .get(srv.url("/login"))
+        .follow_redirects(awc::RedirectPolicy::default())
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let uri = srv.url("/api/user").parse().unwrap();
+    assert_eq!(store.cookies(&uri)[0].value(), "secret");
+    let res = client.get(srv.url("/api/user")).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    store.clear();
+    let res = client.get(srv.url("/api/user")).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
 #[actix_rt::test]
 async fn client_basic_auth() {
     let srv = test::start(|| {
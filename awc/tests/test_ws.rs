@@ -1,6 +1,7 @@
 use std::io;
 
 use actix_codec::Framed;
+use actix_http::http::header;
 use actix_http::{body::BodySize, h1, ws, Error, HttpService, Request, Response};
 use actix_http_test::test_server;
 use bytes::Bytes;
@@ -68,3 +69,269 @@ async fn test_simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[actix_rt::test]
+async fn test_deflate() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| {
+                async move {
+                    let mut res = ws::handshake_response(req.head());
+                    let mut codec = ws::Codec::new();
+                    if let Some((extension, context)) =
+                        ws::DeflateConfig::new().negotiate(req.head())
+                    {
+                        res.header(header::SEC_WEBSOCKET_EXTENSIONS, extension);
+                        codec = codec.deflate(context);
+                    }
+                    // send handshake response
+                    framed
+                        .send(h1::Message::Item((
+                            res.finish().drop_body(),
+                            BodySize::None,
+                        )))
+                        .await?;
+
+                    // start websocket service
+                    let framed = framed.into_framed(codec);
+                    ws::Dispatcher::with(framed, ws_service).await
+                }
+            })
+            .finish(|_| ok::<_, Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    // client service
+    let (res, mut framed) = awc::Client::new()
+        .ws(srv.url("/"))
+        .deflate(
+            ws::DeflateConfig::new()
+                .no_context_takeover(true)
+                .peer_no_context_takeover(true),
+        )
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers().get(header::SEC_WEBSOCKET_EXTENSIONS).unwrap(),
+        "permessage-deflate; server_no_context_takeover"
+    );
+
+    let text = "compressed text ".repeat(1000);
+    for _ in 0..2 {
+        framed.send(ws::Message::Text(text.clone())).await.unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from(text.clone())));
+    }
+
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+}
+
+/// Minimal HTTP proxy, tunnels single connection to `target`
+fn start_proxy(target: std::net::SocketAddr) -> std::net::SocketAddr {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        let mut head = Vec::new();
+        let mut byte = [0u8];
+        while !head.ends_with(b"\r\n\r\n") {
+            client.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap();
+        let request_line = format!("CONNECT localhost:{} HTTP/1.1\r\n", target.port());
+        assert!(head.starts_with(&request_line));
+        // user:pass
+        assert!(head.contains("proxy-authorization: Basic dXNlcjpwYXNz\r\n"));
+
+        let server = std::net::TcpStream::connect(target).unwrap();
+        client
+            .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+            .unwrap();
+        forward(client, server);
+    });
+    addr
+}
+
+/// Minimal SOCKS5 proxy, tunnels single connection to `target`
+fn start_socks5_proxy(target: std::net::SocketAddr) -> std::net::SocketAddr {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+
+        // username/password authentication
+        let mut buf = [0u8; 4];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 2, 0, 2]);
+        client.write_all(&[5, 2]).unwrap();
+        let mut buf = [0u8; 11];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"\x01\x04user\x04pass");
+        client.write_all(&[1, 0]).unwrap();
+
+        // connect to domain name
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 1, 0, 3, 9]);
+        let mut buf = [0u8; 11];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..9], b"localhost");
+        assert_eq!(u16::from_be_bytes([buf[9], buf[10]]), target.port());
+
+        let server = std::net::TcpStream::connect(target).unwrap();
+        client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).unwrap();
+        forward(client, server);
+    });
+    addr
+}
+
+fn forward(mut client: std::net::TcpStream, mut server: std::net::TcpStream) {
+    let mut client2 = client.try_clone().unwrap();
+    let mut server2 = server.try_clone().unwrap();
+    std::thread::spawn(move || {
+        let _ = io::copy(&mut client2, &mut server2);
+    });
+    let _ = io::copy(&mut server, &mut client);
+}
+
+#[actix_rt::test]
+async fn test_proxy() {
+    use actix_http::client::{Connector, Proxy};
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| {
+                async move {
+                    let res = ws::handshake_response(req.head()).finish();
+                    framed
+                        .send(h1::Message::Item((res.drop_body(), BodySize::None)))
+                        .await?;
+
+                    let framed = framed.into_framed(ws::Codec::new());
+                    ws::Dispatcher::with(framed, ws_service).await
+                }
+            })
+            .finish(|_| ok::<_, Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    let proxy = format!("http://{}", start_proxy(srv.addr()));
+    let client = awc::Client::build()
+        .connector(
+            Connector::new()
+                .proxy(
+                    Proxy::new(proxy.parse().unwrap())
+                        .basic_auth("user", Some("pass")),
+                )
+                .finish(),
+        )
+        .finish();
+
+    let (_, mut framed) = client.ws(srv.url("/")).connect().await.unwrap();
+    framed
+        .send(ws::Message::Text("text".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+}
+
+#[actix_rt::test]
+async fn test_socks5_proxy() {
+    use actix_http::client::{Connector, Proxy};
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| {
+                async move {
+                    let res = ws::handshake_response(req.head()).finish();
+                    framed
+                        .send(h1::Message::Item((res.drop_body(), BodySize::None)))
+                        .await?;
+
+                    let framed = framed.into_framed(ws::Codec::new());
+                    ws::Dispatcher::with(framed, ws_service).await
+                }
+            })
+            .finish(|_| ok::<_, Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    let proxy = format!("socks5h://user:pass@{}", start_socks5_proxy(srv.addr()));
+    let client = awc::Client::build()
+        .connector(
+            Connector::new()
+                .proxy(Proxy::new(proxy.parse().unwrap()))
+                .finish(),
+        )
+        .finish();
+
+    let (_, mut framed) = client.ws(srv.url("/")).connect().await.unwrap();
+    framed
+        .send(ws::Message::Text("text".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+
+    // hosts in no-proxy list are connected directly
+    let client = awc::Client::build()
+        .connector(
+            Connector::new()
+                .proxy(
+                    Proxy::new("socks5://127.0.0.1:1".parse().unwrap())
+                        .no_proxy(vec!["localhost"]),
+                )
+                .finish(),
+        )
+        .finish();
+    let (_, mut framed) = client.ws(srv.url("/")).connect().await.unwrap();
+    framed.send(ws::Message::Ping("p".into())).await.unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Pong(Bytes::from_static(b"p")));
+}
+
+#[actix_rt::test]
+async fn test_request_proxy() {
+    use actix_http::client::Connector;
+    use awc::Proxy;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .finish(|_| ok::<_, Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let client = awc::Client::build()
+        .connector(
+            Connector::new()
+                .proxy(Proxy::new("http://127.0.0.1:1".parse().unwrap()))
+                .finish(),
+        )
+        .finish();
+    assert!(client.get(srv.url("/")).send().await.is_err());
+
+    // proxy of request is used instead of proxy of connector
+    let proxy = format!("http://user:pass@{}", start_proxy(srv.addr()));
+    let res = client
+        .get(srv.url("/"))
+        .proxy(Proxy::new(proxy.parse().unwrap()))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let res = client.get(srv.url("/")).no_proxy().send().await.unwrap();
+    assert!(res.status().is_success());
+}
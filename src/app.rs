@@ -10,11 +10,10 @@ use actix_service::boxed::{self, BoxServiceFactory};
 use actix_service::{
     apply, apply_fn_factory, IntoServiceFactory, ServiceFactory, Transform,
 };
-use futures::future::{FutureExt, LocalBoxFuture};
 
 use crate::app_service::{AppEntry, AppInit, AppRoutingFactory};
-use crate::config::ServiceConfig;
-use crate::data::{Data, DataFactory};
+use crate::config::{RouterOptions, ServiceConfig, TrailingSlash};
+use crate::data::{fn_data_factory, Data, DataFactory, FnDataFactory};
 use crate::dev::ResourceDef;
 use crate::error::Error;
 use crate::resource::Resource;
@@ -25,8 +24,6 @@ use crate::service::{
 };
 
 type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
-type FnDataFactory =
-    Box<dyn Fn() -> LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>;
 
 /// Application builder - structure that follows the builder pattern
 /// for building application instances.
@@ -39,6 +36,7 @@ pub struct App<T, B> {
     data_factories: Vec<FnDataFactory>,
     external: Vec<ResourceDef>,
     extensions: Extensions,
+    router_options: RouterOptions,
     _t: PhantomData<B>,
 }
 
@@ -55,6 +53,7 @@ impl App<AppEntry, Body> {
             factory_ref: fref,
             external: Vec::new(),
             extensions: Extensions::new(),
+            router_options: RouterOptions::default(),
             _t: PhantomData,
         }
     }
@@ -109,6 +108,32 @@ where
     /// Set application data factory. This function is
     /// similar to `.data()` but it accepts data factory. Data object get
     /// constructed asynchronously during application initialization.
+    ///
+    /// Http server constructs application for each worker, so factory runs
+    /// once per worker and worker starts accepting requests only after all
+    /// data is constructed. This is useful for state that requires async
+    /// setup, i.e. connection pool or service discovery lookup. If factory
+    /// returns error, error is logged and application fails to start.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// struct Pool {
+    ///     url: String,
+    /// }
+    ///
+    /// async fn connect(url: &str) -> Result<Pool, std::io::Error> {
+    ///     Ok(Pool { url: url.to_owned() })
+    /// }
+    ///
+    /// async fn index(pool: web::Data<Pool>) -> HttpResponse {
+    ///     HttpResponse::Ok().body(pool.url.clone())
+    /// }
+    ///
+    /// let app = App::new()
+    ///     .data_factory(|| connect("postgres://localhost/app"))
+    ///     .route("/", web::get().to(index));
+    /// ```
     pub fn data_factory<F, Out, D, E>(mut self, data: F) -> Self
     where
         F: Fn() -> Out + 'static,
@@ -116,24 +141,7 @@ where
         D: 'static,
         E: std::fmt::Debug,
     {
-        self.data_factories.push(Box::new(move || {
-            {
-                let fut = data();
-                async move {
-                    match fut.await {
-                        Err(e) => {
-                            log::error!("Can not construct data instance: {:?}", e);
-                            Err(())
-                        }
-                        Ok(data) => {
-                            let data: Box<dyn DataFactory> = Box::new(Data::new(data));
-                            Ok(data)
-                        }
-                    }
-                }
-            }
-            .boxed_local()
-        }));
+        self.data_factories.push(fn_data_factory(data));
         self
     }
 
@@ -182,6 +190,7 @@ where
         let mut cfg = ServiceConfig::new();
         f(&mut cfg);
         self.data.extend(cfg.data);
+        self.data_factories.extend(cfg.data_factories);
         self.services.extend(cfg.services);
         self.external.extend(cfg.external);
         self
@@ -285,6 +294,47 @@ where
         self
     }
 
+    /// Set handling of request paths that differ from registered paths only
+    /// by trailing slash, paths have to match exactly by default.
+    ///
+    /// Scopes use the same handling unless it is set for the scope.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .trailing_slash(web::TrailingSlash::Rewrite)
+    ///         .service(web::resource("/index.html").to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.router_options.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Match request paths case-insensitively, disabled by default.
+    ///
+    /// Request path is converted to lower case if it does not match any
+    /// route as is, so routes have to be registered in lower case. Handlers
+    /// get original path and path segments.
+    ///
+    /// Scopes use the same matching unless it is set for the scope.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .case_insensitive(true)
+    ///         .service(web::resource("/users/{name}").to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        self.router_options.case_insensitive = enabled;
+        self
+    }
+
     /// Register an external resource.
     ///
     /// External resources are useful for URL generation purposes only
@@ -380,6 +430,7 @@ where
             factory_ref: self.factory_ref,
             external: self.external,
             extensions: self.extensions,
+            router_options: self.router_options,
             _t: PhantomData,
         }
     }
@@ -442,6 +493,7 @@ where
             factory_ref: self.factory_ref,
             external: self.external,
             extensions: self.extensions,
+            router_options: self.router_options,
             _t: PhantomData,
         }
     }
@@ -468,6 +520,7 @@ where
             default: self.default,
             factory_ref: self.factory_ref,
             extensions: RefCell::new(Some(self.extensions)),
+            router_options: self.router_options,
         }
     }
 }
@@ -681,4 +734,151 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
     }
+
+    #[actix_rt::test]
+    async fn test_trailing_slash() {
+        let mut srv = init_service(
+            App::new()
+                .trailing_slash(TrailingSlash::Redirect(StatusCode::MOVED_PERMANENTLY))
+                .route("/test", web::get().to(|| HttpResponse::Ok()))
+                .route("/test2/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test/?id=1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            HeaderValue::from_static("/test?id=1")
+        );
+
+        let req = TestRequest::with_uri("/test2").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            HeaderValue::from_static("/test2/")
+        );
+
+        let req = TestRequest::with_uri("/test3/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let mut srv = init_service(
+            App::new()
+                .trailing_slash(TrailingSlash::Rewrite)
+                .route(
+                    "/test",
+                    web::get().to(|req: HttpRequest| {
+                        HttpResponse::Ok().body(req.path().to_owned())
+                    }),
+                )
+                .service(
+                    web::scope("/app")
+                        .route("/test/", web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::scope("/strict")
+                        .trailing_slash(TrailingSlash::Strict)
+                        .route("/test", web::get().to(|| HttpResponse::Ok())),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        assert_eq!(body, Bytes::from_static(b"/test"));
+
+        let req = TestRequest::with_uri("/app/test").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/strict/test/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_case_insensitive() {
+        let mut srv = init_service(
+            App::new()
+                .case_insensitive(true)
+                .route(
+                    "/users/{name}",
+                    web::get().to(|req: HttpRequest| {
+                        HttpResponse::Ok().body(format!(
+                            "{} {}",
+                            req.path(),
+                            &req.match_info()["name"]
+                        ))
+                    }),
+                )
+                .service(
+                    web::scope("/app")
+                        .route("/test", web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::scope("/exact")
+                        .case_insensitive(false)
+                        .route("/test", web::get().to(|| HttpResponse::Ok())),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/Users/John").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        assert_eq!(body, Bytes::from_static(b"/Users/John John"));
+
+        let req = TestRequest::with_uri("/APP/Test").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/exact/Test").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_priority() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/{tail:.*}")
+                        .priority(-1)
+                        .to(|| HttpResponse::NoContent()),
+                )
+                .service(web::resource("/test").to(|| HttpResponse::Ok()))
+                .service(
+                    web::scope("/app")
+                        .service(web::resource("/{name}").to(|| HttpResponse::Ok()))
+                        .service(
+                            web::resource("/admin")
+                                .priority(1)
+                                .to(|| HttpResponse::Created()),
+                        ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/other").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let req = TestRequest::with_uri("/app/admin").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_uri("/app/user").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
 }
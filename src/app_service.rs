@@ -11,10 +11,10 @@ use actix_service::boxed::{self, BoxService, BoxServiceFactory};
 use actix_service::{fn_service, Service, ServiceFactory};
 use futures::future::{ok, FutureExt, LocalBoxFuture};
 
-use crate::config::{AppConfig, AppService};
-use crate::data::DataFactory;
+use crate::config::{AppConfig, AppService, RouterOptions};
+use crate::data::{DataFactory, FnDataFactory};
 use crate::error::Error;
-use crate::guard::Guard;
+use crate::guard::{check_guards, Guard};
 use crate::request::{HttpRequest, HttpRequestPool};
 use crate::rmap::ResourceMap;
 use crate::service::{AppServiceFactory, ServiceRequest, ServiceResponse};
@@ -23,8 +23,6 @@ type Guards = Vec<Box<dyn Guard>>;
 type HttpService = BoxService<ServiceRequest, ServiceResponse, Error>;
 type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
 type BoxResponse = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
-type FnDataFactory =
-    Box<dyn Fn() -> LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>;
 
 /// Service factory to convert `Request` to a `ServiceRequest<S>`.
 /// It also executes data factories.
@@ -46,6 +44,7 @@ where
     pub(crate) default: Option<Rc<HttpNewService>>,
     pub(crate) factory_ref: Rc<RefCell<Option<AppRoutingFactory>>>,
     pub(crate) external: RefCell<Vec<ResourceDef>>,
+    pub(crate) router_options: RouterOptions,
 }
 
 impl<T, B> ServiceFactory for AppInit<T, B>
@@ -76,6 +75,7 @@ where
 
         // App config
         let mut config = AppService::new(config, default.clone(), self.data.clone());
+        config.set_router_options(self.router_options);
 
         // register services
         std::mem::replace(&mut *self.services.borrow_mut(), Vec::new())
@@ -83,12 +83,14 @@ where
             .for_each(|mut srv| srv.register(&mut config));
 
         let mut rmap = ResourceMap::new(ResourceDef::new(""));
+        rmap.set_operations(config.take_operations());
 
         let (config, services) = config.into_services();
 
         // complete pipeline creation
         *self.factory_ref.borrow_mut() = Some(AppRoutingFactory {
             default,
+            options: self.router_options,
             services: Rc::new(
                 services
                     .into_iter()
@@ -194,7 +196,7 @@ where
                 rmap: this.rmap.clone(),
                 config: this.config.clone(),
                 data: Rc::new(data),
-                pool: HttpRequestPool::create(),
+                pool: HttpRequestPool::create(this.config.request_pool().clone()),
             }))
         } else {
             Poll::Pending
@@ -236,7 +238,8 @@ where
             inner.path.reset();
             inner.head = head;
             inner.payload = payload;
-            inner.app_data = self.data.clone();
+            inner.app_data.clear();
+            inner.app_data.push(self.data.clone());
             req
         } else {
             HttpRequest::new(
@@ -265,6 +268,7 @@ where
 pub struct AppRoutingFactory {
     services: Rc<Vec<(ResourceDef, HttpNewService, RefCell<Option<Guards>>)>>,
     default: Rc<HttpNewService>,
+    options: RouterOptions,
 }
 
 impl ServiceFactory for AppRoutingFactory {
@@ -291,6 +295,7 @@ impl ServiceFactory for AppRoutingFactory {
                 .collect(),
             default: None,
             default_fut: Some(self.default.new_service(())),
+            options: self.options,
         }
     }
 }
@@ -303,6 +308,7 @@ pub struct AppRoutingFactoryResponse {
     fut: Vec<CreateAppRoutingItem>,
     default: Option<HttpService>,
     default_fut: Option<LocalBoxFuture<'static, Result<HttpService, ()>>>,
+    options: RouterOptions,
 }
 
 enum CreateAppRoutingItem {
@@ -365,6 +371,7 @@ impl Future for AppRoutingFactoryResponse {
                 ready: None,
                 router: router.finish(),
                 default: self.default.take(),
+                options: self.options,
             }))
         } else {
             Poll::Pending
@@ -376,6 +383,7 @@ pub struct AppRouting {
     router: Router<HttpService, Guards>,
     ready: Option<(ServiceRequest, ResourceInfo)>,
     default: Option<HttpService>,
+    options: RouterOptions,
 }
 
 impl Service for AppRouting {
@@ -393,20 +401,33 @@ impl Service for AppRouting {
     }
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
-        let res = self.router.recognize_mut_checked(&mut req, |req, guards| {
-            if let Some(ref guards) = guards {
-                for f in guards {
-                    if !f.check(req.head()) {
-                        return false;
+        if let Some((srv, _info)) =
+            self.router.recognize_mut_checked(&mut req, check_guards)
+        {
+            return srv.call(req);
+        }
+
+        // try alternative paths, i.e. with trailing slash added or removed
+        let alts = self.options.alternatives(&req.head().uri);
+        if !alts.is_empty() {
+            for alt in alts {
+                req.match_info_mut().get_mut().update(&alt.matching);
+                if let Some((srv, _info)) =
+                    self.router.recognize_mut_checked(&mut req, check_guards)
+                {
+                    req.match_info_mut().get_mut().update(&alt.uri);
+                    if let Some(res) = alt.redirect(self.options.trailing_slash) {
+                        return ok(req.into_response(res)).boxed_local();
                     }
+                    req.head_mut().uri = alt.uri;
+                    return srv.call(req);
                 }
             }
-            true
-        });
+            let uri = req.head().uri.clone();
+            req.match_info_mut().get_mut().update(&uri);
+        }
 
-        if let Some((srv, _info)) = res {
-            srv.call(req)
-        } else if let Some(ref mut default) = self.default {
+        if let Some(ref mut default) = self.default {
             default.call(req)
         } else {
             let req = req.into_parts().0;
@@ -1,13 +1,17 @@
+use std::future::Future;
 use std::net::SocketAddr;
 use std::rc::Rc;
 
-use actix_http::Extensions;
+use actix_http::http::{header, Method, StatusCode, Uri};
+use actix_http::{Extensions, PoolConfig, Response};
 use actix_router::ResourceDef;
 use actix_service::{boxed, IntoServiceFactory, ServiceFactory};
 
-use crate::data::{Data, DataFactory};
+use crate::data::{fn_data_factory, Data, DataFactory, FnDataFactory};
 use crate::error::Error;
 use crate::guard::Guard;
+use crate::info::TrustedProxies;
+use crate::openapi::{Operation, PathOperation};
 use crate::resource::Resource;
 use crate::rmap::ResourceMap;
 use crate::route::Route;
@@ -15,6 +19,7 @@ use crate::service::{
     AppServiceFactory, HttpServiceFactory, ServiceFactoryWrapper, ServiceRequest,
     ServiceResponse,
 };
+use crate::shutdown::ShutdownSignal;
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpNewService =
@@ -30,8 +35,11 @@ pub struct AppService {
         HttpNewService,
         Option<Guards>,
         Option<Rc<ResourceMap>>,
+        i32,
     )>,
     service_data: Rc<Vec<Box<dyn DataFactory>>>,
+    router_options: RouterOptions,
+    operations: Vec<PathOperation>,
 }
 
 impl AppService {
@@ -47,6 +55,8 @@ impl AppService {
             service_data,
             root: true,
             services: Vec::new(),
+            router_options: RouterOptions::default(),
+            operations: Vec::new(),
         }
     }
 
@@ -66,7 +76,15 @@ impl AppService {
             Option<Rc<ResourceMap>>,
         )>,
     ) {
-        (self.config, self.services)
+        // higher priority services are matched first, registration order is
+        // kept otherwise
+        let mut services = self.services;
+        services.sort_by_key(|item| std::cmp::Reverse(item.4));
+        let services = services
+            .into_iter()
+            .map(|(rdef, srv, guards, nested, _)| (rdef, srv, guards, nested))
+            .collect();
+        (self.config, services)
     }
 
     pub(crate) fn clone_config(&self) -> Self {
@@ -76,6 +94,8 @@ impl AppService {
             services: Vec::new(),
             root: false,
             service_data: self.service_data.clone(),
+            router_options: self.router_options,
+            operations: Vec::new(),
         }
     }
 
@@ -84,6 +104,34 @@ impl AppService {
         &self.config
     }
 
+    /// Path matching options of parent router
+    pub(crate) fn router_options(&self) -> RouterOptions {
+        self.router_options
+    }
+
+    pub(crate) fn set_router_options(&mut self, options: RouterOptions) {
+        self.router_options = options;
+    }
+
+    /// Register operation of route, path is relative to current scope
+    pub(crate) fn register_operation(
+        &mut self,
+        path: String,
+        method: Method,
+        operation: Operation,
+    ) {
+        self.operations.push(PathOperation {
+            path,
+            method,
+            operation,
+        });
+    }
+
+    /// Operations of registered routes
+    pub(crate) fn take_operations(&mut self) -> Vec<PathOperation> {
+        std::mem::replace(&mut self.operations, Vec::new())
+    }
+
     /// Default resource
     pub fn default_service(&self) -> Rc<HttpNewService> {
         self.default.clone()
@@ -113,16 +161,175 @@ impl AppService {
                 Error = Error,
                 InitError = (),
             > + 'static,
+    {
+        self.register_service_with_priority(rdef, guards, factory, nested, 0)
+    }
+
+    /// Register http service with matching priority, services with higher
+    /// priority are matched first
+    pub(crate) fn register_service_with_priority<F, S>(
+        &mut self,
+        rdef: ResourceDef,
+        guards: Option<Vec<Box<dyn Guard>>>,
+        factory: F,
+        nested: Option<Rc<ResourceMap>>,
+        priority: i32,
+    ) where
+        F: IntoServiceFactory<S>,
+        S: ServiceFactory<
+                Config = (),
+                Request = ServiceRequest,
+                Response = ServiceResponse,
+                Error = Error,
+                InitError = (),
+            > + 'static,
     {
         self.services.push((
             rdef,
             boxed::factory(factory.into_factory()),
             guards,
             nested,
+            priority,
         ));
     }
 }
 
+/// Handling of request paths that differ from registered paths only by
+/// trailing slash.
+///
+/// ```rust
+/// use actix_web::{http, web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .trailing_slash(web::TrailingSlash::Redirect(
+///             http::StatusCode::MOVED_PERMANENTLY,
+///         ))
+///         .service(web::resource("/index.html").to(|| HttpResponse::Ok()))
+///         .service(
+///             web::scope("/api")
+///                 .trailing_slash(web::TrailingSlash::Rewrite)
+///                 .service(web::resource("/users/").to(|| HttpResponse::Ok())),
+///         );
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrailingSlash {
+    /// Match paths exactly as registered, default
+    Strict,
+    /// Redirect to path with trailing slash added or removed with specified
+    /// status code, i.e. *301 Moved Permanently* or *307 Temporary Redirect*
+    Redirect(StatusCode),
+    /// Handle request as if path had trailing slash added or removed,
+    /// request uri is updated
+    Rewrite,
+}
+
+impl Default for TrailingSlash {
+    fn default() -> Self {
+        TrailingSlash::Strict
+    }
+}
+
+/// Path matching options of app or scope router
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RouterOptions {
+    pub(crate) trailing_slash: TrailingSlash,
+    pub(crate) case_insensitive: bool,
+}
+
+impl RouterOptions {
+    /// Alternative uris to match if request uri does not match any route
+    pub(crate) fn alternatives(&self, uri: &Uri) -> Vec<Alternative> {
+        let mut alts = Vec::new();
+        if self.case_insensitive {
+            if let Some(matching) = lowercase_path(uri) {
+                alts.push(Alternative {
+                    matching,
+                    uri: uri.clone(),
+                    redirect: false,
+                });
+            }
+        }
+
+        let path = uri.path();
+        if self.trailing_slash != TrailingSlash::Strict && path.len() > 1 {
+            let path = if path.ends_with('/') {
+                path[..path.len() - 1].to_owned()
+            } else {
+                format!("{}/", path)
+            };
+            if let Some(alt) = replace_path(uri, &path) {
+                if self.case_insensitive {
+                    if let Some(matching) = lowercase_path(&alt) {
+                        alts.push(Alternative {
+                            matching,
+                            uri: alt.clone(),
+                            redirect: true,
+                        });
+                    }
+                }
+                alts.push(Alternative {
+                    matching: alt.clone(),
+                    uri: alt,
+                    redirect: true,
+                });
+            }
+        }
+        alts
+    }
+}
+
+/// Alternative request uri
+pub(crate) struct Alternative {
+    /// Uri for matching with router
+    pub(crate) matching: Uri,
+    /// Uri for handling request, differs from matching uri by case only
+    pub(crate) uri: Uri,
+    /// Trailing slash differs from request uri
+    redirect: bool,
+}
+
+impl Alternative {
+    /// Redirect response to alternative uri
+    pub(crate) fn redirect(&self, trailing_slash: TrailingSlash) -> Option<Response> {
+        match trailing_slash {
+            TrailingSlash::Redirect(status) if self.redirect => {
+                let location = self
+                    .uri
+                    .path_and_query()
+                    .map(|pq| pq.as_str())
+                    .unwrap_or("/");
+                Some(
+                    Response::build(status)
+                        .header(header::LOCATION, location)
+                        .finish(),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Uri with path in lower case, `None` if path is in lower case already
+fn lowercase_path(uri: &Uri) -> Option<Uri> {
+    let path = uri.path();
+    if path.bytes().any(|b| b.is_ascii_uppercase()) {
+        replace_path(uri, &path.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+fn replace_path(uri: &Uri, path: &str) -> Option<Uri> {
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(match uri.query() {
+        Some(q) => format!("{}?{}", path, q).parse().ok()?,
+        None => path.parse().ok()?,
+    });
+    Uri::from_parts(parts).ok()
+}
+
 #[derive(Clone)]
 pub struct AppConfig(Rc<AppConfigInner>);
 
@@ -130,11 +337,42 @@ struct AppConfigInner {
     secure: bool,
     host: String,
     addr: SocketAddr,
+    trusted_proxies: Option<TrustedProxies>,
+    shutdown: Option<ShutdownSignal>,
+    request_pool: PoolConfig,
 }
 
 impl AppConfig {
-    pub(crate) fn new(secure: bool, addr: SocketAddr, host: String) -> Self {
-        AppConfig(Rc::new(AppConfigInner { secure, addr, host }))
+    pub(crate) fn new(
+        secure: bool,
+        addr: SocketAddr,
+        host: String,
+        trusted_proxies: Option<TrustedProxies>,
+    ) -> Self {
+        AppConfig(Rc::new(AppConfigInner {
+            secure,
+            addr,
+            host,
+            trusted_proxies,
+            shutdown: None,
+            request_pool: PoolConfig::default(),
+        }))
+    }
+
+    /// Notify requests of server shutdown with signal
+    pub(crate) fn with_shutdown(mut self, signal: ShutdownSignal) -> Self {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .shutdown = Some(signal);
+        self
+    }
+
+    /// Set pool configuration of `HttpRequest` objects
+    pub(crate) fn with_request_pool(mut self, config: PoolConfig) -> Self {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .request_pool = config;
+        self
     }
 
     /// Server host name.
@@ -157,6 +395,23 @@ impl AppConfig {
     pub fn local_addr(&self) -> SocketAddr {
         self.0.addr
     }
+
+    /// Proxies allowed to set forwarding headers, `None` if all peers are
+    /// trusted
+    pub fn trusted_proxies(&self) -> Option<&TrustedProxies> {
+        self.0.trusted_proxies.as_ref()
+    }
+
+    /// Shutdown state of server, `None` for services that are not run by
+    /// `HttpServer`
+    pub(crate) fn shutdown(&self) -> Option<&ShutdownSignal> {
+        self.0.shutdown.as_ref()
+    }
+
+    /// Pool configuration of `HttpRequest` objects
+    pub(crate) fn request_pool(&self) -> &PoolConfig {
+        &self.0.request_pool
+    }
 }
 
 impl Default for AppConfig {
@@ -165,6 +420,7 @@ impl Default for AppConfig {
             false,
             "127.0.0.1:8080".parse().unwrap(),
             "localhost:8080".to_owned(),
+            None,
         )
     }
 }
@@ -176,6 +432,7 @@ impl Default for AppConfig {
 pub struct ServiceConfig {
     pub(crate) services: Vec<Box<dyn AppServiceFactory>>,
     pub(crate) data: Vec<Box<dyn DataFactory>>,
+    pub(crate) data_factories: Vec<FnDataFactory>,
     pub(crate) external: Vec<ResourceDef>,
 }
 
@@ -184,6 +441,7 @@ impl ServiceConfig {
         Self {
             services: Vec::new(),
             data: Vec::new(),
+            data_factories: Vec::new(),
             external: Vec::new(),
         }
     }
@@ -197,6 +455,21 @@ impl ServiceConfig {
         self
     }
 
+    /// Set application data factory, data object is constructed
+    /// asynchronously during application initialization.
+    ///
+    /// This is same as `App::data_factory()` method.
+    pub fn data_factory<F, Out, D, E>(&mut self, data: F) -> &mut Self
+    where
+        F: Fn() -> Out + 'static,
+        Out: Future<Output = Result<D, E>> + 'static,
+        D: 'static,
+        E: std::fmt::Debug,
+    {
+        self.data_factories.push(fn_data_factory(data));
+        self
+    }
+
     /// Configure route for a specific path.
     ///
     /// This is same as `App::route()` method.
@@ -265,37 +538,37 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
-    // #[actix_rt::test]
-    // async fn test_data_factory() {
-    //     let cfg = |cfg: &mut ServiceConfig| {
-    //         cfg.data_factory(|| {
-    //             sleep(std::time::Duration::from_millis(50)).then(|_| {
-    //                 println!("READY");
-    //                 Ok::<_, ()>(10usize)
-    //             })
-    //         });
-    //     };
-
-    //     let mut srv =
-    //         init_service(App::new().configure(cfg).service(
-    //             web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()),
-    //         ));
-    //     let req = TestRequest::default().to_request();
-    //     let resp = srv.call(req).await.unwrap();
-    //     assert_eq!(resp.status(), StatusCode::OK);
-
-    //     let cfg2 = |cfg: &mut ServiceConfig| {
-    //         cfg.data_factory(|| Ok::<_, ()>(10u32));
-    //     };
-    //     let mut srv = init_service(
-    //         App::new()
-    //             .service(web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()))
-    //             .configure(cfg2),
-    //     );
-    //     let req = TestRequest::default().to_request();
-    //     let resp = srv.call(req).await.unwrap();
-    //     assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
-    // }
+    #[actix_rt::test]
+    async fn test_data_factory() {
+        let cfg = |cfg: &mut ServiceConfig| {
+            cfg.data_factory(|| async {
+                actix_rt::time::delay_for(std::time::Duration::from_millis(50)).await;
+                Ok::<_, ()>(10usize)
+            });
+        };
+
+        let mut srv =
+            init_service(App::new().configure(cfg).service(
+                web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()),
+            ))
+            .await;
+        let req = TestRequest::default().to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let cfg2 = |cfg: &mut ServiceConfig| {
+            cfg.data_factory(|| futures::future::ok::<_, ()>(10u32));
+        };
+        let mut srv = init_service(
+            App::new()
+                .service(web::resource("/").to(|_: web::Data<usize>| HttpResponse::Ok()))
+                .configure(cfg2),
+        )
+        .await;
+        let req = TestRequest::default().to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
 
     #[actix_rt::test]
     async fn test_external_resource() {
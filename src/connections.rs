@@ -0,0 +1,295 @@
+//! Server wide connection limit
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, Ready};
+
+/// Counter of connections served by all workers of http server.
+///
+/// Counter is shared by all listeners of a server and can be used for
+/// monitoring.
+///
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let srv = HttpServer::new(|| App::new())
+///     .max_connections(10_000)
+///     .bind("127.0.0.1:8080")?;
+/// let connections = srv.connections();
+///
+/// std::thread::spawn(move || loop {
+///     println!("active connections: {}", connections.count());
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+/// });
+/// srv.run().await
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ConnectionCounter(Arc<Inner>);
+
+struct Inner {
+    count: AtomicUsize,
+    max: AtomicUsize,
+    /// Number of pending drain futures
+    draining: AtomicUsize,
+    waiters: Mutex<Vec<Waker>>,
+}
+
+impl ConnectionCounter {
+    pub(crate) fn new() -> Self {
+        ConnectionCounter(Arc::new(Inner {
+            count: AtomicUsize::new(0),
+            max: AtomicUsize::new(0),
+            draining: AtomicUsize::new(0),
+            waiters: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Number of currently open connections
+    pub fn count(&self) -> usize {
+        self.0.count.load(Ordering::Acquire)
+    }
+
+    /// Maximum number of connections, 0 if not limited
+    pub fn max(&self) -> usize {
+        self.0.max.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_max(&self, max: usize) {
+        self.0.max.store(max, Ordering::Relaxed);
+    }
+
+    /// Wait until all connections are closed.
+    ///
+    /// Future resolves immediately if there are no open connections,
+    /// so it is usually awaited once server stopped accepting new ones.
+    pub fn drain(&self) -> impl Future<Output = ()> {
+        Drain {
+            counter: self.clone(),
+            registered: false,
+        }
+    }
+
+    /// Check if new connection can be accepted, register waker otherwise.
+    fn available(&self, cx: &mut Context<'_>) -> bool {
+        let max = self.max();
+        if max == 0 || self.count() < max {
+            return true;
+        }
+        self.0.waiters.lock().unwrap().push(cx.waker().clone());
+
+        // connection could be closed before waker got registered
+        self.count() < max
+    }
+
+    fn acquire(&self) -> ConnectionGuard {
+        self.0.count.fetch_add(1, Ordering::AcqRel);
+        ConnectionGuard(self.clone())
+    }
+}
+
+/// Decrements counter once connection is closed
+struct ConnectionGuard(ConnectionCounter);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let inner = &(self.0).0;
+        inner.count.fetch_sub(1, Ordering::AcqRel);
+        if inner.max.load(Ordering::Relaxed) != 0
+            || inner.draining.load(Ordering::Acquire) != 0
+        {
+            for waker in inner.waiters.lock().unwrap().drain(..) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Future of `ConnectionCounter::drain()`.
+///
+/// Closed connections wake up waiters only while drain future is pending,
+/// so counter stops draining once future completes or is dropped.
+struct Drain {
+    counter: ConnectionCounter,
+    registered: bool,
+}
+
+impl Drain {
+    fn unregister(&mut self) {
+        if self.registered {
+            self.registered = false;
+            self.counter.0.draining.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl Future for Drain {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let inner = &this.counter.0;
+        if inner.count.load(Ordering::Acquire) != 0 {
+            if !this.registered {
+                this.registered = true;
+                inner.draining.fetch_add(1, Ordering::AcqRel);
+            }
+            inner.waiters.lock().unwrap().push(cx.waker().clone());
+
+            // connection could be closed before waker got registered
+            if inner.count.load(Ordering::Acquire) != 0 {
+                return Poll::Pending;
+            }
+        }
+        this.unregister();
+        Poll::Ready(())
+    }
+}
+
+impl Drop for Drain {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Connection counting transform for server services.
+///
+/// Service is not ready while number of connections reaches maximum,
+/// so workers stop accepting new connections.
+pub(crate) struct ConnectionLimit(pub(crate) ConnectionCounter);
+
+impl<S> Transform<S> for ConnectionLimit
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ConnectionLimitService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ConnectionLimitService {
+            service,
+            counter: self.0.clone(),
+        })
+    }
+}
+
+pub(crate) struct ConnectionLimitService<S> {
+    service: S,
+    counter: ConnectionCounter,
+}
+
+impl<S> Service for ConnectionLimitService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConnectionFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.counter.available(cx) {
+            self.service.poll_ready(cx)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        ConnectionFuture {
+            _guard: self.counter.acquire(),
+            fut: self.service.call(req),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub(crate) struct ConnectionFuture<F> {
+    #[pin]
+    fut: F,
+    _guard: ConnectionGuard,
+}
+
+impl<F: Future> Future for ConnectionFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_service::IntoService;
+    use futures::future::{pending, poll_fn};
+
+    #[actix_rt::test]
+    async fn test_connection_limit() {
+        let counter = ConnectionCounter::new();
+        counter.set_max(1);
+
+        let srv = (|_: ()| pending::<Result<(), ()>>()).into_service();
+        let mut srv = ConnectionLimit(counter.clone())
+            .new_transform(srv)
+            .await
+            .unwrap();
+
+        assert!(poll_fn(|cx| Poll::Ready(srv.poll_ready(cx)))
+            .await
+            .is_ready());
+        let conn = srv.call(());
+        assert_eq!(counter.count(), 1);
+        assert!(poll_fn(|cx| Poll::Ready(srv.poll_ready(cx)))
+            .await
+            .is_pending());
+
+        drop(conn);
+        assert_eq!(counter.count(), 0);
+        assert!(poll_fn(|cx| Poll::Ready(srv.poll_ready(cx)))
+            .await
+            .is_ready());
+    }
+
+    #[actix_rt::test]
+    async fn test_connection_drain() {
+        let counter = ConnectionCounter::new();
+        let srv = (|_: ()| pending::<Result<(), ()>>()).into_service();
+        let mut srv = ConnectionLimit(counter.clone())
+            .new_transform(srv)
+            .await
+            .unwrap();
+        counter.drain().await;
+
+        let conn = srv.call(());
+        let mut drain = Box::pin(counter.drain());
+        assert!(poll_fn(|cx| Poll::Ready(drain.as_mut().poll(cx)))
+            .await
+            .is_pending());
+
+        drop(conn);
+        assert!(poll_fn(|cx| Poll::Ready(drain.as_mut().poll(cx)))
+            .await
+            .is_ready());
+        assert_eq!(counter.0.draining.load(Ordering::Acquire), 0);
+
+        // dropped drain future, i.e. on shutdown timeout, stops draining
+        let _conn = srv.call(());
+        let mut drain = Box::pin(counter.drain());
+        assert!(poll_fn(|cx| Poll::Ready(drain.as_mut().poll(cx)))
+            .await
+            .is_pending());
+        assert_eq!(counter.0.draining.load(Ordering::Acquire), 1);
+        drop(drain);
+        assert_eq!(counter.0.draining.load(Ordering::Acquire), 0);
+    }
+}
@@ -1,9 +1,10 @@
+use std::future::Future;
 use std::ops::Deref;
 use std::sync::Arc;
 
 use actix_http::error::{Error, ErrorInternalServerError};
 use actix_http::Extensions;
-use futures::future::{err, ok, Ready};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
 
 use crate::dev::Payload;
 use crate::extract::FromRequest;
@@ -14,6 +15,38 @@ pub(crate) trait DataFactory {
     fn create(&self, extensions: &mut Extensions) -> bool;
 }
 
+/// Async application data factory
+pub(crate) type FnDataFactory =
+    Box<dyn Fn() -> LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>;
+
+/// Box async data constructor, construction error is logged
+pub(crate) fn fn_data_factory<F, Out, D, E>(data: F) -> FnDataFactory
+where
+    F: Fn() -> Out + 'static,
+    Out: Future<Output = Result<D, E>> + 'static,
+    D: 'static,
+    E: std::fmt::Debug,
+{
+    Box::new(move || {
+        {
+            let fut = data();
+            async move {
+                match fut.await {
+                    Err(e) => {
+                        log::error!("Can not construct data instance: {:?}", e);
+                        Err(())
+                    }
+                    Ok(data) => {
+                        let data: Box<dyn DataFactory> = Box::new(Data::new(data));
+                        Ok(data)
+                    }
+                }
+            }
+        }
+        .boxed_local()
+    })
+}
+
 /// Application data.
 ///
 /// Application data is an arbitrary data attached to the app.
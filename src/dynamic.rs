@@ -0,0 +1,562 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use actix_http::{RequestHead, Response};
+use actix_router::{Path, ResourceDef, Url};
+use actix_service::boxed::{BoxService, BoxServiceFactory};
+use actix_service::{Service, ServiceFactory};
+use futures::future::{join_all, poll_fn, LocalBoxFuture};
+use regex::Regex;
+
+use crate::dev::{AppService, HttpServiceFactory};
+use crate::error::{Error, ErrorInternalServerError, PatternError};
+use crate::guard::{check_guards, Guard};
+use crate::resource::Resource;
+use crate::rmap::ResourceMap;
+use crate::service::{ServiceRequest, ServiceResponse};
+
+type Guards = Vec<Box<dyn Guard>>;
+type HttpService = BoxService<ServiceRequest, ServiceResponse, Error>;
+type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
+type ResourceFn = Arc<dyn Fn(Resource) -> Resource + Send + Sync>;
+
+/// Maximum number of dynamic segments supported by router
+const MAX_SEGMENTS: usize = 16;
+
+/// Routing table that could be changed while application is running.
+///
+/// Handle is shared by application instances of all workers, resources
+/// added or removed through any clone of the handle are visible to all
+/// workers with the next request. Resources are matched in order of
+/// addition, after services registered before the handle.
+///
+/// Resources are constructed by each worker, so configuration closure must
+/// be `Send + Sync`. Dynamic resources are not available for `url_for()`.
+///
+/// ```rust
+/// use actix_web::{web, App, Error, HttpResponse, HttpServer};
+///
+/// async fn hook(routes: web::Data<web::DynamicRoutes>) -> Result<HttpResponse, Error> {
+///     routes.add("/hooks/{id}", |r| r.route(web::post().to(|| HttpResponse::Ok())))?;
+///     Ok(HttpResponse::Created().finish())
+/// }
+///
+/// fn main() {
+///     let routes = web::DynamicRoutes::new();
+///
+///     HttpServer::new(move || {
+///         App::new()
+///             .data(routes.clone())
+///             .route("/admin/hook", web::put().to(hook))
+///             .service(routes.clone())
+///     });
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct DynamicRoutes(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    version: AtomicUsize,
+    entries: RwLock<Vec<(String, ResourceFn)>>,
+}
+
+impl DynamicRoutes {
+    /// Create empty routing table.
+    pub fn new() -> Self {
+        DynamicRoutes::default()
+    }
+
+    /// Add resource for specified path pattern, configured by `f`.
+    ///
+    /// Resource with the same pattern gets replaced. Malformed pattern,
+    /// i.e. with unclosed segment or invalid regex, is rejected with
+    /// `PatternError` and routing table is not changed.
+    pub fn add<F>(&self, path: &str, f: F) -> Result<(), PatternError>
+    where
+        F: Fn(Resource) -> Resource + Send + Sync + 'static,
+    {
+        // pattern is parsed by each worker, it must not panic there
+        if !is_valid_pattern(path) {
+            return Err(PatternError::new(path));
+        }
+
+        let mut entries = self.0.entries.write().unwrap();
+        let f: ResourceFn = Arc::new(f);
+        if let Some(item) = entries.iter_mut().find(|(p, _)| p == path) {
+            item.1 = f;
+        } else {
+            entries.push((path.to_string(), f));
+        }
+        self.0.version.fetch_add(1, Ordering::Release);
+        Ok(())
+    }
+
+    /// Remove resource with specified path pattern.
+    ///
+    /// Returns `false` if there was no such resource.
+    pub fn remove(&self, path: &str) -> bool {
+        let mut entries = self.0.entries.write().unwrap();
+        let len = entries.len();
+        entries.retain(|(p, _)| p != path);
+        if entries.len() == len {
+            return false;
+        }
+        self.0.version.fetch_add(1, Ordering::Release);
+        true
+    }
+
+    /// Replace all resources with resources of `routes`.
+    ///
+    /// Routing table is swapped at once, requests are matched either against
+    /// old or new set of resources, i.e. when routes are reloaded from
+    /// configuration file.
+    pub fn replace(&self, routes: &DynamicRoutes) {
+        let new = routes.0.entries.read().unwrap().clone();
+        *self.0.entries.write().unwrap() = new;
+        self.0.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Check if there is resource with specified path pattern.
+    pub fn contains(&self, path: &str) -> bool {
+        self.0
+            .entries
+            .read()
+            .unwrap()
+            .iter()
+            .any(|(p, _)| p == path)
+    }
+
+    /// Path patterns of resources, in matching order
+    pub fn paths(&self) -> Vec<String> {
+        let entries = self.0.entries.read().unwrap();
+        entries.iter().map(|(p, _)| p.clone()).collect()
+    }
+}
+
+/// Check path pattern the same way `ResourceDef` parses it, `ResourceDef::new()`
+/// panics on malformed pattern.
+fn is_valid_pattern(path: &str) -> bool {
+    let mut re = String::from("^");
+    let mut rem = path;
+    let mut segments = 0;
+
+    while let Some(idx) = rem.find('{') {
+        re.push_str(&regex::escape(&rem[..idx]));
+        rem = &rem[idx..];
+
+        // segment ends with matching closing brace
+        let mut nesting = 0;
+        let end = match rem.find(|c| match c {
+            '{' => {
+                nesting += 1;
+                false
+            }
+            '}' => {
+                nesting -= 1;
+                nesting == 0
+            }
+            _ => false,
+        }) {
+            Some(end) => end,
+            None => return false,
+        };
+        let param = &rem[1..end];
+        rem = &rem[end + 1..];
+
+        let tail = rem == "*";
+        let (name, pattern) = match param.find(':') {
+            // custom regex is not supported for remainder match
+            Some(_) if tail => return false,
+            Some(idx) => (&param[..idx], &param[idx + 1..]),
+            None if tail => {
+                rem = "";
+                (param, ".*")
+            }
+            None => (param, "[^/]+"),
+        };
+        re.push_str(&format!("(?P<{}>{})", name, pattern));
+        segments += 1;
+    }
+    re.push_str(&regex::escape(rem));
+
+    segments <= MAX_SEGMENTS && Regex::new(&re).is_ok()
+}
+
+impl HttpServiceFactory for DynamicRoutes {
+    fn register(self, config: &mut AppService) {
+        let local = Rc::new(Local {
+            routes: self.0,
+            config: config.clone_config(),
+            table: RefCell::new(None),
+        });
+        config.register_service(
+            ResourceDef::root_prefix(""),
+            Some(vec![Box::new(DynamicGuard(local.clone()))]),
+            DynamicFactory(local),
+            Some(Rc::new(ResourceMap::new(ResourceDef::root_prefix("")))),
+        )
+    }
+}
+
+/// Worker local state of routing table
+struct Local {
+    routes: Arc<Inner>,
+    config: AppService,
+    table: RefCell<Option<Rc<Table>>>,
+}
+
+/// Resources of one version of routing table
+struct Table {
+    version: usize,
+    routes: Vec<(ResourceDef, Option<Guards>)>,
+    factories: Vec<HttpNewService>,
+}
+
+impl Local {
+    /// Current table, rebuilt if routing table got changed
+    fn table(&self) -> Rc<Table> {
+        let (version, entries) = {
+            let entries = self.routes.entries.read().unwrap();
+            let version = self.routes.version.load(Ordering::Acquire);
+            if let Some(ref table) = *self.table.borrow() {
+                if table.version == version {
+                    return table.clone();
+                }
+            }
+            (version, entries.clone())
+        };
+
+        let mut cfg = self.config.clone_config();
+        for (path, f) in entries.iter() {
+            f(Resource::new(path.as_str())).register(&mut cfg);
+        }
+        let mut routes = Vec::new();
+        let mut factories = Vec::new();
+        for (rdef, factory, guards, _) in cfg.into_services().1 {
+            routes.push((rdef, guards));
+            factories.push(factory);
+        }
+
+        let table = Rc::new(Table {
+            version,
+            routes,
+            factories,
+        });
+        *self.table.borrow_mut() = Some(table.clone());
+        table
+    }
+}
+
+impl Table {
+    /// Create services of all resources
+    fn new_services(&self) -> ServicesFuture {
+        let futs: Vec<_> = self.factories.iter().map(|f| f.new_service(())).collect();
+        Box::pin(async move { join_all(futs).await.into_iter().collect() })
+    }
+
+    /// Find matching resource, match info of request gets updated
+    fn recognize(&self, req: &mut ServiceRequest) -> Option<usize> {
+        for (idx, (rdef, guards)) in self.routes.iter().enumerate() {
+            let mut path = req.match_info().clone();
+            if rdef.match_path(&mut path) && check_guards(req, guards) {
+                *req.match_info_mut() = path;
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    fn is_match(&self, path: &Path<Url>, req: Option<&ServiceRequest>) -> bool {
+        self.routes.iter().any(|(rdef, guards)| {
+            rdef.match_path(&mut path.clone())
+                && req.map_or(true, |req| check_guards(req, guards))
+        })
+    }
+}
+
+struct DynamicGuard(Rc<Local>);
+
+impl Guard for DynamicGuard {
+    fn check(&self, req: &RequestHead) -> bool {
+        let path = Path::new(Url::new(req.uri.clone()));
+        self.0.table().is_match(&path, None)
+    }
+
+    fn check_request(&self, req: &ServiceRequest) -> bool {
+        self.0.table().is_match(req.match_info(), Some(req))
+    }
+}
+
+struct DynamicFactory(Rc<Local>);
+
+impl ServiceFactory for DynamicFactory {
+    type Config = ();
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Service = DynamicService;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let local = self.0.clone();
+        let table = local.table();
+        let fut = table.new_services();
+
+        Box::pin(async move {
+            let services = fut.await?;
+            Ok(DynamicService(Rc::new(RefCell::new(State {
+                local,
+                table,
+                services,
+                pending: None,
+            }))))
+        })
+    }
+}
+
+type ServicesFuture = LocalBoxFuture<'static, Result<Vec<HttpService>, ()>>;
+
+struct DynamicService(Rc<RefCell<State>>);
+
+/// Services of routing table version
+struct State {
+    local: Rc<Local>,
+    table: Rc<Table>,
+    services: Vec<HttpService>,
+    /// Services of changed routing table that are being created
+    pending: Option<(Rc<Table>, ServicesFuture)>,
+}
+
+impl State {
+    /// Services are replaced once services of changed routing table are
+    /// created, readiness of all services is checked
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let table = self.local.table();
+        if Rc::ptr_eq(&table, &self.table) {
+            self.pending = None;
+        } else if self
+            .pending
+            .as_ref()
+            .map_or(true, |(pending, _)| !Rc::ptr_eq(pending, &table))
+        {
+            let fut = table.new_services();
+            self.pending = Some((table, fut));
+        }
+
+        if let Some((_, ref mut fut)) = self.pending {
+            let res = match fut.as_mut().poll(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => return Poll::Pending,
+            };
+            let (table, _) = self.pending.take().unwrap();
+            match res {
+                Ok(services) => {
+                    self.table = table;
+                    self.services = services;
+                }
+                Err(_) => {
+                    return Poll::Ready(Err(ErrorInternalServerError(
+                        "Can not create service",
+                    )))
+                }
+            }
+        }
+
+        let mut ready = true;
+        for srv in self.services.iter_mut() {
+            if srv.poll_ready(cx)?.is_pending() {
+                ready = false;
+            }
+        }
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Service for DynamicService {
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let state = self.0.clone();
+
+        Box::pin(async move {
+            // application and scope routers do not check readiness of
+            // their services
+            poll_fn(|cx| state.borrow_mut().poll_ready(cx)).await?;
+
+            let fut = {
+                let mut state = state.borrow_mut();
+                // resource could be removed after guard check
+                match state.table.recognize(&mut req) {
+                    Some(idx) => state.services[idx].call(req),
+                    None => {
+                        let req = req.into_parts().0;
+                        return Ok(ServiceResponse::new(
+                            req,
+                            Response::NotFound().finish(),
+                        ));
+                    }
+                }
+            };
+            fut.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use actix_service::{fn_factory, fn_service};
+    use futures::future::ok;
+
+    use crate::dev::ServiceRequest;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, Error, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_dynamic_routes() {
+        let routes = web::DynamicRoutes::new();
+        let mut srv = init_service(
+            App::new()
+                .route("/static", web::get().to(|| HttpResponse::Ok()))
+                .service(routes.clone()),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/hooks/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        routes
+            .add("/hooks/{id}", |r| {
+                r.route(web::post().to(|p: web::Path<String>| {
+                    HttpResponse::Created().body(p.into_inner())
+                }))
+            })
+            .unwrap();
+        assert!(routes.contains("/hooks/{id}"));
+        assert_eq!(routes.paths(), vec!["/hooks/{id}".to_string()]);
+
+        let req = TestRequest::post().uri("/hooks/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body = crate::test::read_body(resp).await;
+        assert_eq!(&body[..], b"1");
+
+        let req = TestRequest::with_uri("/hooks/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let req = TestRequest::with_uri("/static").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // replace resource
+        routes
+            .add("/hooks/{id}", |r| r.to(|| HttpResponse::Accepted()))
+            .unwrap();
+        let req = TestRequest::post().uri("/hooks/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        // malformed pattern is rejected, resources are not changed
+        let err = routes.add("/{bad", |r| r).unwrap_err();
+        assert_eq!(err.pattern(), "/{bad");
+        assert!(routes.add("/{id:[0-9}", |r| r).is_err());
+        assert!(routes.add("/{tail:.*}*", |r| r).is_err());
+        let many: String = (0..17).map(|i| format!("/{{p{}}}", i)).collect();
+        assert!(routes.add(&many, |r| r).is_err());
+        assert!(routes.add(&many[..many.len() - 6], |r| r).is_ok());
+        assert!(routes.remove(&many[..many.len() - 6]));
+        assert_eq!(routes.paths(), vec!["/hooks/{id}".to_string()]);
+
+        assert!(routes.remove("/hooks/{id}"));
+        assert!(!routes.remove("/hooks/{id}"));
+        let req = TestRequest::post().uri("/hooks/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_replace_routes() {
+        let routes = web::DynamicRoutes::new();
+        routes.add("/a", |r| r.to(|| HttpResponse::Ok())).unwrap();
+        let mut srv = init_service(App::new().service(routes.clone())).await;
+
+        let new = web::DynamicRoutes::new();
+        new.add("/b", |r| r.to(|| HttpResponse::Accepted()))
+            .unwrap();
+        new.add("/c", |r| r.to(|| HttpResponse::Created())).unwrap();
+        routes.replace(&new);
+        assert_eq!(routes.paths(), vec!["/b".to_string(), "/c".to_string()]);
+
+        let req = TestRequest::with_uri("/a").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/c").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[actix_rt::test]
+    async fn test_service_creation() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let routes = web::DynamicRoutes::new();
+        let add = |path: &str| {
+            let created = created.clone();
+            routes
+                .add(path, move |r| {
+                    let created = created.clone();
+                    r.default_service(fn_factory(move || {
+                        created.fetch_add(1, Ordering::SeqCst);
+                        ok::<_, ()>(fn_service(|req: ServiceRequest| {
+                            ok::<_, Error>(
+                                req.into_response(HttpResponse::Ok().finish()),
+                            )
+                        }))
+                    }))
+                })
+                .unwrap();
+        };
+        add("/a");
+
+        // services are created with application
+        let mut srv = init_service(App::new().service(routes.clone())).await;
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+
+        for _ in 0..2 {
+            let req = TestRequest::with_uri("/a").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+
+        // services of changed routing table are created once
+        add("/b");
+        for path in &["/b", "/a", "/b"] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+    }
+}
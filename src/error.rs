@@ -19,6 +19,9 @@ pub enum UrlGenerationError {
     /// URL parse error
     #[display(fmt = "{}", _0)]
     ParseError(UrlParseError),
+    /// Query parameters serialization error
+    #[display(fmt = "{}", _0)]
+    QueryError(serde_urlencoded::ser::Error),
 }
 
 /// `InternalServerError` for `UrlGeneratorError`
@@ -46,6 +49,9 @@ pub enum UrlencodedError {
     /// Parse error
     #[display(fmt = "Parse error")]
     Parse,
+    /// Deserialize error
+    #[display(fmt = "Urlencoded deserialize error: {}", _0)]
+    Deserialize(serde::de::value::Error),
     /// Payload error
     #[display(fmt = "Error that occur during reading payload: {}", _0)]
     Payload(PayloadError),
@@ -149,6 +155,133 @@ impl ResponseError for ReadlinesError {
     }
 }
 
+/// Errors of both extractors of `Either<A, B>` extractor
+#[derive(Debug, Display)]
+#[display(fmt = "{}; {}", first, second)]
+pub struct EitherExtractError {
+    first: Error,
+    second: Error,
+}
+
+impl EitherExtractError {
+    pub(crate) fn new(first: Error, second: Error) -> Self {
+        EitherExtractError { first, second }
+    }
+
+    /// Error of the first extractor
+    pub fn first(&self) -> &Error {
+        &self.first
+    }
+
+    /// Error of the second extractor
+    pub fn second(&self) -> &Error {
+        &self.second
+    }
+}
+
+/// Return `BadRequest` for `EitherExtractError`
+impl ResponseError for EitherExtractError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Error of authentication extractors, credentials are missing or malformed
+#[derive(Debug, Display)]
+#[display(fmt = "{}", reason)]
+pub struct AuthenticationError {
+    reason: &'static str,
+    challenge: String,
+}
+
+impl AuthenticationError {
+    pub(crate) fn new(reason: &'static str, challenge: String) -> Self {
+        AuthenticationError { reason, challenge }
+    }
+
+    /// Challenge sent with `WWW-Authenticate` header
+    pub fn challenge(&self) -> &str {
+        &self.challenge
+    }
+}
+
+/// Return `Unauthorized` with `WWW-Authenticate` challenge for `AuthenticationError`
+impl ResponseError for AuthenticationError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .header(
+                crate::http::header::WWW_AUTHENTICATE,
+                self.challenge.as_str(),
+            )
+            .content_type("text/plain; charset=utf-8")
+            .body(self.reason)
+    }
+}
+
+/// Error of adding resource with malformed path pattern to `DynamicRoutes`
+#[derive(Debug, Display)]
+#[display(fmt = "Invalid path pattern: {}", pattern)]
+pub struct PatternError {
+    pattern: String,
+}
+
+impl PatternError {
+    pub(crate) fn new(pattern: &str) -> Self {
+        PatternError {
+            pattern: pattern.to_owned(),
+        }
+    }
+
+    /// Rejected path pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+/// `InternalServerError` for `PatternError`
+impl ResponseError for PatternError {}
+
+/// Error of rate limiting middleware, client exceeded allowed rate
+#[derive(Debug, Display)]
+#[display(fmt = "Too many requests")]
+pub struct RateLimitError {
+    retry_after: std::time::Duration,
+}
+
+impl RateLimitError {
+    pub(crate) fn new(retry_after: std::time::Duration) -> Self {
+        RateLimitError { retry_after }
+    }
+
+    /// Time after which request could be retried
+    pub fn retry_after(&self) -> std::time::Duration {
+        self.retry_after
+    }
+}
+
+/// Return `TooManyRequests` with `Retry-After` header for `RateLimitError`
+impl ResponseError for RateLimitError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // round up to whole seconds
+        let mut secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs += 1;
+        }
+        HttpResponse::build(self.status_code())
+            .header(crate::http::header::RETRY_AFTER, secs.to_string())
+            .content_type("text/plain; charset=utf-8")
+            .body("Too many requests")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +314,33 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_authentication_error() {
+        let resp: HttpResponse =
+            AuthenticationError::new("Missing credentials", "Basic".to_string())
+                .error_response();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers()
+                .get(crate::http::header::WWW_AUTHENTICATE)
+                .unwrap(),
+            "Basic"
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_error() {
+        let resp: HttpResponse =
+            RateLimitError::new(std::time::Duration::from_millis(1500)).error_response();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers()
+                .get(crate::http::header::RETRY_AFTER)
+                .unwrap(),
+            "2"
+        );
+    }
+
     #[test]
     fn test_readlines_error() {
         let resp: HttpResponse = ReadlinesError::LimitOverflow.error_response();
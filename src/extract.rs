@@ -3,11 +3,18 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use actix_http::error::Error;
-use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use actix_http::error::{Error, PayloadError};
+use actix_http::h1;
+use actix_http::http::header;
+use bytes::{Bytes, BytesMut};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
+use futures::StreamExt;
 
 use crate::dev::Payload;
+use crate::error::EitherExtractError;
+use crate::openapi::Operation;
 use crate::request::HttpRequest;
+use crate::responder::Either;
 
 /// Trait implemented by types that can be extracted from request.
 ///
@@ -25,6 +32,10 @@ pub trait FromRequest: Sized {
     /// Convert request to a Self
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future;
 
+    /// Describe extractor in OpenAPI operation of route, i.e. add request
+    /// body content type or header parameter. See `openapi` module.
+    fn describe(_: &mut Operation) {}
+
     /// Convert request to a Self
     ///
     /// This method uses `Payload::None` as payload stream.
@@ -99,6 +110,10 @@ where
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Option<T>, Error>>;
 
+    fn describe(op: &mut Operation) {
+        op.add_optional::<T>();
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         T::from_request(req, payload)
@@ -169,6 +184,10 @@ where
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Result<T, T::Error>, Error>>;
 
+    fn describe(op: &mut Operation) {
+        op.add_optional::<T>();
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         T::from_request(req, payload)
@@ -180,6 +199,165 @@ where
     }
 }
 
+/// Extract one of two types from the request
+///
+/// Extractor `A` is tried first, `B` is used if `A` fails. Request payload,
+/// if there is one, is buffered to be available for both extractors, size of
+/// buffered payload is limited by [**EitherConfig**](web/struct.EitherConfig.html).
+/// If both extractors fail, error holds errors of both.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, Either};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// accept json or urlencoded form
+/// async fn index(info: Either<web::Json<Info>, web::Form<Info>>) -> String {
+///     let info = match info {
+///         Either::A(json) => json.into_inner(),
+///         Either::B(form) => form.into_inner(),
+///     };
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html").route(web::post().to(index))
+///     );
+/// }
+/// ```
+impl<A, B> FromRequest for Either<A, B>
+where
+    A: FromRequest + 'static,
+    A::Future: 'static,
+    B: FromRequest + 'static,
+    B::Future: 'static,
+{
+    type Config = EitherConfig;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Either<A, B>, Error>>;
+
+    fn describe(op: &mut Operation) {
+        op.add_optional::<A>();
+        op.add_optional::<B>();
+    }
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req
+            .app_data::<EitherConfig>()
+            .map_or(DEFAULT_EITHER_LIMIT, |cfg| cfg.limit);
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse::<usize>().ok());
+
+        // payload is buffered only if request has one
+        let body = match (&payload, length) {
+            (Payload::None, _) | (_, Some(0)) => None,
+            (_, Some(len)) if len > limit => {
+                return err(PayloadError::Overflow.into()).boxed_local()
+            }
+            _ => Some(buffer_payload(payload.take(), limit)),
+        };
+        let req = req.clone();
+
+        async move {
+            let body = match body {
+                Some(fut) => Some(fut.await?),
+                None => None,
+            };
+            let first = match A::from_request(&req, &mut body_payload(&body)).await {
+                Ok(a) => return Ok(Either::A(a)),
+                Err(e) => e.into(),
+            };
+            match B::from_request(&req, &mut body_payload(&body)).await {
+                Ok(b) => Ok(Either::B(b)),
+                Err(e) => Err(EitherExtractError::new(first, e.into()).into()),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+const DEFAULT_EITHER_LIMIT: usize = 262_144;
+
+/// Configuration of `Either` extractor.
+///
+/// Request payload is buffered to be available for both extractors, requests
+/// with larger payload than `limit()` are rejected with `PayloadError::Overflow`.
+///
+/// ```rust
+/// use actix_web::{web, App, Either};
+///
+/// async fn index(body: Either<web::Json<String>, String>) -> String {
+///     match body {
+///         Either::A(json) => json.into_inner(),
+///         Either::B(text) => text,
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             // limit buffered payload to 4kb
+///             .app_data(web::EitherConfig::default().limit(4096))
+///             .route(web::post().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct EitherConfig {
+    limit: usize,
+}
+
+impl EitherConfig {
+    /// Change max size of buffered payload. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for EitherConfig {
+    fn default() -> Self {
+        EitherConfig {
+            limit: DEFAULT_EITHER_LIMIT,
+        }
+    }
+}
+
+/// Read raw request payload, up to `limit` bytes
+async fn buffer_payload(mut payload: Payload, limit: usize) -> Result<Bytes, Error> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > limit {
+            return Err(PayloadError::Overflow.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+fn body_payload(body: &Option<Bytes>) -> Payload {
+    match body {
+        Some(body) => {
+            let mut payload = h1::Payload::empty();
+            payload.unread_data(body.clone());
+            payload.into()
+        }
+        None => Payload::None,
+    }
+}
+
 #[doc(hidden)]
 impl FromRequest for () {
     type Config = ();
@@ -202,6 +380,10 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
         type Future = $fut_type<$($T),+>;
         type Config = ($($T::Config),+);
 
+        fn describe(op: &mut Operation) {
+            $($T::describe(op);)+
+        }
+
         fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
             $fut_type {
                 items: <($(Option<$T>,)+)>::default(),
@@ -262,6 +444,8 @@ tuple_from_req!(TupleFromRequest7, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F
 tuple_from_req!(TupleFromRequest8, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H));
 tuple_from_req!(TupleFromRequest9, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I));
 tuple_from_req!(TupleFromRequest10, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J));
+tuple_from_req!(TupleFromRequest11, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K));
+tuple_from_req!(TupleFromRequest12, (0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G), (7, H), (8, I), (9, J), (10, K), (11, L));
 }
 
 #[cfg(test)]
@@ -271,8 +455,9 @@ mod tests {
     use serde_derive::Deserialize;
 
     use super::*;
+    use crate::error::{JsonPayloadError, UrlencodedError};
     use crate::test::TestRequest;
-    use crate::types::{Form, FormConfig};
+    use crate::types::{Form, FormConfig, Json, Query};
 
     #[derive(Deserialize, Debug, PartialEq)]
     struct Info {
@@ -325,6 +510,95 @@ mod tests {
         assert_eq!(r, None);
     }
 
+    #[actix_rt::test]
+    async fn test_either() {
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .header(header::CONTENT_LENGTH, "11")
+        .set_payload(Bytes::from_static(b"hello=world"))
+        .to_http_parts();
+
+        let r = Either::<Json<Info>, Form<Info>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        match r {
+            Either::B(form) => assert_eq!(form.hello, "world"),
+            _ => panic!("expected form"),
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .header(header::CONTENT_LENGTH, "17")
+                .set_payload(Bytes::from_static(b"{\"hello\":\"json\"}"))
+                .to_http_parts();
+
+        let r = Either::<Json<Info>, Form<Info>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        match r {
+            Either::A(json) => assert_eq!(json.hello, "json"),
+            _ => panic!("expected json"),
+        }
+
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .header(header::CONTENT_LENGTH, "5")
+            .set_payload(Bytes::from_static(b"hello"))
+            .to_http_parts();
+
+        let e = Either::<Json<Info>, Form<Info>>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        let e = e.as_error::<EitherExtractError>().unwrap();
+        assert!(e.first().as_error::<JsonPayloadError>().is_some());
+        assert!(e.second().as_error::<UrlencodedError>().is_some());
+    }
+
+    #[actix_rt::test]
+    async fn test_either_limit() {
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .header(header::CONTENT_LENGTH, "11")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .app_data(EitherConfig::default().limit(5))
+            .to_http_parts();
+        let e = Either::<Json<Info>, Form<Info>>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        match e.as_error::<PayloadError>() {
+            Some(PayloadError::Overflow) => (),
+            _ => panic!("expected overflow"),
+        }
+
+        // payload of unknown length
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .app_data(EitherConfig::default().limit(5))
+            .to_http_parts();
+        let e = Either::<Json<Info>, Form<Info>>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        match e.as_error::<PayloadError>() {
+            Some(PayloadError::Overflow) => (),
+            _ => panic!("expected overflow"),
+        }
+
+        // request without payload is not buffered
+        let (req, mut pl) = TestRequest::with_uri("/?hello=query")
+            .app_data(EitherConfig::default().limit(0))
+            .to_http_parts();
+        let r = Either::<Json<Info>, Query<Info>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        match r {
+            Either::B(query) => assert_eq!(query.hello, "query"),
+            _ => panic!("expected query"),
+        }
+    }
+
     #[actix_rt::test]
     async fn test_result() {
         let (req, mut pl) = TestRequest::with_header(
@@ -359,4 +633,27 @@ mod tests {
             .unwrap();
         assert!(r.is_err());
     }
+
+    #[actix_rt::test]
+    async fn test_tuple_12() {
+        let (req, mut pl) = TestRequest::with_uri("/test").to_http_parts();
+
+        let (r, ..) = <(
+            HttpRequest,
+            (),
+            (),
+            (),
+            (),
+            (),
+            (),
+            (),
+            (),
+            (),
+            (),
+            Option<Json<Info>>,
+        )>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+        assert_eq!(r.path(), "/test");
+    }
 }
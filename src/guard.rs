@@ -30,6 +30,8 @@ use std::convert::TryFrom;
 use actix_http::http::{self, header, uri::Uri};
 use actix_http::RequestHead;
 
+use crate::service::ServiceRequest;
+
 /// Trait defines resource guards. Guards are used for route selection.
 ///
 /// Guards can not modify the request object. But it is possible
@@ -38,6 +40,60 @@ use actix_http::RequestHead;
 pub trait Guard {
     /// Check if request matches predicate
     fn check(&self, request: &RequestHead) -> bool;
+
+    /// Check if service request matches predicate, used by router
+    #[doc(hidden)]
+    fn check_request(&self, req: &ServiceRequest) -> bool {
+        self.check(req.head())
+    }
+
+    /// Combine with another guard, both guards have to match.
+    ///
+    /// ```rust
+    /// use actix_web::guard::{self, Guard};
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     App::new().service(web::resource("/index.html").route(
+    ///         web::route()
+    ///             .guard(
+    ///                 guard::Get()
+    ///                     .or(guard::Head())
+    ///                     .and(guard::Not(guard::Header("x-internal", "1"))),
+    ///             )
+    ///             .to(|| HttpResponse::Ok()))
+    ///     );
+    /// }
+    /// ```
+    fn and<F: Guard + 'static>(self, guard: F) -> AllGuard
+    where
+        Self: Sized + 'static,
+    {
+        All(self).and(guard)
+    }
+
+    /// Combine with another guard, any of guards has to match.
+    fn or<F: Guard + 'static>(self, guard: F) -> AnyGuard
+    where
+        Self: Sized + 'static,
+    {
+        Any(self).or(guard)
+    }
+}
+
+/// Check if service request matches all guards of router entry
+pub(crate) fn check_guards(
+    req: &ServiceRequest,
+    guards: &Option<Vec<Box<dyn Guard>>>,
+) -> bool {
+    if let Some(ref guards) = guards {
+        for f in guards {
+            if !f.check_request(req) {
+                return false;
+            }
+        }
+    }
+    true
 }
 
 /// Create guard object for supplied function.
@@ -259,6 +315,11 @@ impl Guard for HeaderGuard {
 
 /// Return predicate that matches if request contains specified Host name.
 ///
+/// Host names are compared case-insensitively. Pattern that starts with
+/// `*.` matches any subdomain of the rest of the pattern, i.e.
+/// `*.example.com` matches `api.example.com` and `a.b.example.com` but
+/// not `example.com` itself.
+///
 /// ```rust
 /// use actix_web::{web, guard::Host, App, HttpResponse};
 ///
@@ -304,7 +365,7 @@ impl Guard for HostGuard {
         };
 
         if let Some(uri_host) = req_host_uri.host() {
-            if self.0 != uri_host {
+            if !host_matches(&self.0, uri_host) {
                 return false;
             }
         } else {
@@ -321,6 +382,17 @@ impl Guard for HostGuard {
     }
 }
 
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if pattern.starts_with("*.") {
+        let suffix = &pattern.as_bytes()[1..];
+        let host = host.as_bytes();
+        host.len() > suffix.len()
+            && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+    } else {
+        pattern.eq_ignore_ascii_case(host)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_http::http::{header, Method};
@@ -427,6 +499,38 @@ mod tests {
         assert!(!pred.check(req.head()));
     }
 
+    #[test]
+    fn test_host_wildcard() {
+        let req = TestRequest::default()
+            .header(
+                header::HOST,
+                header::HeaderValue::from_static("API.Rust-Lang.org:8080"),
+            )
+            .to_http_request();
+
+        let pred = Host("api.rust-lang.org");
+        assert!(pred.check(req.head()));
+
+        let pred = Host("*.rust-lang.org");
+        assert!(pred.check(req.head()));
+
+        let pred = Host("*.api.rust-lang.org");
+        assert!(!pred.check(req.head()));
+
+        let pred = Host("*.crates.io");
+        assert!(!pred.check(req.head()));
+
+        let req = TestRequest::default()
+            .header(
+                header::HOST,
+                header::HeaderValue::from_static("rust-lang.org"),
+            )
+            .to_http_request();
+
+        let pred = Host("*.rust-lang.org");
+        assert!(!pred.check(req.head()));
+    }
+
     #[test]
     fn test_methods() {
         let req = TestRequest::default().to_http_request();
@@ -495,4 +599,34 @@ mod tests {
         assert!(Any(Get()).or(Trace()).check(r.head()));
         assert!(!Any(Get()).or(Get()).check(r.head()));
     }
+
+    #[test]
+    fn test_combinators() {
+        let guard = Get()
+            .or(Head())
+            .and(Not(Header("x-internal", "1")))
+            .or(fn_guard(|head| head.uri.path() == "/public"));
+
+        let r = TestRequest::default()
+            .method(Method::HEAD)
+            .to_http_request();
+        assert!(guard.check(r.head()));
+
+        let r = TestRequest::default()
+            .method(Method::GET)
+            .header("x-internal", "1")
+            .to_http_request();
+        assert!(!guard.check(r.head()));
+
+        let r = TestRequest::with_uri("/public")
+            .method(Method::POST)
+            .header("x-internal", "1")
+            .to_http_request();
+        assert!(guard.check(r.head()));
+
+        let r = TestRequest::default()
+            .method(Method::POST)
+            .to_http_request();
+        assert!(!guard.check(r.head()));
+    }
 }
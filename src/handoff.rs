@@ -0,0 +1,338 @@
+//! Zero-downtime restart with listener handoff
+//!
+//! Server that is configured with `HttpServer::handoff()` starts new process
+//! when it receives handoff signal, `SIGUSR2` by default. Listening sockets
+//! of the server are passed to new process as inherited file descriptors,
+//! the same way systemd passes sockets, so new process takes them with
+//! `HttpServer::bind_from_env()` and starts accepting connections on the
+//! same sockets. Once new process signals that it is ready, old process
+//! stops accepting and shuts down gracefully, like on `SIGTERM`, so
+//! in-flight requests are completed and no connection is reset. If new
+//! process exits or does not signal readiness in time, it is killed and
+//! old process keeps serving.
+//!
+//! Sockets added with `listen()`, `bind()` and their tls variants,
+//! `listen_uds()` and `bind_from_env()` are handed off, sockets bound with
+//! `bind_uds()` are not.
+//!
+//! Descriptors start from 3, their count is passed in `LISTEN_FDS` and
+//! names in `LISTEN_FDNAMES` environment variables. Instead of `LISTEN_PID`
+//! new process gets `LISTEN_PARENT_PID` with pid of old process, so sockets
+//! are taken only by direct child of old process.
+//!
+//! Readiness is signalled through inherited pipe, its descriptor follows
+//! listening sockets and is passed in `LISTEN_READY_FD`. `HttpServer::run()`
+//! writes to it once server is started, process that is not actix web
+//! server has to write single byte to the descriptor itself.
+//!
+//! ```rust,no_run
+//! use actix_web::handoff::Handoff;
+//! use actix_web::{App, HttpServer};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let srv = HttpServer::new(|| App::new()).handoff(Handoff::new());
+//!
+//! // inherit sockets of old process, bind them on first start
+//! let srv = if std::env::var_os("LISTEN_FDS").is_some() {
+//!     srv.bind_from_env()?
+//! } else {
+//!     srv.bind("127.0.0.1:8080")?
+//! };
+//! srv.run().await
+//! # }
+//! ```
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+use actix_rt::signal::unix::{signal, SignalKind};
+use actix_rt::time::timeout;
+use actix_threadpool::BlockingError;
+use futures::future::pending;
+use log::{error, info};
+
+/// Environment variable with descriptor of readiness pipe
+const READY_FD: &str = "LISTEN_READY_FD";
+
+type CommandFn = Box<dyn Fn() -> io::Result<Command>>;
+
+/// Listener handoff configuration.
+///
+/// By default handoff starts current executable with the same arguments
+/// on `SIGUSR2` and waits 10 seconds for it to signal readiness.
+pub struct Handoff {
+    signal: SignalKind,
+    timeout: Duration,
+    command: CommandFn,
+}
+
+impl Handoff {
+    /// Create new handoff configuration.
+    pub fn new() -> Self {
+        Handoff {
+            signal: SignalKind::user_defined2(),
+            timeout: Duration::from_secs(10),
+            command: Box::new(current_exe),
+        }
+    }
+
+    /// Set signal that starts handoff, by default `SIGUSR2`.
+    pub fn signal(mut self, signal: SignalKind) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// Set time new process is given to signal readiness, by default
+    /// 10 seconds.
+    ///
+    /// New process that is not ready once timeout expires is killed and
+    /// old process keeps serving.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set command that starts new process, i.e. to run new version of
+    /// executable after deploy.
+    pub fn command<F>(mut self, command: F) -> Self
+    where
+        F: Fn() -> io::Result<Command> + 'static,
+    {
+        self.command = Box::new(command);
+        self
+    }
+
+    /// Wait for handoff signal and start new process, completes once new
+    /// process is ready and old process has to shutdown.
+    pub(crate) async fn run(self, fds: Vec<(RawFd, &'static str)>) {
+        let mut stream = match signal(self.signal) {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Can not initialize handoff signal handler: {}", e);
+                return pending().await;
+            }
+        };
+
+        while stream.recv().await.is_some() {
+            info!("Starting new process, handing off {} listeners", fds.len());
+            let (ready, notifier) = match ready_pipe() {
+                Ok(pipe) => pipe,
+                Err(e) => {
+                    error!("Can not create readiness pipe: {}", e);
+                    continue;
+                }
+            };
+            let child = (self.command)()
+                .and_then(|cmd| spawn(cmd, &fds, Some(notifier.as_raw_fd())));
+            // pipe is closed once new process exits, so readiness wait
+            // does not outlive it
+            drop(notifier);
+            let mut child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    error!("Can not start new process: {}", e);
+                    continue;
+                }
+            };
+
+            match timeout(self.timeout, wait_ready(ready)).await {
+                Ok(Ok(true)) => {
+                    info!("New process {} is ready, shutting down", child.id());
+                    return;
+                }
+                Ok(Ok(false)) => error!("New process exited before it was ready"),
+                Ok(Err(e)) => error!("Can not read readiness of new process: {}", e),
+                Err(_) => error!("New process is not ready in {:?}", self.timeout),
+            }
+            // new process must not accept connections on handed off sockets
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        pending().await
+    }
+}
+
+impl Default for Handoff {
+    fn default() -> Self {
+        Handoff::new()
+    }
+}
+
+impl fmt::Debug for Handoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handoff")
+            .field("signal", &self.signal)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Command that runs current executable with the same arguments
+fn current_exe() -> io::Result<Command> {
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.args(std::env::args_os().skip(1));
+    Ok(cmd)
+}
+
+/// Signal parent process that handed off listeners that server is ready.
+///
+/// Readiness descriptor is taken only by direct child of process that
+/// handed off listeners, so it is not signalled twice.
+pub(crate) fn notify_ready() {
+    let parent = std::env::var("LISTEN_PARENT_PID")
+        .ok()
+        .and_then(|pid| pid.parse().ok());
+    if parent != Some(std::os::unix::process::parent_id()) {
+        return;
+    }
+    let fd = match std::env::var(READY_FD).ok().and_then(|fd| fd.parse().ok()) {
+        Some(fd) => fd,
+        None => return,
+    };
+    std::env::remove_var(READY_FD);
+
+    let mut pipe = unsafe { File::from_raw_fd(fd) };
+    if let Err(e) = pipe.write_all(b"1") {
+        error!("Can not signal readiness to parent process: {}", e);
+    }
+}
+
+/// Pipe for readiness of new process, both ends are closed on exec.
+fn ready_pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let pipe = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    for fd in fds.iter() {
+        if unsafe { libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(pipe)
+}
+
+/// Wait for new process to write to readiness pipe, resolves to false if
+/// it exits without doing so.
+async fn wait_ready(mut pipe: File) -> io::Result<bool> {
+    let res = actix_threadpool::run(move || loop {
+        match pipe.read(&mut [0; 1]) {
+            Ok(n) => return Ok(n == 1),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    })
+    .await;
+    match res {
+        Ok(ready) => Ok(ready),
+        Err(BlockingError::Error(e)) => Err(e),
+        Err(BlockingError::Canceled) => {
+            Err(io::Error::new(io::ErrorKind::Other, "Thread pool is gone"))
+        }
+    }
+}
+
+/// Start new process with listeners passed as file descriptors from 3,
+/// followed by write end of readiness pipe.
+pub(crate) fn spawn(
+    mut cmd: Command,
+    fds: &[(RawFd, &str)],
+    ready: Option<RawFd>,
+) -> io::Result<Child> {
+    /// First passed file descriptor
+    const LISTEN_FDS_START: RawFd = 3;
+
+    let names: Vec<_> = fds.iter().map(|(_, name)| *name).collect();
+    cmd.env_remove("LISTEN_PID")
+        .env_remove(READY_FD)
+        .env("LISTEN_FDS", fds.len().to_string())
+        .env("LISTEN_FDNAMES", names.join(":"))
+        .env("LISTEN_PARENT_PID", std::process::id().to_string());
+    if ready.is_some() {
+        let fd = LISTEN_FDS_START + fds.len() as RawFd;
+        cmd.env(READY_FD, fd.to_string());
+    }
+
+    let fds: Vec<RawFd> = fds.iter().map(|(fd, _)| *fd).chain(ready).collect();
+    let first_free = LISTEN_FDS_START + fds.len() as RawFd;
+    let mut tmp = vec![0; fds.len()];
+    unsafe {
+        // only async-signal-safe calls are allowed after fork, so buffers
+        // are allocated beforehand
+        cmd.pre_exec(move || {
+            // move descriptors out of target range first, so dup2 does
+            // not overwrite descriptor that is not moved yet
+            for (fd, tmp) in fds.iter().zip(tmp.iter_mut()) {
+                *tmp = libc::fcntl(*fd, libc::F_DUPFD, first_free);
+                if *tmp < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            for (idx, tmp) in tmp.iter().enumerate() {
+                let target = LISTEN_FDS_START + idx as RawFd;
+                if libc::dup2(*tmp, target) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                libc::close(*tmp);
+            }
+            Ok(())
+        });
+    }
+    cmd.spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::os::unix::io::AsRawFd;
+    use std::process::Stdio;
+
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[test]
+    fn test_spawn() {
+        let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+        let lst2 = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut cmd = sh("echo $LISTEN_FDS $LISTEN_FDNAMES $LISTEN_PARENT_PID; \
+             test -e /dev/fd/3 && test -e /dev/fd/4 && echo ok");
+        cmd.stdout(Stdio::piped());
+        let fds = [(lst.as_raw_fd(), "http"), (lst2.as_raw_fd(), "https")];
+        let out = spawn(cmd, &fds, None).unwrap().wait_with_output().unwrap();
+
+        let out = String::from_utf8(out.stdout).unwrap();
+        assert_eq!(out, format!("2 http:https {}\nok\n", std::process::id()));
+    }
+
+    #[actix_rt::test]
+    async fn test_wait_ready() {
+        let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+        let fds = [(lst.as_raw_fd(), "http")];
+
+        // readiness descriptor follows listeners
+        let (ready, notifier) = ready_pipe().unwrap();
+        let cmd = sh("test \"$LISTEN_READY_FD\" = 4 && printf 1 >&4; sleep 5");
+        let mut child = spawn(cmd, &fds, Some(notifier.as_raw_fd())).unwrap();
+        drop(notifier);
+        assert!(wait_ready(ready).await.unwrap());
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        // process exits without signalling readiness
+        let (ready, notifier) = ready_pipe().unwrap();
+        let mut child = spawn(sh("exit 0"), &fds, Some(notifier.as_raw_fd())).unwrap();
+        drop(notifier);
+        assert!(!wait_ready(ready).await.unwrap());
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+//! Health and readiness checks
+//!
+//! `Health` service serves liveness endpoint, `/healthz` by default, and
+//! readiness endpoint, `/readyz` by default. Liveness endpoint runs checks
+//! that are registered with `Health::liveness_check()`, readiness endpoint
+//! runs all registered checks. Checks run concurrently, each check is
+//! limited by timeout. Both endpoints respond with `200 OK` if all checks
+//! pass and `503 Service Unavailable` otherwise, status of every check is
+//! reported in json body:
+//!
+//! ```json
+//! {
+//!     "status": "fail",
+//!     "checks": {
+//!         "database": { "status": "ok", "duration_ms": 2 },
+//!         "cache": { "status": "fail", "duration_ms": 1000, "error": "check timed out" }
+//!     }
+//! }
+//! ```
+//!
+//! Readiness endpoint responds with `503 Service Unavailable` and `draining`
+//! status without running checks once `Readiness` handle is set to not
+//! ready, i.e. by `HttpServer::readiness()` when server receives stop
+//! signal, so load balancers stop routing requests to the instance while
+//! connections are drained.
+//!
+//! ```rust,no_run
+//! use actix_web::health::{Health, Readiness};
+//! use actix_web::{App, HttpServer};
+//!
+//! async fn ping_database() -> Result<(), String> {
+//!     Ok(())
+//! }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let readiness = Readiness::new();
+//! let handle = readiness.clone();
+//!
+//! HttpServer::new(move || {
+//!     App::new().service(
+//!         Health::new()
+//!             .readiness(handle.clone())
+//!             .check("database", ping_database),
+//!     )
+//! })
+//! .readiness(readiness)
+//! .bind("127.0.0.1:8080")?
+//! .run()
+//! .await
+//! # }
+//! ```
+use std::fmt;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_rt::time::timeout;
+use futures::future::{join_all, FutureExt, LocalBoxFuture};
+use serde_json::{json, Map, Value};
+
+use crate::dev::{AppService, HttpServiceFactory};
+use crate::http::StatusCode;
+use crate::{guard, HttpResponse, Resource, Route};
+
+type CheckFn = Box<dyn Fn() -> LocalBoxFuture<'static, Result<(), String>>>;
+
+/// Readiness state of application instance.
+///
+/// Clones of handle share state, handle can be moved between threads, so
+/// single handle is shared by `Health` services of all workers. Instance is
+/// ready by default.
+#[derive(Debug, Clone)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    /// Create new handle in ready state.
+    pub fn new() -> Self {
+        Readiness(Arc::new(AtomicBool::new(true)))
+    }
+
+    /// Returns true if instance is ready.
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Set readiness of instance.
+    pub fn set_ready(&self, ready: bool) {
+        self.0.store(ready, Ordering::Release)
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Readiness::new()
+    }
+}
+
+struct Check {
+    name: String,
+    liveness: bool,
+    timeout: Option<Duration>,
+    check: CheckFn,
+}
+
+/// Service that serves liveness and readiness endpoints.
+///
+/// `Health` service must be registered with `App::service()` method.
+pub struct Health {
+    liveness_path: String,
+    readiness_path: String,
+    timeout: Duration,
+    readiness: Readiness,
+    checks: Vec<Check>,
+}
+
+impl Health {
+    /// Create new `Health` service without checks.
+    pub fn new() -> Self {
+        Health {
+            liveness_path: "/healthz".to_owned(),
+            readiness_path: "/readyz".to_owned(),
+            timeout: Duration::from_secs(5),
+            readiness: Readiness::new(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// Set path of liveness endpoint, by default `/healthz`.
+    pub fn liveness_path(mut self, path: &str) -> Self {
+        self.liveness_path = path.to_owned();
+        self
+    }
+
+    /// Set path of readiness endpoint, by default `/readyz`.
+    pub fn readiness_path(mut self, path: &str) -> Self {
+        self.readiness_path = path.to_owned();
+        self
+    }
+
+    /// Set timeout of checks that do not set own timeout, by default 5
+    /// seconds. Check fails if it does not complete within timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use shared readiness handle.
+    pub fn readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// Register readiness check, i.e. database ping or probe of dependency
+    /// service. Check fails if it returns error.
+    pub fn check<F, R, E>(self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: fmt::Display + 'static,
+    {
+        self.add_check(name, false, None, check)
+    }
+
+    /// Register readiness check with own timeout.
+    pub fn check_timeout<F, R, E>(self, name: &str, timeout: Duration, check: F) -> Self
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: fmt::Display + 'static,
+    {
+        self.add_check(name, false, Some(timeout), check)
+    }
+
+    /// Register check that runs on both liveness and readiness endpoints.
+    ///
+    /// Liveness checks should not depend on external services, failing
+    /// liveness check usually means instance has to be restarted.
+    pub fn liveness_check<F, R, E>(self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: fmt::Display + 'static,
+    {
+        self.add_check(name, true, None, check)
+    }
+
+    fn add_check<F, R, E>(
+        mut self,
+        name: &str,
+        liveness: bool,
+        timeout: Option<Duration>,
+        check: F,
+    ) -> Self
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: fmt::Display + 'static,
+    {
+        self.checks.push(Check {
+            name: name.to_owned(),
+            liveness,
+            timeout,
+            check: Box::new(move || {
+                check()
+                    .map(|res| res.map_err(|e| e.to_string()))
+                    .boxed_local()
+            }),
+        });
+        self
+    }
+
+    /// Run checks and build response
+    async fn respond(&self, liveness: bool) -> HttpResponse {
+        if !liveness && !self.readiness.is_ready() {
+            return HttpResponse::ServiceUnavailable()
+                .json(json!({ "status": "draining", "checks": {} }));
+        }
+
+        let results = join_all(
+            self.checks
+                .iter()
+                .filter(|check| check.liveness || !liveness)
+                .map(|check| self.run(check)),
+        )
+        .await;
+
+        let mut ok = true;
+        let mut checks = Map::new();
+        for (name, result) in results {
+            ok &= result["status"] == "ok";
+            checks.insert(name, result);
+        }
+
+        let status = if ok {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        HttpResponse::build(status).json(json!({
+            "status": if ok { "ok" } else { "fail" },
+            "checks": checks,
+        }))
+    }
+
+    /// Run single check, its status is reported as json object
+    async fn run(&self, check: &Check) -> (String, Value) {
+        let start = Instant::now();
+        let result =
+            timeout(check.timeout.unwrap_or(self.timeout), (check.check)()).await;
+        let duration = start.elapsed().as_millis() as u64;
+
+        let value = match result {
+            Ok(Ok(())) => json!({ "status": "ok", "duration_ms": duration }),
+            Ok(Err(e)) => {
+                json!({ "status": "fail", "duration_ms": duration, "error": e })
+            }
+            Err(_) => json!({
+                "status": "fail",
+                "duration_ms": duration,
+                "error": "check timed out",
+            }),
+        };
+        (check.name.clone(), value)
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new()
+    }
+}
+
+impl fmt::Debug for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checks: Vec<_> = self.checks.iter().map(|check| &check.name).collect();
+        f.debug_struct("Health")
+            .field("liveness_path", &self.liveness_path)
+            .field("readiness_path", &self.readiness_path)
+            .field("timeout", &self.timeout)
+            .field("readiness", &self.readiness)
+            .field("checks", &checks)
+            .finish()
+    }
+}
+
+impl HttpServiceFactory for Health {
+    fn register(self, config: &mut AppService) {
+        let health = Rc::new(self);
+
+        for &liveness in &[true, false] {
+            let path = if liveness {
+                health.liveness_path.clone()
+            } else {
+                health.readiness_path.clone()
+            };
+            let health = health.clone();
+            Resource::new(path.as_str())
+                .route(Route::new().guard(guard::Get()).to(move || {
+                    let health = health.clone();
+                    async move { health.respond(liveness).await }
+                }))
+                .register(config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::delay_for;
+    use futures::future::ready;
+
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::App;
+
+    #[actix_rt::test]
+    async fn test_health() {
+        let readiness = Readiness::new();
+        let mut srv = init_service(
+            App::new().service(
+                Health::new()
+                    .readiness(readiness.clone())
+                    .timeout(Duration::from_millis(50))
+                    .liveness_check("memory", || ready(Ok::<_, String>(())))
+                    .check("database", || ready(Ok::<_, String>(())))
+                    .check("cache", || ready(Err("connection refused")))
+                    .check_timeout("search", Duration::from_millis(10), || async {
+                        delay_for(Duration::from_millis(200)).await;
+                        Ok::<_, String>(())
+                    }),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/healthz").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["memory"]["status"], "ok");
+        assert!(body["checks"].get("database").is_none());
+
+        let req = TestRequest::with_uri("/readyz").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["status"], "fail");
+        assert_eq!(body["checks"]["memory"]["status"], "ok");
+        assert_eq!(body["checks"]["database"]["status"], "ok");
+        assert_eq!(body["checks"]["cache"]["status"], "fail");
+        assert_eq!(body["checks"]["cache"]["error"], "connection refused");
+        assert_eq!(body["checks"]["search"]["error"], "check timed out");
+
+        readiness.set_ready(false);
+        let req = TestRequest::with_uri("/readyz").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["status"], "draining");
+
+        // liveness is not affected by readiness
+        let req = TestRequest::with_uri("/healthz").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post().uri("/healthz").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[actix_rt::test]
+    async fn test_health_ready() {
+        let mut srv = init_service(
+            App::new().service(
+                Health::new()
+                    .readiness_path("/ready")
+                    .check("database", || ready(Ok::<_, String>(()))),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/ready").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["database"]["status"], "ok");
+    }
+}
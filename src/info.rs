@@ -1,4 +1,5 @@
 use std::cell::Ref;
+use std::net::IpAddr;
 
 use crate::dev::{AppConfig, RequestHead};
 use crate::http::header::{self, HeaderName};
@@ -32,28 +33,38 @@ impl ConnectionInfo {
         let mut remote = None;
         let mut peer = None;
 
-        // load forwarded header
-        for hdr in req.headers.get_all(&header::FORWARDED) {
-            if let Ok(val) = hdr.to_str() {
-                for pair in val.split(';') {
-                    for el in pair.split(',') {
-                        let mut items = el.trim().splitn(2, '=');
-                        if let Some(name) = items.next() {
-                            if let Some(val) = items.next() {
-                                match &name.to_lowercase() as &str {
-                                    "for" => {
-                                        if remote.is_none() {
-                                            remote = Some(val.trim());
-                                        }
-                                    }
+        // forwarding headers are used only for connections from trusted proxies
+        let proxies = cfg.trusted_proxies();
+        let trusted = match proxies {
+            Some(proxies) => req
+                .peer_addr
+                .map(|addr| proxies.contains(addr.ip()))
+                .unwrap_or(false),
+            None => true,
+        };
+
+        if trusted {
+            // load forwarded header, elements are separated by comma and
+            // pairs of element are separated by semicolon
+            let mut chain = Vec::new();
+            for hdr in req.headers.get_all(&header::FORWARDED) {
+                if let Ok(val) = hdr.to_str() {
+                    for el in val.split(',') {
+                        for pair in el.split(';') {
+                            let mut items = pair.trim().splitn(2, '=');
+                            if let (Some(name), Some(val)) = (items.next(), items.next())
+                            {
+                                let val = val.trim().trim_matches('"');
+                                match &name.trim().to_lowercase() as &str {
+                                    "for" => chain.push(val),
                                     "proto" => {
                                         if scheme.is_none() {
-                                            scheme = Some(val.trim());
+                                            scheme = Some(val);
                                         }
                                     }
                                     "host" => {
                                         if host.is_none() {
-                                            host = Some(val.trim());
+                                            host = Some(val);
                                         }
                                     }
                                     _ => (),
@@ -63,63 +74,67 @@ impl ConnectionInfo {
                     }
                 }
             }
-        }
 
-        // scheme
-        if scheme.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    scheme = h.split(',').next().map(|v| v.trim());
+            // scheme
+            if scheme.is_none() {
+                if let Some(h) = req
+                    .headers
+                    .get(HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        scheme = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
-            if scheme.is_none() {
-                scheme = req.uri.scheme().map(|a| a.as_str());
-                if scheme.is_none() && cfg.secure() {
-                    scheme = Some("https")
+
+            // host
+            if host.is_none() {
+                if let Some(h) = req
+                    .headers
+                    .get(HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        host = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
+
+            // remote addr
+            if chain.is_empty() {
+                if let Some(h) = req
+                    .headers
+                    .get(HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        chain = h.split(',').map(|v| v.trim()).collect();
+                    }
+                }
+            }
+            remote = client_node(&chain, proxies);
+        }
+
+        if scheme.is_none() {
+            scheme = req.uri.scheme().map(|a| a.as_str());
+            if scheme.is_none() && cfg.secure() {
+                scheme = Some("https")
+            }
         }
 
-        // host
         if host.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    host = h.split(',').next().map(|v| v.trim());
-                }
+            if let Some(h) = req.headers.get(&header::HOST) {
+                host = h.to_str().ok();
             }
             if host.is_none() {
-                if let Some(h) = req.headers.get(&header::HOST) {
-                    host = h.to_str().ok();
-                }
+                host = req.uri.authority().map(|a| a.as_str());
                 if host.is_none() {
-                    host = req.uri.authority().map(|a| a.as_str());
-                    if host.is_none() {
-                        host = Some(cfg.host());
-                    }
+                    host = Some(cfg.host());
                 }
             }
         }
 
-        // remote addr
         if remote.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    remote = h.split(',').next().map(|v| v.trim());
-                }
-            }
-            if remote.is_none() {
-                // get peeraddr from socketaddr
-                peer = req.peer_addr.map(|addr| format!("{}", addr));
-            }
+            // get peeraddr from socketaddr
+            peer = req.peer_addr.map(|addr| format!("{}", addr));
         }
 
         ConnectionInfo {
@@ -163,6 +178,11 @@ impl ConnectionInfo {
     /// - X-Forwarded-For
     /// - peer name of opened socket
     ///
+    /// If trusted proxies are configured with `HttpServer::trusted_proxies()`,
+    /// headers are used only for connections from trusted proxies and the
+    /// last address of forwarding chain that is not a trusted proxy is
+    /// returned.
+    ///
     /// # Security
     /// Do not use this function for security purposes, unless you can ensure the Forwarded and
     /// X-Forwarded-For headers cannot be spoofed by the client. If you want the client's socket
@@ -180,6 +200,160 @@ impl ConnectionInfo {
     }
 }
 
+/// Client node of forwarding chain
+fn client_node<'a>(
+    chain: &[&'a str],
+    proxies: Option<&TrustedProxies>,
+) -> Option<&'a str> {
+    match proxies {
+        Some(proxies) => chain
+            .iter()
+            .rev()
+            .find(|node| node_ip(node).map_or(true, |ip| !proxies.contains(ip)))
+            .or_else(|| chain.first())
+            .copied(),
+        None => chain.first().copied(),
+    }
+}
+
+/// Client address of request, forwarding headers are used only if peer is
+/// one of trusted `proxies`.
+///
+/// Unlike `ConnectionInfo::remote()`, forwarding headers are ignored if
+/// trusted proxies are not configured.
+pub(crate) fn client_ip(
+    req: &RequestHead,
+    proxies: Option<&TrustedProxies>,
+) -> Option<IpAddr> {
+    let peer = req.peer_addr?.ip();
+    let proxies = match proxies {
+        Some(proxies) if proxies.contains(peer) => proxies,
+        _ => return Some(peer),
+    };
+
+    let mut chain = Vec::new();
+    for hdr in req.headers.get_all(&header::FORWARDED) {
+        if let Ok(val) = hdr.to_str() {
+            for pair in val.split(|c| c == ',' || c == ';') {
+                let mut items = pair.trim().splitn(2, '=');
+                if let (Some(name), Some(val)) = (items.next(), items.next()) {
+                    if name.trim().eq_ignore_ascii_case("for") {
+                        chain.push(val.trim().trim_matches('"'));
+                    }
+                }
+            }
+        }
+    }
+    if chain.is_empty() {
+        if let Some(h) = req
+            .headers
+            .get(HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+        {
+            if let Ok(h) = h.to_str() {
+                chain = h.split(',').map(|v| v.trim()).collect();
+            }
+        }
+    }
+
+    if chain.is_empty() {
+        Some(peer)
+    } else {
+        client_node(&chain, Some(proxies)).and_then(node_ip)
+    }
+}
+
+/// Ip address of forwarding chain node, i.e. `192.0.2.60:4711` or
+/// `[2001:db8::1]`
+pub(crate) fn node_ip(node: &str) -> Option<IpAddr> {
+    if node.starts_with('[') {
+        node[1..].split(']').next()?.parse().ok()
+    } else if let Ok(ip) = node.parse() {
+        Some(ip)
+    } else {
+        node.rsplitn(2, ':').nth(1)?.parse().ok()
+    }
+}
+
+/// Networks of proxies trusted to provide forwarding headers.
+///
+/// Check [`HttpServer::trusted_proxies()`](../struct.HttpServer.html#method.trusted_proxies)
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl TrustedProxies {
+    /// Create empty set of trusted proxies.
+    pub fn new() -> Self {
+        TrustedProxies::default()
+    }
+
+    /// Add network in CIDR notation, i.e. `10.0.0.0/8`, or single address.
+    ///
+    /// Panics if network is not valid.
+    pub fn add(mut self, net: &str) -> Self {
+        let net = parse_network(net)
+            .unwrap_or_else(|| panic!("Invalid proxy network: {:?}", net));
+        self.0.push(net);
+        self
+    }
+
+    /// Check if address belongs to one of trusted networks.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        network_contains(&self.0, addr)
+    }
+}
+
+/// Parse network in CIDR notation, i.e. `10.0.0.0/8`, or single address
+pub(crate) fn parse_network(net: &str) -> Option<(IpAddr, u8)> {
+    let mut parts = net.trim().splitn(2, '/');
+    let addr: IpAddr = parts.next()?.parse().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match parts.next() {
+        Some(prefix) => match prefix.parse::<u8>() {
+            Ok(prefix) if prefix <= max => prefix,
+            _ => return None,
+        },
+        None => max,
+    };
+    match canonical(addr) {
+        // ipv4-mapped network, prefix covers 96 bits of mapping
+        IpAddr::V4(net) if addr.is_ipv6() => {
+            if prefix < 96 {
+                return None;
+            }
+            Some((IpAddr::V4(net), prefix - 96))
+        }
+        net => Some((net, prefix)),
+    }
+}
+
+/// Check if address belongs to one of networks
+pub(crate) fn network_contains(networks: &[(IpAddr, u8)], addr: IpAddr) -> bool {
+    let addr = canonical(addr);
+    networks.iter().any(|(net, prefix)| match (net, addr) {
+        (IpAddr::V4(net), IpAddr::V4(addr)) => {
+            let mask = (!0u32).checked_shl(32 - u32::from(*prefix)).unwrap_or(0);
+            u32::from(*net) & mask == u32::from(addr) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(addr)) => {
+            let mask = (!0u128).checked_shl(128 - u32::from(*prefix)).unwrap_or(0);
+            u128::from(*net) & mask == u128::from(addr) & mask
+        }
+        _ => false,
+    })
+}
+
+/// Ipv4 address for ipv4-mapped ipv6 address
+fn canonical(addr: IpAddr) -> IpAddr {
+    if let IpAddr::V6(v6) = addr {
+        if v6.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] {
+            if let Some(v4) = v6.to_ipv4() {
+                return IpAddr::V4(v4);
+            }
+        }
+    }
+    addr
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,4 +406,66 @@ mod tests {
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
     }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let proxies = TrustedProxies::new().add("10.0.0.0/8").add("::1");
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("::1".parse().unwrap()));
+        assert!(!proxies.contains("11.0.0.1".parse().unwrap()));
+        assert!(!proxies.contains("::2".parse().unwrap()));
+        assert!(TrustedProxies::new()
+            .add("0.0.0.0/0")
+            .contains("192.0.2.60".parse().unwrap()));
+
+        // ipv4-mapped network
+        let mapped = TrustedProxies::new().add("::ffff:10.0.0.0/104");
+        assert!(mapped.contains("10.1.2.3".parse().unwrap()));
+        assert!(mapped.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!mapped.contains("11.0.0.1".parse().unwrap()));
+        assert!(parse_network("::ffff:10.0.0.0/95").is_none());
+
+        let cfg = AppConfig::new(
+            false,
+            "127.0.0.1:8080".parse().unwrap(),
+            "localhost:8080".to_owned(),
+            Some(proxies),
+        );
+
+        // untrusted peer
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.1:4000".parse().unwrap())
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60;proto=https;host=rust-lang.org",
+            )
+            .header(header::HOST, "localhost")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "localhost");
+        assert_eq!(info.remote(), Some("192.0.2.1:4000"));
+
+        // trusted peer, spoofed address before client address is skipped
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .header(
+                header::FORWARDED,
+                r#"for=1.1.1.1, for="192.0.2.60:4711";proto=https, for=10.0.0.1"#,
+            )
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.remote(), Some("192.0.2.60:4711"));
+
+        let req = TestRequest::default()
+            .peer_addr("[::1]:4000".parse().unwrap())
+            .header(X_FORWARDED_FOR, "192.0.2.60, 10.0.0.1")
+            .header(X_FORWARDED_HOST, "rust-lang.org")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+    }
 }
@@ -72,6 +72,7 @@
 //!
 //! * `client` - enables http client (default enabled)
 //! * `compress` - enables content encoding compression support (default enabled)
+//! * `brotli` - enables brotli compression support, requires `compress` (default enabled)
 //! * `openssl` - enables ssl support via `openssl` crate, supports `http/2`
 //! * `rustls` - enables ssl support via `rustls` crate, supports `http/2`
 //! * `secure-cookies` - enables secure cookies support, includes `ring` crate as
@@ -81,14 +82,22 @@
 mod app;
 mod app_service;
 mod config;
+mod connections;
 mod data;
+mod dynamic;
 pub mod error;
 mod extract;
 pub mod guard;
 mod handler;
+#[cfg(unix)]
+pub mod handoff;
+pub mod health;
 mod info;
+mod metrics;
 pub mod middleware;
+pub mod openapi;
 mod request;
+mod request_data;
 mod resource;
 mod responder;
 mod rmap;
@@ -96,6 +105,8 @@ mod route;
 mod scope;
 mod server;
 mod service;
+mod shutdown;
+pub mod sse;
 pub mod test;
 mod types;
 pub mod web;
@@ -112,9 +123,12 @@ pub use crate::extract::FromRequest;
 pub use crate::request::HttpRequest;
 pub use crate::resource::Resource;
 pub use crate::responder::{Either, Responder};
+pub use crate::rmap::UrlParams;
 pub use crate::route::Route;
 pub use crate::scope::Scope;
 pub use crate::server::HttpServer;
+#[cfg(feature = "openssl")]
+pub use crate::server::OpensslReloadHandle;
 
 pub mod dev {
     //! The `actix-web` prelude for library developers
@@ -128,24 +142,33 @@ pub mod dev {
     //! ```
 
     pub use crate::config::{AppConfig, AppService};
+    pub use crate::connections::ConnectionCounter;
     #[doc(hidden)]
     pub use crate::handler::Factory;
-    pub use crate::info::ConnectionInfo;
+    pub use crate::info::{ConnectionInfo, TrustedProxies};
+    pub use crate::metrics::{ServerMetrics, WorkerMetrics};
     pub use crate::rmap::ResourceMap;
     pub use crate::service::{
         HttpServiceFactory, ServiceRequest, ServiceResponse, WebService,
     };
+    pub use crate::shutdown::ServerHandle;
 
     pub use crate::types::form::UrlEncoded;
     pub use crate::types::json::JsonBody;
     pub use crate::types::readlines::Readlines;
 
-    pub use actix_http::body::{Body, BodySize, MessageBody, ResponseBody, SizedStream};
+    pub use actix_http::body::{
+        Body, BodySize, BodyTransform, MessageBody, ReaderBody, ResponseBody,
+        SizedStream, TransformBody,
+    };
     #[cfg(feature = "compress")]
     pub use actix_http::encoding::Decoder as Decompress;
+    pub use actix_http::h1::{EarlyHints, Upgraded};
+    pub use actix_http::h2::ServerPush;
     pub use actix_http::ResponseBuilder as HttpResponseBuilder;
     pub use actix_http::{
-        Extensions, Payload, PayloadStream, RequestHead, ResponseHead,
+        Extensions, Payload, PayloadStream, PoolConfig, PoolStats, RequestHead,
+        ResponseHead, Trailers,
     };
     pub use actix_router::{Path, ResourceDef, ResourcePath, Url};
     pub use actix_server::Server;
@@ -227,6 +250,6 @@ pub mod client {
         ConnectError, InvalidUrl, PayloadError, SendRequestError, WsClientError,
     };
     pub use awc::{
-        test, Client, ClientBuilder, ClientRequest, ClientResponse, Connector,
+        multipart, test, Client, ClientBuilder, ClientRequest, ClientResponse, Connector,
     };
 }
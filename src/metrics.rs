@@ -0,0 +1,439 @@
+//! Server metrics
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::thread::{self, ThreadId};
+
+use actix_http::body::{BodySize, MessageBody, ResponseBody};
+use actix_http::{
+    ConnectionExtensions, Error, Extensions, HttpMessage, IdleLimit, Request, Response,
+};
+use actix_service::{Service, ServiceFactory};
+use bytes::Bytes;
+
+/// Metrics of http server collected by all workers.
+///
+/// Metrics are collected only if handle is requested with
+/// `HttpServer::metrics()` before server is started. Workers are removed
+/// from metrics once they are stopped.
+///
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let srv = HttpServer::new(|| App::new()).bind("127.0.0.1:8080")?;
+/// let metrics = srv.metrics();
+///
+/// std::thread::spawn(move || loop {
+///     let total = metrics.total();
+///     println!(
+///         "connections: {}, requests: {}",
+///         total.connections, total.total_requests
+///     );
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+/// });
+/// srv.run().await
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ServerMetrics {
+    workers: Arc<Mutex<Vec<(ThreadId, Weak<Counters>)>>>,
+    idle_limit: IdleLimit,
+}
+
+/// Snapshot of server or worker metrics
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WorkerMetrics {
+    /// Number of open connections
+    pub connections: usize,
+    /// Number of idle keep-alive connections
+    pub keep_alive_connections: usize,
+    /// Number of requests being handled, request is handled until its
+    /// response body is sent
+    pub requests_in_flight: usize,
+    /// Total number of received requests
+    pub total_requests: u64,
+}
+
+impl ServerMetrics {
+    pub(crate) fn new(idle_limit: IdleLimit) -> Self {
+        ServerMetrics {
+            workers: Arc::new(Mutex::new(Vec::new())),
+            idle_limit,
+        }
+    }
+
+    /// Metrics of all workers combined
+    pub fn total(&self) -> WorkerMetrics {
+        self.workers()
+            .iter()
+            .fold(WorkerMetrics::default(), |total, m| WorkerMetrics {
+                connections: total.connections + m.connections,
+                keep_alive_connections: total.keep_alive_connections
+                    + m.keep_alive_connections,
+                requests_in_flight: total.requests_in_flight + m.requests_in_flight,
+                total_requests: total.total_requests + m.total_requests,
+            })
+    }
+
+    /// Metrics of each running worker
+    pub fn workers(&self) -> Vec<WorkerMetrics> {
+        let mut workers = self.workers.lock().unwrap();
+        workers.retain(|(_, counters)| counters.strong_count() > 0);
+        workers
+            .iter()
+            .filter_map(|(_, counters)| counters.upgrade())
+            .map(|counters| Worker(counters).metrics())
+            .collect()
+    }
+
+    /// Total number of idle keep-alive connections closed because of
+    /// `HttpServer::max_idle_connections()` limit
+    pub fn evicted_connections(&self) -> u64 {
+        self.idle_limit.evicted()
+    }
+
+    /// Get counters of current worker thread
+    ///
+    /// Counters are kept while worker's services hold them, they are
+    /// dropped once worker is stopped.
+    pub(crate) fn worker(&self) -> Worker {
+        let id = thread::current().id();
+        let mut workers = self.workers.lock().unwrap();
+        workers.retain(|(_, counters)| counters.strong_count() > 0);
+        let counters = workers
+            .iter()
+            .find(|(tid, _)| *tid == id)
+            .and_then(|(_, counters)| counters.upgrade());
+        if let Some(counters) = counters {
+            return Worker(counters);
+        }
+        let counters = Arc::new(Counters::default());
+        workers.push((id, Arc::downgrade(&counters)));
+        Worker(counters)
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    connections: AtomicUsize,
+    keep_alive: AtomicUsize,
+    in_flight: AtomicUsize,
+    requests: AtomicU64,
+}
+
+/// Counters of one worker
+#[derive(Clone)]
+pub(crate) struct Worker(Arc<Counters>);
+
+impl Worker {
+    fn metrics(&self) -> WorkerMetrics {
+        WorkerMetrics {
+            connections: self.0.connections.load(Ordering::Relaxed),
+            keep_alive_connections: self.0.keep_alive.load(Ordering::Relaxed),
+            requests_in_flight: self.0.in_flight.load(Ordering::Relaxed),
+            total_requests: self.0.requests.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start tracking new connection, used as on-connect callback
+    pub(crate) fn connect(&self, ext: &mut Extensions) {
+        self.0.connections.fetch_add(1, Ordering::Relaxed);
+        ext.insert(ConnectionMetrics(Rc::new(Connection {
+            worker: self.clone(),
+            in_flight: Cell::new(0),
+            served: Cell::new(false),
+        })));
+    }
+}
+
+#[derive(Clone)]
+struct ConnectionMetrics(Rc<Connection>);
+
+/// Connection state, dropped once connection is closed
+struct Connection {
+    worker: Worker,
+    in_flight: Cell<usize>,
+    served: Cell<bool>,
+}
+
+impl Connection {
+    fn is_idle(&self) -> bool {
+        self.served.get() && self.in_flight.get() == 0
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let counters = &(self.worker).0;
+        counters.connections.fetch_sub(1, Ordering::Relaxed);
+        if self.is_idle() {
+            counters.keep_alive.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Tracks request until its response body is dropped
+struct RequestGuard {
+    worker: Worker,
+    conn: Option<ConnectionMetrics>,
+}
+
+impl RequestGuard {
+    fn new(worker: Worker, conn: Option<ConnectionMetrics>) -> Self {
+        let counters = &worker.0;
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        if let Some(ref conn) = conn {
+            let conn = &conn.0;
+            if conn.is_idle() {
+                counters.keep_alive.fetch_sub(1, Ordering::Relaxed);
+            }
+            conn.in_flight.set(conn.in_flight.get() + 1);
+            conn.served.set(true);
+        }
+        RequestGuard { worker, conn }
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        let counters = &self.worker.0;
+        counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+        if let Some(ref conn) = self.conn {
+            let conn = &conn.0;
+            conn.in_flight.set(conn.in_flight.get() - 1);
+            if conn.is_idle() {
+                counters.keep_alive.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Request counting wrapper for application service factory
+pub(crate) struct Metered<S, B> {
+    worker: Option<Worker>,
+    factory: S,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> Metered<S, B> {
+    pub(crate) fn new(worker: Option<Worker>, factory: S) -> Self {
+        Metered {
+            worker,
+            factory,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, B> ServiceFactory for Metered<S, B>
+where
+    S: ServiceFactory<Request = Request>,
+    S::Response: Into<Response<B>>,
+    B: MessageBody,
+{
+    type Config = S::Config;
+    type Request = Request;
+    type Response = Response<MeteredBody<B>>;
+    type Error = S::Error;
+    type InitError = S::InitError;
+    type Service = MeteredService<S::Service, B>;
+    type Future = MeteredFactoryFuture<S::Future, B>;
+
+    fn new_service(&self, cfg: S::Config) -> Self::Future {
+        MeteredFactoryFuture {
+            fut: self.factory.new_service(cfg),
+            worker: self.worker.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub(crate) struct MeteredFactoryFuture<F, B> {
+    #[pin]
+    fut: F,
+    worker: Option<Worker>,
+    _t: PhantomData<B>,
+}
+
+impl<F, S, E, B> Future for MeteredFactoryFuture<F, B>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<MeteredService<S, B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.fut.poll(cx) {
+            Poll::Ready(Ok(service)) => Poll::Ready(Ok(MeteredService {
+                worker: this.worker.take(),
+                service,
+                _t: PhantomData,
+            })),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub(crate) struct MeteredService<S, B> {
+    worker: Option<Worker>,
+    service: S,
+    _t: PhantomData<B>,
+}
+
+impl<S, B> Service for MeteredService<S, B>
+where
+    S: Service<Request = Request>,
+    S::Response: Into<Response<B>>,
+    B: MessageBody,
+{
+    type Request = Request;
+    type Response = Response<MeteredBody<B>>;
+    type Error = S::Error;
+    type Future = MeteredFuture<S::Future, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let guard = self.worker.clone().map(|worker| {
+            let conn = req
+                .extensions()
+                .get::<ConnectionExtensions>()
+                .and_then(|ext| ext.get::<ConnectionMetrics>().cloned());
+            RequestGuard::new(worker, conn)
+        });
+        MeteredFuture {
+            fut: self.service.call(req),
+            guard,
+            _t: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub(crate) struct MeteredFuture<F, B> {
+    #[pin]
+    fut: F,
+    guard: Option<RequestGuard>,
+    _t: PhantomData<B>,
+}
+
+impl<F, R, E, B> Future for MeteredFuture<F, B>
+where
+    F: Future<Output = Result<R, E>>,
+    R: Into<Response<B>>,
+{
+    type Output = Result<Response<MeteredBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.fut.poll(cx) {
+            Poll::Ready(Ok(res)) => {
+                let guard = this.guard.take();
+                Poll::Ready(Ok(res.into().map_body(|_, body| {
+                    ResponseBody::Body(MeteredBody {
+                        body,
+                        _guard: guard,
+                    })
+                })))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Response body that keeps request tracked until body is dropped
+pub(crate) struct MeteredBody<B> {
+    body: ResponseBody<B>,
+    _guard: Option<RequestGuard>,
+}
+
+impl<B: MessageBody> MessageBody for MeteredBody<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        self.body.poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_http::body::Body;
+    use actix_http::test::TestRequest;
+    use actix_service::fn_service;
+    use futures::future::{ok, pending};
+
+    #[actix_rt::test]
+    async fn test_metrics() {
+        let metrics = ServerMetrics::new(IdleLimit::new(0));
+        let worker = metrics.worker();
+        assert_eq!(metrics.workers().len(), 1);
+
+        let srv = fn_service(|_: Request| pending::<Result<Response, ()>>());
+        let mut srv = Metered::<_, Body>::new(Some(worker.clone()), srv)
+            .new_service(())
+            .await
+            .unwrap();
+
+        let mut ext = Extensions::new();
+        worker.connect(&mut ext);
+        let conn = ext.remove::<ConnectionMetrics>().unwrap();
+        assert_eq!(metrics.total().connections, 1);
+
+        let req = srv.call(TestRequest::default().finish());
+        let guard = RequestGuard::new(worker.clone(), Some(conn.clone()));
+        let m = metrics.total();
+        assert_eq!(m.requests_in_flight, 2);
+        assert_eq!(m.total_requests, 2);
+        assert_eq!(m.keep_alive_connections, 0);
+
+        drop(req);
+        drop(guard);
+        let m = metrics.total();
+        assert_eq!(m.requests_in_flight, 0);
+        assert_eq!(m.keep_alive_connections, 1);
+
+        drop(conn);
+        let m = metrics.total();
+        assert_eq!(m.connections, 0);
+        assert_eq!(m.keep_alive_connections, 0);
+        assert_eq!(m.total_requests, 2);
+
+        // worker is removed once its services are dropped
+        drop(srv);
+        drop(worker);
+        assert!(metrics.workers().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_metrics_body() {
+        let metrics = ServerMetrics::new(IdleLimit::new(0));
+        let srv = fn_service(|_: Request| ok::<_, ()>(Response::Ok().body("test")));
+        let mut srv = Metered::<_, Body>::new(Some(metrics.worker()), srv)
+            .new_service(())
+            .await
+            .unwrap();
+
+        let res = srv.call(TestRequest::default().finish()).await.unwrap();
+        assert_eq!(metrics.total().requests_in_flight, 1);
+        assert_eq!(res.body().size(), BodySize::Sized(4));
+
+        drop(res);
+        assert_eq!(metrics.total().requests_in_flight, 0);
+        assert_eq!(metrics.total().total_requests, 1);
+    }
+}
@@ -0,0 +1,193 @@
+//! Middleware for limiting size of request body
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_http::error::PayloadError;
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures::future::{ok, Either, Ready};
+use futures::Stream;
+
+use crate::dev::Payload;
+use crate::error::Error;
+use crate::http::header::CONTENT_LENGTH;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// `Middleware` for limiting size of request body.
+///
+/// Requests with `Content-Length` over the limit are rejected with
+/// `413 Payload Too Large` response before handler is called. Payload of
+/// other requests is counted while it is read, and reading fails with
+/// `PayloadError::Overflow` once limit is exceeded, so limit applies to
+/// any extractor and to handlers reading raw payload.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// # fn main() {
+/// let app = App::new()
+///     .wrap(middleware::BodyLimit::new(1024 * 1024))
+///     .route("/upload", web::post().to(|body: web::Bytes| HttpResponse::Ok()));
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct BodyLimit {
+    limit: u64,
+}
+
+impl BodyLimit {
+    /// Construct `BodyLimit` middleware with limit in bytes.
+    pub fn new(limit: u64) -> BodyLimit {
+        BodyLimit { limit }
+    }
+}
+
+impl<S, B> Transform<S> for BodyLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BodyLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(BodyLimitMiddleware {
+            service,
+            limit: self.limit,
+        })
+    }
+}
+
+pub struct BodyLimitMiddleware<S> {
+    service: S,
+    limit: u64,
+}
+
+impl<S, B> Service for BodyLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let length = req
+            .headers()
+            .get(&CONTENT_LENGTH)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<u64>().ok());
+        if length.map_or(false, |length| length > self.limit) {
+            log::debug!("Request body is too large. Request path: {}", req.path());
+            return Either::Right(ok(req.error_response(PayloadError::Overflow)));
+        }
+
+        match req.take_payload() {
+            Payload::None => (),
+            payload => req.set_payload(Payload::Stream(Box::pin(LimitedPayload {
+                payload,
+                limit: self.limit,
+                size: 0,
+            }))),
+        }
+        Either::Left(self.service.call(req))
+    }
+}
+
+/// Payload that fails once limit is exceeded
+struct LimitedPayload {
+    payload: Payload,
+    limit: u64,
+    size: u64,
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.size > this.limit {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut this.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.size += chunk.len() as u64;
+                if this.size > this.limit {
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            val => val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_content_length() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(BodyLimit::new(4))
+                .route("/", web::post().to(|_: web::Bytes| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::post().set_payload("1234").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post().set_payload("12345").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_streamed_payload() {
+        let srv = |mut req: ServiceRequest| async move {
+            let mut payload = req.take_payload();
+            let mut size = 0;
+            while let Some(chunk) = futures::StreamExt::next(&mut payload).await {
+                match chunk {
+                    Ok(chunk) => size += chunk.len(),
+                    Err(e) => return Ok::<_, Error>(req.error_response(e)),
+                }
+            }
+            Ok(req.into_response(HttpResponse::Ok().body(size.to_string())))
+        };
+        let mut mw = BodyLimit::new(4)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let mut req = TestRequest::post().set_payload("12345").to_srv_request();
+        req.headers_mut().remove(CONTENT_LENGTH);
+        let resp = call_service(&mut mw, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut req = TestRequest::post().set_payload("123").to_srv_request();
+        req.headers_mut().remove(CONTENT_LENGTH);
+        let resp = call_service(&mut mw, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
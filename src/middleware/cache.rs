@@ -0,0 +1,591 @@
+//! Middleware for caching responses in memory
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_service::{Service, Transform};
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use futures::StreamExt;
+
+use crate::dev::{
+    Body, BodySize, MessageBody, ResponseBody, ServiceRequest, ServiceResponse,
+};
+use crate::error::Error;
+use crate::http::header::{self, HeaderMap, HeaderName};
+use crate::http::{Method, StatusCode};
+use crate::HttpResponse;
+
+/// `Middleware` for caching successful `GET` responses in memory.
+///
+/// Responses are cached by request method, path, query and values of
+/// headers set with `vary_by()`. Only `200 OK` responses with known body
+/// size and without `Set-Cookie` header are cached. Response's
+/// `Cache-Control` header is respected, i.e. responses with `no-store`,
+/// `no-cache` or `private` directives are not cached and `s-maxage` or
+/// `max-age` directive overrides default time to live. Responses with
+/// `Vary` header that lists headers not set with `vary_by()` are not
+/// cached.
+///
+/// Requests with `Authorization` or `Cookie` header are never served from
+/// cache, their responses are cached only if they are marked with
+/// `Cache-Control: public`.
+///
+/// `HttpServer` calls application factory for each worker, a cache created
+/// inside of factory would be filled separately by every worker. Entries
+/// could be removed with [`CacheHandle`](struct.CacheHandle.html), e.g.
+/// after update of resource.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, middleware, App, HttpResponse, HttpServer};
+/// use actix_web::middleware::CacheHandle;
+///
+/// async fn update(cache: web::Data<CacheHandle>) -> HttpResponse {
+///     cache.invalidate("/items");
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// fn main() {
+///     let cache = middleware::ResponseCache::new(Duration::from_secs(5))
+///         .vary_by("accept-encoding")
+///         .max_entries(1000);
+///
+///     HttpServer::new(move || {
+///         App::new()
+///             .data(cache.handle())
+///             .wrap(cache.clone())
+///             .route("/items", web::get().to(|| HttpResponse::Ok().body("items")))
+///             .route("/items", web::post().to(update))
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ResponseCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    ttl: Duration,
+    vary: Vec<HeaderName>,
+    max_entries: usize,
+    max_bytes: usize,
+    max_entry_bytes: usize,
+    store: Arc<Mutex<Store>>,
+}
+
+/// Handle for removing entries from `ResponseCache`
+#[derive(Clone)]
+pub struct CacheHandle(Arc<Mutex<Store>>);
+
+#[derive(Default)]
+struct Store {
+    entries: HashMap<String, Entry>,
+    /// Keys in order of insertion, with sequence number of entry
+    order: VecDeque<(String, u64)>,
+    bytes: usize,
+    seq: u64,
+}
+
+struct Entry {
+    seq: u64,
+    path: String,
+    expires: Instant,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl ResponseCache {
+    /// Construct `ResponseCache` middleware with default time to live
+    /// of entries.
+    pub fn new(ttl: Duration) -> ResponseCache {
+        ResponseCache {
+            inner: Arc::new(Inner {
+                ttl,
+                vary: Vec::new(),
+                max_entries: 10_000,
+                max_bytes: 64 * 1024 * 1024,
+                max_entry_bytes: 1024 * 1024,
+                store: Arc::new(Mutex::new(Store::default())),
+            }),
+        }
+    }
+
+    /// Use value of request header in cache key, e.g. `Accept-Encoding`.
+    pub fn vary_by(mut self, name: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("Invalid header name: {:?}", name));
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .vary
+            .push(name);
+        self
+    }
+
+    /// Set maximum number of cached responses. By default is 10000.
+    pub fn max_entries(mut self, entries: usize) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_entries = entries;
+        self
+    }
+
+    /// Set maximum size of cached bodies, in bytes. By default is 64Mb.
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_bytes = bytes;
+        self
+    }
+
+    /// Set maximum body size of cached response, in bytes. By default is 1Mb.
+    pub fn max_entry_bytes(mut self, bytes: usize) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_entry_bytes = bytes;
+        self
+    }
+
+    /// Get handle for removing cached entries.
+    pub fn handle(&self) -> CacheHandle {
+        CacheHandle(self.inner.store.clone())
+    }
+}
+
+impl CacheHandle {
+    /// Remove cached responses of specified path.
+    pub fn invalidate(&self, path: &str) {
+        self.remove(|entry| entry.path == path);
+    }
+
+    /// Remove cached responses of paths with specified prefix.
+    pub fn invalidate_prefix(&self, prefix: &str) {
+        self.remove(|entry| entry.path.starts_with(prefix));
+    }
+
+    /// Remove all cached responses.
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = Store::default();
+    }
+
+    /// Number of cached responses, including expired ones.
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().entries.len()
+    }
+
+    /// Check if cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn remove<F: Fn(&Entry) -> bool>(&self, f: F) {
+        let mut store = self.0.lock().unwrap();
+        let mut bytes = store.bytes;
+        store.entries.retain(|_, entry| {
+            if f(entry) {
+                bytes -= entry.body.len();
+                false
+            } else {
+                true
+            }
+        });
+        store.bytes = bytes;
+    }
+}
+
+impl Store {
+    fn get(&mut self, key: &str) -> Option<&Entry> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.expires <= Instant::now(),
+            None => return None,
+        };
+        if expired {
+            if let Some(entry) = self.entries.remove(key) {
+                self.bytes -= entry.body.len();
+            }
+            return None;
+        }
+        self.entries.get(key)
+    }
+
+    fn insert(&mut self, key: String, mut entry: Entry, inner: &Inner) {
+        self.seq += 1;
+        entry.seq = self.seq;
+        self.bytes += entry.body.len();
+        if let Some(prev) = self.entries.insert(key.clone(), entry) {
+            self.bytes -= prev.body.len();
+        }
+        self.order.push_back((key, self.seq));
+
+        // evict oldest entries
+        while self.entries.len() > inner.max_entries || self.bytes > inner.max_bytes {
+            let (key, seq) = match self.order.pop_front() {
+                Some(item) => item,
+                None => break,
+            };
+            if self.entries.get(&key).map_or(false, |e| e.seq == seq) {
+                let entry = self.entries.remove(&key).unwrap();
+                self.bytes -= entry.body.len();
+            }
+        }
+        // drop keys of replaced and removed entries
+        if self.order.len() > self.entries.len() * 2 {
+            let entries = &self.entries;
+            self.order
+                .retain(|(key, seq)| entries.get(key).map_or(false, |e| e.seq == *seq));
+        }
+    }
+}
+
+impl Inner {
+    fn key(&self, req: &ServiceRequest) -> String {
+        let mut key = format!("{} {}", req.method(), req.uri());
+        for name in &self.vary {
+            key.push('\n');
+            for val in req.headers().get_all(name) {
+                key.push_str(val.to_str().unwrap_or(""));
+                key.push(',');
+            }
+        }
+        key
+    }
+
+    /// Time to live of response, or `None` if it could not be cached
+    fn ttl<B: MessageBody>(
+        &self,
+        res: &ServiceResponse<B>,
+        credentials: bool,
+    ) -> Option<Duration> {
+        if res.status() != StatusCode::OK
+            || res.headers().contains_key(header::SET_COOKIE)
+        {
+            return None;
+        }
+        // response must not vary by headers that are not part of key
+        for val in res.headers().get_all(header::VARY) {
+            for name in val.to_str().ok()?.split(',') {
+                let name = name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+                match HeaderName::from_bytes(name.to_ascii_lowercase().as_bytes()) {
+                    Ok(ref name) if self.vary.contains(name) => (),
+                    _ => return None,
+                }
+            }
+        }
+        match res.response().body().size() {
+            BodySize::Sized(size) if size <= self.max_entry_bytes => (),
+            BodySize::Sized64(size) if size <= self.max_entry_bytes as u64 => (),
+            BodySize::Empty => (),
+            _ => return None,
+        }
+
+        let mut max_age = None;
+        let mut s_maxage = None;
+        let mut public = false;
+        for val in res.headers().get_all(header::CACHE_CONTROL) {
+            let val = val.to_str().ok()?;
+            for directive in val.split(',') {
+                let mut parts = directive.trim().splitn(2, '=');
+                let name = parts.next().unwrap_or("").to_ascii_lowercase();
+                let secs = parts.next().and_then(|v| v.trim_matches('"').parse().ok());
+                match name.as_str() {
+                    "no-store" | "no-cache" | "private" => return None,
+                    "public" => public = true,
+                    "max-age" => max_age = secs,
+                    "s-maxage" => s_maxage = secs,
+                    _ => (),
+                }
+            }
+        }
+        if credentials && !public {
+            return None;
+        }
+        match s_maxage.or(max_age) {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(self.ttl),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for ResponseCache
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseCacheMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ResponseCacheMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct ResponseCacheMiddleware<S> {
+    service: S,
+    inner: Arc<Inner>,
+}
+
+impl<S, B> Service for ResponseCacheMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if *req.method() != Method::GET {
+            return self.service.call(req).boxed_local();
+        }
+
+        // responses for authenticated requests could be user specific
+        let credentials = req.headers().contains_key(header::AUTHORIZATION)
+            || req.headers().contains_key(header::COOKIE);
+        let key = self.inner.key(&req);
+        if credentials {
+            // never serve cached response to authenticated request
+        } else if let Some(entry) = self.inner.store.lock().unwrap().get(&key) {
+            let mut res =
+                HttpResponse::with_body(entry.status, Body::Bytes(entry.body.clone()));
+            *res.headers_mut() = entry.headers.clone();
+            return ok(req.into_response(res.into_body())).boxed_local();
+        }
+
+        let inner = self.inner.clone();
+        let path = req.path().to_owned();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+            let ttl = match inner.ttl(&res, credentials) {
+                Some(ttl) => ttl,
+                None => return Ok(res),
+            };
+
+            let mut body = res.take_body();
+            let mut bytes = BytesMut::new();
+            while let Some(chunk) = body.next().await {
+                bytes.extend_from_slice(&chunk?);
+            }
+            let bytes = bytes.freeze();
+
+            let entry = Entry {
+                seq: 0,
+                path,
+                expires: Instant::now() + ttl,
+                status: res.status(),
+                headers: res.headers().clone(),
+                body: bytes.clone(),
+            };
+            inner.store.lock().unwrap().insert(key, entry, &inner);
+
+            Ok(res.map_body(|_, _| ResponseBody::Other(Body::Bytes(bytes))))
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_cache() {
+        let cache = ResponseCache::new(Duration::from_secs(60)).vary_by("accept");
+        let handle = cache.handle();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cnt = counter.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(cache)
+                .route(
+                    "/",
+                    web::get().to(move || {
+                        let n = cnt.fetch_add(1, Ordering::Relaxed);
+                        HttpResponse::Ok()
+                            .header("x-n", n.to_string())
+                            .body("hello")
+                    }),
+                )
+                .route(
+                    "/private",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .header(header::CACHE_CONTROL, "private, max-age=60")
+                            .finish()
+                    }),
+                ),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let req = TestRequest::with_uri("/").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.headers().get("x-n").unwrap(), "0");
+            assert_eq!(read_body(resp).await, Bytes::from_static(b"hello"));
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+        // vary header is part of key
+        let req = TestRequest::with_header("accept", "text/plain").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.headers().get("x-n").unwrap(), "1");
+
+        // query is part of key
+        let req = TestRequest::with_uri("/?q=1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.headers().get("x-n").unwrap(), "2");
+        assert_eq!(handle.len(), 3);
+
+        handle.invalidate("/");
+        assert!(handle.is_empty());
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.headers().get("x-n").unwrap(), "3");
+
+        let req = TestRequest::with_uri("/private").to_request();
+        let _ = call_service(&mut srv, req).await;
+        assert_eq!(handle.len(), 1);
+
+        // non GET requests are not cached
+        let req = TestRequest::post().uri("/").to_request();
+        let _ = call_service(&mut srv, req).await;
+        assert_eq!(handle.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_private_responses() {
+        let cache = ResponseCache::new(Duration::from_secs(60)).vary_by("accept");
+        let handle = cache.handle();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cnt = counter.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(cache)
+                .route(
+                    "/user",
+                    web::get().to(move |req: crate::HttpRequest| {
+                        let n = cnt.fetch_add(1, Ordering::Relaxed);
+                        let user = req
+                            .headers()
+                            .get(header::AUTHORIZATION)
+                            .map_or("anonymous", |v| v.to_str().unwrap())
+                            .to_owned();
+                        HttpResponse::Ok().header("x-n", n.to_string()).body(user)
+                    }),
+                )
+                .route(
+                    "/public",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .header(header::CACHE_CONTROL, "public, max-age=60")
+                            .body("public")
+                    }),
+                )
+                .route(
+                    "/vary",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .header(header::VARY, "accept-language")
+                            .finish()
+                    }),
+                )
+                .route(
+                    "/vary-key",
+                    web::get().to(|| {
+                        HttpResponse::Ok().header(header::VARY, "Accept").finish()
+                    }),
+                ),
+        )
+        .await;
+
+        // authenticated responses are not cached or served from cache
+        let req = TestRequest::with_uri("/user")
+            .header(header::AUTHORIZATION, "alice")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"alice"));
+        assert!(handle.is_empty());
+
+        let req = TestRequest::with_uri("/user").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"anonymous"));
+        assert_eq!(handle.len(), 1);
+
+        let req = TestRequest::with_uri("/user")
+            .header(header::COOKIE, "session=bob")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.headers().get("x-n").unwrap(), "2");
+
+        // public responses of authenticated requests are cached
+        let req = TestRequest::with_uri("/public")
+            .header(header::AUTHORIZATION, "alice")
+            .to_request();
+        let _ = call_service(&mut srv, req).await;
+        assert_eq!(handle.len(), 2);
+
+        // response varies by header that is not part of key
+        let req = TestRequest::with_uri("/vary").to_request();
+        let _ = call_service(&mut srv, req).await;
+        assert_eq!(handle.len(), 2);
+
+        let req = TestRequest::with_uri("/vary-key").to_request();
+        let _ = call_service(&mut srv, req).await;
+        assert_eq!(handle.len(), 3);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let cache = ResponseCache::new(Duration::from_secs(60))
+            .max_entries(2)
+            .max_bytes(10);
+        let entry = |path: &str, body: &'static [u8]| Entry {
+            seq: 0,
+            path: path.to_owned(),
+            expires: Instant::now() + Duration::from_secs(60),
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body),
+        };
+
+        let inner = &cache.inner;
+        let mut store = inner.store.lock().unwrap();
+        store.insert("a".to_owned(), entry("/a", b"1234"), inner);
+        store.insert("b".to_owned(), entry("/b", b"1234"), inner);
+        store.insert("c".to_owned(), entry("/c", b"1234"), inner);
+        assert!(store.get("a").is_none());
+        assert!(store.get("b").is_some());
+        assert_eq!(store.bytes, 8);
+
+        store.insert("d".to_owned(), entry("/d", b"12345678"), inner);
+        assert_eq!(store.entries.len(), 1);
+        assert_eq!(store.bytes, 8);
+    }
+}
@@ -0,0 +1,295 @@
+//! Middleware for converting panics to responses
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::Error;
+use crate::request::RequestSnapshot;
+use crate::{HttpRequest, HttpResponse};
+
+/// `Middleware` for converting panics to `500 Internal Server Error`
+/// responses.
+///
+/// Middleware catches panics of inner services and handlers, both while
+/// response future is created and while it is polled, so panic does not
+/// drop connection. Hook function could be set for reporting panics, and
+/// response function for rendering custom response.
+///
+/// Panics could be caught only if application is built with
+/// `panic = "unwind"`, which is default. Panics that happen while response
+/// body is streamed are not caught.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::CatchPanic::new()
+///                 .hook(|panic| {
+///                     eprintln!(
+///                         "Panic {:?} at {} {}",
+///                         panic.message(),
+///                         panic.request().method(),
+///                         panic.request().path()
+///                     );
+///                 })
+///                 .response(|_| HttpResponse::InternalServerError().body("Oops")),
+///         )
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct CatchPanic {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    hook: Option<Box<dyn Fn(&CaughtPanic)>>,
+    response: Option<Box<dyn Fn(&CaughtPanic) -> HttpResponse>>,
+}
+
+/// Panic that was caught by `CatchPanic` middleware.
+pub struct CaughtPanic {
+    request: HttpRequest,
+    payload: Box<dyn Any + Send>,
+}
+
+impl CaughtPanic {
+    /// Request that caused panic.
+    ///
+    /// Original request is lost on panic, so this request contains method,
+    /// uri, version and peer address of original request, but not headers,
+    /// payload or match info.
+    pub fn request(&self) -> &HttpRequest {
+        &self.request
+    }
+
+    /// Panic payload.
+    pub fn payload(&self) -> &(dyn Any + Send) {
+        &*self.payload
+    }
+
+    /// Panic message, if payload is a string.
+    pub fn message(&self) -> Option<&str> {
+        if let Some(msg) = self.payload.downcast_ref::<&'static str>() {
+            Some(msg)
+        } else if let Some(msg) = self.payload.downcast_ref::<String>() {
+            Some(msg.as_str())
+        } else {
+            None
+        }
+    }
+}
+
+impl CatchPanic {
+    /// Construct `CatchPanic` middleware.
+    pub fn new() -> CatchPanic {
+        CatchPanic::default()
+    }
+
+    /// Set function that is called for each caught panic, i.e. for alerting.
+    pub fn hook<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&CaughtPanic) + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .hook = Some(Box::new(f));
+        self
+    }
+
+    /// Set function that creates response for caught panic. By default
+    /// empty `500 Internal Server Error` response is sent.
+    pub fn response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&CaughtPanic) -> HttpResponse + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .response = Some(Box::new(f));
+        self
+    }
+}
+
+impl Inner {
+    fn handle<B>(
+        &self,
+        snapshot: RequestSnapshot,
+        payload: Box<dyn Any + Send>,
+    ) -> ServiceResponse<B> {
+        let panic = CaughtPanic {
+            request: snapshot.into_request(),
+            payload,
+        };
+        log::error!(
+            "Panic while handling request: {}. Request path: {}",
+            panic.message().unwrap_or("Box<Any>"),
+            panic.request.path()
+        );
+
+        if let Some(ref hook) = self.hook {
+            hook(&panic);
+        }
+        let res = match self.response {
+            Some(ref f) => f(&panic),
+            None => HttpResponse::InternalServerError().finish(),
+        };
+        ServiceResponse::new(panic.request, res.into_body())
+    }
+}
+
+impl<S, B> Transform<S> for CatchPanic
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CatchPanicMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CatchPanicMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for CatchPanicMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let (http_req, payload) = req.into_parts();
+        let snapshot = http_req.snapshot();
+        let mut req = ServiceRequest::new(http_req);
+        req.set_payload(payload);
+        let inner = self.inner.clone();
+
+        let service = &mut self.service;
+        let fut = match panic::catch_unwind(AssertUnwindSafe(|| service.call(req))) {
+            Ok(fut) => fut,
+            Err(payload) => return ok(inner.handle(snapshot, payload)).boxed_local(),
+        };
+
+        async move {
+            match AssertUnwindSafe(fut).catch_unwind().await {
+                Ok(res) => res,
+                Err(payload) => Ok(inner.handle(snapshot, payload)),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_catch_panic() {
+        let panics = Rc::new(RefCell::new(Vec::new()));
+        let panics2 = panics.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(
+                    CatchPanic::new()
+                        .hook(move |panic| {
+                            panics2.borrow_mut().push(format!(
+                                "{} {}: {}",
+                                panic.request().method(),
+                                panic.request().path(),
+                                panic.message().unwrap()
+                            ))
+                        })
+                        .response(|_| HttpResponse::InternalServerError().body("panic")),
+                )
+                .route("/", web::get().to(|| HttpResponse::Ok()))
+                .route(
+                    "/sync",
+                    web::get().to(|| -> HttpResponse { panic!("sync panic") }),
+                )
+                .route(
+                    "/async",
+                    web::post().to(|| async {
+                        actix_rt::time::delay_for(std::time::Duration::from_millis(10))
+                            .await;
+                        if true {
+                            panic!("async panic {}", 1);
+                        }
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/sync").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(read_body(resp).await, web::Bytes::from_static(b"panic"));
+
+        let req = TestRequest::post().uri("/async").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // application keeps working
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        assert_eq!(
+            *panics.borrow(),
+            vec![
+                "GET /sync: sync panic".to_owned(),
+                "POST /async: async panic 1".to_owned()
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_default_response() {
+        let mut srv = init_service(App::new().wrap(CatchPanic::new()).route(
+            "/",
+            web::get().to(|| -> HttpResponse { panic!("sync panic") }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(read_body(resp).await.is_empty());
+    }
+}
@@ -0,0 +1,392 @@
+//! Middleware for short-circuiting requests to failing resources
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::Error;
+use crate::http::header;
+use crate::HttpResponse;
+
+/// Key of requests that do not match any resource
+const UNMATCHED: &str = "unmatched";
+
+/// `Middleware` for protecting failing resources and their dependencies.
+///
+/// Middleware tracks failures of each resource, i.e. errors and responses
+/// with `5xx` status, including timeouts reported by `Timeout` middleware.
+/// If ratio of failures within `window` exceeds `threshold`, circuit of
+/// the resource opens and requests are rejected with
+/// `503 Service Unavailable` response with `Retry-After` header, without
+/// calling handler. After `open_duration` single probe request is passed
+/// to handler, circuit is closed if it succeeds, otherwise it opens again.
+///
+/// State is kept per worker thread.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::CircuitBreaker::new()
+///                 .threshold(0.5)
+///                 .min_requests(20)
+///                 .open_duration(Duration::from_secs(30)),
+///         )
+///         .wrap(middleware::Timeout::new(Duration::from_secs(5)))
+///         .route("/report", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    threshold: f64,
+    min_requests: u32,
+    window: Duration,
+    open_duration: Duration,
+    circuits: RefCell<HashMap<String, Circuit>>,
+}
+
+enum Circuit {
+    Closed {
+        started: Instant,
+        total: u32,
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen,
+}
+
+impl Circuit {
+    fn closed() -> Circuit {
+        Circuit::Closed {
+            started: Instant::now(),
+            total: 0,
+            failures: 0,
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker {
+            inner: Rc::new(Inner {
+                threshold: 0.5,
+                min_requests: 20,
+                window: Duration::from_secs(10),
+                open_duration: Duration::from_secs(30),
+                circuits: RefCell::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Construct `CircuitBreaker` middleware.
+    pub fn new() -> CircuitBreaker {
+        CircuitBreaker::default()
+    }
+
+    /// Set ratio of failed requests that opens circuit. By default is `0.5`.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .threshold = threshold;
+        self
+    }
+
+    /// Set minimal number of requests within window for opening circuit.
+    /// By default is 20.
+    pub fn min_requests(mut self, requests: u32) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .min_requests = requests;
+        self
+    }
+
+    /// Set duration of window, in which requests are counted. By default is
+    /// 10 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .window = window;
+        self
+    }
+
+    /// Set duration of rejecting requests, before probe request is passed.
+    /// By default is 30 seconds.
+    pub fn open_duration(mut self, duration: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .open_duration = duration;
+        self
+    }
+}
+
+impl Inner {
+    /// Check if request could be passed to handler and if it is a probe,
+    /// returns time until circuit could be probed otherwise.
+    fn acquire(&self, key: &str) -> Result<bool, Duration> {
+        let mut circuits = self.circuits.borrow_mut();
+        let circuit = match circuits.get_mut(key) {
+            Some(circuit) => circuit,
+            None => return Ok(false),
+        };
+
+        match *circuit {
+            Circuit::Closed { .. } => Ok(false),
+            Circuit::Open { until } => {
+                let now = Instant::now();
+                if now >= until {
+                    *circuit = Circuit::HalfOpen;
+                    Ok(true)
+                } else {
+                    Err(until - now)
+                }
+            }
+            // probe is in progress
+            Circuit::HalfOpen => Err(Duration::from_secs(1)),
+        }
+    }
+
+    /// Record result of request
+    fn record(&self, key: &str, probe: bool, failed: bool) {
+        let mut circuits = self.circuits.borrow_mut();
+        let circuit = circuits
+            .entry(key.to_owned())
+            .or_insert_with(Circuit::closed);
+
+        match *circuit {
+            Circuit::Closed {
+                ref mut started,
+                ref mut total,
+                ref mut failures,
+            } => {
+                if started.elapsed() >= self.window {
+                    *started = Instant::now();
+                    *total = 0;
+                    *failures = 0;
+                }
+                *total += 1;
+                if failed {
+                    *failures += 1;
+                }
+                if *total >= self.min_requests
+                    && f64::from(*failures) / f64::from(*total) >= self.threshold
+                {
+                    log::warn!("Circuit of resource {} is open", key);
+                    *circuit = self.open();
+                }
+            }
+            Circuit::HalfOpen if probe => {
+                *circuit = if failed {
+                    self.open()
+                } else {
+                    log::info!("Circuit of resource {} is closed", key);
+                    Circuit::closed()
+                };
+            }
+            // request was started before circuit opened
+            Circuit::Open { .. } | Circuit::HalfOpen => (),
+        }
+    }
+
+    /// Release probe that did not complete, e.g. on client disconnect
+    fn release(&self, key: &str) {
+        if let Some(circuit) = self.circuits.borrow_mut().get_mut(key) {
+            if let Circuit::HalfOpen = *circuit {
+                *circuit = Circuit::Open {
+                    until: Instant::now(),
+                };
+            }
+        }
+    }
+
+    fn open(&self) -> Circuit {
+        Circuit::Open {
+            until: Instant::now() + self.open_duration,
+        }
+    }
+}
+
+/// Releases probe if request handling is cancelled
+struct Pending {
+    inner: Rc<Inner>,
+    key: String,
+    probe: bool,
+    done: bool,
+}
+
+impl Pending {
+    fn finish(mut self, failed: bool) {
+        self.done = true;
+        self.inner.record(&self.key, self.probe, failed);
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if self.probe && !self.done {
+            self.inner.release(&self.key);
+        }
+    }
+}
+
+impl<S, B> Transform<S> for CircuitBreaker
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CircuitBreakerMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CircuitBreakerMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct CircuitBreakerMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for CircuitBreakerMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let key = req
+            .resource_map()
+            .match_pattern(req.path())
+            .unwrap_or_else(|| UNMATCHED.to_owned());
+
+        let probe = match self.inner.acquire(&key) {
+            Ok(probe) => probe,
+            Err(retry) => {
+                log::debug!("Circuit is open. Request path: {}", req.path());
+                // round up, so retry does not happen before circuit could be probed
+                let secs = retry.as_secs() + u64::from(retry.subsec_nanos() > 0);
+                let res = HttpResponse::ServiceUnavailable()
+                    .header(header::RETRY_AFTER, secs.to_string())
+                    .finish();
+                return ok(req.into_response(res.into_body())).boxed_local();
+            }
+        };
+
+        let pending = Pending {
+            inner: self.inner.clone(),
+            key,
+            probe,
+            done: false,
+        };
+        let fut = self.service.call(req);
+
+        async move {
+            let res = fut.await;
+            let failed = match res {
+                Ok(ref res) => res.status().is_server_error(),
+                Err(_) => true,
+            };
+            pending.finish(failed);
+            res
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_circuit_breaker() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(
+                    CircuitBreaker::new()
+                        .min_requests(4)
+                        .threshold(0.5)
+                        .open_duration(Duration::from_millis(50)),
+                )
+                .route(
+                    "/items/{id}",
+                    web::get().to(|path: web::Path<(u32,)>| {
+                        if path.0 < 10 {
+                            HttpResponse::Ok()
+                        } else {
+                            HttpResponse::InternalServerError()
+                        }
+                    }),
+                )
+                .route("/other", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        for path in &["/items/1", "/items/11", "/items/2", "/items/12"] {
+            let req = TestRequest::with_uri(path).to_request();
+            let _ = call_service(&mut srv, req).await;
+        }
+
+        // circuit of resource is open
+        let req = TestRequest::with_uri("/items/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "1");
+
+        // other resources are not affected
+        let req = TestRequest::with_uri("/other").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // failed probe opens circuit again
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        let req = TestRequest::with_uri("/items/11").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let req = TestRequest::with_uri("/items/1").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // successful probe closes circuit
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        for _ in 0..2 {
+            let req = TestRequest::with_uri("/items/1").to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+}
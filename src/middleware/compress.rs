@@ -3,12 +3,13 @@ use std::cmp;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::str::FromStr;
 use std::task::{Context, Poll};
 
-use actix_http::body::MessageBody;
+use actix_http::body::{BodySize, MessageBody};
 use actix_http::encoding::Encoder;
-use actix_http::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use actix_http::http::header::{ContentEncoding, ACCEPT_ENCODING, CONTENT_TYPE};
 use actix_http::Error;
 use actix_service::{Service, Transform};
 use futures::future::{ok, Ready};
@@ -23,6 +24,13 @@ use crate::service::{ServiceRequest, ServiceResponse};
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// `br` encoding is negotiated only if `brotli` feature is enabled
+/// (default enabled).
+///
+/// Small responses and responses of excluded content types could be
+/// skipped with `CompressConfig`. Config could be overridden for a scope
+/// or resource by registering it with `app_data()`.
+///
 /// ```rust
 /// use actix_web::{web, middleware, App, HttpResponse};
 ///
@@ -36,12 +44,35 @@ use crate::service::{ServiceRequest, ServiceResponse};
 ///         );
 /// }
 /// ```
-pub struct Compress(ContentEncoding);
+pub struct Compress {
+    encoding: ContentEncoding,
+    brotli_quality: Option<u32>,
+    config: Rc<CompressConfig>,
+}
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress(encoding)
+        Compress {
+            encoding,
+            brotli_quality: None,
+            config: Rc::new(CompressConfig::default()),
+        }
+    }
+
+    /// Set compression policy, by default all responses are compressed.
+    pub fn config(mut self, config: CompressConfig) -> Self {
+        self.config = Rc::new(config);
+        self
+    }
+
+    /// Set quality of brotli compression, in range 0-11. By default is 3.
+    ///
+    /// Higher quality produces smaller responses, but is significantly
+    /// slower.
+    pub fn brotli_quality(mut self, quality: u32) -> Self {
+        self.brotli_quality = Some(quality);
+        self
     }
 }
 
@@ -51,6 +82,85 @@ impl Default for Compress {
     }
 }
 
+/// Compression policy of `Compress` middleware.
+///
+/// Policy applies to negotiated encoding only, encoding set explicitly with
+/// `BodyEncoding::encoding()` is always used.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::middleware::CompressConfig;
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Compress::default().config(
+///                 CompressConfig::default()
+///                     .min_size(1024)
+///                     .exclude(mime::IMAGE_STAR)
+///                     .exclude(mime::APPLICATION_OCTET_STREAM),
+///             ),
+///         )
+///         .service(
+///             web::resource("/stream")
+///                 // compress everything for this resource
+///                 .app_data(CompressConfig::default())
+///                 .route(web::get().to(|| HttpResponse::Ok())),
+///         );
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompressConfig {
+    min_size: u64,
+    excluded: Vec<mime::Mime>,
+}
+
+impl CompressConfig {
+    /// Do not compress responses with body smaller than `size` bytes.
+    ///
+    /// Responses of unknown size, i.e. streaming responses, are
+    /// always compressed.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    /// Do not compress responses of specified content type. Wildcard
+    /// subtype, e.g. `image/*`, matches all subtypes.
+    pub fn exclude(mut self, mime: mime::Mime) -> Self {
+        self.excluded.push(mime);
+        self
+    }
+
+    fn skip<B: MessageBody>(&self, resp: &ServiceResponse<B>) -> bool {
+        let size = match resp.response().body().size() {
+            BodySize::Sized(size) => size as u64,
+            BodySize::Sized64(size) => size,
+            _ => u64::max_value(),
+        };
+        if size < self.min_size {
+            return true;
+        }
+
+        if self.excluded.is_empty() {
+            return false;
+        }
+        let ct = resp
+            .headers()
+            .get(&CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<mime::Mime>().ok());
+        if let Some(ct) = ct {
+            self.excluded.iter().any(|m| {
+                (m.type_() == mime::STAR || m.type_() == ct.type_())
+                    && (m.subtype() == mime::STAR || m.subtype() == ct.subtype())
+            })
+        } else {
+            false
+        }
+    }
+}
+
 impl<S, B> Transform<S> for Compress
 where
     B: MessageBody,
@@ -66,7 +176,9 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(CompressMiddleware {
             service,
-            encoding: self.0,
+            encoding: self.encoding,
+            brotli_quality: self.brotli_quality,
+            config: self.config.clone(),
         })
     }
 }
@@ -74,6 +186,8 @@ where
 pub struct CompressMiddleware<S> {
     service: S,
     encoding: ContentEncoding,
+    brotli_quality: Option<u32>,
+    config: Rc<CompressConfig>,
 }
 
 impl<S, B> Service for CompressMiddleware<S>
@@ -104,6 +218,8 @@ where
 
         CompressResponse {
             encoding,
+            brotli_quality: self.brotli_quality,
+            config: self.config.clone(),
             fut: self.service.call(req),
             _t: PhantomData,
         }
@@ -120,6 +236,8 @@ where
     #[pin]
     fut: S::Future,
     encoding: ContentEncoding,
+    brotli_quality: Option<u32>,
+    config: Rc<CompressConfig>,
     _t: PhantomData<B>,
 }
 
@@ -138,12 +256,26 @@ where
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
                 } else {
-                    *this.encoding
+                    let config = resp
+                        .request()
+                        .app_data::<CompressConfig>()
+                        .unwrap_or(&**this.config);
+                    if config.skip(&resp) {
+                        ContentEncoding::Identity
+                    } else {
+                        *this.encoding
+                    }
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let level = if enc == ContentEncoding::Br {
+                    *this.brotli_quality
+                } else {
+                    None
+                };
+
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_level(enc, level, head, body)
+                })))
             }
             Err(e) => Poll::Ready(Err(e)),
         }
@@ -189,6 +321,12 @@ impl AcceptEncoding {
             0 => return None,
             _ => ContentEncoding::from(parts[0]),
         };
+        #[cfg(not(feature = "brotli"))]
+        {
+            if encoding == ContentEncoding::Br {
+                return None;
+            }
+        }
         let quality = match parts.len() {
             1 => encoding.quality(),
             _ => match f64::from_str(parts[1]) {
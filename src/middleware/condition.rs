@@ -1,9 +1,15 @@
 //! `Middleware` for conditionally enables another middleware.
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use actix_service::{Service, Transform};
 use futures::future::{ok, Either, FutureExt, LocalBoxFuture};
 
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::Error;
+use crate::guard::Guard;
+
 /// `Middleware` for conditionally enables another middleware.
 /// The controled middleware must not change the `Service` interfaces.
 /// This means you cannot control such middlewares like `Logger` or `Compress`.
@@ -93,6 +99,128 @@ where
     }
 }
 
+/// `Middleware` that applies another middleware only to requests matching
+/// a guard.
+/// Same as with `Condition`, the controlled middleware must not change the
+/// `Service` interfaces.
+///
+/// ## Usage
+///
+/// ```rust
+/// use actix_web::middleware::{DefaultHeaders, Filter};
+/// use actix_web::{guard, App};
+///
+/// # fn main() {
+/// // skip middleware for health checks
+/// let app = App::new().wrap(Filter::new(
+///     guard::Not(guard::fn_guard(|head| head.uri.path() == "/health")),
+///     DefaultHeaders::new().header("Cache-Control", "no-store"),
+/// ));
+/// # }
+/// ```
+pub struct Filter<T> {
+    trans: T,
+    guard: Rc<dyn Guard>,
+}
+
+impl<T> Filter<T> {
+    pub fn new<G: Guard + 'static>(guard: G, trans: T) -> Self {
+        Self {
+            trans,
+            guard: Rc::new(guard),
+        }
+    }
+}
+
+impl<S, T, B> Transform<S> for Filter<T>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    T: Transform<
+        SharedService<S>,
+        Request = ServiceRequest,
+        Response = ServiceResponse<B>,
+        Error = Error,
+    >,
+    T::Future: 'static,
+    T::InitError: 'static,
+    T::Transform: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = T::InitError;
+    type Transform = FilterMiddleware<T::Transform, S>;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = Rc::new(RefCell::new(service));
+        let fut = self.trans.new_transform(SharedService(service.clone()));
+        let guard = self.guard.clone();
+
+        async move {
+            let enabled = fut.await?;
+            Ok(FilterMiddleware {
+                enabled,
+                service,
+                guard,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// Service shared by middleware and `FilterMiddleware`
+#[doc(hidden)]
+pub struct SharedService<S>(Rc<RefCell<S>>);
+
+impl<S: Service> Service for SharedService<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.0.borrow_mut().call(req)
+    }
+}
+
+pub struct FilterMiddleware<E, S> {
+    enabled: E,
+    service: Rc<RefCell<S>>,
+    guard: Rc<dyn Guard>,
+}
+
+impl<E, S> Service for FilterMiddleware<E, S>
+where
+    E: Service<Request = ServiceRequest>,
+    S: Service<Request = ServiceRequest, Response = E::Response, Error = E::Error>,
+{
+    type Request = ServiceRequest;
+    type Response = E::Response;
+    type Error = E::Error;
+    type Future = Either<E::Future, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.enabled.poll_ready(cx)?.is_pending() {
+            return Poll::Pending;
+        }
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if self.guard.check_request(&req) {
+            Either::Left(self.enabled.call(req))
+        } else {
+            Either::Right(self.service.borrow_mut().call(req))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::IntoService;
@@ -148,4 +276,27 @@ mod tests {
             test::call_service(&mut mw, TestRequest::default().to_srv_request()).await;
         assert_eq!(resp.headers().get(CONTENT_TYPE), None);
     }
+
+    #[actix_rt::test]
+    async fn test_filter() {
+        let srv = |req: ServiceRequest| {
+            ok(req.into_response(HttpResponse::InternalServerError().finish()))
+        };
+
+        let mw =
+            ErrorHandlers::new().handler(StatusCode::INTERNAL_SERVER_ERROR, render_500);
+
+        let mut mw = Filter::new(crate::guard::Header("x-render", "1"), mw)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header("x-render", "1").to_srv_request();
+        let resp = test::call_service(&mut mw, req).await;
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
+
+        let resp =
+            test::call_service(&mut mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(resp.headers().get(CONTENT_TYPE), None);
+    }
 }
@@ -0,0 +1,255 @@
+//! Request context propagation to outgoing client requests
+use std::time::Duration;
+
+use actix_http::body::Body;
+use actix_http::RequestHead;
+use actix_rt::time::{delay_until, Instant};
+use awc::error::SendRequestError;
+use awc::{ClientResponse, Middleware as ClientMiddleware, Next};
+use futures::future::{err, ok, select, Either, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::Payload;
+use crate::error::Error;
+use crate::extract::FromRequest;
+use crate::http::header::HeaderMap;
+use crate::request::HttpRequest;
+use crate::HttpMessage;
+
+use super::{RequestDeadline, TraceContext};
+
+/// Context of request that is propagated to outgoing requests, trace
+/// context assigned by [`Tracing`](struct.Tracing.html) middleware and
+/// deadline assigned by [`Deadline`](struct.Deadline.html) middleware.
+///
+/// Unlike its parts, `RequestContext` argument never fails, context of
+/// request that passed neither middleware is empty and is propagated as
+/// no headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    trace: Option<TraceContext>,
+    deadline: Option<RequestDeadline>,
+}
+
+impl RequestContext {
+    /// Get context of request.
+    pub fn get<T: HttpMessage>(req: &T) -> RequestContext {
+        RequestContext {
+            trace: TraceContext::get(req),
+            deadline: RequestDeadline::get(req),
+        }
+    }
+
+    /// Get context of request, which is handled by current thread.
+    pub fn current() -> RequestContext {
+        RequestContext {
+            trace: TraceContext::current(),
+            deadline: RequestDeadline::current(),
+        }
+    }
+
+    /// Trace context of request span
+    pub fn trace(&self) -> Option<&TraceContext> {
+        self.trace.as_ref()
+    }
+
+    /// Trace identifier as 32 hex digits
+    pub fn trace_id(&self) -> Option<String> {
+        self.trace.as_ref().map(|ctx| ctx.trace_id())
+    }
+
+    /// Deadline of request
+    pub fn deadline(&self) -> Option<&RequestDeadline> {
+        self.deadline.as_ref()
+    }
+
+    /// Remaining budget of request, `None` if request has no deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.as_ref().map(|deadline| deadline.remaining())
+    }
+
+    /// Add trace context and remaining budget headers.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if let Some(ref trace) = self.trace {
+            trace.inject(headers);
+        }
+        if let Some(ref deadline) = self.deadline {
+            deadline.inject(headers);
+        }
+    }
+
+    /// Add headers of context of current request to outgoing request head.
+    pub fn inject_current(head: &mut RequestHead) {
+        RequestContext::current().inject(&mut head.headers);
+    }
+}
+
+impl FromRequest for RequestContext {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(RequestContext::get(req))
+    }
+}
+
+/// Client middleware that propagates context of current request.
+///
+/// Trace context and remaining budget headers are added to outgoing
+/// requests that are sent while request is handled, and outgoing request
+/// fails with `SendRequestError::Timeout` if it does not complete before
+/// deadline of current request, so timeout budget is kept across services.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::client::Client;
+/// use actix_web::middleware::{self, PropagateContext};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn index(client: web::Data<Client>) -> HttpResponse {
+///     // `traceparent` and `x-request-timeout-ms` headers are added to request
+///     match client.get("http://localhost:8081/").send().await {
+///         Ok(res) => HttpResponse::build(res.status()).finish(),
+///         Err(_) => HttpResponse::GatewayTimeout().finish(),
+///     }
+/// }
+///
+/// #[actix_rt::main]
+/// async fn main() {
+///     let client = Client::build().wrap(PropagateContext).finish();
+///
+///     let app = App::new()
+///         .data(client)
+///         .wrap(middleware::Tracing::new())
+///         .wrap(middleware::Deadline::new().max(Duration::from_secs(5)))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PropagateContext;
+
+impl ClientMiddleware for PropagateContext {
+    fn handle(
+        &self,
+        mut head: RequestHead,
+        body: Body,
+        next: Next,
+    ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+        let ctx = RequestContext::current();
+        ctx.inject(&mut head.headers);
+
+        let deadline = match ctx.deadline {
+            Some(deadline) => deadline,
+            None => return next.send(head, body),
+        };
+        if deadline.is_expired() {
+            return err(SendRequestError::Timeout).boxed_local();
+        }
+
+        let fut = next.send(head, body);
+        let delay = delay_until(Instant::from_std(deadline.at()));
+        async move {
+            match select(fut, delay).await {
+                Either::Left((res, _)) => res,
+                Either::Right(_) => Err(SendRequestError::Timeout),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use futures::future::pending;
+
+    use super::*;
+    use crate::client::Client;
+    use crate::http::StatusCode;
+    use crate::middleware::{Deadline, Tracing};
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    /// Records headers of outgoing request, request never completes
+    struct Capture(Rc<RefCell<Option<HeaderMap>>>);
+
+    impl ClientMiddleware for Capture {
+        fn handle(
+            &self,
+            head: RequestHead,
+            _: Body,
+            _: Next,
+        ) -> LocalBoxFuture<'static, Result<ClientResponse, SendRequestError>> {
+            *self.0.borrow_mut() = Some(head.headers.clone());
+            pending().boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_propagate_context() {
+        let headers = Rc::new(RefCell::new(None));
+        let headers2 = headers.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(Tracing::new())
+                .wrap(Deadline::new().max(Duration::from_millis(100)))
+                .route(
+                    "/",
+                    web::get().to(move |ctx: RequestContext| {
+                        let client = Client::build()
+                            .wrap(PropagateContext)
+                            .wrap(Capture(headers2.clone()))
+                            .finish();
+                        async move {
+                            assert_eq!(RequestContext::current(), ctx);
+                            match client.get("http://localhost/").send().await {
+                                Err(SendRequestError::Timeout) => {
+                                    HttpResponse::Ok().body(ctx.trace_id().unwrap())
+                                }
+                                _ => HttpResponse::InternalServerError().finish(),
+                            }
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let trace_id = read_body(resp).await;
+
+        let headers = headers.borrow_mut().take().unwrap();
+        let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.contains(std::str::from_utf8(&trace_id).unwrap()));
+        let millis: u64 = headers
+            .get("x-request-timeout-ms")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(millis <= 100);
+    }
+
+    #[actix_rt::test]
+    async fn test_empty_context() {
+        let mut srv = init_service(App::new().route(
+            "/",
+            web::get().to(|ctx: RequestContext| {
+                assert_eq!(ctx, RequestContext::default());
+                assert!(ctx.remaining().is_none());
+                HttpResponse::Ok()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
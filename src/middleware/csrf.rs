@@ -0,0 +1,525 @@
+//! Middleware for protection against cross-site request forgery
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use bytes::{Bytes, BytesMut};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
+use futures::{stream, StreamExt};
+use rand::Rng;
+use url::form_urlencoded;
+
+use crate::cookie::{Cookie, SameSite};
+use crate::dev::Payload;
+use crate::error::{Error, ErrorForbidden, ErrorInternalServerError, PayloadError};
+use crate::extract::FromRequest;
+use crate::http::header::HeaderName;
+use crate::http::Method;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// Default token cookie name
+const CSRF_COOKIE: &str = "csrf-token";
+
+/// Default token header name
+const X_CSRF_TOKEN: &str = "x-csrf-token";
+
+/// Default token form field name
+const CSRF_FIELD: &str = "csrf_token";
+
+/// Max size of urlencoded form that is read for token field
+const FORM_LIMIT: usize = 65_536;
+
+/// Length of token in bytes, before encoding
+const TOKEN_LENGTH: usize = 32;
+
+/// `Middleware` for protection against cross-site request forgery.
+///
+/// Middleware implements double submit cookie pattern. Each client gets
+/// random token in a cookie, requests with unsafe methods (i.e. other than
+/// `GET`, `HEAD`, `OPTIONS` and `TRACE`) must send the same token in
+/// `X-Csrf-Token` header, otherwise they are rejected with
+/// `403 Forbidden` response. Cross-site pages could not read the cookie,
+/// so they could not forge the header.
+///
+/// Html forms could not set headers, so urlencoded form requests without
+/// token header could send token in `csrf_token` form field instead.
+/// Middleware reads form of up to 64KB to check the field, and passes it
+/// on to handlers unchanged. Multipart forms must use the header.
+///
+/// Token of current request is available with `CsrfToken` extractor, e.g.
+/// for rendering it in templates or sending it to javascript clients.
+/// Cookie is not http only, so client side code could read it directly.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::middleware::CsrfToken;
+///
+/// async fn form(token: CsrfToken) -> HttpResponse {
+///     HttpResponse::Ok().content_type("text/html").body(format!(
+///         "<form method=\"post\"><input type=\"hidden\" name=\"csrf_token\" value=\"{}\"></form>",
+///         token
+///     ))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Csrf::new().exempt("/webhook"))
+///         .route("/form", web::get().to(form))
+///         .route("/form", web::post().to(|| HttpResponse::Ok()))
+///         .route("/webhook", web::post().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Csrf {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    cookie_name: String,
+    cookie_path: String,
+    cookie_domain: Option<String>,
+    cookie_secure: bool,
+    header: HeaderName,
+    form_field: String,
+    exempt: HashSet<String>,
+}
+
+impl Default for Csrf {
+    fn default() -> Self {
+        Csrf {
+            inner: Rc::new(Inner {
+                cookie_name: CSRF_COOKIE.to_owned(),
+                cookie_path: "/".to_owned(),
+                cookie_domain: None,
+                cookie_secure: true,
+                header: HeaderName::from_static(X_CSRF_TOKEN),
+                form_field: CSRF_FIELD.to_owned(),
+                exempt: HashSet::new(),
+            }),
+        }
+    }
+}
+
+impl Csrf {
+    /// Construct `Csrf` middleware.
+    pub fn new() -> Csrf {
+        Csrf::default()
+    }
+
+    /// Set name of token cookie, by default `csrf-token`
+    pub fn cookie_name<S: Into<String>>(mut self, name: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .cookie_name = name.into();
+        self
+    }
+
+    /// Set path of token cookie, by default `/`
+    pub fn cookie_path<S: Into<String>>(mut self, path: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .cookie_path = path.into();
+        self
+    }
+
+    /// Set domain of token cookie
+    pub fn cookie_domain<S: Into<String>>(mut self, domain: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .cookie_domain = Some(domain.into());
+        self
+    }
+
+    /// Set `secure` attribute of token cookie. By default is `true`, cookie
+    /// is sent over `https` only.
+    pub fn cookie_secure(mut self, secure: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .cookie_secure = secure;
+        self
+    }
+
+    /// Set name of token header, by default `X-Csrf-Token`
+    pub fn header<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => {
+                Rc::get_mut(&mut self.inner)
+                    .expect("Multiple copies exist")
+                    .header = key
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Set name of token field of urlencoded forms, by default `csrf_token`
+    pub fn form_field<S: Into<String>>(mut self, name: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .form_field = name.into();
+        self
+    }
+
+    /// Do not verify token of requests to specified path, e.g. webhooks
+    /// that are authenticated by other means.
+    pub fn exempt<S: Into<String>>(mut self, path: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .exempt
+            .insert(path.into());
+        self
+    }
+}
+
+impl<S, B> Transform<S> for Csrf
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CsrfMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for CsrfMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let token = req
+            .cookie(&self.inner.cookie_name)
+            .map(|cookie| cookie.value().to_owned())
+            .filter(|token| !token.is_empty());
+        let (token, is_new) = match token {
+            Some(token) => (token, false),
+            None => (generate(), true),
+        };
+
+        // token is verified with header, or with field of urlencoded form
+        let mut read_form = false;
+        if !is_safe(req.method()) && !self.inner.exempt.contains(req.path()) {
+            let header = req.headers().get(&self.inner.header);
+            read_form = !is_new && header.is_none() && is_form(&req);
+            let valid = !is_new
+                && header.map_or(false, |val| eq(val.as_bytes(), token.as_bytes()));
+            if !valid && !read_form {
+                return ok(forbidden(req)).boxed_local();
+            }
+        }
+
+        let token = Rc::new(token);
+        req.extensions_mut().insert(CsrfToken(token.clone()));
+
+        let inner = self.inner.clone();
+        let srv = self.service.clone();
+
+        async move {
+            if read_form {
+                let body = match read_body(req.take_payload()).await {
+                    Ok(body) => body,
+                    Err(e) => return Ok(req.error_response(e)),
+                };
+                let valid = form_urlencoded::parse(&body).any(|(name, value)| {
+                    name == inner.form_field.as_str()
+                        && eq(value.as_bytes(), token.as_bytes())
+                });
+                if !valid {
+                    return Ok(forbidden(req));
+                }
+                let body = stream::once(ok::<_, PayloadError>(body));
+                req.set_payload(Payload::Stream(Box::pin(body)));
+            }
+
+            let fut = { srv.borrow_mut().call(req) };
+            let mut res = fut.await?;
+
+            if is_new {
+                let mut cookie =
+                    Cookie::new(inner.cookie_name.clone(), (*token).clone());
+                cookie.set_path(inner.cookie_path.clone());
+                cookie.set_secure(inner.cookie_secure);
+                cookie.set_same_site(SameSite::Strict);
+                if let Some(ref domain) = inner.cookie_domain {
+                    cookie.set_domain(domain.clone());
+                }
+                let _ = res.response_mut().add_cookie(&cookie);
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+/// CSRF token of current request, assigned by [`Csrf`](struct.Csrf.html)
+/// middleware.
+///
+/// Token is available to handlers of all requests, including safe ones, so
+/// `CsrfToken` argument of page handler renders token into forms and
+/// scripts. Outside of `Csrf` middleware there is no token to render, so
+/// extraction fails with `500 Internal Server Error` response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrfToken(Rc<String>);
+
+impl CsrfToken {
+    /// Get token of current request, if any.
+    pub fn get<T: HttpMessage>(req: &T) -> Option<CsrfToken> {
+        req.extensions().get::<CsrfToken>().cloned()
+    }
+
+    /// Token as string slice
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Deref for CsrfToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for CsrfToken {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match CsrfToken::get(req) {
+            Some(token) => ok(token),
+            None => err(ErrorInternalServerError(
+                "CSRF token is not available, register Csrf middleware",
+            )),
+        }
+    }
+}
+
+/// Reject request with missing or invalid token
+fn forbidden<B>(req: ServiceRequest) -> ServiceResponse<B> {
+    log::debug!(
+        "CSRF token is missing or invalid. Request path: {}",
+        req.path()
+    );
+    req.error_response(ErrorForbidden("CSRF token is missing or invalid"))
+}
+
+fn is_form(req: &ServiceRequest) -> bool {
+    req.content_type()
+        .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+}
+
+/// Read urlencoded form, so token field could be checked
+async fn read_body(mut payload: Payload) -> Result<Bytes, PayloadError> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > FORM_LIMIT {
+            return Err(PayloadError::Overflow);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+/// Methods that must not change state, so they are not verified
+fn is_safe(method: &Method) -> bool {
+    *method == Method::GET
+        || *method == Method::HEAD
+        || *method == Method::OPTIONS
+        || *method == Method::TRACE
+}
+
+/// Generate random url safe token
+fn generate() -> String {
+    let bytes: [u8; TOKEN_LENGTH] = rand::thread_rng().gen();
+    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Constant time comparison
+fn eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_csrf() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(Csrf::new().exempt("/webhook"))
+                .route(
+                    "/",
+                    web::get().to(|token: CsrfToken| {
+                        HttpResponse::Ok().body(token.to_string())
+                    }),
+                )
+                .route("/", web::post().to(|| HttpResponse::Ok()))
+                .route("/webhook", web::post().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        // token is issued on first request
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == CSRF_COOKIE)
+            .unwrap()
+            .into_owned();
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.http_only(), None);
+        let token = cookie.value().to_owned();
+        assert_eq!(token.len(), 43);
+
+        // existing token is reused
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert!(resp.headers().get(header::SET_COOKIE).is_none());
+        let body = crate::test::read_body(resp).await;
+        assert_eq!(&body[..], token.as_bytes());
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header(X_CSRF_TOKEN, token.as_str())
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header(X_CSRF_TOKEN, "invalid")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // header without cookie
+        let req = TestRequest::post()
+            .header(X_CSRF_TOKEN, token.as_str())
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post().uri("/webhook").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_csrf_form() {
+        let mut srv = init_service(App::new().wrap(Csrf::new()).route(
+            "/",
+            web::post().to(|form: web::Form<HashMap<String, String>>| {
+                HttpResponse::Ok().body(form["name"].clone())
+            }),
+        ))
+        .await;
+        let cookie = Cookie::new(CSRF_COOKIE, "token");
+
+        // form is passed to handler after token field is checked
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload("name=test&csrf_token=token")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = crate::test::read_body(resp).await;
+        assert_eq!(&body[..], b"test");
+
+        for payload in &["name=test&csrf_token=invalid", "name=test"] {
+            let req = TestRequest::post()
+                .cookie(cookie.clone())
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(*payload)
+                .to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+
+        // field is not checked for other content types
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("csrf_token=token")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post()
+            .cookie(cookie)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(vec![b'a'; FORM_LIMIT + 1])
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_extractor() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(CsrfToken::from_request(&req, &mut pl).await.is_err());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert!(eq(b"abc", b"abc"));
+        assert!(!eq(b"abc", b"abd"));
+        assert!(!eq(b"abc", b"ab"));
+    }
+}
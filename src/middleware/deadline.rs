@@ -0,0 +1,437 @@
+//! Middleware for request deadlines
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_http::RequestHead;
+use actix_rt::time::{self, delay_until};
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, select, Either, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError, InternalError};
+use crate::extract::FromRequest;
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::StatusCode;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::types::{CancelReason, Cancellation};
+use crate::{HttpMessage, HttpResponse};
+
+const X_REQUEST_TIMEOUT_MS: &str = "x-request-timeout-ms";
+
+/// Budget of requests is never longer than one day, even if `max()` is not set
+const MAX_BUDGET: Duration = Duration::from_secs(24 * 60 * 60);
+
+thread_local! {
+    static CURRENT: RefCell<Option<RequestDeadline>> = RefCell::new(None);
+}
+
+/// `Middleware` for end-to-end request deadlines.
+///
+/// Time budget of request is read from `X-Request-Timeout-Ms` header, that
+/// contains remaining milliseconds, and is limited by `max()` duration.
+/// If request handling does not complete before the deadline, handling is
+/// cancelled and middleware resolves to an error, which is rendered as
+/// `504 Gateway Timeout` response. Requests that arrive with exhausted
+/// budget are rejected without calling inner service. Requests without
+/// header, if `max()` is not set, are handled without deadline. Budget of
+/// header is limited to one day if `max()` is not set.
+///
+/// Deadline could be accessed by using `RequestDeadline` extractor, and
+/// with `RequestDeadline::current()` while request is handled. Remaining
+/// budget is propagated to outgoing requests of clients that are built with
+/// [`PropagateContext`](struct.PropagateContext.html) middleware.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::middleware::RequestDeadline;
+///
+/// async fn index(deadline: RequestDeadline) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("{:?} left", deadline.remaining()))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Deadline::new().max(Duration::from_secs(10)))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Deadline {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    header: HeaderName,
+    max: Option<Duration>,
+    status: StatusCode,
+}
+
+impl Default for Deadline {
+    fn default() -> Self {
+        Deadline {
+            inner: Rc::new(Inner {
+                header: HeaderName::from_static(X_REQUEST_TIMEOUT_MS),
+                max: None,
+                status: StatusCode::GATEWAY_TIMEOUT,
+            }),
+        }
+    }
+}
+
+impl Deadline {
+    /// Construct `Deadline` middleware.
+    pub fn new() -> Deadline {
+        Deadline::default()
+    }
+
+    /// Set name of header that contains remaining milliseconds of request
+    /// budget. By default is `X-Request-Timeout-Ms`.
+    ///
+    /// Same header is used for propagating budget to outgoing requests.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = header;
+        self
+    }
+
+    /// Set max budget of request, it is used for requests without header.
+    pub fn max(mut self, max: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max = Some(max);
+        self
+    }
+
+    /// Set status code of timeout response. By default is
+    /// `504 Gateway Timeout`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = status;
+        self
+    }
+}
+
+impl<S, B> Transform<S> for Deadline
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = DeadlineMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DeadlineMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct DeadlineMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for DeadlineMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let incoming = req
+            .headers()
+            .get(&self.inner.header)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.trim().parse().ok())
+            .map(Duration::from_millis);
+        let budget = match (incoming, self.inner.max) {
+            (Some(budget), Some(max)) => budget.min(max),
+            (budget, max) => match budget.or(max) {
+                Some(budget) => budget,
+                None => return self.service.call(req).boxed_local(),
+            },
+        };
+        let budget = budget.min(MAX_BUDGET);
+
+        let status = self.inner.status;
+        if budget == Duration::from_millis(0) {
+            log::debug!("Request deadline is exceeded. Request path: {}", req.path());
+            return err(timeout_error(status)).boxed_local();
+        }
+
+        let at = match Instant::now().checked_add(budget) {
+            Some(at) => at,
+            None => return self.service.call(req).boxed_local(),
+        };
+        let deadline = RequestDeadline {
+            at,
+            header: self.inner.header.clone(),
+        };
+        req.extensions_mut().insert(deadline.clone());
+        let path = req.path().to_string();
+        let cancel = Cancellation::get(req.head(), req.app_config());
+
+        // call inner service within deadline, it could start outgoing requests
+        let _guard = Entered::enter(deadline.clone());
+        let fut = Box::pin(self.service.call(req));
+
+        let delay = delay_until(time::Instant::from_std(deadline.at));
+        let fut = async move {
+            match select(fut, delay).await {
+                Either::Left((res, _)) => res,
+                Either::Right(_) => {
+                    log::debug!("Request deadline is exceeded. Request path: {}", path);
+                    cancel.cancel(CancelReason::Timeout);
+                    Err(timeout_error(status))
+                }
+            }
+        }
+        .boxed_local();
+
+        Scoped { fut, deadline }.boxed_local()
+    }
+}
+
+fn timeout_error(status: StatusCode) -> Error {
+    let res = HttpResponse::build(status).body("Request deadline exceeded");
+    InternalError::from_response("Request deadline exceeded", res).into()
+}
+
+/// Future that sets current deadline while it is polled
+struct Scoped<F> {
+    fut: F,
+    deadline: RequestDeadline,
+}
+
+impl<F: Future + Unpin> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = Entered::enter(self.deadline.clone());
+        Pin::new(&mut self.fut).poll(cx)
+    }
+}
+
+/// Restores previous current deadline on drop, including unwinding
+struct Entered(Option<RequestDeadline>);
+
+impl Entered {
+    fn enter(deadline: RequestDeadline) -> Entered {
+        Entered(CURRENT.with(|current| current.replace(Some(deadline))))
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let prev = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Deadline of request, assigned by [`Deadline`](struct.Deadline.html)
+/// middleware.
+///
+/// Requests without `X-Request-Timeout-Ms` header have no deadline unless
+/// `Deadline::max()` is set, so `RequestDeadline` argument fails with
+/// `500 Internal Server Error` for them. Handlers that serve both kinds of
+/// requests should use `RequestDeadline::get()` and treat `None` as no
+/// time limit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestDeadline {
+    at: Instant,
+    header: HeaderName,
+}
+
+impl RequestDeadline {
+    /// Get deadline of request, if any.
+    pub fn get<T: HttpMessage>(req: &T) -> Option<RequestDeadline> {
+        req.extensions().get::<RequestDeadline>().cloned()
+    }
+
+    /// Get deadline of request, which is handled by current thread, if any.
+    pub fn current() -> Option<RequestDeadline> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Add header with remaining budget of current request to outgoing
+    /// request head, if current request has deadline.
+    pub fn inject_current(head: &mut RequestHead) {
+        if let Some(deadline) = RequestDeadline::current() {
+            deadline.inject(&mut head.headers);
+        }
+    }
+
+    /// Instant when request handling is cancelled
+    pub fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// Remaining budget of request
+    pub fn remaining(&self) -> Duration {
+        self.at
+            .checked_duration_since(Instant::now())
+            .unwrap_or_else(|| Duration::from_millis(0))
+    }
+
+    /// Check if budget of request is exhausted
+    pub fn is_expired(&self) -> bool {
+        self.at <= Instant::now()
+    }
+
+    /// Add header with remaining milliseconds of budget.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let millis = self.remaining().as_millis().to_string();
+        if let Ok(value) = HeaderValue::from_str(&millis) {
+            headers.insert(self.header.clone(), value);
+        }
+    }
+}
+
+impl FromRequest for RequestDeadline {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match RequestDeadline::get(req) {
+            Some(deadline) => ok(deadline),
+            None => err(ErrorInternalServerError(
+                "Request deadline is not available, register Deadline middleware",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::delay_for;
+
+    use super::*;
+    use crate::responder::tests::BodyTest;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_deadline() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(Deadline::new().max(Duration::from_millis(500)))
+                .route(
+                    "/",
+                    web::get().to(|deadline: RequestDeadline| async move {
+                        assert_eq!(RequestDeadline::current().unwrap(), deadline);
+
+                        let mut head = RequestHead::default();
+                        RequestDeadline::inject_current(&mut head);
+                        let millis: u64 = head
+                            .headers
+                            .get(X_REQUEST_TIMEOUT_MS)
+                            .unwrap()
+                            .to_str()
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        HttpResponse::Ok().body(millis.to_string())
+                    }),
+                )
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        delay_for(Duration::from_millis(200)).await;
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        // budget is limited by max
+        let req = TestRequest::with_uri("/")
+            .header(X_REQUEST_TIMEOUT_MS, "60000")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        let millis: u64 = std::str::from_utf8(&read_body(resp).await)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(millis <= 500 && millis > 400, "{}", millis);
+        assert!(RequestDeadline::current().is_none());
+
+        let req = TestRequest::with_uri("/slow").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/slow")
+            .header(X_REQUEST_TIMEOUT_MS, "50")
+            .to_request();
+        let resp: HttpResponse = srv.call(req).await.err().unwrap().into();
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(resp.body().bin_ref(), b"Request deadline exceeded");
+
+        // exhausted budget
+        let req = TestRequest::with_uri("/")
+            .header(X_REQUEST_TIMEOUT_MS, "0")
+            .to_request();
+        let resp: HttpResponse = srv.call(req).await.err().unwrap().into();
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[actix_rt::test]
+    async fn test_no_deadline() {
+        let mut srv = init_service(App::new().wrap(Deadline::new()).route(
+            "/",
+            web::get().to(|req: HttpRequest| {
+                assert!(RequestDeadline::get(&req).is_none());
+                HttpResponse::Ok()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(X_REQUEST_TIMEOUT_MS, "invalid")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_huge_budget() {
+        let mut srv = init_service(App::new().wrap(Deadline::new()).route(
+            "/",
+            web::get().to(|deadline: RequestDeadline| {
+                assert!(deadline.remaining() <= MAX_BUDGET);
+                HttpResponse::Ok()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(X_REQUEST_TIMEOUT_MS, u64::max_value().to_string())
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
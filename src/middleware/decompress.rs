@@ -0,0 +1,293 @@
+//! `Middleware` for decompressing request body.
+use std::task::{Context, Poll};
+
+use actix_http::encoding::Decoder;
+use actix_http::error::InternalError;
+use actix_http::http::header::{
+    ContentEncoding, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
+};
+use actix_service::{Service, Transform};
+use futures::future::{ok, Either, Ready};
+
+use crate::dev::Payload;
+use crate::error::Error;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::{HttpMessage, HttpResponse};
+
+#[cfg(feature = "brotli")]
+const SUPPORTED: &str = "gzip, deflate, br";
+#[cfg(not(feature = "brotli"))]
+const SUPPORTED: &str = "gzip, deflate";
+
+/// `Middleware` for decompressing request body.
+///
+/// Request body with `gzip`, `deflate` or `br` content encoding is
+/// decompressed before extractors and handlers read it, `Content-Encoding`
+/// and `Content-Length` headers are removed from request. Requests with
+/// other content encodings are rejected with `415 Unsupported Media Type`
+/// response that lists supported encodings in `Accept-Encoding` header.
+///
+/// Decompressed body is limited to protect from decompression bombs,
+/// reading body fails with `PayloadError::Overflow` once decompressed size
+/// exceeds limit, by default 16MB, or decompressed body is more than
+/// `ratio` times larger than compressed body, by default 100.
+///
+/// `br` encoding is supported only if `brotli` feature is enabled
+/// (default enabled).
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Decompress::new().limit(1024 * 1024).ratio(20))
+///         .route("/upload", web::post().to(|body: web::Bytes| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Decompress {
+    limit: u64,
+    ratio: u64,
+}
+
+impl Decompress {
+    /// Create new `Decompress` middleware with default limits.
+    pub fn new() -> Self {
+        Decompress {
+            limit: 16_777_216,
+            ratio: 100,
+        }
+    }
+
+    /// Set max size of decompressed body in bytes.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set max ratio of decompressed and compressed body size.
+    ///
+    /// Ratio is checked for bodies that are decompressed to more than 64KB.
+    pub fn ratio(mut self, ratio: u64) -> Self {
+        self.ratio = ratio;
+        self
+    }
+}
+
+impl Default for Decompress {
+    fn default() -> Self {
+        Decompress::new()
+    }
+}
+
+impl<S, B> Transform<S> for Decompress
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = DecompressMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DecompressMiddleware {
+            service,
+            limit: self.limit,
+            ratio: self.ratio,
+        })
+    }
+}
+
+pub struct DecompressMiddleware<S> {
+    service: S,
+    limit: u64,
+    ratio: u64,
+}
+
+impl<S, B> Service for DecompressMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let encoding = match req.headers().get(&CONTENT_ENCODING) {
+            Some(val) => match val.to_str().ok().and_then(parse_encoding) {
+                Some(encoding) => encoding,
+                None => {
+                    log::debug!(
+                        "Unsupported request content encoding {:?}. Request path: {}",
+                        val,
+                        req.path()
+                    );
+                    let res = HttpResponse::UnsupportedMediaType()
+                        .header(ACCEPT_ENCODING, SUPPORTED)
+                        .finish();
+                    let err = InternalError::from_response(
+                        "Unsupported request content encoding",
+                        res,
+                    );
+                    return Either::Right(ok(req.error_response(err)));
+                }
+            },
+            None => ContentEncoding::Identity,
+        };
+        if encoding == ContentEncoding::Identity {
+            return Either::Left(self.service.call(req));
+        }
+
+        req.headers_mut().remove(&CONTENT_ENCODING);
+        req.headers_mut().remove(&CONTENT_LENGTH);
+        match req.take_payload() {
+            Payload::None => (),
+            payload => {
+                let decoder = Decoder::new(payload, encoding)
+                    .limit(self.limit)
+                    .ratio(self.ratio);
+                req.set_payload(Payload::Stream(Box::pin(decoder)))
+            }
+        }
+        Either::Left(self.service.call(req))
+    }
+}
+
+/// Encoding of `Content-Encoding` header, `None` for unsupported encodings.
+/// Only single encoding is supported, `identity` is ignored.
+fn parse_encoding(val: &str) -> Option<ContentEncoding> {
+    let mut encodings = val
+        .split(',')
+        .map(|enc| enc.trim())
+        .filter(|enc| !enc.is_empty() && !enc.eq_ignore_ascii_case("identity"));
+    let encoding = match encodings.next() {
+        Some(enc) => enc,
+        None => return Some(ContentEncoding::Identity),
+    };
+    if encodings.next().is_some() {
+        return None;
+    }
+
+    if encoding.eq_ignore_ascii_case("gzip") || encoding.eq_ignore_ascii_case("x-gzip") {
+        Some(ContentEncoding::Gzip)
+    } else if encoding.eq_ignore_ascii_case("deflate") {
+        Some(ContentEncoding::Deflate)
+    } else if cfg!(feature = "brotli") && encoding.eq_ignore_ascii_case("br") {
+        Some(ContentEncoding::Br)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::Bytes;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(data).unwrap();
+        Bytes::from(enc.finish().unwrap())
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!(parse_encoding("gzip"), Some(ContentEncoding::Gzip));
+        assert_eq!(parse_encoding(" Deflate "), Some(ContentEncoding::Deflate));
+        assert_eq!(
+            parse_encoding("identity, gzip"),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(parse_encoding("identity"), Some(ContentEncoding::Identity));
+        assert_eq!(parse_encoding("compress"), None);
+        assert_eq!(parse_encoding("gzip, deflate"), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_decompress() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(Decompress::new().limit(100_000).ratio(10))
+                .route(
+                    "/",
+                    web::post().to(|req: crate::HttpRequest, body: Bytes| {
+                        assert!(req.headers().get(CONTENT_ENCODING).is_none());
+                        HttpResponse::Ok().body(body)
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(b"hello world"))
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        let req = TestRequest::post().set_payload("plain").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"plain"));
+
+        // decompressed size exceeds limit
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(&vec![b'a'; 200_000]))
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // compression ratio exceeds limit
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(&vec![b'a'; 90_000]))
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "compress")
+            .set_payload("data")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(resp.headers().get(ACCEPT_ENCODING).unwrap(), SUPPORTED);
+    }
+
+    #[actix_rt::test]
+    async fn test_decompression_bomb() {
+        let mut srv =
+            init_service(App::new().wrap(Decompress::new().limit(1_000_000)).route(
+                "/",
+                web::post().to(|body: Bytes| HttpResponse::Ok().body(body)),
+            ))
+            .await;
+
+        // single chunk of ~32KB is decoded to 32MB, decoder must abort
+        // once limit is reached instead of decoding whole chunk
+        let data = gzip(&vec![0; 32 * 1024 * 1024]);
+        assert!(data.len() < 64 * 1024);
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(data)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,4 +1,5 @@
 //! Custom handlers service for responses.
+use std::future::Future;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
@@ -24,7 +25,9 @@ type ErrorHandler<B> = dyn Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse
 ///
 /// You can use `ErrorHandlers::handler()` method  to register a custom error
 /// handler for specific status code. You can modify existing response or
-/// create completely new one.
+/// create completely new one. Use `ErrorHandlers::async_handler()` method
+/// to register handler that awaits other futures, i.e. renders error page
+/// with a template engine or fetches it from other service.
 ///
 /// ## Example
 ///
@@ -79,6 +82,50 @@ impl<B> ErrorHandlers<B> {
             .insert(status, Box::new(handler));
         self
     }
+
+    /// Register async error handler for specified status code
+    ///
+    /// Handler takes original response, so it can read its error with
+    /// `res.response().error()` and its body with `res.take_body()`, and
+    /// could create new response with `res.into_response()`.
+    ///
+    /// ```rust
+    /// use actix_web::middleware::errhandlers::ErrorHandlers;
+    /// use actix_web::{dev, http, web, App, HttpResponse, Result};
+    /// use futures::StreamExt;
+    ///
+    /// async fn render_404(mut res: dev::ServiceResponse) -> Result<dev::ServiceResponse> {
+    ///     let mut body = web::BytesMut::new();
+    ///     let mut stream = res.take_body();
+    ///     while let Some(chunk) = stream.next().await {
+    ///         body.extend_from_slice(&chunk?);
+    ///     }
+    ///     let page = format!(
+    ///         "<h1>Not Found</h1><p>{}</p>",
+    ///         String::from_utf8_lossy(&body)
+    ///     );
+    ///     Ok(res.into_response(
+    ///         HttpResponse::NotFound()
+    ///             .content_type("text/html")
+    ///             .body(page),
+    ///     ))
+    /// }
+    ///
+    /// # fn main() {
+    /// let app = App::new()
+    ///     .wrap(ErrorHandlers::new().async_handler(http::StatusCode::NOT_FOUND, render_404))
+    ///     .route("/", web::get().to(|| HttpResponse::Ok()));
+    /// # }
+    /// ```
+    pub fn async_handler<F, Fut>(self, status: StatusCode, handler: F) -> Self
+    where
+        F: Fn(ServiceResponse<B>) -> Fut + 'static,
+        Fut: Future<Output = Result<ServiceResponse<B>>> + 'static,
+    {
+        self.handler(status, move |res| {
+            Ok(ErrorHandlerResponse::Future(handler(res).boxed_local()))
+        })
+    }
 }
 
 impl<S, B> Transform<S> for ErrorHandlers<B>
@@ -147,7 +194,9 @@ where
 #[cfg(test)]
 mod tests {
     use actix_service::IntoService;
+    use bytes::{Bytes, BytesMut};
     use futures::future::ok;
+    use futures::StreamExt;
 
     use super::*;
     use crate::http::{header::CONTENT_TYPE, HeaderValue, StatusCode};
@@ -203,4 +252,37 @@ mod tests {
             test::call_service(&mut mw, TestRequest::default().to_srv_request()).await;
         assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
     }
+
+    #[actix_rt::test]
+    async fn test_async_handler() {
+        let srv = |req: ServiceRequest| {
+            ok(req.error_response(crate::error::ErrorBadRequest("invalid id")))
+        };
+
+        let mut mw = ErrorHandlers::new()
+            .async_handler(StatusCode::BAD_REQUEST, |mut res| async move {
+                let error = res.response().error().unwrap().to_string();
+                let mut body = BytesMut::new();
+                let mut stream = res.take_body();
+                while let Some(chunk) = stream.next().await {
+                    body.extend_from_slice(&chunk?);
+                }
+                let page = format!("{}: {}", error, String::from_utf8_lossy(&body));
+                Ok(res.into_response(
+                    HttpResponse::UnprocessableEntity()
+                        .content_type("text/html")
+                        .body(page),
+                ))
+            })
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let resp =
+            test::call_service(&mut mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/html");
+        let body = test::read_body(resp).await;
+        assert_eq!(body, Bytes::from_static(b"invalid id: invalid id"));
+    }
 }
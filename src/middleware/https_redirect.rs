@@ -0,0 +1,304 @@
+//! Middleware for redirecting plain http requests to https
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::error::{Error, ErrorBadRequest};
+use crate::http::header::{self, HeaderValue};
+use crate::http::{StatusCode, Uri};
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpResponse;
+
+/// `Middleware` for redirecting plain http requests to https.
+///
+/// Scheme and host of request are resolved with `ConnectionInfo`, so
+/// `Forwarded` and `X-Forwarded-Proto` headers of proxies that terminate
+/// TLS are respected. Configure trusted proxies with
+/// `HttpServer::trusted_proxies()` if application is reachable not only
+/// through proxies, otherwise clients could skip redirect by setting these
+/// headers.
+///
+/// Requests are redirected with `301 Moved Permanently` by default.
+/// `Strict-Transport-Security` header could be set on https responses
+/// with `hsts()`.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::HttpsRedirect::new()
+///                 .preserve_method(true)
+///                 .hsts(Duration::from_secs(31_536_000), true, false),
+///         )
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct HttpsRedirect {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    status: StatusCode,
+    port: Option<u16>,
+    hsts: Option<HeaderValue>,
+}
+
+impl Default for HttpsRedirect {
+    fn default() -> Self {
+        HttpsRedirect {
+            inner: Rc::new(Inner {
+                status: StatusCode::MOVED_PERMANENTLY,
+                port: None,
+                hsts: None,
+            }),
+        }
+    }
+}
+
+impl HttpsRedirect {
+    /// Construct `HttpsRedirect` middleware.
+    pub fn new() -> HttpsRedirect {
+        HttpsRedirect::default()
+    }
+
+    /// Redirect with `308 Permanent Redirect` instead of
+    /// `301 Moved Permanently`, so clients repeat request with the same
+    /// method and body.
+    pub fn preserve_method(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = if value {
+            StatusCode::PERMANENT_REDIRECT
+        } else {
+            StatusCode::MOVED_PERMANENTLY
+        };
+        self
+    }
+
+    /// Set port of https server, if it is not the default one. Port of
+    /// request host is replaced with this port.
+    pub fn port(mut self, port: u16) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .port = Some(port);
+        self
+    }
+
+    /// Set `Strict-Transport-Security` header on https responses, unless
+    /// it is already set by handler.
+    pub fn hsts(
+        mut self,
+        max_age: Duration,
+        include_subdomains: bool,
+        preload: bool,
+    ) -> Self {
+        let mut val = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            val.push_str("; includeSubDomains");
+        }
+        if preload {
+            val.push_str("; preload");
+        }
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .hsts = Some(HeaderValue::try_from(val).unwrap());
+        self
+    }
+}
+
+impl Inner {
+    /// Build https location for request
+    fn location(&self, host: &str, uri: &Uri) -> String {
+        let mut location = String::from("https://");
+        match self.port {
+            Some(port) => {
+                location.push_str(strip_port(host));
+                if port != 443 {
+                    location.push_str(&format!(":{}", port));
+                }
+            }
+            None => location.push_str(host),
+        }
+        match uri.path_and_query() {
+            Some(path) => location.push_str(path.as_str()),
+            None => location.push('/'),
+        }
+        location
+    }
+}
+
+/// Remove port from host, host could be IPv6 address in brackets
+fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(idx) if !host[idx..].contains(']') => &host[..idx],
+        _ => host,
+    }
+}
+
+impl<S, B> Transform<S> for HttpsRedirect
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = HttpsRedirectMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HttpsRedirectMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct HttpsRedirectMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for HttpsRedirectMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let location = {
+            let info = req.connection_info();
+            if info.scheme() == "https" {
+                None
+            } else {
+                Some(self.inner.location(info.host(), req.uri()))
+            }
+        };
+
+        if let Some(location) = location {
+            let res = match HeaderValue::try_from(location) {
+                Ok(location) => {
+                    let res = HttpResponse::build(self.inner.status)
+                        .header(header::LOCATION, location)
+                        .finish();
+                    req.into_response(res.into_body())
+                }
+                Err(_) => req.error_response(ErrorBadRequest("Invalid host")),
+            };
+            return ok(res).boxed_local();
+        }
+
+        let inner = self.inner.clone();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+            if let Some(ref hsts) = inner.hsts {
+                let headers = res.headers_mut();
+                if !headers.contains_key(header::STRICT_TRANSPORT_SECURITY) {
+                    headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_redirect() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(HttpsRedirect::new().hsts(Duration::from_secs(60), true, false))
+                .route("/", web::get().to(|| HttpResponse::Ok()))
+                .route("/test", web::post().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/test?q=1")
+            .header(header::HOST, "example.com")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://example.com/test?q=1"
+        );
+        assert!(!resp
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        // request from proxy that terminates tls
+        let req = TestRequest::default()
+            .header(header::HOST, "example.com")
+            .header("x-forwarded-proto", "https")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=60; includeSubDomains"
+        );
+
+        let req = TestRequest::with_uri("https://example.com/").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_redirect_port() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(HttpsRedirect::new().preserve_method(true).port(8443))
+                .route("/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .header(header::HOST, "example.com:8080")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://example.com:8443/"
+        );
+
+        let req = TestRequest::default()
+            .header(header::HOST, "[::1]")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://[::1]:8443/"
+        );
+    }
+}
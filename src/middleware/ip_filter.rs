@@ -0,0 +1,309 @@
+//! Middleware for filtering requests by client ip address
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, Either, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::{Error, ErrorForbidden};
+use crate::info::{client_ip, network_contains, parse_network, TrustedProxies};
+
+/// `Middleware` for filtering requests by client ip address.
+///
+/// Client address is peer address of connection. `Forwarded` and
+/// `X-Forwarded-For` headers are used only if peer is one of trusted
+/// proxies, set with `IpFilter::trusted_proxies()` or
+/// `HttpServer::trusted_proxies()`. Without trusted proxies forwarding
+/// headers are ignored, since any client could send them.
+///
+/// Request is rejected with `403 Forbidden` response if:
+///
+/// * client address belongs to one of denied networks,
+/// * otherwise, lookup function returns `Some(false)`,
+/// * otherwise, allowed networks are set and client address does not belong
+///   to any of them or could not be resolved.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/admin")
+///             .wrap(
+///                 middleware::IpFilter::new()
+///                     .allow("10.0.0.0/8")
+///                     .allow("::1")
+///                     .deny("10.0.13.0/24"),
+///             )
+///             .route("/", web::get().to(|| HttpResponse::Ok())),
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct IpFilter {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    allow: Vec<(IpAddr, u8)>,
+    deny: Vec<(IpAddr, u8)>,
+    proxies: Option<TrustedProxies>,
+    lookup: Option<Box<dyn Fn(IpAddr, &ServiceRequest) -> Option<bool>>>,
+}
+
+impl IpFilter {
+    /// Construct `IpFilter` middleware that allows all requests.
+    pub fn new() -> IpFilter {
+        IpFilter::default()
+    }
+
+    /// Allow network in CIDR notation, i.e. `10.0.0.0/8`, or single address.
+    ///
+    /// Panics if network is not valid.
+    pub fn allow(mut self, net: &str) -> Self {
+        let net =
+            parse_network(net).unwrap_or_else(|| panic!("Invalid network: {:?}", net));
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .allow
+            .push(net);
+        self
+    }
+
+    /// Deny network in CIDR notation, i.e. `10.0.0.0/8`, or single address.
+    ///
+    /// Panics if network is not valid.
+    pub fn deny(mut self, net: &str) -> Self {
+        let net =
+            parse_network(net).unwrap_or_else(|| panic!("Invalid network: {:?}", net));
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .deny
+            .push(net);
+        self
+    }
+
+    /// Set proxies that are trusted to provide forwarding headers,
+    /// by default proxies of `HttpServer::trusted_proxies()` are used.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .proxies = Some(proxies);
+        self
+    }
+
+    /// Set function for dynamic lookup of client address, e.g. in a
+    /// block list that is updated at runtime.
+    ///
+    /// Function returns `Some(true)` to allow request, `Some(false)` to deny
+    /// it, or `None` to check allowed networks.
+    pub fn lookup<F>(mut self, f: F) -> Self
+    where
+        F: Fn(IpAddr, &ServiceRequest) -> Option<bool> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .lookup = Some(Box::new(f));
+        self
+    }
+}
+
+impl Inner {
+    fn is_allowed(&self, req: &ServiceRequest) -> bool {
+        let proxies = self
+            .proxies
+            .as_ref()
+            .or_else(|| req.app_config().trusted_proxies());
+        let ip = match client_ip(req.head(), proxies) {
+            Some(ip) => ip,
+            None => return self.allow.is_empty(),
+        };
+
+        if network_contains(&self.deny, ip) {
+            return false;
+        }
+        if let Some(allowed) = self.lookup.as_ref().and_then(|f| f(ip, req)) {
+            return allowed;
+        }
+        self.allow.is_empty() || network_contains(&self.allow, ip)
+    }
+}
+
+impl<S, B> Transform<S> for IpFilter
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IpFilterMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IpFilterMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct IpFilterMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for IpFilterMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if self.inner.is_allowed(&req) {
+            Either::Left(self.service.call(req))
+        } else {
+            log::debug!(
+                "Request from {:?} is not allowed. Request path: {}",
+                req.peer_addr(),
+                req.path()
+            );
+            Either::Right(ok(req.error_response(ErrorForbidden("Access denied"))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_ip_filter() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(
+                    IpFilter::new()
+                        .allow("10.0.0.0/8")
+                        .allow("2001:db8::/32")
+                        .deny("10.0.13.0/24")
+                        .lookup(|ip, _| {
+                            if ip == IpAddr::from([192, 0, 2, 1]) {
+                                Some(true)
+                            } else {
+                                None
+                            }
+                        }),
+                )
+                .route("/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        for (addr, status) in &[
+            ("10.1.2.3:1234", StatusCode::OK),
+            ("[2001:db8::1]:1234", StatusCode::OK),
+            ("10.0.13.7:1234", StatusCode::FORBIDDEN),
+            ("192.0.2.1:1234", StatusCode::OK),
+            ("192.0.2.2:1234", StatusCode::FORBIDDEN),
+        ] {
+            let req = TestRequest::default()
+                .peer_addr(addr.parse().unwrap())
+                .to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), *status, "{}", addr);
+        }
+
+        // address could not be resolved
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // spoofed forwarding headers are ignored, no trusted proxies are configured
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.2:1234".parse().unwrap())
+            .header("x-forwarded-for", "10.1.2.3")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.2:1234".parse().unwrap())
+            .header("forwarded", "for=10.1.2.3")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_trusted_proxies() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(
+                    IpFilter::new()
+                        .allow("10.0.0.0/8")
+                        .trusted_proxies(TrustedProxies::new().add("192.0.2.1")),
+                )
+                .route("/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        for (peer, forwarded, status) in &[
+            ("192.0.2.1:1234", "10.1.2.3", StatusCode::OK),
+            (
+                "192.0.2.1:1234",
+                "10.1.2.3, 192.0.2.9",
+                StatusCode::FORBIDDEN,
+            ),
+            ("192.0.2.1:1234", "192.0.2.9", StatusCode::FORBIDDEN),
+            ("192.0.2.2:1234", "10.1.2.3", StatusCode::FORBIDDEN),
+        ] {
+            let req = TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .header("x-forwarded-for", *forwarded)
+                .to_request();
+            let resp = call_service(&mut srv, req).await;
+            assert_eq!(resp.status(), *status, "{} {}", peer, forwarded);
+        }
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.1:1234".parse().unwrap())
+            .header("forwarded", "for=\"10.1.2.3\";proto=https")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_deny_only() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(IpFilter::new().deny("192.0.2.0/24"))
+                .route("/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.2:1234".parse().unwrap())
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,287 @@
+//! Non-blocking access log file writer
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{
+    sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError,
+};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::logger::LogSink;
+
+/// Interval of flushing buffered entries to file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log sink that writes entries to file on background thread.
+///
+/// Entries are passed to writer thread through bounded queue, if queue is
+/// full entry is dropped instead of blocking worker. File is rotated when
+/// its size exceeds `max_size` or when it is open longer than
+/// `rotate_every`, rotated files get numeric suffix, `access.log.1` is
+/// the most recent one.
+///
+/// Clones send entries to the same writer thread, writer constructed in
+/// application factory would start one thread per worker, all of them
+/// appending to and rotating the same file.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use actix_web::middleware::{FileLogWriter, Logger};
+/// use actix_web::{App, HttpServer};
+///
+/// fn main() -> std::io::Result<()> {
+///     let writer = FileLogWriter::builder("access.log")
+///         .max_size(100 * 1024 * 1024)
+///         .rotate_every(Duration::from_secs(24 * 3600))
+///         .keep(7)
+///         .open()?;
+///
+///     HttpServer::new(move || {
+///         App::new().wrap(Logger::default().sink(writer.clone()))
+///     });
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct FileLogWriter {
+    tx: SyncSender<String>,
+    dropped: Arc<AtomicUsize>,
+}
+
+/// Builder of `FileLogWriter`
+pub struct FileLogWriterBuilder {
+    path: PathBuf,
+    max_size: Option<u64>,
+    rotate_every: Option<Duration>,
+    keep: usize,
+    queue: usize,
+}
+
+impl FileLogWriter {
+    /// Create builder of writer to file at specified path.
+    pub fn builder<P: AsRef<Path>>(path: P) -> FileLogWriterBuilder {
+        FileLogWriterBuilder {
+            path: path.as_ref().to_path_buf(),
+            max_size: None,
+            rotate_every: None,
+            keep: 5,
+            queue: 8192,
+        }
+    }
+
+    /// Number of entries dropped because queue was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl LogSink for FileLogWriter {
+    fn write(&self, entry: &str) {
+        match self.tx.try_send(entry.to_owned()) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                log::error!("Access log writer is stopped");
+            }
+        }
+    }
+}
+
+impl FileLogWriterBuilder {
+    /// Rotate file when its size exceeds specified number of bytes.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate file after it is open for specified duration.
+    pub fn rotate_every(mut self, interval: Duration) -> Self {
+        self.rotate_every = Some(interval);
+        self
+    }
+
+    /// Set number of rotated files to keep. By default is 5.
+    pub fn keep(mut self, files: usize) -> Self {
+        self.keep = files;
+        self
+    }
+
+    /// Set maximum number of entries waiting to be written. By default is 8192.
+    pub fn queue(mut self, entries: usize) -> Self {
+        self.queue = entries;
+        self
+    }
+
+    /// Open log file and start writer thread.
+    pub fn open(self) -> io::Result<FileLogWriter> {
+        let (tx, rx) = sync_channel(self.queue);
+        let mut file = LogFile {
+            file: None,
+            size: 0,
+            opened: Instant::now(),
+            cfg: self,
+        };
+        file.open()?;
+
+        thread::Builder::new()
+            .name("actix-web-access-log".to_owned())
+            .spawn(move || file.run(rx))?;
+
+        Ok(FileLogWriter {
+            tx,
+            dropped: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+}
+
+/// State of writer thread
+struct LogFile {
+    file: Option<BufWriter<File>>,
+    size: u64,
+    opened: Instant,
+    cfg: FileLogWriterBuilder,
+}
+
+impl LogFile {
+    fn run(mut self, rx: Receiver<String>) {
+        let mut flushed = Instant::now();
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(entry) => self.write(&entry),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    return;
+                }
+            }
+            if flushed.elapsed() >= FLUSH_INTERVAL {
+                self.flush();
+                flushed = Instant::now();
+            }
+            if self.needs_rotation() {
+                self.rotate();
+            }
+        }
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.cfg.path)?;
+        self.size = file.metadata()?.len();
+        self.opened = Instant::now();
+        self.file = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    fn write(&mut self, entry: &str) {
+        if self.file.is_none() {
+            // previous attempt to reopen file failed
+            if let Err(e) = self.open() {
+                log::error!("Can not open access log file: {}", e);
+                return;
+            }
+        }
+        if let Some(ref mut file) = self.file {
+            match file
+                .write_all(entry.as_bytes())
+                .and_then(|_| file.write_all(b"\n"))
+            {
+                Ok(()) => self.size += entry.len() as u64 + 1,
+                Err(e) => log::error!("Can not write access log entry: {}", e),
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(ref mut file) = self.file {
+            if let Err(e) = file.flush() {
+                log::error!("Can not flush access log file: {}", e);
+            }
+        }
+    }
+
+    fn needs_rotation(&self) -> bool {
+        self.cfg.max_size.map_or(false, |max| self.size >= max)
+            || self
+                .cfg
+                .rotate_every
+                .map_or(false, |interval| self.opened.elapsed() >= interval)
+    }
+
+    fn rotate(&mut self) {
+        self.flush();
+        self.file = None;
+
+        if self.cfg.keep == 0 {
+            let _ = fs::remove_file(&self.cfg.path);
+        } else {
+            for idx in (1..self.cfg.keep).rev() {
+                let _ = fs::rename(self.rotated(idx), self.rotated(idx + 1));
+            }
+            if let Err(e) = fs::rename(&self.cfg.path, self.rotated(1)) {
+                log::error!("Can not rotate access log file: {}", e);
+            }
+        }
+        if let Err(e) = self.open() {
+            log::error!("Can not open access log file: {}", e);
+        }
+    }
+
+    fn rotated(&self, idx: usize) -> PathBuf {
+        let mut path = self.cfg.path.clone().into_os_string();
+        path.push(format!(".{}", idx));
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("actix-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+
+        let writer = FileLogWriter::builder(&path)
+            .max_size(10)
+            .keep(2)
+            .open()
+            .unwrap();
+        for entry in &["first entry", "second entry", "third entry", "last"] {
+            writer.write(entry);
+        }
+        assert_eq!(writer.dropped(), 0);
+        drop(writer);
+
+        // writer thread flushes file when all clones are dropped
+        let mut content = String::new();
+        for _ in 0..50 {
+            thread::sleep(Duration::from_millis(20));
+            content = fs::read_to_string(&path).unwrap_or_default();
+            if content == "last\n" {
+                break;
+            }
+        }
+        assert_eq!(content, "last\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("access.log.1")).unwrap(),
+            "third entry\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("access.log.2")).unwrap(),
+            "second entry\n"
+        );
+        assert!(!dir.join("access.log.3").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
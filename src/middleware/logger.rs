@@ -2,7 +2,7 @@
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::env;
-use std::fmt::{self, Display, Formatter};
+use std::fmt::{self, Display, Formatter, Write};
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
@@ -12,15 +12,16 @@ use std::task::{Context, Poll};
 use actix_service::{Service, Transform};
 use bytes::Bytes;
 use futures::future::{ok, Ready};
-use log::debug;
+use log::{debug, warn};
 use regex::Regex;
 use time;
 
-use crate::dev::{BodySize, MessageBody, ResponseBody};
+use crate::dev::{BodySize, MessageBody, ResponseBody, ResponseHead};
 use crate::error::{Error, Result};
 use crate::http::{HeaderName, StatusCode};
+use crate::middleware::RequestId;
 use crate::service::{ServiceRequest, ServiceResponse};
-use crate::HttpResponse;
+use crate::{HttpRequest, HttpResponse};
 
 /// `Middleware` for logging request and response info to the terminal.
 ///
@@ -72,17 +73,70 @@ use crate::HttpResponse;
 ///
 /// `%U`  Request URL
 ///
+/// `%R`  Request id assigned by
+/// [`RequestIdentifier`](struct.RequestIdentifier.html) middleware
+///
 /// `%{FOO}i`  request.headers['FOO']
 ///
 /// `%{FOO}o`  response.headers['FOO']
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
+/// `%{FOO}xi`  value returned by function registered with `custom_field("FOO", ..)`
+///
+/// ## Custom fields
+///
+/// Custom field function receives request and head of finished response,
+/// so it could render values stored in extensions by handlers or other
+/// middlewares:
+///
+/// ```rust
+/// use actix_web::middleware::Logger;
+///
+/// struct UserId(u64);
+///
+/// let logger = Logger::new("%a %s %{user}xi")
+///     .custom_field("user", |req, _res| match req.extensions().get::<UserId>() {
+///         Some(id) => id.0.to_string(),
+///         None => "-".to_string(),
+///     });
+/// ```
+///
+/// ## Sink
+///
+/// By default entries are logged through `log` crate with `info` level.
+/// Entries could be passed to custom [`LogSink`](trait.LogSink.html)
+/// instead, e.g. to non-blocking [`FileLogWriter`](struct.FileLogWriter.html).
+///
+/// ## JSON
+///
+/// Logger created with `json` method logs one JSON object per request,
+/// with fields `timestamp`, `method`, `path`, `pattern` (pattern of matched
+/// resource), `status`, `size`, `duration` (in seconds), `remote_addr` and
+/// `request_id`. Extra fields are rendered from format strings:
+///
+/// ```rust
+/// use actix_web::middleware::Logger;
+///
+/// let logger = Logger::json()
+///     .json_field("user_agent", "%{User-Agent}i")
+///     .json_field("service", "api");
+/// ```
 pub struct Logger(Rc<Inner>);
 
 struct Inner {
     format: Format,
     exclude: HashSet<String>,
+    json: bool,
+    custom_fields: Vec<(String, CustomField)>,
+    sink: Option<Rc<dyn LogSink>>,
+}
+
+/// Destination of access log entries.
+pub trait LogSink {
+    /// Write formatted log entry. Called on worker thread, so it should
+    /// not block.
+    fn write(&self, entry: &str);
 }
 
 impl Logger {
@@ -91,9 +145,69 @@ impl Logger {
         Logger(Rc::new(Inner {
             format: Format::new(format),
             exclude: HashSet::new(),
+            json: false,
+            custom_fields: Vec::new(),
+            sink: None,
+        }))
+    }
+
+    /// Create `Logger` middleware that logs entries as JSON objects.
+    pub fn json() -> Logger {
+        Logger(Rc::new(Inner {
+            format: Format::json(),
+            exclude: HashSet::new(),
+            json: true,
+            custom_fields: Vec::new(),
+            sink: None,
         }))
     }
 
+    /// Add field to JSON log entries, `value` is a format string.
+    ///
+    /// Value is always rendered as JSON string.
+    /// Panics if `Logger` is not created with `json` method.
+    pub fn json_field(mut self, name: &str, value: &str) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        assert!(inner.json, "Logger does not use JSON format");
+
+        let mut value = Format::new(value).0;
+        for (label, field) in &inner.custom_fields {
+            for unit in &mut value {
+                unit.set_custom_field(label, field);
+            }
+        }
+
+        let units = &mut inner.format.0;
+        let end = units.pop();
+        units.push(FormatText::Str(format!(",{}:", JsonString(name))));
+        units.push(FormatText::Json(value));
+        units.extend(end);
+        self
+    }
+
+    /// Write log entries to specified sink instead of `log` crate.
+    pub fn sink<T: LogSink + 'static>(mut self, sink: T) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().sink = Some(Rc::new(sink));
+        self
+    }
+
+    /// Register function that renders `%{label}xi` custom field.
+    ///
+    /// Function is called after response is created, with request and
+    /// response head.
+    pub fn custom_field<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&HttpRequest, &ResponseHead) -> String + 'static,
+    {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        let field = CustomField(Rc::new(f));
+        for unit in &mut inner.format.0 {
+            unit.set_custom_field(label, &field);
+        }
+        inner.custom_fields.push((label.to_owned(), field));
+        self
+    }
+
     /// Ignore and do not log access info for specified path.
     pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
         Rc::get_mut(&mut self.0)
@@ -114,6 +228,9 @@ impl Default for Logger {
         Logger(Rc::new(Inner {
             format: Format::default(),
             exclude: HashSet::new(),
+            json: false,
+            custom_fields: Vec::new(),
+            sink: None,
         }))
     }
 }
@@ -131,6 +248,9 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
+        for unit in &self.0.format.0 {
+            unit.check_custom_fields();
+        }
         ok(LoggerMiddleware {
             service,
             inner: self.0.clone(),
@@ -163,6 +283,7 @@ where
             LoggerResponse {
                 fut: self.service.call(req),
                 format: None,
+                sink: None,
                 time: time::now(),
                 _t: PhantomData,
             }
@@ -176,6 +297,7 @@ where
             LoggerResponse {
                 fut: self.service.call(req),
                 format: Some(format),
+                sink: self.inner.sink.clone(),
                 time: now,
                 _t: PhantomData,
             }
@@ -194,6 +316,7 @@ where
     fut: S::Future,
     time: time::Tm,
     format: Option<Format>,
+    sink: Option<Rc<dyn LogSink>>,
     _t: PhantomData<(B,)>,
 }
 
@@ -221,17 +344,22 @@ where
         if let Some(ref mut format) = this.format {
             for unit in &mut format.0 {
                 unit.render_response(res.response());
+                unit.render_request_id(res.request());
+                unit.render_match_pattern(res.request());
+                unit.render_custom(res.request(), res.response().head());
             }
         }
 
         let time = *this.time;
         let format = this.format.take();
+        let sink = this.sink.take();
 
         Poll::Ready(Ok(res.map_body(move |_, body| {
             ResponseBody::Body(StreamLog {
                 body,
                 time,
                 format,
+                sink,
                 size: 0,
             })
         })))
@@ -241,6 +369,7 @@ where
 pub struct StreamLog<B> {
     body: ResponseBody<B>,
     format: Option<Format>,
+    sink: Option<Rc<dyn LogSink>>,
     size: usize,
     time: time::Tm,
 }
@@ -254,7 +383,10 @@ impl<B> Drop for StreamLog<B> {
                 }
                 Ok(())
             };
-            log::info!("{}", FormatDisplay(&render));
+            match self.sink {
+                Some(ref sink) => sink.write(&FormatDisplay(&render).to_string()),
+                None => log::info!("{}", FormatDisplay(&render)),
+            }
         }
     }
 }
@@ -294,7 +426,8 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     pub fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrUsbTD]?)").unwrap();
+        let fmt =
+            Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}(xi|[ioe])|[atPrUsbTDR]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -315,6 +448,7 @@ impl Format {
                         HeaderName::try_from(key.as_str()).unwrap(),
                     ),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "xi" => FormatText::Custom(key.as_str().to_owned(), None),
                     _ => unreachable!(),
                 })
             } else {
@@ -329,6 +463,7 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "R" => FormatText::RequestId,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -339,6 +474,32 @@ impl Format {
 
         Format(results)
     }
+
+    /// Create a `Format` of JSON log entries.
+    fn json() -> Format {
+        let field = |name: &str| FormatText::Str(format!(",\"{}\":", name));
+        Format(vec![
+            FormatText::Str("{\"timestamp\":".to_owned()),
+            FormatText::Json(vec![FormatText::RequestTime]),
+            field("method"),
+            FormatText::Json(vec![FormatText::Method]),
+            field("path"),
+            FormatText::Json(vec![FormatText::UrlPath]),
+            field("pattern"),
+            FormatText::Json(vec![FormatText::MatchPattern]),
+            field("status"),
+            FormatText::ResponseStatus,
+            field("size"),
+            FormatText::ResponseSize,
+            field("duration"),
+            FormatText::Time,
+            field("remote_addr"),
+            FormatText::Json(vec![FormatText::RemoteAddr]),
+            field("request_id"),
+            FormatText::Json(vec![FormatText::RequestId]),
+            FormatText::Str("}".to_owned()),
+        ])
+    }
 }
 
 /// A string of text to be logged. This is either one of the data
@@ -350,15 +511,32 @@ pub enum FormatText {
     Percent,
     RequestLine,
     RequestTime,
+    Method,
     ResponseStatus,
     ResponseSize,
     Time,
     TimeMillis,
     RemoteAddr,
     UrlPath,
+    RequestId,
+    MatchPattern,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    Custom(String, Option<CustomField>),
+    /// Units rendered as JSON string
+    Json(Vec<FormatText>),
+}
+
+/// Function that renders custom field
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct CustomField(Rc<dyn Fn(&HttpRequest, &ResponseHead) -> String>);
+
+impl fmt::Debug for CustomField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomField")
+    }
 }
 
 impl FormatText {
@@ -389,12 +567,27 @@ impl FormatText {
                     "-".fmt(fmt)
                 }
             }
+            FormatText::Json(ref units) => {
+                let render = |fmt: &mut Formatter<'_>| {
+                    for unit in units {
+                        unit.render(fmt, size, entry_time)?;
+                    }
+                    Ok(())
+                };
+                let s = FormatDisplay(&render).to_string();
+                JsonString(&s).fmt(fmt)
+            }
             _ => Ok(()),
         }
     }
 
     fn render_response<B>(&mut self, res: &HttpResponse<B>) {
         match *self {
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.render_response(res);
+                }
+            }
             FormatText::ResponseStatus => {
                 *self = FormatText::Str(format!("{}", res.status().as_u16()))
             }
@@ -414,8 +607,96 @@ impl FormatText {
         }
     }
 
+    // request id could be assigned by inner middleware, so it is
+    // rendered after request is handled
+    fn render_request_id(&mut self, req: &HttpRequest) {
+        match *self {
+            FormatText::RequestId => {
+                *self = match RequestId::get(req) {
+                    Some(id) => FormatText::Str(id.to_string()),
+                    None => FormatText::Str("-".to_string()),
+                };
+            }
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.render_request_id(req);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn set_custom_field(&mut self, label: &str, field: &CustomField) {
+        match *self {
+            FormatText::Custom(ref name, ref mut f) if name == label => {
+                *f = Some(field.clone())
+            }
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.set_custom_field(label, field);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn check_custom_fields(&self) {
+        match *self {
+            FormatText::Custom(ref label, None) => {
+                warn!("Function is not registered for custom field: {}", label)
+            }
+            FormatText::Json(ref units) => {
+                for unit in units {
+                    unit.check_custom_fields();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn render_custom(&mut self, req: &HttpRequest, res: &ResponseHead) {
+        match *self {
+            FormatText::Custom(_, ref f) => {
+                *self = match f {
+                    Some(f) => FormatText::Str((f.0)(req, res)),
+                    None => FormatText::Str("-".to_string()),
+                };
+            }
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.render_custom(req, res);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // resource is matched by inner service
+    fn render_match_pattern(&mut self, req: &HttpRequest) {
+        match *self {
+            FormatText::MatchPattern => {
+                *self = match req.match_pattern() {
+                    Some(pattern) => FormatText::Str(pattern),
+                    None => FormatText::Str("-".to_string()),
+                };
+            }
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.render_match_pattern(req);
+                }
+            }
+            _ => (),
+        }
+    }
+
     fn render_request(&mut self, now: time::Tm, req: &ServiceRequest) {
         match *self {
+            FormatText::Json(ref mut units) => {
+                for unit in units {
+                    unit.render_request(now, req);
+                }
+            }
+            FormatText::Method => *self = FormatText::Str(req.method().to_string()),
             FormatText::RequestLine => {
                 *self = if req.query_string().is_empty() {
                     FormatText::Str(format!(
@@ -473,6 +754,27 @@ impl<'a> fmt::Display for FormatDisplay<'a> {
     }
 }
 
+/// Quoted and escaped JSON string
+struct JsonString<'a>(&'a str);
+
+impl<'a> fmt::Display for JsonString<'a> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        fmt.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => fmt.write_str("\\\"")?,
+                '\\' => fmt.write_str("\\\\")?,
+                '\n' => fmt.write_str("\\n")?,
+                '\r' => fmt.write_str("\\r")?,
+                '\t' => fmt.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(fmt, "\\u{:04x}", c as u32)?,
+                c => fmt.write_char(c)?,
+            }
+        }
+        fmt.write_str("\"")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::{IntoService, Service, Transform};
@@ -591,4 +893,147 @@ mod tests {
         let s = format!("{}", FormatDisplay(&render));
         assert!(s.contains(&format!("{}", now.rfc3339())));
     }
+
+    #[actix_rt::test]
+    async fn test_request_id_format() {
+        let srv =
+            |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mut srv = crate::middleware::RequestIdentifier::new()
+            .generator(|| "id-1".to_string())
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+
+        let mut format = Format::new("[%R]");
+        for unit in &mut format.0 {
+            unit.render_request_id(res.request());
+        }
+        let now = time::now();
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        assert_eq!(format!("{}", FormatDisplay(&render)), "[id-1]");
+
+        let mut format = Format::new("%R");
+        let req = TestRequest::default().to_http_request();
+        for unit in &mut format.0 {
+            unit.render_request_id(&req);
+        }
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        assert_eq!(format!("{}", FormatDisplay(&render)), "-");
+    }
+
+    #[actix_rt::test]
+    async fn test_json_format() {
+        let logger = Logger::json().json_field("user_agent", "%{User-Agent}i \"%U\"");
+        let mut format = logger.0.format.clone();
+        let req = TestRequest::with_header(
+            header::USER_AGENT,
+            header::HeaderValue::from_static("ACTIX-WEB"),
+        )
+        .uri("/test?q=1")
+        .to_srv_request();
+
+        let now = time::now();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+
+        let resp = HttpResponse::build(StatusCode::OK).force_close().finish();
+        let req = TestRequest::default().to_http_request();
+        for unit in &mut format.0 {
+            unit.render_response(&resp);
+            unit.render_request_id(&req);
+            unit.render_match_pattern(&req);
+        }
+
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert!(s.starts_with(&format!("{{\"timestamp\":\"{}\",", now.rfc3339())));
+        assert!(s.contains(
+            r#","method":"GET","path":"/test","pattern":"-","status":200,"size":1024,"duration":"#
+        ));
+        assert!(s.ends_with(
+            r#","remote_addr":"-","request_id":"-","user_agent":"ACTIX-WEB \"/test\""}"#
+        ));
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(
+            JsonString("a\"b\\c\n\u{1}").to_string(),
+            r#""a\"b\\c\n\u0001""#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Logger does not use JSON format")]
+    fn test_json_field_text_format() {
+        let _ = Logger::default().json_field("name", "value");
+    }
+
+    #[actix_rt::test]
+    async fn test_custom_field() {
+        let logger = Logger::new("%{user}xi %{cache}xi %{unknown}xi")
+            .custom_field("user", |req, _| match req.extensions().get::<u64>() {
+                Some(id) => id.to_string(),
+                None => "-".to_string(),
+            })
+            .custom_field("cache", |_, res| {
+                res.headers()
+                    .get("x-cache")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("MISS")
+                    .to_string()
+            });
+        let mut format = logger.0.format.clone();
+
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(42u64);
+        let resp = HttpResponse::Ok().header("x-cache", "HIT").finish();
+        for unit in &mut format.0 {
+            unit.render_custom(&req, resp.head());
+        }
+
+        let now = time::now();
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        assert_eq!(format!("{}", FormatDisplay(&render)), "42 HIT -");
+
+        let logger = Logger::json()
+            .custom_field("user", |_, _| "admin".to_string())
+            .json_field("user", "%{user}xi");
+        let mut format = logger.0.format.clone();
+        for unit in &mut format.0 {
+            unit.render_custom(&req, resp.head());
+        }
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        assert!(format!("{}", FormatDisplay(&render)).ends_with(r#","user":"admin"}"#));
+    }
 }
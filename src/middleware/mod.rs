@@ -3,15 +3,50 @@
 #[cfg(feature = "compress")]
 mod compress;
 #[cfg(feature = "compress")]
-pub use self::compress::Compress;
+pub use self::compress::{Compress, CompressConfig};
+#[cfg(feature = "compress")]
+mod decompress;
+#[cfg(feature = "compress")]
+pub use self::decompress::Decompress;
 
+mod body_limit;
+mod cache;
+mod catch_panic;
+mod circuit_breaker;
 mod condition;
+mod context;
+mod csrf;
+mod deadline;
 mod defaultheaders;
 pub mod errhandlers;
+mod https_redirect;
+mod ip_filter;
+mod log_file;
 mod logger;
 mod normalize;
+mod prometheus;
+pub mod ratelimit;
+mod request_id;
+mod security;
+mod timeout;
+mod trace;
 
-pub use self::condition::Condition;
+pub use self::body_limit::BodyLimit;
+pub use self::cache::{CacheHandle, ResponseCache};
+pub use self::catch_panic::{CatchPanic, CaughtPanic};
+pub use self::circuit_breaker::CircuitBreaker;
+pub use self::condition::{Condition, Filter};
+pub use self::context::{PropagateContext, RequestContext};
+pub use self::csrf::{Csrf, CsrfToken};
+pub use self::deadline::{Deadline, RequestDeadline};
 pub use self::defaultheaders::DefaultHeaders;
-pub use self::logger::Logger;
-pub use self::normalize::NormalizePath;
+pub use self::https_redirect::HttpsRedirect;
+pub use self::ip_filter::IpFilter;
+pub use self::log_file::{FileLogWriter, FileLogWriterBuilder};
+pub use self::logger::{LogSink, Logger};
+pub use self::normalize::{NormalizePath, NormalizePathConfig};
+pub use self::prometheus::PrometheusMetrics;
+pub use self::request_id::{RequestId, RequestIdentifier};
+pub use self::security::{ContentSecurityPolicy, CspNonce, SecurityHeaders};
+pub use self::timeout::Timeout;
+pub use self::trace::{RequestSpan, TraceContext, TraceFormat, Tracing};
@@ -1,14 +1,14 @@
 //! `Middleware` to normalize request's URI
+use std::borrow::Cow;
 use std::task::{Context, Poll};
 
 use actix_http::http::{PathAndQuery, Uri};
 use actix_service::{Service, Transform};
 use bytes::Bytes;
-use futures::future::{ok, Ready};
-use regex::Regex;
+use futures::future::{err, ok, Either, Ready};
 
+use crate::error::{Error, ErrorBadRequest};
 use crate::service::{ServiceRequest, ServiceResponse};
-use crate::Error;
 
 #[derive(Default, Clone, Copy)]
 /// `Middleware` to normalize request's URI in place
@@ -16,6 +16,12 @@ use crate::Error;
 /// Performs following:
 ///
 /// - Merges multiple slashes into one.
+/// - Removes `.` segments and resolves `..` segments, percent-encoded dots
+///   are recognized as well. Requests with `..` segments that would escape
+///   root are rejected with `400 Bad Request`.
+///
+/// Steps could be disabled with
+/// [`NormalizePath::config()`](struct.NormalizePath.html#method.config).
 ///
 /// ```rust
 /// use actix_web::{web, http, middleware, App, HttpResponse};
@@ -33,6 +39,22 @@ use crate::Error;
 
 pub struct NormalizePath;
 
+impl NormalizePath {
+    /// Path normalization with configurable steps, all enabled by default.
+    ///
+    /// ```rust
+    /// use actix_web::{middleware, App};
+    ///
+    /// # fn main() {
+    /// let app = App::new()
+    ///     .wrap(middleware::NormalizePath::config().merge_slashes(false));
+    /// # }
+    /// ```
+    pub fn config() -> NormalizePathConfig {
+        NormalizePathConfig::default()
+    }
+}
+
 impl<S, B> Transform<S> for NormalizePath
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
@@ -45,17 +67,120 @@ where
     type Transform = NormalizePathNormalization<S>;
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
+    fn new_transform(&self, service: S) -> Self::Future {
+        NormalizePathConfig::default().new_transform(service)
+    }
+}
+
+/// Configurable path normalization, created with `NormalizePath::config()`
+#[derive(Clone, Copy, Debug)]
+pub struct NormalizePathConfig {
+    merge_slashes: bool,
+    dot_segments: bool,
+}
+
+impl Default for NormalizePathConfig {
+    fn default() -> Self {
+        NormalizePathConfig {
+            merge_slashes: true,
+            dot_segments: true,
+        }
+    }
+}
+
+impl NormalizePathConfig {
+    /// Merge multiple slashes into one.
+    pub fn merge_slashes(mut self, enabled: bool) -> Self {
+        self.merge_slashes = enabled;
+        self
+    }
+
+    /// Resolve `.` and `..` segments and reject paths escaping root.
+    pub fn dot_segments(mut self, enabled: bool) -> Self {
+        self.dot_segments = enabled;
+        self
+    }
+
+    /// Normalized path, `None` if path escapes root
+    fn normalize<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        if !path.starts_with('/') {
+            return Some(Cow::Borrowed(path));
+        }
+
+        let mut segments: Vec<&str> = Vec::new();
+        let mut changed = false;
+        let mut iter = path[1..].split('/').peekable();
+        while let Some(seg) = iter.next() {
+            let last = iter.peek().is_none();
+            if self.dot_segments && is_dots(seg, 1) {
+                changed = true;
+            } else if self.dot_segments && is_dots(seg, 2) {
+                changed = true;
+                segments.pop()?;
+            } else if seg.is_empty() && self.merge_slashes && !last {
+                changed = true;
+                continue;
+            } else {
+                segments.push(seg);
+                continue;
+            }
+            // dot segment at the end keeps trailing slash
+            if last {
+                segments.push("");
+            }
+        }
+
+        if changed {
+            let mut normalized = String::with_capacity(path.len());
+            for seg in segments {
+                normalized.push('/');
+                normalized.push_str(seg);
+            }
+            Some(Cow::Owned(normalized))
+        } else {
+            Some(Cow::Borrowed(path))
+        }
+    }
+}
+
+/// Check if path segment consists of `n` dots, possibly percent-encoded
+fn is_dots(seg: &str, n: usize) -> bool {
+    let mut rest = seg.as_bytes();
+    for _ in 0..n {
+        if rest.starts_with(b".") {
+            rest = &rest[1..];
+        } else if rest.len() >= 3 && rest[..3].eq_ignore_ascii_case(b"%2e") {
+            rest = &rest[3..];
+        } else {
+            return false;
+        }
+    }
+    rest.is_empty()
+}
+
+impl<S, B> Transform<S> for NormalizePathConfig
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = NormalizePathNormalization<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
     fn new_transform(&self, service: S) -> Self::Future {
         ok(NormalizePathNormalization {
             service,
-            merge_slash: Regex::new("//+").unwrap(),
+            config: *self,
         })
     }
 }
 
 pub struct NormalizePathNormalization<S> {
     service: S,
-    merge_slash: Regex,
+    config: NormalizePathConfig,
 }
 
 impl<S, B> Service for NormalizePathNormalization<S>
@@ -66,7 +191,7 @@ where
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
     type Error = Error;
-    type Future = S::Future;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.service.poll_ready(cx)
@@ -74,11 +199,12 @@ where
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
         let head = req.head_mut();
-        let path = head.uri.path();
-        let original_len = path.len();
-        let path = self.merge_slash.replace_all(path, "/");
+        let path = match self.config.normalize(head.uri.path()) {
+            Some(path) => path,
+            None => return Either::Right(err(ErrorBadRequest("Invalid path"))),
+        };
 
-        if original_len != path.len() {
+        if let Cow::Owned(path) = path {
             let mut parts = head.uri.clone().into_parts();
             let pq = parts.path_and_query.as_ref().unwrap();
 
@@ -94,7 +220,7 @@ where
             req.head_mut().uri = uri;
         }
 
-        self.service.call(req)
+        Either::Left(self.service.call(req))
     }
 }
 
@@ -104,6 +230,7 @@ mod tests {
 
     use super::*;
     use crate::dev::ServiceRequest;
+    use crate::http::StatusCode;
     use crate::test::{call_service, init_service, TestRequest};
     use crate::{web, App, HttpResponse};
 
@@ -156,4 +283,43 @@ mod tests {
         let res = normalize.call(req).await.unwrap();
         assert!(res.status().is_success());
     }
+
+    #[test]
+    fn test_normalize() {
+        let config = NormalizePath::config();
+        assert_eq!(config.normalize("/").unwrap(), "/");
+        assert_eq!(config.normalize("//").unwrap(), "/");
+        assert_eq!(config.normalize("/a/./b/.").unwrap(), "/a/b/");
+        assert_eq!(config.normalize("/a/b/../../c").unwrap(), "/c");
+        assert_eq!(config.normalize("/a//b/%2E%2e").unwrap(), "/a/");
+        assert_eq!(config.normalize("/a/..b/.c").unwrap(), "/a/..b/.c");
+        assert!(config.normalize("/a/../..").is_none());
+        assert!(config.normalize("/%2e./etc/passwd").is_none());
+
+        let config = NormalizePath::config().merge_slashes(false);
+        assert_eq!(config.normalize("/a//../b").unwrap(), "/a/b");
+        let config = NormalizePath::config().dot_segments(false);
+        assert_eq!(config.normalize("/a//../b").unwrap(), "/a/../b");
+    }
+
+    #[actix_rt::test]
+    async fn test_dot_segments() {
+        let mut app = init_service(
+            App::new()
+                .wrap(NormalizePath)
+                .service(web::resource("/static/file.txt").to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/./css/../file.txt?v=1").to_request();
+        let res = call_service(&mut app, req).await;
+        assert!(res.status().is_success());
+
+        let req = TestRequest::with_uri("/static/../../file.txt").to_request();
+        let err = app.call(req).await.err().unwrap();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::BAD_REQUEST
+        );
+    }
 }
@@ -0,0 +1,408 @@
+//! Prometheus metrics middleware
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::Error;
+use crate::http::{Method, StatusCode};
+use crate::HttpResponse;
+
+/// Default histogram buckets of request duration, in seconds
+const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Label of requests that do not match any resource
+const UNMATCHED: &str = "unmatched";
+
+/// `Middleware` for collecting metrics in Prometheus format.
+///
+/// Middleware records following metrics, labeled by request method,
+/// pattern of matched resource (e.g. `/users/{id}`) and response status:
+///
+/// * `http_requests_total` - number of handled requests
+/// * `http_request_duration_seconds` - histogram of time until response is
+///   created, streaming of response body is not included
+///
+/// and unlabeled `http_requests_in_flight` gauge. Metric names are prefixed
+/// with namespace, if set.
+///
+/// Series are kept in one registry that clones share, so each worker
+/// records into it and `render()` reports totals of the whole server.
+/// Middleware constructed inside application factory would render only
+/// requests of the worker that serves the scrape. Metrics are served in text exposition
+/// format by middleware itself on path set with `endpoint()`, or could be
+/// rendered with `render()` method.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse, HttpServer};
+///
+/// fn main() {
+///     let metrics = middleware::PrometheusMetrics::new("api").endpoint("/metrics");
+///
+///     HttpServer::new(move || {
+///         App::new()
+///             .wrap(metrics.clone())
+///             .route("/users/{id}", web::get().to(|| HttpResponse::Ok()))
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PrometheusMetrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    namespace: String,
+    endpoint: Option<String>,
+    buckets: Vec<f64>,
+    in_flight: AtomicI64,
+    series: Mutex<BTreeMap<(&'static str, String, u16), Series>>,
+}
+
+/// Counters of one label set
+struct Series {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl PrometheusMetrics {
+    /// Construct `PrometheusMetrics` middleware, metric names are prefixed with
+    /// `namespace`, unless it is empty.
+    pub fn new(namespace: &str) -> PrometheusMetrics {
+        PrometheusMetrics {
+            inner: Arc::new(Inner {
+                namespace: namespace.to_string(),
+                endpoint: None,
+                buckets: DEFAULT_BUCKETS.to_vec(),
+                in_flight: AtomicI64::new(0),
+                series: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Serve metrics on `GET` requests to specified path.
+    ///
+    /// Requests to the endpoint are not recorded.
+    pub fn endpoint(mut self, path: &str) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .endpoint = Some(path.to_string());
+        self
+    }
+
+    /// Set upper bounds of request duration histogram buckets, in seconds.
+    ///
+    /// Panics if any bound is `NaN` or infinite, `+Inf` bucket is always
+    /// rendered.
+    pub fn buckets(mut self, buckets: &[f64]) -> Self {
+        assert!(
+            buckets.iter().all(|b| b.is_finite()),
+            "Histogram bucket bounds must be finite"
+        );
+        let mut buckets = buckets.to_vec();
+        // finite floats are totally ordered
+        buckets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .buckets = buckets;
+        self
+    }
+
+    /// Render metrics in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        self.inner.render()
+    }
+}
+
+impl Inner {
+    fn name(&self, name: &str) -> String {
+        if self.namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}_{}", self.namespace, name)
+        }
+    }
+
+    fn record(&self, method: &Method, pattern: &str, status: StatusCode, secs: f64) {
+        let key = (method_label(method), pattern.to_string(), status.as_u16());
+        let mut series = self.series.lock().unwrap();
+        let series = series.entry(key).or_insert_with(|| Series {
+            buckets: vec![0; self.buckets.len()],
+            count: 0,
+            sum: 0.0,
+        });
+        for (bound, count) in self.buckets.iter().zip(series.buckets.iter_mut()) {
+            if secs <= *bound {
+                *count += 1;
+            }
+        }
+        series.count += 1;
+        series.sum += secs;
+    }
+
+    fn render(&self) -> String {
+        let series = self.series.lock().unwrap();
+        let mut out = String::new();
+
+        let total = self.name("http_requests_total");
+        let _ = writeln!(out, "# HELP {} Total number of HTTP requests", total);
+        let _ = writeln!(out, "# TYPE {} counter", total);
+        for ((method, pattern, status), s) in series.iter() {
+            let labels = labels(method, pattern, *status);
+            let _ = writeln!(out, "{}{{{}}} {}", total, labels, s.count);
+        }
+
+        let in_flight = self.name("http_requests_in_flight");
+        let _ = writeln!(
+            out,
+            "# HELP {} Number of HTTP requests being handled",
+            in_flight
+        );
+        let _ = writeln!(out, "# TYPE {} gauge", in_flight);
+        let _ = writeln!(
+            out,
+            "{} {}",
+            in_flight,
+            self.in_flight.load(Ordering::Relaxed)
+        );
+
+        let duration = self.name("http_request_duration_seconds");
+        let _ = writeln!(out, "# HELP {} HTTP request duration in seconds", duration);
+        let _ = writeln!(out, "# TYPE {} histogram", duration);
+        for ((method, pattern, status), s) in series.iter() {
+            let labels = labels(method, pattern, *status);
+            for (bound, count) in self.buckets.iter().zip(s.buckets.iter()) {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    duration, labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                duration, labels, s.count
+            );
+            let _ = writeln!(out, "{}_sum{{{}}} {}", duration, labels, s.sum);
+            let _ = writeln!(out, "{}_count{{{}}} {}", duration, labels, s.count);
+        }
+        out
+    }
+}
+
+fn labels(method: &str, pattern: &str, status: u16) -> String {
+    format!(
+        "method=\"{}\",path=\"{}\",status=\"{}\"",
+        escape(method),
+        escape(pattern),
+        status
+    )
+}
+
+fn escape(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Tracks number of requests being handled
+struct InFlight(Arc<Inner>);
+
+impl InFlight {
+    fn new(inner: Arc<Inner>) -> Self {
+        inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(inner)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S, B> Transform<S> for PrometheusMetrics
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = PrometheusMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PrometheusMetricsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct PrometheusMetricsMiddleware<S> {
+    service: S,
+    inner: Arc<Inner>,
+}
+
+impl<S, B> Service for PrometheusMetricsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if let Some(ref endpoint) = self.inner.endpoint {
+            if req.path() == endpoint.as_str() && *req.method() == Method::GET {
+                let res = HttpResponse::Ok()
+                    .content_type("text/plain; version=0.0.4; charset=utf-8")
+                    .body(self.inner.render());
+                return ok(req.into_response(res.into_body())).boxed_local();
+            }
+        }
+
+        let start = Instant::now();
+        let guard = InFlight::new(self.inner.clone());
+        let method = req.method().clone();
+        let fut = self.service.call(req);
+
+        async move {
+            let res = fut.await;
+            let secs = duration_secs(start.elapsed());
+            let inner = &guard.0;
+
+            match res {
+                Ok(ref res) => {
+                    let pattern = res.request().match_pattern();
+                    let pattern = pattern.as_ref().map_or(UNMATCHED, |p| p.as_str());
+                    inner.record(&method, pattern, res.status(), secs);
+                }
+                Err(ref e) => {
+                    let status = e.as_response_error().status_code();
+                    inner.record(&method, UNMATCHED, status, secs);
+                }
+            }
+            res
+        }
+        .boxed_local()
+    }
+}
+
+/// Label of request method, extension methods are labeled as `OTHER`, so
+/// clients can not create arbitrary number of series
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+fn duration_secs(d: std::time::Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_metrics() {
+        let metrics = PrometheusMetrics::new("api")
+            .endpoint("/metrics")
+            .buckets(&[1.0, 0.5]);
+        // `App::route()` moves method guard to resource, other methods
+        // would not match it and get 404
+        let mut srv = init_service(App::new().wrap(metrics.clone()).service(
+            web::resource("/users/{id}").route(web::get().to(|| HttpResponse::Ok())),
+        ))
+        .await;
+
+        for path in &["/users/1", "/users/2", "/unknown"] {
+            let req = TestRequest::with_uri(path).to_request();
+            let _ = call_service(&mut srv, req).await;
+        }
+        for method in &["FOO", "BAR"] {
+            let req = TestRequest::with_uri("/users/1")
+                .method(Method::from_bytes(method.as_bytes()).unwrap())
+                .to_request();
+            let _ = call_service(&mut srv, req).await;
+        }
+
+        let req = TestRequest::with_uri("/metrics").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        assert!(body.contains("# TYPE api_http_requests_total counter\n"));
+        assert!(body.contains(
+            "api_http_requests_total{method=\"GET\",path=\"/users/{id}\",status=\"200\"} 2\n"
+        ));
+        assert!(body.contains(
+            "api_http_requests_total{method=\"GET\",path=\"unmatched\",status=\"404\"} 1\n"
+        ));
+        // extension methods share one label
+        assert!(body.contains(
+            "api_http_requests_total{method=\"OTHER\",path=\"/users/{id}\",status=\"405\"} 2\n"
+        ));
+        assert!(!body.contains("FOO"));
+        assert!(body.contains("api_http_requests_in_flight 0\n"));
+        assert!(body.contains(
+            "api_http_request_duration_seconds_bucket{method=\"GET\",path=\"/users/{id}\",status=\"200\",le=\"0.5\"} 2\n"
+        ));
+        assert!(body.contains(
+            "api_http_request_duration_seconds_count{method=\"GET\",path=\"/users/{id}\",status=\"200\"} 2\n"
+        ));
+        // metrics endpoint is not recorded
+        assert!(!body.contains("/metrics"));
+        assert_eq!(body, metrics.render());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            labels("GET", "/a\"b", 200),
+            "method=\"GET\",path=\"/a\\\"b\",status=\"200\""
+        );
+        let metrics = PrometheusMetrics::new("");
+        assert!(metrics
+            .render()
+            .contains("# TYPE http_requests_total counter"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Histogram bucket bounds must be finite")]
+    fn test_buckets_not_finite() {
+        let _ = PrometheusMetrics::new("api").buckets(&[0.5, std::f64::NAN]);
+    }
+}
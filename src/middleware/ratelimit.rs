@@ -0,0 +1,498 @@
+//! Rate limiting middleware
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_service::{Service, Transform};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::{Error, RateLimitError};
+use crate::http::header::{HeaderName, HeaderValue};
+
+/// Number of tracked keys after which memory store removes stale entries
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// Default max number of keys tracked by memory store
+const DEFAULT_MAX_KEYS: usize = 100_000;
+
+/// Result of registering request in rate limit store
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    /// Request is allowed, `remaining` requests are left in current period
+    Allow { remaining: u32 },
+    /// Request is denied, client should retry after specified time
+    Deny { retry_after: Duration },
+}
+
+/// Storage of rate limit counters.
+///
+/// Store is shared between workers, so it has to use thread safe state or
+/// external service, e.g. Redis.
+pub trait RateLimitStore {
+    /// Register request for `key`, at most `limit` requests are allowed
+    /// per `period`.
+    fn hit(
+        &self,
+        key: &str,
+        limit: u32,
+        period: Duration,
+    ) -> LocalBoxFuture<'static, Result<Decision, Error>>;
+}
+
+/// In-memory token bucket store.
+///
+/// Each key gets bucket of `limit` tokens, which is refilled at rate of
+/// `limit` tokens per `period`. Counters live behind a mutex that clones
+/// share, store created inside application factory would give each worker
+/// its own buckets and multiply effective limit by number of workers.
+///
+/// Buckets that are refilled completely are dropped. Number of tracked keys
+/// is limited with `max_keys()`, least recently used buckets are dropped
+/// once store is full, so clients with many addresses can not exhaust
+/// memory.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<MemoryStoreInner>>,
+}
+
+struct MemoryStoreInner {
+    buckets: HashMap<String, Bucket>,
+    last_sweep: Option<Instant>,
+    max_keys: usize,
+}
+
+impl Default for MemoryStoreInner {
+    fn default() -> Self {
+        MemoryStoreInner {
+            buckets: HashMap::new(),
+            last_sweep: None,
+            max_keys: DEFAULT_MAX_KEYS,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl MemoryStore {
+    /// Create new in-memory store
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Set max number of tracked keys, by default 100 000.
+    ///
+    /// Once store is full, tenth of keys that were used least recently is
+    /// dropped, so their clients get full buckets.
+    pub fn max_keys(mut self, max: usize) -> Self {
+        assert!(max > 0, "Max number of keys must be positive");
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .get_mut()
+            .unwrap()
+            .max_keys = max;
+        self
+    }
+
+    fn decide(&self, key: &str, limit: u32, period: Duration, now: Instant) -> Decision {
+        let mut inner = self.inner.lock().unwrap();
+        let limit = f64::from(limit);
+        let period_secs = duration_secs(period);
+
+        // drop buckets that are refilled completely
+        if inner.buckets.len() >= SWEEP_THRESHOLD
+            && inner
+                .last_sweep
+                .map_or(true, |t| now.duration_since(t) >= period)
+        {
+            inner
+                .buckets
+                .retain(|_, b| now.duration_since(b.updated) < period);
+            inner.last_sweep = Some(now);
+        }
+
+        // make room for new key
+        if inner.buckets.len() >= inner.max_keys && !inner.buckets.contains_key(key) {
+            inner
+                .buckets
+                .retain(|_, b| now.duration_since(b.updated) < period);
+            inner.last_sweep = Some(now);
+            if inner.buckets.len() >= inner.max_keys {
+                let mut used: Vec<_> = inner
+                    .buckets
+                    .iter()
+                    .map(|(key, b)| (b.updated, key.clone()))
+                    .collect();
+                used.sort();
+                let evict = inner.buckets.len() - inner.max_keys
+                    + cmp::max(1, inner.max_keys / 10);
+                for (_, key) in used.into_iter().take(evict) {
+                    inner.buckets.remove(&key);
+                }
+            }
+        }
+
+        let bucket = inner
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: limit,
+                updated: now,
+            });
+        let elapsed = duration_secs(now.duration_since(bucket.updated));
+        bucket.tokens = (bucket.tokens + elapsed * limit / period_secs).min(limit);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Decision::Allow {
+                remaining: bucket.tokens as u32,
+            }
+        } else {
+            let secs = (1.0 - bucket.tokens) * period_secs / limit;
+            Decision::Deny {
+                retry_after: Duration::from_nanos((secs * 1e9) as u64),
+            }
+        }
+    }
+}
+
+impl RateLimitStore for MemoryStore {
+    fn hit(
+        &self,
+        key: &str,
+        limit: u32,
+        period: Duration,
+    ) -> LocalBoxFuture<'static, Result<Decision, Error>> {
+        ok(self.decide(key, limit, period, Instant::now())).boxed_local()
+    }
+}
+
+/// Default key of request, client's IP address.
+///
+/// IPv6 clients usually get whole /64 network, so IPv6 addresses are
+/// grouped by /64 prefix.
+fn peer_key(req: &ServiceRequest) -> Option<String> {
+    req.peer_addr().map(|addr| match addr.ip() {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => {
+            let s = ip.segments();
+            if s[..5] == [0; 5] && s[5] == 0xffff {
+                // ipv4-mapped address of dual-stack socket
+                IpAddr::V4(ip.to_ipv4().unwrap()).to_string()
+            } else {
+                format!("{:x}:{:x}:{:x}:{:x}::/64", s[0], s[1], s[2], s[3])
+            }
+        }
+    })
+}
+
+fn duration_secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1e9
+}
+
+/// `Middleware` for limiting rate of requests.
+///
+/// Requests are grouped by key, by default it is client's IP address,
+/// IPv6 addresses are grouped by /64 network.
+/// Requests that exceed the limit are rejected with
+/// `429 Too Many Requests` response with `Retry-After` header.
+/// Allowed responses get `X-RateLimit-Limit` and `X-RateLimit-Remaining`
+/// headers. If store fails, request is rejected with store's error.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::middleware::ratelimit::{MemoryStore, RateLimiter};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn main() {
+///     // counters are shared by all workers
+///     let store = MemoryStore::new();
+///
+///     let app = App::new()
+///         .wrap(
+///             // 100 requests per minute for each user
+///             RateLimiter::new(store.clone(), 100, Duration::from_secs(60))
+///                 .key(|req| {
+///                     req.headers()
+///                         .get("x-api-key")
+///                         .and_then(|val| val.to_str().ok())
+///                         .map(|key| key.to_string())
+///                 }),
+///         )
+///         .route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    store: Box<dyn RateLimitStore>,
+    limit: u32,
+    period: Duration,
+    key: Box<dyn Fn(&ServiceRequest) -> Option<String>>,
+}
+
+impl RateLimiter {
+    /// Construct `RateLimiter` middleware, at most `limit` requests are
+    /// allowed per `period`.
+    pub fn new<T>(store: T, limit: u32, period: Duration) -> RateLimiter
+    where
+        T: RateLimitStore + 'static,
+    {
+        RateLimiter {
+            inner: Rc::new(Inner {
+                store: Box::new(store),
+                limit,
+                period,
+                key: Box::new(peer_key),
+            }),
+        }
+    }
+
+    /// Set function that extracts key of request.
+    ///
+    /// Requests without key are not limited. Use this method if
+    /// application runs behind reverse proxy, or to limit requests per
+    /// user instead of ip address.
+    pub fn key<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .key = Box::new(f);
+        self
+    }
+}
+
+impl<S, B> Transform<S> for RateLimiter
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimiterMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimiterMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for RateLimiterMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let key = match (self.inner.key)(&req) {
+            Some(key) => key,
+            None => return self.service.borrow_mut().call(req).boxed_local(),
+        };
+        let srv = self.service.clone();
+        let limit = self.inner.limit;
+        let fut = self.inner.store.hit(&key, limit, self.inner.period);
+
+        async move {
+            let remaining = match fut.await {
+                Ok(Decision::Allow { remaining }) => remaining,
+                Ok(Decision::Deny { retry_after }) => {
+                    return Ok(req.error_response(RateLimitError::new(retry_after)))
+                }
+                Err(e) => return Ok(req.error_response(e)),
+            };
+
+            let fut = { srv.borrow_mut().call(req) };
+            let mut res = fut.await?;
+
+            let headers = res.headers_mut();
+            headers.insert(
+                HeaderName::from_static("x-ratelimit-limit"),
+                HeaderValue::from(limit),
+            );
+            headers.insert(
+                HeaderName::from_static("x-ratelimit-remaining"),
+                HeaderValue::from(remaining),
+            );
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::test::{ok_service, TestRequest};
+    use crate::HttpResponse;
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new();
+        let period = Duration::from_secs(10);
+        let now = Instant::now();
+
+        assert_eq!(
+            store.decide("a", 2, period, now),
+            Decision::Allow { remaining: 1 }
+        );
+        assert_eq!(
+            store.decide("a", 2, period, now),
+            Decision::Allow { remaining: 0 }
+        );
+        assert_eq!(
+            store.decide("a", 2, period, now),
+            Decision::Deny {
+                retry_after: Duration::from_secs(5)
+            }
+        );
+        assert_eq!(
+            store.decide("b", 2, period, now),
+            Decision::Allow { remaining: 1 }
+        );
+
+        // one token is refilled
+        let later = now + Duration::from_secs(5);
+        assert_eq!(
+            store.decide("a", 2, period, later),
+            Decision::Allow { remaining: 0 }
+        );
+    }
+
+    #[test]
+    fn test_memory_store_max_keys() {
+        let store = MemoryStore::new().max_keys(10);
+        let period = Duration::from_secs(10);
+        let now = Instant::now();
+
+        for i in 0..10 {
+            let at = now + Duration::from_millis(i);
+            store.decide(&format!("key{}", i), 2, period, at);
+        }
+        store.decide("key1", 2, period, now + Duration::from_millis(10));
+
+        // least recently used key is dropped
+        let at = now + Duration::from_millis(11);
+        store.decide("new", 2, period, at);
+        let inner = store.inner.lock().unwrap();
+        assert_eq!(inner.buckets.len(), 10);
+        assert!(!inner.buckets.contains_key("key0"));
+        assert!(inner.buckets.contains_key("key1"));
+        assert!(inner.buckets.contains_key("new"));
+    }
+
+    #[actix_rt::test]
+    async fn test_rate_limiter() {
+        let mut mw = RateLimiter::new(MemoryStore::new(), 1, Duration::from_secs(60))
+            .key(|req| {
+                req.headers()
+                    .get("x-key")
+                    .map(|val| val.to_str().unwrap().to_string())
+            })
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header("x-key", "a").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-ratelimit-limit").unwrap(), "1");
+        assert_eq!(resp.headers().get("x-ratelimit-remaining").unwrap(), "0");
+
+        let req = TestRequest::with_header("x-key", "a").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "60");
+
+        // requests without key are not limited
+        for _ in 0..3 {
+            let req = TestRequest::default().to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().get("x-ratelimit-limit").is_none());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_peer_addr_key() {
+        let srv =
+            |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mut mw = RateLimiter::new(MemoryStore::new(), 1, Duration::from_secs(60))
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // different port, same client
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8081".parse().unwrap())
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // ipv6 addresses of the same /64 network
+        for (addr, status) in &[
+            ("[2001:db8:1:2::1]:80", StatusCode::OK),
+            ("[2001:db8:1:2:ffff::2]:80", StatusCode::TOO_MANY_REQUESTS),
+            ("[2001:db8:1:3::1]:80", StatusCode::OK),
+            ("[::ffff:127.0.0.1]:80", StatusCode::TOO_MANY_REQUESTS),
+        ] {
+            let req = TestRequest::default()
+                .peer_addr(addr.parse().unwrap())
+                .to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), *status);
+        }
+    }
+
+    #[test]
+    fn test_peer_key() {
+        let req = TestRequest::default()
+            .peer_addr("[2001:db8::1]:80".parse().unwrap())
+            .to_srv_request();
+        assert_eq!(peer_key(&req).unwrap(), "2001:db8:0:0::/64");
+    }
+}
@@ -0,0 +1,328 @@
+//! Middleware for request identifiers
+use std::collections::hash_map::RandomState;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError};
+use crate::extract::FromRequest;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// Default request id header name
+const X_REQUEST_ID: &str = "x-request-id";
+
+/// Max length of propagated request id
+const MAX_LENGTH: usize = 128;
+
+/// `Middleware` for assigning unique identifier to each request.
+///
+/// Identifier is taken from request's `X-Request-Id` header, or generated
+/// if request does not contain valid one. Identifier is stored in request
+/// extensions, could be accessed by using `RequestId` extractor, and is
+/// set to response's `X-Request-Id` header. `Logger` middleware renders it
+/// with `%R` format.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App};
+/// use actix_web::middleware::RequestId;
+///
+/// async fn index(id: RequestId) -> String {
+///     format!("Request {}", id)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Logger::new("%R %r %s"))
+///         .wrap(middleware::RequestIdentifier::default())
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RequestIdentifier {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    header: HeaderName,
+    propagate: bool,
+    generator: Box<dyn Fn() -> String>,
+}
+
+impl Default for RequestIdentifier {
+    fn default() -> Self {
+        RequestIdentifier {
+            inner: Rc::new(Inner {
+                header: HeaderName::from_static(X_REQUEST_ID),
+                propagate: true,
+                generator: Box::new(generate),
+            }),
+        }
+    }
+}
+
+impl RequestIdentifier {
+    /// Construct `RequestIdentifier` middleware.
+    pub fn new() -> RequestIdentifier {
+        RequestIdentifier::default()
+    }
+
+    /// Set name of request and response header, by default `X-Request-Id`
+    pub fn header<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => {
+                Rc::get_mut(&mut self.inner)
+                    .expect("Multiple copies exist")
+                    .header = key
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Use identifier of incoming request if present. By default is `true`.
+    ///
+    /// Disable if requests come from untrusted clients.
+    pub fn propagate(mut self, propagate: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .propagate = propagate;
+        self
+    }
+
+    /// Set function that generates new identifiers.
+    ///
+    /// Generated value must be a valid header value. By default
+    /// identifier is 32 random hex digits.
+    pub fn generator<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> String + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .generator = Box::new(f);
+        self
+    }
+}
+
+impl<S, B> Transform<S> for RequestIdentifier
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdentifierMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdentifierMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct RequestIdentifierMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for RequestIdentifierMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let propagated = if self.inner.propagate {
+            req.headers()
+                .get(&self.inner.header)
+                .filter(|val| !val.is_empty() && val.len() <= MAX_LENGTH)
+                .and_then(|val| val.to_str().ok())
+                .map(|s| s.to_string())
+        } else {
+            None
+        };
+        let id = propagated.unwrap_or_else(|| (self.inner.generator)());
+        let value = HeaderValue::from_str(&id);
+        req.extensions_mut().insert(RequestId(Rc::new(id)));
+
+        let header = self.inner.header.clone();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+
+            if let Ok(value) = value {
+                if !res.headers().contains_key(&header) {
+                    res.headers_mut().insert(header, value);
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+/// Identifier of request assigned by
+/// [`RequestIdentifier`](struct.RequestIdentifier.html) middleware.
+///
+/// Handlers take `RequestId` argument to include identifier in logs or
+/// error reports. Identifier is assigned before inner services are called,
+/// so extractor fails with `500 Internal Server Error` only for services
+/// that are not wrapped by middleware, `RequestId::get()` returns `None`
+/// for them instead.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(Rc<String>);
+
+impl RequestId {
+    /// Get identifier of current request, if any.
+    pub fn get<T: HttpMessage>(req: &T) -> Option<RequestId> {
+        req.extensions().get::<RequestId>().cloned()
+    }
+
+    /// Identifier as string slice
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Deref for RequestId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match RequestId::get(req) {
+            Some(id) => ok(id),
+            None => err(ErrorInternalServerError(
+                "Request id is not available, register RequestIdentifier middleware",
+            )),
+        }
+    }
+}
+
+/// Generate 128 bit random identifier
+fn generate() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static STATE: RandomState = RandomState::new();
+    }
+
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let (hi, lo) = STATE.with(|state| {
+        let mut hasher = state.build_hasher();
+        seq.hash(&mut hasher);
+        let hi = hasher.finish();
+        hi.hash(&mut hasher);
+        (hi, hasher.finish())
+    });
+    format!("{:016x}{:016x}", hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::test::{ok_service, TestRequest};
+    use crate::HttpResponse;
+
+    #[actix_rt::test]
+    async fn test_generate() {
+        let mut mw = RequestIdentifier::new()
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let id1 = resp.headers().get(X_REQUEST_ID).unwrap().clone();
+        assert_eq!(id1.len(), 32);
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let id2 = resp.headers().get(X_REQUEST_ID).unwrap().clone();
+        assert_ne!(id1, id2);
+    }
+
+    #[actix_rt::test]
+    async fn test_propagate() {
+        let srv = |req: ServiceRequest| {
+            let id = RequestId::get(&req).unwrap();
+            assert_eq!(id.as_str(), "abc");
+            ok(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let mut mw = RequestIdentifier::new()
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header(X_REQUEST_ID, "abc").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.headers().get(X_REQUEST_ID).unwrap(), "abc");
+
+        let mut mw = RequestIdentifier::new()
+            .header("x-correlation-id")
+            .propagate(false)
+            .generator(|| "generated".to_string())
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header("x-correlation-id", "abc").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.headers().get("x-correlation-id").unwrap(), "generated");
+        assert!(resp.headers().get(X_REQUEST_ID).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_extractor() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(RequestId::from_request(&req, &mut pl).await.is_err());
+
+        req.extensions_mut()
+            .insert(RequestId(Rc::new("abc".to_string())));
+        let id = RequestId::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(&*id, "abc");
+    }
+}
@@ -0,0 +1,567 @@
+//! Middleware for setting security related response headers
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
+use rand::Rng;
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError};
+use crate::extract::FromRequest;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+/// Length of nonce in bytes, before encoding
+const NONCE_LENGTH: usize = 16;
+
+/// `Middleware` for setting security related response headers.
+///
+/// By default following headers are set:
+///
+/// * `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// * `X-Content-Type-Options: nosniff`
+/// * `X-Frame-Options: DENY`
+/// * `Referrer-Policy: strict-origin-when-cross-origin`
+///
+/// `Content-Security-Policy` is set if policy is configured with
+/// `content_security_policy()`. Headers already set by handler are not
+/// overridden.
+///
+/// If policy uses nonce, new nonce is generated for each request and is
+/// available with `CspNonce` extractor, e.g. for rendering it in
+/// templates.
+///
+/// ```rust
+/// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::middleware::{ContentSecurityPolicy, CspNonce};
+///
+/// async fn index(nonce: CspNonce) -> HttpResponse {
+///     HttpResponse::Ok()
+///         .content_type("text/html")
+///         .body(format!("<script nonce=\"{}\">init()</script>", nonce))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::SecurityHeaders::new().content_security_policy(
+///                 ContentSecurityPolicy::new()
+///                     .directive("default-src", &["'self'"])
+///                     .directive("img-src", &["'self'", "data:"])
+///                     .nonce("script-src"),
+///             ),
+///         )
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    csp: Option<CspHeader>,
+}
+
+/// Configured policy, value of policy without nonce is the same for all
+/// requests
+struct CspHeader {
+    name: HeaderName,
+    value: Option<HeaderValue>,
+    policy: ContentSecurityPolicy,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders {
+            inner: Rc::new(Inner {
+                headers: vec![
+                    (
+                        header::STRICT_TRANSPORT_SECURITY,
+                        HeaderValue::from_static("max-age=31536000; includeSubDomains"),
+                    ),
+                    (
+                        header::X_CONTENT_TYPE_OPTIONS,
+                        HeaderValue::from_static("nosniff"),
+                    ),
+                    (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+                    (
+                        header::REFERRER_POLICY,
+                        HeaderValue::from_static("strict-origin-when-cross-origin"),
+                    ),
+                ],
+                csp: None,
+            }),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Construct `SecurityHeaders` middleware with default headers.
+    pub fn new() -> SecurityHeaders {
+        SecurityHeaders::default()
+    }
+
+    /// Set `Strict-Transport-Security` header.
+    ///
+    /// Browsers ignore this header on plain http responses.
+    pub fn hsts(
+        self,
+        max_age: Duration,
+        include_subdomains: bool,
+        preload: bool,
+    ) -> Self {
+        let mut val = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            val.push_str("; includeSubDomains");
+        }
+        if preload {
+            val.push_str("; preload");
+        }
+        self.set(header::STRICT_TRANSPORT_SECURITY, Some(&val))
+    }
+
+    /// Do not set `Strict-Transport-Security` header.
+    pub fn disable_hsts(self) -> Self {
+        self.set(header::STRICT_TRANSPORT_SECURITY, None)
+    }
+
+    /// Do not set `X-Content-Type-Options` header.
+    pub fn disable_content_type_options(self) -> Self {
+        self.set(header::X_CONTENT_TYPE_OPTIONS, None)
+    }
+
+    /// Set `X-Frame-Options` header, e.g. `SAMEORIGIN`. By default is `DENY`.
+    pub fn frame_options(self, value: &str) -> Self {
+        self.set(header::X_FRAME_OPTIONS, Some(value))
+    }
+
+    /// Do not set `X-Frame-Options` header.
+    pub fn disable_frame_options(self) -> Self {
+        self.set(header::X_FRAME_OPTIONS, None)
+    }
+
+    /// Set `Referrer-Policy` header. By default is
+    /// `strict-origin-when-cross-origin`.
+    pub fn referrer_policy(self, value: &str) -> Self {
+        self.set(header::REFERRER_POLICY, Some(value))
+    }
+
+    /// Do not set `Referrer-Policy` header.
+    pub fn disable_referrer_policy(self) -> Self {
+        self.set(header::REFERRER_POLICY, None)
+    }
+
+    /// Set `Content-Security-Policy` header.
+    ///
+    /// Panics if policy could not be rendered to header value, i.e. if
+    /// sources contain control characters.
+    pub fn content_security_policy(mut self, csp: ContentSecurityPolicy) -> Self {
+        // nonce is base64, so it does not change validity of value
+        let value = match HeaderValue::try_from(csp.render(Some(""))) {
+            Ok(value) => value,
+            Err(_) => panic!("Can not create Content-Security-Policy value"),
+        };
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .csp = Some(CspHeader {
+            name: csp.header_name(),
+            value: if csp.uses_nonce() { None } else { Some(value) },
+            policy: csp,
+        });
+        self
+    }
+
+    fn set(mut self, name: HeaderName, value: Option<&str>) -> Self {
+        let headers = &mut Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .headers;
+        headers.retain(|(key, _)| *key != name);
+        if let Some(value) = value {
+            match HeaderValue::try_from(value) {
+                Ok(value) => headers.push((name, value)),
+                Err(_) => panic!("Can not create header value"),
+            }
+        }
+        self
+    }
+}
+
+/// Builder of `Content-Security-Policy` header value.
+///
+/// Directives are rendered in order of registration.
+#[derive(Clone, Debug, Default)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<Directive>,
+    report_only: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Directive {
+    name: String,
+    sources: Vec<String>,
+    nonce: bool,
+}
+
+impl ContentSecurityPolicy {
+    /// Construct empty policy.
+    pub fn new() -> ContentSecurityPolicy {
+        ContentSecurityPolicy::default()
+    }
+
+    /// Add sources to directive, e.g. `directive("script-src", &["'self'"])`.
+    ///
+    /// Directive without sources is rendered as is, e.g.
+    /// `upgrade-insecure-requests`.
+    pub fn directive(mut self, name: &str, sources: &[&str]) -> Self {
+        let directive = self.get_mut(name);
+        directive
+            .sources
+            .extend(sources.iter().map(|s| (*s).to_owned()));
+        self
+    }
+
+    /// Add per-request nonce source to directive, e.g. `script-src`.
+    pub fn nonce(mut self, name: &str) -> Self {
+        self.get_mut(name).nonce = true;
+        self
+    }
+
+    /// Send policy in `Content-Security-Policy-Report-Only` header, so
+    /// violations are reported, but not enforced.
+    pub fn report_only(mut self) -> Self {
+        self.report_only = true;
+        self
+    }
+
+    fn get_mut(&mut self, name: &str) -> &mut Directive {
+        let name = name.to_ascii_lowercase();
+        if let Some(idx) = self.directives.iter().position(|d| d.name == name) {
+            &mut self.directives[idx]
+        } else {
+            self.directives.push(Directive {
+                name,
+                sources: Vec::new(),
+                nonce: false,
+            });
+            self.directives.last_mut().unwrap()
+        }
+    }
+
+    fn uses_nonce(&self) -> bool {
+        self.directives.iter().any(|d| d.nonce)
+    }
+
+    fn header_name(&self) -> HeaderName {
+        if self.report_only {
+            header::CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            header::CONTENT_SECURITY_POLICY
+        }
+    }
+
+    /// Render header value with specified nonce
+    fn render(&self, nonce: Option<&str>) -> String {
+        let mut val = String::new();
+        for directive in &self.directives {
+            if !val.is_empty() {
+                val.push_str("; ");
+            }
+            val.push_str(&directive.name);
+            for source in &directive.sources {
+                val.push(' ');
+                val.push_str(source);
+            }
+            if let (true, Some(nonce)) = (directive.nonce, nonce) {
+                val.push_str(" 'nonce-");
+                val.push_str(nonce);
+                val.push('\'');
+            }
+        }
+        val
+    }
+}
+
+impl<S, B> Transform<S> for SecurityHeaders
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SecurityHeadersMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SecurityHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for SecurityHeadersMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let nonce = match self.inner.csp {
+            Some(CspHeader { value: None, .. }) => {
+                let nonce = CspNonce(Rc::new(generate()));
+                req.extensions_mut().insert(nonce.clone());
+                Some(nonce)
+            }
+            _ => None,
+        };
+
+        let inner = self.inner.clone();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+            let headers = res.headers_mut();
+
+            for (name, value) in &inner.headers {
+                if !headers.contains_key(name) {
+                    headers.insert(name.clone(), value.clone());
+                }
+            }
+
+            if let Some(ref csp) = inner.csp {
+                if !headers.contains_key(&csp.name) {
+                    let value = match (&csp.value, nonce) {
+                        (Some(value), _) => value.clone(),
+                        // policy is validated by builder
+                        (None, nonce) => {
+                            let nonce = nonce.as_ref().map(|n| n.as_str());
+                            HeaderValue::try_from(csp.policy.render(nonce)).unwrap()
+                        }
+                    };
+                    headers.insert(csp.name.clone(), value);
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+/// Content security policy nonce of current request, generated by
+/// [`SecurityHeaders`](struct.SecurityHeaders.html) middleware.
+///
+/// Nonce is generated only if configured policy has a `nonce()` source,
+/// `CspNonce` argument fails with `500 Internal Server Error` for policies
+/// without it. Templates shared by pages with and without nonce should use
+/// `CspNonce::get()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CspNonce(Rc<String>);
+
+impl CspNonce {
+    /// Get nonce of current request, if any.
+    pub fn get<T: HttpMessage>(req: &T) -> Option<CspNonce> {
+        req.extensions().get::<CspNonce>().cloned()
+    }
+
+    /// Nonce as string slice
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Deref for CspNonce {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for CspNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for CspNonce {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match CspNonce::get(req) {
+            Some(nonce) => ok(nonce),
+            None => err(ErrorInternalServerError(
+                "CSP nonce is not available, configure policy with nonce",
+            )),
+        }
+    }
+}
+
+/// Generate random base64 nonce
+fn generate() -> String {
+    let bytes: [u8; NONCE_LENGTH] = rand::thread_rng().gen();
+    base64::encode(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_default_headers() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(SecurityHeaders::new().disable_referrer_policy().hsts(
+                    Duration::from_secs(60),
+                    false,
+                    true,
+                ))
+                .route("/", web::get().to(|| HttpResponse::Ok()))
+                .route(
+                    "/frame",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .header(header::X_FRAME_OPTIONS, "SAMEORIGIN")
+                            .finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=60; preload"
+        );
+        assert_eq!(
+            headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(headers.get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert!(!headers.contains_key(header::REFERRER_POLICY));
+        assert!(!headers.contains_key(header::CONTENT_SECURITY_POLICY));
+
+        // handler headers are not overridden
+        let req = TestRequest::with_uri("/frame").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::X_FRAME_OPTIONS).unwrap(),
+            "SAMEORIGIN"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_csp_nonce() {
+        let csp = ContentSecurityPolicy::new()
+            .directive("default-src", &["'self'"])
+            .nonce("script-src")
+            .directive("script-src", &["'strict-dynamic'"])
+            .directive("upgrade-insecure-requests", &[]);
+        let mut srv = init_service(
+            App::new()
+                .wrap(SecurityHeaders::new().content_security_policy(csp))
+                .route(
+                    "/",
+                    web::get().to(|nonce: CspNonce| {
+                        HttpResponse::Ok().body(nonce.to_string())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        let csp = resp
+            .headers()
+            .get(header::CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let nonce = read_body(resp).await;
+        let nonce = std::str::from_utf8(&nonce).unwrap();
+        assert_eq!(nonce.len(), 24);
+        assert_eq!(
+            csp,
+            format!(
+                "default-src 'self'; script-src 'strict-dynamic' 'nonce-{}'; \
+                 upgrade-insecure-requests",
+                nonce
+            )
+        );
+
+        // nonce is generated per request
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        let body = read_body(resp).await;
+        assert_ne!(&body[..], nonce.as_bytes());
+    }
+
+    #[actix_rt::test]
+    async fn test_csp_report_only() {
+        let csp = ContentSecurityPolicy::new()
+            .directive("Default-Src", &["'none'"])
+            .report_only();
+        let mut srv = init_service(
+            App::new()
+                .wrap(SecurityHeaders::new().content_security_policy(csp))
+                .route("/", web::get().to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_SECURITY_POLICY_REPORT_ONLY)
+                .unwrap(),
+            "default-src 'none'"
+        );
+        assert!(!resp.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    #[actix_rt::test]
+    async fn test_extractor() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(CspNonce::from_request(&req, &mut pl).await.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can not create Content-Security-Policy value")]
+    fn test_invalid_policy() {
+        let csp = ContentSecurityPolicy::new().directive("default-src", &["'self'\n"]);
+        let _ = SecurityHeaders::new().content_security_policy(csp);
+    }
+}
@@ -0,0 +1,206 @@
+//! Middleware for limiting request handling time
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_rt::time::timeout;
+use actix_service::{Service, Transform};
+use bytes::Bytes;
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::{ServiceRequest, ServiceResponse};
+use crate::error::{Error, InternalError};
+use crate::http::StatusCode;
+use crate::types::{CancelReason, Cancellation};
+use crate::HttpResponse;
+
+/// `Middleware` for limiting time of request handling.
+///
+/// If inner service does not produce response within specified duration,
+/// handling is cancelled and middleware resolves to an error, which is
+/// rendered as `503 Service Unavailable` response. Cancellation handle of
+/// request, see `HttpRequest::cancellation()`, is notified on timeout.
+/// Deadline applies to response creation only, streaming of response body
+/// is not limited.
+///
+/// Middleware could be registered for application, scope or single resource.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix_web::{web, middleware, App, HttpResponse};
+/// use actix_web::http::StatusCode;
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Timeout::new(Duration::from_secs(30)))
+///         .service(
+///             web::resource("/report")
+///                 .wrap(
+///                     middleware::Timeout::new(Duration::from_secs(5))
+///                         .status(StatusCode::GATEWAY_TIMEOUT)
+///                         .body("Report is not ready"),
+///                 )
+///                 .route(web::get().to(|| HttpResponse::Ok())),
+///         );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Timeout {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    duration: Duration,
+    status: StatusCode,
+    body: Bytes,
+}
+
+impl Timeout {
+    /// Construct `Timeout` middleware with specified deadline.
+    pub fn new(duration: Duration) -> Timeout {
+        Timeout {
+            inner: Rc::new(Inner {
+                duration,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                body: Bytes::from_static(b"Request timeout"),
+            }),
+        }
+    }
+
+    /// Set status code of timeout response. By default is
+    /// `503 Service Unavailable`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = status;
+        self
+    }
+
+    /// Set body of timeout response.
+    pub fn body<B: Into<Bytes>>(mut self, body: B) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .body = body.into();
+        self
+    }
+}
+
+impl<S, B> Transform<S> for Timeout
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TimeoutMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TimeoutMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct TimeoutMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for TimeoutMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        let path = req.path().to_string();
+        let cancel = Cancellation::get(req.head(), req.app_config());
+        let fut = self.service.call(req);
+
+        async move {
+            match timeout(inner.duration, fut).await {
+                Ok(res) => res,
+                Err(_) => {
+                    log::debug!("Request handling timed out. Request path: {}", path);
+                    cancel.cancel(CancelReason::Timeout);
+                    let res = HttpResponse::build(inner.status).body(inner.body.clone());
+                    Err(InternalError::from_response("Request timeout", res).into())
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::delay_for;
+
+    use super::*;
+    use crate::responder::tests::BodyTest;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App};
+
+    #[actix_rt::test]
+    async fn test_timeout() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(50)))
+                .route("/fast", web::get().to(|| HttpResponse::Ok()))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        delay_for(Duration::from_millis(500)).await;
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/fast").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/slow").to_request();
+        let resp: HttpResponse = srv.call(req).await.err().unwrap().into();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.body().bin_ref(), b"Request timeout");
+    }
+
+    #[actix_rt::test]
+    async fn test_resource_timeout() {
+        let mut srv = init_service(
+            App::new().service(
+                web::resource("/slow")
+                    .wrap(
+                        Timeout::new(Duration::from_millis(50))
+                            .status(StatusCode::GATEWAY_TIMEOUT)
+                            .body("too slow"),
+                    )
+                    .to(|| async {
+                        delay_for(Duration::from_millis(500)).await;
+                        HttpResponse::Ok()
+                    }),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/slow").to_request();
+        let resp: HttpResponse = srv.call(req).await.err().unwrap().into();
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(resp.body().bin_ref(), b"too slow");
+    }
+}
@@ -0,0 +1,731 @@
+//! Middleware for distributed tracing
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use actix_http::RequestHead;
+use actix_service::{Service, Transform};
+use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorInternalServerError};
+use crate::extract::FromRequest;
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::StatusCode;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+use crate::HttpMessage;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+const B3: &str = "b3";
+const X_B3_TRACEID: &str = "x-b3-traceid";
+const X_B3_SPANID: &str = "x-b3-spanid";
+const X_B3_PARENTSPANID: &str = "x-b3-parentspanid";
+const X_B3_SAMPLED: &str = "x-b3-sampled";
+const X_B3_FLAGS: &str = "x-b3-flags";
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = RefCell::new(None);
+}
+
+/// `Middleware` for distributed tracing.
+///
+/// Trace context is extracted from request's W3C `traceparent` header or
+/// B3 headers, new trace is started if request does not contain valid
+/// context. Each request gets a span, which is a child of the incoming
+/// context. Span context could be accessed by using `TraceContext`
+/// extractor, and with `TraceContext::current()` while request is
+/// handled, so it could be added to log records.
+///
+/// Context is propagated to outgoing requests of clients that are
+/// built with `TraceContext::inject_current` request hook. Finished spans
+/// of sampled traces are passed to function set with `on_finish()`, i.e.
+/// for exporting, and logged with `debug` level.
+///
+/// ```rust
+/// use actix_web::{client::Client, web, middleware, App, HttpResponse};
+/// use actix_web::middleware::TraceContext;
+///
+/// async fn index(client: web::Data<Client>, ctx: TraceContext) -> HttpResponse {
+///     // `traceparent` header is added to request
+///     let _ = client.get("http://localhost:8081/").send().await;
+///     HttpResponse::Ok().body(ctx.trace_id())
+/// }
+///
+/// #[actix_rt::main]
+/// async fn main() {
+///     let client = Client::build()
+///         .on_request(TraceContext::inject_current)
+///         .finish();
+///
+///     let app = App::new()
+///         .data(client)
+///         .wrap(
+///             middleware::Logger::new("%{trace}xi %r %s")
+///                 .custom_field("trace", |req, _| {
+///                     TraceContext::get(req).map(|ctx| ctx.trace_id()).unwrap_or_default()
+///                 }),
+///         )
+///         .wrap(middleware::Tracing::new().on_finish(|span| {
+///             println!("{} took {:?}", span.name(), span.duration());
+///         }))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Tracing {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    format: TraceFormat,
+    propagate: bool,
+    on_finish: Option<Box<dyn Fn(&RequestSpan)>>,
+}
+
+impl Default for Tracing {
+    fn default() -> Self {
+        Tracing {
+            inner: Rc::new(Inner {
+                format: TraceFormat::TraceContext,
+                propagate: true,
+                on_finish: None,
+            }),
+        }
+    }
+}
+
+impl Tracing {
+    /// Construct `Tracing` middleware.
+    pub fn new() -> Tracing {
+        Tracing::default()
+    }
+
+    /// Set format of headers for propagating context of new traces.
+    /// By default is `TraceFormat::TraceContext`.
+    ///
+    /// Format of propagated traces is preserved.
+    pub fn format(mut self, format: TraceFormat) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .format = format;
+        self
+    }
+
+    /// Use trace context of incoming request if present. By default is `true`.
+    ///
+    /// Disable if requests come from untrusted clients.
+    pub fn propagate(mut self, propagate: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .propagate = propagate;
+        self
+    }
+
+    /// Set function that is called with each finished span of sampled trace.
+    pub fn on_finish<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&RequestSpan) + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .on_finish = Some(Box::new(f));
+        self
+    }
+}
+
+impl<S, B> Transform<S> for Tracing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TracingMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+pub struct TracingMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service for TracingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future =
+        Instrumented<LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let incoming = if self.inner.propagate {
+            TraceContext::from_headers(req.headers())
+        } else {
+            None
+        };
+        let context = match incoming {
+            Some(ctx) => ctx.child(),
+            None => TraceContext::new(self.inner.format),
+        };
+        req.extensions_mut().insert(context.clone());
+
+        let name = format!(
+            "{} {}",
+            req.method(),
+            req.resource_map()
+                .match_pattern(req.path())
+                .unwrap_or_else(|| req.path().to_owned())
+        );
+        let start = SystemTime::now();
+        let started = Instant::now();
+        let inner = self.inner.clone();
+        let span_context = context.clone();
+
+        // call inner service within context, it could start outgoing requests
+        let _guard = Entered::enter(context.clone());
+        let fut = self.service.call(req);
+
+        let fut = async move {
+            let res = fut.await;
+
+            if span_context.is_sampled() {
+                let span = RequestSpan {
+                    context: span_context,
+                    name,
+                    start,
+                    duration: started.elapsed(),
+                    status: match res {
+                        Ok(ref res) => res.status(),
+                        Err(ref e) => e.as_response_error().status_code(),
+                    },
+                };
+                log::debug!("{}", span);
+                if let Some(ref f) = inner.on_finish {
+                    f(&span);
+                }
+            }
+            res
+        }
+        .boxed_local();
+
+        Instrumented { fut, context }
+    }
+}
+
+/// Future that sets current trace context while it is polled
+#[doc(hidden)]
+pub struct Instrumented<F> {
+    fut: F,
+    context: TraceContext,
+}
+
+impl<F: Future + Unpin> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = Entered::enter(self.context.clone());
+        Pin::new(&mut self.fut).poll(cx)
+    }
+}
+
+/// Restores previous current trace context on drop, including unwinding
+struct Entered(Option<TraceContext>);
+
+impl Entered {
+    fn enter(context: TraceContext) -> Entered {
+        Entered(CURRENT.with(|current| current.replace(Some(context))))
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let prev = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Format of trace context headers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// W3C Trace Context `traceparent` and `tracestate` headers
+    TraceContext,
+    /// Zipkin B3 `X-B3-*` headers
+    B3,
+}
+
+/// Trace context of request span, assigned by
+/// [`Tracing`](struct.Tracing.html) middleware.
+///
+/// Handlers take `TraceContext` argument to log trace id or to start child
+/// spans with `child()`. Every request that passes `Tracing` middleware has
+/// a context, sampled or not, so extractor fails with
+/// `500 Internal Server Error` only for unwrapped services.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    sampled: bool,
+    state: Option<HeaderValue>,
+    format: TraceFormat,
+}
+
+impl TraceContext {
+    /// Start new sampled trace.
+    pub fn new(format: TraceFormat) -> TraceContext {
+        TraceContext {
+            trace_id: random_id(),
+            span_id: random_id(),
+            parent_id: None,
+            sampled: true,
+            state: None,
+            format,
+        }
+    }
+
+    /// Extract trace context from W3C `traceparent` header, B3 single
+    /// header or B3 multiple headers, if any of them is valid.
+    pub fn from_headers(headers: &HeaderMap) -> Option<TraceContext> {
+        let get = |name| headers.get(name).and_then(|val| val.to_str().ok());
+
+        if let Some(ctx) = get(TRACEPARENT).and_then(parse_traceparent) {
+            return Some(TraceContext {
+                state: headers.get(TRACESTATE).cloned(),
+                ..ctx
+            });
+        }
+        if let Some(ctx) = get(B3).and_then(parse_b3) {
+            return Some(ctx);
+        }
+
+        let trace_id = get(X_B3_TRACEID).and_then(parse_trace_id)?;
+        let span_id = get(X_B3_SPANID).and_then(parse_span_id)?;
+        let parent_id = match get(X_B3_PARENTSPANID) {
+            Some(val) => Some(parse_span_id(val)?),
+            None => None,
+        };
+        let sampled = match (get(X_B3_FLAGS), get(X_B3_SAMPLED)) {
+            (Some("1"), _) => true,
+            (_, Some("0")) | (_, Some("false")) => false,
+            _ => true,
+        };
+        Some(TraceContext {
+            trace_id,
+            span_id,
+            parent_id,
+            sampled,
+            state: None,
+            format: TraceFormat::B3,
+        })
+    }
+
+    /// Get trace context of current request span, if any.
+    pub fn get<T: HttpMessage>(req: &T) -> Option<TraceContext> {
+        req.extensions().get::<TraceContext>().cloned()
+    }
+
+    /// Get trace context of request span, which is handled by current
+    /// thread, if any.
+    pub fn current() -> Option<TraceContext> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Add headers of trace context of current request span to outgoing
+    /// request head, if there is current span.
+    ///
+    /// It could be used as client request hook:
+    ///
+    /// ```rust
+    /// use actix_web::client::Client;
+    /// use actix_web::middleware::TraceContext;
+    ///
+    /// # #[actix_rt::main]
+    /// # async fn main() {
+    /// let client = Client::build()
+    ///     .on_request(TraceContext::inject_current)
+    ///     .finish();
+    /// # }
+    /// ```
+    pub fn inject_current(head: &mut RequestHead) {
+        if let Some(ctx) = TraceContext::current() {
+            ctx.inject(&mut head.headers);
+        }
+    }
+
+    /// Create context of child span.
+    pub fn child(&self) -> TraceContext {
+        TraceContext {
+            span_id: random_id(),
+            parent_id: Some(self.span_id),
+            ..self.clone()
+        }
+    }
+
+    /// Add headers of trace context, so span is parent of request.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        match self.format {
+            TraceFormat::TraceContext => {
+                let value = format!(
+                    "00-{}-{}-{}",
+                    self.trace_id(),
+                    self.span_id(),
+                    if self.sampled { "01" } else { "00" }
+                );
+                insert(headers, TRACEPARENT, &value);
+                if let Some(ref state) = self.state {
+                    headers.insert(HeaderName::from_static(TRACESTATE), state.clone());
+                }
+            }
+            TraceFormat::B3 => {
+                insert(headers, X_B3_TRACEID, &self.trace_id());
+                insert(headers, X_B3_SPANID, &self.span_id());
+                insert(headers, X_B3_SAMPLED, if self.sampled { "1" } else { "0" });
+            }
+        }
+    }
+
+    /// Trace identifier as 32 hex digits
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// Span identifier as 16 hex digits
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// Parent span identifier as 16 hex digits, if span is not root span
+    pub fn parent_id(&self) -> Option<String> {
+        self.parent_id.map(|id| format!("{:016x}", id))
+    }
+
+    /// Check if trace is sampled
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// Format of context headers
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+}
+
+impl FromRequest for TraceContext {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match TraceContext::get(req) {
+            Some(ctx) => ok(ctx),
+            None => err(ErrorInternalServerError(
+                "Trace context is not available, register Tracing middleware",
+            )),
+        }
+    }
+}
+
+/// Finished span of request, produced by [`Tracing`](struct.Tracing.html)
+/// middleware.
+pub struct RequestSpan {
+    context: TraceContext,
+    name: String,
+    start: SystemTime,
+    duration: Duration,
+    status: StatusCode,
+}
+
+impl RequestSpan {
+    /// Trace context of span
+    pub fn context(&self) -> &TraceContext {
+        &self.context
+    }
+
+    /// Span name, request method and matched resource pattern or path
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time when request handling started
+    pub fn start(&self) -> SystemTime {
+        self.start
+    }
+
+    /// Duration of request handling, without streaming of response body
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Response status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl fmt::Display for RequestSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Span \"{}\" trace_id={} span_id={} parent_id={} status={} duration={:?}",
+            self.name,
+            self.context.trace_id(),
+            self.context.span_id(),
+            self.context
+                .parent_id()
+                .as_ref()
+                .map_or("-", |s| s.as_str()),
+            self.status.as_u16(),
+            self.duration,
+        )
+    }
+}
+
+fn insert(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+/// Generate non-zero random identifier
+fn random_id<T>() -> T
+where
+    T: Default + PartialEq,
+    rand::distributions::Standard: rand::distributions::Distribution<T>,
+{
+    loop {
+        let id = rand::random();
+        if id != T::default() {
+            return id;
+        }
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes()
+        .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Parse non-zero 64 or 128 bit trace identifier
+fn parse_trace_id(s: &str) -> Option<u128> {
+    if (s.len() == 16 || s.len() == 32) && is_hex(s) {
+        u128::from_str_radix(s, 16).ok().filter(|id| *id != 0)
+    } else {
+        None
+    }
+}
+
+/// Parse non-zero 64 bit span identifier
+fn parse_span_id(s: &str) -> Option<u64> {
+    if s.len() == 16 && is_hex(s) {
+        u64::from_str_radix(s, 16).ok().filter(|id| *id != 0)
+    } else {
+        None
+    }
+}
+
+/// Parse `traceparent` header, i.e. `00-{trace-id}-{parent-id}-{flags}`
+fn parse_traceparent(s: &str) -> Option<TraceContext> {
+    let mut parts = s.split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parts.next()?;
+
+    // future versions could append fields
+    if version.len() != 2 || !is_hex(version) || version == "ff" {
+        return None;
+    }
+    if version == "00" && parts.next().is_some() {
+        return None;
+    }
+    if trace_id.len() != 32 || flags.len() != 2 || !is_hex(flags) {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+
+    Some(TraceContext {
+        trace_id: parse_trace_id(trace_id)?,
+        span_id: parse_span_id(span_id)?,
+        parent_id: None,
+        sampled: flags & 1 == 1,
+        state: None,
+        format: TraceFormat::TraceContext,
+    })
+}
+
+/// Parse B3 single header, i.e. `{trace-id}-{span-id}-{sampled}-{parent-id}`
+fn parse_b3(s: &str) -> Option<TraceContext> {
+    let mut parts = s.split('-');
+    let trace_id = parse_trace_id(parts.next()?)?;
+    let span_id = parse_span_id(parts.next()?)?;
+    let sampled = match parts.next() {
+        None | Some("1") | Some("d") => true,
+        Some("0") => false,
+        Some(_) => return None,
+    };
+    let parent_id = match parts.next() {
+        Some(val) => Some(parse_span_id(val)?),
+        None => None,
+    };
+
+    Some(TraceContext {
+        trace_id,
+        span_id,
+        parent_id,
+        sampled,
+        state: None,
+        format: TraceFormat::B3,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    const PARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn test_traceparent() {
+        let ctx = parse_traceparent(PARENT).unwrap();
+        assert_eq!(ctx.trace_id(), "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(ctx.span_id(), "b7ad6b7169203331");
+        assert!(ctx.is_sampled());
+
+        let mut headers = HeaderMap::new();
+        ctx.inject(&mut headers);
+        assert_eq!(headers.get(TRACEPARENT).unwrap(), PARENT);
+
+        let ctx = parse_traceparent(
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-extra",
+        )
+        .unwrap();
+        assert!(!ctx.is_sampled());
+
+        for s in &[
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-+7ad6b7169203331-01",
+            "00-0af7651916cd43dd-b7ad6b7169203331-01",
+        ] {
+            assert!(parse_traceparent(s).is_none(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_b3() {
+        let ctx = parse_b3(
+            "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-0-05e3ac9a4f6e3b90",
+        )
+        .unwrap();
+        assert_eq!(ctx.trace_id(), "80f198ee56343ba864fe8b2a57d3eff7");
+        assert_eq!(ctx.span_id(), "e457b5a2e4d86bd1");
+        assert_eq!(ctx.parent_id().unwrap(), "05e3ac9a4f6e3b90");
+        assert!(!ctx.is_sampled());
+        assert!(parse_b3("0").is_none());
+
+        let mut headers = HeaderMap::new();
+        insert(&mut headers, X_B3_TRACEID, "64fe8b2a57d3eff7");
+        insert(&mut headers, X_B3_SPANID, "e457b5a2e4d86bd1");
+        insert(&mut headers, X_B3_SAMPLED, "1");
+        let ctx = TraceContext::from_headers(&headers).unwrap();
+        assert_eq!(ctx.trace_id(), "000000000000000064fe8b2a57d3eff7");
+        assert_eq!(ctx.format(), TraceFormat::B3);
+        assert!(ctx.is_sampled());
+
+        let mut headers = HeaderMap::new();
+        ctx.child().inject(&mut headers);
+        assert_eq!(
+            headers.get(X_B3_TRACEID).unwrap(),
+            "000000000000000064fe8b2a57d3eff7"
+        );
+        assert_ne!(headers.get(X_B3_SPANID).unwrap(), "e457b5a2e4d86bd1");
+    }
+
+    #[actix_rt::test]
+    async fn test_tracing() {
+        let spans = Rc::new(RefCell::new(Vec::new()));
+        let spans2 = spans.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(Tracing::new().on_finish(move |span| {
+                    spans2
+                        .borrow_mut()
+                        .push((span.name().to_owned(), span.context().clone()))
+                }))
+                .route(
+                    "/items/{id}",
+                    web::get().to(|ctx: TraceContext| async move {
+                        actix_rt::time::delay_for(Duration::from_millis(10)).await;
+                        assert_eq!(TraceContext::current().unwrap(), ctx);
+
+                        let mut head = RequestHead::default();
+                        TraceContext::inject_current(&mut head);
+                        HttpResponse::Ok().body(
+                            head.headers
+                                .get(TRACEPARENT)
+                                .unwrap()
+                                .to_str()
+                                .unwrap()
+                                .to_owned(),
+                        )
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/items/1")
+            .header(TRACEPARENT, PARENT)
+            .header(TRACESTATE, "congo=t61rcWkgMzE")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        let body = read_body(resp).await;
+        assert!(TraceContext::current().is_none());
+
+        let (name, ctx) = spans.borrow_mut().pop().unwrap();
+        assert_eq!(name, "GET /items/{id}");
+        assert_eq!(ctx.trace_id(), "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(ctx.parent_id().unwrap(), "b7ad6b7169203331");
+        assert_eq!(ctx.state.as_ref().unwrap(), "congo=t61rcWkgMzE");
+        assert_eq!(
+            body,
+            format!("00-0af7651916cd43dd8448eb211c80319c-{}-01", ctx.span_id())
+        );
+
+        // new trace
+        let req = TestRequest::with_uri("/items/2").to_request();
+        let _ = call_service(&mut srv, req).await;
+        let (_, ctx) = spans.borrow_mut().pop().unwrap();
+        assert_ne!(ctx.trace_id(), "0af7651916cd43dd8448eb211c80319c");
+        assert!(ctx.parent_id().is_none());
+    }
+}
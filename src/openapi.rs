@@ -0,0 +1,751 @@
+//! OpenAPI 3.0 document of application routes
+//!
+//! Every resource route is described by operation of document, route with
+//! method guard, i.e. route created with `web::get()`, by operation of its
+//! method, route without method guard, i.e. created with `web::to()`, by
+//! operations of `GET`, `POST`, `PUT`, `PATCH` and `DELETE` methods that are
+//! not handled by other routes of resource, method of route with
+//! `guard::Get()` guard is not known. Path parameters are
+//! derived from resource patterns, scopes included, request body content
+//! types and header parameters are derived from extractors of handler,
+//! other details are set with `Route::operation()`. Document and optional
+//! docs page are served by `OpenApi` service, that has to be registered on
+//! `App`.
+//!
+//! ```rust
+//! use actix_web::http::StatusCode;
+//! use actix_web::openapi::{OpenApi, Operation, Parameter};
+//! use actix_web::{web, App, HttpResponse};
+//! use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Pet {
+//!     name: String,
+//! }
+//!
+//! async fn update_pet(pet: web::Json<Pet>) -> HttpResponse {
+//!     HttpResponse::NoContent().finish()
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .service(
+//!             web::resource("/pets/{id}").route(
+//!                 web::put()
+//!                     .operation(
+//!                         Operation::new()
+//!                             .summary("Update pet")
+//!                             .tag("pets")
+//!                             .parameter(Parameter::query("notify"))
+//!                             .response(StatusCode::NO_CONTENT, "Pet is updated"),
+//!                     )
+//!                     .to(update_pet),
+//!             ),
+//!         )
+//!         .service(OpenApi::new("Pet store", "1.0").ui("/docs"));
+//! }
+//! ```
+use std::rc::Rc;
+
+use actix_http::http::{Method, StatusCode};
+use futures::future::ready;
+use serde_json::{json, Map, Value};
+
+use crate::dev::{AppService, HttpServiceFactory};
+use crate::extract::FromRequest;
+use crate::guard;
+use crate::request::HttpRequest;
+use crate::resource::Resource;
+use crate::route::Route;
+use crate::HttpResponse;
+
+/// Location of parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterIn {
+    /// Dynamic segment of path
+    Path,
+    /// Query parameter
+    Query,
+    /// Request header
+    Header,
+    /// Cookie
+    Cookie,
+}
+
+impl ParameterIn {
+    fn as_str(self) -> &'static str {
+        match self {
+            ParameterIn::Path => "path",
+            ParameterIn::Query => "query",
+            ParameterIn::Header => "header",
+            ParameterIn::Cookie => "cookie",
+        }
+    }
+}
+
+/// Parameter of operation
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    name: String,
+    location: ParameterIn,
+    description: Option<String>,
+    required: bool,
+    schema: Value,
+}
+
+impl Parameter {
+    /// Create parameter with `string` schema, path parameters are required.
+    pub fn new<N: Into<String>>(name: N, location: ParameterIn) -> Self {
+        Parameter {
+            name: name.into(),
+            location,
+            description: None,
+            required: location == ParameterIn::Path,
+            schema: json!({ "type": "string" }),
+        }
+    }
+
+    /// Create path parameter
+    pub fn path<N: Into<String>>(name: N) -> Self {
+        Parameter::new(name, ParameterIn::Path)
+    }
+
+    /// Create query parameter
+    pub fn query<N: Into<String>>(name: N) -> Self {
+        Parameter::new(name, ParameterIn::Query)
+    }
+
+    /// Create header parameter
+    pub fn header<N: Into<String>>(name: N) -> Self {
+        Parameter::new(name, ParameterIn::Header)
+    }
+
+    /// Set description of parameter
+    pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set whether parameter is required
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Set json schema of parameter
+    pub fn schema(mut self, schema: Value) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut param = Map::new();
+        param.insert("name".to_owned(), self.name.clone().into());
+        param.insert("in".to_owned(), self.location.as_str().into());
+        if let Some(ref description) = self.description {
+            param.insert("description".to_owned(), description.clone().into());
+        }
+        param.insert("required".to_owned(), self.required.into());
+        param.insert("schema".to_owned(), self.schema.clone());
+        Value::Object(param)
+    }
+}
+
+/// Operation of route
+///
+/// Responses are described with `default` response if none is set.
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    operation_id: Option<String>,
+    tags: Vec<String>,
+    deprecated: bool,
+    parameters: Vec<Parameter>,
+    content: Vec<(String, Value)>,
+    responses: Vec<(StatusCode, String)>,
+}
+
+impl Operation {
+    /// Create empty operation
+    pub fn new() -> Self {
+        Operation::default()
+    }
+
+    /// Set short summary of operation
+    pub fn summary<S: Into<String>>(mut self, summary: S) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Set description of operation
+    pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set unique id of operation
+    pub fn operation_id<I: Into<String>>(mut self, id: I) -> Self {
+        self.operation_id = Some(id.into());
+        self
+    }
+
+    /// Add tag of operation
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Mark operation as deprecated
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    /// Add parameter, path parameter overrides parameter that is derived
+    /// from resource pattern.
+    pub fn parameter(mut self, parameter: Parameter) -> Self {
+        self.add_parameter(parameter);
+        self
+    }
+
+    /// Add request body content type with json schema
+    pub fn request_body(mut self, content_type: &str, schema: Value) -> Self {
+        self.content.retain(|(ct, _)| ct != content_type);
+        self.content.push((content_type.to_owned(), schema));
+        self
+    }
+
+    /// Add response with description
+    pub fn response<D: Into<String>>(
+        mut self,
+        status: StatusCode,
+        description: D,
+    ) -> Self {
+        self.responses.push((status, description.into()));
+        self
+    }
+
+    /// Add parameter unless operation has parameter with same name and
+    /// location, used by extractors.
+    pub fn add_parameter(&mut self, parameter: Parameter) {
+        let exists = self.parameters.iter().any(|p| {
+            p.location == parameter.location
+                && p.name.eq_ignore_ascii_case(&parameter.name)
+        });
+        if !exists {
+            self.parameters.push(parameter);
+        }
+    }
+
+    /// Add request body content type with any schema unless it is already
+    /// described, used by extractors.
+    pub fn add_request_content(&mut self, content_type: &str) {
+        if !self.content.iter().any(|(ct, _)| ct == content_type) {
+            self.content.push((content_type.to_owned(), json!({})));
+        }
+    }
+
+    /// Add parameters and request body of extractor `T` as optional
+    pub(crate) fn add_optional<T: FromRequest>(&mut self) {
+        let mut op = Operation::new();
+        T::describe(&mut op);
+        for param in op.parameters {
+            let required = param.location == ParameterIn::Path;
+            self.add_parameter(param.required(required));
+        }
+        for (ct, _) in op.content {
+            self.add_request_content(&ct);
+        }
+    }
+
+    fn to_json(&self, path_params: &[String]) -> Value {
+        let mut op = Map::new();
+        if let Some(ref summary) = self.summary {
+            op.insert("summary".to_owned(), summary.clone().into());
+        }
+        if let Some(ref description) = self.description {
+            op.insert("description".to_owned(), description.clone().into());
+        }
+        if let Some(ref id) = self.operation_id {
+            op.insert("operationId".to_owned(), id.clone().into());
+        }
+        if !self.tags.is_empty() {
+            op.insert("tags".to_owned(), self.tags.clone().into());
+        }
+        if self.deprecated {
+            op.insert("deprecated".to_owned(), true.into());
+        }
+
+        let mut params = self.clone();
+        for name in path_params {
+            params.add_parameter(Parameter::path(name.as_str()));
+        }
+        if !params.parameters.is_empty() {
+            let params = params.parameters.iter().map(Parameter::to_json).collect();
+            op.insert("parameters".to_owned(), Value::Array(params));
+        }
+
+        if !self.content.is_empty() {
+            let mut content = Map::new();
+            for (ct, schema) in &self.content {
+                content.insert(ct.clone(), json!({ "schema": schema }));
+            }
+            op.insert("requestBody".to_owned(), json!({ "content": content }));
+        }
+
+        let mut responses = Map::new();
+        for (status, description) in &self.responses {
+            responses.insert(
+                status.as_str().to_owned(),
+                json!({ "description": description }),
+            );
+        }
+        if responses.is_empty() {
+            responses.insert(
+                "default".to_owned(),
+                json!({ "description": "Default response" }),
+            );
+        }
+        op.insert("responses".to_owned(), Value::Object(responses));
+        Value::Object(op)
+    }
+}
+
+/// Methods that describe route without method guard
+pub(crate) const ANY_METHODS: &[Method] = &[
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Default Swagger UI assets, version is pinned so page does not change
+/// with new releases
+const SWAGGER_UI_CSS: &str = "https://unpkg.com/swagger-ui-dist@3.25.0/swagger-ui.css";
+const SWAGGER_UI_JS: &str =
+    "https://unpkg.com/swagger-ui-dist@3.25.0/swagger-ui-bundle.js";
+
+/// Operation of route with full path pattern of resource
+#[derive(Debug, Clone)]
+pub(crate) struct PathOperation {
+    pub(crate) path: String,
+    pub(crate) method: Method,
+    pub(crate) operation: Operation,
+}
+
+/// Service that serves OpenAPI document of application
+///
+/// Document is served at `/openapi.json` by default, docs page uses
+/// Swagger UI 3.25.0 that is loaded from `unpkg.com`, self-hosted or
+/// integrity checked assets are set with `ui_assets()`. Document and docs
+/// page are not described in document.
+///
+/// Service could be registered in scope, document describes routes of whole
+/// application.
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    path: String,
+    ui: Option<String>,
+    assets: UiAssets,
+}
+
+/// Stylesheet and script of docs page
+#[derive(Debug, Clone)]
+struct UiAssets {
+    css: String,
+    js: String,
+    integrity: Option<(String, String)>,
+}
+
+impl OpenApi {
+    /// Create service for api with `title` and `version`
+    pub fn new<T: Into<String>, V: Into<String>>(title: T, version: V) -> Self {
+        OpenApi {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+            path: "/openapi.json".to_owned(),
+            ui: None,
+            assets: UiAssets {
+                css: SWAGGER_UI_CSS.to_owned(),
+                js: SWAGGER_UI_JS.to_owned(),
+                integrity: None,
+            },
+        }
+    }
+
+    /// Set description of api
+    pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set path of document
+    pub fn path<P: Into<String>>(mut self, path: P) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Serve docs page at `path`
+    pub fn ui<P: Into<String>>(mut self, path: P) -> Self {
+        self.ui = Some(path.into());
+        self
+    }
+
+    /// Set urls of `swagger-ui.css` and `swagger-ui-bundle.js` assets of docs
+    /// page, e.g. files served by application itself.
+    pub fn ui_assets<C: Into<String>, J: Into<String>>(mut self, css: C, js: J) -> Self {
+        self.assets.css = css.into();
+        self.assets.js = js.into();
+        self
+    }
+
+    /// Set subresource integrity hashes of docs page assets, e.g.
+    /// `sha384-...`, browser refuses assets that do not match.
+    pub fn ui_integrity(mut self, css: &str, js: &str) -> Self {
+        self.assets.integrity = Some((css.to_owned(), js.to_owned()));
+        self
+    }
+
+    /// OpenAPI document of application that handles `req`
+    pub fn document(&self, req: &HttpRequest) -> Value {
+        let mut paths = Map::new();
+        for item in req.resource_map().operations() {
+            let method = match item.method {
+                Method::GET => "get",
+                Method::PUT => "put",
+                Method::POST => "post",
+                Method::DELETE => "delete",
+                Method::OPTIONS => "options",
+                Method::HEAD => "head",
+                Method::PATCH => "patch",
+                Method::TRACE => "trace",
+                _ => continue,
+            };
+            let (path, params) = path_template(&item.path);
+            let ops = paths
+                .entry(path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(ref mut ops) = ops {
+                ops.insert(method.to_owned(), item.operation.to_json(&params));
+            }
+        }
+
+        let mut info = Map::new();
+        info.insert("title".to_owned(), self.title.clone().into());
+        info.insert("version".to_owned(), self.version.clone().into());
+        if let Some(ref description) = self.description {
+            info.insert("description".to_owned(), description.clone().into());
+        }
+        json!({
+            "openapi": "3.0.3",
+            "info": info,
+            "paths": paths,
+        })
+    }
+
+    /// Docs page, `prefix` is path of scope that service is registered in
+    fn ui_page(&self, prefix: &str) -> String {
+        let title = escape(&self.title);
+        let (css, js) = (escape(&self.assets.css), escape(&self.assets.js));
+        let (css_attrs, js_attrs) = match self.assets.integrity {
+            Some((ref css, ref js)) => (
+                format!(" integrity=\"{}\" crossorigin=\"anonymous\"", escape(css)),
+                format!(" integrity=\"{}\" crossorigin=\"anonymous\"", escape(js)),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let url = serde_json::to_string(&format!("{}{}", prefix, self.path))
+            .unwrap_or_default()
+            .replace('<', "\\u003c");
+        format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+<link rel="stylesheet" href="{}"{}>
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="{}"{}></script>
+<script>
+window.onload = function() {{
+    SwaggerUIBundle({{ url: {}, dom_id: "#swagger-ui" }});
+}};
+</script>
+</body>
+</html>
+"##,
+            title, css, css_attrs, js, js_attrs, url
+        )
+    }
+}
+
+impl HttpServiceFactory for OpenApi {
+    fn register(self, config: &mut AppService) {
+        let api = Rc::new(self);
+
+        if let Some(ref ui) = api.ui {
+            let api = api.clone();
+            Resource::new(ui.as_str())
+                .route(Route::new().guard(guard::Get()).undocumented().to(
+                    move |req: HttpRequest| {
+                        let ui = api.ui.as_ref().unwrap();
+                        let path = req.path();
+                        let prefix = if path.ends_with(ui.as_str()) {
+                            &path[..path.len() - ui.len()]
+                        } else {
+                            ""
+                        };
+                        ready(
+                            HttpResponse::Ok()
+                                .content_type("text/html; charset=utf-8")
+                                .body(api.ui_page(prefix)),
+                        )
+                    },
+                ))
+                .register(config);
+        }
+
+        let path = api.path.clone();
+        Resource::new(path.as_str())
+            .route(Route::new().guard(guard::Get()).undocumented().to(
+                move |req: HttpRequest| {
+                    ready(HttpResponse::Ok().json(api.document(&req)))
+                },
+            ))
+            .register(config);
+    }
+}
+
+/// Escape text for html element content and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// OpenAPI path template of resource pattern and names of its dynamic
+/// segments, regex of segments is removed.
+fn path_template(pattern: &str) -> (String, Vec<String>) {
+    let mut path = String::with_capacity(pattern.len());
+    let mut params = Vec::new();
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '{' {
+            path.push(ch);
+            continue;
+        }
+        let mut name = String::new();
+        let mut in_regex = false;
+        let mut depth = 1;
+        for ch in &mut chars {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                ':' if depth == 1 => in_regex = true,
+                _ => (),
+            }
+            if !in_regex {
+                name.push(ch);
+            }
+        }
+        // tail segment
+        if chars.peek() == Some(&'*') {
+            chars.next();
+        }
+        path.push('{');
+        path.push_str(&name);
+        path.push('}');
+        params.push(name);
+    }
+    (path, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::http::header;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[derive(Deserialize)]
+    struct Info {
+        _name: String,
+    }
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(
+            path_template("/users/{id}/posts/{post:\\d{1,3}}"),
+            (
+                "/users/{id}/posts/{post}".to_owned(),
+                vec!["id".to_owned(), "post".to_owned()]
+            )
+        );
+        assert_eq!(
+            path_template("/files/{tail}*"),
+            ("/files/{tail}".to_owned(), vec!["tail".to_owned()])
+        );
+        assert_eq!(
+            path_template("/index.html"),
+            ("/index.html".to_owned(), vec![])
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_document() {
+        // scope with dynamic prefix matches document path too,
+        // so document is registered first
+        let mut srv = init_service(
+            App::new()
+                .service(OpenApi::new("Test", "1.0").ui("/docs"))
+                .service(
+                    web::scope("/{tenant}").service(
+                        web::resource("/users/{id:\\d+}")
+                            .route(
+                                web::get()
+                                    .operation(
+                                        Operation::new()
+                                            .summary("Get user")
+                                            .tag("users")
+                                            .parameter(
+                                                Parameter::path("id")
+                                                    .schema(json!({"type": "integer"})),
+                                            )
+                                            .response(StatusCode::OK, "User"),
+                                    )
+                                    .to(|| async { HttpResponse::Ok().finish() }),
+                            )
+                            .route(web::post().to(
+                                |_: web::Header<header::ContentType>,
+                                 _: web::Json<Info>| {
+                                    async { HttpResponse::Ok().finish() }
+                                },
+                            )),
+                    ),
+                )
+                .route("/any", web::to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/openapi.json").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let doc: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(doc["openapi"], "3.0.3");
+        assert_eq!(doc["info"], json!({"title": "Test", "version": "1.0"}));
+
+        // routes without method guards are described for common methods
+        let paths = doc["paths"].as_object().unwrap();
+        assert_eq!(paths.len(), 2);
+        let any: Vec<_> = doc["paths"]["/any"].as_object().unwrap().keys().collect();
+        assert_eq!(any, ["delete", "get", "patch", "post", "put"]);
+
+        let ops = &doc["paths"]["/{tenant}/users/{id}"];
+        assert_eq!(ops["get"]["summary"], "Get user");
+        assert_eq!(ops["get"]["tags"], json!(["users"]));
+        assert_eq!(
+            ops["get"]["parameters"],
+            json!([
+                {"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}},
+                {"name": "tenant", "in": "path", "required": true, "schema": {"type": "string"}},
+            ])
+        );
+        assert_eq!(
+            ops["get"]["responses"],
+            json!({"200": {"description": "User"}})
+        );
+
+        // parameters and request body are derived from extractors
+        let names: Vec<_> = ops["post"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                format!(
+                    "{}:{}",
+                    p["in"].as_str().unwrap(),
+                    p["name"].as_str().unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(names, ["header:content-type", "path:tenant", "path:id"]);
+        assert_eq!(
+            ops["post"]["requestBody"],
+            json!({"content": {"application/json": {"schema": {}}}})
+        );
+        assert_eq!(
+            ops["post"]["responses"],
+            json!({"default": {"description": "Default response"}})
+        );
+
+        let req = TestRequest::with_uri("/docs").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        let body = str::from_utf8(&body).unwrap();
+        assert!(body.contains("url: \"/openapi.json\""));
+        assert!(body.contains("swagger-ui-dist@3.25.0/swagger-ui-bundle.js"));
+    }
+
+    #[actix_rt::test]
+    async fn test_document_in_scope() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/users")
+                        .route(web::get().to(|| async { HttpResponse::Ok().finish() }))
+                        .route(web::to(|| async { HttpResponse::Ok().finish() })),
+                )
+                .service(
+                    web::scope("/api").service(
+                        OpenApi::new("Test", "1.0")
+                            .ui("/docs")
+                            .ui_assets("/static/ui.css", "/static/ui.js")
+                            .ui_integrity("sha384-css", "sha384-js"),
+                    ),
+                ),
+        )
+        .await;
+
+        // document describes routes of whole application
+        let req = TestRequest::with_uri("/api/openapi.json").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let doc: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        let ops: Vec<_> = doc["paths"]["/users"].as_object().unwrap().keys().collect();
+        assert_eq!(ops, ["delete", "get", "patch", "post", "put"]);
+
+        let req = TestRequest::with_uri("/api/docs").to_request();
+        let resp = call_service(&mut srv, req).await;
+        let body = read_body(resp).await;
+        let body = str::from_utf8(&body).unwrap();
+        assert!(body.contains("url: \"/api/openapi.json\""));
+        assert!(body.contains(
+            "<link rel=\"stylesheet\" href=\"/static/ui.css\" \
+             integrity=\"sha384-css\" crossorigin=\"anonymous\">"
+        ));
+        assert!(body.contains(
+            "<script src=\"/static/ui.js\" integrity=\"sha384-js\" \
+             crossorigin=\"anonymous\"></script>"
+        ));
+    }
+}
@@ -1,17 +1,26 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::{Ref, RefMut};
 use std::rc::Rc;
+use std::time::SystemTime;
 use std::{fmt, net};
 
+use actix_http::h1::EarlyHints;
+use actix_http::h2::ServerPush;
+use actix_http::http::header::{self, EntityTag, HttpDate};
 use actix_http::http::{HeaderMap, Method, Uri, Version};
-use actix_http::{Error, Extensions, HttpMessage, Message, Payload, RequestHead};
+use actix_http::{
+    ConnectionExtensions, Error, Extensions, HttpMessage, Message, ObjectPool, Payload,
+    PoolConfig, RequestHead, Response,
+};
 use actix_router::{Path, Url};
 use futures::future::{ok, Ready};
+use serde::Serialize;
 
 use crate::config::AppConfig;
 use crate::error::UrlGenerationError;
 use crate::extract::FromRequest;
 use crate::info::ConnectionInfo;
-use crate::rmap::ResourceMap;
+use crate::rmap::{ResourceMap, UrlParams};
+use crate::types::Cancellation;
 
 #[derive(Clone)]
 /// An HTTP Request
@@ -21,7 +30,7 @@ pub(crate) struct HttpRequestInner {
     pub(crate) head: Message<RequestHead>,
     pub(crate) path: Path<Url>,
     pub(crate) payload: Payload,
-    pub(crate) app_data: Rc<Extensions>,
+    pub(crate) app_data: Vec<Rc<Extensions>>,
     rmap: Rc<ResourceMap>,
     config: AppConfig,
     pool: &'static HttpRequestPool,
@@ -44,10 +53,59 @@ impl HttpRequest {
             payload,
             rmap,
             config,
-            app_data,
+            app_data: vec![app_data],
             pool,
         }))
     }
+
+    /// Take parts of request, which allow to create similar request if
+    /// original one is lost, i.e. on panic.
+    pub(crate) fn snapshot(&self) -> RequestSnapshot {
+        let head = self.head();
+        RequestSnapshot {
+            method: head.method.clone(),
+            uri: head.uri.clone(),
+            version: head.version,
+            peer_addr: head.peer_addr,
+            app_data: self.0.app_data.clone(),
+            rmap: self.0.rmap.clone(),
+            config: self.0.config.clone(),
+            pool: self.0.pool,
+        }
+    }
+}
+
+/// Parts of request taken with `HttpRequest::snapshot()`
+pub(crate) struct RequestSnapshot {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    peer_addr: Option<net::SocketAddr>,
+    app_data: Vec<Rc<Extensions>>,
+    rmap: Rc<ResourceMap>,
+    config: AppConfig,
+    pool: &'static HttpRequestPool,
+}
+
+impl RequestSnapshot {
+    /// Create request without headers, payload and match info
+    pub(crate) fn into_request(self) -> HttpRequest {
+        let mut head = Message::<RequestHead>::new();
+        head.method = self.method;
+        head.uri = self.uri;
+        head.version = self.version;
+        head.peer_addr = self.peer_addr;
+
+        HttpRequest(Rc::new(HttpRequestInner {
+            path: Path::new(Url::new(head.uri.clone())),
+            head,
+            payload: Payload::None,
+            app_data: self.app_data,
+            rmap: self.rmap,
+            config: self.config,
+            pool: self.pool,
+        }))
+    }
 }
 
 impl HttpRequest {
@@ -165,6 +223,96 @@ impl HttpRequest {
         self.0.rmap.url_for(&self, name, elements)
     }
 
+    /// Generate url for named resource with query parameters
+    ///
+    /// Path segments are filled the same way as with
+    /// `HttpRequest::url_for()`, `query` is serialized with `serde_urlencoded`
+    /// and appended to query string of the resource pattern, if any. This is
+    /// useful for redirects to external resources.
+    ///
+    /// ```rust
+    /// # use actix_web::{web, App, HttpRequest, HttpResponse};
+    /// use serde_derive::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: String,
+    ///     page: u32,
+    /// }
+    ///
+    /// fn index(req: HttpRequest) -> HttpResponse {
+    ///     let search = Search { q: "coffee & tea".to_string(), page: 2 };
+    ///     let url = req.url_for_with_query("maps", &["eu"], &search).unwrap();
+    ///     assert_eq!(
+    ///         url.as_str(),
+    ///         "https://maps.example.com/eu/search?src=app&q=coffee+%26+tea&page=2"
+    ///     );
+    ///     HttpResponse::Found().header("location", url.as_str()).finish()
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .route("/search", web::get().to(index))
+    ///         .external_resource("maps", "https://maps.example.com/{region}/search?src=app");
+    /// }
+    /// ```
+    pub fn url_for_with_query<U, I, Q>(
+        &self,
+        name: &str,
+        elements: U,
+        query: &Q,
+    ) -> Result<url::Url, UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        Q: Serialize,
+    {
+        let mut url = self.url_for(name, elements)?;
+        let query = serde_urlencoded::to_string(query)?;
+        if !query.is_empty() {
+            let query = match url.query() {
+                Some(q) if !q.is_empty() => format!("{}&{}", q, query),
+                _ => query,
+            };
+            url.set_query(Some(&query));
+        }
+        Ok(url)
+    }
+
+    /// Generate url for resource with typed parameters
+    ///
+    /// Resource name and values of segments are taken from `params`, see
+    /// [`UrlParams`](trait.UrlParams.html).
+    ///
+    /// ```rust
+    /// # use actix_web::{web, App, HttpRequest, HttpResponse, UrlParams};
+    /// #
+    /// #[derive(UrlParams)]
+    /// #[url(name = "foo", path = "/test/{one}/{two}")]
+    /// struct Foo {
+    ///     one: u32,
+    ///     two: String,
+    /// }
+    ///
+    /// fn index(req: HttpRequest) -> HttpResponse {
+    ///     let url = req.url_for_params(&Foo { one: 1, two: "2".to_string() });
+    ///     HttpResponse::Ok().into()
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(web::resource_for::<Foo>()
+    ///              .route(web::get().to(|| HttpResponse::Ok()))
+    ///         );
+    /// }
+    /// ```
+    pub fn url_for_params<P: UrlParams>(
+        &self,
+        params: &P,
+    ) -> Result<url::Url, UrlGenerationError> {
+        self.url_for(P::NAME, params.elements())
+    }
+
     /// Generate url for named resource
     ///
     /// This method is similar to `HttpRequest::url_for()` but it can be used
@@ -180,6 +328,15 @@ impl HttpRequest {
         &self.0.rmap
     }
 
+    /// Pattern of resource that matches request's path, e.g. `/users/{id}`.
+    ///
+    /// Returns `None` if no resource matches the path. Pattern is useful for
+    /// metrics and logging, where raw path would produce too many
+    /// distinct values.
+    pub fn match_pattern(&self) -> Option<String> {
+        self.0.rmap.match_pattern(self.path())
+    }
+
     /// Peer socket address
     ///
     /// Peer address is actual socket address, if proxy is used in front of
@@ -191,6 +348,53 @@ impl HttpRequest {
         self.head().peer_addr
     }
 
+    /// Get connection level data inserted by `HttpServer::on_connect()` callback.
+    ///
+    /// This method panics if request's extensions container is already
+    /// mutably borrowed.
+    pub fn conn_data<T: Clone + 'static>(&self) -> Option<T> {
+        self.extensions()
+            .get::<ConnectionExtensions>()
+            .and_then(|ext| ext.get::<T>().cloned())
+    }
+
+    /// Get handle for sending `103 Early Hints` responses.
+    ///
+    /// Handle is available for HTTP/1.1 requests if early hints are
+    /// enabled with `HttpServer::early_hints()`.
+    pub fn early_hints(&self) -> Option<EarlyHints> {
+        self.extensions().get::<EarlyHints>().cloned()
+    }
+
+    /// Get handle for HTTP/2 server push.
+    ///
+    /// Handle is available for HTTP/2 requests until client refuses
+    /// pushed resources.
+    ///
+    /// ```rust
+    /// use actix_web::{HttpRequest, HttpResponse};
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     if let Some(push) = req.server_push() {
+    ///         push.push("/static/style.css");
+    ///     }
+    ///     HttpResponse::Ok()
+    ///         .content_type("text/html")
+    ///         .body(r#"<link rel="stylesheet" href="/static/style.css">"#)
+    /// }
+    /// ```
+    pub fn server_push(&self) -> Option<ServerPush> {
+        self.extensions().get::<ServerPush>().cloned()
+    }
+
+    /// Get cancellation handle of request.
+    ///
+    /// Handle resolves once client disconnects, request times out or server
+    /// shutdown begins, see [`Cancellation`](web/struct.Cancellation.html).
+    pub fn cancellation(&self) -> Cancellation {
+        Cancellation::get(self.head(), self.app_config())
+    }
+
     /// Get *ConnectionInfo* for the current request.
     ///
     /// This method panics if request's extensions container is already
@@ -209,18 +413,140 @@ impl HttpRequest {
     /// Get an application data object stored with `App::data` or `App::app_data`
     /// methods during application configuration.
     ///
+    /// Data stored on enclosing scopes and on the matched resource takes
+    /// precedence over application data of the same type.
+    ///
     /// If `App::data` was used to store object, use `Data<T>`:
     ///
     /// ```rust,ignore
     /// let opt_t = req.app_data::<Data<T>>();
     /// ```
     pub fn app_data<T: 'static>(&self) -> Option<&T> {
-        if let Some(st) = self.0.app_data.get::<T>() {
-            Some(&st)
+        self.0
+            .app_data
+            .iter()
+            .rev()
+            .find_map(|container| container.get::<T>())
+    }
+
+    /// Check if client's cached copy of resource is fresh.
+    ///
+    /// Cached copy is fresh if `If-None-Match` header matches `etag`
+    /// with weak comparison, or if there is no `If-None-Match` header and
+    /// resource was not modified after `If-Modified-Since` date.
+    pub fn is_fresh(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<&HttpDate>,
+    ) -> bool {
+        if self.headers().contains_key(&header::IF_NONE_MATCH) {
+            match self.get_header::<header::IfNoneMatch>() {
+                Some(header::IfNoneMatch::Any) => true,
+                Some(header::IfNoneMatch::Items(ref items)) => etag
+                    .map(|etag| items.iter().any(|item| item.weak_eq(etag)))
+                    .unwrap_or(false),
+                None => false,
+            }
+        } else if let (Some(modified), Some(header::IfModifiedSince(since))) =
+            (last_modified, self.get_header())
+        {
+            SystemTime::from(*modified) <= SystemTime::from(since)
         } else {
-            None
+            false
         }
     }
+
+    /// Check if `If-Match` or `If-Unmodified-Since` precondition of request
+    /// fails for resource with `etag` and `last_modified` date.
+    ///
+    /// Unlike `If-None-Match`, `If-Match` header is matched with strong
+    /// comparison.
+    pub fn is_precondition_failed(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<&HttpDate>,
+    ) -> bool {
+        if self.headers().contains_key(&header::IF_MATCH) {
+            match self.get_header::<header::IfMatch>() {
+                Some(header::IfMatch::Any) => false,
+                Some(header::IfMatch::Items(ref items)) => !etag
+                    .map(|etag| items.iter().any(|item| item.strong_eq(etag)))
+                    .unwrap_or(false),
+                None => true,
+            }
+        } else if let (Some(modified), Some(header::IfUnmodifiedSince(since))) =
+            (last_modified, self.get_header())
+        {
+            SystemTime::from(*modified) > SystemTime::from(since)
+        } else {
+            false
+        }
+    }
+
+    /// Evaluate conditional request headers against `ETag` and
+    /// `Last-Modified` headers of successful response.
+    ///
+    /// Returns `304 Not Modified` response for `GET` and `HEAD` requests if
+    /// client's cached copy is fresh, `412 Precondition Failed` response if
+    /// precondition fails, or original response otherwise. `304` response
+    /// keeps caching related headers of original response.
+    ///
+    /// ```rust
+    /// use actix_web::{http::header, HttpRequest, HttpResponse};
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     let body = "rendered page";
+    ///     let res = HttpResponse::Ok()
+    ///         .set(header::ETag(header::EntityTag::from_content(body.as_bytes())))
+    ///         .body(body);
+    ///     req.conditional(res)
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn conditional(&self, res: Response) -> Response {
+        if !res.status().is_success() {
+            return res;
+        }
+
+        let etag = res
+            .headers()
+            .get(&header::ETAG)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<EntityTag>().ok());
+        let last_modified = res
+            .headers()
+            .get(&header::LAST_MODIFIED)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<HttpDate>().ok());
+
+        if self.is_precondition_failed(etag.as_ref(), last_modified.as_ref()) {
+            return Response::PreconditionFailed().finish();
+        }
+        if !self.is_fresh(etag.as_ref(), last_modified.as_ref()) {
+            return res;
+        }
+        if self.method() != Method::GET && self.method() != Method::HEAD {
+            return Response::PreconditionFailed().finish();
+        }
+
+        let mut not_modified = Response::NotModified().finish();
+        for name in &[
+            header::CACHE_CONTROL,
+            header::CONTENT_LOCATION,
+            header::DATE,
+            header::ETAG,
+            header::EXPIRES,
+            header::LAST_MODIFIED,
+            header::VARY,
+        ] {
+            for value in res.headers().get_all(name) {
+                not_modified
+                    .headers_mut()
+                    .append(name.clone(), value.clone());
+            }
+        }
+        not_modified
+    }
 }
 
 impl HttpMessage for HttpRequest {
@@ -253,11 +579,9 @@ impl HttpMessage for HttpRequest {
 impl Drop for HttpRequest {
     fn drop(&mut self) {
         if Rc::strong_count(&self.0) == 1 {
-            let v = &mut self.0.pool.0.borrow_mut();
-            if v.len() < 128 {
-                self.extensions_mut().clear();
-                v.push(self.0.clone());
-            }
+            self.extensions_mut().clear();
+            let headers_capacity = self.0.head.headers.capacity();
+            self.0.pool.0.release(self.0.clone(), headers_capacity);
         }
     }
 }
@@ -317,31 +641,29 @@ impl fmt::Debug for HttpRequest {
 }
 
 /// Request's objects pool
-pub(crate) struct HttpRequestPool(RefCell<Vec<Rc<HttpRequestInner>>>);
+pub(crate) struct HttpRequestPool(ObjectPool<Rc<HttpRequestInner>>);
 
 impl HttpRequestPool {
-    pub(crate) fn create() -> &'static HttpRequestPool {
-        let pool = HttpRequestPool(RefCell::new(Vec::with_capacity(128)));
+    pub(crate) fn create(config: PoolConfig) -> &'static HttpRequestPool {
+        let pool = HttpRequestPool(ObjectPool::new(config));
         Box::leak(Box::new(pool))
     }
 
     /// Get message from the pool
     #[inline]
     pub(crate) fn get_request(&self) -> Option<HttpRequest> {
-        if let Some(inner) = self.0.borrow_mut().pop() {
-            Some(HttpRequest(inner))
-        } else {
-            None
-        }
+        self.0.get().map(HttpRequest)
     }
 
     pub(crate) fn clear(&self) {
-        self.0.borrow_mut().clear()
+        self.0.clear()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+
     use super::*;
     use crate::dev::{ResourceDef, ResourceMap};
     use crate::http::{header, StatusCode};
@@ -461,6 +783,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_url_for_with_query() {
+        #[derive(serde_derive::Serialize)]
+        struct Search<'a> {
+            q: &'a str,
+            page: Option<u32>,
+        }
+
+        let mut rdef = ResourceDef::new("https://maps.example.com/{region}/search");
+        *rdef.name_mut() = "maps".to_string();
+        let mut rdef2 = ResourceDef::new("https://example.com/search?src=app");
+        *rdef2.name_mut() = "search".to_string();
+
+        let mut rmap = ResourceMap::new(ResourceDef::new(""));
+        rmap.add(&mut rdef, None);
+        rmap.add(&mut rdef2, None);
+
+        let req = TestRequest::default().rmap(rmap).to_http_request();
+        let search = Search {
+            q: "coffee & tea",
+            page: Some(2),
+        };
+        let url = req.url_for_with_query("maps", &["eu"], &search).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://maps.example.com/eu/search?q=coffee+%26+tea&page=2"
+        );
+
+        let search = Search {
+            q: "a/b",
+            page: None,
+        };
+        let url = req.url_for_with_query("search", &[""; 0], &search).unwrap();
+        assert_eq!(url.as_str(), "https://example.com/search?src=app&q=a%2Fb");
+
+        let query: Vec<(&str, &str)> = Vec::new();
+        let url = req.url_for_with_query("maps", &["eu"], &query).unwrap();
+        assert_eq!(url.as_str(), "https://maps.example.com/eu/search");
+
+        match req.url_for_with_query("maps", &["eu"], &"text") {
+            Err(UrlGenerationError::QueryError(_)) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
     #[actix_rt::test]
     async fn test_data() {
         let mut srv = init_service(App::new().app_data(10usize).service(
@@ -494,6 +861,33 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[actix_rt::test]
+    async fn test_match_pattern() {
+        let mut srv = init_service(
+            App::new()
+                .service(web::scope("/user").route(
+                    "/{id}",
+                    web::get().to(|req: HttpRequest| {
+                        assert_eq!(req.match_pattern(), Some("/user/{id}".to_owned()));
+                        HttpResponse::Ok()
+                    }),
+                ))
+                .default_service(web::to(|req: HttpRequest| {
+                    assert!(req.match_pattern().is_none());
+                    HttpResponse::NotFound()
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/user/22").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[actix_rt::test]
     async fn test_extensions_dropped() {
         struct Tracker {
@@ -528,4 +922,110 @@ mod tests {
 
         assert!(tracker.borrow().dropped);
     }
+
+    #[actix_rt::test]
+    async fn test_conditional() {
+        let etag = header::EntityTag::from_content(b"body");
+        let modified = header::HttpDate::from(
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000),
+        );
+        let later = header::HttpDate::from(
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(2_000_000),
+        );
+        let response = || {
+            HttpResponse::Ok()
+                .set(header::ETag(etag.clone()))
+                .set(header::LastModified(modified))
+                .header(header::CACHE_CONTROL, "max-age=60")
+                .body("body")
+        };
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.conditional(response()).status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, format!("W/\"x\", {}", etag))
+            .to_http_request();
+        let res = req.conditional(response());
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), &etag.to_string());
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "max-age=60"
+        );
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .set(header::IfModifiedSince(later))
+            .to_http_request();
+        assert_eq!(req.conditional(response()).status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .set(header::IfModifiedSince(later))
+            .to_http_request();
+        assert_eq!(
+            req.conditional(response()).status(),
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = TestRequest::post()
+            .header(header::IF_NONE_MATCH, "*")
+            .to_http_request();
+        assert_eq!(
+            req.conditional(response()).status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        let req = TestRequest::put()
+            .header(header::IF_MATCH, "\"other\"")
+            .to_http_request();
+        assert_eq!(
+            req.conditional(response()).status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        let req = TestRequest::put()
+            .header(header::IF_MATCH, etag.to_string())
+            .to_http_request();
+        assert_eq!(req.conditional(response()).status(), StatusCode::OK);
+
+        let req = TestRequest::put()
+            .set(header::IfUnmodifiedSince(header::HttpDate::from(
+                SystemTime::UNIX_EPOCH,
+            )))
+            .to_http_request();
+        assert_eq!(
+            req.conditional(response()).status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+    }
+
+    #[test]
+    fn test_request_pool() {
+        let stats = actix_http::PoolStats::new();
+        let pool = HttpRequestPool::create(PoolConfig::new(1).stats(stats.clone()));
+        let request = || {
+            HttpRequest::new(
+                Path::new(Url::new(Uri::default())),
+                Message::new(),
+                Payload::None,
+                Rc::new(ResourceMap::new(ResourceDef::new(""))),
+                AppConfig::default(),
+                Rc::new(Extensions::new()),
+                pool,
+            )
+        };
+
+        let (req, req2) = (request(), request());
+        drop(req);
+        drop(req2);
+        assert_eq!(stats.discarded(), 1);
+
+        let req = pool.get_request();
+        assert!(req.is_some());
+        assert!(pool.get_request().is_none());
+        assert_eq!(stats.hits(), 1);
+        assert_eq!(stats.misses(), 1);
+    }
 }
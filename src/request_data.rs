@@ -0,0 +1,135 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_http::error::{Error, ErrorInternalServerError};
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+
+/// Request-local data.
+///
+/// Request data is an arbitrary typed value attached to a single request,
+/// usually by middleware, e.g. authenticated user or tenant id.
+/// Data is stored in request extensions with
+/// `ServiceRequest::insert_req_data()` method (or `extensions_mut()`)
+/// and could be accessed by using `ReqData<T>` extractor, which
+/// clones stored value.
+///
+/// If request data is not set for a request, using `ReqData<T>` extractor
+/// would cause *Internal Server Error* response.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+/// use actix_web::dev::Service;
+///
+/// #[derive(Clone)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn index(user: web::ReqData<User>) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("Hello, {}!", user.name))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap_fn(|req, srv| {
+///             req.insert_req_data(User { name: "admin".to_string() });
+///             srv.call(req)
+///         })
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReqData<T>(T);
+
+impl<T> ReqData<T> {
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ReqData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ReqData<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Clone + 'static> FromRequest for ReqData<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(data) = req.extensions().get::<T>() {
+            ok(ReqData(data.clone()))
+        } else {
+            log::debug!(
+                "Failed to construct Request-level Data extractor. \
+                 Request path: {:?}",
+                req.path()
+            );
+            err(ErrorInternalServerError(
+                "Request data is not configured, to configure use \
+                 ServiceRequest::insert_req_data()",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::Service;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct User(String);
+
+    #[actix_rt::test]
+    async fn test_req_data_extractor() {
+        let mut srv = init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    req.insert_req_data(User("admin".to_string()));
+                    srv.call(req)
+                })
+                .route(
+                    "/",
+                    web::get().to(|user: ReqData<User>| {
+                        assert_eq!(user.into_inner(), User("admin".to_string()));
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_req_data_missing() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(ReqData::<User>::from_request(&req, &mut pl).await.is_err());
+
+        req.extensions_mut().insert(User("guest".to_string()));
+        let user = ReqData::<User>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(*user, User("guest".to_string()));
+    }
+}
@@ -5,7 +5,8 @@ use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
-use actix_http::{Error, Extensions, Response};
+use actix_http::http::{header, HeaderValue, Method};
+use actix_http::{Error, Extensions, RequestHead, Response};
 use actix_router::IntoPattern;
 use actix_service::boxed::{self, BoxService, BoxServiceFactory};
 use actix_service::{
@@ -46,6 +47,11 @@ type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Err
 /// ```
 ///
 /// If no matching route could be found, *405* response code get returned.
+/// Requests with `OPTIONS` method get *200 OK* response with `Allow` header,
+/// unless resource has a route for `OPTIONS` method or a route without
+/// method guard. Requests with `HEAD`
+/// method are handled by `GET` route, unless resource has a route for `HEAD`
+/// method.
 /// Default behavior could be overriden with `default_resource()` method.
 pub struct Resource<T = ResourceEndpoint> {
     endpoint: T,
@@ -54,6 +60,9 @@ pub struct Resource<T = ResourceEndpoint> {
     routes: Vec<Route>,
     data: Option<Extensions>,
     guards: Vec<Box<dyn Guard>>,
+    matchers: Vec<(String, Box<dyn Fn(&str) -> bool>)>,
+    method_not_allowed: bool,
+    priority: i32,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     factory_ref: Rc<RefCell<Option<ResourceFactory>>>,
 }
@@ -69,6 +78,9 @@ impl Resource {
             endpoint: ResourceEndpoint::new(fref.clone()),
             factory_ref: fref,
             guards: Vec::new(),
+            matchers: Vec::new(),
+            method_not_allowed: true,
+            priority: 0,
             data: None,
             default: Rc::new(RefCell::new(None)),
         }
@@ -121,6 +133,86 @@ where
         self
     }
 
+    /// Add custom matcher for a variable path segment.
+    ///
+    /// Resource matches only if matcher returns `true` for the value of
+    /// the segment, otherwise router tries next resource. Simple
+    /// constraints could be expressed with a regex in the path pattern,
+    /// i.e. `/users/{id:\d+}`.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(
+    ///             web::resource("/users/{id}")
+    ///                 .param_matcher("id", |id| id.parse::<u32>().is_ok())
+    ///                 .to(|| HttpResponse::Ok()),
+    ///         )
+    ///         .service(
+    ///             web::resource("/users/{name}").to(|| HttpResponse::Found()),
+    ///         );
+    /// }
+    /// ```
+    pub fn param_matcher<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.matchers.push((name.to_string(), Box::new(f)));
+        self
+    }
+
+    /// Respond with *405 Method Not Allowed* if request method does not
+    /// match any route, enabled by default.
+    ///
+    /// `Allow` header of the response lists methods of method guards of
+    /// resource routes and `OPTIONS`. Methods accepted by other guards are
+    /// not known, so header is sent only if every route has a method guard.
+    /// If disabled, router skips the resource for requests with other
+    /// methods, so next resource or default service handles them.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(
+    ///             web::resource("/index.html")
+    ///                 .method_not_allowed(false)
+    ///                 .route(web::get().to(|| HttpResponse::Ok())),
+    ///         )
+    ///         .default_service(web::to(|| HttpResponse::NotFound()));
+    /// }
+    /// ```
+    pub fn method_not_allowed(mut self, enabled: bool) -> Self {
+        self.method_not_allowed = enabled;
+        self
+    }
+
+    /// Set matching priority of the resource, default is 0.
+    ///
+    /// Router checks resources and scopes with higher priority first,
+    /// services of the same priority are checked in registration order.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(
+    ///             web::resource("/{tail:.*}")
+    ///                 .priority(-1)
+    ///                 .to(|| HttpResponse::NotFound()),
+    ///         )
+    ///         .service(web::resource("/index.html").to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
     pub(crate) fn add_guards(mut self, guards: Vec<Box<dyn Guard>>) -> Self {
         self.guards.extend(guards);
         self
@@ -271,6 +363,9 @@ where
             rdef: self.rdef,
             name: self.name,
             guards: self.guards,
+            matchers: self.matchers,
+            method_not_allowed: self.method_not_allowed,
+            priority: self.priority,
             routes: self.routes,
             default: self.default,
             data: self.data,
@@ -333,6 +428,9 @@ where
             rdef: self.rdef,
             name: self.name,
             guards: self.guards,
+            matchers: self.matchers,
+            method_not_allowed: self.method_not_allowed,
+            priority: self.priority,
             routes: self.routes,
             default: self.default,
             data: self.data,
@@ -376,16 +474,55 @@ where
         > + 'static,
 {
     fn register(mut self, config: &mut AppService) {
+        let patterns = if config.is_root() || !self.rdef.is_empty() {
+            insert_slash(self.rdef.clone())
+        } else {
+            self.rdef.clone()
+        };
+        // route without method guard is described for common methods
+        // that are not handled by routes with method guards
+        let guarded = allowed_methods(&self.routes);
+        for route in self.routes.iter().filter(|r| r.is_documented()) {
+            let methods = if route.methods().is_empty() {
+                crate::openapi::ANY_METHODS
+                    .iter()
+                    .filter(|m| !guarded.contains(*m))
+                    .cloned()
+                    .collect()
+            } else {
+                route.methods().to_vec()
+            };
+            for method in methods {
+                for path in &patterns {
+                    config.register_operation(
+                        path.clone(),
+                        method.clone(),
+                        route.operation_info(),
+                    );
+                }
+            }
+        }
+        let mut rdef = ResourceDef::new(patterns);
+        if !self.method_not_allowed {
+            let methods = allowed_methods(&self.routes);
+            if !methods.is_empty() && self.routes.iter().all(|r| !r.methods().is_empty())
+            {
+                self.guards.push(Box::new(move |head: &RequestHead| {
+                    methods.contains(&head.method)
+                }));
+            }
+        }
+        if !self.matchers.is_empty() {
+            self.guards.push(Box::new(ParamGuard {
+                rdef: rdef.clone(),
+                matchers: std::mem::replace(&mut self.matchers, Vec::new()),
+            }));
+        }
         let guards = if self.guards.is_empty() {
             None
         } else {
             Some(std::mem::replace(&mut self.guards, Vec::new()))
         };
-        let mut rdef = if config.is_root() || !self.rdef.is_empty() {
-            ResourceDef::new(insert_slash(self.rdef.clone()))
-        } else {
-            ResourceDef::new(self.rdef.clone())
-        };
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
         }
@@ -393,7 +530,8 @@ where
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
         }
-        config.register_service(rdef, guards, self, None)
+        let priority = self.priority;
+        config.register_service_with_priority(rdef, guards, self, None, priority)
     }
 }
 
@@ -408,20 +546,76 @@ where
     >,
 {
     fn into_factory(self) -> T {
+        // route without method guard could accept any method
+        let methods = allowed_methods(&self.routes);
+        let allow = if methods.is_empty()
+            || self.routes.iter().any(|r| r.methods().is_empty())
+        {
+            None
+        } else {
+            let methods: Vec<_> = methods.iter().map(|m| m.as_str()).collect();
+            HeaderValue::from_str(&methods.join(", ")).ok()
+        };
+
         *self.factory_ref.borrow_mut() = Some(ResourceFactory {
             routes: self.routes,
             data: self.data.map(Rc::new),
             default: self.default,
+            allow,
+            method_not_allowed: self.method_not_allowed,
         });
 
         self.endpoint
     }
 }
 
+/// Unique methods of method guards of routes, `HEAD` is allowed if `GET`
+/// is, `OPTIONS` is always allowed
+fn allowed_methods(routes: &[Route]) -> Vec<Method> {
+    let mut methods: Vec<Method> = Vec::new();
+    for method in routes.iter().flat_map(|r| r.methods()) {
+        if !methods.contains(method) {
+            methods.push(method.clone());
+        }
+    }
+    if methods.contains(&Method::GET) && !methods.contains(&Method::HEAD) {
+        methods.push(Method::HEAD);
+    }
+    if !methods.is_empty() && !methods.contains(&Method::OPTIONS) {
+        methods.push(Method::OPTIONS);
+    }
+    methods
+}
+
+/// Checks values of matched path segments
+struct ParamGuard {
+    rdef: ResourceDef,
+    matchers: Vec<(String, Box<dyn Fn(&str) -> bool>)>,
+}
+
+impl Guard for ParamGuard {
+    fn check(&self, _: &RequestHead) -> bool {
+        // segments are not available without service request
+        true
+    }
+
+    fn check_request(&self, req: &ServiceRequest) -> bool {
+        let mut path = req.match_info().clone();
+        if !self.rdef.match_path(&mut path) {
+            return false;
+        }
+        self.matchers
+            .iter()
+            .all(|(name, f)| path.get(name).map(f).unwrap_or(false))
+    }
+}
+
 pub struct ResourceFactory {
     routes: Vec<Route>,
     data: Option<Rc<Extensions>>,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
+    allow: Option<HeaderValue>,
+    method_not_allowed: bool,
 }
 
 impl ServiceFactory for ResourceFactory {
@@ -449,6 +643,8 @@ impl ServiceFactory for ResourceFactory {
             data: self.data.clone(),
             default: None,
             default_fut,
+            allow: self.allow.clone(),
+            method_not_allowed: self.method_not_allowed,
         }
     }
 }
@@ -463,6 +659,8 @@ pub struct CreateResourceService {
     data: Option<Rc<Extensions>>,
     default: Option<HttpService>,
     default_fut: Option<LocalBoxFuture<'static, Result<HttpService, ()>>>,
+    allow: Option<HeaderValue>,
+    method_not_allowed: bool,
 }
 
 impl Future for CreateResourceService {
@@ -506,6 +704,8 @@ impl Future for CreateResourceService {
                 routes,
                 data: self.data.clone(),
                 default: self.default.take(),
+                allow: self.allow.take(),
+                method_not_allowed: self.method_not_allowed,
             }))
         } else {
             Poll::Pending
@@ -517,6 +717,8 @@ pub struct ResourceService {
     routes: Vec<RouteService>,
     data: Option<Rc<Extensions>>,
     default: Option<HttpService>,
+    allow: Option<HeaderValue>,
+    method_not_allowed: bool,
 }
 
 impl Service for ResourceService {
@@ -536,19 +738,44 @@ impl Service for ResourceService {
         for route in self.routes.iter_mut() {
             if route.check(&mut req) {
                 if let Some(ref data) = self.data {
-                    req.set_data_container(data.clone());
+                    req.add_data_container(data.clone());
+                }
+                return Either::Right(route.call(req));
+            }
+        }
+        // HEAD request is handled by GET route, payload is not sent by
+        // protocol dispatcher
+        if *req.method() == Method::HEAD {
+            req.head_mut().method = Method::GET;
+            let route = self.routes.iter_mut().find(|route| route.check(&mut req));
+            req.head_mut().method = Method::HEAD;
+
+            if let Some(route) = route {
+                if let Some(ref data) = self.data {
+                    req.add_data_container(data.clone());
                 }
                 return Either::Right(route.call(req));
             }
         }
+        if *req.method() == Method::OPTIONS {
+            if let Some(ref allow) = self.allow {
+                let res = Response::Ok().header(header::ALLOW, allow.clone()).finish();
+                let req = req.into_parts().0;
+                return Either::Left(ok(ServiceResponse::new(req, res)));
+            }
+        }
         if let Some(ref mut default) = self.default {
             Either::Right(default.call(req))
+        } else if self.method_not_allowed {
+            let mut res = Response::MethodNotAllowed();
+            if let Some(ref allow) = self.allow {
+                res.header(header::ALLOW, allow.clone());
+            }
+            let req = req.into_parts().0;
+            Either::Left(ok(ServiceResponse::new(req, res.finish())))
         } else {
             let req = req.into_parts().0;
-            Either::Left(ok(ServiceResponse::new(
-                req,
-                Response::MethodNotAllowed().finish(),
-            )))
+            Either::Left(ok(ServiceResponse::new(req, Response::NotFound().finish())))
         }
     }
 }
@@ -722,6 +949,114 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[actix_rt::test]
+    async fn test_method_not_allowed() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/test")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(web::post().to(|| HttpResponse::Ok()))
+                        .route(web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::resource("/test2")
+                        .method_not_allowed(false)
+                        .route(web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::resource("/test3")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(
+                            web::route()
+                                .guard(guard::Header("x-test", "1"))
+                                .to(|| HttpResponse::Ok()),
+                        ),
+                )
+                .default_service(|r: ServiceRequest| {
+                    ok(r.into_response(HttpResponse::BadRequest()))
+                }),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, POST, HEAD, OPTIONS")
+        );
+
+        let req = TestRequest::with_uri("/test2")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::with_uri("/test2")
+            .method(Method::HEAD)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // methods of route without method guard are not known
+        let req = TestRequest::with_uri("/test3")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(resp.headers().get(header::ALLOW).is_none());
+
+        let req = TestRequest::with_uri("/test3")
+            .method(Method::PUT)
+            .header("x-test", "1")
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test2").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_options() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/test")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(web::delete().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::resource("/test2")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(
+                            web::method(Method::OPTIONS).to(|| HttpResponse::Created()),
+                        ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, DELETE, HEAD, OPTIONS")
+        );
+
+        let req = TestRequest::with_uri("/test2")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
     #[actix_rt::test]
     async fn test_resource_guards() {
         let mut srv = init_service(
@@ -763,6 +1098,44 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
     }
 
+    #[actix_rt::test]
+    async fn test_param_matcher() {
+        let mut srv = init_service(
+            App::new()
+                .service(web::resource("/user/{id:\\d+}").to(|| HttpResponse::Ok()))
+                .service(
+                    web::scope("/app").service(
+                        web::resource("/{id}/{name}")
+                            .param_matcher("id", |id| id.parse::<u8>().is_ok())
+                            .param_matcher("name", |name| name.len() < 5)
+                            .to(|| HttpResponse::Created()),
+                    ),
+                )
+                .service(web::resource("/{any}/{tail:.*}").to(|| HttpResponse::Found())),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/user/10").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/user/name").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+
+        let req = TestRequest::with_uri("/app/10/name").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_uri("/app/1000/name").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/app/10/long-name").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[actix_rt::test]
     async fn test_data() {
         let mut srv = init_service(
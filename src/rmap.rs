@@ -6,14 +6,37 @@ use fxhash::FxHashMap;
 use url::Url;
 
 use crate::error::UrlGenerationError;
+use crate::openapi::PathOperation;
 use crate::request::HttpRequest;
 
+/// Typed parameters of named resource.
+///
+/// Ties resource name and path pattern to a struct holding values of the
+/// pattern's dynamic segments, usually implemented with
+/// `#[derive(UrlParams)]`. Derive checks at compile time that every segment
+/// has a field and every field is a segment.
+///
+/// Resource is registered with `web::resource_for()` and urls are generated
+/// with `HttpRequest::url_for_params()`. Only segments of resource pattern are
+/// covered, resource should not be nested in scope with dynamic segments.
+pub trait UrlParams {
+    /// Name of resource
+    const NAME: &'static str;
+
+    /// Path pattern of resource
+    const PATH: &'static str;
+
+    /// Values of dynamic segments, in pattern order
+    fn elements(&self) -> Vec<String>;
+}
+
 #[derive(Clone, Debug)]
 pub struct ResourceMap {
     root: ResourceDef,
     parent: RefCell<Option<Rc<ResourceMap>>>,
     named: FxHashMap<String, ResourceDef>,
     patterns: Vec<(ResourceDef, Option<Rc<ResourceMap>>)>,
+    operations: Vec<PathOperation>,
 }
 
 impl ResourceMap {
@@ -23,6 +46,7 @@ impl ResourceMap {
             parent: RefCell::new(None),
             named: FxHashMap::default(),
             patterns: Vec::new(),
+            operations: Vec::new(),
         }
     }
 
@@ -35,6 +59,22 @@ impl ResourceMap {
         }
     }
 
+    /// Set operations of routes of application
+    pub(crate) fn set_operations(&mut self, operations: Vec<PathOperation>) {
+        self.operations = operations;
+    }
+
+    /// Operations of routes of application, see `openapi` module.
+    ///
+    /// Operations are kept by map of application, maps of scopes refer to
+    /// it through parent maps.
+    pub(crate) fn operations(&self) -> Vec<PathOperation> {
+        match *self.parent.borrow() {
+            Some(ref parent) => parent.operations(),
+            None => self.operations.clone(),
+        }
+    }
+
     pub(crate) fn finish(&self, current: Rc<ResourceMap>) {
         for (_, nested) in &self.patterns {
             if let Some(ref nested) = nested {
@@ -95,6 +135,25 @@ impl ResourceMap {
         false
     }
 
+    /// Pattern of resource that matches path, patterns of enclosing
+    /// scopes are included, e.g. `/users/{id}`.
+    pub fn match_pattern(&self, path: &str) -> Option<String> {
+        let path = if path.is_empty() { "/" } else { path };
+
+        for (pattern, rmap) in &self.patterns {
+            if let Some(ref rmap) = rmap {
+                if let Some(plen) = pattern.is_prefix_match(path) {
+                    return rmap
+                        .match_pattern(&path[plen..])
+                        .map(|tail| format!("{}{}", pattern.pattern(), tail));
+                }
+            } else if pattern.is_match(path) {
+                return Some(pattern.pattern().to_string());
+            }
+        }
+        None
+    }
+
     fn patterns_for<U, I>(
         &self,
         name: &str,
@@ -10,6 +10,7 @@ use futures::future::{ready, FutureExt, LocalBoxFuture};
 use crate::extract::FromRequest;
 use crate::guard::{self, Guard};
 use crate::handler::{Extract, Factory, Handler};
+use crate::openapi::Operation;
 use crate::responder::Responder;
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::HttpResponse;
@@ -42,6 +43,10 @@ type BoxedRouteNewService<Req, Res> = Box<
 pub struct Route {
     service: BoxedRouteNewService<ServiceRequest, ServiceResponse>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    methods: Vec<Method>,
+    operation: Option<Operation>,
+    describe: fn(&mut Operation),
+    documented: bool,
 }
 
 impl Route {
@@ -52,9 +57,35 @@ impl Route {
                 ready(HttpResponse::NotFound())
             })))),
             guards: Rc::new(Vec::new()),
+            methods: Vec::new(),
+            operation: None,
+            describe: |_| (),
+            documented: true,
         }
     }
 
+    /// Methods of method guards of the route
+    pub(crate) fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// Do not describe the route in OpenAPI document
+    pub(crate) fn undocumented(mut self) -> Self {
+        self.documented = false;
+        self
+    }
+
+    pub(crate) fn is_documented(&self) -> bool {
+        self.documented
+    }
+
+    /// OpenAPI operation of the route, completed by extractors of handler
+    pub(crate) fn operation_info(&self) -> Operation {
+        let mut operation = self.operation.clone().unwrap_or_default();
+        (self.describe)(&mut operation);
+        operation
+    }
+
     pub(crate) fn take_guards(&mut self) -> Vec<Box<dyn Guard>> {
         std::mem::replace(Rc::get_mut(&mut self.guards).unwrap(), Vec::new())
     }
@@ -153,7 +184,8 @@ impl Route {
     pub fn method(mut self, method: Method) -> Self {
         Rc::get_mut(&mut self.guards)
             .unwrap()
-            .push(Box::new(guard::Method(method)));
+            .push(Box::new(guard::Method(method.clone())));
+        self.methods.push(method);
         self
     }
 
@@ -175,6 +207,28 @@ impl Route {
         self
     }
 
+    /// Set OpenAPI operation of the route.
+    ///
+    /// Parameters and request body that are derived from extractors of
+    /// handler are added to operation, see `openapi` module.
+    ///
+    /// ```rust
+    /// # use actix_web::*;
+    /// use actix_web::openapi::Operation;
+    ///
+    /// # fn main() {
+    /// App::new().service(web::resource("/path").route(
+    ///     web::get()
+    ///         .operation(Operation::new().summary("Get path").tag("paths"))
+    ///         .to(|| HttpResponse::Ok()))
+    /// );
+    /// # }
+    /// ```
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
     /// Set handler function, use request extractors for parameters.
     ///
     /// ```rust
@@ -232,6 +286,7 @@ impl Route {
     {
         self.service =
             Box::new(RouteNewService::new(Extract::new(Handler::new(handler))));
+        self.describe = T::describe;
         self
     }
 }
@@ -419,7 +474,7 @@ mod tests {
             .method(Method::HEAD)
             .to_request();
         let resp = call_service(&mut srv, req).await;
-        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.status(), StatusCode::OK);
 
         let req = TestRequest::with_uri("/json").to_request();
         let resp = call_service(&mut srv, req).await;
@@ -4,6 +4,7 @@ use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
+use actix_http::body::{Body, MessageBody, ResponseBody};
 use actix_http::{Extensions, Response};
 use actix_router::{ResourceDef, ResourceInfo, Router};
 use actix_service::boxed::{self, BoxService, BoxServiceFactory};
@@ -12,11 +13,11 @@ use actix_service::{
 };
 use futures::future::{ok, Either, Future, LocalBoxFuture, Ready};
 
-use crate::config::ServiceConfig;
+use crate::config::{RouterOptions, ServiceConfig, TrailingSlash};
 use crate::data::Data;
 use crate::dev::{AppService, HttpServiceFactory};
 use crate::error::Error;
-use crate::guard::Guard;
+use crate::guard::{check_guards, Guard};
 use crate::resource::Resource;
 use crate::rmap::ResourceMap;
 use crate::route::Route;
@@ -28,6 +29,7 @@ type Guards = Vec<Box<dyn Guard>>;
 type HttpService = BoxService<ServiceRequest, ServiceResponse, Error>;
 type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
 type BoxedResponse = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+type DataFromFn = Box<dyn FnOnce(&Extensions, &mut Extensions)>;
 
 /// Resources scope.
 ///
@@ -62,10 +64,14 @@ pub struct Scope<T = ScopeEndpoint> {
     endpoint: T,
     rdef: String,
     data: Option<Extensions>,
+    data_from: Vec<DataFromFn>,
     services: Vec<Box<dyn AppServiceFactory>>,
     guards: Vec<Box<dyn Guard>>,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
     external: Vec<ResourceDef>,
+    trailing_slash: Option<TrailingSlash>,
+    case_insensitive: Option<bool>,
+    priority: i32,
     factory_ref: Rc<RefCell<Option<ScopeFactory>>>,
 }
 
@@ -77,10 +83,14 @@ impl Scope {
             endpoint: ScopeEndpoint::new(fref.clone()),
             rdef: path.to_string(),
             data: None,
+            data_from: Vec::new(),
             guards: Vec::new(),
             services: Vec::new(),
             default: Rc::new(RefCell::new(None)),
             external: Vec::new(),
+            trailing_slash: None,
+            case_insensitive: None,
+            priority: 0,
             factory_ref: fref,
         }
     }
@@ -151,6 +161,63 @@ where
         self.app_data(Data::new(data))
     }
 
+    /// Set scope data derived from application data.
+    ///
+    /// Function is called with application data of type `P` stored with
+    /// `App::data()` once for each worker, result is available to scope
+    /// services with `Data<U>` extractor. This allows scopes to carry their
+    /// own state instead of sharing one application wide data type.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// struct Global {
+    ///     db_url: String,
+    /// }
+    ///
+    /// struct Billing {
+    ///     db_url: String,
+    ///     currency: &'static str,
+    /// }
+    ///
+    /// async fn index(billing: web::Data<Billing>) -> HttpResponse {
+    ///     HttpResponse::Ok().body(billing.currency)
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .data(Global { db_url: "postgres://localhost".to_string() })
+    ///         .service(
+    ///             web::scope("/billing")
+    ///                 .data_from(|global: &Global| Billing {
+    ///                     db_url: global.db_url.clone(),
+    ///                     currency: "EUR",
+    ///                 })
+    ///                 .route("/", web::get().to(index)),
+    ///         );
+    /// }
+    /// ```
+    ///
+    /// Panics during application construction if there is no application
+    /// data of type `P`.
+    pub fn data_from<P, U, F>(mut self, f: F) -> Self
+    where
+        P: 'static,
+        U: 'static,
+        F: FnOnce(&P) -> U + 'static,
+    {
+        self.data_from.push(Box::new(move |parent, data| {
+            let parent = parent.get::<Data<P>>().unwrap_or_else(|| {
+                panic!(
+                    "Application data of type {} is not available for scope data",
+                    std::any::type_name::<P>()
+                )
+            });
+            data.insert(Data::new(f(parent.get_ref())));
+        }));
+        self
+    }
+
     /// Set or override application data.
     ///
     /// This method overrides data stored with [`App::app_data()`](#method.app_data)
@@ -301,16 +368,69 @@ where
         self
     }
 
+    /// Set handling of request paths that differ from registered paths only
+    /// by trailing slash.
+    ///
+    /// If it is not set, handling of parent scope or app is used.
+    ///
+    /// ```rust
+    /// use actix_web::{http, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .trailing_slash(web::TrailingSlash::Redirect(
+    ///                 http::StatusCode::TEMPORARY_REDIRECT,
+    ///             ))
+    ///             .route("/users", web::get().to(|| HttpResponse::Ok())),
+    ///     );
+    /// }
+    /// ```
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = Some(trailing_slash);
+        self
+    }
+
+    /// Match request paths case-insensitively.
+    ///
+    /// Routes have to be registered in lower case. If it is not set,
+    /// matching of parent scope or app is used.
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = Some(enabled);
+        self
+    }
+
+    /// Set matching priority of the scope, default is 0.
+    ///
+    /// Router checks resources and scopes with higher priority first,
+    /// services of the same priority are checked in registration order.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Registers middleware, in the form of a middleware component (type),
     /// that runs during inbound processing in the request
     /// lifecycle (request -> response), modifying request as
-    /// necessary, across all requests managed by the *Scope*.  Scope-level
-    /// middleware is more limited in what it can modify, relative to Route or
-    /// Application level middleware, in that Scope-level middleware can not modify
-    /// ServiceResponse.
+    /// necessary, across all requests managed by the *Scope*.
+    ///
+    /// Middleware could change type of response body, i.e. `Logger` or
+    /// `Compress`, responses are converted back to `Body` afterwards.
     ///
     /// Use middleware when you need to read or modify *every* request in some way.
-    pub fn wrap<M>(
+    ///
+    /// ```rust
+    /// use actix_web::{middleware, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .wrap(middleware::Logger::default())
+    ///             .route("/users", web::get().to(|| HttpResponse::Ok())),
+    ///     );
+    /// }
+    /// ```
+    pub fn wrap<M, B>(
         self,
         mw: M,
     ) -> Scope<
@@ -326,19 +446,24 @@ where
         M: Transform<
             T::Service,
             Request = ServiceRequest,
-            Response = ServiceResponse,
+            Response = ServiceResponse<B>,
             Error = Error,
             InitError = (),
         >,
+        B: MessageBody + 'static,
     {
         Scope {
-            endpoint: apply(mw, self.endpoint),
+            endpoint: apply(mw, self.endpoint).map(into_body_response),
             rdef: self.rdef,
             data: self.data,
+            data_from: self.data_from,
             guards: self.guards,
             services: self.services,
             default: self.default,
             external: self.external,
+            trailing_slash: self.trailing_slash,
+            case_insensitive: self.case_insensitive,
+            priority: self.priority,
             factory_ref: self.factory_ref,
         }
     }
@@ -395,15 +520,29 @@ where
             endpoint: apply_fn_factory(self.endpoint, mw),
             rdef: self.rdef,
             data: self.data,
+            data_from: self.data_from,
             guards: self.guards,
             services: self.services,
             default: self.default,
             external: self.external,
+            trailing_slash: self.trailing_slash,
+            case_insensitive: self.case_insensitive,
+            priority: self.priority,
             factory_ref: self.factory_ref,
         }
     }
 }
 
+/// Convert response of scope middleware to response with `Body`
+fn into_body_response<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+) -> ServiceResponse {
+    res.map_body(|_, body| match body {
+        ResponseBody::Body(body) => ResponseBody::Body(Body::from_message(body)),
+        ResponseBody::Other(body) => ResponseBody::Other(body),
+    })
+}
+
 impl<T> HttpServiceFactory for Scope<T>
 where
     T: ServiceFactory<
@@ -421,10 +560,20 @@ where
         }
 
         // register nested services
+        let parent = config.router_options();
+        let options = RouterOptions {
+            trailing_slash: self.trailing_slash.unwrap_or(parent.trailing_slash),
+            case_insensitive: self.case_insensitive.unwrap_or(parent.case_insensitive),
+        };
         let mut cfg = config.clone_config();
+        cfg.set_router_options(options);
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
+        for op in cfg.take_operations() {
+            let path = format!("{}{}", self.rdef, op.path);
+            config.register_operation(path, op.method, op.operation);
+        }
 
         let mut rmap = ResourceMap::new(ResourceDef::root_prefix(&self.rdef));
 
@@ -433,6 +582,16 @@ where
             rmap.add(&mut rdef, None);
         }
 
+        // data derived from application data
+        if !self.data_from.is_empty() {
+            let mut parent = Extensions::new();
+            config.set_service_data(&mut parent);
+            let data = self.data.get_or_insert_with(Extensions::new);
+            for f in std::mem::replace(&mut self.data_from, Vec::new()) {
+                f(&parent, data);
+            }
+        }
+
         // custom app data storage
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
@@ -442,6 +601,7 @@ where
         *self.factory_ref.borrow_mut() = Some(ScopeFactory {
             data: self.data.take().map(Rc::new),
             default: self.default.clone(),
+            options,
             services: Rc::new(
                 cfg.into_services()
                     .1
@@ -462,11 +622,12 @@ where
         };
 
         // register final service
-        config.register_service(
+        config.register_service_with_priority(
             ResourceDef::root_prefix(&self.rdef),
             guards,
             self.endpoint,
             Some(Rc::new(rmap)),
+            self.priority,
         )
     }
 }
@@ -475,6 +636,7 @@ pub struct ScopeFactory {
     data: Option<Rc<Extensions>>,
     services: Rc<Vec<(ResourceDef, HttpNewService, RefCell<Option<Guards>>)>>,
     default: Rc<RefCell<Option<Rc<HttpNewService>>>>,
+    options: RouterOptions,
 }
 
 impl ServiceFactory for ScopeFactory {
@@ -508,6 +670,7 @@ impl ServiceFactory for ScopeFactory {
             default: None,
             data: self.data.clone(),
             default_fut,
+            options: self.options,
         }
     }
 }
@@ -520,6 +683,7 @@ pub struct ScopeFactoryResponse {
     data: Option<Rc<Extensions>>,
     default: Option<HttpService>,
     default_fut: Option<LocalBoxFuture<'static, Result<HttpService, ()>>>,
+    options: RouterOptions,
 }
 
 type HttpServiceFut = LocalBoxFuture<'static, Result<HttpService, ()>>;
@@ -583,6 +747,7 @@ impl Future for ScopeFactoryResponse {
                 data: self.data.clone(),
                 router: router.finish(),
                 default: self.default.take(),
+                options: self.options,
                 _ready: None,
             }))
         } else {
@@ -595,6 +760,7 @@ pub struct ScopeService {
     data: Option<Rc<Extensions>>,
     router: Router<HttpService, Vec<Box<dyn Guard>>>,
     default: Option<HttpService>,
+    options: RouterOptions,
     _ready: Option<(ServiceRequest, ResourceInfo)>,
 }
 
@@ -609,23 +775,39 @@ impl Service for ScopeService {
     }
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
-        let res = self.router.recognize_mut_checked(&mut req, |req, guards| {
-            if let Some(ref guards) = guards {
-                for f in guards {
-                    if !f.check(req.head()) {
-                        return false;
+        if let Some((srv, _info)) =
+            self.router.recognize_mut_checked(&mut req, check_guards)
+        {
+            if let Some(ref data) = self.data {
+                req.add_data_container(data.clone());
+            }
+            return Either::Left(srv.call(req));
+        }
+
+        // try alternative paths, i.e. with trailing slash added or removed
+        let alts = self.options.alternatives(&req.head().uri);
+        if !alts.is_empty() {
+            for alt in alts {
+                req.match_info_mut().get_mut().update(&alt.matching);
+                if let Some((srv, _info)) =
+                    self.router.recognize_mut_checked(&mut req, check_guards)
+                {
+                    req.match_info_mut().get_mut().update(&alt.uri);
+                    if let Some(res) = alt.redirect(self.options.trailing_slash) {
+                        return Either::Right(ok(req.into_response(res)));
                     }
+                    if let Some(ref data) = self.data {
+                        req.add_data_container(data.clone());
+                    }
+                    req.head_mut().uri = alt.uri;
+                    return Either::Left(srv.call(req));
                 }
             }
-            true
-        });
+            let uri = req.head().uri.clone();
+            req.match_info_mut().get_mut().update(&uri);
+        }
 
-        if let Some((srv, _info)) = res {
-            if let Some(ref data) = self.data {
-                req.set_data_container(data.clone());
-            }
-            Either::Left(srv.call(req))
-        } else if let Some(ref mut default) = self.default {
+        if let Some(ref mut default) = self.default {
             Either::Left(default.call(req))
         } else {
             let req = req.into_parts().0;
@@ -667,7 +849,7 @@ mod tests {
 
     use crate::dev::{Body, ResponseBody};
     use crate::http::{header, HeaderValue, Method, StatusCode};
-    use crate::middleware::DefaultHeaders;
+    use crate::middleware::{DefaultHeaders, Logger};
     use crate::service::ServiceRequest;
     use crate::test::{call_service, init_service, read_body, TestRequest};
     use crate::{guard, web, App, HttpRequest, HttpResponse};
@@ -821,6 +1003,34 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[actix_rt::test]
+    async fn test_scope_host() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::host("api.example.com")
+                        .route("/", web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::host("*.example.com")
+                        .route("/", web::get().to(|| HttpResponse::Created())),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_header(header::HOST, "api.example.com").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_header(header::HOST, "www.example.com").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_header(header::HOST, "example.com").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[actix_rt::test]
     async fn test_scope_variable_segment() {
         let mut srv =
@@ -1070,6 +1280,34 @@ mod tests {
         );
     }
 
+    #[actix_rt::test]
+    async fn test_middleware_body() {
+        let mut srv = init_service(
+            App::new().service(
+                web::scope("app")
+                    .wrap(Logger::default())
+                    .route("/test", web::get().to(|| HttpResponse::Ok().body("test")))
+                    .wrap(
+                        DefaultHeaders::new().header(
+                            header::CONTENT_TYPE,
+                            HeaderValue::from_static("0001"),
+                        ),
+                    ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/test").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("0001")
+        );
+        let body = read_body(resp).await;
+        assert_eq!(body, Bytes::from_static(b"test"));
+    }
+
     #[actix_rt::test]
     async fn test_middleware_fn() {
         let mut srv = init_service(
@@ -1138,6 +1376,96 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[actix_rt::test]
+    async fn test_nested_app_data() {
+        let mut srv = init_service(
+            App::new().app_data(1usize).app_data(1u32).service(
+                web::scope("app")
+                    .app_data(10u32)
+                    .app_data('s')
+                    .route(
+                        "/t",
+                        web::get().to(|req: HttpRequest| {
+                            assert_eq!(*req.app_data::<usize>().unwrap(), 1);
+                            assert_eq!(*req.app_data::<u32>().unwrap(), 10);
+                            HttpResponse::Ok()
+                        }),
+                    )
+                    .service(web::resource("/r").app_data(100u32).to(
+                        |req: HttpRequest| {
+                            assert_eq!(*req.app_data::<usize>().unwrap(), 1);
+                            assert_eq!(*req.app_data::<u32>().unwrap(), 100);
+                            assert_eq!(*req.app_data::<char>().unwrap(), 's');
+                            HttpResponse::Ok()
+                        },
+                    )),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/t").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/app/r").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_scope_payload_config() {
+        let mut srv = init_service(
+            App::new()
+                .app_data(web::PayloadConfig::new(5))
+                .service(
+                    web::scope("upload")
+                        .app_data(web::PayloadConfig::new(100))
+                        .route("/", web::post().to(|body: Bytes| async move { body })),
+                )
+                .route("/", web::post().to(|body: Bytes| async move { body })),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/upload/")
+            .set_payload(Bytes::from_static(b"0123456789"))
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/")
+            .set_payload(Bytes::from_static(b"0123456789"))
+            .to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_data_from() {
+        struct Billing(usize);
+
+        let mut srv = init_service(
+            App::new().data(10usize).service(
+                web::scope("app")
+                    .data_from(|n: &usize| Billing(*n + 1))
+                    .route(
+                        "/t",
+                        web::get().to(|b: web::Data<Billing>, n: web::Data<usize>| {
+                            assert_eq!(b.0, 11);
+                            assert_eq!(**n, 10);
+                            HttpResponse::Ok()
+                        }),
+                    ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/t").to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[actix_rt::test]
     async fn test_scope_config() {
         let mut srv =
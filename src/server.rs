@@ -1,26 +1,67 @@
+use std::any::Any;
+use std::future::Future;
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{fmt, io, net};
 
-use actix_http::{body::MessageBody, Error, HttpService, KeepAlive, Request, Response};
-use actix_server::{Server, ServerBuilder};
-use actix_service::{map_config, IntoServiceFactory, Service, ServiceFactory};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
 
-use net2::TcpBuilder;
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_http::{
+    body::MessageBody, h1::UpgradeHandler, Error, Extensions, HttpServiceBuilder,
+    IdleLimit, KeepAlive, PoolConfig, Protocol, Request, RequestHead, Response,
+};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use actix_rt::net::TcpStream;
+use actix_server::ServerBuilder;
+use actix_service::{
+    apply, fn_service, map_config, pipeline_factory, IntoServiceFactory, Service,
+    ServiceFactory,
+};
+use futures::channel::mpsc;
+use futures::future::{
+    ok, poll_fn, ready, select, Either, FutureExt, LocalBoxFuture, Ready,
+};
+use futures::{Stream, StreamExt};
 
-#[cfg(unix)]
-use actix_http::Protocol;
-#[cfg(unix)]
-use actix_service::pipeline_factory;
-#[cfg(unix)]
-use futures::future::ok;
+use net2::TcpBuilder;
 
 #[cfg(feature = "openssl")]
-use actix_tls::openssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
+use actix_tls::openssl::{AlpnError, SslAcceptor, SslAcceptorBuilder, SslStream};
 #[cfg(feature = "rustls")]
-use actix_tls::rustls::ServerConfig as RustlsServerConfig;
+use actix_tls::rustls::{ServerConfig as RustlsServerConfig, Session, TlsStream};
+#[cfg(feature = "openssl")]
+use open_ssl::ssl::{NameType, SniError};
+#[cfg(feature = "openssl")]
+use std::collections::HashMap;
+#[cfg(feature = "openssl")]
+use std::sync::RwLock;
 
 use crate::config::AppConfig;
+use crate::connections::{ConnectionCounter, ConnectionLimit};
+#[cfg(unix)]
+use crate::handoff::Handoff;
+use crate::health::Readiness;
+use crate::info::TrustedProxies;
+use crate::metrics::{Metered, ServerMetrics, Worker};
+use crate::shutdown::{Running, ServerHandle, ShutdownHooks, ShutdownSignal};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::types::peer_cert::{PeerCertificate, PeerCertificateData};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::types::tls_info::TlsInfo;
+
+/// Max flow control window size of HTTP/2, RFC 7540 section 6.9.1
+const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+/// Bounds of HTTP/2 `SETTINGS_MAX_FRAME_SIZE`, RFC 7540 section 6.5.2
+const MIN_FRAME_SIZE: u32 = 1 << 14;
+const MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// Default maximum size of request head in bytes
+const DEFAULT_MAX_HEADER_SIZE: usize = 128 * 1024;
+/// Default maximum number of headers in request
+const DEFAULT_MAX_HEADERS: usize = 96;
 
 struct Socket {
     scheme: &'static str,
@@ -29,11 +70,42 @@ struct Socket {
 
 struct Config {
     host: Option<String>,
+    trusted_proxies: Option<TrustedProxies>,
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_shutdown: u64,
+    client_read_timeout: u64,
+    proxy_protocol: bool,
+    h2c: bool,
+    max_header_size: usize,
+    max_headers: usize,
+    strict_parsing: bool,
+    max_pipelined_requests: usize,
+    early_hints: bool,
+    max_requests: usize,
+    max_lifetime: u64,
+    tcp_nodelay: bool,
+    tcp_keepalive: Option<Duration>,
+    tcp_recv_buffer_size: usize,
+    tcp_send_buffer_size: usize,
+    h2_initial_window_size: u32,
+    h2_initial_connection_window_size: u32,
+    h2_max_concurrent_streams: u32,
+    h2_max_frame_size: u32,
+    h2_max_header_list_size: u32,
+    date_interval: u64,
+    date_header: bool,
+    on_connect: Option<Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>>,
+    expect: Option<Arc<ExpectFn>>,
+    metrics: Option<ServerMetrics>,
+    idle_limit: IdleLimit,
+    request_pool: PoolConfig,
+    response_pool: PoolConfig,
+    shutdown: ShutdownSignal,
 }
 
+type ExpectFn = dyn Fn(&RequestHead) -> Result<(), Error> + Send + Sync;
+
 /// An HTTP Server.
 ///
 /// Create new http server with application factory.
@@ -64,7 +136,17 @@ where
     pub(super) factory: F,
     config: Arc<Mutex<Config>>,
     backlog: i32,
+    reuse_port: bool,
+    counter: ConnectionCounter,
+    hooks: ShutdownHooks,
+    streams: Vec<Box<dyn FnOnce(LocalBoxFuture<'static, ()>)>>,
+    signals: bool,
+    shutdown_timeout: u64,
     sockets: Vec<Socket>,
+    #[cfg(unix)]
+    listen_fds: Vec<(RawFd, &'static str)>,
+    #[cfg(unix)]
+    handoff: Option<Handoff>,
     builder: ServerBuilder,
     _t: PhantomData<(S, B)>,
 }
@@ -86,12 +168,51 @@ where
             factory,
             config: Arc::new(Mutex::new(Config {
                 host: None,
+                trusted_proxies: None,
                 keep_alive: KeepAlive::Timeout(5),
                 client_timeout: 5000,
                 client_shutdown: 5000,
+                client_read_timeout: 0,
+                proxy_protocol: false,
+                h2c: false,
+                max_header_size: DEFAULT_MAX_HEADER_SIZE,
+                max_headers: DEFAULT_MAX_HEADERS,
+                strict_parsing: false,
+                max_pipelined_requests: 16,
+                early_hints: false,
+                max_requests: 0,
+                max_lifetime: 0,
+                tcp_nodelay: false,
+                tcp_keepalive: None,
+                tcp_recv_buffer_size: 0,
+                tcp_send_buffer_size: 0,
+                h2_initial_window_size: 0,
+                h2_initial_connection_window_size: 0,
+                h2_max_concurrent_streams: 0,
+                h2_max_frame_size: 0,
+                h2_max_header_list_size: 0,
+                date_interval: 500,
+                date_header: true,
+                on_connect: None,
+                expect: None,
+                metrics: None,
+                idle_limit: IdleLimit::new(0),
+                request_pool: PoolConfig::default(),
+                response_pool: PoolConfig::default(),
+                shutdown: ShutdownSignal::default(),
             })),
             backlog: 1024,
+            reuse_port: false,
+            counter: ConnectionCounter::new(),
+            hooks: ShutdownHooks::default(),
+            streams: Vec::new(),
+            signals: true,
+            shutdown_timeout: 30,
             sockets: Vec::new(),
+            #[cfg(unix)]
+            listen_fds: Vec::new(),
+            #[cfg(unix)]
+            handoff: None,
             builder: ServerBuilder::default(),
             _t: PhantomData,
         }
@@ -122,6 +243,21 @@ where
         self
     }
 
+    /// Bind listeners with `SO_REUSEPORT` socket option.
+    ///
+    /// Allows several server processes to bind to the same address, kernel
+    /// distributes incoming connections between them. This is useful for
+    /// rolling restarts, new process starts accepting connections before
+    /// old one is stopped. Option is ignored on non-unix platforms.
+    ///
+    /// By default `SO_REUSEPORT` is not set.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
     /// All socket listeners will stop accepting connections when this limit is reached
@@ -133,6 +269,91 @@ where
         self
     }
 
+    /// Sets the maximum number of concurrent connections for all workers.
+    ///
+    /// All socket listeners stop accepting connections when this limit is
+    /// reached and resume once connections get closed. The limit is
+    /// approximate, workers may accept a few more connections concurrently.
+    /// Use `maxconn()` to limit number of connections per worker.
+    ///
+    /// By default number of connections is not limited, value 0 disables limit.
+    pub fn max_connections(self, num: usize) -> Self {
+        self.counter.set_max(num);
+        self
+    }
+
+    /// Sets the maximum number of idle keep-alive connections for all workers.
+    ///
+    /// Once the limit is exceeded, least recently used idle connection of
+    /// any worker is closed, so clients that hold idle connections can not
+    /// exhaust file descriptors. Number of evicted connections is available
+    /// with `ServerMetrics::evicted_connections()`.
+    ///
+    /// By default idle connections are only limited by keep-alive timeout,
+    /// value 0 disables limit.
+    pub fn max_idle_connections(self, num: usize) -> Self {
+        self.config.lock().unwrap().idle_limit.set_max(num);
+        self
+    }
+
+    /// Sets pool configuration of request objects.
+    ///
+    /// Request heads and `HttpRequest` objects are kept in pools of each
+    /// worker and are reused for next requests. Stats handle of
+    /// configuration counts both pools.
+    ///
+    /// By default each pool keeps up to 128 objects, use
+    /// `PoolConfig::disabled()` to disable pooling.
+    ///
+    /// ```rust,no_run
+    /// use actix_web::dev::{PoolConfig, PoolStats};
+    /// use actix_web::{App, HttpServer};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let stats = PoolStats::new();
+    /// HttpServer::new(|| App::new())
+    ///     .request_pool(
+    ///         PoolConfig::new(32)
+    ///             .max_headers_capacity(64)
+    ///             .stats(stats.clone()),
+    ///     )
+    ///     .bind("127.0.0.1:8080")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn request_pool(self, config: PoolConfig) -> Self {
+        self.config.lock().unwrap().request_pool = config;
+        self
+    }
+
+    /// Sets pool configuration of response heads.
+    ///
+    /// By default pool of each worker keeps up to 128 response heads.
+    pub fn response_pool(self, config: PoolConfig) -> Self {
+        self.config.lock().unwrap().response_pool = config;
+        self
+    }
+
+    /// Get counter of connections served by this server.
+    ///
+    /// Counter can be used for monitoring after server is started.
+    pub fn connections(&self) -> ConnectionCounter {
+        self.counter.clone()
+    }
+
+    /// Get metrics of this server.
+    ///
+    /// Metrics of connections and requests are collected by every worker
+    /// once this method is called, it has to be called before server is started.
+    pub fn metrics(&self) -> ServerMetrics {
+        let mut config = self.config.lock().unwrap();
+        let idle_limit = config.idle_limit.clone();
+        config
+            .metrics
+            .get_or_insert_with(|| ServerMetrics::new(idle_limit))
+            .clone()
+    }
+
     /// Sets the maximum per-worker concurrent connection establish process.
     ///
     /// All listeners will stop accepting connections when this limit is reached. It
@@ -166,6 +387,20 @@ where
         self
     }
 
+    /// Set server client read timeout in milliseconds for subsequent requests.
+    ///
+    /// Defines a timeout for reading request headers of keep-alive requests,
+    /// measured from the first received byte, and a timeout between reads of
+    /// request payload. Protects server from clients that send requests slowly.
+    ///
+    /// To disable timeout set value to 0.
+    ///
+    /// By default client read timeout is disabled.
+    pub fn client_read_timeout(self, val: u64) -> Self {
+        self.config.lock().unwrap().client_read_timeout = val;
+        self
+    }
+
     /// Set server connection shutdown timeout in milliseconds.
     ///
     /// Defines a timeout for shutdown connection. If a shutdown procedure does not complete
@@ -179,6 +414,318 @@ where
         self
     }
 
+    /// Set maximum size of request head in bytes.
+    ///
+    /// Requests with larger head, including request line and headers, are
+    /// responded with 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default maximum head size is 128 KiB.
+    pub fn max_header_size(self, val: usize) -> Self {
+        self.config.lock().unwrap().max_header_size = val;
+        self
+    }
+
+    /// Set maximum number of headers in request.
+    ///
+    /// Requests with more headers are responded with
+    /// 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default maximum number of headers is 96.
+    pub fn max_headers(self, val: usize) -> Self {
+        self.config.lock().unwrap().max_headers = val;
+        self
+    }
+
+    /// Set maximum number of pipelined HTTP/1.1 requests buffered per connection.
+    ///
+    /// Requests are always processed in order. Value 0 disables pipelining,
+    /// next request is read only after response to the previous one is sent.
+    ///
+    /// By default 16 requests could be buffered.
+    pub fn max_pipelined_requests(self, val: usize) -> Self {
+        self.config.lock().unwrap().max_pipelined_requests = val;
+        self
+    }
+
+    /// Enable strict parsing of HTTP/1 request heads.
+    ///
+    /// Requests with ambiguous message length, obsolete line folding or
+    /// bare LF line endings are rejected with 400 (Bad Request) error.
+    /// Use it when server is running behind a proxy to prevent request
+    /// smuggling.
+    ///
+    /// By default strict parsing is disabled.
+    pub fn strict_parsing(self, val: bool) -> Self {
+        self.config.lock().unwrap().strict_parsing = val;
+        self
+    }
+
+    /// Enable `103 Early Hints` informational responses.
+    ///
+    /// If enabled, handlers could send hints to HTTP/1.1 clients with
+    /// `HttpRequest::early_hints()` before final response is ready.
+    ///
+    /// ```rust
+    /// use actix_web::{http::header, HttpRequest, HttpResponse};
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     if let Some(hints) = req.early_hints() {
+    ///         hints.send(
+    ///             HttpResponse::EarlyHints()
+    ///                 .header(header::LINK, "</style.css>; rel=preload; as=style")
+    ///                 .finish(),
+    ///         );
+    ///     }
+    ///     HttpResponse::Ok().finish()
+    /// }
+    /// ```
+    ///
+    /// By default early hints are disabled.
+    pub fn early_hints(self, val: bool) -> Self {
+        self.config.lock().unwrap().early_hints = val;
+        self
+    }
+
+    /// Set maximum number of requests served by one keep-alive connection.
+    ///
+    /// Last allowed request is responded with `Connection: close` header.
+    /// Load balancers can use this to rebalance keep-alive connections
+    /// between backends. Applies to HTTP/1 connections only.
+    ///
+    /// By default number of requests is not limited, value 0 disables limit.
+    pub fn max_requests(self, val: usize) -> Self {
+        self.config.lock().unwrap().max_requests = val;
+        self
+    }
+
+    /// Set maximum lifetime of keep-alive connection in seconds.
+    ///
+    /// First request received after lifetime elapsed is responded with
+    /// `Connection: close` header. Applies to HTTP/1 connections only.
+    ///
+    /// By default lifetime is not limited, value 0 disables limit.
+    pub fn max_lifetime(self, val: u64) -> Self {
+        self.config.lock().unwrap().max_lifetime = val;
+        self
+    }
+
+    /// Set `TCP_NODELAY` option on accepted tcp connections.
+    ///
+    /// Disables Nagle's algorithm, small responses are sent without delay.
+    ///
+    /// By default option is not set.
+    pub fn tcp_nodelay(self, val: bool) -> Self {
+        self.config.lock().unwrap().tcp_nodelay = val;
+        self
+    }
+
+    /// Set `SO_KEEPALIVE` option with given idle time on accepted tcp connections.
+    ///
+    /// By default operating system setting is used.
+    pub fn tcp_keepalive(self, val: Option<Duration>) -> Self {
+        self.config.lock().unwrap().tcp_keepalive = val;
+        self
+    }
+
+    /// Set `SO_RCVBUF` size of accepted tcp connections.
+    ///
+    /// By default operating system setting is used, value 0 keeps it.
+    pub fn tcp_recv_buffer_size(self, val: usize) -> Self {
+        self.config.lock().unwrap().tcp_recv_buffer_size = val;
+        self
+    }
+
+    /// Set `SO_SNDBUF` size of accepted tcp connections.
+    ///
+    /// By default operating system setting is used, value 0 keeps it.
+    pub fn tcp_send_buffer_size(self, val: usize) -> Self {
+        self.config.lock().unwrap().tcp_send_buffer_size = val;
+        self
+    }
+
+    /// Set initial HTTP/2 stream flow control window size.
+    ///
+    /// By default h2 crate setting (64 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value exceeds 2^31-1, maximum window size of HTTP/2.
+    pub fn h2_initial_window_size(self, val: u32) -> Self {
+        assert!(
+            val <= MAX_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31-1"
+        );
+        self.config.lock().unwrap().h2_initial_window_size = val;
+        self
+    }
+
+    /// Set initial HTTP/2 connection flow control window size.
+    ///
+    /// By default h2 crate setting (64 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value exceeds 2^31-1, maximum window size of HTTP/2.
+    pub fn h2_initial_connection_window_size(self, val: u32) -> Self {
+        assert!(
+            val <= MAX_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31-1"
+        );
+        self.config
+            .lock()
+            .unwrap()
+            .h2_initial_connection_window_size = val;
+        self
+    }
+
+    /// Set maximum number of concurrent HTTP/2 streams per connection.
+    ///
+    /// By default number of streams is not limited, value 0 keeps it.
+    pub fn h2_max_concurrent_streams(self, val: u32) -> Self {
+        self.config.lock().unwrap().h2_max_concurrent_streams = val;
+        self
+    }
+
+    /// Set maximum HTTP/2 frame size accepted from client.
+    ///
+    /// By default h2 crate setting (16 KiB) is used, value 0 keeps it.
+    ///
+    /// Panics if value is not in 16384..=16777215 range allowed by HTTP/2.
+    pub fn h2_max_frame_size(self, val: u32) -> Self {
+        assert!(
+            val == 0 || (MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(&val),
+            "HTTP/2 max frame size must be in 16384..=16777215 range"
+        );
+        self.config.lock().unwrap().h2_max_frame_size = val;
+        self
+    }
+
+    /// Set maximum size of HTTP/2 request header list.
+    ///
+    /// By default size is not limited, value 0 keeps it.
+    pub fn h2_max_header_list_size(self, val: u32) -> Self {
+        self.config.lock().unwrap().h2_max_header_list_size = val;
+        self
+    }
+
+    /// Set refresh interval of cached `Date` header value in milliseconds.
+    ///
+    /// By default interval is set to 500 milliseconds.
+    pub fn date_interval(self, val: u64) -> Self {
+        self.config.lock().unwrap().date_interval = val;
+        self
+    }
+
+    /// Enable or disable `Date` header for responses.
+    ///
+    /// By default `Date` header is enabled.
+    pub fn date_header(self, val: bool) -> Self {
+        self.config.lock().unwrap().date_header = val;
+        self
+    }
+
+    /// Set on-connect callback.
+    ///
+    /// Callback is invoked once per accepted connection with the connection
+    /// stream and extensions shared by all requests of the connection.
+    /// Stream is a `TcpStream`, `SslStream<TcpStream>`, `TlsStream<TcpStream>`
+    /// or `UnixStream` depending on the listener type. Inserted values are
+    /// available with `HttpRequest::conn_data()`.
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    /// use actix_rt::net::TcpStream;
+    ///
+    /// #[derive(Clone)]
+    /// struct Ttl(u32);
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     let ttl = req.conn_data::<Ttl>().map(|ttl| ttl.0);
+    ///     HttpResponse::Ok().body(format!("{:?}", ttl))
+    /// }
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new().route("/", web::get().to(index)))
+    ///     .on_connect(|io, ext| {
+    ///         if let Some(stream) = io.downcast_ref::<TcpStream>() {
+    ///             if let Ok(ttl) = stream.ttl() {
+    ///                 ext.insert(Ttl(ttl));
+    ///             }
+    ///         }
+    ///     })
+    ///     .bind("127.0.0.1:8080")?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn on_connect<CB>(self, f: CB) -> Self
+    where
+        CB: Fn(&dyn Any, &mut Extensions) + Send + Sync + 'static,
+    {
+        self.config.lock().unwrap().on_connect = Some(Arc::new(f));
+        self
+    }
+
+    /// Set callback for requests with `Expect: 100-continue` header.
+    ///
+    /// Callback inspects request head before server replies with
+    /// `100 Continue`. Returned error is sent as a final response and
+    /// connection is closed, so client does not upload request body.
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{error, App, HttpServer};
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new())
+    ///     .expect(|head| {
+    ///         let len = head
+    ///             .headers
+    ///             .get("content-length")
+    ///             .and_then(|len| len.to_str().ok())
+    ///             .and_then(|len| len.parse::<u64>().ok());
+    ///         match len {
+    ///             Some(len) if len <= 1_048_576 => Ok(()),
+    ///             _ => Err(error::ErrorPayloadTooLarge("payload is too large")),
+    ///         }
+    ///     })
+    ///     .bind("127.0.0.1:8080")?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn expect<CB>(self, f: CB) -> Self
+    where
+        CB: Fn(&RequestHead) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.config.lock().unwrap().expect = Some(Arc::new(f));
+        self
+    }
+
+    /// Enable HAProxy PROXY protocol support.
+    ///
+    /// With this option enabled every incoming connection must start with
+    /// PROXY protocol header (v1 or v2). Client address announced by the proxy
+    /// is available via `HttpRequest::peer_addr()` and `ConnectionInfo`,
+    /// `LOCAL` and `UNKNOWN` connections keep address of the socket.
+    /// Connections without valid header get closed.
+    ///
+    /// Applies to plain tcp listeners, it does not affect tls and unix socket
+    /// listeners.
+    ///
+    /// By default PROXY protocol is disabled.
+    pub fn proxy_protocol(self, enabled: bool) -> Self {
+        self.config.lock().unwrap().proxy_protocol = enabled;
+        self
+    }
+
+    /// Enable cleartext HTTP/2 (h2c).
+    ///
+    /// Plain tcp connections that start with HTTP/2 connection preface get
+    /// served with HTTP/2 protocol, other connections fall back to HTTP/1.x
+    /// and get upgraded to HTTP/2 with `Upgrade: h2c` request.
+    ///
+    /// By default h2c is disabled.
+    pub fn h2c(self, enabled: bool) -> Self {
+        self.config.lock().unwrap().h2c = enabled;
+        self
+    }
+
     /// Set server host name.
     ///
     /// Host name is used by application router as a hostname for url generation.
@@ -191,6 +738,29 @@ where
         self
     }
 
+    /// Set proxies trusted to provide forwarding headers.
+    ///
+    /// `Forwarded` and `X-Forwarded-*` headers are used by
+    /// [ConnectionInfo](./dev/struct.ConnectionInfo.html) only for connections
+    /// from trusted peers, connections without peer address, i.e. unix
+    /// sockets, are not trusted. Client address is the last address of
+    /// forwarding chain that is not a trusted proxy.
+    ///
+    /// By default forwarding headers of all peers are used.
+    ///
+    /// ```rust
+    /// use actix_web::{dev::TrustedProxies, web, App, HttpResponse, HttpServer};
+    ///
+    /// fn main() {
+    ///     HttpServer::new(|| App::new().route("/", web::get().to(|| HttpResponse::Ok())))
+    ///         .trusted_proxies(TrustedProxies::new().add("10.0.0.0/8").add("::1"));
+    /// }
+    /// ```
+    pub fn trusted_proxies(self, proxies: TrustedProxies) -> Self {
+        self.config.lock().unwrap().trusted_proxies = Some(proxies);
+        self
+    }
+
     /// Stop actix system.
     pub fn system_exit(mut self) -> Self {
         self.builder = self.builder.system_exit();
@@ -200,6 +770,7 @@ where
     /// Disable signal handling
     pub fn disable_signals(mut self) -> Self {
         self.builder = self.builder.disable_signals();
+        self.signals = false;
         self
     }
 
@@ -207,11 +778,100 @@ where
     ///
     /// After receiving a stop signal, workers have this much time to finish
     /// serving requests. Workers still alive after the timeout are force
-    /// dropped.
+    /// dropped. With shutdown hooks, timeout also covers the hooks and
+    /// connection drain, see `on_shutdown()`.
     ///
     /// By default shutdown timeout sets to 30 seconds.
     pub fn shutdown_timeout(mut self, sec: u64) -> Self {
         self.builder = self.builder.shutdown_timeout(sec);
+        self.shutdown_timeout = sec;
+        self
+    }
+
+    /// Register async hook that runs when server receives stop signal.
+    ///
+    /// Hooks run one by one before server stops accepting new connections,
+    /// i.e. to deregister instance from service discovery.
+    ///
+    /// Hooks run on stop signals, unless signals are disabled, and once
+    /// server is stopped with `ServerHandle::stop()`.
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{App, HttpServer};
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new())
+    ///     .on_shutdown(|| async { println!("deregister") })
+    ///     .on_drain(|| async { println!("all connections are closed") })
+    ///     .bind("127.0.0.1:8080")?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn on_shutdown<H, R>(mut self, hook: H) -> Self
+    where
+        H: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.hooks.on_shutdown(hook);
+        self
+    }
+
+    /// Register async hook that runs once connections are drained.
+    ///
+    /// Hooks run after all connections are closed or shutdown timeout
+    /// expires, right before server workers stop.
+    pub fn on_drain<H, R>(mut self, hook: H) -> Self
+    where
+        H: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.hooks.on_drain(hook);
+        self
+    }
+
+    /// Set readiness handle to not ready when server receives stop signal.
+    ///
+    /// Readiness is switched before server stops accepting new connections,
+    /// so `Health` readiness endpoint reports `draining` status while
+    /// connections are drained. It is a shutdown hook, see `on_shutdown()`.
+    pub fn readiness(mut self, readiness: Readiness) -> Self {
+        self.hooks.on_shutdown(move || {
+            readiness.set_ready(false);
+            ready(())
+        });
+        self
+    }
+
+    #[cfg(unix)]
+    /// Hand off listening sockets to new process on signal.
+    ///
+    /// When server receives handoff signal, `SIGUSR2` by default, it starts
+    /// new process with listening sockets passed as inherited descriptors,
+    /// new process takes them with `bind_from_env()`. Once new process
+    /// signals readiness from its `run()`, server shuts down gracefully the
+    /// same way as on `SIGTERM`, shutdown hooks run and connections are
+    /// drained, while new process accepts connections on the same sockets. See
+    /// [`handoff`](handoff/index.html) module for details.
+    ///
+    /// Handoff relies on server signal handling, it is disabled if signals
+    /// are disabled.
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use actix_web::handoff::Handoff;
+    /// use actix_web::{App, HttpServer};
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new())
+    ///     .handoff(Handoff::new().timeout(Duration::from_secs(30)))
+    ///     .bind_from_env()?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn handoff(mut self, handoff: Handoff) -> Self {
+        self.handoff = Some(handoff);
         self
     }
 
@@ -236,8 +896,11 @@ where
     /// it needs to be configured before passing it to listen() method.
     pub fn listen(mut self, lst: net::TcpListener) -> io::Result<Self> {
         let cfg = self.config.clone();
+        let counter = self.counter.clone();
         let factory = self.factory.clone();
         let addr = lst.local_addr().unwrap();
+        #[cfg(unix)]
+        self.listen_fds.push((lst.as_raw_fd(), "http"));
         self.sockets.push(Socket {
             addr,
             scheme: "http",
@@ -248,23 +911,92 @@ where
             lst,
             move || {
                 let c = cfg.lock().unwrap();
-                let cfg = AppConfig::new(
-                    false,
-                    addr,
-                    c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
-
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
+                let cfg = app_config(&c, false, addr);
+
+                let worker = c.metrics.as_ref().map(ServerMetrics::worker);
+                let svc = http_service(&c, worker.clone())
                     .local_addr(addr)
-                    .finish(map_config(factory(), move |_| cfg.clone()))
-                    .tcp()
+                    .proxy_protocol(c.proxy_protocol)
+                    .h2c(c.h2c);
+                let srv = svc
+                    .finish(Metered::<_, B>::new(
+                        worker,
+                        map_config(factory(), move |_| cfg.clone()),
+                    ))
+                    .tcp();
+                apply(ConnectionLimit(counter.clone()), srv)
             },
         )?;
         Ok(self)
     }
 
+    /// Serve connections from a stream of already established io objects.
+    ///
+    /// Io objects could be connections taken over from external tls
+    /// terminator, in-memory pipes or tunneled transports. Connections are
+    /// served with HTTP/1 on the thread that runs the server, serving starts
+    /// once server is started. New connections are not taken from the stream
+    /// once it is exhausted, server is stopped or its graceful shutdown
+    /// begins. Connection limit and metrics of the server apply to these
+    /// connections, so graceful shutdown waits for them to drain.
+    ///
+    /// `addr` is local address of served connections, it is reported by
+    /// `AppConfig::local_addr()` and is used as default host. Server does
+    /// not need any bound sockets to serve io streams, server without them
+    /// does not start workers and handles stop signals by itself.
+    pub fn listen_with_stream<St, T>(mut self, stream: St, addr: net::SocketAddr) -> Self
+    where
+        St: Stream<Item = T> + Unpin + 'static,
+        T: AsyncRead + AsyncWrite + Unpin + 'static,
+        S: 'static,
+    {
+        let cfg = self.config.clone();
+        let counter = self.counter.clone();
+        let factory = self.factory.clone();
+        let socket_addr = addr;
+
+        self.streams.push(Box::new(move |stopped| {
+            let c = cfg.lock().unwrap();
+            let config = app_config(&c, false, socket_addr);
+            let worker = c.metrics.as_ref().map(ServerMetrics::worker);
+            let svc = http_service(&c, worker.clone());
+            let srv = pipeline_factory(|io: T| ok((io, Protocol::Http1, None)))
+                .and_then(svc.finish(Metered::<_, B>::new(
+                    worker,
+                    map_config(factory(), move |_| config.clone()),
+                )));
+            let srv = apply(ConnectionLimit(counter), srv);
+
+            actix_rt::spawn(async move {
+                let mut srv = match srv.new_service(()).await {
+                    Ok(srv) => srv,
+                    Err(_) => {
+                        log::error!("Can not construct http service for io stream");
+                        return;
+                    }
+                };
+                let (mut stream, mut stopped) = (stream, stopped);
+                loop {
+                    let io = match select(stream.next(), stopped).await {
+                        Either::Left((Some(io), fut)) => {
+                            stopped = fut;
+                            io
+                        }
+                        _ => break,
+                    };
+                    if poll_fn(|cx| srv.poll_ready(cx)).await.is_err() {
+                        break;
+                    }
+                    let fut = srv.call(io);
+                    actix_rt::spawn(async move {
+                        let _ = fut.await;
+                    });
+                }
+            });
+        }));
+        self
+    }
+
     #[cfg(feature = "openssl")]
     /// Use listener for accepting incoming tls connection requests
     ///
@@ -285,7 +1017,10 @@ where
     ) -> io::Result<Self> {
         let factory = self.factory.clone();
         let cfg = self.config.clone();
+        let counter = self.counter.clone();
         let addr = lst.local_addr().unwrap();
+        #[cfg(unix)]
+        self.listen_fds.push((lst.as_raw_fd(), TLS_FD_NAME));
         self.sockets.push(Socket {
             addr,
             scheme: "https",
@@ -296,17 +1031,19 @@ where
             lst,
             move || {
                 let c = cfg.lock().unwrap();
-                let cfg = AppConfig::new(
-                    true,
-                    addr,
-                    c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
+                let cfg = app_config(&c, true, addr);
+                let worker = c.metrics.as_ref().map(ServerMetrics::worker);
+                let svc = http_service(&c, worker.clone())
                     .client_disconnect(c.client_shutdown)
-                    .finish(map_config(factory(), move |_| cfg.clone()))
-                    .openssl(acceptor.clone())
+                    .on_connect(openssl_peer_certificate)
+                    .on_connect(openssl_tls_info);
+                let srv = svc
+                    .finish(Metered::<_, B>::new(
+                        worker,
+                        map_config(factory(), move |_| cfg.clone()),
+                    ))
+                    .openssl(acceptor.clone());
+                apply(ConnectionLimit(counter.clone()), srv)
             },
         )?;
         Ok(self)
@@ -332,7 +1069,10 @@ where
     ) -> io::Result<Self> {
         let factory = self.factory.clone();
         let cfg = self.config.clone();
+        let counter = self.counter.clone();
         let addr = lst.local_addr().unwrap();
+        #[cfg(unix)]
+        self.listen_fds.push((lst.as_raw_fd(), TLS_FD_NAME));
         self.sockets.push(Socket {
             addr,
             scheme: "https",
@@ -343,17 +1083,19 @@ where
             lst,
             move || {
                 let c = cfg.lock().unwrap();
-                let cfg = AppConfig::new(
-                    true,
-                    addr,
-                    c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
+                let cfg = app_config(&c, true, addr);
+                let worker = c.metrics.as_ref().map(ServerMetrics::worker);
+                let svc = http_service(&c, worker.clone())
                     .client_disconnect(c.client_shutdown)
-                    .finish(map_config(factory(), move |_| cfg.clone()))
-                    .rustls(config.clone())
+                    .on_connect(rustls_peer_certificate)
+                    .on_connect(rustls_tls_info);
+                let srv = svc
+                    .finish(Metered::<_, B>::new(
+                        worker,
+                        map_config(factory(), move |_| cfg.clone()),
+                    ))
+                    .rustls(config.clone());
+                apply(ConnectionLimit(counter.clone()), srv)
             },
         )?;
         Ok(self)
@@ -380,7 +1122,7 @@ where
         let mut succ = false;
         let mut sockets = Vec::new();
         for addr in addr.to_socket_addrs()? {
-            match create_tcp_listener(addr, self.backlog) {
+            match create_tcp_listener(addr, self.backlog, self.reuse_port) {
                 Ok(lst) => {
                     succ = true;
                     sockets.push(lst);
@@ -425,10 +1167,106 @@ where
         Ok(self)
     }
 
+    #[cfg(feature = "openssl")]
+    /// Start listening for incoming tls connections with per-host certificates.
+    ///
+    /// Certificate is selected by the server name (SNI) sent by the client.
+    /// `builder` is used for clients that do not send server name or send
+    /// an unknown one. Host names are matched case-insensitively.
+    ///
+    /// This method sets alpn protocols to "h2" and "http/1.1" for every certificate
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{App, HttpServer};
+    /// use open_ssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+    ///
+    /// fn builder(name: &str) -> std::io::Result<SslAcceptorBuilder> {
+    ///     let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    ///     builder.set_private_key_file(format!("{}.key", name), SslFiletype::PEM)?;
+    ///     builder.set_certificate_chain_file(format!("{}.pem", name))?;
+    ///     Ok(builder)
+    /// }
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new())
+    ///     .bind_openssl_sni("0.0.0.0:443", builder("default")?, vec![
+    ///         ("api.example.com".to_string(), builder("api")?),
+    ///         ("www.example.com".to_string(), builder("www")?),
+    ///     ])?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn bind_openssl_sni<A, H>(
+        mut self,
+        addr: A,
+        mut builder: SslAcceptorBuilder,
+        hosts: H,
+    ) -> io::Result<Self>
+    where
+        A: net::ToSocketAddrs,
+        H: IntoIterator<Item = (String, SslAcceptorBuilder)>,
+    {
+        let mut acceptors = HashMap::new();
+        for (host, builder) in hosts {
+            acceptors.insert(host.to_lowercase(), openssl_acceptor(builder)?);
+        }
+
+        builder.set_servername_callback(move |ssl, _| {
+            let acceptor = ssl
+                .servername(NameType::HOST_NAME)
+                .and_then(|name| acceptors.get(&name.to_lowercase()));
+            if let Some(acceptor) = acceptor {
+                ssl.set_ssl_context(acceptor.context())
+                    .map_err(|_| SniError::ALERT_FATAL)?;
+            }
+            Ok(())
+        });
+
+        let sockets = self.bind2(addr)?;
+        let acceptor = openssl_acceptor(builder)?;
+
+        for lst in sockets {
+            self = self.listen_ssl_inner(lst, acceptor.clone())?;
+        }
+
+        Ok(self)
+    }
+
+    #[cfg(feature = "openssl")]
+    /// Start listening for incoming tls connections with reloadable certificate.
+    ///
+    /// Certificate used by these listeners can be replaced at runtime with
+    /// `OpensslReloadHandle::reload()`, without restarting workers or dropping
+    /// established connections. New certificate is used for subsequent handshakes,
+    /// including handshakes of clients that do not send server name.
+    ///
+    /// This method sets alpn protocols to "h2" and "http/1.1"
+    pub fn bind_openssl_reloadable<A>(
+        mut self,
+        addr: A,
+        handle: &OpensslReloadHandle,
+    ) -> io::Result<Self>
+    where
+        A: net::ToSocketAddrs,
+    {
+        let sockets = self.bind2(addr)?;
+
+        for lst in sockets {
+            self = self.listen_ssl_inner(lst, handle.acceptor.clone())?;
+        }
+
+        Ok(self)
+    }
+
     #[cfg(feature = "rustls")]
     /// Start listening for incoming tls connections.
     ///
     /// This method sets alpn protocols to "h2" and "http/1.1"
+    ///
+    /// To serve multiple certificates selected by the server name (SNI),
+    /// set `ServerConfig::cert_resolver`, for example to
+    /// `rustls::ResolvesServerCertUsingSNI`, before passing config to this method.
     pub fn bind_rustls<A: net::ToSocketAddrs>(
         mut self,
         addr: A,
@@ -441,6 +1279,61 @@ where
         Ok(self)
     }
 
+    #[cfg(unix)]
+    /// Start listening on sockets passed by systemd socket activation.
+    ///
+    /// Sockets are taken from `LISTEN_FDS`/`LISTEN_PID` environment variables,
+    /// variables are unset once they are read, so child processes do not
+    /// inherit them. Sockets that are handed off by parent process configured
+    /// with `handoff()` are taken too.
+    /// Both tcp and unix domain sockets are supported. Sockets named `https`
+    /// (`FileDescriptorName=https` in the socket unit) are skipped, use
+    /// `bind_from_env_openssl()` or `bind_from_env_rustls()` for them.
+    ///
+    /// Returns error if process is not socket activated. Every socket is
+    /// taken only once, repeated calls do not listen on the same sockets.
+    pub fn bind_from_env(mut self) -> io::Result<Self> {
+        for lst in env_listeners(|name| name != TLS_FD_NAME)? {
+            self = match lst {
+                EnvListener::Tcp(lst) => self.listen(lst)?,
+                EnvListener::Uds(lst) => self.listen_uds(lst)?,
+            };
+        }
+        Ok(self)
+    }
+
+    #[cfg(all(unix, feature = "openssl"))]
+    /// Start listening for incoming tls connections on sockets named `https`
+    /// and passed by systemd socket activation.
+    ///
+    /// This method sets alpn protocols to "h2" and "http/1.1"
+    pub fn bind_from_env_openssl(
+        mut self,
+        builder: SslAcceptorBuilder,
+    ) -> io::Result<Self> {
+        let acceptor = openssl_acceptor(builder)?;
+
+        for lst in env_tls_listeners()? {
+            self = self.listen_ssl_inner(lst, acceptor.clone())?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(all(unix, feature = "rustls"))]
+    /// Start listening for incoming tls connections on sockets named `https`
+    /// and passed by systemd socket activation.
+    ///
+    /// This method sets alpn protocols to "h2" and "http/1.1"
+    pub fn bind_from_env_rustls(
+        mut self,
+        config: RustlsServerConfig,
+    ) -> io::Result<Self> {
+        for lst in env_tls_listeners()? {
+            self = self.listen_rustls_inner(lst, config.clone())?;
+        }
+        Ok(self)
+    }
+
     #[cfg(unix)]
     /// Start listening for unix domain connections on existing listener.
     ///
@@ -452,6 +1345,7 @@ where
         use actix_rt::net::UnixStream;
 
         let cfg = self.config.clone();
+        let counter = self.counter.clone();
         let factory = self.factory.clone();
         let socket_addr = net::SocketAddr::new(
             net::IpAddr::V4(net::Ipv4Addr::new(127, 0, 0, 1)),
@@ -463,20 +1357,19 @@ where
         });
 
         let addr = format!("actix-web-service-{:?}", lst.local_addr()?);
+        self.listen_fds.push((lst.as_raw_fd(), "http"));
 
         self.builder = self.builder.listen_uds(addr, lst, move || {
             let c = cfg.lock().unwrap();
-            let config = AppConfig::new(
-                false,
-                socket_addr,
-                c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-            );
-            pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None))).and_then(
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
-                    .finish(map_config(factory(), move |_| config.clone())),
-            )
+            let config = app_config(&c, false, socket_addr);
+            let worker = c.metrics.as_ref().map(ServerMetrics::worker);
+            let svc = http_service(&c, worker.clone());
+            let srv = pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None)))
+                .and_then(svc.finish(Metered::<_, B>::new(
+                    worker,
+                    map_config(factory(), move |_| config.clone()),
+                )));
+            apply(ConnectionLimit(counter.clone()), srv)
         })?;
         Ok(self)
     }
@@ -485,43 +1378,21 @@ where
     /// Start listening for incoming unix domain connections.
     ///
     /// This method is available with `uds` feature.
-    pub fn bind_uds<A>(mut self, addr: A) -> io::Result<Self>
+    pub fn bind_uds<A>(self, addr: A) -> io::Result<Self>
     where
         A: AsRef<std::path::Path>,
     {
-        use actix_rt::net::UnixStream;
+        use std::os::unix::net::UnixListener;
 
-        let cfg = self.config.clone();
-        let factory = self.factory.clone();
-        let socket_addr = net::SocketAddr::new(
-            net::IpAddr::V4(net::Ipv4Addr::new(127, 0, 0, 1)),
-            8080,
-        );
-        self.sockets.push(Socket {
-            scheme: "http",
-            addr: socket_addr,
-        });
+        // path must not exist, stale socket file is removed
+        if let Err(e) = std::fs::remove_file(addr.as_ref()) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e);
+            }
+        }
 
-        self.builder = self.builder.bind_uds(
-            format!("actix-web-service-{:?}", addr.as_ref()),
-            addr,
-            move || {
-                let c = cfg.lock().unwrap();
-                let config = AppConfig::new(
-                    false,
-                    socket_addr,
-                    c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-                );
-                pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None)))
-                    .and_then(
-                        HttpService::build()
-                            .keep_alive(c.keep_alive)
-                            .client_timeout(c.client_timeout)
-                            .finish(map_config(factory(), move |_| config.clone())),
-                    )
-            },
-        )?;
-        Ok(self)
+        let lst = UnixListener::bind(addr)?;
+        self.listen_uds(lst)
     }
 }
 
@@ -557,24 +1428,354 @@ where
     ///         .await
     /// }
     /// ```
-    pub fn run(self) -> Server {
-        self.builder.start()
+    pub fn run(self) -> ServerHandle {
+        #[cfg(unix)]
+        let hooks = {
+            let mut hooks = self.hooks;
+            if let Some(handoff) = self.handoff {
+                hooks.stop_on(handoff.run(self.listen_fds));
+            }
+            hooks
+        };
+        #[cfg(not(unix))]
+        let hooks = self.hooks;
+
+        let signal = self.config.lock().unwrap().shutdown.clone();
+        let shutdown_timeout = Duration::from_secs(self.shutdown_timeout);
+
+        // actix-server requires at least one bound socket, server that only
+        // serves io streams runs without it and handles signals by itself
+        let streams_only = self.sockets.is_empty() && !self.streams.is_empty();
+        // shutdown hooks take over signal handling, if there are any
+        let hooked = !hooks.is_empty() || streams_only;
+        let srv = if streams_only {
+            Running::Streams {
+                stopped: ShutdownSignal::default(),
+                counter: self.counter.clone(),
+                shutdown_timeout,
+            }
+        } else if hooked && self.signals {
+            Running::Server(self.builder.disable_signals().start())
+        } else {
+            Running::Server(self.builder.start())
+        };
+        // server started by handoff signals readiness to its parent
+        #[cfg(unix)]
+        crate::handoff::notify_ready();
+
+        // io streams are served until server stops or shutdown begins
+        for serve in self.streams {
+            let stopped = select(srv.stopped(), signal.started()).map(|_| ());
+            serve(stopped.boxed_local());
+        }
+
+        if !hooked {
+            return ServerHandle::new(srv, None);
+        }
+        let (tx, rx) = mpsc::unbounded();
+        actix_rt::spawn(hooks.run(
+            srv.clone(),
+            rx,
+            self.signals,
+            self.counter,
+            signal,
+            shutdown_timeout,
+        ));
+        ServerHandle::new(srv, Some(tx))
+    }
+}
+
+/// On-connect callback that runs `HttpServer::on_connect()` callback and
+/// counts connection in worker metrics, both share connection extensions
+fn connect_fn<T: Any>(
+    handler: Option<Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>>,
+    worker: Option<Worker>,
+) -> impl Fn(&T, &mut Extensions) {
+    move |io: &T, ext: &mut Extensions| {
+        if let Some(ref handler) = handler {
+            handler(io, ext);
+        }
+        if let Some(ref worker) = worker {
+            worker.connect(ext);
+        }
+    }
+}
+
+/// Application config of listener with settings of server applied.
+fn app_config(c: &Config, secure: bool, addr: net::SocketAddr) -> AppConfig {
+    AppConfig::new(
+        secure,
+        addr,
+        c.host.clone().unwrap_or_else(|| format!("{}", addr)),
+        c.trusted_proxies.clone(),
+    )
+    .with_shutdown(c.shutdown.clone())
+    .with_request_pool(c.request_pool.clone())
+}
+
+/// Http service builder with settings of server applied, settings specific
+/// to listener kind are applied by caller.
+fn http_service<T, S>(
+    c: &Config,
+    worker: Option<Worker>,
+) -> HttpServiceBuilder<
+    T,
+    S,
+    impl ServiceFactory<
+        Config = (),
+        Request = Request,
+        Response = Request,
+        Error = Error,
+        InitError = (),
+        Service = impl Service<
+            Request = Request,
+            Response = Request,
+            Error = Error,
+            Future = Ready<Result<Request, Error>>,
+        >,
+    >,
+    UpgradeHandler<T>,
+>
+where
+    T: Any,
+    S: ServiceFactory<Config = (), Request = Request>,
+    S::Error: Into<Error> + 'static,
+    S::InitError: fmt::Debug,
+    <S::Service as Service>::Future: 'static,
+{
+    let mut svc = HttpServiceBuilder::new()
+        .keep_alive(c.keep_alive)
+        .client_timeout(c.client_timeout)
+        .client_read_timeout(c.client_read_timeout)
+        .max_header_size(c.max_header_size)
+        .max_headers(c.max_headers)
+        .strict_parsing(c.strict_parsing)
+        .max_pipelined_requests(c.max_pipelined_requests)
+        .early_hints(c.early_hints)
+        .max_requests(c.max_requests)
+        .max_lifetime(c.max_lifetime)
+        .date_interval(c.date_interval)
+        .date_header(c.date_header)
+        .idle_limit(c.idle_limit.clone())
+        .request_pool(c.request_pool.clone())
+        .response_pool(c.response_pool.clone())
+        .h2_initial_window_size(c.h2_initial_window_size)
+        .h2_initial_connection_window_size(c.h2_initial_connection_window_size)
+        .h2_max_concurrent_streams(c.h2_max_concurrent_streams)
+        .h2_max_frame_size(c.h2_max_frame_size)
+        .h2_max_header_list_size(c.h2_max_header_list_size)
+        .tcp_nodelay(c.tcp_nodelay)
+        .tcp_keepalive(c.tcp_keepalive)
+        .tcp_recv_buffer_size(c.tcp_recv_buffer_size)
+        .tcp_send_buffer_size(c.tcp_send_buffer_size)
+        .expect(fn_service(expect_fn(c.expect.clone())));
+    if c.on_connect.is_some() || worker.is_some() {
+        svc = svc.on_connect_ext(connect_fn(c.on_connect.clone(), worker));
+    }
+    svc
+}
+
+/// Expect handler that runs `HttpServer::expect()` callback
+fn expect_fn(
+    handler: Option<Arc<ExpectFn>>,
+) -> impl Fn(Request) -> Ready<Result<Request, Error>> + Clone {
+    move |req: Request| match handler {
+        Some(ref f) => ready(f(req.head()).map(|_| req)),
+        None => ready(Ok(req)),
     }
 }
 
 fn create_tcp_listener(
     addr: net::SocketAddr,
     backlog: i32,
+    reuse_port: bool,
 ) -> io::Result<net::TcpListener> {
     let builder = match addr {
         net::SocketAddr::V4(_) => TcpBuilder::new_v4()?,
         net::SocketAddr::V6(_) => TcpBuilder::new_v6()?,
     };
     builder.reuse_address(true)?;
+    #[cfg(unix)]
+    {
+        use net2::unix::UnixTcpBuilderExt;
+        if reuse_port {
+            builder.reuse_port(true)?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = reuse_port;
     builder.bind(addr)?;
     Ok(builder.listen(backlog)?)
 }
 
+#[cfg(unix)]
+/// Name of systemd sockets that accept tls connections
+const TLS_FD_NAME: &str = "https";
+
+#[cfg(unix)]
+enum EnvListener {
+    Tcp(net::TcpListener),
+    Uds(std::os::unix::net::UnixListener),
+}
+
+#[cfg(unix)]
+lazy_static::lazy_static! {
+    /// Descriptors passed by systemd or parent process with their names,
+    /// descriptors are removed once they are taken.
+    static ref ENV_FDS: Mutex<Option<Vec<(RawFd, String)>>> = Mutex::new(env_fds());
+}
+
+#[cfg(unix)]
+/// Read passed descriptors, see `sd_listen_fds(3)`.
+///
+/// Environment variables are unset, so they are not inherited
+/// by child processes.
+fn env_fds() -> Option<Vec<(RawFd, String)>> {
+    /// First passed file descriptor
+    const SD_LISTEN_FDS_START: RawFd = 3;
+
+    let pid = |name: &str| std::env::var(name).ok().and_then(|pid| pid.parse().ok());
+    let activated = pid("LISTEN_PID") == Some(std::process::id())
+        || pid("LISTEN_PARENT_PID") == Some(std::os::unix::process::parent_id());
+    let fds = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.parse::<RawFd>().ok());
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let vars = [
+        "LISTEN_PID",
+        "LISTEN_PARENT_PID",
+        "LISTEN_FDS",
+        "LISTEN_FDNAMES",
+    ];
+    for name in &vars {
+        std::env::remove_var(name);
+    }
+
+    let fds = fds.filter(|_| activated)?;
+    let mut names = names.split(':');
+    Some(
+        (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + fds)
+            .map(|fd| (fd, names.next().unwrap_or("").to_owned()))
+            .collect(),
+    )
+}
+
+#[cfg(unix)]
+/// Take sockets passed by systemd, see `sd_listen_fds(3)`, or handed off by
+/// parent process.
+///
+/// Only sockets with names accepted by `select` are taken, others are left open.
+fn env_listeners<P>(select: P) -> io::Result<Vec<EnvListener>>
+where
+    P: Fn(&str) -> bool,
+{
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    let mut env_fds = ENV_FDS.lock().unwrap();
+    let env_fds = env_fds.as_mut().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "Process is not socket activated")
+    })?;
+    let (fds, rest) = env_fds.drain(..).partition(|(_, name)| select(name));
+    *env_fds = rest;
+
+    let mut listeners = Vec::new();
+    for (fd, _) in fds {
+        // tcp listener reports its local address only for inet sockets
+        let lst = unsafe { net::TcpListener::from_raw_fd(fd) };
+        if lst.local_addr().is_ok() {
+            listeners.push(EnvListener::Tcp(lst));
+        } else {
+            let fd = lst.into_raw_fd();
+            let lst = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+            listeners.push(EnvListener::Uds(lst));
+        }
+    }
+    Ok(listeners)
+}
+
+#[cfg(all(unix, any(feature = "openssl", feature = "rustls")))]
+/// Take tcp sockets named `https` passed by systemd.
+fn env_tls_listeners() -> io::Result<Vec<net::TcpListener>> {
+    env_listeners(|name| name == TLS_FD_NAME)?
+        .into_iter()
+        .map(|lst| match lst {
+            EnvListener::Tcp(lst) => Ok(lst),
+            EnvListener::Uds(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Tls is not supported for unix domain sockets",
+            )),
+        })
+        .collect()
+}
+
+#[cfg(feature = "openssl")]
+#[derive(Clone)]
+/// Handle for replacing certificate of running openssl listeners.
+///
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer, OpensslReloadHandle};
+/// use open_ssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+///
+/// fn builder() -> std::io::Result<SslAcceptorBuilder> {
+///     let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+///     builder.set_private_key_file("key.pem", SslFiletype::PEM)?;
+///     builder.set_certificate_chain_file("cert.pem")?;
+///     Ok(builder)
+/// }
+///
+/// # fn run() -> std::io::Result<()> {
+/// let handle = OpensslReloadHandle::new(builder()?)?;
+///
+/// HttpServer::new(|| App::new())
+///     .bind_openssl_reloadable("0.0.0.0:443", &handle)?
+///     .run();
+///
+/// // later, once certificate is renewed
+/// handle.reload(builder()?)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct OpensslReloadHandle {
+    acceptor: SslAcceptor,
+    current: Arc<RwLock<Option<SslAcceptor>>>,
+}
+
+#[cfg(feature = "openssl")]
+impl OpensslReloadHandle {
+    /// Create handle with initial acceptor configuration.
+    pub fn new(mut builder: SslAcceptorBuilder) -> io::Result<Self> {
+        let current = Arc::new(RwLock::new(None::<SslAcceptor>));
+
+        // openssl runs servername callback for every client hello, with
+        // or without server name, so reloaded context replaces default
+        // certificate as well
+        let slot = current.clone();
+        builder.set_servername_callback(move |ssl, _| {
+            if let Some(ref acceptor) = *slot.read().unwrap() {
+                ssl.set_ssl_context(acceptor.context())
+                    .map_err(|_| SniError::ALERT_FATAL)?;
+            }
+            Ok(())
+        });
+
+        Ok(OpensslReloadHandle {
+            acceptor: openssl_acceptor(builder)?,
+            current,
+        })
+    }
+
+    /// Replace certificate used for new tls connections.
+    ///
+    /// Certificate of `builder` is used for all clients, server name
+    /// selection of `builder` is not applied. Already established
+    /// connections are not affected.
+    pub fn reload(&self, builder: SslAcceptorBuilder) -> io::Result<()> {
+        let acceptor = openssl_acceptor(builder)?;
+        *self.current.write().unwrap() = Some(acceptor);
+        Ok(())
+    }
+}
+
 #[cfg(feature = "openssl")]
 /// Configure `SslAcceptorBuilder` with custom server flags.
 fn openssl_acceptor(mut builder: SslAcceptorBuilder) -> io::Result<SslAcceptor> {
@@ -593,3 +1794,67 @@ fn openssl_acceptor(mut builder: SslAcceptorBuilder) -> io::Result<SslAcceptor>
 
     Ok(builder.build())
 }
+
+#[cfg(feature = "openssl")]
+/// Extract client certificate of openssl connection.
+fn openssl_peer_certificate(io: &SslStream<TcpStream>) -> PeerCertificateData {
+    PeerCertificateData(io.ssl().peer_certificate().and_then(|cert| {
+        let subject = cert
+            .subject_name()
+            .entries()
+            .map(|entry| {
+                let name = entry.object().nid().short_name().unwrap_or("UNDEF");
+                match entry.data().as_utf8() {
+                    Ok(value) => format!("{}={}", name, value),
+                    Err(_) => name.to_owned(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        cert.to_der()
+            .ok()
+            .map(|der| PeerCertificate::new(der, Some(subject)))
+    }))
+}
+
+#[cfg(feature = "rustls")]
+/// Extract client certificate of rustls connection.
+fn rustls_peer_certificate(io: &TlsStream<TcpStream>) -> PeerCertificateData {
+    let (_, session) = io.get_ref();
+    PeerCertificateData(
+        session
+            .get_peer_certificates()
+            .and_then(|certs| certs.into_iter().next())
+            .map(|cert| PeerCertificate::new(cert.0, None)),
+    )
+}
+
+#[cfg(feature = "openssl")]
+/// Extract negotiated session parameters of openssl connection.
+fn openssl_tls_info(io: &SslStream<TcpStream>) -> TlsInfo {
+    let ssl = io.ssl();
+    TlsInfo::new(
+        ssl.selected_alpn_protocol().map(|p| p.to_vec()),
+        Some(ssl.version_str().to_owned()),
+        ssl.current_cipher().map(|c| c.name().to_owned()),
+    )
+}
+
+#[cfg(feature = "rustls")]
+/// Extract negotiated session parameters of rustls connection.
+fn rustls_tls_info(io: &TlsStream<TcpStream>) -> TlsInfo {
+    use rust_tls::ProtocolVersion;
+
+    let (_, session) = io.get_ref();
+    TlsInfo::new(
+        session.get_alpn_protocol().map(|p| p.to_vec()),
+        session.get_protocol_version().map(|v| match v {
+            ProtocolVersion::TLSv1_2 => "TLSv1.2".to_owned(),
+            ProtocolVersion::TLSv1_3 => "TLSv1.3".to_owned(),
+            v => format!("{:?}", v),
+        }),
+        session
+            .get_negotiated_ciphersuite()
+            .map(|suite| format!("{:?}", suite.suite)),
+    )
+}
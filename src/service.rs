@@ -2,7 +2,7 @@ use std::cell::{Ref, RefMut};
 use std::rc::Rc;
 use std::{fmt, net};
 
-use actix_http::body::{Body, MessageBody, ResponseBody};
+use actix_http::body::{Body, BodyTransform, MessageBody, ResponseBody, TransformBody};
 use actix_http::http::{HeaderMap, Method, StatusCode, Uri, Version};
 use actix_http::{
     Error, Extensions, HttpMessage, Payload, PayloadStream, RequestHead, Response,
@@ -214,14 +214,18 @@ impl ServiceRequest {
         self.0.app_config()
     }
 
+    /// Insert request-local data, available to handlers via
+    /// `ReqData<T>` extractor.
+    ///
+    /// Previously inserted value of the same type is replaced.
+    pub fn insert_req_data<T: 'static>(&self, data: T) {
+        self.extensions_mut().insert(data);
+    }
+
     /// Get an application data stored with `App::data()` method during
     /// application configuration.
     pub fn app_data<T: 'static>(&self) -> Option<Data<T>> {
-        if let Some(st) = (self.0).0.app_data.get::<Data<T>>() {
-            Some(st.clone())
-        } else {
-            None
-        }
+        self.0.app_data::<Data<T>>().cloned()
     }
 
     /// Set request payload.
@@ -230,9 +234,19 @@ impl ServiceRequest {
     }
 
     #[doc(hidden)]
-    /// Set new app data container
+    /// Set new app data container, replacing all existing containers
     pub fn set_data_container(&mut self, extensions: Rc<Extensions>) {
-        Rc::get_mut(&mut (self.0).0).unwrap().app_data = extensions;
+        Rc::get_mut(&mut (self.0).0).unwrap().app_data = vec![extensions];
+    }
+
+    #[doc(hidden)]
+    /// Add app data container, its data takes precedence over
+    /// data of previously added containers
+    pub fn add_data_container(&mut self, extensions: Rc<Extensions>) {
+        Rc::get_mut(&mut (self.0).0)
+            .unwrap()
+            .app_data
+            .push(extensions);
     }
 }
 
@@ -395,6 +409,45 @@ impl<B> ServiceResponse<B> {
             request: self.request,
         }
     }
+
+    /// Transform body chunk by chunk while it is streamed
+    ///
+    /// This could be used by middleware for rewriting response body without
+    /// buffering it. `Content-Length` header is removed and body is sent with
+    /// chunked transfer encoding.
+    ///
+    /// ```rust
+    /// use actix_service::Service;
+    /// use actix_web::{web, App, Error, HttpResponse};
+    /// use futures::future::FutureExt;
+    ///
+    /// # fn main() {
+    /// let app = App::new()
+    ///     .wrap_fn(|req, srv| {
+    ///         srv.call(req).map(|res| {
+    ///             res.map(|res| {
+    ///                 res.transform_body(|chunk: web::Bytes| {
+    ///                     Ok::<_, Error>(web::Bytes::from(chunk.to_ascii_uppercase()))
+    ///                 })
+    ///             })
+    ///         })
+    ///     })
+    ///     .route("/", web::get().to(|| HttpResponse::Ok().body("text")));
+    /// # }
+    /// ```
+    pub fn transform_body<T>(
+        self,
+        transform: T,
+    ) -> ServiceResponse<TransformBody<ResponseBody<B>, T>>
+    where
+        B: MessageBody,
+        T: BodyTransform,
+    {
+        ServiceResponse {
+            response: self.response.transform_body(transform),
+            request: self.request,
+        }
+    }
 }
 
 impl<B> Into<Response<B>> for ServiceResponse<B> {
@@ -528,6 +581,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dev::BodySize;
     use crate::test::{init_service, TestRequest};
     use crate::{guard, http, web, App, HttpResponse};
     use actix_service::Service;
@@ -599,4 +653,22 @@ mod tests {
         assert!(s.contains("ServiceResponse"));
         assert!(s.contains("x-test"));
     }
+
+    #[actix_rt::test]
+    async fn test_transform_body() {
+        let res = HttpResponse::Ok()
+            .header(http::header::CONTENT_LENGTH, "4")
+            .body("text");
+        let upper = |chunk: web::Bytes| {
+            Ok::<_, Error>(web::Bytes::from(chunk.to_ascii_uppercase()))
+        };
+        let res = TestRequest::default()
+            .to_srv_response(res)
+            .transform_body(upper);
+        assert!(!res.headers().contains_key(http::header::CONTENT_LENGTH));
+        assert_eq!(res.response().body().size(), BodySize::Stream);
+
+        let body = crate::test::read_body(res).await;
+        assert_eq!(body, web::Bytes::from_static(b"TEXT"));
+    }
 }
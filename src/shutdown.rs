@@ -0,0 +1,402 @@
+//! Server shutdown hooks
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use actix_rt::time::timeout;
+use actix_server::Server;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{ready, select_all, Either, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use log::{info, warn};
+
+use crate::connections::ConnectionCounter;
+
+type Hook = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()>>;
+
+/// Stop request of `ServerHandle::stop()`, sender is notified once server
+/// is stopped
+type StopRequest = (bool, oneshot::Sender<()>);
+
+/// Server controlled by `ServerHandle`
+#[derive(Clone)]
+pub(crate) enum Running {
+    /// Actix server that accepts connections on bound sockets
+    Server(Server),
+    /// Server that only serves io streams on current arbiter
+    Streams {
+        stopped: ShutdownSignal,
+        counter: ConnectionCounter,
+        shutdown_timeout: Duration,
+    },
+}
+
+impl Running {
+    fn pause(&self) -> impl Future<Output = ()> {
+        match self {
+            Running::Server(srv) => Either::Left(srv.pause()),
+            Running::Streams { .. } => Either::Right(ready(())),
+        }
+    }
+
+    fn resume(&self) -> impl Future<Output = ()> {
+        match self {
+            Running::Server(srv) => Either::Left(srv.resume()),
+            Running::Streams { .. } => Either::Right(ready(())),
+        }
+    }
+
+    fn stop(&self, graceful: bool) -> LocalBoxFuture<'static, ()> {
+        match self {
+            Running::Server(srv) => srv.stop(graceful).boxed_local(),
+            Running::Streams {
+                stopped,
+                counter,
+                shutdown_timeout,
+            } => {
+                // streams are not served once server is stopped, graceful
+                // stop waits for their connections to drain
+                stopped.start();
+                let drain = counter.drain();
+                let shutdown_timeout = *shutdown_timeout;
+                async move {
+                    if graceful && timeout(shutdown_timeout, drain).await.is_err() {
+                        warn!("Connections did not drain in time");
+                    }
+                }
+                .boxed_local()
+            }
+        }
+    }
+
+    /// Future that resolves once server is stopped.
+    pub(crate) fn stopped(&self) -> LocalBoxFuture<'static, ()> {
+        match self {
+            Running::Server(srv) => srv.clone().map(|_| ()).boxed_local(),
+            Running::Streams { stopped, .. } => stopped.started().boxed_local(),
+        }
+    }
+}
+
+/// Handle of running http server, returned by `HttpServer::run()`.
+///
+/// Handle resolves once server is stopped. Server stopped with `stop()`
+/// runs shutdown hooks the same way as on stop signals, see
+/// `HttpServer::on_shutdown()`.
+pub struct ServerHandle {
+    srv: Running,
+    hooks: Option<mpsc::UnboundedSender<StopRequest>>,
+    stopped: Option<Started>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(
+        srv: Running,
+        hooks: Option<mpsc::UnboundedSender<StopRequest>>,
+    ) -> Self {
+        ServerHandle {
+            srv,
+            hooks,
+            stopped: None,
+        }
+    }
+
+    /// Pause accepting incoming connections.
+    ///
+    /// Io streams of `HttpServer::listen_with_stream()` are not paused.
+    pub fn pause(&self) -> impl Future<Output = ()> {
+        self.srv.pause()
+    }
+
+    /// Resume accepting incoming connections.
+    pub fn resume(&self) -> impl Future<Output = ()> {
+        self.srv.resume()
+    }
+
+    /// Stop incoming connection processing, stop all workers and exit.
+    ///
+    /// If graceful is true, server waits for workers to finish
+    /// in-flight requests. Shutdown hooks run before server stops.
+    pub fn stop(&self, graceful: bool) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        match self.hooks {
+            Some(ref hooks) if hooks.unbounded_send((graceful, tx)).is_ok() => {
+                Either::Left(rx.map(|_| ()))
+            }
+            // shutdown is already in progress or there are no hooks
+            _ => Either::Right(self.srv.stop(graceful)),
+        }
+    }
+}
+
+impl Clone for ServerHandle {
+    fn clone(&self) -> Self {
+        ServerHandle::new(self.srv.clone(), self.hooks.clone())
+    }
+}
+
+impl Future for ServerHandle {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_mut().get_mut();
+        match this.srv {
+            Running::Server(ref mut srv) => Pin::new(srv).poll(cx),
+            Running::Streams { ref stopped, .. } => {
+                let stopped = this.stopped.get_or_insert_with(|| stopped.started());
+                Pin::new(stopped).poll(cx).map(Ok)
+            }
+        }
+    }
+}
+
+/// Hooks registered with `HttpServer::on_shutdown()` and `HttpServer::on_drain()`
+#[derive(Default)]
+pub(crate) struct ShutdownHooks {
+    shutdown: Vec<Hook>,
+    drain: Vec<Hook>,
+    stop: Vec<LocalBoxFuture<'static, ()>>,
+}
+
+impl ShutdownHooks {
+    /// Stop server gracefully once future completes, as on `SIGTERM`.
+    pub(crate) fn stop_on<F>(&mut self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.stop.push(fut.boxed_local());
+    }
+
+    /// Returns true if there are no hooks or stop futures, server signals
+    /// are handled by actix server then.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shutdown.is_empty() && self.drain.is_empty() && self.stop.is_empty()
+    }
+
+    pub(crate) fn on_shutdown<F, R>(&mut self, f: F)
+    where
+        F: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.shutdown.push(Box::new(move || f().boxed_local()));
+    }
+
+    pub(crate) fn on_drain<F, R>(&mut self, f: F)
+    where
+        F: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.drain.push(Box::new(move || f().boxed_local()));
+    }
+
+    /// Wait for stop signal or stop request and shutdown server.
+    ///
+    /// Shutdown hooks run before server stops accepting connections, drain
+    /// hooks run once all connections are closed or shutdown timeout expires.
+    /// Shutdown timeout is a single deadline for shutdown hooks, connection
+    /// drain and workers stop, workers are stopped forcibly once it expires.
+    pub(crate) async fn run(
+        self,
+        srv: Running,
+        requests: mpsc::UnboundedReceiver<StopRequest>,
+        signals: bool,
+        counter: ConnectionCounter,
+        signal: ShutdownSignal,
+        shutdown_timeout: Duration,
+    ) {
+        let mut stop: Vec<LocalBoxFuture<'static, Option<StopCause>>> = self
+            .stop
+            .into_iter()
+            .map(|fut| fut.map(|_| Some((true, None))).boxed_local())
+            .collect();
+        if signals {
+            stop.push(
+                stop_signal()
+                    .map(|graceful| graceful.map(|graceful| (graceful, None)))
+                    .boxed_local(),
+            );
+        }
+        stop.push(
+            requests
+                .into_future()
+                .map(|(req, _)| req.map(|(graceful, tx)| (graceful, Some(tx))))
+                .boxed_local(),
+        );
+
+        // signal handler could fail and handles could be dropped, server
+        // is stopped by remaining sources then
+        let (mut graceful, completion) = loop {
+            let (cause, _, rest) = select_all(stop).await;
+            match cause {
+                Some(cause) => break cause,
+                None if rest.is_empty() => return,
+                None => stop = rest,
+            }
+        };
+        signal.start();
+        let deadline = Instant::now() + shutdown_timeout;
+
+        let shutdown = self.shutdown;
+        let hooks = async move {
+            for hook in shutdown {
+                hook().await;
+            }
+        };
+        if timeout(remaining(deadline), hooks).await.is_err() {
+            warn!("Shutdown hooks did not complete in time");
+            graceful = false;
+        }
+        srv.pause().await;
+
+        if graceful {
+            info!("Waiting for {} connections to drain", counter.count());
+            if timeout(remaining(deadline), counter.drain()).await.is_err() {
+                warn!("Connections did not drain in time");
+                graceful = false;
+            }
+        }
+
+        for hook in self.drain {
+            hook().await;
+        }
+        // connections are closed unless deadline expired, so graceful stop
+        // of workers does not wait for shutdown timeout once more
+        srv.stop(graceful).await;
+        if let Some(tx) = completion {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Graceful flag of stop and sender of stop request
+type StopCause = (bool, Option<oneshot::Sender<()>>);
+
+/// Time left until `deadline`
+fn remaining(deadline: Instant) -> Duration {
+    deadline.saturating_duration_since(Instant::now())
+}
+
+/// Shutdown state of server, it is shared by workers so in-flight requests
+/// are notified once shutdown begins.
+#[derive(Clone, Default)]
+pub(crate) struct ShutdownSignal(Arc<SignalInner>);
+
+#[derive(Default)]
+struct SignalInner {
+    started: AtomicBool,
+    next: AtomicUsize,
+    tasks: Mutex<HashMap<usize, Waker>>,
+}
+
+impl ShutdownSignal {
+    /// Returns true if server shutdown has begun.
+    pub(crate) fn is_started(&self) -> bool {
+        self.0.started.load(Ordering::Acquire)
+    }
+
+    /// Begin shutdown and wake up waiting tasks.
+    pub(crate) fn start(&self) {
+        let mut tasks = self.0.tasks.lock().unwrap();
+        self.0.started.store(true, Ordering::Release);
+        for (_, task) in tasks.drain() {
+            task.wake();
+        }
+    }
+
+    /// Allocate key for waiting task.
+    pub(crate) fn register(&self) -> usize {
+        self.0.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Check if shutdown has begun, task is woken up once it begins otherwise.
+    pub(crate) fn poll_started(&self, key: usize, cx: &mut Context<'_>) -> Poll<()> {
+        let mut tasks = self.0.tasks.lock().unwrap();
+        if self.is_started() {
+            Poll::Ready(())
+        } else {
+            tasks.insert(key, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Remove waiting task.
+    pub(crate) fn unregister(&self, key: usize) {
+        self.0.tasks.lock().unwrap().remove(&key);
+    }
+
+    /// Future that resolves once shutdown begins.
+    pub(crate) fn started(&self) -> Started {
+        Started {
+            key: self.register(),
+            signal: self.clone(),
+        }
+    }
+}
+
+/// Future of `ShutdownSignal::started()`
+pub(crate) struct Started {
+    signal: ShutdownSignal,
+    key: usize,
+}
+
+impl Future for Started {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.signal.poll_started(self.key, cx)
+    }
+}
+
+impl Drop for Started {
+    fn drop(&mut self) {
+        self.signal.unregister(self.key);
+    }
+}
+
+/// Wait for stop signal, returns true for graceful stop.
+///
+/// Signals are handled the same way as by actix server, SIGTERM
+/// stops server gracefully while SIGINT and SIGQUIT stop it immediately.
+#[cfg(unix)]
+async fn stop_signal() -> Option<bool> {
+    use actix_rt::signal::unix::{signal, SignalKind};
+    use log::error;
+
+    let kinds = [
+        (SignalKind::interrupt(), false),
+        (SignalKind::terminate(), true),
+        (SignalKind::quit(), false),
+    ];
+
+    let mut signals = Vec::new();
+    for (kind, graceful) in kinds.iter() {
+        match signal(*kind) {
+            Ok(stream) => signals.push((stream, *graceful)),
+            Err(e) => error!(
+                "Can not initialize stream handler for {:?} err: {}",
+                kind, e
+            ),
+        }
+    }
+    if signals.is_empty() {
+        return None;
+    }
+
+    let waiters = signals.iter_mut().map(|(stream, graceful)| {
+        let graceful = *graceful;
+        async move { stream.recv().await.map(|_| graceful) }.boxed_local()
+    });
+    let (graceful, _, _) = select_all(waiters).await;
+    graceful
+}
+
+/// Wait for stop signal, ctrl-c stops server immediately.
+#[cfg(not(unix))]
+async fn stop_signal() -> Option<bool> {
+    actix_rt::signal::ctrl_c().await.ok().map(|_| false)
+}
@@ -0,0 +1,353 @@
+//! Server-Sent Events
+//!
+//! Handler creates sender and response pair with `sse::channel()`, returns
+//! response and sends events with sender, i.e. from spawned task. Response
+//! is finished once all senders are dropped, sending fails once client is
+//! disconnected.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use actix_web::{web, App};
+//! use actix_web::sse::{self, Event, SseResponse};
+//!
+//! async fn events() -> SseResponse {
+//!     let (tx, res) = sse::channel();
+//!
+//!     actix_rt::spawn(async move {
+//!         for id in 0.. {
+//!             let event = Event::new(format!("tick {}", id))
+//!                 .event("tick")
+//!                 .id(id.to_string());
+//!             if tx.send(event).is_err() {
+//!                 // client is disconnected
+//!                 break;
+//!             }
+//!             actix_rt::time::delay_for(Duration::from_secs(1)).await;
+//!         }
+//!     });
+//!
+//!     res.keep_alive(Duration::from_secs(30))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/events", web::get().to(events));
+//! }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_http::{Error, Response};
+use actix_rt::time::{delay_for, Delay, Instant};
+use bytes::Bytes;
+use derive_more::Display;
+use futures::channel::mpsc;
+use futures::future::{ok, Ready};
+use futures::Stream;
+use serde::Serialize;
+
+use crate::dev::BodyEncoding;
+use crate::http::header::{self, ContentEncoding};
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Default interval of keep-alive comments
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Create sender and response pair for Server-Sent Events.
+pub fn channel() -> (SseSender, SseResponse) {
+    let (tx, rx) = mpsc::unbounded();
+    (
+        SseSender { tx },
+        SseResponse {
+            rx,
+            keep_alive: Some(KEEP_ALIVE),
+        },
+    )
+}
+
+/// Server-Sent Event
+///
+/// Data is sent in `data` field per line, so it could contain new lines.
+/// New lines are removed from event type and id.
+#[derive(Debug, Clone)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Create event with data.
+    pub fn new<S: Into<String>>(data: S) -> Event {
+        Event {
+            data: data.into(),
+            event: None,
+            id: None,
+            retry: None,
+        }
+    }
+
+    /// Create event with value serialized to json as data.
+    pub fn json<T: Serialize>(value: &T) -> Result<Event, serde_json::Error> {
+        Ok(Event::new(serde_json::to_string(value)?))
+    }
+
+    /// Set event type, clients dispatch events by type.
+    pub fn event<S: AsRef<str>>(mut self, event: S) -> Event {
+        self.event = Some(single_line(event.as_ref()));
+        self
+    }
+
+    /// Set event id, client sends last received id in `Last-Event-ID`
+    /// header on reconnection.
+    pub fn id<S: AsRef<str>>(mut self, id: S) -> Event {
+        self.id = Some(single_line(id.as_ref()));
+        self
+    }
+
+    /// Set reconnection time of client.
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut buf = String::with_capacity(self.data.len() + 16);
+        if let Some(ref event) = self.event {
+            buf.push_str("event: ");
+            buf.push_str(event);
+            buf.push('\n');
+        }
+        if let Some(ref id) = self.id {
+            buf.push_str("id: ");
+            buf.push_str(id);
+            buf.push('\n');
+        }
+        if let Some(retry) = self.retry {
+            buf.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        let data = self.data.replace("\r\n", "\n").replace('\r', "\n");
+        for line in data.split('\n') {
+            buf.push_str("data: ");
+            buf.push_str(line);
+            buf.push('\n');
+        }
+        buf.push('\n');
+        Bytes::from(buf)
+    }
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(|c| c == '\r' || c == '\n', "")
+}
+
+/// Error of sending event to disconnected client
+#[derive(Debug, Display)]
+#[display(fmt = "Client is disconnected")]
+pub struct Disconnected;
+
+impl std::error::Error for Disconnected {}
+
+/// Sender of Server-Sent Events
+#[derive(Debug, Clone)]
+pub struct SseSender {
+    tx: mpsc::UnboundedSender<Bytes>,
+}
+
+impl SseSender {
+    /// Send event to client.
+    ///
+    /// Fails if client is disconnected, and response is dropped.
+    pub fn send(&self, event: Event) -> Result<(), Disconnected> {
+        self.tx
+            .unbounded_send(event.encode())
+            .map_err(|_| Disconnected)
+    }
+
+    /// Send comment to client, clients ignore comments.
+    pub fn comment(&self, comment: &str) -> Result<(), Disconnected> {
+        let mut buf = String::with_capacity(comment.len() + 4);
+        for line in comment.lines() {
+            buf.push_str(": ");
+            buf.push_str(line);
+            buf.push('\n');
+        }
+        buf.push('\n');
+        self.tx
+            .unbounded_send(Bytes::from(buf))
+            .map_err(|_| Disconnected)
+    }
+
+    /// Check if client is disconnected.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// Server-Sent Events response
+///
+/// Response has `text/event-stream` content type and is not compressed,
+/// so events are delivered as soon as they are sent. Keep-alive comment is
+/// sent every 15 seconds if there are no events, so proxies do not close
+/// idle connection and disconnected clients are detected.
+pub struct SseResponse {
+    rx: mpsc::UnboundedReceiver<Bytes>,
+    keep_alive: Option<Duration>,
+}
+
+impl SseResponse {
+    /// Set interval of keep-alive comments.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Do not send keep-alive comments.
+    pub fn disable_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+}
+
+impl Responder for SseResponse {
+    type Error = Error;
+    type Future = Ready<Result<Response, Error>>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        let stream = SseStream {
+            rx: self.rx,
+            keep_alive: self
+                .keep_alive
+                .map(|interval| (interval, delay_for(interval))),
+        };
+        ok(Response::Ok()
+            .content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            // disable buffering of nginx
+            .header("x-accel-buffering", "no")
+            .encoding(ContentEncoding::Identity)
+            .streaming(stream))
+    }
+}
+
+struct SseStream {
+    rx: mpsc::UnboundedReceiver<Bytes>,
+    keep_alive: Option<(Duration, Delay)>,
+}
+
+impl Stream for SseStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.rx).poll_next(cx) {
+            Poll::Ready(Some(chunk)) => {
+                if let Some((interval, ref mut delay)) = this.keep_alive {
+                    delay.reset(Instant::now() + interval);
+                }
+                return Poll::Ready(Some(Ok(chunk)));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => (),
+        }
+
+        if let Some((interval, ref mut delay)) = this.keep_alive {
+            if Pin::new(&mut *delay).poll(cx).is_ready() {
+                delay.reset(Instant::now() + interval);
+                return Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{web, App};
+
+    #[test]
+    fn test_encode() {
+        let event = Event::new("line1\nline2")
+            .event("message\n")
+            .id("1")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            event.encode(),
+            Bytes::from_static(
+                b"event: message\nid: 1\nretry: 3000\ndata: line1\ndata: line2\n\n"
+            )
+        );
+        assert_eq!(
+            Event::json(&vec![1, 2]).unwrap().encode(),
+            Bytes::from_static(b"data: [1,2]\n\n")
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_sse_response() {
+        let mut srv = init_service(App::new().route(
+            "/",
+            web::get().to(|| async {
+                let (tx, res) = channel();
+                tx.comment("start").unwrap();
+                tx.send(Event::new("data")).unwrap();
+                res
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&mut srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        // response is finished when sender is dropped
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b": start\n\ndata: data\n\n")
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_keep_alive_and_disconnect() {
+        let (tx, res) = channel();
+        let req = TestRequest::default().to_http_request();
+        let mut resp = res
+            .keep_alive(Duration::from_millis(10))
+            .respond_to(&req)
+            .await
+            .unwrap();
+        let mut body = resp.take_body();
+
+        assert_eq!(
+            body.next().await.unwrap().unwrap(),
+            Bytes::from_static(b":\n\n")
+        );
+        tx.send(Event::new("1")).unwrap();
+        assert_eq!(
+            body.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"data: 1\n\n")
+        );
+
+        assert!(!tx.is_closed());
+        drop(body);
+        assert!(tx.is_closed());
+        assert!(tx.send(Event::new("2")).is_err());
+    }
+}
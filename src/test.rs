@@ -1,25 +1,35 @@
 //! Various helpers for Actix applications to use during testing.
+use std::cell::RefCell;
 use std::convert::TryFrom;
 use std::net::SocketAddr;
 use std::rc::Rc;
 use std::sync::mpsc;
-use std::{fmt, net, thread, time};
+use std::task::{Context, Poll};
+use std::{fmt, net, str, thread, time};
 
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
-use actix_http::http::header::{ContentType, Header, HeaderName, IntoHeaderValue};
-use actix_http::http::{Error as HttpError, Method, StatusCode, Uri, Version};
+use actix_http::http::header::{
+    ContentType, Header, HeaderName, IntoHeaderValue, CONTENT_TYPE, LOCATION,
+};
+use actix_http::http::{
+    Error as HttpError, HeaderMap, Method, StatusCode, Uri, Version,
+};
 use actix_http::test::TestRequest as HttpTestRequest;
 use actix_http::{cookie::Cookie, ws, Extensions, HttpService, Request};
 use actix_router::{Path, ResourceDef, Url};
-use actix_rt::{time::delay_for, System};
+use actix_rt::time::{delay_for, timeout};
+use actix_rt::System;
 use actix_service::{
     map_config, IntoService, IntoServiceFactory, Service, ServiceFactory,
 };
 use awc::error::PayloadError;
+use awc::multipart::Form;
 use awc::{Client, ClientRequest, ClientResponse, Connector};
 use bytes::{Bytes, BytesMut};
-use futures::future::ok;
+use futures::future::{ok, poll_fn};
+use futures::sink::SinkExt;
 use futures::stream::{Stream, StreamExt};
+use futures::task::noop_waker;
 use net2::TcpBuilder;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -261,6 +271,213 @@ where
         .unwrap_or_else(|_| panic!("read_response_json failed during deserialization"))
 }
 
+/// Application that is called in process, without server and sockets.
+///
+/// Requests are handled on current task, tests with `TestApp` neither bind
+/// ports nor start threads, so they are fast and can run in parallel.
+/// Response body, including streaming body, is read completely and errors
+/// of application are converted to responses the same way server does.
+///
+/// ```rust
+/// use actix_web::{test, web, App, HttpResponse, http::StatusCode};
+///
+/// #[actix_rt::test]
+/// async fn test_index() {
+///     let app = test::init_app(App::new().route(
+///         "/",
+///         web::get().to(|| async { HttpResponse::Ok().body("welcome!") }),
+///     ))
+///     .await;
+///
+///     let resp = app.call(test::TestRequest::get().uri("/").to_request()).await;
+///     assert_eq!(resp.status(), StatusCode::OK);
+///     assert_eq!(resp.text(), "welcome!");
+/// }
+/// ```
+pub struct TestApp<S> {
+    srv: Rc<RefCell<S>>,
+}
+
+/// Initialize application and wrap it into `TestApp`.
+pub async fn init_app<R, S, B>(
+    app: R,
+) -> TestApp<impl Service<Request = Request, Response = ServiceResponse<B>, Error = Error>>
+where
+    R: IntoServiceFactory<S>,
+    S: ServiceFactory<
+        Config = AppConfig,
+        Request = Request,
+        Response = ServiceResponse<B>,
+        Error = Error,
+    >,
+    S::InitError: std::fmt::Debug,
+    B: MessageBody,
+{
+    TestApp::new(init_service(app).await)
+}
+
+impl<S, B> TestApp<S>
+where
+    S: Service<Request = Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    /// Wrap initialized application service.
+    pub fn new(srv: S) -> Self {
+        TestApp {
+            srv: Rc::new(RefCell::new(srv)),
+        }
+    }
+
+    /// Call application and read complete response.
+    pub async fn call(&self, req: Request) -> TestResponse {
+        let srv = &self.srv;
+        poll_fn(|cx| srv.borrow_mut().poll_ready(cx))
+            .await
+            .unwrap_or_else(|_| panic!("TestApp failed at readiness check"));
+        let fut = srv.borrow_mut().call(req);
+
+        match fut.await {
+            Ok(res) => TestResponse::from_response(res).await,
+            Err(e) => {
+                let mut res: HttpResponse = e.into();
+                let body = res.take_body();
+                TestResponse::read(res.status(), res.headers().clone(), body).await
+            }
+        }
+    }
+}
+
+impl<S> Clone for TestApp<S> {
+    fn clone(&self) -> Self {
+        TestApp {
+            srv: self.srv.clone(),
+        }
+    }
+}
+
+/// Response of `TestApp` with complete body.
+///
+/// Assertion methods panic with description of mismatch and return
+/// response, so they can be chained.
+///
+/// ```rust
+/// use actix_web::{test, web, App, HttpResponse, http::header, http::StatusCode};
+///
+/// #[actix_rt::test]
+/// async fn test_index() {
+///     let mut app = test::init_service(App::new().route(
+///         "/",
+///         web::get().to(|| async {
+///             HttpResponse::Found().header(header::LOCATION, "/login").finish()
+///         }),
+///     ))
+///     .await;
+///
+///     let req = test::TestRequest::get().uri("/").to_request();
+///     let resp = test::call_service(&mut app, req).await;
+///     test::TestResponse::from_response(resp)
+///         .await
+///         .assert_status(StatusCode::FOUND)
+///         .assert_redirect("/login");
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    async fn read<S>(status: StatusCode, headers: HeaderMap, mut body: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, Error>> + Unpin,
+    {
+        let mut bytes = BytesMut::new();
+        while let Some(item) = body.next().await {
+            bytes.extend_from_slice(&item.unwrap());
+        }
+        TestResponse {
+            status,
+            headers,
+            body: bytes.freeze(),
+        }
+    }
+
+    /// Read complete body of service response
+    pub async fn from_response<B: MessageBody>(mut res: ServiceResponse<B>) -> Self {
+        let body = res.take_body();
+        TestResponse::read(res.status(), res.headers().clone(), body).await
+    }
+
+    /// Response status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Response headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Response body
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Response body as text, panics if body is not valid utf-8
+    pub fn text(&self) -> &str {
+        str::from_utf8(&self.body)
+            .unwrap_or_else(|_| panic!("TestResponse body is not valid utf-8"))
+    }
+
+    /// Deserialize json body, panics if body is not valid json for `T`
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|_| panic!("TestResponse json deserialization failed"))
+    }
+
+    /// Assert response status code
+    pub fn assert_status(&self, status: StatusCode) -> &Self {
+        assert_eq!(
+            self.status, status,
+            "Unexpected response status, body: {:?}",
+            self.body
+        );
+        self
+    }
+
+    /// Assert that response has header with `value`
+    pub fn assert_header<K>(&self, key: K, value: &str) -> &Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        let name =
+            HeaderName::try_from(key).unwrap_or_else(|_| panic!("Invalid header name"));
+        match self.headers.get(&name) {
+            Some(val) => assert_eq!(
+                val.to_str().ok(),
+                Some(value),
+                "Unexpected value of {} header",
+                name
+            ),
+            None => panic!("Response has no {} header", name),
+        }
+        self
+    }
+
+    /// Assert that response redirects to `location`
+    pub fn assert_redirect(&self, location: &str) -> &Self {
+        assert!(
+            self.status.is_redirection(),
+            "Response status {} is not redirection",
+            self.status
+        );
+        self.assert_header(LOCATION, location)
+    }
+}
+
 /// Test `Request` builder.
 ///
 /// For unit testing, actix provides a request builder type and a simple handler runner. TestRequest implements a builder-like pattern.
@@ -443,6 +660,45 @@ impl TestRequest {
         self
     }
 
+    /// Set `multipart/form-data` body of `form` as the request payload. The `Content-Type`
+    /// header is set to `multipart/form-data` with boundary of form.
+    ///
+    /// Contents of parts have to be available without waiting, i.e. text, bytes or
+    /// in-memory streams, this method panics if a part is not ready or fails.
+    ///
+    /// ```rust
+    /// use actix_web::client::multipart::{Form, Part};
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::post()
+    ///     .set_multipart(
+    ///         Form::new()
+    ///             .text("title", "report")
+    ///             .part("file", Part::bytes("col1,col2\n").file_name("report.csv")),
+    ///     )
+    ///     .to_request();
+    /// ```
+    pub fn set_multipart(mut self, form: Form) -> Self {
+        let content_type = form.content_type();
+        let mut body = form.into_body();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut buf = BytesMut::new();
+        loop {
+            match body.poll_next(&mut cx) {
+                Poll::Ready(Some(Ok(chunk))) => buf.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(e))) => {
+                    panic!("Failed to read multipart test data: {}", e)
+                }
+                Poll::Ready(None) => break,
+                Poll::Pending => panic!("Multipart test data is not ready"),
+            }
+        }
+        self.req.set_payload(buf.freeze());
+        self.req.header(CONTENT_TYPE, content_type);
+        self
+    }
+
     /// Set application data. This is equivalent of `App::data()` method
     /// for testing purpose.
     pub fn data<T: 'static>(mut self, data: T) -> Self {
@@ -484,7 +740,7 @@ impl TestRequest {
             Rc::new(self.rmap),
             self.config.clone(),
             Rc::new(self.app_data),
-            HttpRequestPool::create(),
+            HttpRequestPool::create(self.config.request_pool().clone()),
         ))
     }
 
@@ -506,7 +762,7 @@ impl TestRequest {
             Rc::new(self.rmap),
             self.config.clone(),
             Rc::new(self.app_data),
-            HttpRequestPool::create(),
+            HttpRequestPool::create(self.config.request_pool().clone()),
         )
     }
 
@@ -523,7 +779,7 @@ impl TestRequest {
             Rc::new(self.rmap),
             self.config.clone(),
             Rc::new(self.app_data),
-            HttpRequestPool::create(),
+            HttpRequestPool::create(self.config.request_pool().clone()),
         );
 
         (req, payload)
@@ -608,6 +864,12 @@ where
 {
     let (tx, rx) = mpsc::channel();
 
+    // cleartext http/2 server is accessed with prior knowledge
+    let h2c = match (&cfg.stream, &cfg.tp) {
+        (StreamType::Tcp, HttpVer::Http2) => true,
+        (StreamType::Tcp, HttpVer::Both) => cfg.h2_prior_knowledge,
+        _ => false,
+    };
     let ssl = match cfg.stream {
         StreamType::Tcp => false,
         #[cfg(feature = "openssl")]
@@ -629,26 +891,39 @@ where
         let srv = match cfg.stream {
             StreamType::Tcp => match cfg.tp {
                 HttpVer::Http1 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(false, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        false,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h1(map_config(factory(), move |_| cfg.clone()))
                         .tcp()
                 }),
                 HttpVer::Http2 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(false, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        false,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h2(map_config(factory(), move |_| cfg.clone()))
                         .tcp()
                 }),
                 HttpVer::Both => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(false, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        false,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
+                        .h2c(true)
                         .finish(map_config(factory(), move |_| cfg.clone()))
                         .tcp()
                 }),
@@ -656,24 +931,36 @@ where
             #[cfg(feature = "openssl")]
             StreamType::Openssl(acceptor) => match cfg.tp {
                 HttpVer::Http1 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h1(map_config(factory(), move |_| cfg.clone()))
                         .openssl(acceptor.clone())
                 }),
                 HttpVer::Http2 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h2(map_config(factory(), move |_| cfg.clone()))
                         .openssl(acceptor.clone())
                 }),
                 HttpVer::Both => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .finish(map_config(factory(), move |_| cfg.clone()))
@@ -683,24 +970,36 @@ where
             #[cfg(feature = "rustls")]
             StreamType::Rustls(config) => match cfg.tp {
                 HttpVer::Http1 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h1(map_config(factory(), move |_| cfg.clone()))
                         .rustls(config.clone())
                 }),
                 HttpVer::Http2 => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .h2(map_config(factory(), move |_| cfg.clone()))
                         .rustls(config.clone())
                 }),
                 HttpVer::Both => builder.listen("test", tcp, move || {
-                    let cfg =
-                        AppConfig::new(true, local_addr, format!("{}", local_addr));
+                    let cfg = AppConfig::new(
+                        true,
+                        local_addr,
+                        format!("{}", local_addr),
+                        None,
+                    );
                     HttpService::build()
                         .client_timeout(ctimeout)
                         .finish(map_config(factory(), move |_| cfg.clone()))
@@ -732,18 +1031,21 @@ where
                     .conn_lifetime(time::Duration::from_secs(0))
                     .timeout(time::Duration::from_millis(30000))
                     .ssl(builder.build())
-                    .finish()
             }
             #[cfg(not(feature = "openssl"))]
             {
                 Connector::new()
                     .conn_lifetime(time::Duration::from_secs(0))
                     .timeout(time::Duration::from_millis(30000))
-                    .finish()
             }
         };
+        let connector = if h2c {
+            connector.h2_prior_knowledge()
+        } else {
+            connector
+        };
 
-        Client::build().connector(connector).finish()
+        Client::build().connector(connector.finish()).finish()
     };
 
     TestServer {
@@ -760,6 +1062,7 @@ pub struct TestServerConfig {
     tp: HttpVer,
     stream: StreamType,
     client_timeout: u64,
+    h2_prior_knowledge: bool,
 }
 
 #[derive(Clone)]
@@ -796,6 +1099,7 @@ impl TestServerConfig {
             tp: HttpVer::Both,
             stream: StreamType::Tcp,
             client_timeout: 5000,
+            h2_prior_knowledge: false,
         }
     }
 
@@ -806,11 +1110,23 @@ impl TestServerConfig {
     }
 
     /// Start http/2 server only
+    ///
+    /// Without tls client of test server connects with http/2 prior
+    /// knowledge.
     pub fn h2(mut self) -> Self {
         self.tp = HttpVer::Http2;
         self
     }
 
+    /// Use http/2 prior knowledge in client of test server.
+    ///
+    /// Server without tls accepts both http/1.1 and cleartext http/2
+    /// connections, this setting makes client connect with http/2.
+    pub fn h2_prior_knowledge(mut self) -> Self {
+        self.h2_prior_knowledge = true;
+        self
+    }
+
     /// Start openssl server
     #[cfg(feature = "openssl")]
     pub fn openssl(mut self, acceptor: open_ssl::ssl::SslAcceptor) -> Self {
@@ -818,6 +1134,14 @@ impl TestServerConfig {
         self
     }
 
+    /// Start openssl server with generated self-signed certificate for
+    /// `localhost`, protocol is negotiated with ALPN and http/2 is
+    /// preferred.
+    #[cfg(feature = "openssl")]
+    pub fn openssl_self_signed(self) -> Self {
+        self.openssl(self_signed_acceptor())
+    }
+
     /// Start rustls server
     #[cfg(feature = "rustls")]
     pub fn rustls(mut self, config: rust_tls::ServerConfig) -> Self {
@@ -832,6 +1156,60 @@ impl TestServerConfig {
     }
 }
 
+#[cfg(feature = "openssl")]
+/// Create `SslAcceptor` with self-signed certificate
+fn self_signed_acceptor() -> open_ssl::ssl::SslAcceptor {
+    use open_ssl::asn1::Asn1Time;
+    use open_ssl::bn::{BigNum, MsbOption};
+    use open_ssl::ec::{EcGroup, EcKey};
+    use open_ssl::hash::MessageDigest;
+    use open_ssl::nid::Nid;
+    use open_ssl::pkey::PKey;
+    use open_ssl::ssl::{AlpnError, SslAcceptor, SslMethod};
+    use open_ssl::x509::{X509NameBuilder, X509};
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "localhost")
+        .unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    let cert = cert.build();
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key(&key).unwrap();
+    builder.set_certificate(&cert).unwrap();
+    builder.set_alpn_select_callback(|_, protos| {
+        const H2: &[u8] = b"\x02h2";
+        const H11: &[u8] = b"\x08http/1.1";
+        if protos.windows(3).any(|window| window == H2) {
+            Ok(b"h2")
+        } else if protos.windows(9).any(|window| window == H11) {
+            Ok(b"http/1.1")
+        } else {
+            Err(AlpnError::NOACK)
+        }
+    });
+    builder.build()
+}
+
 /// Get first available unused address
 pub fn unused_addr() -> net::SocketAddr {
     let addr: net::SocketAddr = "127.0.0.1:0".parse().unwrap();
@@ -937,6 +1315,23 @@ impl TestServer {
         self.ws_at("/").await
     }
 
+    /// Connect to websocket server at a given path, see `TestWsClient`
+    pub async fn ws_client_at(
+        &mut self,
+        path: &str,
+    ) -> Result<TestWsClient<impl AsyncRead + AsyncWrite>, awc::error::WsClientError>
+    {
+        self.ws_at(path).await.map(TestWsClient::new)
+    }
+
+    /// Connect to a websocket server, see `TestWsClient`
+    pub async fn ws_client(
+        &mut self,
+    ) -> Result<TestWsClient<impl AsyncRead + AsyncWrite>, awc::error::WsClientError>
+    {
+        self.ws_client_at("/").await
+    }
+
     /// Gracefully stop http server
     pub async fn stop(self) {
         self.server.stop(true).await;
@@ -951,6 +1346,149 @@ impl Drop for TestServer {
     }
 }
 
+/// Websocket client for tests
+///
+/// Every step, i.e. sending message or waiting for frame, panics if it
+/// does not complete within timeout, 5 seconds by default, so misbehaving
+/// server fails test instead of blocking it forever. Frames are not
+/// answered automatically, ping frames of server heartbeat are answered
+/// with `expect_ping()`.
+///
+/// ```rust,no_run
+/// use actix_http::ws;
+/// use actix_web::test::TestServer;
+///
+/// // `srv` serves websocket echo service
+/// async fn test_echo(mut srv: TestServer) {
+///     let mut client = srv.ws_client().await.unwrap();
+///     client.send(ws::Message::Text("text".to_string())).await;
+///     client.expect_text("text").await;
+///     client.ping(b"hb").await;
+///     client.close(Some(ws::CloseCode::Normal.into())).await;
+/// }
+/// ```
+pub struct TestWsClient<T> {
+    framed: Framed<T, ws::Codec>,
+    timeout: time::Duration,
+}
+
+impl<T> TestWsClient<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Create client for websocket connection
+    pub fn new(framed: Framed<T, ws::Codec>) -> Self {
+        TestWsClient {
+            framed,
+            timeout: time::Duration::from_secs(5),
+        }
+    }
+
+    /// Set timeout of every step
+    pub fn timeout(mut self, timeout: time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Websocket connection
+    pub fn get_mut(&mut self) -> &mut Framed<T, ws::Codec> {
+        &mut self.framed
+    }
+
+    /// Convert to websocket connection
+    pub fn into_inner(self) -> Framed<T, ws::Codec> {
+        self.framed
+    }
+
+    /// Send message
+    pub async fn send(&mut self, msg: ws::Message) {
+        match timeout(self.timeout, self.framed.send(msg)).await {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => panic!("Failed to send websocket message: {:?}", e),
+            Err(_) => panic!("Timeout while sending websocket message"),
+        }
+    }
+
+    /// Wait for next frame
+    pub async fn next(&mut self) -> ws::Frame {
+        match timeout(self.timeout, self.framed.next()).await {
+            Ok(Some(Ok(frame))) => frame,
+            Ok(Some(Err(e))) => panic!("Websocket protocol error: {:?}", e),
+            Ok(None) => panic!("Websocket connection is closed"),
+            Err(_) => panic!("Timeout while waiting for websocket frame"),
+        }
+    }
+
+    /// Wait for next frame and assert that it is equal to `frame`
+    pub async fn expect(&mut self, frame: ws::Frame) {
+        assert_eq!(self.next().await, frame);
+    }
+
+    /// Wait for next frame and assert that it is text frame with `text`
+    pub async fn expect_text(&mut self, text: &str) {
+        self.expect(ws::Frame::Text(Bytes::copy_from_slice(text.as_bytes())))
+            .await
+    }
+
+    /// Wait for next frame and assert that it is binary frame with `data`
+    pub async fn expect_binary<B: Into<Bytes>>(&mut self, data: B) {
+        self.expect(ws::Frame::Binary(data.into())).await
+    }
+
+    /// Wait for ping frame of server and answer it with pong, payload of
+    /// ping is returned
+    pub async fn expect_ping(&mut self) -> Bytes {
+        match self.next().await {
+            ws::Frame::Ping(data) => {
+                self.send(ws::Message::Pong(data.clone())).await;
+                data
+            }
+            frame => panic!("Expected ping frame, got {:?}", frame),
+        }
+    }
+
+    /// Send ping and wait for pong with same payload
+    pub async fn ping(&mut self, data: &[u8]) {
+        let data = Bytes::copy_from_slice(data);
+        self.send(ws::Message::Ping(data.clone())).await;
+        self.expect(ws::Frame::Pong(data)).await
+    }
+
+    /// Wait for next frame and assert that it is close frame with `code`,
+    /// description of close reason is returned
+    pub async fn expect_close(&mut self, code: ws::CloseCode) -> Option<String> {
+        match self.next().await {
+            ws::Frame::Close(Some(reason)) => {
+                assert_eq!(reason.code, code);
+                reason.description
+            }
+            frame => panic!("Expected close frame with {:?}, got {:?}", code, frame),
+        }
+    }
+
+    /// Send close message and wait for close frame of server, reason of
+    /// server is returned
+    pub async fn close(
+        &mut self,
+        reason: Option<ws::CloseReason>,
+    ) -> Option<ws::CloseReason> {
+        self.send(ws::Message::Close(reason)).await;
+        match self.next().await {
+            ws::Frame::Close(reason) => reason,
+            frame => panic!("Expected close frame, got {:?}", frame),
+        }
+    }
+
+    /// Wait until server closes connection
+    pub async fn expect_disconnect(&mut self) {
+        match timeout(self.timeout, self.framed.next()).await {
+            Ok(None) | Ok(Some(Err(_))) => (),
+            Ok(Some(Ok(frame))) => panic!("Expected disconnect, got {:?}", frame),
+            Err(_) => panic!("Timeout while waiting for disconnect"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_http::httpmessage::HttpMessage;
@@ -961,6 +1499,90 @@ mod tests {
     use super::*;
     use crate::{http::header, web, App, HttpResponse, Responder};
 
+    #[actix_rt::test]
+    async fn test_app() {
+        let app = init_app(
+            App::new()
+                .route(
+                    "/stream",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().streaming(futures::stream::iter(vec![
+                            Ok::<_, Error>(Bytes::from_static(b"hello ")),
+                            Ok(Bytes::from_static(b"world")),
+                        ]))
+                    }),
+                )
+                .route(
+                    "/json",
+                    web::get().to(|| async { HttpResponse::Ok().json(vec![1, 2]) }),
+                )
+                .route(
+                    "/error",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(crate::error::ErrorConflict("conflict"))
+                    }),
+                ),
+        )
+        .await;
+
+        let resp = app
+            .call(TestRequest::with_uri("/stream").to_request())
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text(), "hello world");
+
+        // clones share application
+        let resp = app
+            .clone()
+            .call(TestRequest::with_uri("/json").to_request())
+            .await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(resp.json::<Vec<u32>>(), vec![1, 2]);
+
+        let resp = app.call(TestRequest::with_uri("/error").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(resp.body(), &Bytes::from_static(b"conflict"));
+
+        let resp = app.call(TestRequest::with_uri("/none").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_response_assertions() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut app = init_service(App::new().route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::SeeOther()
+                    .header(header::LOCATION, "/login")
+                    .header("x-test", "value")
+                    .body("redirect")
+            }),
+        ))
+        .await;
+
+        let resp = call_service(&mut app, TestRequest::get().to_request()).await;
+        let resp = TestResponse::from_response(resp).await;
+        resp.assert_status(StatusCode::SEE_OTHER)
+            .assert_header(header::LOCATION, "/login")
+            .assert_header("x-test", "value")
+            .assert_redirect("/login");
+        assert_eq!(resp.text(), "redirect");
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            resp.assert_status(StatusCode::OK);
+        }));
+        assert!(res.is_err());
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            resp.assert_header("x-missing", "value");
+        }));
+        assert!(res.is_err());
+    }
+
     #[actix_rt::test]
     async fn test_basics() {
         let req = TestRequest::with_hdr(header::ContentType::json())
@@ -1096,6 +1718,31 @@ mod tests {
         assert_eq!(&result.name, "User name");
     }
 
+    #[actix_rt::test]
+    async fn test_request_multipart() {
+        use crate::FromRequest;
+        use awc::multipart::Part;
+
+        let form = Form::new()
+            .text("title", "report")
+            .part("file", Part::bytes("data").file_name("a.txt"));
+        let boundary = form.boundary().to_owned();
+        let (req, mut pl) = TestRequest::post().set_multipart(form).to_http_parts();
+        assert_eq!(
+            req.headers().get(header::CONTENT_TYPE).unwrap(),
+            format!("multipart/form-data; boundary={}", boundary).as_str()
+        );
+
+        let body = Bytes::from_request(&req, &mut pl).await.unwrap();
+        let expected = format!(
+            "--{b}\r\ncontent-disposition: form-data; name=\"title\"\r\n\r\nreport\r\n\
+             --{b}\r\ncontent-disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             content-type: application/octet-stream\r\n\r\ndata\r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(body, Bytes::from(expected));
+    }
+
     #[actix_rt::test]
     async fn test_request_response_json() {
         let mut app = init_service(App::new().service(web::resource("/people").route(
@@ -1206,4 +1853,40 @@ mod tests {
         let res = app.call(req).await.unwrap();
         assert!(res.status().is_success());
     }
+
+    async fn version(req: HttpRequest) -> HttpResponse {
+        HttpResponse::Ok().body(format!("{:?}", req.version()))
+    }
+
+    #[actix_rt::test]
+    async fn test_h2_prior_knowledge() {
+        let srv = start_with(config().h2(), || {
+            App::new().service(web::resource("/").to(version))
+        });
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.version(), Version::HTTP_2);
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"HTTP/2.0"));
+
+        let srv = start_with(config().h2_prior_knowledge(), || {
+            App::new().service(web::resource("/").to(version))
+        });
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"HTTP/2.0"));
+
+        let srv = start(|| App::new().service(web::resource("/").to(version)));
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"HTTP/1.1"));
+    }
+
+    #[cfg(feature = "openssl")]
+    #[actix_rt::test]
+    async fn test_h2_self_signed() {
+        let srv = start_with(config().openssl_self_signed(), || {
+            App::new().service(web::resource("/").to(version))
+        });
+        assert!(srv.url("/").starts_with("https://"));
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.version(), Version::HTTP_2);
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"HTTP/2.0"));
+    }
 }
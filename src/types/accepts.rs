@@ -0,0 +1,438 @@
+//! Content negotiation
+use std::fmt;
+
+use actix_http::{Error, Response};
+use futures::future::{ok, Ready};
+use mime::Mime;
+
+use crate::dev::Payload;
+use crate::extract::FromRequest;
+use crate::http::header::{self, q, Quality, QualityItem};
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Media ranges of request's `Accept` header, ordered by quality.
+///
+/// Missing header means that any media type is acceptable, media ranges
+/// that can not be parsed are ignored.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn index(accepts: web::Accepts) -> HttpResponse {
+///     match accepts.negotiate(&["application/json", "text/html"]) {
+///         Some("application/json") => HttpResponse::Ok().json("Hello!"),
+///         Some(_) => HttpResponse::Ok()
+///             .content_type("text/html")
+///             .body("<p>Hello!</p>"),
+///         None => HttpResponse::NotAcceptable().finish(),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accepts(Vec<QualityItem<Mime>>);
+
+impl Accepts {
+    /// Parse `Accept` headers of the request
+    pub fn from_req(req: &HttpRequest) -> Self {
+        let mut items: Vec<QualityItem<Mime>> = req
+            .headers()
+            .get_all(&header::ACCEPT)
+            .filter_map(|val| val.to_str().ok())
+            .flat_map(|val| val.split(','))
+            .filter_map(|item| item.trim().parse().ok())
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.quality));
+        Accepts(items)
+    }
+
+    /// Media ranges ordered from the most preferred
+    pub fn items(&self) -> &[QualityItem<Mime>] {
+        &self.0
+    }
+
+    /// Quality of media type, `None` if it is not acceptable.
+    ///
+    /// Quality is taken from the most specific matching media range.
+    pub fn quality(&self, media_type: &Mime) -> Option<Quality> {
+        if self.0.is_empty() {
+            return Some(q(1000));
+        }
+
+        let mut best = None;
+        for item in &self.0 {
+            let range = &item.item;
+            let specificity = if range.type_() == mime::STAR {
+                1
+            } else if range.type_() != media_type.type_() {
+                continue;
+            } else if range.subtype() == mime::STAR {
+                2
+            } else if range.subtype() != media_type.subtype() {
+                continue;
+            } else if range.params().next().is_none() {
+                3
+            } else if range.params().all(|(name, val)| {
+                media_type
+                    .params()
+                    .any(|(n, v)| n == name && v.as_str() == val.as_str())
+            }) {
+                4
+            } else {
+                continue;
+            };
+
+            match best {
+                Some((spec, _)) if spec >= specificity => (),
+                _ => best = Some((specificity, item.quality)),
+            }
+        }
+
+        best.map(|(_, quality)| quality)
+            .filter(|quality| *quality > q(0))
+    }
+
+    /// Select the most preferred of offered media types.
+    ///
+    /// Offers with the same quality are preferred in the order they are
+    /// passed, `None` is returned if none of them is acceptable.
+    pub fn negotiate<'a>(&self, offered: &[&'a str]) -> Option<&'a str> {
+        let mut best: Option<(&'a str, Quality)> = None;
+        for &media_type in offered {
+            let quality = match media_type.parse().ok().and_then(|m| self.quality(&m)) {
+                Some(quality) => quality,
+                None => continue,
+            };
+            match best {
+                Some((_, prev)) if prev >= quality => (),
+                _ => best = Some((media_type, quality)),
+            }
+        }
+        best.map(|(media_type, _)| media_type)
+    }
+}
+
+impl FromRequest for Accepts {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(Accepts::from_req(req))
+    }
+}
+
+/// Language ranges of request's `Accept-Language` header, ordered by quality.
+///
+/// Ranges are lowercased, ranges that are not well formed are ignored.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// async fn index(languages: web::Languages) -> &'static str {
+///     match languages.preferred(&["en", "de", "fr"]) {
+///         Some("de") => "Hallo!",
+///         Some("fr") => "Bonjour!",
+///         _ => "Hello!",
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Languages(Vec<QualityItem<String>>);
+
+impl Languages {
+    /// Parse `Accept-Language` headers of the request
+    pub fn from_req(req: &HttpRequest) -> Self {
+        let mut items: Vec<QualityItem<String>> = req
+            .headers()
+            .get_all(&header::ACCEPT_LANGUAGE)
+            .filter_map(|val| val.to_str().ok())
+            .flat_map(|val| val.split(','))
+            .filter_map(|item| item.trim().parse::<QualityItem<String>>().ok())
+            .filter(|item| is_language_range(&item.item))
+            .map(|item| QualityItem::new(item.item.to_ascii_lowercase(), item.quality))
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.quality));
+        Languages(items)
+    }
+
+    /// Language ranges ordered from the most preferred
+    pub fn items(&self) -> &[QualityItem<String>] {
+        &self.0
+    }
+
+    /// Select the most preferred of supported language tags.
+    ///
+    /// Range matches tags that are equal or start with range followed by
+    /// `-`, `*` matches any tag. If none of tags matches, range is shortened
+    /// by removing last subtag, so `en-US` falls back to `en`, before the
+    /// next range is tried. Tags matched by ranges with quality 0 are not
+    /// selected. If header is missing, the first supported tag is returned.
+    pub fn preferred<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        if self.0.is_empty() {
+            return supported.first().cloned();
+        }
+
+        let excluded = |tag: &str| {
+            self.0
+                .iter()
+                .any(|item| item.quality == q(0) && language_matches(&item.item, tag))
+        };
+
+        for item in self.0.iter().filter(|item| item.quality > q(0)) {
+            let mut range = item.item.as_str();
+            loop {
+                let found = supported
+                    .iter()
+                    .cloned()
+                    .find(|tag| language_matches(range, tag) && !excluded(tag));
+                if let Some(tag) = found {
+                    return Some(tag);
+                }
+                match range.rfind('-') {
+                    Some(idx) => range = &range[..idx],
+                    None => break,
+                }
+            }
+        }
+        None
+    }
+}
+
+fn is_language_range(range: &str) -> bool {
+    range == "*"
+        || (!range.is_empty()
+            && range.split('-').all(|tag| {
+                !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric())
+            }))
+}
+
+fn language_matches(range: &str, tag: &str) -> bool {
+    let (range, tag) = (range.as_bytes(), tag.as_bytes());
+    range == b"*"
+        || (tag.len() >= range.len()
+            && tag[..range.len()].eq_ignore_ascii_case(range)
+            && (tag.len() == range.len() || tag[range.len()] == b'-'))
+}
+
+impl FromRequest for Languages {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(Languages::from_req(req))
+    }
+}
+
+/// Responder that renders representation selected by request's `Accept` header.
+///
+/// `Content-Type` of response is set to the selected media type, unless it
+/// is set by representation. If none of representations is acceptable,
+/// `406 Not Acceptable` response is returned.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse, Responder};
+///
+/// async fn index() -> impl Responder {
+///     web::Negotiated::new()
+///         .with("application/json", || HttpResponse::Ok().json("Hello!"))
+///         .with("text/html", || HttpResponse::Ok().body("<p>Hello!</p>"))
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Default)]
+pub struct Negotiated {
+    offers: Vec<(&'static str, Box<dyn FnOnce() -> Response>)>,
+}
+
+impl Negotiated {
+    /// Create responder without representations
+    pub fn new() -> Self {
+        Negotiated::default()
+    }
+
+    /// Add representation of media type, rendered by `f` if it is selected.
+    pub fn with<F, R>(mut self, media_type: &'static str, f: F) -> Self
+    where
+        F: FnOnce() -> R + 'static,
+        R: Into<Response>,
+    {
+        self.offers.push((media_type, Box::new(move || f().into())));
+        self
+    }
+}
+
+impl fmt::Debug for Negotiated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offers: Vec<_> = self
+            .offers
+            .iter()
+            .map(|(media_type, _)| media_type)
+            .collect();
+        f.debug_struct("Negotiated")
+            .field("offers", &offers)
+            .finish()
+    }
+}
+
+impl Responder for Negotiated {
+    type Error = Error;
+    type Future = Ready<Result<Response, Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let offered: Vec<_> = self
+            .offers
+            .iter()
+            .map(|(media_type, _)| *media_type)
+            .collect();
+        let selected = match Accepts::from_req(req).negotiate(&offered) {
+            Some(media_type) => media_type,
+            None => return ok(Response::NotAcceptable().finish()),
+        };
+
+        let (media_type, f) = self
+            .offers
+            .into_iter()
+            .find(|(media_type, _)| *media_type == selected)
+            .unwrap();
+        let mut res = f();
+        if !res.headers().contains_key(&header::CONTENT_TYPE) {
+            res.headers_mut().insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static(media_type),
+            );
+        }
+        ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::responder::tests::BodyTest;
+    use crate::test::TestRequest;
+
+    fn accepts(val: &str) -> Accepts {
+        Accepts::from_req(
+            &TestRequest::with_header(header::ACCEPT, val).to_http_request(),
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_accepts() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let a = Accepts::from_request(&req, &mut pl).await.unwrap();
+        assert!(a.items().is_empty());
+        assert_eq!(a.negotiate(&["text/html", "text/plain"]), Some("text/html"));
+
+        let a = accepts("text/html;q=0.5, application/json, bad, */*;q=0.1");
+        assert_eq!(a.items().len(), 3);
+        assert_eq!(a.items()[0].item, mime::APPLICATION_JSON);
+        assert_eq!(a.quality(&mime::TEXT_HTML), Some(q(500)));
+        assert_eq!(a.quality(&mime::IMAGE_PNG), Some(q(100)));
+        assert_eq!(
+            a.negotiate(&["text/html", "application/json"]),
+            Some("application/json")
+        );
+        assert_eq!(a.negotiate(&["image/png", "text/html"]), Some("text/html"));
+
+        let a = accepts("text/*, text/plain;q=0, text/html;level=1;q=0.2");
+        assert_eq!(a.quality(&mime::TEXT_PLAIN), None);
+        assert_eq!(a.quality(&mime::TEXT_HTML), Some(q(1000)));
+        assert_eq!(
+            a.quality(&"text/html;level=1".parse().unwrap()),
+            Some(q(200))
+        );
+        assert_eq!(a.negotiate(&["text/plain", "application/json"]), None);
+        assert_eq!(a.negotiate(&["text/plain", "text/css"]), Some("text/css"));
+    }
+
+    #[actix_rt::test]
+    async fn test_languages() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let l = Languages::from_request(&req, &mut pl).await.unwrap();
+        assert!(l.items().is_empty());
+        assert_eq!(l.preferred(&["en", "de"]), Some("en"));
+
+        let languages = |val| {
+            Languages::from_req(
+                &TestRequest::with_header(header::ACCEPT_LANGUAGE, val)
+                    .to_http_request(),
+            )
+        };
+
+        let l = languages("da, en-GB;q=0.8, en;q=0.7, b@d");
+        let ranges: Vec<_> = l.items().iter().map(|i| i.item.as_str()).collect();
+        assert_eq!(ranges, vec!["da", "en-gb", "en"]);
+        assert_eq!(l.preferred(&["en-US", "en-GB", "da-DK"]), Some("da-DK"));
+        assert_eq!(l.preferred(&["en-US", "en-GB"]), Some("en-GB"));
+        assert_eq!(l.preferred(&["de", "en-US"]), Some("en-US"));
+        assert_eq!(l.preferred(&["en"]), Some("en"));
+        assert_eq!(l.preferred(&["de"]), None);
+
+        let l = languages("de-CH, fr;q=0.5");
+        assert_eq!(l.preferred(&["fr", "de"]), Some("de"));
+
+        // offered tags are not split inside of multibyte characters
+        let l = languages("fr-ab");
+        assert_eq!(l.preferred(&["fr-a\u{e9}"]), Some("fr-a\u{e9}"));
+
+        let l = languages("*, en;q=0");
+        assert_eq!(l.preferred(&["en", "de"]), Some("de"));
+        assert_eq!(l.preferred(&["en"]), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_negotiated() {
+        let negotiated = || {
+            Negotiated::new()
+                .with("application/json", || Response::Ok().body("{}"))
+                .with("text/html", || {
+                    Response::Ok()
+                        .content_type("text/html; charset=utf-8")
+                        .body("<p></p>")
+                })
+        };
+
+        let req =
+            TestRequest::with_header(header::ACCEPT, "text/html").to_http_request();
+        let res = negotiated().respond_to(&req).await.unwrap();
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("text/html; charset=utf-8")
+        );
+
+        let req = TestRequest::default().to_http_request();
+        let res = negotiated().respond_to(&req).await.unwrap();
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/json")
+        );
+        assert_eq!(res.body().bin_ref(), b"{}");
+
+        let req = TestRequest::with_header(header::ACCEPT, "image/*").to_http_request();
+        let res = negotiated().respond_to(&req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+}
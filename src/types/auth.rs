@@ -0,0 +1,336 @@
+//! Authentication extractors
+use std::fmt;
+
+use actix_http::error::Error;
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::error::AuthenticationError;
+use crate::extract::FromRequest;
+use crate::http::header;
+use crate::request::HttpRequest;
+
+/// Extract credentials of `Basic` authentication scheme.
+///
+/// If `Authorization` header is missing or malformed, extraction fails
+/// with `401 Unauthorized` response with `WWW-Authenticate` challenge.
+/// Realm of challenge is configured with
+/// [**BasicAuthConfig**](struct.BasicAuthConfig.html).
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// async fn index(auth: web::BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/")
+///             .app_data(web::BasicAuthConfig::default().realm("Restricted area"))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    user_id: String,
+    password: Option<String>,
+}
+
+impl BasicAuth {
+    /// User id of credentials
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Password of credentials, `None` if credentials have no `:` separator
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_ref().map(|s| s.as_str())
+    }
+
+    fn parse(req: &HttpRequest) -> Result<Self, &'static str> {
+        let credentials = credentials(req, "Basic")?;
+        let decoded = base64::decode(credentials).map_err(|_| "Invalid credentials")?;
+        let decoded = String::from_utf8(decoded).map_err(|_| "Invalid credentials")?;
+
+        let mut parts = decoded.splitn(2, ':');
+        let user_id = parts.next().unwrap_or("").to_string();
+        let password = parts.next().map(|s| s.to_string());
+        Ok(BasicAuth { user_id, password })
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("user_id", &self.user_id)
+            .field("password", &"******")
+            .finish()
+    }
+}
+
+impl FromRequest for BasicAuth {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = BasicAuthConfig;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match BasicAuth::parse(req) {
+            Ok(auth) => ok(auth),
+            Err(reason) => {
+                let challenge = req
+                    .app_data::<Self::Config>()
+                    .map(|c| c.challenge())
+                    .unwrap_or_else(|| BasicAuthConfig::default().challenge());
+                err(AuthenticationError::new(reason, challenge).into())
+            }
+        }
+    }
+}
+
+/// Basic authentication extractor configuration
+#[derive(Clone, Debug, Default)]
+pub struct BasicAuthConfig {
+    realm: Option<String>,
+}
+
+impl BasicAuthConfig {
+    /// Set realm of challenge
+    pub fn realm<T: Into<String>>(mut self, realm: T) -> Self {
+        self.realm = Some(realm.into());
+        self
+    }
+
+    fn challenge(&self) -> String {
+        match self.realm {
+            Some(ref realm) => format!("Basic realm=\"{}\"", quote(realm)),
+            None => "Basic".to_string(),
+        }
+    }
+}
+
+/// Extract token of `Bearer` authentication scheme.
+///
+/// If `Authorization` header is missing or malformed, extraction fails
+/// with `401 Unauthorized` response with `WWW-Authenticate` challenge.
+/// Realm and scope of challenge are configured with
+/// [**BearerAuthConfig**](struct.BearerAuthConfig.html).
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn index(auth: web::BearerAuth) -> HttpResponse {
+///     if auth.token() == "secret" {
+///         HttpResponse::Ok().finish()
+///     } else {
+///         HttpResponse::Forbidden().finish()
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/")
+///             .app_data(web::BearerAuthConfig::default().scope("read"))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct BearerAuth {
+    token: String,
+}
+
+impl BearerAuth {
+    /// Bearer token
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn parse(req: &HttpRequest) -> Result<Self, &'static str> {
+        let token = credentials(req, "Bearer")?;
+        let valid = token.bytes().all(|c| match c {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => true,
+            b'-' | b'.' | b'_' | b'~' | b'+' | b'/' | b'=' => true,
+            _ => false,
+        });
+        if !valid {
+            return Err("Invalid token");
+        }
+        Ok(BearerAuth {
+            token: token.to_string(),
+        })
+    }
+}
+
+impl fmt::Debug for BearerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerAuth")
+            .field("token", &"******")
+            .finish()
+    }
+}
+
+impl FromRequest for BearerAuth {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = BearerAuthConfig;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match BearerAuth::parse(req) {
+            Ok(auth) => ok(auth),
+            Err(reason) => {
+                let config = req.app_data::<Self::Config>();
+                let challenge = match config {
+                    Some(c) => c.challenge(reason),
+                    None => BearerAuthConfig::default().challenge(reason),
+                };
+                err(AuthenticationError::new(reason, challenge).into())
+            }
+        }
+    }
+}
+
+/// Bearer authentication extractor configuration
+#[derive(Clone, Debug, Default)]
+pub struct BearerAuthConfig {
+    realm: Option<String>,
+    scope: Option<String>,
+}
+
+impl BearerAuthConfig {
+    /// Set realm of challenge
+    pub fn realm<T: Into<String>>(mut self, realm: T) -> Self {
+        self.realm = Some(realm.into());
+        self
+    }
+
+    /// Set scope of challenge, space separated list of scopes
+    pub fn scope<T: Into<String>>(mut self, scope: T) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    fn challenge(&self, reason: &str) -> String {
+        let mut params = Vec::new();
+        if let Some(ref realm) = self.realm {
+            params.push(format!("realm=\"{}\"", quote(realm)));
+        }
+        if let Some(ref scope) = self.scope {
+            params.push(format!("scope=\"{}\"", quote(scope)));
+        }
+        // malformed token, rfc6750 section 3.1
+        if reason == "Invalid token" {
+            params.push("error=\"invalid_token\"".to_string());
+        }
+
+        if params.is_empty() {
+            "Bearer".to_string()
+        } else {
+            format!("Bearer {}", params.join(", "))
+        }
+    }
+}
+
+/// Credentials of `Authorization` header with specified scheme
+fn credentials<'a>(req: &'a HttpRequest, scheme: &str) -> Result<&'a str, &'static str> {
+    let value = req
+        .headers()
+        .get(&header::AUTHORIZATION)
+        .ok_or("Missing credentials")?
+        .to_str()
+        .map_err(|_| "Invalid credentials")?;
+
+    let mut parts = value.trim().splitn(2, ' ');
+    match parts.next() {
+        Some(s) if s.eq_ignore_ascii_case(scheme) => (),
+        _ => return Err("Missing credentials"),
+    }
+    match parts.next().map(|s| s.trim()) {
+        Some(s) if !s.is_empty() => Ok(s),
+        _ => Err("Invalid credentials"),
+    }
+}
+
+fn quote(val: &str) -> String {
+    val.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::TestRequest;
+    use crate::HttpResponse;
+
+    fn challenge(e: Error) -> String {
+        let res: HttpResponse = e.into();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        res.headers()
+            .get(header::WWW_AUTHENTICATE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[actix_rt::test]
+    async fn test_basic_auth() {
+        // Aladdin:open sesame
+        let (req, mut pl) = TestRequest::with_header(
+            header::AUTHORIZATION,
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+        )
+        .to_http_parts();
+        let auth = BasicAuth::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.user_id(), "Aladdin");
+        assert_eq!(auth.password(), Some("open sesame"));
+
+        let (req, mut pl) = TestRequest::default()
+            .app_data(BasicAuthConfig::default().realm("Area \"51\""))
+            .to_http_parts();
+        let e = BasicAuth::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(e.to_string(), "Missing credentials");
+        assert_eq!(challenge(e), "Basic realm=\"Area \\\"51\\\"\"");
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::AUTHORIZATION, "Basic !!").to_http_parts();
+        let e = BasicAuth::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(e.to_string(), "Invalid credentials");
+        assert_eq!(challenge(e), "Basic");
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer abc")
+                .to_http_parts();
+        let e = BasicAuth::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(e.to_string(), "Missing credentials");
+    }
+
+    #[actix_rt::test]
+    async fn test_bearer_auth() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::AUTHORIZATION, "bearer a.b-c")
+                .to_http_parts();
+        let auth = BearerAuth::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.token(), "a.b-c");
+
+        let (req, mut pl) = TestRequest::default()
+            .app_data(BearerAuthConfig::default().realm("api").scope("read write"))
+            .to_http_parts();
+        let e = BearerAuth::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(challenge(e), "Bearer realm=\"api\", scope=\"read write\"");
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer a b")
+                .to_http_parts();
+        let e = BearerAuth::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(e.to_string(), "Invalid token");
+        assert_eq!(challenge(e), "Bearer error=\"invalid_token\"");
+    }
+}
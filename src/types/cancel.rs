@@ -0,0 +1,263 @@
+//! Request cancellation
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use actix_http::h1::Disconnect;
+use actix_http::RequestHead;
+use derive_more::Display;
+use futures::future::{ok, Ready};
+
+use crate::config::AppConfig;
+use crate::dev::Payload;
+use crate::error::Error;
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+use crate::shutdown::ShutdownSignal;
+
+/// Reason of request cancellation
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum CancelReason {
+    /// Client closed connection
+    #[display(fmt = "Client is disconnected")]
+    Disconnected,
+    /// Request handling timed out, see `Timeout` and `Deadline` middlewares
+    #[display(fmt = "Request timed out")]
+    Timeout,
+    /// Server shutdown has begun
+    #[display(fmt = "Server is shutting down")]
+    Shutdown,
+}
+
+/// Cancellation handle of request.
+///
+/// Handle is a future that resolves once response of request is not
+/// needed anymore:
+///
+/// * client disconnects before response is ready, disconnect is detected
+///   for HTTP/1 connections only
+/// * `middleware::Timeout` or `middleware::Deadline` time out request
+/// * graceful shutdown of `HttpServer` begins, shutdown is observed if
+///   server has shutdown hooks, i.e. `HttpServer::on_shutdown()`
+///
+/// Handlers that do expensive work could stop early. Handle could be
+/// cloned, clones share state of request cancellation. It is possible to
+/// get `Cancellation` as extractor or with `HttpRequest::cancellation()`.
+///
+/// ```rust
+/// use actix_web::web::{self, Cancellation};
+/// use actix_web::HttpResponse;
+/// use futures::future::{select, Either};
+///
+/// async fn render_report() -> String {
+///     "report".to_owned()
+/// }
+///
+/// async fn report(cancel: Cancellation) -> HttpResponse {
+///     match select(Box::pin(render_report()), cancel).await {
+///         Either::Left((report, _)) => HttpResponse::Ok().body(report),
+///         Either::Right((reason, _)) => {
+///             HttpResponse::ServiceUnavailable().body(reason.to_string())
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Cancellation(Rc<Inner>);
+
+struct Inner {
+    reason: Cell<Option<CancelReason>>,
+    disconnect: Option<Disconnect>,
+    shutdown: Option<(ShutdownSignal, usize)>,
+    tasks: RefCell<Vec<Waker>>,
+}
+
+impl Cancellation {
+    /// Get cancellation handle of request, handle is created on first use.
+    pub(crate) fn get(head: &RequestHead, config: &AppConfig) -> Cancellation {
+        if let Some(cancel) = head.extensions().get::<Cancellation>() {
+            return cancel.clone();
+        }
+
+        let cancel = Cancellation(Rc::new(Inner {
+            reason: Cell::new(None),
+            disconnect: head.extensions().get::<Disconnect>().cloned(),
+            shutdown: config
+                .shutdown()
+                .map(|signal| (signal.clone(), signal.register())),
+            tasks: RefCell::new(Vec::new()),
+        }));
+        head.extensions_mut().insert(cancel.clone());
+        cancel
+    }
+
+    /// Reason of cancellation, `None` if request is not cancelled.
+    pub fn reason(&self) -> Option<CancelReason> {
+        if self.0.reason.get().is_none() {
+            if self.0.disconnect.as_ref().map(|d| d.is_disconnected()) == Some(true) {
+                self.cancel(CancelReason::Disconnected);
+            } else if self.0.shutdown.as_ref().map(|(s, _)| s.is_started()) == Some(true)
+            {
+                self.cancel(CancelReason::Shutdown);
+            }
+        }
+        self.0.reason.get()
+    }
+
+    /// Returns true if request is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Cancel request, i.e. by middleware that limits request handling.
+    ///
+    /// Reason of first cancellation is kept.
+    pub fn cancel(&self, reason: CancelReason) {
+        if self.0.reason.get().is_none() {
+            self.0.reason.set(Some(reason));
+            for task in self.0.tasks.borrow_mut().drain(..) {
+                task.wake();
+            }
+        }
+    }
+}
+
+impl Future for Cancellation {
+    type Output = CancelReason;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &self.0;
+        if let Some(reason) = inner.reason.get() {
+            return Poll::Ready(reason);
+        }
+
+        if let Some(ref disconnect) = inner.disconnect {
+            if disconnect.poll_disconnected(cx).is_ready() {
+                self.cancel(CancelReason::Disconnected);
+                return Poll::Ready(CancelReason::Disconnected);
+            }
+        }
+        if let Some((ref signal, key)) = inner.shutdown {
+            if signal.poll_started(key, cx).is_ready() {
+                self.cancel(CancelReason::Shutdown);
+                return Poll::Ready(CancelReason::Shutdown);
+            }
+        }
+
+        let mut tasks = inner.tasks.borrow_mut();
+        if !tasks.iter().any(|task| task.will_wake(cx.waker())) {
+            tasks.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some((ref signal, key)) = self.shutdown {
+            signal.unregister(key);
+        }
+    }
+}
+
+impl fmt::Debug for Cancellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cancellation")
+            .field("reason", &self.0.reason.get())
+            .finish()
+    }
+}
+
+/// Extract cancellation handle of request, extraction never fails.
+impl FromRequest for Cancellation {
+    type Config = ();
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req.cancellation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_rt::time::delay_for;
+    use futures::future::{select, Either};
+
+    use super::*;
+    use crate::dev::Service;
+    use crate::http::StatusCode;
+    use crate::middleware::Timeout;
+    use crate::test::{init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn test_cancel() {
+        let (req, _) = TestRequest::default().to_http_parts();
+        let cancel = req.cancellation();
+        assert!(!cancel.is_cancelled());
+
+        let cancel2 = req.cancellation();
+        actix_rt::spawn(async move { cancel2.cancel(CancelReason::Timeout) });
+        assert_eq!(cancel.clone().await, CancelReason::Timeout);
+
+        cancel.cancel(CancelReason::Disconnected);
+        assert_eq!(cancel.reason(), Some(CancelReason::Timeout));
+    }
+
+    #[actix_rt::test]
+    async fn test_shutdown() {
+        let signal = ShutdownSignal::default();
+        let (req, _) = TestRequest::default().to_http_parts();
+        let config = AppConfig::default().with_shutdown(signal.clone());
+        let cancel = Cancellation::get(req.head(), &config);
+        assert!(!cancel.is_cancelled());
+
+        signal.start();
+        assert_eq!(cancel.reason(), Some(CancelReason::Shutdown));
+        assert_eq!(cancel.await, CancelReason::Shutdown);
+    }
+
+    #[actix_rt::test]
+    async fn test_timeout_cancel() {
+        let cancelled = Rc::new(Cell::new(None));
+        let cancelled2 = cancelled.clone();
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(50)))
+                .route(
+                    "/",
+                    web::get().to(move |cancel: Cancellation| {
+                        let cancelled = cancelled2.clone();
+                        actix_rt::spawn(async move {
+                            let work = delay_for(Duration::from_millis(500));
+                            if let Either::Right((reason, _)) =
+                                select(work, cancel).await
+                            {
+                                cancelled.set(Some(reason));
+                            }
+                        });
+                        async {
+                            delay_for(Duration::from_millis(500)).await;
+                            HttpResponse::Ok()
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let resp: HttpResponse = srv.call(req).await.err().unwrap().into();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        delay_for(Duration::from_millis(10)).await;
+        assert_eq!(cancelled.get(), Some(CancelReason::Timeout));
+    }
+}
@@ -22,8 +22,10 @@ use crate::http::{
     header::{ContentType, CONTENT_LENGTH},
     StatusCode,
 };
+use crate::openapi::Operation;
 use crate::request::HttpRequest;
 use crate::responder::Responder;
+use crate::types::qs;
 
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
@@ -117,16 +119,21 @@ where
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self, Error>>;
 
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/x-www-form-urlencoded");
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         let req2 = req.clone();
-        let (limit, err) = req
+        let (limit, nested, err) = req
             .app_data::<FormConfig>()
-            .map(|c| (c.limit, c.ehandler.clone()))
-            .unwrap_or((16384, None));
+            .map(|c| (c.limit, c.nested, c.ehandler.clone()))
+            .unwrap_or((16384, false, None));
 
         UrlEncoded::new(req, payload)
             .limit(limit)
+            .nested(nested)
             .map(move |res| match res {
                 Err(e) => {
                     if let Some(err) = err {
@@ -200,6 +207,7 @@ impl<T: Serialize> Responder for Form<T> {
 #[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    nested: bool,
     ehandler: Option<Rc<dyn Fn(UrlencodedError, &HttpRequest) -> Error>>,
 }
 
@@ -210,6 +218,18 @@ impl FormConfig {
         self
     }
 
+    /// Enable support of repeated keys (`tag=a&tag=b`), bracketed arrays
+    /// (`tag[]=a&tag[]=b`), indexed arrays (`tag[0]=a`) and nested keys
+    /// (`filter[min]=1`).
+    ///
+    /// Repeated, bracketed and indexed keys are deserialized into sequences,
+    /// nested keys into maps or structs, i.e. `item[][name]=a&item[][qty]=1`
+    /// is a sequence with one struct. Disabled by default, keys are flat.
+    pub fn nested(mut self, enabled: bool) -> Self {
+        self.nested = enabled;
+        self
+    }
+
     /// Set custom error handler
     pub fn error_handler<F>(mut self, f: F) -> Self
     where
@@ -224,6 +244,7 @@ impl Default for FormConfig {
     fn default() -> Self {
         FormConfig {
             limit: 16384,
+            nested: false,
             ehandler: None,
         }
     }
@@ -246,6 +267,7 @@ pub struct UrlEncoded<U> {
     #[cfg(not(feature = "compress"))]
     stream: Option<Payload>,
     limit: usize,
+    nested: bool,
     length: Option<usize>,
     encoding: &'static Encoding,
     err: Option<UrlencodedError>,
@@ -286,6 +308,7 @@ impl<U> UrlEncoded<U> {
             encoding,
             stream: Some(payload),
             limit: 32_768,
+            nested: false,
             length: len,
             fut: None,
             err: None,
@@ -296,6 +319,7 @@ impl<U> UrlEncoded<U> {
         UrlEncoded {
             stream: None,
             limit: 32_768,
+            nested: false,
             fut: None,
             err: Some(e),
             length: None,
@@ -308,6 +332,12 @@ impl<U> UrlEncoded<U> {
         self.limit = limit;
         self
     }
+
+    /// Enable support of repeated keys, bracketed arrays and nested keys.
+    pub fn nested(mut self, enabled: bool) -> Self {
+        self.nested = enabled;
+        self
+    }
 }
 
 impl<U> Future for UrlEncoded<U>
@@ -335,6 +365,7 @@ where
 
         // future
         let encoding = self.encoding;
+        let nested = self.nested;
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
@@ -353,17 +384,24 @@ where
                     }
                 }
 
-                if encoding == UTF_8 {
-                    serde_urlencoded::from_bytes::<U>(&body)
-                        .map_err(|_| UrlencodedError::Parse)
+                let res = if encoding == UTF_8 {
+                    if nested {
+                        qs::from_bytes::<U>(&body)
+                    } else {
+                        serde_urlencoded::from_bytes::<U>(&body)
+                    }
                 } else {
                     let body = encoding
                         .decode_without_bom_handling_and_without_replacement(&body)
                         .map(|s| s.into_owned())
                         .ok_or(UrlencodedError::Parse)?;
-                    serde_urlencoded::from_str::<U>(&body)
-                        .map_err(|_| UrlencodedError::Parse)
-                }
+                    if nested {
+                        qs::from_str::<U>(&body)
+                    } else {
+                        serde_urlencoded::from_str::<U>(&body)
+                    }
+                };
+                res.map_err(UrlencodedError::Deserialize)
             }
             .boxed_local(),
         );
@@ -377,8 +415,10 @@ mod tests {
     use serde::{Deserialize, Serialize};
 
     use super::*;
+    use crate::error::InternalError;
     use crate::http::header::{HeaderValue, CONTENT_TYPE};
     use crate::test::TestRequest;
+    use crate::HttpResponse;
 
     #[derive(Deserialize, Serialize, Debug, PartialEq)]
     struct Info {
@@ -422,6 +462,27 @@ mod tests {
         }
     }
 
+    #[actix_rt::test]
+    async fn test_form_error_handler() {
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .app_data(FormConfig::default().error_handler(|e, req| {
+                    let msg = format!("{}: {}", req.path(), e);
+                    let resp = HttpResponse::UnprocessableEntity().body(msg.clone());
+                    InternalError::from_response(msg, resp).into()
+                }))
+                .set_payload(Bytes::from_static(b"hello=world"))
+                .to_http_parts();
+
+        let e = Form::<Info>::from_request(&req, &mut pl).await.unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "/: Urlencoded deserialize error: missing field `counter`"
+        );
+        let resp: HttpResponse = e.into();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
     #[actix_rt::test]
     async fn test_urlencoded_error() {
         let (req, mut pl) =
@@ -483,6 +544,39 @@ mod tests {
         );
     }
 
+    #[actix_rt::test]
+    async fn test_urlencoded_nested() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Order {
+            item: Vec<String>,
+            address: Address,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Address {
+            city: String,
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .app_data(FormConfig::default().nested(true))
+                .set_payload(Bytes::from_static(
+                    b"item[]=a&item[]=b&address%5Bcity%5D=Paris",
+                ))
+                .to_http_parts();
+
+        let Form(s) = Form::<Order>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(
+            s,
+            Order {
+                item: vec!["a".to_owned(), "b".to_owned()],
+                address: Address {
+                    city: "Paris".to_owned()
+                },
+            }
+        );
+    }
+
     #[actix_rt::test]
     async fn test_responder() {
         let req = TestRequest::default().to_http_request();
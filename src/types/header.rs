@@ -0,0 +1,148 @@
+//! Typed header extractor
+use std::{fmt, ops};
+
+use actix_http::error::{Error, ErrorBadRequest};
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::extract::FromRequest;
+use crate::http::header;
+use crate::openapi::{Operation, Parameter};
+use crate::request::HttpRequest;
+
+/// Extract typed header from the request.
+///
+/// Any type that implements `http::header::Header` could be extracted.
+/// Extraction fails with `400 Bad Request` if header is missing or can not
+/// be parsed, use `Option<Header<T>>` if header is optional.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{http::header, web, App, HttpResponse};
+///
+/// async fn index(etags: Option<web::Header<header::IfNoneMatch>>) -> HttpResponse {
+///     match etags.map(|h| h.into_inner()) {
+///         Some(header::IfNoneMatch::Any) => HttpResponse::NotModified().finish(),
+///         _ => HttpResponse::Ok().finish(),
+///     }
+/// }
+///
+/// async fn upload(ct: web::Header<header::ContentType>) -> String {
+///     format!("Content type: {}", ct.0)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .route("/", web::get().to(index))
+///         .route("/upload", web::post().to(upload));
+/// }
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub struct Header<T>(pub T);
+
+impl<T> Header<T> {
+    /// Deconstruct to a inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for Header<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for Header<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Header<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Header<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> FromRequest for Header<T>
+where
+    T: header::Header,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    fn describe(op: &mut Operation) {
+        op.add_parameter(Parameter::header(T::name().as_str()));
+    }
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match T::parse(req) {
+            Ok(val) => ok(Header(val)),
+            Err(_) => {
+                let name = T::name();
+                log::debug!(
+                    "Failed during Header extractor parsing of {:?}. \
+                     Request path: {:?}",
+                    name,
+                    req.path()
+                );
+                if req.headers().contains_key(&name) {
+                    err(ErrorBadRequest(format!("Invalid {} header", name)))
+                } else {
+                    err(ErrorBadRequest(format!("Missing {} header", name)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::TestRequest;
+
+    #[actix_rt::test]
+    async fn test_header() {
+        let (req, mut pl) = TestRequest::default()
+            .header(header::CONTENT_TYPE, "application/json")
+            .to_http_parts();
+        let ct = Header::<header::ContentType>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(ct.into_inner(), header::ContentType::json());
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let e = Header::<header::ContentType>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "Missing content-type header");
+        let res = Option::<Header<header::ContentType>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(res.is_none());
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::CONTENT_TYPE, "json")
+            .to_http_parts();
+        let e = Header::<header::ContentType>::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "Invalid content-type header");
+        assert_eq!(e.as_response_error().status_code(), StatusCode::BAD_REQUEST);
+    }
+}
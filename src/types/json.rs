@@ -4,9 +4,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::{fmt, ops};
+use std::{cmp, fmt, ops};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
 use futures::StreamExt;
 use serde::de::DeserializeOwned;
@@ -20,9 +20,13 @@ use actix_http::{HttpMessage, Payload, Response};
 use crate::dev::Decompress;
 use crate::error::{Error, JsonPayloadError};
 use crate::extract::FromRequest;
+use crate::openapi::Operation;
 use crate::request::HttpRequest;
 use crate::responder::Responder;
 
+/// Maximum size of buffer allocated before body is read
+const MAX_PREALLOC: usize = 65_536;
+
 /// Json helper
 ///
 /// Json can be used for two different purpose. First is for json response
@@ -33,7 +37,8 @@ use crate::responder::Responder;
 /// implement the `Deserialize` trait from *serde*.
 ///
 /// [**JsonConfig**](struct.JsonConfig.html) allows to configure extraction
-/// process.
+/// process, [**JsonResponseConfig**](struct.JsonResponseConfig.html)
+/// allows to configure response generation.
 ///
 /// ## Example
 ///
@@ -125,18 +130,110 @@ impl<T: Serialize> Responder for Json<T> {
     type Error = Error;
     type Future = Ready<Result<Response, Error>>;
 
-    fn respond_to(self, _: &HttpRequest) -> Self::Future {
-        let body = match serde_json::to_string(&self.0) {
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let cfg = req.app_data::<JsonResponseConfig>();
+        let body = match cfg {
+            Some(cfg) => cfg.serialize(&self.0),
+            None => serde_json::to_vec(&self.0)
+                .map(Bytes::from)
+                .map_err(Error::from),
+        };
+        let body = match body {
             Ok(body) => body,
-            Err(e) => return err(e.into()),
+            Err(e) => return err(e),
         };
 
+        let content_type = cfg
+            .map(|cfg| cfg.content_type.as_ref())
+            .unwrap_or("application/json");
         ok(Response::build(StatusCode::OK)
-            .content_type("application/json")
+            .content_type(content_type)
             .body(body))
     }
 }
 
+/// Json responder configuration
+///
+/// Configuration is registered with `app_data()` of application, scope or
+/// resource, responses of handlers in it are generated with this
+/// configuration.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/api")
+///             // <- problem details responses, pretty-printed
+///             .app_data(
+///                 web::JsonResponseConfig::default()
+///                     .pretty(true)
+///                     .content_type("application/problem+json"),
+///             )
+///             .route("/", web::get().to(|| async {
+///                 web::Json(vec!["a", "b"])
+///             })),
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct JsonResponseConfig {
+    pretty: bool,
+    content_type: String,
+    serializer:
+        Option<Arc<dyn Fn(&serde_json::Value) -> Result<Bytes, Error> + Send + Sync>>,
+}
+
+impl JsonResponseConfig {
+    /// Pretty-print json, i.e. for debugging. By default json is compact.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Set content type of response, i.e. `application/problem+json`.
+    /// By default content type is `application/json`.
+    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Set custom serializer.
+    ///
+    /// Value is converted to `serde_json::Value` first and passed to
+    /// serializer, so it could be encoded with different settings or
+    /// transformed, i.e. wrapped in envelope. `pretty()` is ignored if
+    /// serializer is set.
+    pub fn serializer<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&serde_json::Value) -> Result<Bytes, Error> + Send + Sync + 'static,
+    {
+        self.serializer = Some(Arc::new(f));
+        self
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Bytes, Error> {
+        if let Some(ref serializer) = self.serializer {
+            let value = serde_json::to_value(value)?;
+            (*serializer)(&value)
+        } else if self.pretty {
+            Ok(Bytes::from(serde_json::to_vec_pretty(value)?))
+        } else {
+            Ok(Bytes::from(serde_json::to_vec(value)?))
+        }
+    }
+}
+
+impl Default for JsonResponseConfig {
+    fn default() -> Self {
+        JsonResponseConfig {
+            pretty: false,
+            content_type: "application/json".to_owned(),
+            serializer: None,
+        }
+    }
+}
+
 /// Json extractor. Allow to extract typed information from request's
 /// payload.
 ///
@@ -177,12 +274,16 @@ where
     type Future = LocalBoxFuture<'static, Result<Self, Error>>;
     type Config = JsonConfig;
 
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/json");
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         let req2 = req.clone();
         let (limit, err, ctype) = req
             .app_data::<Self::Config>()
-            .map(|c| (c.limit, c.ehandler.clone(), c.content_type.clone()))
+            .map(|c| (c.limit_for(req), c.ehandler.clone(), c.content_type.clone()))
             .unwrap_or((32768, None, None));
 
         JsonBody::new(req, payload, ctype)
@@ -229,6 +330,8 @@ where
 ///                 // change json extractor configuration
 ///                 web::Json::<Info>::configure(|cfg| {
 ///                     cfg.limit(4096)
+///                        // <- allow bigger payloads for geojson documents
+///                        .content_type_limit("application/geo+json".parse().unwrap(), 65536)
 ///                        .content_type(|mime| {  // <- accept text/plain content type
 ///                            mime.type_() == mime::TEXT && mime.subtype() == mime::PLAIN
 ///                        })
@@ -246,6 +349,7 @@ pub struct JsonConfig {
     limit: usize,
     ehandler: Option<Arc<dyn Fn(JsonPayloadError, &HttpRequest) -> Error + Send + Sync>>,
     content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    content_type_limits: Vec<(mime::Mime, usize)>,
 }
 
 impl JsonConfig {
@@ -272,6 +376,30 @@ impl JsonConfig {
         self.content_type = Some(Arc::new(predicate));
         self
     }
+
+    /// Change max size of payload for specific content type.
+    ///
+    /// Content type is matched by type and subtype, parameters are ignored.
+    /// Payloads of other content types use limit set with `limit()`.
+    pub fn content_type_limit(mut self, mime: mime::Mime, limit: usize) -> Self {
+        self.content_type_limits.push((mime, limit));
+        self
+    }
+
+    /// Max size of payload for request's content type
+    fn limit_for(&self, req: &HttpRequest) -> usize {
+        if self.content_type_limits.is_empty() {
+            return self.limit;
+        }
+        if let Ok(Some(mime)) = req.mime_type() {
+            for (m, limit) in &self.content_type_limits {
+                if m.type_() == mime.type_() && m.subtype() == mime.subtype() {
+                    return *limit;
+                }
+            }
+        }
+        self.limit
+    }
 }
 
 impl Default for JsonConfig {
@@ -280,6 +408,7 @@ impl Default for JsonConfig {
             limit: 32768,
             ehandler: None,
             content_type: None,
+            content_type_limits: Vec::new(),
         }
     }
 }
@@ -375,16 +504,19 @@ where
         }
 
         let limit = self.limit;
+        let mut capacity = 8192;
         if let Some(len) = self.length.take() {
             if len > limit {
                 return Poll::Ready(Err(JsonPayloadError::Overflow));
             }
+            // content length is not trusted for allocation of large buffers
+            capacity = cmp::min(len, MAX_PREALLOC);
         }
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
             async move {
-                let mut body = BytesMut::with_capacity(8192);
+                let mut body = BytesMut::with_capacity(capacity);
 
                 while let Some(item) = stream.next().await {
                     let chunk = item?;
@@ -405,7 +537,6 @@ where
 
 #[cfg(test)]
 mod tests {
-    use bytes::Bytes;
     use serde_derive::{Deserialize, Serialize};
 
     use super::*;
@@ -451,6 +582,48 @@ mod tests {
         assert_eq!(resp.body().bin_ref(), b"{\"name\":\"test\"}");
     }
 
+    #[actix_rt::test]
+    async fn test_responder_config() {
+        use crate::responder::tests::BodyTest;
+
+        let req = TestRequest::default()
+            .app_data(
+                JsonResponseConfig::default()
+                    .pretty(true)
+                    .content_type("application/problem+json"),
+            )
+            .to_http_request();
+        let resp = Json(MyObject {
+            name: "test".to_string(),
+        })
+        .respond_to(&req)
+        .await
+        .unwrap();
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/problem+json")
+        );
+        assert_eq!(resp.body().bin_ref(), b"{\n  \"name\": \"test\"\n}");
+
+        let req = TestRequest::default()
+            .app_data(JsonResponseConfig::default().serializer(|value| {
+                let value = serde_json::json!({ "data": value });
+                Ok(Bytes::from(serde_json::to_vec(&value)?))
+            }))
+            .to_http_request();
+        let resp = Json(MyObject {
+            name: "test".to_string(),
+        })
+        .respond_to(&req)
+        .await
+        .unwrap();
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/json")
+        );
+        assert_eq!(resp.body().bin_ref(), b"{\"data\":{\"name\":\"test\"}}");
+    }
+
     #[actix_rt::test]
     async fn test_custom_error_responder() {
         let (req, mut pl) = TestRequest::default()
@@ -651,4 +824,52 @@ mod tests {
         let s = Json::<MyObject>::from_request(&req, &mut pl).await;
         assert!(s.is_err())
     }
+
+    #[actix_rt::test]
+    async fn test_content_type_limit() {
+        let config = JsonConfig::default()
+            .limit(10)
+            .content_type_limit("application/geo+json".parse().unwrap(), 100);
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/geo+json; charset=utf-8"),
+        )
+        .header(
+            header::CONTENT_LENGTH,
+            header::HeaderValue::from_static("16"),
+        )
+        .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+        .app_data(config.clone())
+        .to_http_parts();
+        let s = Json::<MyObject>::from_request(&req, &mut pl).await;
+        assert_eq!(s.unwrap().name, "test");
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        )
+        .header(
+            header::CONTENT_LENGTH,
+            header::HeaderValue::from_static("16"),
+        )
+        .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+        .app_data(config.clone())
+        .to_http_parts();
+        let s = Json::<MyObject>::from_request(&req, &mut pl).await;
+        assert!(format!("{}", s.err().unwrap())
+            .contains("Json payload size is bigger than allowed"));
+
+        // no content-length, overflow is detected while reading payload
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        )
+        .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+        .app_data(config)
+        .to_http_parts();
+        let s = Json::<MyObject>::from_request(&req, &mut pl).await;
+        assert!(format!("{}", s.err().unwrap())
+            .contains("Json payload size is bigger than allowed"));
+    }
 }
@@ -1,15 +1,30 @@
 //! Helper types
 
+mod accepts;
+mod auth;
+mod cancel;
 pub(crate) mod form;
+mod header;
 pub(crate) mod json;
 mod path;
 pub(crate) mod payload;
+pub(crate) mod peer_cert;
+mod qs;
 mod query;
+mod range;
 pub(crate) mod readlines;
+pub(crate) mod tls_info;
 
+pub use self::accepts::{Accepts, Languages, Negotiated};
+pub use self::auth::{BasicAuth, BasicAuthConfig, BearerAuth, BearerAuthConfig};
+pub use self::cancel::{CancelReason, Cancellation};
 pub use self::form::{Form, FormConfig};
-pub use self::json::{Json, JsonConfig};
-pub use self::path::{Path, PathConfig};
-pub use self::payload::{Payload, PayloadConfig};
+pub use self::header::Header;
+pub use self::json::{Json, JsonConfig, JsonResponseConfig};
+pub use self::path::{Path, PathConfig, PathDecoding};
+pub use self::payload::{Payload, PayloadConfig, SpooledBody};
+pub use self::peer_cert::PeerCertificate;
 pub use self::query::{Query, QueryConfig};
+pub use self::range::Ranged;
 pub use self::readlines::Readlines;
+pub use self::tls_info::TlsInfo;
@@ -5,11 +5,12 @@ use std::{fmt, ops};
 use actix_http::error::{Error, ErrorNotFound};
 use actix_router::PathDeserializer;
 use futures::future::{ready, Ready};
-use serde::de;
+use serde::de::{self, Deserializer, Visitor};
 
 use crate::dev::Payload;
 use crate::error::PathError;
 use crate::request::HttpRequest;
+use crate::types::qs::Value;
 use crate::FromRequest;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -166,28 +167,29 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let error_handler = req
-            .app_data::<Self::Config>()
-            .map(|c| c.ehandler.clone())
-            .unwrap_or(None);
-
-        ready(
-            de::Deserialize::deserialize(PathDeserializer::new(req.match_info()))
-                .map(|inner| Path { inner })
-                .map_err(move |e| {
-                    log::debug!(
-                        "Failed during Path extractor deserialization. \
+        let config = req.app_data::<Self::Config>();
+        let error_handler = config.and_then(|c| c.ehandler.clone());
+
+        let res = match config {
+            Some(cfg) if cfg.decoding != PathDecoding::Partial => {
+                Segments::decode(req, cfg).and_then(de::Deserialize::deserialize)
+            }
+            _ => de::Deserialize::deserialize(PathDeserializer::new(req.match_info())),
+        };
+
+        ready(res.map(|inner| Path { inner }).map_err(move |e| {
+            log::debug!(
+                "Failed during Path extractor deserialization. \
                          Request path: {:?}",
-                        req.path()
-                    );
-                    if let Some(error_handler) = error_handler {
-                        let e = PathError::Deserialize(e);
-                        (error_handler)(e, req)
-                    } else {
-                        ErrorNotFound(e)
-                    }
-                }),
-        )
+                req.path()
+            );
+            if let Some(error_handler) = error_handler {
+                let e = PathError::Deserialize(e);
+                (error_handler)(e, req)
+            } else {
+                ErrorNotFound(e)
+            }
+        }))
     }
 }
 
@@ -228,6 +230,9 @@ where
 #[derive(Clone)]
 pub struct PathConfig {
     ehandler: Option<Arc<dyn Fn(PathError, &HttpRequest) -> Error + Send + Sync>>,
+    decoding: PathDecoding,
+    decode_slash: bool,
+    lossy_utf8: bool,
 }
 
 impl PathConfig {
@@ -239,11 +244,231 @@ impl PathConfig {
         self.ehandler = Some(Arc::new(f));
         self
     }
+
+    /// Set decoding of path segments, by default `PathDecoding::Partial`.
+    pub fn decoding(mut self, decoding: PathDecoding) -> Self {
+        self.decoding = decoding;
+        self
+    }
+
+    /// Decode `%2F` into `/` with `PathDecoding::Full`, enabled by default.
+    ///
+    /// If disabled, encoded slash is kept as `%2F` in decoded segments.
+    pub fn decode_slash(mut self, enabled: bool) -> Self {
+        self.decode_slash = enabled;
+        self
+    }
+
+    /// Replace invalid UTF-8 sequences of fully decoded segments with
+    /// `U+FFFD`, instead of failing extraction. Disabled by default.
+    pub fn lossy_utf8(mut self, enabled: bool) -> Self {
+        self.lossy_utf8 = enabled;
+        self
+    }
 }
 
 impl Default for PathConfig {
     fn default() -> Self {
-        PathConfig { ehandler: None }
+        PathConfig {
+            ehandler: None,
+            decoding: PathDecoding::Partial,
+            decode_slash: true,
+            lossy_utf8: false,
+        }
+    }
+}
+
+/// Decoding of percent-encoded path segments
+///
+/// ```rust
+/// use actix_web::web::{self, PathConfig, PathDecoding};
+/// use actix_web::App;
+///
+/// // token could contain encoded slashes and binary data
+/// async fn index(token: web::Path<String>) -> String {
+///     format!("Token: {}", token)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/tokens/{token}")
+///             .app_data(PathConfig::default().decoding(PathDecoding::Raw))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathDecoding {
+    /// Segments are decoded same way as path is decoded for matching,
+    /// encoded reserved characters like `%2F` are kept as is.
+    Partial,
+    /// Segments are fully percent-decoded and validated as UTF-8.
+    Full,
+    /// Segments are not decoded, values are kept as sent by client.
+    Raw,
+}
+
+/// Decoded path segments
+struct Segments(Vec<(String, String)>);
+
+impl Segments {
+    fn decode(req: &HttpRequest, cfg: &PathConfig) -> Result<Self, de::value::Error> {
+        let url = req.match_info().get_ref();
+        let (raw, quoted) = (url.uri().path(), url.path());
+
+        let mut segments = Vec::new();
+        for (name, value) in req.match_info().iter() {
+            let value = raw_segment(raw, quoted, value);
+            let value = if cfg.decoding == PathDecoding::Raw {
+                value.to_string()
+            } else {
+                let buf = percent_decode(value, cfg.decode_slash);
+                if cfg.lossy_utf8 {
+                    String::from_utf8_lossy(&buf).into_owned()
+                } else {
+                    String::from_utf8(buf).map_err(|_| {
+                        de::Error::custom(format!(
+                            "invalid utf-8 in path segment `{}`",
+                            name
+                        ))
+                    })?
+                }
+            };
+            segments.push((name.to_string(), value));
+        }
+        Ok(Segments(segments))
+    }
+
+    fn map(self) -> Result<Value, de::value::Error> {
+        Ok(Value::Map(
+            self.0
+                .into_iter()
+                .map(|(name, value)| (name, Value::Str(value)))
+                .collect(),
+        ))
+    }
+
+    fn single(self) -> Result<Value, de::value::Error> {
+        let mut segments = self.0;
+        if segments.len() != 1 {
+            return Err(de::Error::custom(format!(
+                "wrong number of parameters: {} expected 1",
+                segments.len()
+            )));
+        }
+        Ok(Value::Str(segments.pop().unwrap().1))
+    }
+}
+
+/// Find value of segment in raw path.
+///
+/// Segment values point into requoted path, static values are returned as is.
+fn raw_segment<'a>(raw: &'a str, quoted: &'a str, value: &'a str) -> &'a str {
+    let base = quoted.as_ptr() as usize;
+    let start = value.as_ptr() as usize;
+    if start < base || start + value.len() > base + quoted.len() {
+        return value;
+    }
+
+    let start = start - base;
+    let end = start + value.len();
+    match (raw_offset(raw, quoted, start), raw_offset(raw, quoted, end)) {
+        (Some(start), Some(end)) => &raw[start..end],
+        _ => value,
+    }
+}
+
+/// Position in raw path of position in requoted path
+fn raw_offset(raw: &str, quoted: &str, pos: usize) -> Option<usize> {
+    let raw = raw.as_bytes();
+    let mut idx = 0;
+    for &ch in &quoted.as_bytes()[..pos] {
+        // requoting replaces percent-encoded bytes with decoded byte
+        if raw.get(idx) == Some(&b'%') && ch != b'%' {
+            idx += 3;
+        } else {
+            idx += 1;
+        }
+    }
+    if idx <= raw.len() {
+        Some(idx)
+    } else {
+        None
+    }
+}
+
+fn percent_decode(value: &str, slash: bool) -> Vec<u8> {
+    let hex = |ch: u8| (ch as char).to_digit(16).map(|d| d as u8);
+    let bytes = value.as_bytes();
+    let mut buf = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' && idx + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[idx + 1]), hex(bytes[idx + 2])) {
+                let ch = (hi << 4) | lo;
+                if slash || ch != b'/' {
+                    buf.push(ch);
+                    idx += 3;
+                    continue;
+                }
+            }
+        }
+        buf.push(bytes[idx]);
+        idx += 1;
+    }
+    buf
+}
+
+macro_rules! forward {
+    ($target:ident: $($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                self.$target()?.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Segments {
+    type Error = de::value::Error;
+
+    forward! { map:
+        deserialize_any(),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_ignored_any(),
+    }
+
+    forward! { single:
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_identifier(),
+        deserialize_option(),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
     }
 }
 
@@ -357,6 +582,72 @@ mod tests {
         assert_eq!(res[1], "32".to_owned());
     }
 
+    #[actix_rt::test]
+    async fn test_path_decoding() {
+        let resource = ResourceDef::new("/{key}/{value}/");
+        let extract = |config: PathConfig| {
+            let mut req = TestRequest::with_uri("/a%2Fb/%E2%9C%93%41/")
+                .app_data(config)
+                .to_srv_request();
+            resource.match_path(req.match_info_mut());
+            req.into_parts()
+        };
+
+        let (req, mut pl) = extract(PathConfig::default());
+        let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.key, "a%2Fb");
+
+        let (req, mut pl) = extract(PathConfig::default().decoding(PathDecoding::Full));
+        let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.key, "a/b");
+        assert_eq!(s.value, "\u{2713}A");
+        let s = Path::<(String, String)>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(s.0, "a/b");
+        assert!(Path::<String>::from_request(&req, &mut pl).await.is_err());
+
+        let (req, mut pl) = extract(
+            PathConfig::default()
+                .decoding(PathDecoding::Full)
+                .decode_slash(false),
+        );
+        let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.key, "a%2Fb");
+        assert_eq!(s.value, "\u{2713}A");
+
+        let (req, mut pl) = extract(PathConfig::default().decoding(PathDecoding::Raw));
+        let s = Path::<MyStruct>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.key, "a%2Fb");
+        assert_eq!(s.value, "%E2%9C%93%41");
+    }
+
+    #[actix_rt::test]
+    async fn test_path_decoding_utf8() {
+        // router requotes non-ascii escapes before matching, so segment
+        // with invalid utf-8 escape is set directly
+        let extract = |config: PathConfig| {
+            let mut req = TestRequest::default().app_data(config).to_srv_request();
+            req.match_info_mut().add_static("id", "%FF1");
+            req.into_parts()
+        };
+
+        let (req, mut pl) = extract(PathConfig::default().decoding(PathDecoding::Full));
+        assert!(Path::<String>::from_request(&req, &mut pl).await.is_err());
+
+        let (req, mut pl) = extract(
+            PathConfig::default()
+                .decoding(PathDecoding::Full)
+                .lossy_utf8(true),
+        );
+        let s = Path::<String>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(*s, "\u{fffd}1");
+
+        let (req, mut pl) = extract(PathConfig::default().decoding(PathDecoding::Raw));
+        let s = Path::<String>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(*s, "%FF1");
+    }
+
     #[actix_rt::test]
     async fn test_custom_err_handler() {
         let (req, mut pl) = TestRequest::with_uri("/name/user1/")
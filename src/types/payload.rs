@@ -1,7 +1,11 @@
 //! Payload/Bytes/String extractors
+use std::fmt;
+use std::fs::File;
 use std::future::Future;
+use std::io::{self, Seek, Write};
 use std::pin::Pin;
 use std::str;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use actix_http::error::{Error, ErrorBadRequest, PayloadError};
@@ -15,6 +19,7 @@ use mime::Mime;
 use crate::dev;
 use crate::extract::FromRequest;
 use crate::http::header;
+use crate::openapi::Operation;
 use crate::request::HttpRequest;
 
 /// Payload extractor returns request 's payload stream.
@@ -97,6 +102,10 @@ impl FromRequest for Payload {
     type Error = Error;
     type Future = Ready<Result<Payload, Error>>;
 
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/octet-stream");
+    }
+
     #[inline]
     fn from_request(_: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
         ok(Payload(payload.take()))
@@ -136,6 +145,10 @@ impl FromRequest for Bytes {
         Ready<Result<Bytes, Error>>,
     >;
 
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/octet-stream");
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
         let tmp;
@@ -147,12 +160,16 @@ impl FromRequest for Bytes {
         };
 
         if let Err(e) = cfg.check_mimetype(req) {
-            return Either::Right(err(e));
+            return Either::Right(err(cfg.handle_error(e, req)));
         }
 
         let limit = cfg.limit;
+        let ehandler = cfg.ehandler.clone().map(|f| (f, req.clone()));
         let fut = HttpMessageBody::new(req, payload).limit(limit);
-        Either::Left(async move { Ok(fut.await?) }.boxed_local())
+        Either::Left(
+            async move { fut.await.map_err(|e| handle_error(ehandler, e.into())) }
+                .boxed_local(),
+        )
     }
 }
 
@@ -191,6 +208,10 @@ impl FromRequest for String {
         Ready<Result<String, Error>>,
     >;
 
+    fn describe(op: &mut Operation) {
+        op.add_request_content("text/plain");
+    }
+
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
         let tmp;
@@ -203,41 +224,267 @@ impl FromRequest for String {
 
         // check content-type
         if let Err(e) = cfg.check_mimetype(req) {
-            return Either::Right(err(e));
+            return Either::Right(err(cfg.handle_error(e, req)));
         }
 
         // check charset
         let encoding = match req.encoding() {
             Ok(enc) => enc,
-            Err(e) => return Either::Right(err(e.into())),
+            Err(e) => return Either::Right(err(cfg.handle_error(e.into(), req))),
         };
         let limit = cfg.limit;
+        let ehandler = cfg.ehandler.clone().map(|f| (f, req.clone()));
         let fut = HttpMessageBody::new(req, payload).limit(limit);
 
         Either::Left(
             async move {
-                let body = fut.await?;
+                let body = fut
+                    .await
+                    .map_err(|e| handle_error(ehandler.clone(), e.into()))?;
 
-                if encoding == UTF_8 {
-                    Ok(str::from_utf8(body.as_ref())
-                        .map_err(|_| ErrorBadRequest("Can not decode body"))?
-                        .to_owned())
+                let text = if encoding == UTF_8 {
+                    str::from_utf8(body.as_ref()).ok().map(|s| s.to_owned())
                 } else {
-                    Ok(encoding
+                    encoding
                         .decode_without_bom_handling_and_without_replacement(&body)
                         .map(|s| s.into_owned())
-                        .ok_or_else(|| ErrorBadRequest("Can not decode body"))?)
+                };
+                text.ok_or_else(|| {
+                    handle_error(ehandler, ErrorBadRequest("Can not decode body"))
+                })
+            }
+            .boxed_local(),
+        )
+    }
+}
+
+/// Request body that is kept in memory or spilled to temporary file.
+///
+/// Bodies that are larger than spill threshold of
+/// [**PayloadConfig**](struct.PayloadConfig.html), by default 64Kb, are
+/// written to temporary file instead of memory. Temporary file is removed
+/// once it is closed. Size of body is limited with `PayloadConfig::limit()`,
+/// it has to be raised for large uploads.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::io::Read;
+/// use actix_web::{web, App, Error, FromRequest, HttpResponse};
+///
+/// async fn upload(body: web::SpooledBody) -> Result<HttpResponse, Error> {
+///     let size = body.len();
+///     if let Some(mut file) = body.into_file() {
+///         // file is positioned at start of body
+///         let mut buf = [0; 4];
+///         web::block(move || file.read_exact(&mut buf)).await?;
+///     }
+///     Ok(HttpResponse::Ok().body(format!("{} bytes", size)))
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .app_data(web::SpooledBody::configure(|cfg| {
+///                 cfg.limit(512 * 1024 * 1024).spill_threshold(1024 * 1024)
+///             }))
+///             .route(web::post().to(upload)),
+///     );
+/// }
+/// ```
+pub struct SpooledBody {
+    len: u64,
+    data: SpooledData,
+}
+
+enum SpooledData {
+    Memory(Bytes),
+    File(File),
+}
+
+impl SpooledBody {
+    /// Size of body in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if body is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Body kept in memory, `None` if body is spilled to temporary file
+    pub fn bytes(&self) -> Option<&Bytes> {
+        match self.data {
+            SpooledData::Memory(ref bytes) => Some(bytes),
+            SpooledData::File(_) => None,
+        }
+    }
+
+    /// Temporary file with body, `None` if body is kept in memory.
+    ///
+    /// File is positioned at start of body.
+    pub fn file(&self) -> Option<&File> {
+        match self.data {
+            SpooledData::Memory(_) => None,
+            SpooledData::File(ref file) => Some(file),
+        }
+    }
+
+    /// Convert into temporary file, `None` if body is kept in memory
+    pub fn into_file(self) -> Option<File> {
+        match self.data {
+            SpooledData::Memory(_) => None,
+            SpooledData::File(file) => Some(file),
+        }
+    }
+}
+
+impl fmt::Debug for SpooledBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpooledBody")
+            .field("len", &self.len)
+            .field("file", &self.file().is_some())
+            .finish()
+    }
+}
+
+impl FromRequest for SpooledBody {
+    type Config = PayloadConfig;
+    type Error = Error;
+    type Future = Either<
+        LocalBoxFuture<'static, Result<SpooledBody, Error>>,
+        Ready<Result<SpooledBody, Error>>,
+    >;
+
+    fn describe(op: &mut Operation) {
+        op.add_request_content("application/octet-stream");
+    }
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let tmp;
+        let cfg = if let Some(cfg) = req.app_data::<PayloadConfig>() {
+            cfg
+        } else {
+            tmp = PayloadConfig::default();
+            &tmp
+        };
+
+        if let Err(e) = cfg.check_mimetype(req) {
+            return Either::Right(err(cfg.handle_error(e, req)));
+        }
+
+        let length = match req.headers().get(&header::CONTENT_LENGTH) {
+            Some(l) => match l.to_str().ok().and_then(|s| s.parse::<usize>().ok()) {
+                Some(l) => Some(l),
+                None => {
+                    let e = PayloadError::UnknownLength.into();
+                    return Either::Right(err(cfg.handle_error(e, req)));
                 }
+            },
+            None => None,
+        };
+        if length.map_or(false, |l| l > cfg.limit) {
+            let e = PayloadError::Overflow.into();
+            return Either::Right(err(cfg.handle_error(e, req)));
+        }
+
+        #[cfg(feature = "compress")]
+        let stream = dev::Decompress::from_headers(payload.take(), req.headers());
+        #[cfg(not(feature = "compress"))]
+        let stream = payload.take();
+
+        let limit = cfg.limit;
+        let threshold = cfg.spill_threshold;
+        let ehandler = cfg.ehandler.clone().map(|f| (f, req.clone()));
+        Either::Left(
+            async move {
+                spool(stream, length, limit, threshold)
+                    .await
+                    .map_err(|e| handle_error(ehandler, e.into()))
             }
             .boxed_local(),
         )
     }
 }
+
+/// Read body into memory, body is written to temporary file once it is
+/// larger than `threshold`
+async fn spool<S>(
+    mut stream: S,
+    length: Option<usize>,
+    limit: usize,
+    threshold: usize,
+) -> Result<SpooledBody, PayloadError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    let mut body = BytesMut::new();
+    let mut file = None;
+    let mut size = 0;
+
+    // body of known size is written to file right away
+    if length.map_or(false, |l| l > threshold) {
+        file = Some(crate::web::block(tempfile::tempfile).await?);
+    }
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        size += chunk.len();
+        if size > limit {
+            return Err(PayloadError::Overflow);
+        }
+
+        match file.take() {
+            Some(mut f) => {
+                let f = crate::web::block(move || f.write_all(&chunk).map(|_| f));
+                file = Some(f.await?);
+            }
+            None if size > threshold => {
+                body.extend_from_slice(&chunk);
+                let buf = body.split().freeze();
+                let f = crate::web::block(move || {
+                    let mut f = tempfile::tempfile()?;
+                    f.write_all(&buf).map(|_| f)
+                });
+                file = Some(f.await?);
+            }
+            None => body.extend_from_slice(&chunk),
+        }
+    }
+
+    let data = match file {
+        Some(mut f) => {
+            let f = crate::web::block(move || f.seek(io::SeekFrom::Start(0)).map(|_| f));
+            SpooledData::File(f.await?)
+        }
+        None => SpooledData::Memory(body.freeze()),
+    };
+    Ok(SpooledBody {
+        len: size as u64,
+        data,
+    })
+}
+
+type ErrorHandler = Arc<dyn Fn(Error, &HttpRequest) -> Error + Send + Sync>;
+
+fn handle_error(ehandler: Option<(ErrorHandler, HttpRequest)>, e: Error) -> Error {
+    match ehandler {
+        Some((f, req)) => f(e, &req),
+        None => e,
+    }
+}
+
 /// Payload configuration for request's payload.
+///
+/// Configuration applies to `Bytes`, `String` and `SpooledBody` extractors.
 #[derive(Clone)]
 pub struct PayloadConfig {
     limit: usize,
+    spill_threshold: usize,
     mimetype: Option<Mime>,
+    ehandler: Option<ErrorHandler>,
 }
 
 impl PayloadConfig {
@@ -254,6 +501,13 @@ impl PayloadConfig {
         self
     }
 
+    /// Set size from which `SpooledBody` is written to temporary file
+    /// instead of memory. By default threshold is 64Kb.
+    pub fn spill_threshold(mut self, threshold: usize) -> Self {
+        self.spill_threshold = threshold;
+        self
+    }
+
     /// Set required mime-type of the request. By default mime type is not
     /// enforced.
     pub fn mimetype(mut self, mt: Mime) -> Self {
@@ -261,6 +515,25 @@ impl PayloadConfig {
         self
     }
 
+    /// Set custom error handler for `Bytes` and `String` extractors.
+    ///
+    /// Handler receives extraction error and the request, response of
+    /// returned error is used instead of default one.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Error, &HttpRequest) -> Error + Send + Sync + 'static,
+    {
+        self.ehandler = Some(Arc::new(f));
+        self
+    }
+
+    fn handle_error(&self, e: Error, req: &HttpRequest) -> Error {
+        match self.ehandler {
+            Some(ref f) => f(e, req),
+            None => e,
+        }
+    }
+
     fn check_mimetype(&self, req: &HttpRequest) -> Result<(), Error> {
         // check content-type
         if let Some(ref mt) = self.mimetype {
@@ -286,7 +559,9 @@ impl Default for PayloadConfig {
     fn default() -> Self {
         PayloadConfig {
             limit: 262_144,
+            spill_threshold: 65_536,
             mimetype: None,
+            ehandler: None,
         }
     }
 }
@@ -443,6 +718,65 @@ mod tests {
         assert_eq!(s, "hello=world");
     }
 
+    #[actix_rt::test]
+    async fn test_spooled_body() {
+        use std::io::Read;
+
+        let (req, mut pl) = TestRequest::default()
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let body = SpooledBody::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(body.len(), 11);
+        assert_eq!(body.bytes().unwrap(), &Bytes::from_static(b"hello=world"));
+        assert!(body.file().is_none());
+
+        let (req, mut pl) = TestRequest::default()
+            .app_data(PayloadConfig::default().spill_threshold(4))
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let body = SpooledBody::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(body.len(), 11);
+        assert!(body.bytes().is_none());
+        let mut content = String::new();
+        body.into_file()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "hello=world");
+
+        let (req, mut pl) = TestRequest::default()
+            .app_data(PayloadConfig::new(8).spill_threshold(4))
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let e = SpooledBody::from_request(&req, &mut pl).await.unwrap_err();
+        let resp: crate::HttpResponse = e.into();
+        assert_eq!(resp.status(), crate::http::StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_error_handler() {
+        let cfg = PayloadConfig::new(4).error_handler(|e, req| {
+            let resp = crate::HttpResponse::Conflict().body(req.path().to_string());
+            crate::error::InternalError::from_response(e, resp).into()
+        });
+
+        let (req, mut pl) = TestRequest::with_uri("/upload")
+            .app_data(cfg.clone())
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let e = Bytes::from_request(&req, &mut pl).await.unwrap_err();
+        let resp: crate::HttpResponse = e.into();
+        assert_eq!(resp.status(), crate::http::StatusCode::CONFLICT);
+
+        let (req, mut pl) = TestRequest::with_uri("/upload")
+            .app_data(cfg.limit(16))
+            .set_payload(Bytes::from_static(b"\xff"))
+            .to_http_parts();
+        let e = String::from_request(&req, &mut pl).await.unwrap_err();
+        let resp: crate::HttpResponse = e.into();
+        assert_eq!(resp.status(), crate::http::StatusCode::CONFLICT);
+    }
+
     #[actix_rt::test]
     async fn test_message_body() {
         let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "xxxx")
@@ -0,0 +1,110 @@
+//! Tls client certificate extractor
+use actix_http::error::{Error, ErrorUnauthorized};
+use actix_http::Extensions;
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+
+/// Certificate presented by the client during tls handshake.
+///
+/// Certificate is available only if client certificate verification is
+/// enabled on the acceptor, i.e. `SslAcceptorBuilder::set_verify()` for openssl
+/// or `ServerConfig::new()` with a client certificate verifier for rustls.
+///
+/// Extraction fails with `401 Unauthorized` if client did not present
+/// a certificate, use `Option<PeerCertificate>` if certificate is optional.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn index(cert: web::PeerCertificate) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("Welcome {:?}!", cert.subject()))
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerCertificate {
+    der: Vec<u8>,
+    subject: Option<String>,
+}
+
+impl PeerCertificate {
+    #[cfg_attr(not(any(feature = "openssl", feature = "rustls")), allow(dead_code))]
+    pub(crate) fn new(der: Vec<u8>, subject: Option<String>) -> Self {
+        PeerCertificate { der, subject }
+    }
+
+    /// Get client certificate from connection data stored in request extensions.
+    ///
+    /// Useful in middlewares that have no access to extractors.
+    pub fn from_extensions(ext: &Extensions) -> Option<&PeerCertificate> {
+        ext.get::<PeerCertificateData>()
+            .and_then(|data| data.0.as_ref())
+    }
+
+    /// DER encoded certificate
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// Certificate subject in `CN=example.com, O=Example` form.
+    ///
+    /// Subject is available only for openssl listeners.
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_ref().map(|s| s.as_str())
+    }
+}
+
+/// Per-connection data stored by tls listeners
+#[derive(Clone)]
+#[cfg_attr(not(any(feature = "openssl", feature = "rustls")), allow(dead_code))]
+pub(crate) struct PeerCertificateData(pub(crate) Option<PeerCertificate>);
+
+impl FromRequest for PeerCertificate {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match PeerCertificate::from_extensions(&req.extensions()) {
+            Some(cert) => ok(cert.clone()),
+            None => err(ErrorUnauthorized("Client certificate is required")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[actix_rt::test]
+    async fn test_peer_certificate() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(PeerCertificate::from_request(&req, &mut pl).await.is_err());
+
+        let cert = PeerCertificate::new(vec![1, 2, 3], Some("CN=test".to_owned()));
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        req.extensions_mut()
+            .insert(PeerCertificateData(Some(cert.clone())));
+        let res = PeerCertificate::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(res, cert);
+        assert_eq!(res.der(), &[1, 2, 3]);
+        assert_eq!(res.subject(), Some("CN=test"));
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        req.extensions_mut().insert(PeerCertificateData(None));
+        let res = Option::<PeerCertificate>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(res.is_none());
+    }
+}
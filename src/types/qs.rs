@@ -0,0 +1,511 @@
+//! Urlencoded deserializer with support of arrays and nested keys
+//!
+//! Repeated keys (`tag=a&tag=b`), bracketed arrays (`tag[]=a&tag[]=b`) and
+//! indexed arrays (`tag[0]=a&tag[1]=b`) are deserialized as sequences,
+//! nested keys (`filter[min]=1`) as maps or structs. Keys of bracketed
+//! arrays of maps are grouped like Rails does, `item[][a]=1&item[][b]=2` is
+//! one item and `item[][a]=1&item[][a]=2` are two items. Keys are limited
+//! to `MAX_DEPTH` bracketed segments.
+use std::collections::HashMap;
+
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Unexpected, Visitor};
+use url::form_urlencoded;
+
+/// Maximum number of bracketed segments of a key
+const MAX_DEPTH: usize = 5;
+
+/// Deserialize urlencoded string
+pub(crate) fn from_str<T: DeserializeOwned>(input: &str) -> Result<T, Error> {
+    from_bytes(input.as_bytes())
+}
+
+/// Deserialize urlencoded bytes
+pub(crate) fn from_bytes<T: DeserializeOwned>(input: &[u8]) -> Result<T, Error> {
+    let mut entries = Map::default();
+    for (key, value) in form_urlencoded::parse(input) {
+        let (base, segments) = parse_key(&key);
+        if segments.len() > MAX_DEPTH {
+            return Err(de::Error::custom(format!(
+                "key `{}` is nested too deeply",
+                base
+            )));
+        }
+        insert(&mut entries, base, &segments, value.into_owned())?;
+    }
+    T::deserialize(Value::Map(entries))
+}
+
+pub(crate) enum Value {
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Map),
+}
+
+/// Map entries in insertion order, indexed by key
+#[derive(Default)]
+pub(crate) struct Map {
+    entries: Vec<(String, Value)>,
+    index: HashMap<String, usize>,
+}
+
+impl Map {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.index.get(key).map(|idx| &self.entries[*idx].1)
+    }
+
+    fn push(&mut self, key: &str, value: Value) -> usize {
+        let idx = self.entries.len();
+        self.index.insert(key.to_string(), idx);
+        self.entries.push((key.to_string(), value));
+        idx
+    }
+}
+
+impl std::iter::FromIterator<(String, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut map = Map::default();
+        for (key, value) in iter {
+            map.push(&key, value);
+        }
+        map
+    }
+}
+
+/// Split key to base name and bracketed segments, `a[b][]` is `a`, `[b, ""]`.
+///
+/// Keys that are not well formed are used as is.
+fn parse_key(key: &str) -> (&str, Vec<&str>) {
+    let start = match key.find('[') {
+        Some(0) | None => return (key, Vec::new()),
+        Some(idx) => idx,
+    };
+
+    let mut segments = Vec::new();
+    let mut rest = &key[start..];
+    while !rest.is_empty() {
+        if !rest.starts_with('[') {
+            return (key, Vec::new());
+        }
+        match rest.find(']') {
+            Some(end) => {
+                segments.push(&rest[1..end]);
+                rest = &rest[end + 1..];
+            }
+            None => return (key, Vec::new()),
+        }
+    }
+    (&key[..start], segments)
+}
+
+fn insert(
+    entries: &mut Map,
+    key: &str,
+    segments: &[&str],
+    value: String,
+) -> Result<(), Error> {
+    let idx = match entries.index.get(key) {
+        Some(idx) => *idx,
+        None => {
+            let item = match segments.first() {
+                None => {
+                    entries.push(key, Value::Str(value));
+                    return Ok(());
+                }
+                Some(&"") => Value::Seq(Vec::new()),
+                Some(_) => Value::Map(Map::default()),
+            };
+            entries.push(key, item)
+        }
+    };
+
+    let item = &mut entries.entries[idx].1;
+    match segments.split_first() {
+        // repeated key
+        None => match *item {
+            Value::Str(_) => {
+                let prev = std::mem::replace(item, Value::Seq(Vec::new()));
+                if let Value::Seq(ref mut items) = *item {
+                    items.push(prev);
+                    items.push(Value::Str(value));
+                }
+                Ok(())
+            }
+            Value::Seq(ref mut items) => {
+                items.push(Value::Str(value));
+                Ok(())
+            }
+            Value::Map(_) => Err(conflict(key)),
+        },
+        // array item
+        Some((&"", rest)) => {
+            if let Value::Str(_) = *item {
+                let prev = std::mem::replace(item, Value::Seq(Vec::new()));
+                if let Value::Seq(ref mut items) = *item {
+                    items.push(prev);
+                }
+            }
+            match *item {
+                Value::Seq(ref mut items) => {
+                    if rest.is_empty() {
+                        items.push(Value::Str(value));
+                        return Ok(());
+                    }
+
+                    // key is added to last item unless item has it already
+                    let merge = match items.last() {
+                        Some(Value::Map(last)) => !has_key(last, rest[0], &rest[1..]),
+                        _ => false,
+                    };
+                    match items.last_mut() {
+                        Some(Value::Map(last)) if merge => {
+                            insert(last, rest[0], &rest[1..], value)
+                        }
+                        _ => {
+                            let mut nested = Map::default();
+                            insert(&mut nested, rest[0], &rest[1..], value)?;
+                            items.push(Value::Map(nested));
+                            Ok(())
+                        }
+                    }
+                }
+                _ => Err(conflict(key)),
+            }
+        }
+        // nested key
+        Some((name, rest)) => match *item {
+            Value::Map(ref mut nested) => insert(nested, name, rest, value),
+            _ => Err(conflict(key)),
+        },
+    }
+}
+
+/// Returns true if value exists for key and segments
+fn has_key(entries: &Map, key: &str, segments: &[&str]) -> bool {
+    match entries.get(key) {
+        None => false,
+        Some(item) => match (segments.split_first(), item) {
+            (None, _) => true,
+            (Some((&"", _)), _) => false,
+            (Some((name, rest)), Value::Map(nested)) => has_key(nested, name, rest),
+            (Some(_), _) => true,
+        },
+    }
+}
+
+fn conflict(key: &str) -> Error {
+    de::Error::custom(format!("conflicting values for key `{}`", key))
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Str(s) => Unexpected::Str(s),
+            Value::Seq(_) => Unexpected::Seq,
+            Value::Map(_) => Unexpected::Map,
+        }
+    }
+
+    fn invalid_type<'de, V: Visitor<'de>>(&self, visitor: &V) -> Error {
+        de::Error::invalid_type(self.unexpected(), visitor)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self {
+                    Value::Str(s) => match s.parse() {
+                        Ok(val) => visitor.$visit(val),
+                        Err(e) => Err(de::Error::custom(e)),
+                    },
+                    val => Err(val.invalid_type(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            Value::Seq(items) => {
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+            }
+            Value::Map(map) => visitor.visit_map(MapDeserializer::new(
+                map.entries.into_iter().map(|(k, v)| (Value::Str(k), v)),
+            )),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items = match self {
+            Value::Str(s) => vec![Value::Str(s)],
+            Value::Seq(items) => items,
+            Value::Map(Map { mut entries, .. }) => {
+                // indexed array, items are ordered by index
+                if entries.iter().all(|(k, _)| k.parse::<usize>().is_ok()) {
+                    entries.sort_by_key(|(k, _)| k.parse::<usize>().ok());
+                }
+                entries.into_iter().map(|(_, v)| v).collect()
+            }
+        };
+        visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Map(_) => self.deserialize_any(visitor),
+            val => Err(val.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            val => Err(val.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_byte_buf(s.into_bytes()),
+            val => Err(val.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_enum(s.into_deserializer()),
+            val => Err(val.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_derive::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Filter {
+        min: u32,
+        max: Option<u32>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Search {
+        q: String,
+        tag: Vec<String>,
+        filter: Filter,
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("a"), ("a", vec![]));
+        assert_eq!(parse_key("a[]"), ("a", vec![""]));
+        assert_eq!(parse_key("a[b][]"), ("a", vec!["b", ""]));
+        assert_eq!(parse_key("[a]"), ("[a]", vec![]));
+        assert_eq!(parse_key("a[b"), ("a[b", vec![]));
+        assert_eq!(parse_key("a[b]c"), ("a[b]c", vec![]));
+    }
+
+    #[test]
+    fn test_nested() {
+        let s: Search = from_str("q=rust+web&tag=a&tag=b&filter[min]=1").unwrap();
+        assert_eq!(
+            s,
+            Search {
+                q: "rust web".to_string(),
+                tag: vec!["a".to_string(), "b".to_string()],
+                filter: Filter { min: 1, max: None },
+            }
+        );
+
+        let s: Search = from_str("q=x&tag%5B%5D=a&filter[min]=1&filter[max]=5").unwrap();
+        assert_eq!(s.tag, vec!["a".to_string()]);
+        assert_eq!(
+            s.filter,
+            Filter {
+                min: 1,
+                max: Some(5)
+            }
+        );
+
+        let s: Search = from_str("q=x&tag=a&filter[min]=1").unwrap();
+        assert_eq!(s.tag, vec!["a".to_string()]);
+
+        let m: HashMap<String, Vec<u32>> = from_str("a[]=1&a[]=2&b=3").unwrap();
+        assert_eq!(m["a"], vec![1, 2]);
+        assert_eq!(m["b"], vec![3]);
+
+        let m: HashMap<String, Vec<Filter>> = from_str("f[][min]=1&f[][min]=2").unwrap();
+        assert_eq!(
+            m["f"],
+            vec![Filter { min: 1, max: None }, Filter { min: 2, max: None }]
+        );
+    }
+
+    #[test]
+    fn test_rails_and_php_arrays() {
+        let m: HashMap<String, Vec<Filter>> =
+            from_str("f[][min]=1&f[][max]=5&f[][min]=2").unwrap();
+        assert_eq!(
+            m["f"],
+            vec![
+                Filter {
+                    min: 1,
+                    max: Some(5)
+                },
+                Filter { min: 2, max: None }
+            ]
+        );
+
+        let m: HashMap<String, Vec<Filter>> =
+            from_str("f[1][min]=2&f[0][min]=1&f[0][max]=5").unwrap();
+        assert_eq!(
+            m["f"],
+            vec![
+                Filter {
+                    min: 1,
+                    max: Some(5)
+                },
+                Filter { min: 2, max: None }
+            ]
+        );
+
+        let s: Search = from_str("q=x&tag[1]=b&tag[0]=a&filter[min]=1").unwrap();
+        assert_eq!(s.tag, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(from_str::<Search>("q=x&tag=a&filter=1").is_err());
+        assert!(from_str::<Search>("q=x&q=y&tag=a&filter[min]=1").is_err());
+        assert!(from_str::<Search>("q=x&tag=a&filter[min]=a").is_err());
+
+        let err = from_str::<Search>("filter=1&filter[min]=1").unwrap_err();
+        assert_eq!(err.to_string(), "conflicting values for key `filter`");
+
+        let err = from_str::<Search>("q=x&filter[a][b][c][d][e][f]=1").unwrap_err();
+        assert_eq!(err.to_string(), "key `filter` is nested too deeply");
+
+        // many distinct keys
+        let query = (0..10_000)
+            .map(|i| format!("k{}=1", i))
+            .collect::<Vec<_>>()
+            .join("&");
+        let m: HashMap<String, u32> = from_str(&query).unwrap();
+        assert_eq!(m.len(), 10_000);
+    }
+}
@@ -12,6 +12,7 @@ use crate::dev::Payload;
 use crate::error::QueryPayloadError;
 use crate::extract::FromRequest;
 use crate::request::HttpRequest;
+use crate::types::qs;
 
 /// Extract typed information from the request's query.
 ///
@@ -19,7 +20,8 @@ use crate::request::HttpRequest;
 /// be decoded into any type which depends upon data ordering e.g. tuples or tuple-structs.
 /// Attempts to do so will *fail at runtime*.
 ///
-/// [**QueryConfig**](struct.QueryConfig.html) allows to configure extraction process.
+/// [**QueryConfig**](struct.QueryConfig.html) allows to configure extraction process,
+/// including support of repeated keys, arrays and nested keys.
 ///
 /// ## Example
 ///
@@ -140,30 +142,33 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let error_handler = req
+        let (error_handler, nested) = req
             .app_data::<Self::Config>()
-            .map(|c| c.ehandler.clone())
-            .unwrap_or(None);
-
-        serde_urlencoded::from_str::<T>(req.query_string())
-            .map(|val| ok(Query(val)))
-            .unwrap_or_else(move |e| {
-                let e = QueryPayloadError::Deserialize(e);
-
-                log::debug!(
-                    "Failed during Query extractor deserialization. \
+            .map(|c| (c.ehandler.clone(), c.nested))
+            .unwrap_or((None, false));
+
+        let res = if nested {
+            qs::from_str::<T>(req.query_string())
+        } else {
+            serde_urlencoded::from_str::<T>(req.query_string())
+        };
+        res.map(|val| ok(Query(val))).unwrap_or_else(move |e| {
+            let e = QueryPayloadError::Deserialize(e);
+
+            log::debug!(
+                "Failed during Query extractor deserialization. \
                      Request path: {:?}",
-                    req.path()
-                );
+                req.path()
+            );
 
-                let e = if let Some(error_handler) = error_handler {
-                    (error_handler)(e, req)
-                } else {
-                    e.into()
-                };
+            let e = if let Some(error_handler) = error_handler {
+                (error_handler)(e, req)
+            } else {
+                e.into()
+            };
 
-                err(e)
-            })
+            err(e)
+        })
     }
 }
 
@@ -203,6 +208,7 @@ where
 pub struct QueryConfig {
     ehandler:
         Option<Arc<dyn Fn(QueryPayloadError, &HttpRequest) -> Error + Send + Sync>>,
+    nested: bool,
 }
 
 impl QueryConfig {
@@ -214,11 +220,26 @@ impl QueryConfig {
         self.ehandler = Some(Arc::new(f));
         self
     }
+
+    /// Enable support of repeated keys (`tag=a&tag=b`), bracketed arrays
+    /// (`tag[]=a&tag[]=b`), indexed arrays (`tag[0]=a`) and nested keys
+    /// (`filter[min]=1`).
+    ///
+    /// Repeated, bracketed and indexed keys are deserialized into sequences,
+    /// nested keys into maps or structs, i.e. `item[][name]=a&item[][qty]=1`
+    /// is a sequence with one struct. Disabled by default, keys are flat.
+    pub fn nested(mut self, enabled: bool) -> Self {
+        self.nested = enabled;
+        self
+    }
 }
 
 impl Default for QueryConfig {
     fn default() -> Self {
-        QueryConfig { ehandler: None }
+        QueryConfig {
+            ehandler: None,
+            nested: false,
+        }
     }
 }
 
@@ -272,6 +293,33 @@ mod tests {
         assert_eq!(s.id, "test1");
     }
 
+    #[actix_rt::test]
+    async fn test_nested() {
+        #[derive(Deserialize, Debug)]
+        struct Range {
+            min: u32,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Search {
+            tag: Vec<String>,
+            filter: Range,
+        }
+
+        let req = TestRequest::with_uri("/?tag=a&tag%5B%5D=b&filter%5Bmin%5D=1")
+            .app_data(QueryConfig::default().nested(true))
+            .to_srv_request();
+        let (req, mut pl) = req.into_parts();
+        let s = Query::<Search>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.tag, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(s.filter.min, 1);
+
+        let req =
+            TestRequest::with_uri("/?tag=a&tag=b&filter%5Bmin%5D=1").to_srv_request();
+        let (req, mut pl) = req.into_parts();
+        assert!(Query::<Search>::from_request(&req, &mut pl).await.is_err());
+    }
+
     #[actix_rt::test]
     async fn test_custom_error_responder() {
         let req = TestRequest::with_uri("/name/user1/")
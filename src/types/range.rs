@@ -0,0 +1,371 @@
+//! Byte range responses
+use std::convert::TryFrom;
+
+use actix_http::body::{Body, SizedStream};
+use actix_http::http::Error as HttpError;
+use actix_http::http::header::{self, EntityTag, HeaderName, HeaderValue, HttpDate};
+use actix_http::http::{Method, StatusCode};
+use actix_http::{Error, Response, ResponseBuilder};
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+use futures::stream::{LocalBoxStream, Stream, StreamExt, TryStreamExt};
+
+use crate::dev::BodyEncoding;
+use crate::http::header::ContentEncoding;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Responder that serves byte ranges of a body, for resumable downloads.
+///
+/// Body is a source of known length that could be sliced, i.e. a blob
+/// stored in database or object store. Single range of `Range` header is
+/// served with `206 Partial Content`, unsatisfiable range with
+/// `416 Range Not Satisfiable` response. Whole body is served if request
+/// has no `Range` header, or if it requests multiple ranges.
+///
+/// If `etag()` or `last_modified()` is set, `If-Range` and conditional
+/// request headers are evaluated as well.
+///
+/// ```rust
+/// use actix_web::web;
+/// use futures::stream;
+///
+/// async fn download() -> web::Ranged {
+///     let length = 1024;
+///     // source is called with offset and length of requested range
+///     web::Ranged::new(length, |_offset, length| {
+///         let chunk = vec![0u8; length as usize];
+///         stream::once(async move { Ok::<_, actix_web::Error>(web::Bytes::from(chunk)) })
+///     })
+///     .content_type("application/octet-stream")
+/// }
+/// # fn main() {}
+/// ```
+pub struct Ranged {
+    length: u64,
+    source: Source,
+    builder: ResponseBuilder,
+    etag: Option<EntityTag>,
+    last_modified: Option<HttpDate>,
+}
+
+enum Source {
+    Bytes(Bytes),
+    Stream(Box<dyn FnOnce(u64, u64) -> LocalBoxStream<'static, Result<Bytes, Error>>>),
+}
+
+impl Source {
+    fn body(self, start: u64, length: u64) -> Body {
+        match self {
+            Source::Bytes(bytes) => {
+                Body::Bytes(bytes.slice(start as usize..(start + length) as usize))
+            }
+            Source::Stream(f) => Body::from(SizedStream::new(length, f(start, length))),
+        }
+    }
+}
+
+impl Ranged {
+    /// Create responder for body of `length` bytes.
+    ///
+    /// `source` is called with offset and length of range that has to be
+    /// streamed, it is not called for unsatisfiable ranges.
+    pub fn new<F, S, E>(length: u64, source: F) -> Ranged
+    where
+        F: FnOnce(u64, u64) -> S + 'static,
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        Ranged {
+            length,
+            source: Source::Stream(Box::new(move |offset, length| {
+                source(offset, length).map_err(|e| e.into()).boxed_local()
+            })),
+            builder: Response::Ok(),
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    /// Create responder for body in memory.
+    pub fn bytes<B: Into<Bytes>>(body: B) -> Ranged {
+        let body = body.into();
+        Ranged {
+            length: body.len() as u64,
+            source: Source::Bytes(body),
+            builder: Response::Ok(),
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    /// Set response content type.
+    pub fn content_type<V>(mut self, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        self.builder.content_type(value);
+        self
+    }
+
+    /// Append a header to response.
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        V: header::IntoHeaderValue,
+    {
+        self.builder.header(key, value);
+        self
+    }
+
+    /// Set `ETag` of body, it is matched with `If-Range` header.
+    pub fn etag(mut self, etag: EntityTag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Set `Last-Modified` date of body, it is matched with `If-Range`
+    /// header.
+    pub fn last_modified(mut self, date: HttpDate) -> Self {
+        self.last_modified = Some(date);
+        self
+    }
+
+    /// Check if `If-Range` header matches body, range is ignored otherwise
+    fn if_range_matches(&self, req: &HttpRequest) -> bool {
+        let value = match req
+            .headers()
+            .get(&header::IF_RANGE)
+            .and_then(|val| val.to_str().ok())
+        {
+            Some(value) => value,
+            None => return true,
+        };
+
+        if value.starts_with('"') || value.starts_with("W/") {
+            match (value.parse::<EntityTag>(), &self.etag) {
+                (Ok(tag), Some(etag)) => tag.strong_eq(etag),
+                _ => false,
+            }
+        } else {
+            match (value.parse::<HttpDate>(), self.last_modified) {
+                (Ok(date), Some(last_modified)) => date == last_modified,
+                _ => false,
+            }
+        }
+    }
+}
+
+impl Responder for Ranged {
+    type Error = Error;
+    type Future = Ready<Result<Response, Error>>;
+
+    fn respond_to(mut self, req: &HttpRequest) -> Self::Future {
+        self.builder.header(header::ACCEPT_RANGES, "bytes");
+        if let Some(ref etag) = self.etag {
+            self.builder.set(header::ETag(etag.clone()));
+        }
+        if let Some(last_modified) = self.last_modified {
+            self.builder.set(header::LastModified(last_modified));
+        }
+
+        let range = match req.headers().get(&header::RANGE) {
+            Some(val) if req.method() == Method::GET && self.if_range_matches(req) => {
+                match val.to_str() {
+                    Ok(val) => parse_range(val, self.length),
+                    Err(_) => RangeResult::Full,
+                }
+            }
+            _ => RangeResult::Full,
+        };
+
+        let mut builder = self.builder;
+        let res = match range {
+            RangeResult::Full => builder.body(self.source.body(0, self.length)),
+            RangeResult::Partial(start, length) => builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, start + length - 1, self.length),
+                )
+                .encoding(ContentEncoding::Identity)
+                .body(self.source.body(start, length)),
+            RangeResult::Unsatisfiable => builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", self.length))
+                .finish(),
+        };
+        ok(req.conditional(res))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RangeResult {
+    Full,
+    /// Start and length of range
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parse `Range` header, only single range is served. Header with invalid
+/// syntax is ignored.
+fn parse_range(header: &str, size: u64) -> RangeResult {
+    if !header.starts_with("bytes=") {
+        return RangeResult::Full;
+    }
+
+    let mut count = 0;
+    let mut satisfiable = Vec::new();
+    for spec in header[6..]
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+    {
+        let mut parts = spec.splitn(2, '-');
+        let (start, end) = match (parts.next(), parts.next()) {
+            (Some(start), Some(end)) => (start.trim(), end.trim()),
+            _ => return RangeResult::Full,
+        };
+        count += 1;
+
+        if start.is_empty() {
+            // suffix range, last bytes of body
+            let suffix = match end.parse::<u64>() {
+                Ok(suffix) => suffix,
+                Err(_) => return RangeResult::Full,
+            };
+            if suffix > 0 && size > 0 {
+                let length = suffix.min(size);
+                satisfiable.push((size - length, length));
+            }
+        } else {
+            let start = match start.parse::<u64>() {
+                Ok(start) => start,
+                Err(_) => return RangeResult::Full,
+            };
+            let end = if end.is_empty() {
+                None
+            } else {
+                match end.parse::<u64>() {
+                    Ok(end) if end >= start => Some(end),
+                    _ => return RangeResult::Full,
+                }
+            };
+            if start < size {
+                let end = end.map_or(size - 1, |end| end.min(size - 1));
+                satisfiable.push((start, end - start + 1));
+            }
+        }
+    }
+
+    match satisfiable.len() {
+        _ if count == 0 => RangeResult::Full,
+        0 => RangeResult::Unsatisfiable,
+        1 => RangeResult::Partial(satisfiable[0].0, satisfiable[0].1),
+        _ => RangeResult::Full,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::test::{read_body, TestRequest};
+
+    #[test]
+    fn test_parse_range() {
+        use RangeResult::*;
+
+        for (header, result) in vec![
+            ("", Full),
+            ("items=0-1", Full),
+            ("bytes=", Full),
+            ("bytes=a-b", Full),
+            ("bytes=5-1", Full),
+            ("bytes=0-9", Partial(0, 10)),
+            ("bytes=5-", Partial(5, 5)),
+            ("bytes=5-100", Partial(5, 5)),
+            ("bytes=-3", Partial(7, 3)),
+            ("bytes=-30", Partial(0, 10)),
+            ("bytes= 1-2 ,", Partial(1, 2)),
+            ("bytes=0-1,5-6", Full),
+            ("bytes=20-30, 2-3", Partial(2, 2)),
+            ("bytes=10-", Unsatisfiable),
+            ("bytes=-0", Unsatisfiable),
+        ] {
+            assert_eq!(parse_range(header, 10), result, "{}", header);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_ranged() {
+        let ranged = || {
+            Ranged::new(10, |offset, length| {
+                let data = &b"0123456789"[offset as usize..(offset + length) as usize];
+                stream::once(async move { Ok::<_, Error>(Bytes::from_static(data)) })
+            })
+            .content_type("text/plain")
+        };
+
+        let req = TestRequest::default().to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        let resp = crate::dev::ServiceResponse::new(req, resp);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"0123456789"));
+
+        let req = TestRequest::default()
+            .header(header::RANGE, "bytes=2-4")
+            .to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-4/10"
+        );
+        let resp = crate::dev::ServiceResponse::new(req, resp);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"234"));
+
+        let req = TestRequest::default()
+            .header(header::RANGE, "bytes=10-")
+            .to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */10"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_ranged_if_range() {
+        let etag = EntityTag::from_content(b"0123456789");
+        let ranged = || Ranged::bytes("0123456789").etag(etag.clone());
+
+        let req = TestRequest::default()
+            .header(header::RANGE, "bytes=-2")
+            .header(header::IF_RANGE, etag.to_string())
+            .to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let resp = crate::dev::ServiceResponse::new(req, resp);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"89"));
+
+        // body is changed, whole body is served
+        let req = TestRequest::default()
+            .header(header::RANGE, "bytes=-2")
+            .header(header::IF_RANGE, "\"other\"")
+            .to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, etag.to_string())
+            .to_http_request();
+        let resp = ranged().respond_to(&req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -0,0 +1,119 @@
+//! Tls session information extractor
+use actix_http::error::{Error, ErrorBadRequest};
+use actix_http::Extensions;
+use futures::future::{err, ok, Ready};
+
+use crate::dev::Payload;
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+
+/// Parameters of tls session negotiated during handshake.
+///
+/// Information is available for connections accepted by openssl and
+/// rustls listeners. Extraction fails with `400 Bad Request` for plain
+/// connections, use `Option<TlsInfo>` if tls is optional.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn index(tls: web::TlsInfo) -> HttpResponse {
+///     HttpResponse::Ok().body(format!(
+///         "{:?} {:?}",
+///         tls.version(),
+///         tls.alpn_protocol()
+///     ))
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsInfo {
+    alpn_protocol: Option<Vec<u8>>,
+    version: Option<String>,
+    cipher: Option<String>,
+}
+
+impl TlsInfo {
+    #[cfg_attr(not(any(feature = "openssl", feature = "rustls")), allow(dead_code))]
+    pub(crate) fn new(
+        alpn_protocol: Option<Vec<u8>>,
+        version: Option<String>,
+        cipher: Option<String>,
+    ) -> Self {
+        TlsInfo {
+            alpn_protocol,
+            version,
+            cipher,
+        }
+    }
+
+    /// Get tls session information from connection data stored in request
+    /// extensions.
+    ///
+    /// Useful in middlewares that have no access to extractors.
+    pub fn from_extensions(ext: &Extensions) -> Option<&TlsInfo> {
+        ext.get::<TlsInfo>()
+    }
+
+    /// Protocol negotiated with ALPN, i.e. `h2` or `http/1.1`
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_ref().map(|p| p.as_slice())
+    }
+
+    /// Tls protocol version, i.e. `TLSv1.2` or `TLSv1.3`
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_ref().map(|s| s.as_str())
+    }
+
+    /// Name of negotiated cipher suite
+    pub fn cipher(&self) -> Option<&str> {
+        self.cipher.as_ref().map(|s| s.as_str())
+    }
+}
+
+impl FromRequest for TlsInfo {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match TlsInfo::from_extensions(&req.extensions()) {
+            Some(info) => ok(info.clone()),
+            None => err(ErrorBadRequest("Tls connection is required")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[actix_rt::test]
+    async fn test_tls_info() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(TlsInfo::from_request(&req, &mut pl).await.is_err());
+        let res = Option::<TlsInfo>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(res.is_none());
+
+        let info = TlsInfo::new(
+            Some(b"h2".to_vec()),
+            Some("TLSv1.3".to_owned()),
+            Some("TLS_AES_256_GCM_SHA384".to_owned()),
+        );
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        req.extensions_mut().insert(info.clone());
+        let res = TlsInfo::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(res, info);
+        assert_eq!(res.alpn_protocol(), Some(&b"h2"[..]));
+        assert_eq!(res.version(), Some("TLSv1.3"));
+        assert_eq!(res.cipher(), Some("TLS_AES_256_GCM_SHA384"));
+    }
+}
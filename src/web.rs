@@ -9,16 +9,21 @@ pub use futures::channel::oneshot::Canceled;
 
 use crate::error::BlockingError;
 use crate::extract::FromRequest;
+use crate::guard;
 use crate::handler::Factory;
 use crate::resource::Resource;
 use crate::responder::Responder;
+use crate::rmap::UrlParams;
 use crate::route::Route;
 use crate::scope::Scope;
 use crate::service::WebService;
 
-pub use crate::config::ServiceConfig;
+pub use crate::config::{ServiceConfig, TrailingSlash};
 pub use crate::data::Data;
+pub use crate::dynamic::DynamicRoutes;
+pub use crate::extract::EitherConfig;
 pub use crate::request::HttpRequest;
+pub use crate::request_data::ReqData;
 pub use crate::types::*;
 
 /// Create resource for a specific path.
@@ -55,6 +60,29 @@ pub fn resource<T: IntoPattern>(path: T) -> Resource {
     Resource::new(path)
 }
 
+/// Create resource for a specific path pattern with typed parameters.
+///
+/// Path pattern and name of resource are taken from `P`, see
+/// [`UrlParams`](../trait.UrlParams.html).
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse, UrlParams};
+///
+/// #[derive(UrlParams)]
+/// #[url(name = "user", path = "/users/{userid}")]
+/// struct UserUrl {
+///     userid: u64,
+/// }
+///
+/// let app = App::new().service(
+///     web::resource_for::<UserUrl>()
+///         .route(web::get().to(|| HttpResponse::Ok()))
+/// );
+/// ```
+pub fn resource_for<P: UrlParams>() -> Resource {
+    Resource::new(P::PATH).name(P::NAME)
+}
+
 /// Configure scope for common root path.
 ///
 /// Scopes collect multiple paths under a common path prefix.
@@ -80,6 +108,33 @@ pub fn scope(path: &str) -> Scope {
     Scope::new(path)
 }
 
+/// Configure scope for requests of a specific Host.
+///
+/// This is shortcut for `web::scope("").guard(guard::Host(host))`, host
+/// pattern could contain wildcard subdomain, see [`guard::Host`]. Each
+/// virtual host could be configured as a separate application.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+///
+/// fn api(cfg: &mut web::ServiceConfig) {
+///     cfg.route("/users", web::get().to(|| HttpResponse::Ok()));
+/// }
+///
+/// fn tenants(cfg: &mut web::ServiceConfig) {
+///     cfg.route("/", web::get().to(|| HttpResponse::Ok()));
+/// }
+///
+/// let app = App::new()
+///     .service(web::host("api.example.com").configure(api))
+///     .service(web::host("*.example.com").configure(tenants));
+/// ```
+///
+/// [`guard::Host`]: ../guard/fn.Host.html
+pub fn host<H: AsRef<str>>(host: H) -> Scope {
+    Scope::new("").guard(guard::Host(host))
+}
+
 /// Create *route* without configuration.
 pub fn route() -> Route {
     Route::new()
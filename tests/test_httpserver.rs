@@ -5,7 +5,7 @@ use std::{net, thread, time::Duration};
 #[cfg(feature = "openssl")]
 use open_ssl::ssl::SslAcceptorBuilder;
 
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
 
 fn unused_addr() -> net::SocketAddr {
     let addr: net::SocketAddr = "127.0.0.1:0".parse().unwrap();
@@ -73,6 +73,269 @@ async fn test_start() {
     let _ = sys.stop();
 }
 
+#[actix_rt::test]
+async fn test_expect() {
+    use std::io::{Read, Write};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|| HttpResponse::Ok()))
+        })
+        .workers(1)
+        .disable_signals()
+        .expect(|head| {
+            if head.headers.contains_key("authorization") {
+                Ok(())
+            } else {
+                Err(actix_web::error::ErrorUnauthorized("unauthorized"))
+            }
+        })
+        .bind(format!("{}", addr))
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(
+        b"POST / HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 10\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 401 Unauthorized"));
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(
+        b"POST / HTTP/1.1\r\nexpect: 100-continue\r\nauthorization: token\r\n\
+          content-length: 0\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n"));
+
+    // stop
+    let _ = srv.stop(false);
+
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+#[actix_rt::test]
+async fn test_on_connect_with_metrics() {
+    use std::io::{Read, Write};
+
+    #[derive(Clone)]
+    struct Marker(&'static str);
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|req: HttpRequest| {
+                let marker = req.conn_data::<Marker>().map(|m| m.0).unwrap_or("none");
+                HttpResponse::Ok().body(marker)
+            }))
+        })
+        .workers(1)
+        .disable_signals()
+        .on_connect(|_, ext| {
+            ext.insert(Marker("marker"));
+        });
+        let metrics = srv.metrics();
+        let srv = srv.bind(format!("{}", addr)).unwrap().run();
+
+        let _ = tx.send((srv, metrics, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, metrics, sys) = rx.recv().unwrap();
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    // both callbacks share connection extensions
+    assert!(data.ends_with("marker"));
+    assert_eq!(metrics.total().total_requests, 1);
+    assert_eq!(metrics.total().requests_in_flight, 0);
+    assert_eq!(metrics.workers().len(), 1);
+
+    // workers are stopped by graceful stop only
+    let _ = srv.stop(true);
+
+    thread::sleep(Duration::from_millis(100));
+    // stopped workers are removed
+    assert!(metrics.workers().is_empty());
+    let _ = sys.stop();
+}
+
+#[actix_rt::test]
+async fn test_stop_runs_hooks() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let drain = Arc::new(AtomicBool::new(false));
+
+    let (shutdown2, drain2) = (shutdown.clone(), drain.clone());
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|| HttpResponse::Ok().body("test")))
+        })
+        .workers(1)
+        .disable_signals()
+        .on_shutdown(move || async move { shutdown2.store(true, Ordering::SeqCst) })
+        .on_drain(move || async move { drain2.store(true, Ordering::SeqCst) })
+        .bind(format!("{}", addr))
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    // hooks run even though signals are disabled
+    futures::executor::block_on(srv.stop(true));
+    assert!(shutdown.load(Ordering::SeqCst));
+    assert!(drain.load(Ordering::SeqCst));
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr).is_err());
+    let _ = sys.stop();
+}
+
+#[actix_rt::test]
+async fn test_listen_with_stream() {
+    use futures::{stream, StreamExt};
+    use std::io::{Read, Write};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let lst = net::TcpListener::bind(addr).unwrap();
+        let incoming =
+            stream::once(
+                async move { actix_rt::net::TcpListener::from_std(lst).unwrap() },
+            )
+            .flat_map(|lst| {
+                stream::unfold(lst, |mut lst| async move {
+                    let (io, _) = lst.accept().await.ok()?;
+                    Some((io, lst))
+                })
+            });
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|| HttpResponse::Ok().body("test")))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen_with_stream(Box::pin(incoming), addr)
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("test"));
+
+    // stream is dropped once server stops
+    futures::executor::block_on(srv.stop(true));
+    thread::sleep(Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr).is_err());
+    // server without bound sockets resolves once stopped
+    assert!(futures::executor::block_on(srv).is_ok());
+
+    let _ = sys.stop();
+}
+
+#[actix_rt::test]
+async fn test_proxy_protocol() {
+    use std::io::{Read, Write};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|req: HttpRequest| {
+                let peer = req.peer_addr().unwrap();
+                let remote = req.connection_info().remote().unwrap().to_owned();
+                HttpResponse::Ok().body(format!("{} {}", peer, remote))
+            }))
+        })
+        .workers(1)
+        .disable_signals()
+        .proxy_protocol(true)
+        .bind(format!("{}", addr))
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    // address announced by proxy
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(
+        b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 80\r\n\
+          GET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("192.0.2.1:56324 192.0.2.1:56324"));
+
+    // `LOCAL` connection keeps address of the socket
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let local = stream.local_addr().unwrap();
+    let _ = stream.write_all(
+        b"\r\n\r\n\0\r\nQUIT\n\x20\0\0\0\
+          GET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.ends_with(&format!("{} {}", local, local)));
+
+    // connection without header is closed
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.is_empty());
+
+    let _ = srv.stop(false);
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
 #[cfg(feature = "openssl")]
 fn ssl_acceptor() -> std::io::Result<SslAcceptorBuilder> {
     use open_ssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
@@ -144,3 +407,323 @@ async fn test_start_ssl() {
     thread::sleep(Duration::from_millis(100));
     let _ = sys.stop();
 }
+
+#[cfg(feature = "openssl")]
+/// Acceptor with self signed certificate for `name`
+fn self_signed_acceptor(name: &str) -> SslAcceptorBuilder {
+    use open_ssl::asn1::Asn1Time;
+    use open_ssl::hash::MessageDigest;
+    use open_ssl::pkey::PKey;
+    use open_ssl::rsa::Rsa;
+    use open_ssl::ssl::{SslAcceptor, SslMethod};
+    use open_ssl::x509::{X509NameBuilder, X509};
+
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut subject = X509NameBuilder::new().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_issuer_name(&subject).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key(&key).unwrap();
+    builder.set_certificate(&cert.build()).unwrap();
+    builder
+}
+
+#[cfg(feature = "openssl")]
+/// Common name of certificate that server presents in new handshake
+fn peer_common_name(addr: net::SocketAddr, server_name: Option<&str>) -> Option<String> {
+    use open_ssl::nid::Nid;
+    use open_ssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    let config = builder
+        .build()
+        .configure()
+        .unwrap()
+        .use_server_name_indication(server_name.is_some())
+        .verify_hostname(false);
+
+    let stream = net::TcpStream::connect(addr).unwrap();
+    let stream = config
+        .connect(server_name.unwrap_or("localhost"), stream)
+        .unwrap();
+    let cert = stream.ssl().peer_certificate().unwrap();
+    let name = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next();
+    name.map(|entry| entry.data().as_utf8().unwrap().to_string())
+}
+
+#[test]
+#[cfg(feature = "openssl")]
+fn test_openssl_reload() {
+    use actix_web::OpensslReloadHandle;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let handle = OpensslReloadHandle::new(self_signed_acceptor("initial")).unwrap();
+
+    let srv_handle = handle.clone();
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|| HttpResponse::Ok()))
+        })
+        .workers(1)
+        .shutdown_timeout(1)
+        .disable_signals()
+        .bind_openssl_reloadable(format!("{}", addr), &srv_handle)
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    assert_eq!(peer_common_name(addr, None).unwrap(), "initial");
+    assert_eq!(
+        peer_common_name(addr, Some("example.com")).unwrap(),
+        "initial"
+    );
+
+    handle.reload(self_signed_acceptor("reloaded")).unwrap();
+
+    // new handshakes use reloaded certificate, with and without server name
+    assert_eq!(peer_common_name(addr, None).unwrap(), "reloaded");
+    assert_eq!(
+        peer_common_name(addr, Some("example.com")).unwrap(),
+        "reloaded"
+    );
+
+    let _ = srv.stop(false);
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+#[test]
+#[cfg(feature = "openssl")]
+fn test_openssl_sni() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").to(|| HttpResponse::Ok()))
+        })
+        .workers(1)
+        .shutdown_timeout(1)
+        .disable_signals()
+        .bind_openssl_sni(
+            format!("{}", addr),
+            self_signed_acceptor("default"),
+            vec![
+                ("api.example.com".to_string(), self_signed_acceptor("api")),
+                ("WWW.example.com".to_string(), self_signed_acceptor("www")),
+            ],
+        )
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    assert_eq!(
+        peer_common_name(addr, Some("api.example.com")).unwrap(),
+        "api"
+    );
+    // host names are matched case-insensitively
+    assert_eq!(
+        peer_common_name(addr, Some("www.EXAMPLE.com")).unwrap(),
+        "www"
+    );
+    // default certificate for unknown or missing server name
+    assert_eq!(
+        peer_common_name(addr, Some("other.example.com")).unwrap(),
+        "default"
+    );
+    assert_eq!(peer_common_name(addr, None).unwrap(), "default");
+
+    let _ = srv.stop(false);
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+#[actix_rt::test]
+#[cfg(all(feature = "rustls", feature = "openssl"))]
+async fn test_start_rustls() {
+    use actix_web::HttpRequest;
+    use rust_tls::internal::pemfile::{certs, pkcs8_private_keys};
+    use rust_tls::{NoClientAuth, ServerConfig};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        // load ssl keys
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        let cert_file = &mut BufReader::new(File::open("tests/cert.pem").unwrap());
+        let key_file = &mut BufReader::new(File::open("tests/key.pem").unwrap());
+        let cert_chain = certs(cert_file).unwrap();
+        let mut keys = pkcs8_private_keys(key_file).unwrap();
+        config.set_single_cert(cert_chain, keys.remove(0)).unwrap();
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").route(web::to(|req: HttpRequest| {
+                assert!(req.app_config().secure());
+                HttpResponse::Ok().body("test")
+            })))
+        })
+        .workers(1)
+        .shutdown_timeout(1)
+        .client_shutdown(1000)
+        .system_exit()
+        .disable_signals()
+        .bind_rustls(format!("{}", addr), config)
+        .unwrap()
+        .run();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    use open_ssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    let _ = builder
+        .set_alpn_protos(b"\x02h2\x08http/1.1")
+        .map_err(|e| log::error!("Can not set alpn protocol: {:?}", e));
+
+    let client = awc::Client::build()
+        .connector(
+            awc::Connector::new()
+                .ssl(builder.build())
+                .timeout(Duration::from_millis(100))
+                .finish(),
+        )
+        .finish();
+
+    let host = format!("https://{}", addr);
+    let response = client.get(host.clone()).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), actix_web::http::Version::HTTP_2);
+
+    // stop
+    let _ = srv.stop(false);
+
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+/// Server of `test_bind_from_env`, it runs only in socket activated process
+/// started by the test.
+#[test]
+#[cfg(unix)]
+fn bind_from_env_server() {
+    if std::env::var_os("LISTEN_FDS").is_none() {
+        return;
+    }
+
+    let sys = actix_rt::System::new("test");
+    let srv = HttpServer::new(|| {
+        App::new()
+            .service(web::resource("/").to(|| HttpResponse::Ok().body("activated")))
+    })
+    .workers(1)
+    .disable_signals()
+    .bind_from_env()
+    .unwrap();
+    // environment is not inherited by child processes
+    assert!(std::env::var_os("LISTEN_FDS").is_none());
+    println!("addrs: {:?}", srv.addrs());
+
+    srv.run();
+    let _ = sys.run();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_from_env() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let tls_lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    let fds = [lst.as_raw_fd(), tls_lst.as_raw_fd()];
+
+    // pid of child is not known before exec, so parent pid is passed
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args(&["bind_from_env_server", "--exact", "--nocapture"])
+        .env("LISTEN_FDS", "2")
+        .env("LISTEN_FDNAMES", "http:https")
+        .env("LISTEN_PARENT_PID", std::process::id().to_string())
+        .stdout(Stdio::piped());
+    let mut tmp = [0; 2];
+    unsafe {
+        cmd.pre_exec(move || {
+            // move descriptors out of target range first, dup2 clears
+            // close-on-exec flag of target descriptor
+            for (fd, tmp) in fds.iter().zip(tmp.iter_mut()) {
+                *tmp = libc::fcntl(*fd, libc::F_DUPFD, 3 + fds.len() as i32);
+                if *tmp < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            for (idx, tmp) in tmp.iter().enumerate() {
+                if libc::dup2(*tmp, 3 + idx as i32) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                libc::close(*tmp);
+            }
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn().unwrap();
+
+    // sockets named `https` are left for tls variants of `bind_from_env()`
+    let mut out = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.contains("addrs: ") {
+        line.clear();
+        assert!(out.read_line(&mut line).unwrap() > 0);
+    }
+    assert!(line
+        .trim_end()
+        .ends_with(&format!("addrs: {:?}", vec![addr])));
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK"));
+    assert!(resp.ends_with("activated"));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
@@ -15,8 +15,8 @@ use futures::{ready, Future};
 use rand::{distributions::Alphanumeric, Rng};
 
 use actix_web::dev::BodyEncoding;
-use actix_web::middleware::Compress;
-use actix_web::{dev, test, web, App, Error, HttpResponse};
+use actix_web::middleware::{Compress, CompressConfig};
+use actix_web::{dev, test, web, App, Error, HttpRequest, HttpResponse};
 
 const STR: &str = "Hello World Hello World Hello World Hello World Hello World \
                    Hello World Hello World Hello World Hello World Hello World \
@@ -366,6 +366,32 @@ async fn test_head_binary() {
     assert!(bytes.is_empty());
 }
 
+#[actix_rt::test]
+async fn test_head_from_get() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new().service(web::resource("/").route(web::get().to(
+            |req: HttpRequest| {
+                HttpResponse::Ok()
+                    .header("x-method", req.method().as_str())
+                    .body(STR)
+            },
+        )))
+    });
+
+    let mut response = srv.head("/").send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get("x-method").unwrap(), "HEAD");
+
+    {
+        let len = response.headers().get(CONTENT_LENGTH).unwrap();
+        assert_eq!(format!("{}", STR.len()), len.to_str().unwrap());
+    }
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert!(bytes.is_empty());
+}
+
 #[actix_rt::test]
 async fn test_no_chunking() {
     let srv = test::start_with(test::config().h1(), || {
@@ -443,6 +469,85 @@ async fn test_body_brotli() {
     assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
 }
 
+#[actix_rt::test]
+async fn test_body_brotli_quality() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::default().brotli_quality(11))
+            .service(
+                web::resource("/").route(web::to(move || HttpResponse::Ok().body(STR))),
+            )
+    });
+
+    // client request
+    let mut response = srv
+        .get("/")
+        .header(ACCEPT_ENCODING, "gzip, deflate, br")
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "br");
+
+    // read response
+    let bytes = response.body().await.unwrap();
+
+    // decode brotli
+    let mut e = BrotliDecoder::new(Vec::with_capacity(2048));
+    e.write_all(bytes.as_ref()).unwrap();
+    let dec = e.finish().unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+}
+
+#[actix_rt::test]
+async fn test_compress_config() {
+    let srv = test::start_with(test::config().h1(), || {
+        App::new()
+            .wrap(
+                Compress::default().config(
+                    CompressConfig::default()
+                        .min_size(64)
+                        .exclude(mime::IMAGE_STAR),
+                ),
+            )
+            .service(web::resource("/").route(web::to(|| HttpResponse::Ok().body(STR))))
+            .service(
+                web::resource("/small").route(web::to(|| HttpResponse::Ok().body("ok"))),
+            )
+            .service(web::resource("/image").route(web::to(|| {
+                HttpResponse::Ok().content_type("image/png").body(STR)
+            })))
+            .service(
+                web::resource("/override")
+                    .app_data(CompressConfig::default())
+                    .route(web::to(|| HttpResponse::Ok().body("ok"))),
+            )
+    });
+
+    for (path, compressed) in &[
+        ("/", true),
+        ("/small", false),
+        ("/image", false),
+        ("/override", true),
+    ] {
+        let response = srv
+            .get(*path)
+            .header(ACCEPT_ENCODING, "gzip")
+            .no_decompress()
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.headers().get(CONTENT_ENCODING).is_some(),
+            *compressed,
+            "{}",
+            path
+        );
+    }
+}
+
 #[actix_rt::test]
 async fn test_encoding() {
     let srv = test::start_with(test::config().h1(), || {